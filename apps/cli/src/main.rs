@@ -0,0 +1,396 @@
+//! Command line access to a running node, for server users scripting or debugging it without the
+//! desktop app.
+//!
+//! Talks to the node over the control socket in its data directory, see
+//! `sd_core::api::volumes::ControlRequest` for the protocol.
+
+use sd_core::api::volumes::UnitPolicy;
+
+use std::{env, path::PathBuf, process::ExitCode};
+
+const USAGE: &str = "\
+Usage: sd-cli [--data-dir <path>] [--json] [--si] [--dry-run] <command>
+
+Commands:
+  health                    Print the status of the watcher, volumes, sync and jobs
+  volumes list              List the volumes known to the node
+  volumes watch             Print volume events as they happen, starting with the current volumes
+  volumes unmount <path>    Unmount the volume mounted at <path>
+  volumes eject <path>      Unmount the volume mounted at <path> and power its drive off
+  volumes diagnose          Print what volume detection is missing and the watcher status
+
+Options:
+  --data-dir <path>         Data directory of the node, defaults to the one of the desktop app
+  --json                    Print the responses as lines of JSON, for scripts
+  --si                      Print sizes in powers of 1000 (GB) instead of 1024 (GiB)
+  --dry-run                 Print what unmount or eject would do, without doing it";
+
+#[derive(Debug)]
+struct Args {
+	data_dir: PathBuf,
+	json: bool,
+	units: UnitPolicy,
+	dry_run: bool,
+	command: Vec<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+	let mut data_dir = None;
+	let mut json = false;
+	let mut units = UnitPolicy::Binary;
+	let mut dry_run = false;
+	let mut command = vec![];
+
+	let mut args = env::args().skip(1);
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--data-dir" => {
+				data_dir = Some(PathBuf::from(args.next().ok_or("--data-dir needs a path")?));
+			}
+			"--json" => json = true,
+			"--si" => units = UnitPolicy::Decimal,
+			"--dry-run" => dry_run = true,
+			"-h" | "--help" => return Err(String::new()),
+			_ => command.push(arg),
+		}
+	}
+
+	let data_dir = match data_dir {
+		Some(data_dir) => data_dir,
+		// Same as the desktop app, release builds
+		None => directories::BaseDirs::new()
+			.ok_or("Couldn't find the data directory, pass --data-dir")?
+			.data_dir()
+			.join("spacedrive"),
+	};
+
+	Ok(Args {
+		data_dir,
+		json,
+		units,
+		dry_run,
+		command,
+	})
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+	let args = match parse_args() {
+		Ok(args) => args,
+		Err(e) => {
+			if !e.is_empty() {
+				eprintln!("{e}\n");
+			}
+			eprintln!("{USAGE}");
+			return ExitCode::FAILURE;
+		}
+	};
+
+	match volumes::run(&args).await {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(e) => {
+			eprintln!("{e}");
+			ExitCode::FAILURE
+		}
+	}
+}
+
+#[cfg(unix)]
+mod volumes {
+	use super::{Args, USAGE};
+
+	use sd_core::{
+		api::{
+			volumes::{
+				socket_path, ControlRequest, ControlResponse, OperationPlan, PlanStep,
+				PredictedOutcome, UnitPolicy, Volume, VolumeEvent, VolumeOperation, VolumeSnapshot,
+			},
+			CoreHealth, Severity,
+		},
+		util::messages,
+	};
+
+	use std::{io, path::Path};
+
+	use tokio::{
+		io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+		net::UnixStream,
+	};
+
+	pub async fn run(args: &Args) -> Result<(), String> {
+		let request = match args
+			.command
+			.iter()
+			.map(String::as_str)
+			.collect::<Vec<_>>()
+			.as_slice()
+		{
+			["health"] => ControlRequest::Health,
+			["volumes", "list"] => ControlRequest::List,
+			["volumes", "watch"] => ControlRequest::Watch,
+			["volumes", "unmount", path] => ControlRequest::Unmount {
+				path: path.into(),
+				dry_run: args.dry_run,
+			},
+			["volumes", "eject", path] => ControlRequest::Eject {
+				path: path.into(),
+				dry_run: args.dry_run,
+			},
+			["volumes", "diagnose"] => ControlRequest::Diagnose,
+			_ => return Err(USAGE.to_string()),
+		};
+
+		let socket = socket_path(&args.data_dir);
+		let stream = UnixStream::connect(&socket)
+			.await
+			.map_err(|e| not_running(&socket, e))?;
+		let (reader, mut writer) = stream.into_split();
+
+		let mut line = serde_json::to_vec(&request).map_err(|e| e.to_string())?;
+		line.push(b'\n');
+		writer
+			.write_all(&line)
+			.await
+			.map_err(|e| not_running(&socket, e))?;
+
+		let mut lines = BufReader::new(reader).lines();
+		while let Some(line) = lines.next_line().await.map_err(|e| e.to_string())? {
+			if args.json {
+				println!("{line}");
+				continue;
+			}
+
+			match serde_json::from_str(&line).map_err(|e| e.to_string())? {
+				ControlResponse::Volumes(volumes) => print_volumes(&volumes, args.units),
+				ControlResponse::Event(event) => print_event(&event),
+				ControlResponse::Unmounted(path) => println!("Unmounted {}", path.display()),
+				ControlResponse::Ejected(path) => println!("Ejected {}", path.display()),
+				ControlResponse::Plan(plan) => print_plan(&plan, args.units),
+				ControlResponse::Diagnosis(snapshot) => print_diagnosis(&snapshot),
+				ControlResponse::Health(health) => print_health(&health),
+				ControlResponse::Error(e) => return Err(format!("{}: {}", e.code, e.message)),
+			}
+		}
+
+		Ok(())
+	}
+
+	fn not_running(socket: &Path, e: io::Error) -> String {
+		format!(
+			"Couldn't reach the node at {} ({e}), is it running?",
+			socket.display()
+		)
+	}
+
+	fn print_volumes(volumes: &[Volume], units: UnitPolicy) {
+		println!(
+			"{:<24} {:<32} {:<8} {:<10} {:>10} {:>10}",
+			"NAME", "MOUNT POINT", "FS", "TYPE", "SIZE", "FREE"
+		);
+		for volume in volumes {
+			// Padding only applies to strings
+			let file_system = volume.file_system.to_string();
+			let mount_type = volume.mount_type.to_string();
+
+			let mut notes = Vec::new();
+			if volume.read_only {
+				notes.push("read-only".to_string());
+			}
+			if let Some(encryption) = volume.encryption {
+				notes.push(if volume.is_locked {
+					format!("locked {encryption}")
+				} else {
+					encryption.to_string()
+				});
+			}
+			if !volume.is_accessible && !volume.is_locked {
+				notes.push(
+					match volume.owner.as_ref().and_then(|owner| owner.name.as_ref()) {
+						Some(owner) => format!("mounted for {owner}"),
+						None => "not accessible".to_string(),
+					},
+				);
+			}
+
+			println!(
+				"{:<24} {:<32} {:<8} {:<10} {:>10} {:>10}{}",
+				volume.name,
+				volume.mount_point.display(),
+				file_system,
+				mount_type,
+				volume.total_bytes_capacity.format(units),
+				volume.total_bytes_available.format(units),
+				if notes.is_empty() {
+					String::new()
+				} else {
+					format!(" ({})", notes.join(", "))
+				}
+			);
+		}
+	}
+
+	fn print_event(event: &VolumeEvent) {
+		println!("{}", messages::to_english(&event.message()));
+	}
+
+	fn print_diagnosis(snapshot: &VolumeSnapshot) {
+		println!("Volumes:        {}", snapshot.volumes.len());
+
+		if snapshot.degraded_mode.is_degraded() {
+			println!(
+				"Degraded:       missing {:?}",
+				snapshot.degraded_mode.missing
+			);
+		} else {
+			println!("Degraded:       no");
+		}
+
+		let watcher = &snapshot.watcher;
+		println!(
+			"Watcher:        {}, {} checks",
+			if watcher.running {
+				"running"
+			} else {
+				"stopped"
+			},
+			watcher.checks
+		);
+		if let Some(backend) = watcher.backend {
+			println!("Backend:        {}", backend.as_str());
+		}
+		if let Some(last_check) = watcher.last_check {
+			println!("Last check:     {last_check}");
+		}
+		if let Some(last_error) = &watcher.last_error {
+			println!("Last error:     {last_error}");
+		}
+
+		println!("\nRun with --json to get the full snapshot, to attach to a bug report");
+	}
+
+	fn print_health(health: &CoreHealth) {
+		fn severity(severity: Severity) -> &'static str {
+			match severity {
+				Severity::Ok => "ok",
+				Severity::Warning => "warning",
+				Severity::Error => "error",
+			}
+		}
+
+		println!("Core:           {}", severity(health.severity));
+
+		let watcher = &health.watcher;
+		println!(
+			"Watcher:        {}, {}",
+			severity(watcher.severity),
+			if watcher.status.running {
+				"running"
+			} else {
+				"stopped"
+			}
+		);
+		if let Some(last_error) = &watcher.status.last_error {
+			println!("Last error:     {last_error}");
+		}
+
+		let volumes = &health.volumes;
+		if volumes.degraded_mode.is_degraded() {
+			println!(
+				"Volumes:        {}, missing {:?}",
+				severity(volumes.severity),
+				volumes.degraded_mode.missing
+			);
+		} else {
+			println!("Volumes:        {}", severity(volumes.severity));
+		}
+
+		let sync = &health.sync;
+		println!(
+			"Sync:           {}, {} syncing libraries, {}",
+			severity(sync.severity),
+			sync.syncing_libraries,
+			if sync.offline {
+				"offline"
+			} else if sync.cloud_connected {
+				"connected to the cloud"
+			} else {
+				"not connected to the cloud"
+			}
+		);
+
+		let jobs = &health.jobs;
+		println!(
+			"Jobs:           {}, {} running, {} queued",
+			severity(jobs.severity),
+			jobs.running,
+			jobs.queued
+		);
+	}
+
+	fn print_plan(plan: &OperationPlan, units: UnitPolicy) {
+		let operation = match plan.operation {
+			VolumeOperation::Unmount => "unmount".to_string(),
+			VolumeOperation::Eject => "eject".to_string(),
+			VolumeOperation::Format { ref file_system } => format!("format as {file_system}"),
+		};
+		println!(
+			"Plan:           {operation} {} at {}",
+			plan.name,
+			plan.mount_point.display()
+		);
+
+		for (number, step) in (1..).zip(&plan.steps) {
+			match step {
+				PlanStep::Authorize => println!("  {number}. ask for permission"),
+				PlanStep::Unmount { mount_point } => {
+					println!("  {number}. unmount {}", mount_point.display());
+				}
+				PlanStep::LazyUnmount { mount_point } => {
+					println!("  {number}. detach {} while busy", mount_point.display());
+				}
+				PlanStep::PowerOff { device } => match device {
+					Some(device) => println!("  {number}. power {} off", device.display()),
+					None => println!("  {number}. power the drive off"),
+				},
+				PlanStep::Format {
+					device,
+					file_system,
+				} => match device {
+					Some(device) => {
+						println!("  {number}. format {} as {file_system}", device.display())
+					}
+					None => println!("  {number}. format as {file_system}"),
+				},
+			}
+		}
+
+		if !plan.busy.is_empty() {
+			let busy = plan
+				.busy
+				.iter()
+				.map(|process| format!("{} ({})", process.name, process.pid))
+				.collect::<Vec<_>>();
+			println!("Used by:        {}", busy.join(", "));
+		}
+
+		println!("Affected data:  {}", plan.bytes_affected.format(units));
+		match &plan.outcome {
+			PredictedOutcome::Succeeds => println!("Outcome:        succeeds"),
+			PredictedOutcome::NeedsAuthorization => {
+				println!("Outcome:        succeeds once permission is granted");
+			}
+			PredictedOutcome::Fails(e) => {
+				println!("Outcome:        fails, {}: {}", e.code, e.message);
+			}
+		}
+	}
+}
+
+#[cfg(not(unix))]
+mod volumes {
+	use super::Args;
+
+	pub async fn run(_: &Args) -> Result<(), String> {
+		Err("The node commands aren't available on this platform yet".to_string())
+	}
+}
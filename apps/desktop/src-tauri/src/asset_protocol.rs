@@ -0,0 +1,100 @@
+//! The `odthumb` protocol serves thumbnails straight to the webview, so they don't have to go
+//! through the authenticated localhost server, e.g. `odthumb://localhost/<library_id>/<shard_hex>/<cas_id>.webp`.
+//!
+//! On Windows webviews custom protocols are served as `http://odthumb.localhost/...` instead,
+//! `convertFileSrc` on the frontend takes care of building the right url.
+
+use sd_core::{custom_uri, Node};
+
+use std::sync::Arc;
+
+use axum::body::to_bytes;
+use http::{Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, Runtime, UriSchemeContext, UriSchemeResponder};
+use tracing::error;
+
+pub const SCHEME: &str = "odthumb";
+
+pub fn handler<R: Runtime>(
+	ctx: UriSchemeContext<'_, R>,
+	request: Request<Vec<u8>>,
+	responder: UriSchemeResponder,
+) {
+	let app = ctx.app_handle().clone();
+	tauri::async_runtime::spawn(async move {
+		responder.respond(handle(&app, request).await);
+	});
+}
+
+async fn handle<R: Runtime>(app: &AppHandle<R>, request: Request<Vec<u8>>) -> Response<Vec<u8>> {
+	// Thumbnails can be requested while the core is still starting up
+	let Some(node) = app.try_state::<Arc<Node>>() else {
+		return status(StatusCode::SERVICE_UNAVAILABLE);
+	};
+
+	let Some(path) = thumbnail_path(request.uri().path()) else {
+		return status(StatusCode::NOT_FOUND);
+	};
+
+	let (parts, _) = request.into_parts();
+	let response = match custom_uri::serve_thumbnail(&node, &path, parts).await {
+		Ok(response) | Err(response) => response,
+	};
+
+	let (parts, body) = response.into_parts();
+	match to_bytes(body, usize::MAX).await {
+		Ok(bytes) => Response::from_parts(parts, bytes.to_vec()),
+		Err(e) => {
+			error!(?e, "Failed to read thumbnail;");
+			status(StatusCode::INTERNAL_SERVER_ERROR)
+		}
+	}
+}
+
+/// `convertFileSrc` encodes the whole path as a single component, so separators arrive as `%2F`.
+/// Thumbnail paths are only made of ids and hashes, we refuse anything else.
+fn thumbnail_path(uri_path: &str) -> Option<String> {
+	let path = uri_path
+		.trim_start_matches('/')
+		.replace("%2F", "/")
+		.replace("%2f", "/");
+
+	let is_valid = !path.is_empty()
+		&& path.split('/').all(|segment| {
+			!segment.is_empty()
+				&& segment != "."
+				&& segment != ".."
+				&& segment
+					.chars()
+					.all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+		});
+
+	is_valid.then_some(path)
+}
+
+fn status(status: StatusCode) -> Response<Vec<u8>> {
+	let mut response = Response::new(Vec::new());
+	*response.status_mut() = status;
+	response
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn accepts_only_thumbnail_paths() {
+		assert_eq!(
+			thumbnail_path("/ephemeral%2Fab%2F0123abcd.webp").as_deref(),
+			Some("ephemeral/ab/0123abcd.webp")
+		);
+		assert_eq!(
+			thumbnail_path("/2b6c32a1-7e1a-4a5e-9d1b-2b7bd4f1c9f2/ab/0123abcd.webp").as_deref(),
+			Some("2b6c32a1-7e1a-4a5e-9d1b-2b7bd4f1c9f2/ab/0123abcd.webp")
+		);
+		assert_eq!(thumbnail_path("/..%2F..%2Fconfig.json"), None);
+		assert_eq!(thumbnail_path("/ab//0123.webp"), None);
+		assert_eq!(thumbnail_path("/ab%20cd.webp"), None);
+		assert_eq!(thumbnail_path("/"), None);
+	}
+}
@@ -0,0 +1,426 @@
+//! Launching the app at login, registered with the mechanism each platform uses for it:
+//! a LaunchAgent on macOS, the `Run` registry key on Windows and an XDG autostart entry on Linux.
+//!
+//! The login item passes [`AUTOSTART_ARG`] so we know we were started by it, in which case the
+//! app can optionally start without showing its window, only a tray icon to bring it up.
+
+use std::{
+	path::PathBuf,
+	sync::atomic::{AtomicBool, Ordering},
+};
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::{
+	menu::{Menu, MenuItem},
+	tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
+	AppHandle, Manager,
+};
+use tokio::sync::RwLock;
+use tracing::{error, warn};
+
+const STATE_FILE: &str = "autostart.json";
+
+/// Passed by the login item when launching the app
+const AUTOSTART_ARG: &str = "--autostart";
+
+const TRAY_ID: &str = "autostart";
+
+#[derive(Debug, Clone, Copy, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct AutostartSettings {
+	/// Whether the app is registered to launch at login
+	pub enabled: bool,
+	/// Whether launching at login keeps the window hidden, with only a tray icon
+	pub start_minimized: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PersistedState {
+	start_minimized: bool,
+}
+
+pub struct Autostart {
+	state_path: PathBuf,
+	state: RwLock<PersistedState>,
+	/// Set while the window is kept hidden after being launched at login
+	hidden: AtomicBool,
+}
+
+impl Autostart {
+	pub async fn load(app: &AppHandle) -> Self {
+		let state_path = app
+			.path()
+			.app_data_dir()
+			.unwrap_or_else(|_| PathBuf::from("./"))
+			.join(STATE_FILE);
+
+		let state = match tokio::fs::read(&state_path).await {
+			Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+				warn!(?e, "Corrupted autostart settings, using the defaults;");
+				PersistedState::default()
+			}),
+			Err(_) => PersistedState::default(),
+		};
+
+		let launched_at_login = std::env::args().any(|arg| arg == AUTOSTART_ARG);
+
+		Self {
+			state_path,
+			hidden: AtomicBool::new(launched_at_login && state.start_minimized),
+			state: RwLock::new(state),
+		}
+	}
+
+	/// Whether the window should stay hidden, as we were launched at login in minimized mode
+	pub fn is_hidden(&self) -> bool {
+		self.hidden.load(Ordering::Relaxed)
+	}
+}
+
+/// Puts the app in the tray instead of showing its window, called once the frontend is ready
+pub fn start_minimized(app: &AppHandle) {
+	let tray = || -> tauri::Result<()> {
+		let show = MenuItem::with_id(app, "show", "Show Spacedrive", true, None::<&str>)?;
+		let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+		let menu = Menu::with_items(app, &[&show, &quit])?;
+
+		let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+			.tooltip("Spacedrive")
+			.menu(&menu)
+			.menu_on_left_click(false)
+			.on_menu_event(|app, event| match event.id.as_ref() {
+				"show" => show_window(app),
+				"quit" => app.exit(0),
+				_ => {}
+			})
+			.on_tray_icon_event(|tray, event| {
+				if let TrayIconEvent::Click {
+					button: MouseButton::Left,
+					button_state: MouseButtonState::Up,
+					..
+				} = event
+				{
+					show_window(tray.app_handle());
+				}
+			});
+
+		if let Some(icon) = app.default_window_icon() {
+			builder = builder.icon(icon.clone());
+		}
+
+		builder.build(app).map(|_| ())
+	};
+
+	if let Err(e) = tray() {
+		// Without a tray icon there would be no way to bring the window up
+		error!(?e, "Failed to create tray icon, showing the window instead;");
+		show_window(app);
+	}
+}
+
+fn show_window(app: &AppHandle) {
+	app.state::<Autostart>().hidden.store(false, Ordering::Relaxed);
+	app.remove_tray_by_id(TRAY_ID);
+
+	if let Some(window) = app.get_webview_window("main") {
+		window.show().ok();
+		window.set_focus().ok();
+	}
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn get_autostart_settings(
+	app: AppHandle,
+	state: tauri::State<'_, Autostart>,
+) -> Result<AutostartSettings, String> {
+	Ok(AutostartSettings {
+		enabled: platform::is_registered(&app).map_err(|e| e.to_string())?,
+		start_minimized: state.state.read().await.start_minimized,
+	})
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn set_autostart_enabled(app: AppHandle, enabled: bool) -> Result<(), String> {
+	let result = if enabled {
+		std::env::current_exe().and_then(|exe| platform::register(&app, &exe))
+	} else {
+		platform::unregister(&app)
+	};
+
+	result.map_err(|e| {
+		error!(?e, %enabled, "Failed to update login item;");
+		e.to_string()
+	})
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn set_start_minimized(
+	state: tauri::State<'_, Autostart>,
+	start_minimized: bool,
+) -> Result<(), String> {
+	let mut state_guard = state.state.write().await;
+	state_guard.start_minimized = start_minimized;
+
+	let bytes = serde_json::to_vec(&*state_guard).map_err(|e| e.to_string())?;
+	// The app data dir doesn't exist until something is first written to it
+	if let Some(parent) = state.state_path.parent() {
+		tokio::fs::create_dir_all(parent)
+			.await
+			.map_err(|e| e.to_string())?;
+	}
+	tokio::fs::write(&state.state_path, bytes)
+		.await
+		.map_err(|e| e.to_string())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub(crate) use platform::quote_exec;
+
+#[cfg(target_os = "macos")]
+mod platform {
+	use super::AUTOSTART_ARG;
+
+	use std::{
+		fs, io,
+		path::{Path, PathBuf},
+	};
+
+	use tauri::AppHandle;
+
+	fn plist_path(app: &AppHandle) -> io::Result<PathBuf> {
+		std::env::var_os("HOME")
+			.map(|home| {
+				PathBuf::from(home)
+					.join("Library/LaunchAgents")
+					.join(format!("{}.plist", app.config().identifier))
+			})
+			.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))
+	}
+
+	pub fn is_registered(app: &AppHandle) -> io::Result<bool> {
+		Ok(plist_path(app)?.exists())
+	}
+
+	pub fn register(app: &AppHandle, exe: &Path) -> io::Result<()> {
+		let path = plist_path(app)?;
+		if let Some(parent) = path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+
+		let plist = format!(
+			r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>Label</key>
+	<string>{label}</string>
+	<key>ProgramArguments</key>
+	<array>
+		<string>{exe}</string>
+		<string>{AUTOSTART_ARG}</string>
+	</array>
+	<key>RunAtLoad</key>
+	<true/>
+</dict>
+</plist>
+"#,
+			label = escape(&app.config().identifier),
+			exe = escape(&exe.to_string_lossy()),
+		);
+
+		fs::write(path, plist)
+	}
+
+	pub fn unregister(app: &AppHandle) -> io::Result<()> {
+		match fs::remove_file(plist_path(app)?) {
+			Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+			result => result,
+		}
+	}
+
+	fn escape(s: &str) -> String {
+		s.replace('&', "&amp;")
+			.replace('<', "&lt;")
+			.replace('>', "&gt;")
+	}
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+	use super::AUTOSTART_ARG;
+
+	use std::{io, path::Path};
+
+	use tauri::AppHandle;
+	use windows::{
+		core::{w, HSTRING, PCWSTR},
+		Win32::{
+			Foundation::{ERROR_FILE_NOT_FOUND, WIN32_ERROR},
+			System::Registry::{
+				RegDeleteKeyValueW, RegGetValueW, RegSetKeyValueW, HKEY_CURRENT_USER, REG_SZ,
+				RRF_RT_REG_SZ,
+			},
+		},
+	};
+
+	const RUN_KEY: PCWSTR = w!(r"Software\Microsoft\Windows\CurrentVersion\Run");
+
+	fn value_name(app: &AppHandle) -> HSTRING {
+		HSTRING::from(app.config().product_name.as_deref().unwrap_or("Spacedrive"))
+	}
+
+	fn to_io(error: WIN32_ERROR) -> io::Error {
+		io::Error::from_raw_os_error(error.0 as i32)
+	}
+
+	pub fn is_registered(app: &AppHandle) -> io::Result<bool> {
+		// SAFETY: Only queries whether the value exists, no buffers are passed
+		let result = unsafe {
+			RegGetValueW(
+				HKEY_CURRENT_USER,
+				RUN_KEY,
+				&value_name(app),
+				RRF_RT_REG_SZ,
+				None,
+				None,
+				None,
+			)
+		};
+
+		match result {
+			ERROR_FILE_NOT_FOUND => Ok(false),
+			result if result.is_ok() => Ok(true),
+			error => Err(to_io(error)),
+		}
+	}
+
+	pub fn register(app: &AppHandle, exe: &Path) -> io::Result<()> {
+		let command = format!("\"{}\" {AUTOSTART_ARG}", exe.display());
+		let data = command
+			.encode_utf16()
+			.chain(Some(0))
+			.flat_map(u16::to_le_bytes)
+			.collect::<Vec<_>>();
+
+		// SAFETY: `data` is a null terminated UTF-16 string which outlives the call
+		unsafe {
+			RegSetKeyValueW(
+				HKEY_CURRENT_USER,
+				RUN_KEY,
+				&value_name(app),
+				REG_SZ.0,
+				Some(data.as_ptr().cast()),
+				data.len() as u32,
+			)
+		}
+		.ok()
+		.map_err(io::Error::other)
+	}
+
+	pub fn unregister(app: &AppHandle) -> io::Result<()> {
+		// SAFETY: Only deletes a value of our own
+		match unsafe { RegDeleteKeyValueW(HKEY_CURRENT_USER, RUN_KEY, &value_name(app)) } {
+			ERROR_FILE_NOT_FOUND => Ok(()),
+			result if result.is_ok() => Ok(()),
+			error => Err(to_io(error)),
+		}
+	}
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod platform {
+	use super::AUTOSTART_ARG;
+
+	use std::{
+		fs, io,
+		path::{Path, PathBuf},
+	};
+
+	use tauri::AppHandle;
+
+	/// `$XDG_CONFIG_HOME/autostart`, see the XDG Autostart specification
+	fn entry_path(app: &AppHandle) -> io::Result<PathBuf> {
+		std::env::var_os("XDG_CONFIG_HOME")
+			.map(PathBuf::from)
+			.filter(|dir| dir.is_absolute())
+			.or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+			.map(|config| {
+				config
+					.join("autostart")
+					.join(format!("{}.desktop", app.config().identifier))
+			})
+			.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))
+	}
+
+	pub fn is_registered(app: &AppHandle) -> io::Result<bool> {
+		Ok(entry_path(app)?.exists())
+	}
+
+	pub fn register(app: &AppHandle, exe: &Path) -> io::Result<()> {
+		// AppImages are mounted at a different path on each launch, we must start the image itself
+		let exe = std::env::var_os("APPIMAGE")
+			.map(PathBuf::from)
+			.unwrap_or_else(|| exe.to_path_buf());
+
+		let path = entry_path(app)?;
+		if let Some(parent) = path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+
+		fs::write(
+			path,
+			format!(
+				"[Desktop Entry]\n\
+				Type=Application\n\
+				Name={name}\n\
+				Exec={exec} {AUTOSTART_ARG}\n\
+				Terminal=false\n\
+				X-GNOME-Autostart-enabled=true\n",
+				name = app.config().product_name.as_deref().unwrap_or("Spacedrive"),
+				exec = quote_exec(&exe.to_string_lossy()),
+			),
+		)
+	}
+
+	pub fn unregister(app: &AppHandle) -> io::Result<()> {
+		match fs::remove_file(entry_path(app)?) {
+			Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+			result => result,
+		}
+	}
+
+	/// Quotes an argument of the `Exec` key, as described by the Desktop Entry specification
+	pub(crate) fn quote_exec(arg: &str) -> String {
+		let mut quoted = String::with_capacity(arg.len() + 2);
+		quoted.push('"');
+		for c in arg.chars() {
+			if matches!(c, '"' | '`' | '$' | '\\') {
+				quoted.push('\\');
+			}
+			quoted.push(c);
+		}
+		quoted.push('"');
+
+		// Percent signs are field codes, even within quotes
+		quoted.replace('%', "%%")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	#[test]
+	#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+	fn quotes_desktop_entry_exec() {
+		use super::quote_exec;
+
+		assert_eq!(quote_exec("/usr/bin/spacedrive"), r#""/usr/bin/spacedrive""#);
+		assert_eq!(
+			quote_exec("/home/me/My $Apps/100%.AppImage"),
+			r#""/home/me/My \$Apps/100%%.AppImage""#
+		);
+	}
+}
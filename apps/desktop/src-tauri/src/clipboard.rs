@@ -0,0 +1,374 @@
+//! Reading and writing file lists on the OS clipboard, so files copied in the app
+//! can be pasted in Finder/Explorer/file managers and vice versa.
+//!
+//! Each platform exposes files with a different format:
+//!  - macOS: `NSPasteboard` file URLs
+//!  - Windows: `CF_HDROP` with a `Preferred DropEffect` for cut operations
+//!  - Linux: `text/uri-list`, plus `x-special/gnome-copied-files` and
+//!    `application/x-kde-cutselection` for cut operations
+//!
+//! We go through the platform's own tooling instead of linking against each clipboard API,
+//! except for cuts on Linux which need several targets served at once, so they go through GTK.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::{async_runtime::spawn_blocking, AppHandle};
+use tracing::error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum ClipboardOperation {
+	Copy,
+	Cut,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct ClipboardFiles {
+	pub operation: ClipboardOperation,
+	pub paths: Vec<PathBuf>,
+}
+
+/// Puts `paths` on the OS clipboard as files
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn write_files_to_clipboard(
+	app: AppHandle,
+	paths: Vec<PathBuf>,
+	operation: ClipboardOperation,
+) -> Result<(), String> {
+	if paths.is_empty() {
+		return Err("No files to put on the clipboard".into());
+	}
+
+	spawn_blocking(move || platform::write(&app, &paths, operation))
+		.await
+		.map_err(|e| e.to_string())?
+		.map_err(|e| {
+			error!(?e, "Failed to write files to clipboard;");
+			e
+		})
+}
+
+/// Reads the files currently on the OS clipboard, if any
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn read_files_from_clipboard() -> Result<Option<ClipboardFiles>, String> {
+	spawn_blocking(platform::read)
+		.await
+		.map_err(|e| e.to_string())?
+		.map(|files| files.filter(|files| !files.paths.is_empty()))
+}
+
+#[cfg(target_os = "linux")]
+fn path_to_uri(path: &std::path::Path) -> Option<String> {
+	tauri::Url::from_file_path(path).ok().map(String::from)
+}
+
+/// Parses `text/uri-list` style content, which also covers the GNOME format after its first line
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn parse_uri_list<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<PathBuf> {
+	lines
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.filter_map(|line| tauri::Url::parse(line).ok())
+		.filter_map(|url| url.to_file_path().ok())
+		.collect()
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+	use super::*;
+
+	use std::{
+		io::Write,
+		process::{Command, Stdio},
+		sync::mpsc,
+	};
+
+	use gtk::{gdk, TargetEntry, TargetFlags};
+
+	const URI_LIST: &str = "text/uri-list";
+	const GNOME_COPIED_FILES: &str = "x-special/gnome-copied-files";
+	/// Marks the `text/uri-list` of the clipboard as cut for Dolphin and other KDE apps
+	const KDE_CUT_SELECTION: &str = "application/x-kde-cutselection";
+
+	fn is_wayland() -> bool {
+		std::env::var_os("WAYLAND_DISPLAY").is_some()
+	}
+
+	fn write_target(mime: &str, content: &str) -> Result<(), String> {
+		let mut cmd = if is_wayland() {
+			let mut cmd = Command::new("wl-copy");
+			cmd.args(["--type", mime]);
+			cmd
+		} else {
+			let mut cmd = Command::new("xclip");
+			cmd.args(["-selection", "clipboard", "-t", mime]);
+			cmd
+		};
+
+		let mut child = cmd
+			.stdin(Stdio::piped())
+			.spawn()
+			.map_err(|e| format!("Failed to run clipboard tool: {e}"))?;
+
+		child
+			.stdin
+			.take()
+			.ok_or_else(|| "Clipboard tool has no stdin".to_string())?
+			.write_all(content.as_bytes())
+			.map_err(|e| format!("Failed to write to clipboard tool: {e}"))?;
+
+		// `wl-copy` and `xclip` fork to serve the selection, so only wait for the parent
+		child
+			.wait()
+			.map_err(|e| format!("Clipboard tool failed: {e}"))?;
+
+		Ok(())
+	}
+
+	/// Serves all `targets` at once from our GTK clipboard, which only works on the main thread
+	fn write_targets(app: &AppHandle, targets: Vec<(&'static str, String)>) -> Result<(), String> {
+		let (done_tx, done_rx) = mpsc::sync_channel(1);
+
+		app.run_on_main_thread(move || {
+			let entries = targets
+				.iter()
+				.enumerate()
+				.map(|(info, (mime, _))| {
+					TargetEntry::new(mime, TargetFlags::OTHER_APP, info as u32)
+				})
+				.collect::<Vec<_>>();
+
+			let clipboard = gtk::Clipboard::get(&gdk::SELECTION_CLIPBOARD);
+			let set = clipboard.set_with_data(&entries, move |_, selection, info| {
+				if let Some((_, content)) = targets.get(info as usize) {
+					selection.set(&selection.target(), 8, content.as_bytes());
+				}
+			});
+
+			// Lets clipboard managers keep the files around after we quit
+			clipboard.set_can_store(&entries);
+
+			let _ = done_tx.send(set);
+		})
+		.map_err(|e| format!("Failed to reach the main thread: {e}"))?;
+
+		match done_rx.recv() {
+			Ok(true) => Ok(()),
+			_ => Err("Failed to take ownership of the clipboard".into()),
+		}
+	}
+
+	fn read_target(mime: &str) -> Option<String> {
+		let output = if is_wayland() {
+			Command::new("wl-paste")
+				.args(["--no-newline", "--type", mime])
+				.output()
+		} else {
+			Command::new("xclip")
+				.args(["-selection", "clipboard", "-o", "-t", mime])
+				.output()
+		}
+		.ok()?;
+
+		output
+			.status
+			.success()
+			.then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+	}
+
+	pub fn write(
+		app: &AppHandle,
+		paths: &[PathBuf],
+		operation: ClipboardOperation,
+	) -> Result<(), String> {
+		let uris = paths.iter().filter_map(|p| path_to_uri(p)).collect::<Vec<_>>();
+
+		// Nautilus and friends only honor cut through the GNOME specific target, while KDE
+		// looks for its own marker next to the uri list. Clipboard tools can only serve one.
+		match operation {
+			ClipboardOperation::Copy => write_target(URI_LIST, &uris.join("\r\n")),
+			ClipboardOperation::Cut => write_targets(
+				app,
+				vec![
+					(URI_LIST, uris.join("\r\n")),
+					(GNOME_COPIED_FILES, format!("cut\n{}", uris.join("\n"))),
+					(KDE_CUT_SELECTION, "1".to_string()),
+				],
+			),
+		}
+	}
+
+	pub fn read() -> Result<Option<ClipboardFiles>, String> {
+		if let Some(content) = read_target(GNOME_COPIED_FILES) {
+			let mut lines = content.lines();
+			let operation = match lines.next().map(str::trim) {
+				Some("cut") => ClipboardOperation::Cut,
+				_ => ClipboardOperation::Copy,
+			};
+
+			return Ok(Some(ClipboardFiles {
+				operation,
+				paths: parse_uri_list(lines),
+			}));
+		}
+
+		Ok(read_target(URI_LIST).map(|content| ClipboardFiles {
+			operation: match read_target(KDE_CUT_SELECTION).as_deref().map(str::trim) {
+				Some("1") => ClipboardOperation::Cut,
+				_ => ClipboardOperation::Copy,
+			},
+			paths: parse_uri_list(content.lines()),
+		}))
+	}
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+	use super::*;
+
+	use std::process::Command;
+
+	/// JXA has access to AppKit through the ObjC bridge, so we can write real file URLs
+	fn run_jxa(script: &str) -> Result<String, String> {
+		let output = Command::new("osascript")
+			.args(["-l", "JavaScript", "-e", script])
+			.output()
+			.map_err(|e| format!("Failed to run osascript: {e}"))?;
+
+		if output.status.success() {
+			Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+		} else {
+			Err(String::from_utf8_lossy(&output.stderr).into_owned())
+		}
+	}
+
+	pub fn write(
+		_app: &AppHandle,
+		paths: &[PathBuf],
+		_operation: ClipboardOperation,
+	) -> Result<(), String> {
+		// Finder has no notion of cut on the pasteboard, it's decided at paste time (⌥⌘V)
+		let paths = serde_json::to_string(paths).map_err(|e| e.to_string())?;
+
+		run_jxa(&format!(
+			"ObjC.import('AppKit');\
+			const pb = $.NSPasteboard.generalPasteboard;\
+			pb.clearContents;\
+			pb.writeObjects($({paths}.map(p => $.NSURL.fileURLWithPath(p))));"
+		))
+		.map(|_| ())
+	}
+
+	pub fn read() -> Result<Option<ClipboardFiles>, String> {
+		let output = run_jxa(
+			"ObjC.import('AppKit');\
+			const pb = $.NSPasteboard.generalPasteboard;\
+			const opts = $({ NSPasteboardURLReadingFileURLsOnlyKey: true });\
+			const urls = pb.readObjectsForClassesOptions($([$.NSURL]), opts);\
+			urls.js.map(u => u.absoluteString.js).join('\\n');",
+		)?;
+
+		Ok(Some(ClipboardFiles {
+			operation: ClipboardOperation::Copy,
+			paths: parse_uri_list(output.lines()),
+		}))
+	}
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+	use super::*;
+
+	use std::process::Command;
+
+	/// `Preferred DropEffect` values understood by Explorer
+	const DROPEFFECT_COPY: u8 = 5;
+	const DROPEFFECT_MOVE: u8 = 2;
+
+	fn run_powershell(script: &str) -> Result<String, String> {
+		let output = Command::new("powershell")
+			.args(["-NoProfile", "-NonInteractive", "-STA", "-Command", script])
+			.output()
+			.map_err(|e| format!("Failed to run powershell: {e}"))?;
+
+		if output.status.success() {
+			Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+		} else {
+			Err(String::from_utf8_lossy(&output.stderr).into_owned())
+		}
+	}
+
+	fn quote(path: &std::path::Path) -> String {
+		format!("'{}'", path.to_string_lossy().replace('\'', "''"))
+	}
+
+	pub fn write(
+		_app: &AppHandle,
+		paths: &[PathBuf],
+		operation: ClipboardOperation,
+	) -> Result<(), String> {
+		let files = paths.iter().map(|p| quote(p)).collect::<Vec<_>>().join(",");
+		let effect = match operation {
+			ClipboardOperation::Copy => DROPEFFECT_COPY,
+			ClipboardOperation::Cut => DROPEFFECT_MOVE,
+		};
+
+		run_powershell(&format!(
+			"Add-Type -AssemblyName System.Windows.Forms;\
+			$files = New-Object System.Collections.Specialized.StringCollection;\
+			@({files}) | ForEach-Object {{ [void]$files.Add($_) }};\
+			$data = New-Object System.Windows.Forms.DataObject;\
+			$data.SetFileDropList($files);\
+			$data.SetData('Preferred DropEffect', (New-Object System.IO.MemoryStream(,[byte[]]({effect},0,0,0))));\
+			[System.Windows.Forms.Clipboard]::SetDataObject($data, $true);"
+		))
+		.map(|_| ())
+	}
+
+	pub fn read() -> Result<Option<ClipboardFiles>, String> {
+		let output = run_powershell(
+			"Add-Type -AssemblyName System.Windows.Forms;\
+			$data = [System.Windows.Forms.Clipboard]::GetDataObject();\
+			if ($data -eq $null -or -not $data.GetDataPresent('FileDrop')) { exit 0 };\
+			$effect = $data.GetData('Preferred DropEffect');\
+			if ($effect -ne $null) { 'effect:' + $effect.ReadByte() } else { 'effect:5' };\
+			$data.GetFileDropList() | ForEach-Object { $_ }",
+		)?;
+
+		let mut lines = output.lines().map(str::trim).filter(|l| !l.is_empty());
+		let Some(effect) = lines.next().and_then(|l| l.strip_prefix("effect:")) else {
+			return Ok(None);
+		};
+
+		let operation = if effect.parse::<u8>().ok() == Some(DROPEFFECT_MOVE) {
+			ClipboardOperation::Cut
+		} else {
+			ClipboardOperation::Copy
+		};
+
+		Ok(Some(ClipboardFiles {
+			operation,
+			paths: lines.map(PathBuf::from).collect(),
+		}))
+	}
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+mod platform {
+	use super::*;
+
+	pub fn write(
+		_app: &AppHandle,
+		_paths: &[PathBuf],
+		_operation: ClipboardOperation,
+	) -> Result<(), String> {
+		Err("File clipboard is not supported on this platform".into())
+	}
+
+	pub fn read() -> Result<Option<ClipboardFiles>, String> {
+		Ok(None)
+	}
+}
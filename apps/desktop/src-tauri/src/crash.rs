@@ -0,0 +1,388 @@
+//! Local crash capture. Panics are written as JSON reports and native crashes (segfaults, aborts, ...)
+//! as minidumps, both in the `crashes` folder of the data directory.
+//!
+//! Minidumps must be written from outside the crashing process, so we re-launch ourselves as a small
+//! crash server (see [`run_server_if_requested`]) which the crash handler talks to.
+//!
+//! Nothing ever leaves the machine unless the user explicitly submits a report.
+
+use std::{
+	backtrace::Backtrace,
+	ffi::OsStr,
+	fs,
+	io::{self, Write},
+	panic,
+	path::{Path, PathBuf},
+	process::{Command, Stdio},
+	sync::{atomic::AtomicBool, OnceLock},
+	thread,
+	time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::Manager;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+const CRASHES_DIR: &str = "crashes";
+
+/// Passed as the first argument when we launch ourselves as the crash server
+const SERVER_ARG: &str = "--sd-crash-server";
+
+/// Reports are only submitted to builds configured with an endpoint
+const SUBMIT_URL: Option<&str> = option_env!("SD_CRASH_REPORT_URL");
+
+const PANIC_EXTENSION: &str = "json";
+const MINIDUMP_EXTENSION: &str = "dmp";
+
+/// The crash handler must stay installed for the lifetime of the process
+static CRASH_HANDLER: OnceLock<crash_handler::CrashHandler> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum CrashReportKind {
+	Panic,
+	Minidump,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReportSummary {
+	pub id: String,
+	pub kind: CrashReportKind,
+	pub created_at: DateTime<Utc>,
+	pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct PanicReport {
+	pub message: String,
+	pub location: Option<String>,
+	pub thread: Option<String>,
+	pub backtrace: String,
+	pub version: String,
+	pub os: String,
+	pub arch: String,
+	pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum CrashReport {
+	Panic(PanicReport),
+	/// Minidumps are binary, they are inspected with external tools from `path`
+	Minidump { path: PathBuf, size: u64 },
+}
+
+pub struct CrashReports {
+	dir: PathBuf,
+}
+
+impl CrashReports {
+	fn report_path(&self, id: &str) -> Result<PathBuf, String> {
+		// Ids come from the frontend, don't let them escape the crashes folder
+		let is_valid = Uuid::parse_str(id.split('.').next().unwrap_or_default()).is_ok()
+			&& Path::new(id)
+				.extension()
+				.is_some_and(|ext| ext == PANIC_EXTENSION || ext == MINIDUMP_EXTENSION)
+			&& Path::new(id).file_name() == Some(OsStr::new(id));
+
+		if !is_valid {
+			return Err(format!("Invalid crash report id: {id}"));
+		}
+
+		Ok(self.dir.join(id))
+	}
+}
+
+/// Installs the panic hook and native crash handler, returning the state backing the crash report commands.
+pub fn install(data_dir: &Path) -> CrashReports {
+	let dir = data_dir.join(CRASHES_DIR);
+	if let Err(e) = fs::create_dir_all(&dir) {
+		error!(?e, "Failed to create crash reports directory;");
+	}
+
+	install_panic_hook(dir.clone());
+
+	if let Err(e) = install_crash_handler(&dir) {
+		warn!(?e, "Failed to install native crash handler, only panics will be captured;");
+	}
+
+	CrashReports { dir }
+}
+
+fn install_panic_hook(dir: PathBuf) {
+	let previous_hook = panic::take_hook();
+
+	panic::set_hook(Box::new(move |info| {
+		let message = info
+			.payload()
+			.downcast_ref::<&str>()
+			.map(|s| s.to_string())
+			.or_else(|| info.payload().downcast_ref::<String>().cloned())
+			.unwrap_or_else(|| "Box<dyn Any>".into());
+
+		let report = PanicReport {
+			message,
+			location: info.location().map(ToString::to_string),
+			thread: thread::current().name().map(str::to_string),
+			backtrace: Backtrace::force_capture().to_string(),
+			version: env!("CARGO_PKG_VERSION").into(),
+			os: std::env::consts::OS.into(),
+			arch: std::env::consts::ARCH.into(),
+			created_at: Utc::now(),
+		};
+
+		let path = dir.join(format!("{}.{PANIC_EXTENSION}", Uuid::new_v4()));
+		match serde_json::to_vec_pretty(&report)
+			.map_err(io::Error::from)
+			.and_then(|json| fs::write(&path, json))
+		{
+			Ok(()) => error!(path = %path.display(), "Panic report written;"),
+			Err(e) => error!(?e, "Failed to write panic report;"),
+		}
+
+		previous_hook(info);
+	}));
+}
+
+fn install_crash_handler(dir: &Path) -> io::Result<()> {
+	let socket_name = std::env::temp_dir().join(format!("sd-crash-{}", std::process::id()));
+
+	// The server exits on its own when we disconnect, crashed or not
+	Command::new(std::env::current_exe()?)
+		.arg(SERVER_ARG)
+		.arg(&socket_name)
+		.arg(dir)
+		.stdin(Stdio::null())
+		.stdout(Stdio::null())
+		.stderr(Stdio::null())
+		.spawn()?;
+
+	let client = connect(&socket_name)?;
+
+	// SAFETY: The callback only asks the (out of process) server for a dump, which is what crash-handler expects
+	let handler = crash_handler::CrashHandler::attach(unsafe {
+		crash_handler::make_crash_event(move |context: &crash_handler::CrashContext| {
+			crash_handler::CrashEventResult::Handled(client.request_dump(context).is_ok())
+		})
+	})
+	.map_err(io::Error::other)?;
+
+	let _ = CRASH_HANDLER.set(handler);
+
+	Ok(())
+}
+
+fn connect(socket_name: &Path) -> io::Result<minidumper::Client> {
+	let mut last_error = None;
+
+	// The server might not be listening yet
+	for _ in 0..50 {
+		match minidumper::Client::with_name(socket_name) {
+			Ok(client) => return Ok(client),
+			Err(e) => last_error = Some(e),
+		}
+		thread::sleep(Duration::from_millis(20));
+	}
+
+	Err(io::Error::other(
+		last_error.map_or_else(|| "unknown error".into(), |e| e.to_string()),
+	))
+}
+
+/// If we were launched as the crash server, runs it and exits.
+/// Must be called before anything else in `main`.
+pub fn run_server_if_requested() {
+	let mut args = std::env::args_os().skip(1);
+	if args.next().as_deref() != Some(SERVER_ARG.as_ref()) {
+		return;
+	}
+
+	let (Some(socket_name), Some(dir)) = (args.next(), args.next()) else {
+		std::process::exit(1);
+	};
+
+	let code = match run_server(Path::new(&socket_name), PathBuf::from(dir)) {
+		Ok(()) => 0,
+		Err(e) => {
+			eprintln!("Crash server failed: {e}");
+			1
+		}
+	};
+
+	std::process::exit(code);
+}
+
+struct MinidumpWriter {
+	dir: PathBuf,
+}
+
+impl minidumper::ServerHandler for MinidumpWriter {
+	fn create_minidump_file(&self) -> Result<(fs::File, PathBuf), io::Error> {
+		fs::create_dir_all(&self.dir)?;
+		let path = self
+			.dir
+			.join(format!("{}.{MINIDUMP_EXTENSION}", Uuid::new_v4()));
+		Ok((fs::File::create(&path)?, path))
+	}
+
+	fn on_minidump_created(
+		&self,
+		result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+	) -> minidumper::LoopAction {
+		match result {
+			Ok(mut minidump) => {
+				let _ = minidump.file.flush();
+			}
+			Err(e) => eprintln!("Failed to write minidump: {e}"),
+		}
+
+		minidumper::LoopAction::Exit
+	}
+
+	fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {}
+
+	fn on_client_disconnected(&self, _num_clients: usize) -> minidumper::LoopAction {
+		minidumper::LoopAction::Exit
+	}
+}
+
+fn run_server(socket_name: &Path, dir: PathBuf) -> Result<(), minidumper::Error> {
+	let shutdown = AtomicBool::new(false);
+	minidumper::Server::with_name(socket_name)?.run(
+		Box::new(MinidumpWriter { dir }),
+		&shutdown,
+		None,
+	)
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn list_crash_reports(
+	state: tauri::State<'_, CrashReports>,
+) -> Result<Vec<CrashReportSummary>, String> {
+	let mut reports = Vec::new();
+	let mut entries = tokio::fs::read_dir(&state.dir)
+		.await
+		.map_err(|e| e.to_string())?;
+
+	while let Some(entry) = entries.next_entry().await.map_err(|e| e.to_string())? {
+		let path = entry.path();
+		let kind = match path.extension().and_then(|ext| ext.to_str()) {
+			Some(PANIC_EXTENSION) => CrashReportKind::Panic,
+			Some(MINIDUMP_EXTENSION) => CrashReportKind::Minidump,
+			_ => continue,
+		};
+
+		let Ok(metadata) = entry.metadata().await else {
+			continue;
+		};
+
+		reports.push(CrashReportSummary {
+			id: entry.file_name().to_string_lossy().into_owned(),
+			kind,
+			created_at: metadata
+				.modified()
+				.map(DateTime::<Utc>::from)
+				.unwrap_or_else(|_| Utc::now()),
+			size: metadata.len(),
+		});
+	}
+
+	reports.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+	Ok(reports)
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn get_crash_report(
+	state: tauri::State<'_, CrashReports>,
+	id: String,
+) -> Result<CrashReport, String> {
+	let path = state.report_path(&id)?;
+
+	if path.extension().is_some_and(|ext| ext == MINIDUMP_EXTENSION) {
+		let size = tokio::fs::metadata(&path)
+			.await
+			.map_err(|e| e.to_string())?
+			.len();
+		return Ok(CrashReport::Minidump { path, size });
+	}
+
+	let json = tokio::fs::read(&path).await.map_err(|e| e.to_string())?;
+	serde_json::from_slice(&json)
+		.map(CrashReport::Panic)
+		.map_err(|e| e.to_string())
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn delete_crash_report(
+	state: tauri::State<'_, CrashReports>,
+	id: String,
+) -> Result<(), String> {
+	tokio::fs::remove_file(state.report_path(&id)?)
+		.await
+		.map_err(|e| e.to_string())
+}
+
+/// Uploads a crash report. `consent` must be explicitly set by the user for each submission.
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn submit_crash_report(
+	app: tauri::AppHandle,
+	id: String,
+	consent: bool,
+) -> Result<(), String> {
+	if !consent {
+		return Err("Crash reports are only submitted with the user's consent".into());
+	}
+
+	let Some(url) = SUBMIT_URL else {
+		return Err("Crash report submission is not available in this build".into());
+	};
+
+	let state = app.state::<CrashReports>();
+	let path = state.report_path(&id)?;
+	let body = tokio::fs::read(&path).await.map_err(|e| e.to_string())?;
+
+	let response = tauri_plugin_http::reqwest::Client::new()
+		.post(url)
+		.query(&[("id", id.as_str()), ("version", env!("CARGO_PKG_VERSION"))])
+		.body(body)
+		.send()
+		.await
+		.map_err(|e| e.to_string())?;
+
+	if !response.status().is_success() {
+		return Err(format!("Crash report submission failed: {}", response.status()));
+	}
+
+	info!(%id, "Crash report submitted;");
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn only_accepts_report_ids() {
+		let reports = CrashReports {
+			dir: PathBuf::from("crashes"),
+		};
+		let id = format!("{}.{PANIC_EXTENSION}", Uuid::new_v4());
+
+		assert_eq!(reports.report_path(&id), Ok(PathBuf::from("crashes").join(&id)));
+		assert!(reports.report_path("../config.json").is_err());
+		assert!(reports.report_path(&format!("{}.txt", Uuid::new_v4())).is_err());
+		assert!(reports
+			.report_path(&format!("{}/../x.{MINIDUMP_EXTENSION}", Uuid::new_v4()))
+			.is_err());
+	}
+}
@@ -0,0 +1,447 @@
+use sd_core::Node;
+use sd_prisma::prisma::file_path;
+
+use std::{path::PathBuf, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Url, WebviewWindowBuilder};
+use thiserror::Error;
+use tokio::sync::Mutex;
+use tracing::{debug, error, warn};
+use uuid::Uuid;
+
+/// The URI scheme we register with the OS, e.g. `overdrive://library/<id>/file/<id>`.
+pub const SCHEME: &str = "overdrive";
+
+/// Scheme used by older builds, links using it are still accepted.
+const LEGACY_SCHEME: &str = "spacedrive";
+
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type, tauri_specta::Event)]
+#[serde(rename_all = "camelCase")]
+pub struct DeepLinkEvent {
+	/// Route inside the interface the frontend should navigate to
+	data: String,
+}
+
+#[derive(Error, Debug)]
+pub enum DeepLinkError {
+	#[error("unsupported scheme: {0}")]
+	UnsupportedScheme(String),
+	#[error("unknown route: {0}")]
+	UnknownRoute(String),
+	#[error("invalid id in route: {0}")]
+	InvalidId(String),
+	#[error("library not found: {0}")]
+	LibraryNotFound(Uuid),
+	#[error("file path not found: {0}")]
+	FilePathNotFound(i32),
+	#[error("file path {0} doesn't belong to a location")]
+	FilePathWithoutLocation(i32),
+	#[error("invalid file url: {0}")]
+	InvalidFileUrl(String),
+	#[error("no library to open {} in", .0.display())]
+	NoLibrary(PathBuf),
+	#[error("database error: {0}")]
+	Database(#[from] prisma_client_rust::QueryError),
+}
+
+/// A deep link parsed into something we know how to route.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLink {
+	/// `overdrive://library/<library_id>`
+	Library { library_id: Uuid },
+	/// `overdrive://library/<library_id>/location/<location_id>`
+	Location {
+		library_id: Uuid,
+		location_id: i32,
+	},
+	/// `overdrive://library/<library_id>/file/<file_path_id>`
+	FilePath {
+		library_id: Uuid,
+		file_path_id: i32,
+	},
+	/// `overdrive://library/<library_id>/tag/<tag_id>`
+	Tag { library_id: Uuid, tag_id: i32 },
+	/// `overdrive://-/<interface route>` - forwarded untouched to the frontend
+	Raw(String),
+	/// `file:///<path>` - a file or folder the OS asked us to open, see [`crate::file_associations`]
+	Path(PathBuf),
+}
+
+impl DeepLink {
+	pub fn parse(url: &Url) -> Result<Self, DeepLinkError> {
+		if url.scheme() == "file" {
+			return url
+				.to_file_path()
+				.map(Self::Path)
+				.map_err(|()| DeepLinkError::InvalidFileUrl(url.to_string()));
+		}
+
+		if url.scheme() != SCHEME && url.scheme() != LEGACY_SCHEME {
+			return Err(DeepLinkError::UnsupportedScheme(url.scheme().to_string()));
+		}
+
+		// `overdrive://library/...` puts `library` in the host position
+		let host = url.host_str().unwrap_or_default();
+		let segments = url
+			.path_segments()
+			.map(|s| s.filter(|s| !s.is_empty()).collect::<Vec<_>>())
+			.unwrap_or_default();
+
+		if host == "-" {
+			let mut route = format!("/{}", segments.join("/"));
+			if let Some(query) = url.query() {
+				route.push('?');
+				route.push_str(query);
+			}
+			if let Some(fragment) = url.fragment() {
+				route.push('#');
+				route.push_str(fragment);
+			}
+			return Ok(Self::Raw(route));
+		}
+
+		if host != "library" {
+			return Err(DeepLinkError::UnknownRoute(url.to_string()));
+		}
+
+		let library_id = segments
+			.first()
+			.ok_or_else(|| DeepLinkError::UnknownRoute(url.to_string()))
+			.and_then(|id| {
+				Uuid::parse_str(id).map_err(|_| DeepLinkError::InvalidId(id.to_string()))
+			})?;
+
+		let parse_id = |id: &str| {
+			id.parse::<i32>()
+				.map_err(|_| DeepLinkError::InvalidId(id.to_string()))
+		};
+
+		match segments.get(1..).unwrap_or_default() {
+			[] => Ok(Self::Library { library_id }),
+			["location", id] => Ok(Self::Location {
+				library_id,
+				location_id: parse_id(id)?,
+			}),
+			["file", id] => Ok(Self::FilePath {
+				library_id,
+				file_path_id: parse_id(id)?,
+			}),
+			["tag", id] => Ok(Self::Tag {
+				library_id,
+				tag_id: parse_id(id)?,
+			}),
+			_ => Err(DeepLinkError::UnknownRoute(url.to_string())),
+		}
+	}
+
+	/// Resolves the link to a route the interface router understands.
+	pub async fn resolve(&self, node: &Node) -> Result<String, DeepLinkError> {
+		match self {
+			Self::Raw(route) => Ok(route.clone()),
+			Self::Library { library_id } => Ok(format!("/{library_id}/overview")),
+			Self::Location {
+				library_id,
+				location_id,
+			} => Ok(format!("/{library_id}/location/{location_id}")),
+			Self::Tag { library_id, tag_id } => Ok(format!("/{library_id}/tag/{tag_id}")),
+			Self::FilePath {
+				library_id,
+				file_path_id,
+			} => {
+				let library = node
+					.libraries
+					.get_library(library_id)
+					.await
+					.ok_or(DeepLinkError::LibraryNotFound(*library_id))?;
+
+				let file_path = library
+					.db
+					.file_path()
+					.find_unique(file_path::id::equals(*file_path_id))
+					.select(file_path::select!({ location_id materialized_path name is_dir }))
+					.exec()
+					.await?
+					.ok_or(DeepLinkError::FilePathNotFound(*file_path_id))?;
+
+				let location_id = file_path
+					.location_id
+					.ok_or(DeepLinkError::FilePathWithoutLocation(*file_path_id))?;
+
+				let materialized_path = file_path.materialized_path.unwrap_or_else(|| "/".into());
+
+				// Directories are opened, files are shown inside their parent directory
+				let path = if file_path.is_dir.unwrap_or(false) {
+					format!(
+						"{materialized_path}{}/",
+						file_path.name.unwrap_or_default()
+					)
+				} else {
+					materialized_path
+				};
+
+				Ok(format!(
+					"/{library_id}/location/{location_id}?{}",
+					encode_query([("path", path)])
+				))
+			}
+			Self::Path(path) => {
+				// Paths outside of locations are browsed ephemerally, any library will do
+				let library = node
+					.libraries
+					.get_all()
+					.await
+					.into_iter()
+					.next()
+					.ok_or_else(|| DeepLinkError::NoLibrary(path.clone()))?;
+
+				// Folders are opened, files are previewed inside their parent folder
+				let query = match path.parent() {
+					Some(parent) if !path.is_dir() => encode_query([
+						("path", parent.to_string_lossy().into_owned()),
+						("open", path.to_string_lossy().into_owned()),
+					]),
+					_ => encode_query([("path", path.to_string_lossy().into_owned())]),
+				};
+
+				Ok(format!("/{}/ephemeral/0?{query}", library.id))
+			}
+		}
+	}
+}
+
+fn encode_query<const N: usize>(params: [(&str, String); N]) -> String {
+	Url::parse_with_params("http://-", params)
+		.ok()
+		.and_then(|url| url.query().map(str::to_string))
+		.unwrap_or_default()
+}
+
+/// Links received before the frontend has finished loading are held here
+/// and flushed once `app_ready` is called.
+#[derive(Default)]
+pub struct DeepLinkState {
+	// Behind a single lock, so a link can't be held right after the held ones were flushed
+	inner: Mutex<DeepLinkStateInner>,
+}
+
+#[derive(Default)]
+struct DeepLinkStateInner {
+	pending: Vec<String>,
+	ready: bool,
+}
+
+impl DeepLinkState {
+	/// Emits the route, or holds it until the frontend is ready.
+	async fn route(&self, route: String, emit: impl Fn(String)) {
+		let mut inner = self.inner.lock().await;
+		if inner.ready {
+			emit(route);
+		} else {
+			inner.pending.push(route);
+		}
+	}
+
+	/// Marks the frontend as ready and emits the held routes, in the order they came in.
+	async fn ready(&self, emit: impl Fn(String)) {
+		let mut inner = self.inner.lock().await;
+		inner.ready = true;
+		inner.pending.drain(..).for_each(emit);
+	}
+}
+
+pub async fn handle_urls(app: &AppHandle, urls: Vec<Url>) {
+	for url in urls {
+		debug!(%url, "Received deep link;");
+
+		let link = match DeepLink::parse(&url) {
+			Ok(link) => link,
+			Err(e) => {
+				warn!(%url, ?e, "Ignoring invalid deep link;");
+				continue;
+			}
+		};
+
+		let Some(node) = app.try_state::<Arc<Node>>() else {
+			warn!(%url, "Deep link received before the core started;");
+			continue;
+		};
+
+		match link.resolve(&node).await {
+			Ok(route) => dispatch(app, route).await,
+			Err(e) => error!(%url, ?e, "Failed to resolve deep link;"),
+		}
+	}
+}
+
+async fn dispatch(app: &AppHandle, route: String) {
+	focus_main_window(app);
+
+	app.state::<DeepLinkState>()
+		.route(route, |route| emit(app, route))
+		.await;
+}
+
+/// Called once the frontend is listening, replays any links we held back.
+pub async fn flush_pending(app: &AppHandle) {
+	app.state::<DeepLinkState>()
+		.ready(|route| emit(app, route))
+		.await;
+}
+
+fn emit(app: &AppHandle, route: String) {
+	if let Err(e) = app.emit("deeplink", DeepLinkEvent { data: route }) {
+		error!(?e, "Failed to emit deep link event;");
+	}
+}
+
+fn focus_main_window(app: &AppHandle) {
+	let window = match app.get_webview_window("main") {
+		Some(window) => window,
+		None => {
+			let Some(config) = app.config().app.windows.first() else {
+				error!("No window configuration to recreate the main window from;");
+				return;
+			};
+
+			match WebviewWindowBuilder::from_config(app, config).and_then(|b| b.build()) {
+				Ok(window) => window,
+				Err(e) => {
+					error!(?e, "Failed to recreate main window for deep link;");
+					return;
+				}
+			}
+		}
+	};
+
+	window.unminimize().ok();
+	window.show().ok();
+	window.set_focus().ok();
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(url: &str) -> Result<DeepLink, DeepLinkError> {
+		DeepLink::parse(&Url::parse(url).expect("test url must be valid"))
+	}
+
+	#[test]
+	fn parses_library_routes() {
+		let library_id = Uuid::new_v4();
+
+		assert_eq!(
+			parse(&format!("overdrive://library/{library_id}")).ok(),
+			Some(DeepLink::Library { library_id })
+		);
+		assert_eq!(
+			parse(&format!("overdrive://library/{library_id}/file/42")).ok(),
+			Some(DeepLink::FilePath {
+				library_id,
+				file_path_id: 42
+			})
+		);
+		assert_eq!(
+			parse(&format!("overdrive://library/{library_id}/location/3/")).ok(),
+			Some(DeepLink::Location {
+				library_id,
+				location_id: 3
+			})
+		);
+	}
+
+	#[test]
+	#[cfg(unix)]
+	fn parses_file_urls() {
+		assert_eq!(
+			parse("file:///home/me/Pictures/cat%20photo.jpg").ok(),
+			Some(DeepLink::Path(PathBuf::from("/home/me/Pictures/cat photo.jpg")))
+		);
+	}
+
+	#[test]
+	fn keeps_legacy_raw_routes() {
+		assert_eq!(
+			parse("spacedrive://-/settings/client/general?a=b").ok(),
+			Some(DeepLink::Raw("/settings/client/general?a=b".into()))
+		);
+	}
+
+	fn recorder() -> (Arc<std::sync::Mutex<Vec<String>>>, impl Fn(String) + Clone) {
+		let emitted = Arc::new(std::sync::Mutex::new(vec![]));
+		let emit = {
+			let emitted = Arc::clone(&emitted);
+			move |route| emitted.lock().expect("recorder lock poisoned").push(route)
+		};
+		(emitted, emit)
+	}
+
+	#[test]
+	fn holds_routes_until_ready() {
+		tauri::async_runtime::block_on(async {
+			let state = DeepLinkState::default();
+			let (emitted, emit) = recorder();
+
+			state.route("/a".into(), emit.clone()).await;
+			state.route("/b".into(), emit.clone()).await;
+			assert!(emitted.lock().unwrap().is_empty());
+
+			state.ready(emit.clone()).await;
+			state.route("/c".into(), emit).await;
+			assert_eq!(*emitted.lock().unwrap(), ["/a", "/b", "/c"]);
+		});
+	}
+
+	#[test]
+	fn routes_racing_the_frontend_are_not_lost() {
+		const ROUTES: usize = 16;
+
+		tauri::async_runtime::block_on(async {
+			for _ in 0..100 {
+				let state = Arc::new(DeepLinkState::default());
+				let (emitted, emit) = recorder();
+
+				// Some come in before the frontend is ready, some while the held ones are flushed
+				let routes = (0..ROUTES)
+					.map(|idx| {
+						let state = Arc::clone(&state);
+						let emit = emit.clone();
+						tauri::async_runtime::spawn(async move {
+							state.route(format!("/{idx}"), emit).await;
+						})
+					})
+					.collect::<Vec<_>>();
+				state.ready(emit).await;
+				for route in routes {
+					route.await.expect("route task panicked");
+				}
+
+				let mut emitted = emitted.lock().unwrap().clone();
+				emitted.sort();
+				emitted.dedup();
+				assert_eq!(emitted.len(), ROUTES);
+			}
+		});
+	}
+
+	#[test]
+	fn rejects_bad_links() {
+		assert!(matches!(
+			parse("https://library/abc"),
+			Err(DeepLinkError::UnsupportedScheme(_))
+		));
+		assert!(matches!(
+			parse("overdrive://library/not-a-uuid"),
+			Err(DeepLinkError::InvalidId(_))
+		));
+		assert!(matches!(
+			parse(&format!("overdrive://library/{}/file/x", Uuid::new_v4())),
+			Err(DeepLinkError::InvalidId(_))
+		));
+		assert!(matches!(
+			parse("overdrive://nowhere"),
+			Err(DeepLinkError::UnknownRoute(_))
+		));
+	}
+}
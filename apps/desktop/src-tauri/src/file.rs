@@ -405,3 +405,91 @@ pub async fn reveal_items(
 
 	Ok(())
 }
+
+/// Shows the native preview for `paths`: the Quick Look panel on macOS,
+/// GNOME Sushi or QuickLook for Windows when installed, the default app otherwise.
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn quick_look(paths: Vec<PathBuf>) -> Result<(), String> {
+	if paths.is_empty() {
+		return Ok(());
+	}
+
+	spawn_blocking(move || quick_look::preview(&paths))
+		.await
+		.map_err(|e| e.to_string())?
+}
+
+mod quick_look {
+	use std::path::PathBuf;
+
+	#[cfg(target_os = "macos")]
+	pub fn preview(paths: &[PathBuf]) -> Result<(), String> {
+		use std::process::{Command, Stdio};
+
+		// `qlmanage` blocks until the panel is closed, so we don't wait on it
+		Command::new("qlmanage")
+			.arg("-p")
+			.args(paths)
+			.stdout(Stdio::null())
+			.stderr(Stdio::null())
+			.spawn()
+			.map(|_| ())
+			.map_err(|e| format!("Failed to open Quick Look: {e}"))
+	}
+
+	#[cfg(target_os = "linux")]
+	pub fn preview(paths: &[PathBuf]) -> Result<(), String> {
+		use std::time::Duration;
+
+		use dbus::blocking::LocalConnection;
+
+		// Sushi only previews a single file
+		let path = &paths[0];
+
+		let sushi = || -> Result<(), dbus::Error> {
+			let uri = tauri::Url::from_file_path(path)
+				.map_err(|()| dbus::Error::new_failed("Path is not absolute"))?;
+
+			LocalConnection::new_session()?
+				.with_proxy(
+					"org.gnome.NautilusPreviewer",
+					"/org/gnome/NautilusPreviewer",
+					Duration::from_secs(2),
+				)
+				.method_call(
+					"org.gnome.NautilusPreviewer",
+					"ShowFile",
+					(uri.as_str(), 0i32, false),
+				)
+		};
+
+		sushi().or_else(|_| opener::open(path).map_err(|e| e.to_string()))
+	}
+
+	#[cfg(target_os = "windows")]
+	pub fn preview(paths: &[PathBuf]) -> Result<(), String> {
+		use std::process::Command;
+
+		let path = &paths[0];
+
+		// https://github.com/QL-Win/QuickLook installs per user by default
+		let quick_look = std::env::var_os("LOCALAPPDATA")
+			.map(|dir| PathBuf::from(dir).join(r"Programs\QuickLook\QuickLook.exe"))
+			.filter(|exe| exe.exists());
+
+		match quick_look {
+			Some(exe) => Command::new(exe)
+				.arg(path)
+				.spawn()
+				.map(|_| ())
+				.map_err(|e| e.to_string()),
+			None => opener::open(path).map_err(|e| e.to_string()),
+		}
+	}
+
+	#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+	pub fn preview(paths: &[PathBuf]) -> Result<(), String> {
+		opener::open(&paths[0]).map_err(|e| e.to_string())
+	}
+}
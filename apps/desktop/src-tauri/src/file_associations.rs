@@ -0,0 +1,829 @@
+//! Registering the app as the handler of media files, so they open straight into the viewer,
+//! and optionally of folders.
+//!
+//! Each platform keeps its associations differently:
+//!  - macOS: the Launch Services default role handler of each content type
+//!  - Windows: `OpenWithProgids` entries under `HKCU\Software\Classes`. Windows doesn't let apps
+//!    change the default handler themselves, so the user has to confirm it in the system settings
+//!  - Linux: a desktop entry of our own made the default with `xdg-mime`
+//!
+//! The handlers we replace are remembered so they can be restored when unregistering.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::{async_runtime::spawn_blocking, AppHandle, Manager, Url};
+use tokio::sync::Mutex;
+use tracing::{error, warn};
+
+const STATE_FILE: &str = "file_associations.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum FileAssociationKind {
+	Image,
+	Video,
+	Audio,
+	Document,
+}
+
+/// A file type we can be registered for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)] // Not every platform uses every field
+pub(crate) struct FileType {
+	pub extension: &'static str,
+	pub kind: FileAssociationKind,
+	pub mime_type: &'static str,
+	/// Uniform Type Identifier, used on macOS
+	pub uti: &'static str,
+}
+
+macro_rules! file_types {
+	($($extension:literal => $kind:ident, $mime_type:literal, $uti:literal;)*) => {
+		pub(crate) const FILE_TYPES: &[FileType] = &[$(FileType {
+			extension: $extension,
+			kind: FileAssociationKind::$kind,
+			mime_type: $mime_type,
+			uti: $uti,
+		}),*];
+	};
+}
+
+// Keep in sync with `bundle.fileAssociations` in `tauri.conf.json`
+file_types! {
+	"jpg" => Image, "image/jpeg", "public.jpeg";
+	"jpeg" => Image, "image/jpeg", "public.jpeg";
+	"png" => Image, "image/png", "public.png";
+	"gif" => Image, "image/gif", "com.compuserve.gif";
+	"webp" => Image, "image/webp", "org.webmproject.webp";
+	"heic" => Image, "image/heic", "public.heic";
+	"avif" => Image, "image/avif", "public.avif";
+	"bmp" => Image, "image/bmp", "com.microsoft.bmp";
+	"svg" => Image, "image/svg+xml", "public.svg-image";
+	"mp4" => Video, "video/mp4", "public.mpeg-4";
+	"mov" => Video, "video/quicktime", "com.apple.quicktime-movie";
+	"mkv" => Video, "video/x-matroska", "org.matroska.mkv";
+	"webm" => Video, "video/webm", "org.webmproject.webm";
+	"avi" => Video, "video/x-msvideo", "public.avi";
+	"mp3" => Audio, "audio/mpeg", "public.mp3";
+	"flac" => Audio, "audio/flac", "org.xiph.flac";
+	"wav" => Audio, "audio/wav", "com.microsoft.waveform-audio";
+	"m4a" => Audio, "audio/mp4", "com.apple.m4a-audio";
+	"ogg" => Audio, "audio/ogg", "org.xiph.ogg-audio";
+	"pdf" => Document, "application/pdf", "com.adobe.pdf";
+}
+
+/// Content types of folders, used to become the folder handler
+const FOLDER_MIME_TYPE: &str = "inode/directory";
+const FOLDER_UTI: &str = "public.folder";
+
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct FileAssociation {
+	pub extension: String,
+	pub kind: FileAssociationKind,
+	/// Whether we registered ourselves for this file type
+	pub registered: bool,
+	/// Whether files of this type currently open with us
+	pub is_default: bool,
+	/// The app which opens files of this type, if it's not us. Registering would take over from it.
+	pub conflict: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct FileAssociations {
+	pub file_types: Vec<FileAssociation>,
+	/// Whether folders open with us
+	pub folder_handler: bool,
+	/// On platforms where apps can't make themselves the default (Windows), registering
+	/// only offers us as a choice and the user has to pick us in the system settings.
+	pub requires_user_confirmation: bool,
+}
+
+/// Handlers we replaced, keyed by extension (or the folder mime type), so they can be restored
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PersistedState {
+	registered: Vec<String>,
+	folder_handler: bool,
+	previous_handlers: HashMap<String, String>,
+}
+
+pub struct FileAssociationsState {
+	state_path: PathBuf,
+	state: Mutex<PersistedState>,
+}
+
+impl FileAssociationsState {
+	pub async fn load(app: &AppHandle) -> Self {
+		let state_path = app
+			.path()
+			.app_data_dir()
+			.unwrap_or_else(|_| PathBuf::from("./"))
+			.join(STATE_FILE);
+
+		let state = match tokio::fs::read(&state_path).await {
+			Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+				warn!(?e, "Corrupted file associations state, starting over;");
+				PersistedState::default()
+			}),
+			Err(_) => PersistedState::default(),
+		};
+
+		Self {
+			state_path,
+			state: Mutex::new(state),
+		}
+	}
+
+	async fn save(&self, state: &PersistedState) {
+		let result = match serde_json::to_vec(state) {
+			Ok(bytes) => tokio::fs::write(&self.state_path, bytes).await,
+			Err(e) => Err(e.into()),
+		};
+
+		if let Err(e) = result {
+			error!(?e, "Failed to save file associations state;");
+		}
+	}
+}
+
+fn file_type(extension: &str) -> Result<&'static FileType, String> {
+	let extension = extension.trim_start_matches('.').to_lowercase();
+	FILE_TYPES
+		.iter()
+		.find(|file_type| file_type.extension == extension)
+		.ok_or_else(|| format!("Unsupported file type: {extension}"))
+}
+
+/// Paths the OS launched us with to open, as `file://` urls for [`crate::deep_link::handle_urls`].
+/// On macOS they are delivered through `RunEvent::Opened` instead.
+pub fn launch_urls() -> Vec<Url> {
+	std::env::args_os()
+		.skip(1)
+		.filter(|arg| !arg.to_string_lossy().starts_with('-'))
+		.map(PathBuf::from)
+		.filter(|path| path.exists())
+		.filter_map(|path| path.canonicalize().ok())
+		.filter_map(|path| Url::from_file_path(path).ok())
+		.collect()
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn get_file_associations(
+	app: AppHandle,
+	state: tauri::State<'_, FileAssociationsState>,
+) -> Result<FileAssociations, String> {
+	let state = state.state.lock().await.clone();
+
+	spawn_blocking(move || {
+		let handler = platform::Handler::new(&app)?;
+
+		let file_types = FILE_TYPES
+			.iter()
+			.map(|file_type| {
+				let current = handler.current(file_type);
+				let is_default = current.as_deref().is_some_and(|current| handler.is_us(current));
+
+				FileAssociation {
+					extension: file_type.extension.into(),
+					kind: file_type.kind,
+					registered: state.registered.iter().any(|ext| ext == file_type.extension),
+					is_default,
+					conflict: current.filter(|_| !is_default),
+				}
+			})
+			.collect();
+
+		Ok(FileAssociations {
+			file_types,
+			folder_handler: state.folder_handler,
+			requires_user_confirmation: platform::REQUIRES_USER_CONFIRMATION,
+		})
+	})
+	.await
+	.map_err(|e| e.to_string())?
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn register_file_associations(
+	app: AppHandle,
+	state: tauri::State<'_, FileAssociationsState>,
+	extensions: Vec<String>,
+) -> Result<(), String> {
+	let file_types = extensions
+		.iter()
+		.map(|extension| file_type(extension))
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let mut state_guard = state.state.lock().await;
+	let mut new_state = state_guard.clone();
+
+	let result = spawn_blocking(move || {
+		let handler = platform::Handler::new(&app)?;
+
+		for file_type in file_types {
+			if let Some(current) = handler.current(file_type).filter(|c| !handler.is_us(c)) {
+				new_state
+					.previous_handlers
+					.entry(file_type.extension.into())
+					.or_insert(current);
+			}
+
+			handler.register(file_type)?;
+
+			if !new_state.registered.iter().any(|ext| ext == file_type.extension) {
+				new_state.registered.push(file_type.extension.into());
+			}
+		}
+
+		handler.refresh();
+
+		Ok::<_, String>(new_state)
+	})
+	.await
+	.map_err(|e| e.to_string())?;
+
+	match result {
+		Ok(new_state) => {
+			state.save(&new_state).await;
+			*state_guard = new_state;
+			Ok(())
+		}
+		Err(e) => {
+			error!(%e, "Failed to register file associations;");
+			Err(e)
+		}
+	}
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn unregister_file_associations(
+	app: AppHandle,
+	state: tauri::State<'_, FileAssociationsState>,
+	extensions: Vec<String>,
+) -> Result<(), String> {
+	let file_types = extensions
+		.iter()
+		.map(|extension| file_type(extension))
+		.collect::<Result<Vec<_>, _>>()?;
+
+	let mut state_guard = state.state.lock().await;
+	let mut new_state = state_guard.clone();
+
+	let new_state = spawn_blocking(move || {
+		let handler = platform::Handler::new(&app)?;
+
+		for file_type in file_types {
+			let previous = new_state.previous_handlers.remove(file_type.extension);
+			handler.unregister(file_type, previous.as_deref())?;
+			new_state.registered.retain(|ext| ext != file_type.extension);
+		}
+
+		handler.refresh();
+
+		Ok::<_, String>(new_state)
+	})
+	.await
+	.map_err(|e| e.to_string())??;
+
+	state.save(&new_state).await;
+	*state_guard = new_state;
+
+	Ok(())
+}
+
+/// Makes folders open with us, e.g. when double clicking them on the desktop
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn set_folder_handler(
+	app: AppHandle,
+	state: tauri::State<'_, FileAssociationsState>,
+	enabled: bool,
+) -> Result<(), String> {
+	let mut state_guard = state.state.lock().await;
+	let mut new_state = state_guard.clone();
+
+	let new_state = spawn_blocking(move || {
+		let handler = platform::Handler::new(&app)?;
+
+		if enabled {
+			if let Some(current) = handler.current_folder().filter(|c| !handler.is_us(c)) {
+				new_state
+					.previous_handlers
+					.entry(FOLDER_MIME_TYPE.into())
+					.or_insert(current);
+			}
+			handler.register_folder()?;
+		} else {
+			let previous = new_state.previous_handlers.remove(FOLDER_MIME_TYPE);
+			handler.unregister_folder(previous.as_deref())?;
+		}
+
+		handler.refresh();
+		new_state.folder_handler = enabled;
+
+		Ok::<_, String>(new_state)
+	})
+	.await
+	.map_err(|e| e.to_string())??;
+
+	state.save(&new_state).await;
+	*state_guard = new_state;
+
+	Ok(())
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+	use super::{FileAssociationKind, FileType, FOLDER_UTI};
+
+	use core_foundation::{
+		base::TCFType,
+		string::{CFString, CFStringRef},
+	};
+	use tauri::AppHandle;
+
+	pub const REQUIRES_USER_CONFIRMATION: bool = false;
+
+	const K_LS_ROLES_VIEWER: u32 = 0x0000_0002;
+	const K_LS_ROLES_ALL: u32 = 0xFFFF_FFFF;
+
+	#[link(name = "CoreServices", kind = "framework")]
+	extern "C" {
+		fn LSCopyDefaultRoleHandlerForContentType(
+			content_type: CFStringRef,
+			role: u32,
+		) -> CFStringRef;
+		fn LSSetDefaultRoleHandlerForContentType(
+			content_type: CFStringRef,
+			role: u32,
+			handler_bundle_id: CFStringRef,
+		) -> i32;
+	}
+
+	pub struct Handler {
+		bundle_id: String,
+	}
+
+	impl Handler {
+		pub fn new(app: &AppHandle) -> Result<Self, String> {
+			Ok(Self {
+				bundle_id: app.config().identifier.clone(),
+			})
+		}
+
+		pub fn is_us(&self, handler: &str) -> bool {
+			handler.eq_ignore_ascii_case(&self.bundle_id)
+		}
+
+		fn current_for(&self, uti: &str) -> Option<String> {
+			let uti = CFString::new(uti);
+			// SAFETY: The returned string follows the copy rule, so we take ownership of it
+			unsafe {
+				let handler =
+					LSCopyDefaultRoleHandlerForContentType(uti.as_concrete_TypeRef(), K_LS_ROLES_ALL);
+				(!handler.is_null()).then(|| CFString::wrap_under_create_rule(handler).to_string())
+			}
+		}
+
+		fn set_for(&self, uti: &str, bundle_id: &str) -> Result<(), String> {
+			let (uti, bundle_id) = (CFString::new(uti), CFString::new(bundle_id));
+			// SAFETY: Both strings outlive the call
+			let status = unsafe {
+				LSSetDefaultRoleHandlerForContentType(
+					uti.as_concrete_TypeRef(),
+					K_LS_ROLES_VIEWER,
+					bundle_id.as_concrete_TypeRef(),
+				)
+			};
+
+			if status == 0 {
+				Ok(())
+			} else {
+				Err(format!("Launch Services error {status} for {uti}"))
+			}
+		}
+
+		pub fn current(&self, file_type: &FileType) -> Option<String> {
+			self.current_for(file_type.uti)
+		}
+
+		pub fn register(&self, file_type: &FileType) -> Result<(), String> {
+			self.set_for(file_type.uti, &self.bundle_id)
+		}
+
+		pub fn unregister(&self, file_type: &FileType, previous: Option<&str>) -> Result<(), String> {
+			// The user picked another app since, it stays
+			if !self
+				.current(file_type)
+				.is_some_and(|current| self.is_us(&current))
+			{
+				return Ok(());
+			}
+
+			// Launch Services always needs a handler, so without a previous one it goes back to
+			// the app opening the type on a fresh system
+			self.set_for(
+				file_type.uti,
+				previous.unwrap_or_else(|| system_default(file_type)),
+			)
+		}
+
+		pub fn current_folder(&self) -> Option<String> {
+			self.current_for(FOLDER_UTI)
+		}
+
+		pub fn register_folder(&self) -> Result<(), String> {
+			self.set_for(FOLDER_UTI, &self.bundle_id)
+		}
+
+		pub fn unregister_folder(&self, previous: Option<&str>) -> Result<(), String> {
+			self.set_for(FOLDER_UTI, previous.unwrap_or("com.apple.finder"))
+		}
+
+		pub fn refresh(&self) {}
+	}
+
+	pub(super) fn system_default(file_type: &FileType) -> &'static str {
+		match file_type.kind {
+			FileAssociationKind::Image | FileAssociationKind::Document => "com.apple.Preview",
+			FileAssociationKind::Video | FileAssociationKind::Audio => "com.apple.QuickTimePlayerX",
+		}
+	}
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+	use super::FileType;
+
+	use std::path::PathBuf;
+
+	use tauri::AppHandle;
+	use windows::{
+		core::{HSTRING, PCWSTR},
+		Win32::{
+			Foundation::{ERROR_FILE_NOT_FOUND, WIN32_ERROR},
+			System::Registry::{
+				RegDeleteKeyValueW, RegDeleteTreeW, RegGetValueW, RegSetKeyValueW,
+				HKEY_CURRENT_USER, REG_SZ, RRF_RT_REG_SZ,
+			},
+			UI::Shell::{SHChangeNotify, SHCNE_ASSOCCHANGED, SHCNF_IDLIST},
+		},
+	};
+
+	pub const REQUIRES_USER_CONFIRMATION: bool = true;
+
+	const CLASSES: &str = r"Software\Classes";
+	const FILE_EXTS: &str = r"Software\Microsoft\Windows\CurrentVersion\Explorer\FileExts";
+	const FOLDER_VERB: &str = r"Software\Classes\Directory\shell\Spacedrive";
+
+	pub struct Handler {
+		prog_id: String,
+		exe: PathBuf,
+		name: String,
+	}
+
+	fn set_string(key: &str, value_name: Option<&str>, value: &str) -> Result<(), String> {
+		let data = value
+			.encode_utf16()
+			.chain(Some(0))
+			.flat_map(u16::to_le_bytes)
+			.collect::<Vec<_>>();
+		let value_name = value_name.map(HSTRING::from);
+
+		// SAFETY: `data` is a null terminated UTF-16 string which outlives the call
+		unsafe {
+			RegSetKeyValueW(
+				HKEY_CURRENT_USER,
+				&HSTRING::from(key),
+				value_name
+					.as_ref()
+					.map_or(PCWSTR::null(), |name| PCWSTR(name.as_ptr())),
+				REG_SZ.0,
+				Some(data.as_ptr().cast()),
+				data.len() as u32,
+			)
+		}
+		.ok()
+		.map_err(|e| e.to_string())
+	}
+
+	fn get_string(key: &str, value_name: &str) -> Option<String> {
+		let (key, value_name) = (HSTRING::from(key), HSTRING::from(value_name));
+		let mut buffer = vec![0u16; 512];
+		let mut size = (buffer.len() * 2) as u32;
+
+		// SAFETY: `size` is the size of `buffer` in bytes
+		unsafe {
+			RegGetValueW(
+				HKEY_CURRENT_USER,
+				&key,
+				&value_name,
+				RRF_RT_REG_SZ,
+				None,
+				Some(buffer.as_mut_ptr().cast()),
+				Some(&mut size),
+			)
+		}
+		.ok()
+		.ok()?;
+
+		buffer.truncate((size as usize / 2).saturating_sub(1));
+		Some(String::from_utf16_lossy(&buffer))
+	}
+
+	fn ignore_not_found(result: WIN32_ERROR) -> Result<(), String> {
+		if result == ERROR_FILE_NOT_FOUND {
+			return Ok(());
+		}
+		result.ok().map_err(|e| e.to_string())
+	}
+
+	impl Handler {
+		pub fn new(app: &AppHandle) -> Result<Self, String> {
+			let name = app
+				.config()
+				.product_name
+				.clone()
+				.unwrap_or_else(|| "Spacedrive".into());
+
+			Ok(Self {
+				prog_id: format!("{name}.File"),
+				exe: std::env::current_exe().map_err(|e| e.to_string())?,
+				name,
+			})
+		}
+
+		pub fn is_us(&self, handler: &str) -> bool {
+			handler.eq_ignore_ascii_case(&self.prog_id)
+		}
+
+		fn open_command(&self) -> String {
+			format!("\"{}\" \"%1\"", self.exe.display())
+		}
+
+		/// The choice the user made in the "Open with" dialog or the system settings
+		pub fn current(&self, file_type: &FileType) -> Option<String> {
+			get_string(
+				&format!(r"{FILE_EXTS}\.{}\UserChoice", file_type.extension),
+				"ProgId",
+			)
+		}
+
+		pub fn register(&self, file_type: &FileType) -> Result<(), String> {
+			let prog_id = format!(r"{CLASSES}\{}", self.prog_id);
+			set_string(&prog_id, None, &format!("{} file", self.name))?;
+			set_string(&format!(r"{prog_id}\shell\open\command"), None, &self.open_command())?;
+
+			set_string(
+				&format!(r"{CLASSES}\.{}\OpenWithProgids", file_type.extension),
+				Some(&self.prog_id),
+				"",
+			)
+		}
+
+		pub fn unregister(&self, file_type: &FileType, _previous: Option<&str>) -> Result<(), String> {
+			// The previous handler is still the user's choice unless they picked us,
+			// in which case Windows asks them again on the next open
+			let key = HSTRING::from(format!(
+				r"{CLASSES}\.{}\OpenWithProgids",
+				file_type.extension
+			));
+			// SAFETY: Only deletes a value of our own
+			ignore_not_found(unsafe {
+				RegDeleteKeyValueW(HKEY_CURRENT_USER, &key, &HSTRING::from(&self.prog_id))
+			})
+		}
+
+		pub fn current_folder(&self) -> Option<String> {
+			None
+		}
+
+		/// Explorer can't be replaced as the folder handler, we add an "Open in Spacedrive" folder verb instead
+		pub fn register_folder(&self) -> Result<(), String> {
+			set_string(FOLDER_VERB, None, &format!("Open in {}", self.name))?;
+			set_string(FOLDER_VERB, Some("Icon"), &self.exe.to_string_lossy())?;
+			set_string(&format!(r"{FOLDER_VERB}\command"), None, &self.open_command())
+		}
+
+		pub fn unregister_folder(&self, _previous: Option<&str>) -> Result<(), String> {
+			// SAFETY: Only deletes a key of our own
+			ignore_not_found(unsafe {
+				RegDeleteTreeW(HKEY_CURRENT_USER, &HSTRING::from(FOLDER_VERB))
+			})
+		}
+
+		pub fn refresh(&self) {
+			// SAFETY: Tells the shell associations changed, no items are passed
+			unsafe { SHChangeNotify(SHCNE_ASSOCCHANGED, SHCNF_IDLIST, None, None) };
+		}
+	}
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod platform {
+	use super::{FileType, FILE_TYPES, FOLDER_MIME_TYPE};
+
+	use std::{
+		fs,
+		path::PathBuf,
+		process::{Command, Stdio},
+	};
+
+	use tauri::AppHandle;
+
+	pub const REQUIRES_USER_CONFIRMATION: bool = false;
+
+	pub struct Handler {
+		desktop_file: String,
+		entry_path: PathBuf,
+		exe: PathBuf,
+		name: String,
+	}
+
+	fn xdg_mime(args: &[&str]) -> Result<String, String> {
+		let output = Command::new("xdg-mime")
+			.args(args)
+			.stdin(Stdio::null())
+			.output()
+			.map_err(|e| format!("Failed to run xdg-mime: {e}"))?;
+
+		if !output.status.success() {
+			return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+		}
+
+		Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+	}
+
+	impl Handler {
+		pub fn new(app: &AppHandle) -> Result<Self, String> {
+			let applications_dir = std::env::var_os("XDG_DATA_HOME")
+				.map(PathBuf::from)
+				.filter(|dir| dir.is_absolute())
+				.or_else(|| {
+					std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+				})
+				.ok_or("HOME is not set")?
+				.join("applications");
+
+			// AppImages are mounted at a different path on each launch, we must start the image itself
+			let exe = std::env::var_os("APPIMAGE")
+				.map(PathBuf::from)
+				.map_or_else(std::env::current_exe, Ok)
+				.map_err(|e| e.to_string())?;
+
+			let desktop_file = format!("{}.desktop", app.config().identifier);
+
+			Ok(Self {
+				entry_path: applications_dir.join(&desktop_file),
+				desktop_file,
+				exe,
+				name: app
+					.config()
+					.product_name
+					.clone()
+					.unwrap_or_else(|| "Spacedrive".into()),
+			})
+		}
+
+		pub fn is_us(&self, handler: &str) -> bool {
+			handler == self.desktop_file
+		}
+
+		/// Our own desktop entry, listing every type we can open so we show up in "Open with" menus
+		fn write_entry(&self) -> Result<(), String> {
+			if let Some(parent) = self.entry_path.parent() {
+				fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+			}
+
+			let mut mime_types = FILE_TYPES
+				.iter()
+				.map(|file_type| file_type.mime_type)
+				.chain(Some(FOLDER_MIME_TYPE))
+				.collect::<Vec<_>>();
+			mime_types.dedup();
+
+			fs::write(
+				&self.entry_path,
+				format!(
+					"[Desktop Entry]\n\
+					Type=Application\n\
+					Name={name}\n\
+					Exec={exec} %F\n\
+					Terminal=false\n\
+					NoDisplay=true\n\
+					MimeType={mime_types};\n",
+					name = self.name,
+					exec = crate::autostart::quote_exec(&self.exe.to_string_lossy()),
+					mime_types = mime_types.join(";"),
+				),
+			)
+			.map_err(|e| e.to_string())
+		}
+
+		fn current_for(&self, mime_type: &str) -> Option<String> {
+			xdg_mime(&["query", "default", mime_type])
+				.ok()
+				.filter(|handler| !handler.is_empty())
+		}
+
+		fn set_for(&self, mime_type: &str, desktop_file: &str) -> Result<(), String> {
+			xdg_mime(&["default", desktop_file, mime_type]).map(|_| ())
+		}
+
+		pub fn current(&self, file_type: &FileType) -> Option<String> {
+			self.current_for(file_type.mime_type)
+		}
+
+		pub fn register(&self, file_type: &FileType) -> Result<(), String> {
+			self.write_entry()?;
+			self.set_for(file_type.mime_type, &self.desktop_file)
+		}
+
+		pub fn unregister(&self, file_type: &FileType, previous: Option<&str>) -> Result<(), String> {
+			if !self
+				.current(file_type)
+				.is_some_and(|current| self.is_us(&current))
+			{
+				return Ok(());
+			}
+
+			match previous {
+				Some(previous) => self.set_for(file_type.mime_type, previous),
+				// Nothing to go back to, the desktop environment falls back to its own default
+				None => Ok(()),
+			}
+		}
+
+		pub fn current_folder(&self) -> Option<String> {
+			self.current_for(FOLDER_MIME_TYPE)
+		}
+
+		pub fn register_folder(&self) -> Result<(), String> {
+			self.write_entry()?;
+			self.set_for(FOLDER_MIME_TYPE, &self.desktop_file)
+		}
+
+		pub fn unregister_folder(&self, previous: Option<&str>) -> Result<(), String> {
+			if !self
+				.current_folder()
+				.is_some_and(|current| self.is_us(&current))
+			{
+				return Ok(());
+			}
+
+			previous.map_or(Ok(()), |previous| self.set_for(FOLDER_MIME_TYPE, previous))
+		}
+
+		pub fn refresh(&self) {
+			if let Some(dir) = self.entry_path.parent() {
+				// Best effort, not every system has it
+				let _ = Command::new("update-desktop-database")
+					.arg(dir)
+					.stdout(Stdio::null())
+					.stderr(Stdio::null())
+					.status();
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn looks_up_file_types() {
+		assert_eq!(file_type(".JPG").map(|t| t.mime_type), Ok("image/jpeg"));
+		assert_eq!(file_type("mkv").map(|t| t.kind), Ok(FileAssociationKind::Video));
+		assert!(file_type("exe").is_err());
+	}
+
+	#[test]
+	#[cfg(target_os = "macos")]
+	fn media_goes_back_to_quicktime() {
+		let default = |extension| file_type(extension).map(platform::system_default);
+
+		assert_eq!(default("png"), Ok("com.apple.Preview"));
+		assert_eq!(default("pdf"), Ok("com.apple.Preview"));
+		assert_eq!(default("mov"), Ok("com.apple.QuickTimePlayerX"));
+		assert_eq!(default("mp3"), Ok("com.apple.QuickTimePlayerX"));
+	}
+
+	#[test]
+	fn file_types_are_unique() {
+		for (i, file_type) in FILE_TYPES.iter().enumerate() {
+			assert!(
+				!FILE_TYPES[i + 1..]
+					.iter()
+					.any(|other| other.extension == file_type.extension),
+				"{} is listed twice",
+				file_type.extension
+			);
+		}
+	}
+}
@@ -0,0 +1,269 @@
+//! The app language, detected from the OS unless the user picked one in the settings.
+//!
+//! The frontend translations are embedded so strings rendered by the backend, like native
+//! notifications, follow the same language. Changes are broadcast with [`LocaleChangedEvent`]
+//! so every window switches without a restart.
+
+use std::{
+	path::PathBuf,
+	sync::{OnceLock, RwLock},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use specta::Type;
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+use tracing::{error, info, warn};
+
+const STATE_FILE: &str = "locale.json";
+
+const FALLBACK: &str = "en";
+
+macro_rules! locales {
+	($($code:literal => $dir:literal),+ $(,)?) => {
+		/// Locale codes as used by the frontend, with the translations for each
+		const LOCALES: &[(&str, &str)] = &[
+			$(($code, include_str!(concat!("../../../../interface/locales/", $dir, "/common.json")))),+
+		];
+	};
+}
+
+locales! {
+	"ar" => "ar",
+	"be" => "be",
+	"cs" => "cs",
+	"de" => "de",
+	"en" => "en",
+	"es" => "es",
+	"fr" => "fr",
+	"it" => "it",
+	"ja" => "ja",
+	"nl" => "nl",
+	"ru" => "ru",
+	"tr" => "tr",
+	"uk" => "uk",
+	"zh_CN" => "zh-CN",
+	"zh_TW" => "zh-TW",
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type, tauri_specta::Event)]
+pub struct LocaleChangedEvent {
+	locale: String,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct LocaleSettings {
+	/// The locale reported by the OS, as is
+	pub system: Option<String>,
+	/// The locale picked by the user, the system one is followed when unset
+	pub preferred: Option<String>,
+	/// The locale the app is displayed in
+	pub current: String,
+	pub available: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PersistedState {
+	preferred: Option<String>,
+}
+
+struct Translations {
+	code: &'static str,
+	messages: Map<String, Value>,
+}
+
+impl Translations {
+	fn new(code: &'static str) -> Self {
+		Self {
+			code,
+			messages: parse(code),
+		}
+	}
+}
+
+pub struct Locale {
+	state_path: PathBuf,
+	state: RwLock<PersistedState>,
+	current: RwLock<Translations>,
+}
+
+impl Locale {
+	pub async fn load(app: &AppHandle) -> Self {
+		let state_path = app
+			.path()
+			.app_data_dir()
+			.unwrap_or_else(|_| PathBuf::from("./"))
+			.join(STATE_FILE);
+
+		let state = match tokio::fs::read(&state_path).await {
+			Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+				warn!(?e, "Corrupted locale settings, using the defaults;");
+				PersistedState::default()
+			}),
+			Err(_) => PersistedState::default(),
+		};
+
+		let current = Translations::new(effective(state.preferred.as_deref()));
+		info!(locale = current.code, "Using locale;");
+
+		Self {
+			state_path,
+			state: RwLock::new(state),
+			current: RwLock::new(current),
+		}
+	}
+
+	pub fn current(&self) -> &'static str {
+		self.current.read().expect("locale lock poisoned").code
+	}
+
+	/// Looks up `key` in the current locale, falling back to English, and fills in `{{name}}` placeholders
+	pub fn t(&self, key: &str, args: &[(&str, &str)]) -> String {
+		let current = self.current.read().expect("locale lock poisoned");
+		let message = current
+			.messages
+			.get(key)
+			.or_else(|| fallback().get(key))
+			.and_then(Value::as_str)
+			.unwrap_or(key);
+
+		args.iter().fold(message.to_string(), |message, (name, value)| {
+			message.replace(&format!("{{{{{name}}}}}"), value)
+		})
+	}
+
+	fn settings(&self) -> LocaleSettings {
+		LocaleSettings {
+			system: sys_locale::get_locale(),
+			preferred: self
+				.state
+				.read()
+				.expect("locale lock poisoned")
+				.preferred
+				.clone(),
+			current: self.current().to_string(),
+			available: LOCALES.iter().map(|(code, _)| code.to_string()).collect(),
+		}
+	}
+}
+
+fn parse(code: &str) -> Map<String, Value> {
+	LOCALES
+		.iter()
+		.find(|(c, _)| *c == code)
+		.and_then(|(_, json)| {
+			serde_json::from_str(json)
+				.map_err(|e| error!(?e, %code, "Failed to parse embedded translations;"))
+				.ok()
+		})
+		.unwrap_or_default()
+}
+
+fn fallback() -> &'static Map<String, Value> {
+	static FALLBACK_MESSAGES: OnceLock<Map<String, Value>> = OnceLock::new();
+	FALLBACK_MESSAGES.get_or_init(|| parse(FALLBACK))
+}
+
+/// The locale to display, from the user's pick or the OS locale
+fn effective(preferred: Option<&str>) -> &'static str {
+	preferred
+		.and_then(resolve)
+		.or_else(|| sys_locale::get_locale().as_deref().and_then(resolve))
+		.unwrap_or(FALLBACK)
+}
+
+/// Matches a BCP 47 tag (`de-AT`, `zh-Hant-TW`, `en_US.UTF-8`...) to one of our locales
+fn resolve(tag: &str) -> Option<&'static str> {
+	let tag = tag.split(['.', '@']).next().unwrap_or(tag).replace('-', "_");
+	let mut parts = tag.split('_');
+	let language = parts.next()?.to_ascii_lowercase();
+
+	if language == "zh" {
+		let traditional = parts.any(|part| matches!(part, "Hant" | "TW" | "HK" | "MO"));
+		return Some(if traditional { "zh_TW" } else { "zh_CN" });
+	}
+
+	LOCALES
+		.iter()
+		.map(|(code, _)| *code)
+		.find(|code| *code == language)
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn get_locale_settings(locale: tauri::State<'_, Locale>) -> Result<LocaleSettings, ()> {
+	Ok(locale.settings())
+}
+
+/// Switches the app language, `None` goes back to following the OS
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn set_locale(
+	app: AppHandle,
+	locale: tauri::State<'_, Locale>,
+	preferred: Option<String>,
+) -> Result<LocaleSettings, String> {
+	let preferred = match preferred {
+		Some(preferred) => Some(
+			resolve(&preferred)
+				.ok_or_else(|| format!("Unsupported locale: {preferred}"))?
+				.to_string(),
+		),
+		None => None,
+	};
+
+	let bytes = {
+		let mut state = locale.state.write().expect("locale lock poisoned");
+		state.preferred = preferred;
+		serde_json::to_vec(&*state).map_err(|e| e.to_string())?
+	};
+	// The app data dir doesn't exist until something is first written to it
+	if let Some(parent) = locale.state_path.parent() {
+		tokio::fs::create_dir_all(parent)
+			.await
+			.map_err(|e| e.to_string())?;
+	}
+	tokio::fs::write(&locale.state_path, bytes)
+		.await
+		.map_err(|e| e.to_string())?;
+
+	let code = effective(locale.state.read().expect("locale lock poisoned").preferred.as_deref());
+	if code != locale.current() {
+		info!(locale = code, "Switching locale;");
+		*locale.current.write().expect("locale lock poisoned") = Translations::new(code);
+
+		if let Err(e) = (LocaleChangedEvent {
+			locale: code.to_string(),
+		})
+		.emit(&app)
+		{
+			error!(?e, "Failed to emit locale changed event;");
+		}
+	}
+
+	Ok(locale.settings())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn resolves_os_locales() {
+		assert_eq!(resolve("de-AT"), Some("de"));
+		assert_eq!(resolve("en_US.UTF-8"), Some("en"));
+		assert_eq!(resolve("zh-Hant-TW"), Some("zh_TW"));
+		assert_eq!(resolve("zh-Hans-CN"), Some("zh_CN"));
+		assert_eq!(resolve("zh_TW"), Some("zh_TW"));
+		assert_eq!(resolve("pt-BR"), None);
+	}
+
+	#[test]
+	fn embedded_translations_parse() {
+		for (code, _) in LOCALES {
+			assert!(!parse(code).is_empty(), "{code} translations are empty");
+		}
+	}
+}
@@ -15,25 +15,46 @@ use sd_fda::DiskAccess;
 use serde::{Deserialize, Serialize};
 use specta_typescript::Typescript;
 use tauri::{async_runtime::block_on, webview::PlatformWebview, AppHandle, Manager, WindowEvent};
-use tauri::{Emitter, Listener};
+use tauri::Emitter;
+use tauri_plugin_deep_link::DeepLinkExt;
 use tauri_plugins::{sd_error_plugin, sd_server_plugin};
 use tauri_specta::{collect_events, Builder};
 use tokio::task::block_in_place;
 use tokio::time::sleep;
 use tracing::{debug, error};
 
+mod asset_protocol;
+mod autostart;
+mod clipboard;
+mod crash;
+mod deep_link;
 mod drag;
 mod file;
+mod file_associations;
+mod locale;
 mod menu;
+mod notifications;
+mod offline;
+mod os_search;
+mod permissions;
+mod system_info;
+mod taskbar;
 mod tauri_plugins;
+mod terminal;
 mod theme;
 mod updater;
 
 #[tauri::command(async)]
 #[specta::specta]
 async fn app_ready(app_handle: AppHandle) {
-	let window = app_handle.get_webview_window("main").unwrap();
-	window.show().unwrap();
+	if app_handle.state::<autostart::Autostart>().is_hidden() {
+		autostart::start_minimized(&app_handle);
+	} else {
+		let window = app_handle.get_webview_window("main").unwrap();
+		window.show().unwrap();
+	}
+
+	deep_link::flush_pending(&app_handle).await;
 }
 
 #[tauri::command(async)]
@@ -161,14 +182,10 @@ pub enum DragAndDropEvent {
 	Cancelled,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, specta::Type, tauri_specta::Event)]
-#[serde(rename_all = "camelCase")]
-pub struct DeepLinkEvent {
-	data: String,
-}
-
 #[tokio::main]
 async fn main() -> tauri::Result<()> {
+	crash::run_server_if_requested();
+
 	#[cfg(target_os = "linux")]
 	sd_desktop_linux::normalize_environment();
 
@@ -181,7 +198,25 @@ async fn main() -> tauri::Result<()> {
 			reload_webview,
 			set_menu_bar_item_state,
 			request_fda_macos,
+			autostart::get_autostart_settings,
+			autostart::set_autostart_enabled,
+			autostart::set_start_minimized,
+			locale::get_locale_settings,
+			locale::set_locale,
+			notifications::run_notification_action,
+			offline::get_offline_mode,
+			offline::set_offline_mode,
+			os_search::get_os_search_status,
+			os_search::set_os_search_enabled,
+			permissions::get_disk_permissions,
+			permissions::request_disk_permission,
 			open_trash_in_os_explorer,
+			clipboard::write_files_to_clipboard,
+			clipboard::read_files_from_clipboard,
+			crash::list_crash_reports,
+			crash::get_crash_report,
+			crash::delete_crash_report,
+			crash::submit_crash_report,
 			drag::start_drag,
 			drag::stop_drag,
 			file::open_file_paths,
@@ -191,11 +226,28 @@ async fn main() -> tauri::Result<()> {
 			file::open_file_path_with,
 			file::open_ephemeral_file_with,
 			file::reveal_items,
+			file::quick_look,
+			file_associations::get_file_associations,
+			file_associations::register_file_associations,
+			file_associations::unregister_file_associations,
+			file_associations::set_folder_handler,
+			system_info::system_info,
+			terminal::get_terminal_settings,
+			terminal::set_preferred_terminal,
+			terminal::open_terminal,
 			theme::lock_app_theme,
 			updater::check_for_update,
-			updater::install_update
+			updater::download_update,
+			updater::install_update,
+			updater::install_update_on_quit
 		])
-		.events(collect_events![DragAndDropEvent]);
+		.events(collect_events![
+			DragAndDropEvent,
+			deep_link::DeepLinkEvent,
+			locale::LocaleChangedEvent,
+			offline::OfflineModeChangedEvent,
+			permissions::DiskPermissionChangedEvent
+		]);
 
 	#[cfg(debug_assertions)]
 	builder
@@ -211,19 +263,25 @@ async fn main() -> tauri::Result<()> {
 		.invoke_handler(builder.invoke_handler())
 		.plugin(tauri_plugin_deep_link::init())
 		.plugin(tauri_plugin_cors_fetch::init())
+		.register_asynchronous_uri_scheme_protocol(asset_protocol::SCHEME, asset_protocol::handler)
 		.setup(move |app| {
 			// We need a the app handle to determine the data directory now.
 			// This means all the setup code has to be within `setup`, however it doesn't support async so we `block_on`.
 			let handle = app.handle().clone();
-			app.listen("deep-link://new-url", move |event| {
-				let deep_link_event = DeepLinkEvent {
-					data: event.payload().to_string(),
-				};
-				println!("Deep link event={:?}", deep_link_event);
-
-				handle.emit("deeplink", deep_link_event).unwrap();
+			app.deep_link().on_open_url(move |event| {
+				let handle = handle.clone();
+				let urls = event.urls();
+				tauri::async_runtime::spawn(async move {
+					deep_link::handle_urls(&handle, urls).await;
+				});
 			});
 
+			// On Linux and Windows the scheme is registered at runtime instead of by the installer
+			#[cfg(any(target_os = "linux", target_os = "windows"))]
+			if let Err(e) = app.deep_link().register(deep_link::SCHEME) {
+				error!(?e, "Failed to register deep link scheme;");
+			}
+
 			// #[cfg(debug_assertions)] // only include this code on debug builds
 			// {
 			//   let window = app.get_webview_window("main").unwrap();
@@ -244,6 +302,12 @@ async fn main() -> tauri::Result<()> {
 					#[cfg(debug_assertions)]
 					let data_dir = data_dir.join("dev");
 
+					app.manage(crash::install(&data_dir));
+					app.manage(terminal::Terminals::load(app.handle()).await);
+					app.manage(autostart::Autostart::load(app.handle()).await);
+					app.manage(file_associations::FileAssociationsState::load(app.handle()).await);
+					app.manage(locale::Locale::load(app.handle()).await);
+
 					// The `_guard` must be assigned to variable for flushing remaining logs on main exit through Drop
 					let (_guard, result) = match Node::init_logger(&data_dir) {
 						Ok(guard) => (Some(guard), Node::new(data_dir).await),
@@ -268,6 +332,19 @@ async fn main() -> tauri::Result<()> {
 					}))?;
 					handle.plugin(sd_server_plugin(node.clone()).await.unwrap())?; // TODO: Handle `unwrap`
 					handle.manage(node.clone());
+					permissions::spawn_watcher(handle.clone());
+					taskbar::spawn(handle.clone(), node.clone());
+					notifications::spawn(handle.clone(), node.clone());
+					offline::spawn(handle.clone(), node.clone());
+					os_search::spawn(handle.clone(), node.clone()).await;
+
+					// Links that launched the app are only routable once the core is up
+					if let Ok(Some(urls)) = handle.deep_link().get_current() {
+						deep_link::handle_urls(handle, urls).await;
+					}
+
+					// Same for files and folders the OS asked us to open
+					deep_link::handle_urls(handle, file_associations::launch_urls()).await;
 
 					handle.windows().iter().for_each(|(_, window)| {
 						if should_clear_local_storage {
@@ -281,7 +358,8 @@ async fn main() -> tauri::Result<()> {
 							let window = window.clone();
 							async move {
 								sleep(Duration::from_secs(3)).await;
-								if !window.is_visible().unwrap_or(true) {
+								let is_hidden = window.state::<autostart::Autostart>().is_hidden();
+								if !is_hidden && !window.is_visible().unwrap_or(true) {
 									// This happens if the JS bundle crashes and hence doesn't send ready event.
 									println!(
 										"Window did not emit `app_ready` event fast enough. Showing window..."
@@ -329,8 +407,26 @@ async fn main() -> tauri::Result<()> {
 		.plugin(updater::plugin())
 		.manage(updater::State::default())
 		.manage(drag::DragState::default())
+		.manage(deep_link::DeepLinkState::default())
 		.build(tauri::generate_context!())?
-		.run(|_, _| {});
+		.run(|_app, _event| {
+			// Files and folders opened with us while running, or which launched us
+			#[cfg(target_os = "macos")]
+			if let tauri::RunEvent::Opened { urls } = _event {
+				let app = _app.clone();
+				tauri::async_runtime::spawn(async move {
+					// Opening a file can be what launched us, give the core some time to start
+					for _ in 0..300 {
+						if app.try_state::<Arc<Node>>().is_some() {
+							break;
+						}
+						sleep(Duration::from_millis(100)).await;
+					}
+
+					deep_link::handle_urls(&app, urls).await;
+				});
+			}
+		});
 
 	Ok(())
 }
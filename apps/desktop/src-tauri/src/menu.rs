@@ -3,7 +3,9 @@ use std::str::FromStr;
 use serde::Deserialize;
 use specta::Type;
 use tauri::{
-	menu::{Menu, MenuItemKind},
+	menu::{
+		CheckMenuItemBuilder, Menu, MenuBuilder, MenuItemBuilder, MenuItemKind, SubmenuBuilder,
+	},
 	AppHandle, Emitter, Manager, Wry,
 };
 use tracing::error;
@@ -31,6 +33,9 @@ pub enum MenuEvent {
 	Paste,
 	Duplicate,
 	SelectAll,
+	QuickLook,
+	OpenTerminal,
+	ToggleOfflineMode,
 }
 
 /// Menu items which require a library to be open to use.
@@ -48,8 +53,13 @@ const LIBRARY_LOCKED_MENU_IDS: &[MenuEvent] = &[
 	MenuEvent::NewDirectory,
 	MenuEvent::NewLibrary,
 	MenuEvent::AddLocation,
+	MenuEvent::QuickLook,
+	MenuEvent::OpenTerminal,
 ];
 
+const VIEW_MENU_ID: &str = "View";
+const NETWORK_MENU_ID: &str = "Network";
+
 pub fn setup_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
 	app.on_menu_event(move |app, event| {
 		if let Ok(event) = MenuEvent::from_str(&event.id().0) {
@@ -59,7 +69,23 @@ pub fn setup_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
 		}
 	});
 
-	Menu::new(app)
+	// No accelerator, Space is handled by the explorer so it still types in inputs
+	let quick_look = MenuItemBuilder::with_id(MenuEvent::QuickLook.as_ref(), "Quick Look")
+		.enabled(false)
+		.build(app)?;
+	let view = SubmenuBuilder::with_id(app, VIEW_MENU_ID, "View")
+		.item(&quick_look)
+		.build()?;
+
+	// Checked once the core is up and says it's offline, see `offline::spawn`
+	let offline_mode =
+		CheckMenuItemBuilder::with_id(MenuEvent::ToggleOfflineMode.as_ref(), "Offline Mode")
+			.build(app)?;
+	let network = SubmenuBuilder::with_id(app, NETWORK_MENU_ID, "Network")
+		.item(&offline_mode)
+		.build()?;
+
+	MenuBuilder::new(app).items(&[&view, &network]).build()
 }
 
 pub fn handle_menu_event(event: MenuEvent, app: &AppHandle) {
@@ -85,6 +111,9 @@ pub fn handle_menu_event(event: MenuEvent, app: &AppHandle) {
 		MenuEvent::Paste => webview.emit("keybind", "paste").unwrap(),
 		MenuEvent::Duplicate => webview.emit("keybind", "duplicate").unwrap(),
 		MenuEvent::SelectAll => webview.emit("keybind", "select_all").unwrap(),
+		MenuEvent::QuickLook => webview.emit("keybind", "quick_look").unwrap(),
+		MenuEvent::OpenTerminal => webview.emit("keybind", "open_terminal").unwrap(),
+		MenuEvent::ToggleOfflineMode => crate::offline::toggle(app),
 		MenuEvent::ToggleDeveloperTools =>
 		{
 			#[cfg(feature = "devtools")]
@@ -118,8 +147,25 @@ pub fn refresh_menu_bar(app: &AppHandle, enabled: bool) {
 	}
 }
 
+/// Reflects offline mode in the menu, whether it was toggled from there or not
+pub fn set_offline_checked(app: &AppHandle, offline: bool) {
+	let Some(menu) = app.menu() else {
+		return;
+	};
+
+	let event = MenuEvent::ToggleOfflineMode;
+	let Some(MenuItemKind::Check(item)) = get_item(&menu, event) else {
+		error!("Unable to get menu item: {event:?}");
+		return;
+	};
+
+	if let Err(e) = item.set_checked(offline) {
+		error!("Error setting menu item state: {e:#?}");
+	}
+}
+
 pub fn set_enabled(menu: &Menu<Wry>, event: MenuEvent, enabled: bool) {
-	let result = match menu.get(event.as_ref()) {
+	let result = match get_item(menu, event) {
 		Some(MenuItemKind::MenuItem(i)) => i.set_enabled(enabled),
 		Some(MenuItemKind::Submenu(i)) => i.set_enabled(enabled),
 		Some(MenuItemKind::Predefined(_)) => return,
@@ -135,3 +181,15 @@ pub fn set_enabled(menu: &Menu<Wry>, event: MenuEvent, enabled: bool) {
 		error!("Error setting menu item state: {e:#?}");
 	}
 }
+
+/// Looks the item up in the menu bar and the menus in it
+fn get_item(menu: &Menu<Wry>, event: MenuEvent) -> Option<MenuItemKind<Wry>> {
+	menu.get(event.as_ref()).or_else(|| {
+		[VIEW_MENU_ID, NETWORK_MENU_ID]
+			.into_iter()
+			.find_map(|id| match menu.get(id) {
+				Some(MenuItemKind::Submenu(submenu)) => submenu.get(event.as_ref()),
+				_ => None,
+			})
+	})
+}
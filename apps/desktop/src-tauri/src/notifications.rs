@@ -0,0 +1,478 @@
+//! Native notifications for things worth knowing about while the app is in the background:
+//! jobs finishing or failing, external volumes being attached, volumes running out of space or
+//! forecast to, and failing drives.
+//!
+//! Action buttons are only supported by freedesktop notification servers, on macOS and Windows
+//! notifications are informational and clicking them just brings the app to the front.
+
+use sd_core::{
+	api::{
+		volumes::{
+			HealthStatus, LowSpaceThreshold, MountType, Volume, VolumeEvent, VolumeFingerprint,
+		},
+		CoreEvent, JobFinishedEvent,
+	},
+	Node,
+};
+use sd_core_heavy_lifting::job_system::report::Status;
+
+use std::{
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::{async_runtime::spawn_blocking, AppHandle, Manager};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, error, warn};
+use uuid::Uuid;
+
+use crate::locale::Locale;
+
+/// Volumes are reported as added when the volume manager does its first scan,
+/// these aren't newly attached so we don't prompt for them.
+const STARTUP_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// Track actions are only offered for the first few libraries, notification servers don't cope with more buttons
+const MAX_TRACK_ACTIONS: usize = 3;
+
+/// Things a notification action can do, also exposed as a command so in-app notifications can reuse them
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(tag = "type")]
+pub enum NotificationAction {
+	ShowApp,
+	TrackVolume {
+		fingerprint: VolumeFingerprint,
+		library_id: Uuid,
+	},
+}
+
+struct NativeNotification {
+	title: String,
+	body: String,
+	actions: Vec<(String, NotificationAction)>,
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn run_notification_action(
+	app: AppHandle,
+	node: tauri::State<'_, Arc<Node>>,
+	action: NotificationAction,
+) -> Result<(), String> {
+	run_action(&app, &node, action).await
+}
+
+async fn run_action(app: &AppHandle, node: &Node, action: NotificationAction) -> Result<(), String> {
+	match action {
+		NotificationAction::ShowApp => {
+			if let Some(window) = app.get_webview_window("main") {
+				window.unminimize().ok();
+				window.show().ok();
+				window.set_focus().ok();
+			}
+			Ok(())
+		}
+		NotificationAction::TrackVolume {
+			fingerprint,
+			library_id,
+		} => {
+			let library = node
+				.libraries
+				.get_library(&library_id)
+				.await
+				.ok_or_else(|| format!("Library not found: {library_id}"))?;
+
+			node.volumes
+				.track_volume(fingerprint, library)
+				.await
+				.map_err(|e| e.to_string())
+		}
+	}
+}
+
+pub fn spawn(app: AppHandle, node: Arc<Node>) {
+	#[cfg(target_os = "macos")]
+	if let Err(e) = notify_rust::set_application(&app.config().identifier) {
+		warn!(?e, "Failed to set the notification application;");
+	}
+
+	#[cfg(target_os = "linux")]
+	app.manage(actions::Listener::start(app.clone()));
+
+	tokio::spawn({
+		let app = app.clone();
+		let node = node.clone();
+		async move {
+			let mut rx = node.event_bus.0.subscribe();
+			let locale = app.state::<Locale>();
+			loop {
+				match rx.recv().await {
+					Ok(CoreEvent::JobFinished(event)) => {
+						if let Some(notification) = job_notification(&locale, &event) {
+							if !is_app_focused(&app) {
+								show(&app, notification);
+							}
+						}
+					}
+					Ok(_) => {}
+					Err(RecvError::Lagged(skipped)) => {
+						debug!(%skipped, "Job notifications lagged behind the event bus;");
+					}
+					Err(RecvError::Closed) => break,
+				}
+			}
+		}
+	});
+
+	tokio::spawn(async move {
+		let started_at = Instant::now();
+		let mut rx = node.volumes.subscribe();
+		let locale = app.state::<Locale>();
+
+		loop {
+			let event = match rx.recv().await {
+				Ok(event) => event,
+				Err(RecvError::Lagged(skipped)) => {
+					debug!(%skipped, "Volume notifications lagged behind volume events;");
+					continue;
+				}
+				Err(RecvError::Closed) => break,
+			};
+
+			match event {
+				VolumeEvent::VolumeAdded(volume) => {
+					if started_at.elapsed() > STARTUP_GRACE_PERIOD
+						&& volume.mount_type == MountType::External
+						&& !is_app_focused(&app)
+					{
+						show(&app, volume_added_notification(&locale, &node, &volume).await);
+					}
+				}
+				// The volume manager warns once each time the volume drops below the threshold
+				VolumeEvent::LowSpace { volume, threshold } => {
+					show(&app, low_space_notification(&locale, &volume, threshold))
+				}
+				VolumeEvent::VolumeFillingUp {
+					name,
+					days_until_full,
+					..
+				} => show(
+					&app,
+					filling_up_notification(&locale, &name, days_until_full),
+				),
+				VolumeEvent::VolumeHealthChanged { name, health, .. }
+					if health.status == HealthStatus::Failing =>
+				{
+					show(&app, failing_notification(&locale, &name))
+				}
+				VolumeEvent::VolumeDegraded { volume, .. } => {
+					show(&app, degraded_notification(&locale, &volume.name))
+				}
+				_ => {}
+			}
+		}
+	});
+}
+
+fn is_app_focused(app: &AppHandle) -> bool {
+	app.get_webview_window("main")
+		.and_then(|window| window.is_focused().ok())
+		.unwrap_or(false)
+}
+
+fn job_notification(locale: &Locale, event: &JobFinishedEvent) -> Option<NativeNotification> {
+	let name = event
+		.name
+		.map(|name| humanize(&name.to_string()))
+		.unwrap_or_else(|| locale.t("notification_a_job", &[]));
+	let name = name.as_str();
+
+	let (title, body) = match event.status {
+		Status::Completed => (
+			locale.t("notification_job_finished", &[("name", name)]),
+			String::new(),
+		),
+		Status::CompletedWithErrors => (
+			locale.t("notification_job_finished_with_errors", &[("name", name)]),
+			locale.t(
+				"notification_job_items_failed",
+				&[("count", &event.non_critical_errors_count.to_string())],
+			),
+		),
+		Status::Failed => (
+			locale.t("notification_job_failed", &[("name", name)]),
+			event.error.clone().unwrap_or_default(),
+		),
+		// Canceled and paused jobs were stopped by the user, they already know
+		_ => return None,
+	};
+
+	Some(NativeNotification {
+		title,
+		body,
+		actions: vec![(locale.t("show", &[]), NotificationAction::ShowApp)],
+	})
+}
+
+async fn volume_added_notification(
+	locale: &Locale,
+	node: &Node,
+	volume: &Volume,
+) -> NativeNotification {
+	let mut actions = Vec::new();
+
+	if let Some(fingerprint) = &volume.fingerprint {
+		for library in node
+			.libraries
+			.get_all()
+			.await
+			.into_iter()
+			.take(MAX_TRACK_ACTIONS)
+		{
+			actions.push((
+				locale.t(
+					"notification_track_in_library",
+					&[("library", &*library.config().await.name)],
+				),
+				NotificationAction::TrackVolume {
+					fingerprint: fingerprint.clone(),
+					library_id: library.id,
+				},
+			));
+		}
+	}
+
+	NativeNotification {
+		title: locale.t("notification_volume_connected", &[("volume", &volume.name)]),
+		body: locale.t("notification_volume_connected_description", &[]),
+		actions,
+	}
+}
+
+fn filling_up_notification(
+	locale: &Locale,
+	name: &str,
+	days_until_full: u32,
+) -> NativeNotification {
+	NativeNotification {
+		title: locale.t("notification_volume_filling_up", &[("volume", name)]),
+		body: locale.t(
+			"notification_volume_filling_up_description",
+			&[("days", &days_until_full.to_string())],
+		),
+		actions: vec![(locale.t("show", &[]), NotificationAction::ShowApp)],
+	}
+}
+
+fn failing_notification(locale: &Locale, name: &str) -> NativeNotification {
+	NativeNotification {
+		title: locale.t("notification_volume_failing", &[("volume", name)]),
+		body: locale.t("notification_volume_failing_description", &[]),
+		actions: vec![(locale.t("show", &[]), NotificationAction::ShowApp)],
+	}
+}
+
+fn degraded_notification(locale: &Locale, name: &str) -> NativeNotification {
+	NativeNotification {
+		title: locale.t("notification_volume_degraded", &[("volume", name)]),
+		body: locale.t("notification_volume_degraded_description", &[]),
+		actions: vec![(locale.t("show", &[]), NotificationAction::ShowApp)],
+	}
+}
+
+fn low_space_notification(
+	locale: &Locale,
+	volume: &Volume,
+	threshold: LowSpaceThreshold,
+) -> NativeNotification {
+	let body = match threshold {
+		LowSpaceThreshold::Percentage(_) => locale.t(
+			"notification_volume_almost_full_description",
+			&[(
+				"percent",
+				&volume
+					.total_bytes_available
+					.percent_of(volume.total_bytes_capacity)
+					.to_string(),
+			)],
+		),
+		LowSpaceThreshold::Bytes(_) => locale.t(
+			"notification_volume_almost_full_bytes_description",
+			&[("available", &volume.total_bytes_available.to_string())],
+		),
+	};
+
+	NativeNotification {
+		title: locale.t(
+			"notification_volume_almost_full",
+			&[("volume", &volume.name)],
+		),
+		body,
+		actions: vec![(locale.t("show", &[]), NotificationAction::ShowApp)],
+	}
+}
+
+/// `file_identifier` -> `File identifier`
+fn humanize(name: &str) -> String {
+	let name = name.replace('_', " ");
+	let mut chars = name.chars();
+	chars
+		.next()
+		.map(|first| first.to_uppercase().chain(chars).collect())
+		.unwrap_or_default()
+}
+
+fn show(app: &AppHandle, notification: NativeNotification) {
+	let app = app.clone();
+	spawn_blocking(move || {
+		let NativeNotification {
+			title,
+			body,
+			actions,
+		} = notification;
+
+		let mut native = notify_rust::Notification::new();
+		native
+			.appname(app.config().product_name.as_deref().unwrap_or("Spacedrive"))
+			.summary(&title)
+			.body(&body);
+
+		#[cfg(target_os = "linux")]
+		{
+			for (i, (label, _)) in actions.iter().enumerate() {
+				native.action(&i.to_string(), label);
+			}
+
+			match native.show() {
+				Ok(handle) => app.state::<actions::Listener>().track(
+					handle.id(),
+					actions.into_iter().map(|(_, action)| action).collect(),
+				),
+				Err(e) => error!(?e, "Failed to show notification;"),
+			}
+		}
+
+		#[cfg(not(target_os = "linux"))]
+		{
+			let _ = (&app, &actions);
+			if let Err(e) = native.show() {
+				error!(?e, "Failed to show notification;");
+			}
+		}
+	});
+}
+
+#[cfg(target_os = "linux")]
+mod actions {
+	use std::{
+		collections::HashMap,
+		sync::{Arc, Mutex},
+		thread,
+		time::Duration,
+	};
+
+	use dbus::{blocking::LocalConnection, message::MatchRule};
+	use tauri::{AppHandle, Manager};
+	use tracing::error;
+
+	use super::{run_action, Node, NotificationAction};
+
+	const INTERFACE: &str = "org.freedesktop.Notifications";
+
+	type Pending = Arc<Mutex<HashMap<u32, Vec<NotificationAction>>>>;
+
+	/// Listens for the actions of all our notifications on a single connection, rather than
+	/// blocking a thread per notification until it is dismissed, which may never happen.
+	pub struct Listener {
+		pending: Pending,
+	}
+
+	impl Listener {
+		pub fn start(app: AppHandle) -> Self {
+			let pending = Pending::default();
+
+			let spawned = thread::Builder::new()
+				.name("notification-actions".into())
+				.spawn({
+					let pending = Arc::clone(&pending);
+					move || {
+						if let Err(e) = listen(app, pending) {
+							error!(?e, "Stopped listening for notification actions;");
+						}
+					}
+				});
+
+			if let Err(e) = spawned {
+				error!(?e, "Failed to spawn the notification actions listener;");
+			}
+
+			Self { pending }
+		}
+
+		/// Remembers the actions of a shown notification until it is closed
+		pub fn track(&self, id: u32, actions: Vec<NotificationAction>) {
+			if !actions.is_empty() {
+				self.pending
+					.lock()
+					.expect("notification actions lock poisoned")
+					.insert(id, actions);
+			}
+		}
+	}
+
+	fn listen(app: AppHandle, pending: Pending) -> Result<(), dbus::Error> {
+		let connection = LocalConnection::new_session()?;
+
+		connection.add_match(MatchRule::new_signal(INTERFACE, "ActionInvoked"), {
+			let pending = Arc::clone(&pending);
+			move |(id, key): (u32, String), _: &LocalConnection, _| {
+				let action = pending
+					.lock()
+					.expect("notification actions lock poisoned")
+					.remove(&id)
+					.zip(key.parse::<usize>().ok())
+					.and_then(|(actions, i)| actions.into_iter().nth(i));
+
+				if let Some(action) = action {
+					let app = app.clone();
+					tauri::async_runtime::spawn(async move {
+						let node = app.state::<Arc<Node>>();
+						if let Err(e) = run_action(&app, &node, action).await {
+							error!(?e, "Failed to run notification action;");
+						}
+					});
+				}
+
+				true
+			}
+		})?;
+
+		connection.add_match(
+			MatchRule::new_signal(INTERFACE, "NotificationClosed"),
+			move |(id, _reason): (u32, u32), _: &LocalConnection, _| {
+				pending
+					.lock()
+					.expect("notification actions lock poisoned")
+					.remove(&id);
+				true
+			},
+		)?;
+
+		loop {
+			connection.process(Duration::from_secs(60))?;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn humanizes_job_names() {
+		assert_eq!(humanize("file_identifier"), "File identifier");
+		assert_eq!(humanize(""), "");
+	}
+}
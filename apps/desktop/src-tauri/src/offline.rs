@@ -0,0 +1,73 @@
+use sd_core::{api::CoreEvent, Node};
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, error};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type, tauri_specta::Event)]
+pub struct OfflineModeChangedEvent {
+	offline: bool,
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn get_offline_mode(node: tauri::State<'_, Arc<Node>>) -> Result<bool, ()> {
+	Ok(node.offline.is_offline())
+}
+
+/// Suspends or resumes p2p, cloud services and update checks
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn set_offline_mode(
+	node: tauri::State<'_, Arc<Node>>,
+	offline: bool,
+) -> Result<(), String> {
+	node.set_offline(offline).await.map_err(|e| e.to_string())
+}
+
+/// Flips offline mode from the menu, where there is no webview round trip to wait on
+pub fn toggle(app: &AppHandle) {
+	let Some(node) = app.try_state::<Arc<Node>>() else {
+		return;
+	};
+
+	let node = Arc::clone(&node);
+	tauri::async_runtime::spawn(async move {
+		if let Err(e) = node.set_offline(!node.offline.is_offline()).await {
+			error!(?e, "Failed to toggle offline mode;");
+		}
+	});
+}
+
+pub fn is_offline(app: &AppHandle) -> bool {
+	app.try_state::<Arc<Node>>()
+		.is_some_and(|node| node.offline.is_offline())
+}
+
+/// Forwards offline mode changes from the core, whichever side made them
+pub fn spawn(app: AppHandle, node: Arc<Node>) {
+	tokio::spawn(async move {
+		let mut rx = node.event_bus.0.subscribe();
+		crate::menu::set_offline_checked(&app, node.offline.is_offline());
+		loop {
+			match rx.recv().await {
+				Ok(CoreEvent::OfflineModeChanged { offline }) => {
+					crate::menu::set_offline_checked(&app, offline);
+					if let Err(e) = (OfflineModeChangedEvent { offline }).emit(&app) {
+						error!(?e, "Failed to emit offline mode event;");
+					}
+				}
+				Ok(_) => {}
+				Err(RecvError::Lagged(skipped)) => {
+					debug!(%skipped, "Offline mode events lagged behind the event bus;");
+				}
+				Err(RecvError::Closed) => break,
+			}
+		}
+	});
+}
@@ -0,0 +1,779 @@
+//! Publishes indexed file paths to the system search (Spotlight on macOS, Windows Search on Windows)
+//! so they can be found outside of the app, each result deep linking back to the file in its library.
+//!
+//! Exporting is opt-in. Once enabled, every library is exported in full and then kept in sync
+//! incrementally from the paths indexed or modified since the last sync. Full resyncs happen daily
+//! to catch deleted paths, as the index doesn't keep track of them.
+
+use sd_core::{api::CoreEvent, library::Library, Node};
+use sd_prisma::prisma::{file_path, SortOrder};
+
+use std::{
+	collections::HashMap,
+	path::PathBuf,
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use prisma_client_rust::or;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::{AppHandle, Manager};
+use tokio::{
+	sync::{broadcast::error::RecvError, Mutex, Notify},
+	time::{interval, sleep},
+};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::deep_link::SCHEME;
+
+const STATE_FILE: &str = "os_search.json";
+
+const SYNC_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Newly identified files arrive in bursts, wait for them to settle before syncing
+const SYNC_DEBOUNCE: Duration = Duration::from_secs(30);
+
+const FULL_RESYNC_INTERVAL: chrono::Duration = chrono::Duration::days(1);
+
+const BATCH_SIZE: i64 = 500;
+
+/// A file path as published to the system search
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(dead_code)] // Not every platform uses every field
+pub struct SearchItem {
+	/// Stable across syncs, used to replace the item when it changes
+	pub id: String,
+	pub title: String,
+	pub description: String,
+	pub url: String,
+	pub is_dir: bool,
+	pub date_modified: Option<DateTime<FixedOffset>>,
+}
+
+impl SearchItem {
+	fn new(library_id: Uuid, path: file_path_for_search::Data) -> Option<Self> {
+		let name = path.name?;
+		let title = match path.extension.as_deref() {
+			Some(extension) if !extension.is_empty() => format!("{name}.{extension}"),
+			_ => name,
+		};
+
+		Some(Self {
+			id: format!("{library_id}/{}", path.id),
+			title,
+			description: path.materialized_path.unwrap_or_else(|| "/".into()),
+			url: format!("{SCHEME}://library/{library_id}/file/{}", path.id),
+			is_dir: path.is_dir.unwrap_or(false),
+			date_modified: path.date_modified,
+		})
+	}
+}
+
+file_path::select!(file_path_for_search {
+	id
+	name
+	extension
+	materialized_path
+	is_dir
+	date_modified
+});
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct LibrarySyncState {
+	last_sync: Option<DateTime<Utc>>,
+	last_full_sync: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PersistedState {
+	enabled: bool,
+	libraries: HashMap<Uuid, LibrarySyncState>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct OsSearchStatus {
+	/// Whether this platform has a system search we can publish to
+	pub supported: bool,
+	pub enabled: bool,
+	pub last_sync: Option<DateTime<Utc>>,
+}
+
+pub struct OsSearch {
+	state_path: PathBuf,
+	state: Mutex<PersistedState>,
+	exporter: Option<platform::Exporter>,
+	sync_requested: Notify,
+}
+
+impl OsSearch {
+	async fn load(app: &AppHandle) -> Self {
+		let state_path = app
+			.path()
+			.app_data_dir()
+			.unwrap_or_else(|_| PathBuf::from("./"))
+			.join(STATE_FILE);
+
+		let state = match tokio::fs::read(&state_path).await {
+			Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+				warn!(?e, "Corrupted system search state, starting over;");
+				PersistedState::default()
+			}),
+			Err(_) => PersistedState::default(),
+		};
+
+		let exporter = match platform::Exporter::new(app) {
+			Ok(exporter) => Some(exporter),
+			Err(e) => {
+				debug!(%e, "System search integration unavailable;");
+				None
+			}
+		};
+
+		Self {
+			state_path,
+			state: Mutex::new(state),
+			exporter,
+			sync_requested: Notify::new(),
+		}
+	}
+
+	async fn save(&self, state: &PersistedState) {
+		let result = match serde_json::to_vec(state) {
+			Ok(bytes) => tokio::fs::write(&self.state_path, bytes).await,
+			Err(e) => Err(e.into()),
+		};
+
+		if let Err(e) = result {
+			error!(?e, "Failed to save system search state;");
+		}
+	}
+
+	async fn sync_all(&self, node: &Node) {
+		let Some(exporter) = &self.exporter else {
+			return;
+		};
+
+		if !self.state.lock().await.enabled {
+			return;
+		}
+
+		let libraries = node.libraries.get_all().await;
+
+		// Libraries which were deleted since the last sync
+		let removed = {
+			let mut state = self.state.lock().await;
+			let removed = state
+				.libraries
+				.keys()
+				.filter(|id| !libraries.iter().any(|library| library.id == **id))
+				.copied()
+				.collect::<Vec<_>>();
+			for id in &removed {
+				state.libraries.remove(id);
+			}
+			removed
+		};
+
+		for library_id in removed {
+			if let Err(e) = exporter.remove_library(library_id) {
+				error!(%library_id, %e, "Failed to remove library from system search;");
+			}
+		}
+
+		for library in libraries {
+			let previous = self
+				.state
+				.lock()
+				.await
+				.libraries
+				.get(&library.id)
+				.cloned()
+				.unwrap_or_default();
+
+			match sync_library(exporter, &library, &previous).await {
+				Ok(synced) => {
+					let mut state = self.state.lock().await;
+					// Disabled while we were syncing, `set_os_search_enabled` already cleaned up
+					if !state.enabled {
+						return;
+					}
+					state.libraries.insert(library.id, synced);
+					self.save(&state).await;
+				}
+				Err(e) => {
+					error!(library_id = %library.id, %e, "Failed to sync library to system search;")
+				}
+			}
+		}
+	}
+}
+
+async fn sync_library(
+	exporter: &platform::Exporter,
+	library: &Library,
+	previous: &LibrarySyncState,
+) -> Result<LibrarySyncState, String> {
+	let started_at = Utc::now();
+
+	let full = previous
+		.last_full_sync
+		.map_or(true, |last| started_at - last > FULL_RESYNC_INTERVAL);
+
+	let since = if full {
+		exporter.remove_library(library.id)?;
+		None
+	} else {
+		previous.last_sync
+	};
+
+	let mut cursor = 0;
+	let mut exported = 0;
+	loop {
+		let mut filters = vec![
+			file_path::id::gt(cursor),
+			file_path::location_id::not(None),
+			or![
+				file_path::hidden::equals(None),
+				file_path::hidden::equals(Some(false)),
+			],
+		];
+
+		if let Some(since) = since {
+			filters.push(or![
+				file_path::date_indexed::gt(since.into()),
+				file_path::date_modified::gt(since.into()),
+			]);
+		}
+
+		let paths = library
+			.db
+			.file_path()
+			.find_many(filters)
+			.order_by(file_path::id::order(SortOrder::Asc))
+			.take(BATCH_SIZE)
+			.select(file_path_for_search::select())
+			.exec()
+			.await
+			.map_err(|e| e.to_string())?;
+
+		let Some(last) = paths.last() else {
+			break;
+		};
+		cursor = last.id;
+
+		let items = paths
+			.into_iter()
+			.filter_map(|path| SearchItem::new(library.id, path))
+			.collect::<Vec<_>>();
+
+		exported += items.len();
+		exporter.upsert(library.id, &items)?;
+	}
+
+	info!(library_id = %library.id, exported, full, "Synced library to system search;");
+
+	Ok(LibrarySyncState {
+		last_sync: Some(started_at),
+		last_full_sync: if full {
+			Some(started_at)
+		} else {
+			previous.last_full_sync
+		},
+	})
+}
+
+pub async fn spawn(app: AppHandle, node: Arc<Node>) {
+	let os_search = Arc::new(OsSearch::load(&app).await);
+	app.manage(os_search.clone());
+
+	if os_search.exporter.is_none() {
+		return;
+	}
+
+	tokio::spawn({
+		let os_search = os_search.clone();
+		let node = node.clone();
+		async move {
+			let mut sync_interval = interval(SYNC_INTERVAL);
+			loop {
+				tokio::select! {
+					_ = sync_interval.tick() => {}
+					_ = os_search.sync_requested.notified() => {}
+				}
+				os_search.sync_all(&node).await;
+			}
+		}
+	});
+
+	tokio::spawn(async move {
+		let mut rx = node.event_bus.0.subscribe();
+		let mut pending_since: Option<Instant> = None;
+		loop {
+			tokio::select! {
+				event = rx.recv() => match event {
+					Ok(CoreEvent::NewIdentifiedObjects { .. }) => {
+						pending_since.get_or_insert_with(Instant::now);
+					}
+					Ok(_) => {}
+					Err(RecvError::Lagged(_)) => {
+						pending_since.get_or_insert_with(Instant::now);
+					}
+					Err(RecvError::Closed) => break,
+				},
+				_ = sleep(SYNC_DEBOUNCE), if pending_since.is_some() => {
+					pending_since = None;
+					os_search.sync_requested.notify_one();
+				}
+			}
+		}
+	});
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn get_os_search_status(
+	os_search: tauri::State<'_, Arc<OsSearch>>,
+) -> Result<OsSearchStatus, String> {
+	let state = os_search.state.lock().await;
+	Ok(OsSearchStatus {
+		supported: os_search.exporter.is_some(),
+		enabled: state.enabled,
+		last_sync: state
+			.libraries
+			.values()
+			.filter_map(|library| library.last_sync)
+			.max(),
+	})
+}
+
+/// Enables or disables publishing indexed items, disabling removes everything we published
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn set_os_search_enabled(
+	os_search: tauri::State<'_, Arc<OsSearch>>,
+	enabled: bool,
+) -> Result<(), String> {
+	let Some(exporter) = &os_search.exporter else {
+		return Err("System search integration is not supported on this platform".into());
+	};
+
+	let mut state = os_search.state.lock().await;
+	if state.enabled == enabled {
+		return Ok(());
+	}
+
+	state.enabled = enabled;
+
+	if !enabled {
+		for library_id in std::mem::take(&mut state.libraries).into_keys() {
+			exporter.remove_library(library_id)?;
+		}
+	}
+
+	os_search.save(&state).await;
+	drop(state);
+
+	if enabled {
+		os_search.sync_requested.notify_one();
+	}
+
+	Ok(())
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+	//! Items are published with Core Spotlight, one domain per library. Their identifier is the
+	//! deep link to the file, which opening a result hands back to us.
+
+	use super::SearchItem;
+
+	use std::{ffi::CString, sync::OnceLock};
+
+	use objc2::{
+		class,
+		encode::Encode,
+		ffi, msg_send,
+		rc::Retained,
+		runtime::{AnyClass, AnyObject, Bool, Sel},
+		sel, AllocAnyThread,
+	};
+	use objc2_core_spotlight::{
+		CSSearchableIndex, CSSearchableItem, CSSearchableItemActionType,
+		CSSearchableItemActivityIdentifier, CSSearchableItemAttributeSet,
+	};
+	use objc2_foundation::{NSArray, NSDate, NSString, NSURL};
+	use objc2_uniform_type_identifiers::{UTTypeFolder, UTTypeItem};
+	use tauri::{AppHandle, Url};
+	use tracing::{error, warn};
+	use uuid::Uuid;
+
+	/// The app results are opened in, the delegate method has nowhere else to find it
+	static APP: OnceLock<AppHandle> = OnceLock::new();
+
+	pub struct Exporter {
+		index: Retained<CSSearchableIndex>,
+	}
+
+	// SAFETY: `CSSearchableIndex` is documented as thread safe
+	unsafe impl Send for Exporter {}
+	unsafe impl Sync for Exporter {}
+
+	impl Exporter {
+		pub fn new(app: &AppHandle) -> Result<Self, String> {
+			// SAFETY: Plain Core Spotlight calls
+			let index = unsafe {
+				if !CSSearchableIndex::isIndexingAvailable() {
+					return Err("Spotlight indexing is not available".into());
+				}
+
+				CSSearchableIndex::defaultSearchableIndex()
+			};
+
+			handle_opened_items(app);
+
+			Ok(Self { index })
+		}
+
+		pub fn upsert(&self, library_id: Uuid, items: &[SearchItem]) -> Result<(), String> {
+			let domain = NSString::from_str(&library_id.to_string());
+
+			// SAFETY: Plain Core Spotlight calls, all arguments are valid objects
+			let items = items
+				.iter()
+				.map(|item| unsafe {
+					let content_type = if item.is_dir {
+						UTTypeFolder
+					} else {
+						UTTypeItem
+					};
+					let attributes = CSSearchableItemAttributeSet::initWithContentType(
+						CSSearchableItemAttributeSet::alloc(),
+						content_type,
+					);
+					attributes.setTitle(Some(&NSString::from_str(&item.title)));
+					attributes.setContentDescription(Some(&NSString::from_str(&item.description)));
+					attributes.setContentURL(
+						NSURL::URLWithString(&NSString::from_str(&item.url)).as_deref(),
+					);
+					if let Some(date_modified) = item.date_modified {
+						attributes.setContentModificationDate(Some(
+							&NSDate::dateWithTimeIntervalSince1970(date_modified.timestamp() as f64),
+						));
+					}
+
+					CSSearchableItem::initWithUniqueIdentifier_domainIdentifier_attributeSet(
+						CSSearchableItem::alloc(),
+						Some(&NSString::from_str(&item.url)),
+						Some(&domain),
+						&attributes,
+					)
+				})
+				.collect::<Vec<_>>();
+
+			// SAFETY: Same as above
+			unsafe {
+				self.index.indexSearchableItems_completionHandler(
+					&NSArray::from_retained_slice(&items),
+					None,
+				);
+			}
+
+			Ok(())
+		}
+
+		pub fn remove_library(&self, library_id: Uuid) -> Result<(), String> {
+			let domains =
+				NSArray::from_retained_slice(&[NSString::from_str(&library_id.to_string())]);
+
+			// SAFETY: Plain Core Spotlight call
+			unsafe {
+				self.index
+					.deleteSearchableItemsWithDomainIdentifiers_completionHandler(&domains, None);
+			}
+
+			Ok(())
+		}
+	}
+
+	/// Opening a result continues a `CSSearchableItemActionType` user activity rather than opening
+	/// a URL. Tauri's app delegate doesn't handle user activities, so the method is added to its
+	/// class.
+	fn handle_opened_items(app: &AppHandle) {
+		if APP.set(app.clone()).is_err() {
+			return;
+		}
+
+		let result = app.run_on_main_thread(|| {
+			// SAFETY: The delegate is an object, the method is added with the signature AppKit
+			// calls it with, `class_addMethod` leaves the delegate alone if it already has one
+			unsafe {
+				let ns_app: *mut AnyObject = msg_send![class!(NSApplication), sharedApplication];
+				let delegate: *mut AnyObject = msg_send![ns_app, delegate];
+				let Some(delegate) = delegate.as_ref() else {
+					warn!("No app delegate to open Spotlight results with;");
+					return;
+				};
+
+				let imp: unsafe extern "C" fn(
+					&AnyObject,
+					Sel,
+					&AnyObject,
+					&AnyObject,
+					*mut AnyObject,
+				) -> Bool = continue_user_activity;
+				let types = CString::new(format!("{}@:@@@?", Bool::ENCODING))
+					.expect("encodings have no nul bytes");
+				let added = ffi::class_addMethod(
+					delegate.class() as *const AnyClass as *mut _,
+					sel!(application:continueUserActivity:restorationHandler:),
+					std::mem::transmute(imp),
+					types.as_ptr(),
+				);
+				if added == ffi::NO {
+					warn!("The app delegate already continues user activities;");
+				}
+			}
+		});
+
+		if let Err(e) = result {
+			error!(?e, "Failed to handle opened Spotlight results;");
+		}
+	}
+
+	/// `application:continueUserActivity:restorationHandler:`
+	unsafe extern "C" fn continue_user_activity(
+		_this: &AnyObject,
+		_cmd: Sel,
+		_app: &AnyObject,
+		activity: &AnyObject,
+		_restoration_handler: *mut AnyObject,
+	) -> Bool {
+		let activity_type: *mut NSString = msg_send![activity, activityType];
+		if activity_type.as_ref() != Some(CSSearchableItemActionType) {
+			return Bool::NO;
+		}
+
+		let user_info: *mut AnyObject = msg_send![activity, userInfo];
+		let Some(user_info) = user_info.as_ref() else {
+			return Bool::NO;
+		};
+		let identifier: *mut NSString =
+			msg_send![user_info, objectForKey: CSSearchableItemActivityIdentifier];
+		let Some(url) = identifier
+			.as_ref()
+			.and_then(|identifier| Url::parse(&identifier.to_string()).ok())
+		else {
+			return Bool::NO;
+		};
+
+		if let Some(app) = APP.get().cloned() {
+			tauri::async_runtime::spawn(async move {
+				crate::deep_link::handle_urls(&app, vec![url]).await;
+			});
+		}
+
+		Bool::YES
+	}
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+	//! Windows Search has no API to push arbitrary items without a protocol handler, so each item is
+	//! an Internet Shortcut (`.url`) pointing at its deep link, in a folder we add to the indexer scope.
+
+	use super::SearchItem;
+
+	use std::{
+		collections::{hash_map::Entry, HashMap},
+		fs,
+		path::{Path, PathBuf},
+		sync::Mutex,
+	};
+
+	use tauri::{AppHandle, Manager};
+	use uuid::Uuid;
+	use windows::{
+		core::{w, HSTRING},
+		Win32::System::{
+			Com::{CoCreateInstance, CoInitializeEx, CLSCTX_LOCAL_SERVER, COINIT_MULTITHREADED},
+			Search::{CSearchManager, ISearchManager, FF_INDEXCOMPLEXURLS},
+		},
+	};
+
+	pub struct Exporter {
+		root: PathBuf,
+		/// The shortcut of each file path of the libraries we synced, by file path id, read from
+		/// their folder the first time they're synced
+		shortcuts: Mutex<HashMap<Uuid, HashMap<String, String>>>,
+	}
+
+	impl Exporter {
+		pub fn new(app: &AppHandle) -> Result<Self, String> {
+			let root = app
+				.path()
+				.app_local_data_dir()
+				.map_err(|e| e.to_string())?
+				.join("Search");
+			fs::create_dir_all(&root).map_err(|e| e.to_string())?;
+
+			add_to_index_scope(&root).map_err(|e| e.to_string())?;
+
+			Ok(Self {
+				root,
+				shortcuts: Mutex::default(),
+			})
+		}
+
+		/// Shortcuts are named after the file, the one of a renamed file replaces its old one
+		pub fn upsert(&self, library_id: Uuid, items: &[SearchItem]) -> Result<(), String> {
+			let dir = self.root.join(library_id.to_string());
+			fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+			let mut shortcuts = self.shortcuts.lock().unwrap_or_else(|e| e.into_inner());
+			let shortcuts = match shortcuts.entry(library_id) {
+				Entry::Occupied(entry) => entry.into_mut(),
+				Entry::Vacant(entry) => {
+					entry.insert(read_shortcuts(&dir).map_err(|e| e.to_string())?)
+				}
+			};
+
+			for item in items {
+				let id = item.id.rsplit('/').next().unwrap_or_default();
+				let file_name = format!("{} ({id}).url", sanitize(&item.title));
+				fs::write(
+					dir.join(&file_name),
+					format!("[InternetShortcut]\r\nURL={}\r\n", item.url),
+				)
+				.map_err(|e| e.to_string())?;
+
+				match shortcuts.insert(id.to_string(), file_name.clone()) {
+					Some(previous) if previous != file_name => {
+						match fs::remove_file(dir.join(previous)) {
+							Ok(()) => {}
+							Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+							Err(e) => return Err(e.to_string()),
+						}
+					}
+					_ => {}
+				}
+			}
+
+			Ok(())
+		}
+
+		pub fn remove_library(&self, library_id: Uuid) -> Result<(), String> {
+			self.shortcuts
+				.lock()
+				.unwrap_or_else(|e| e.into_inner())
+				.remove(&library_id);
+
+			match fs::remove_dir_all(self.root.join(library_id.to_string())) {
+				Ok(()) => Ok(()),
+				Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+				Err(e) => Err(e.to_string()),
+			}
+		}
+	}
+
+	/// The shortcuts already in a library folder, by the file path id at the end of their name
+	fn read_shortcuts(dir: &Path) -> std::io::Result<HashMap<String, String>> {
+		let mut shortcuts = HashMap::new();
+		for entry in fs::read_dir(dir)? {
+			let file_name = entry?.file_name().to_string_lossy().into_owned();
+			if let Some(id) = shortcut_id(&file_name) {
+				shortcuts.insert(id.to_string(), file_name);
+			}
+		}
+
+		Ok(shortcuts)
+	}
+
+	fn shortcut_id(file_name: &str) -> Option<&str> {
+		file_name
+			.strip_suffix(").url")?
+			.rsplit_once(" (")
+			.map(|(_, id)| id)
+	}
+
+	fn sanitize(name: &str) -> String {
+		name.chars()
+			.map(|c| match c {
+				'<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+				c if c.is_control() => '_',
+				c => c,
+			})
+			.collect()
+	}
+
+	fn add_to_index_scope(root: &std::path::Path) -> windows::core::Result<()> {
+		let url = HSTRING::from(format!("file:///{}\\", root.display()));
+
+		// SAFETY: Plain COM calls on the current thread
+		unsafe {
+			// Fails harmlessly if COM was already initialized on this thread
+			let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+
+			let manager: ISearchManager =
+				CoCreateInstance(&CSearchManager, None, CLSCTX_LOCAL_SERVER)?;
+			let scope = manager
+				.GetCatalog(w!("SystemIndex"))?
+				.GetCrawlScopeManager()?;
+			scope.AddUserScopeRule(&url, true, false, FF_INDEXCOMPLEXURLS.0 as u32)?;
+			scope.SaveAll()
+		}
+	}
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+mod platform {
+	use super::SearchItem;
+
+	use tauri::AppHandle;
+	use uuid::Uuid;
+
+	pub struct Exporter;
+
+	impl Exporter {
+		pub fn new(_app: &AppHandle) -> Result<Self, String> {
+			Err("No supported system search on this platform".into())
+		}
+
+		pub fn upsert(&self, _library_id: Uuid, _items: &[SearchItem]) -> Result<(), String> {
+			Ok(())
+		}
+
+		pub fn remove_library(&self, _library_id: Uuid) -> Result<(), String> {
+			Ok(())
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn builds_items_deep_linking_to_the_file() {
+		let library_id = Uuid::new_v4();
+		let item = SearchItem::new(
+			library_id,
+			file_path_for_search::Data {
+				id: 42,
+				name: Some("report".into()),
+				extension: Some("pdf".into()),
+				materialized_path: Some("/documents/".into()),
+				is_dir: Some(false),
+				date_modified: None,
+			},
+		)
+		.expect("file path has a name");
+
+		assert_eq!(item.title, "report.pdf");
+		assert_eq!(item.description, "/documents/");
+		assert_eq!(item.url, format!("{SCHEME}://library/{library_id}/file/42"));
+	}
+}
@@ -0,0 +1,133 @@
+use sd_core::Node;
+use sd_fda::{DiskAccess, Permission, PermissionStatus};
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+use tokio::time::sleep;
+use tracing::{error, info};
+
+/// How often we re-probe the permission status
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+pub enum DiskPermission {
+	FullDisk,
+	RemovableVolumes,
+}
+
+impl From<DiskPermission> for Permission {
+	fn from(permission: DiskPermission) -> Self {
+		match permission {
+			DiskPermission::FullDisk => Self::FullDisk,
+			DiskPermission::RemovableVolumes => Self::RemovableVolumes,
+		}
+	}
+}
+
+impl From<Permission> for DiskPermission {
+	fn from(permission: Permission) -> Self {
+		match permission {
+			Permission::FullDisk => Self::FullDisk,
+			Permission::RemovableVolumes => Self::RemovableVolumes,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum DiskPermissionStatus {
+	Granted,
+	Denied,
+	Unknown,
+	NotRequired,
+}
+
+impl From<PermissionStatus> for DiskPermissionStatus {
+	fn from(status: PermissionStatus) -> Self {
+		match status {
+			PermissionStatus::Granted => Self::Granted,
+			PermissionStatus::Denied => Self::Denied,
+			PermissionStatus::Unknown => Self::Unknown,
+			PermissionStatus::NotRequired => Self::NotRequired,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type, tauri_specta::Event)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskPermissionChangedEvent {
+	permission: DiskPermission,
+	status: DiskPermissionStatus,
+}
+
+pub(crate) fn current_statuses() -> HashMap<DiskPermission, DiskPermissionStatus> {
+	Permission::ALL
+		.into_iter()
+		.map(|p| (p.into(), DiskAccess::status(p).into()))
+		.collect()
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn get_disk_permissions() -> HashMap<DiskPermission, DiskPermissionStatus> {
+	current_statuses()
+}
+
+/// Opens the settings pane where the user can grant `permission`
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn request_disk_permission(permission: DiskPermission) -> Result<(), String> {
+	DiskAccess::open_settings(permission.into()).map_err(|e| e.to_string())
+}
+
+/// Watches for permission changes, emitting [`DiskPermissionChangedEvent`] and asking the core
+/// to retry scans which may have failed while access was denied.
+pub fn spawn_watcher(app: AppHandle) {
+	tokio::spawn(async move {
+		let mut previous = current_statuses();
+
+		// Nothing will ever change, so don't bother polling
+		if previous
+			.values()
+			.all(|status| *status == DiskPermissionStatus::NotRequired)
+		{
+			return;
+		}
+
+		loop {
+			sleep(POLL_INTERVAL).await;
+
+			let current = current_statuses();
+			let mut gained_access = false;
+
+			for (permission, status) in &current {
+				if previous.get(permission) == Some(status) {
+					continue;
+				}
+
+				info!(?permission, ?status, "Disk permission changed;");
+				gained_access |= *status == DiskPermissionStatus::Granted;
+
+				if let Err(e) = (DiskPermissionChangedEvent {
+					permission: *permission,
+					status: *status,
+				})
+				.emit(&app)
+				{
+					error!(?e, "Failed to emit disk permission event;");
+				}
+			}
+
+			if gained_access {
+				if let Some(node) = app.try_state::<Arc<Node>>() {
+					node.retry_incomplete_scans().await;
+				}
+			}
+
+			previous = current;
+		}
+	});
+}
@@ -0,0 +1,245 @@
+//! Hardware and environment details, shown in the About panel and attached to bug reports.
+
+use crate::permissions::{self, DiskPermission, DiskPermissionStatus};
+
+use sd_core::Node;
+
+use std::{collections::HashMap, process::Command, sync::Arc};
+
+use serde::Serialize;
+use specta::Type;
+use sysinfo::{CpuExt, System, SystemExt};
+use tauri::{async_runtime::spawn_blocking, AppHandle};
+use tracing::warn;
+
+// Byte counts are sent as strings as they don't fit in a JS number, the same as the core does for volumes
+
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemInfo {
+	pub app_version: String,
+	pub os: OsInfo,
+	pub cpu: CpuInfo,
+	pub memory: MemoryInfo,
+	/// Names of the graphics adapters, empty if they couldn't be detected
+	pub gpus: Vec<String>,
+	pub disks: Vec<DiskSummary>,
+	pub permissions: HashMap<DiskPermission, DiskPermissionStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct OsInfo {
+	pub name: Option<String>,
+	pub version: Option<String>,
+	pub kernel_version: Option<String>,
+	pub arch: String,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct CpuInfo {
+	pub brand: Option<String>,
+	pub physical_cores: Option<u32>,
+	pub logical_cores: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryInfo {
+	pub total_bytes: String,
+	pub available_bytes: String,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskSummary {
+	pub name: String,
+	pub mount_point: String,
+	pub file_system: String,
+	pub disk_type: String,
+	pub total_bytes: String,
+	pub available_bytes: String,
+	pub read_only: bool,
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn system_info(
+	app: AppHandle,
+	node: tauri::State<'_, Arc<Node>>,
+) -> Result<SystemInfo, String> {
+	let (os, cpu, memory, gpus) = spawn_blocking(|| {
+		let mut sys = System::new();
+		sys.refresh_cpu();
+		sys.refresh_memory();
+
+		let os = OsInfo {
+			name: sys.name(),
+			version: sys.long_os_version(),
+			kernel_version: sys.kernel_version(),
+			arch: std::env::consts::ARCH.to_string(),
+		};
+
+		let cpu = CpuInfo {
+			brand: sys
+				.cpus()
+				.first()
+				.map(|cpu| cpu.brand().trim().to_string())
+				.filter(|brand| !brand.is_empty()),
+			physical_cores: sys.physical_core_count().map(|count| count as u32),
+			logical_cores: sys.cpus().len() as u32,
+		};
+
+		let memory = MemoryInfo {
+			total_bytes: sys.total_memory().to_string(),
+			available_bytes: sys.available_memory().to_string(),
+		};
+
+		(os, cpu, memory, gpus())
+	})
+	.await
+	.map_err(|e| e.to_string())?;
+
+	// A volume manager hiccup shouldn't stop the rest of the report from being shown
+	let disks = node
+		.volumes
+		.list_volumes()
+		.await
+		.map_err(|e| warn!(?e, "Failed to list volumes for system info;"))
+		.unwrap_or_default()
+		.into_iter()
+		.filter(|volume| volume.is_mounted)
+		.map(|volume| DiskSummary {
+			name: volume.name,
+			mount_point: volume.mount_point.to_string_lossy().to_string(),
+			file_system: volume.file_system.to_string(),
+			disk_type: volume.disk_type.to_string(),
+			total_bytes: volume.total_bytes_capacity.0.to_string(),
+			available_bytes: volume.total_bytes_available.0.to_string(),
+			read_only: volume.read_only,
+		})
+		.collect();
+
+	Ok(SystemInfo {
+		app_version: app.package_info().version.to_string(),
+		os,
+		cpu,
+		memory,
+		gpus,
+		disks,
+		permissions: permissions::current_statuses(),
+	})
+}
+
+fn gpus() -> Vec<String> {
+	let output = match gpu_command().output() {
+		Ok(output) if output.status.success() => output,
+		Ok(output) => {
+			warn!(status = ?output.status, "Failed to list graphics adapters;");
+			return vec![];
+		}
+		Err(e) => {
+			warn!(?e, "Failed to list graphics adapters;");
+			return vec![];
+		}
+	};
+
+	parse_gpus(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(target_os = "macos")]
+fn gpu_command() -> Command {
+	let mut command = Command::new("system_profiler");
+	command.arg("SPDisplaysDataType");
+	command
+}
+
+#[cfg(target_os = "macos")]
+fn parse_gpus(output: &str) -> Vec<String> {
+	output
+		.lines()
+		.filter_map(|line| line.trim().strip_prefix("Chipset Model:"))
+		.map(|model| model.trim().to_string())
+		.collect()
+}
+
+#[cfg(target_os = "windows")]
+fn gpu_command() -> Command {
+	use std::os::windows::process::CommandExt;
+
+	// CREATE_NO_WINDOW, so no console flashes up
+	const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+	let mut command = Command::new("powershell");
+	command
+		.args([
+			"-NoProfile",
+			"-Command",
+			"(Get-CimInstance Win32_VideoController).Name",
+		])
+		.creation_flags(CREATE_NO_WINDOW);
+	command
+}
+
+#[cfg(target_os = "windows")]
+fn parse_gpus(output: &str) -> Vec<String> {
+	output
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty())
+		.map(str::to_string)
+		.collect()
+}
+
+#[cfg(target_os = "linux")]
+fn gpu_command() -> Command {
+	let mut command = Command::new("lspci");
+	command.arg("-mm");
+	command
+}
+
+/// Parses `lspci -mm`, where every line is `slot "class" "vendor" "device" ...`
+#[cfg(target_os = "linux")]
+fn parse_gpus(output: &str) -> Vec<String> {
+	const DISPLAY_CLASSES: [&str; 3] = [
+		"VGA compatible controller",
+		"3D controller",
+		"Display controller",
+	];
+
+	output
+		.lines()
+		.filter_map(|line| {
+			let mut fields = line.split('"').skip(1).step_by(2);
+			let class = fields.next()?;
+			if !DISPLAY_CLASSES.contains(&class) {
+				return None;
+			}
+
+			let vendor = fields.next()?;
+			let device = fields.next()?;
+			Some(format!("{vendor} {device}"))
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn parses_lspci_display_controllers() {
+		let output = r#"00:00.0 "Host bridge" "Intel Corporation" "Xeon E3-1200 v6/7th Gen Core Processor Host Bridge/DRAM Registers" -r08 "Lenovo" "ThinkPad X1 Carbon 5th Gen"
+00:02.0 "VGA compatible controller" "Intel Corporation" "HD Graphics 620" -r02 "Lenovo" "ThinkPad X1 Carbon 5th Gen"
+01:00.0 "3D controller" "NVIDIA Corporation" "GP108M [GeForce MX150]" -ra1 "Lenovo" "GP108M [GeForce MX150]"
+"#;
+
+		assert_eq!(
+			super::parse_gpus(output),
+			[
+				"Intel Corporation HD Graphics 620",
+				"NVIDIA Corporation GP108M [GeForce MX150]"
+			]
+		);
+	}
+}
@@ -0,0 +1,260 @@
+//! Reflects running job progress on the dock (macOS), taskbar (Windows) and launcher (Linux, Unity
+//! API), with the number of running jobs as a badge on the dock and launcher.
+
+use sd_core::{api::CoreEvent, Node};
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use tauri::{
+	window::{ProgressBarState, ProgressBarStatus},
+	AppHandle, Manager,
+};
+use tokio::{sync::broadcast::error::RecvError, time::interval};
+use tracing::{debug, error};
+use uuid::Uuid;
+
+/// We don't have to repaint the taskbar at the rate jobs report progress
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+struct JobProgress {
+	task_count: i32,
+	completed_task_count: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Summary {
+	running_jobs: usize,
+	/// Overall percentage, `None` if no job knows how many tasks it has yet
+	progress: Option<u64>,
+}
+
+#[derive(Default)]
+struct Tracker {
+	jobs: HashMap<Uuid, JobProgress>,
+}
+
+impl Tracker {
+	fn update(&mut self, id: Uuid, task_count: i32, completed_task_count: i32) {
+		self.jobs.insert(
+			id,
+			JobProgress {
+				task_count,
+				completed_task_count,
+			},
+		);
+	}
+
+	fn finish(&mut self, id: Uuid) {
+		self.jobs.remove(&id);
+	}
+
+	fn summary(&mut self) -> Summary {
+		self.jobs
+			.retain(|_, job| job.task_count == 0 || job.completed_task_count < job.task_count);
+
+		let (total, completed) =
+			self.jobs
+				.values()
+				.fold((0u64, 0u64), |(total, completed), job| {
+					(
+						total + job.task_count.max(0) as u64,
+						completed + job.completed_task_count.max(0) as u64,
+					)
+				});
+
+		Summary {
+			running_jobs: self.jobs.len(),
+			progress: (total > 0).then(|| (completed * 100 / total).min(100)),
+		}
+	}
+}
+
+pub fn spawn(app: AppHandle, node: Arc<Node>) {
+	tokio::spawn(async move {
+		let mut rx = node.event_bus.0.subscribe();
+		let mut tracker = Tracker::default();
+		let mut last_summary = None;
+		let mut refresh = interval(REFRESH_INTERVAL);
+
+		loop {
+			tokio::select! {
+				event = rx.recv() => match event {
+					Ok(CoreEvent::JobProgress(event)) => {
+						tracker.update(event.id, event.task_count, event.completed_task_count);
+					}
+					Ok(CoreEvent::JobFinished(event)) => tracker.finish(event.id),
+					Ok(_) => {}
+					Err(RecvError::Lagged(skipped)) => {
+						debug!(%skipped, "Taskbar progress lagged behind the event bus;");
+					}
+					Err(RecvError::Closed) => break,
+				},
+				_ = refresh.tick() => {
+					let summary = tracker.summary();
+					if last_summary != Some(summary) {
+						apply(&app, summary);
+						last_summary = Some(summary);
+					}
+				}
+			}
+		}
+	});
+}
+
+fn apply(app: &AppHandle, summary: Summary) {
+	let Some(window) = app.get_webview_window("main") else {
+		return;
+	};
+
+	let state = match summary {
+		Summary {
+			running_jobs: 0, ..
+		} => ProgressBarState {
+			status: Some(ProgressBarStatus::None),
+			progress: None,
+		},
+		Summary { progress: None, .. } => ProgressBarState {
+			status: Some(ProgressBarStatus::Indeterminate),
+			progress: None,
+		},
+		Summary {
+			progress: Some(progress),
+			..
+		} => ProgressBarState {
+			status: Some(ProgressBarStatus::Normal),
+			progress: Some(progress),
+		},
+	};
+
+	if let Err(e) = window.set_progress_bar(state) {
+		error!(?e, "Failed to set taskbar progress;");
+	}
+
+	#[cfg(target_os = "linux")]
+	if let Err(e) = unity::set_count(summary.running_jobs) {
+		debug!(?e, "Failed to set launcher job count;");
+	}
+
+	#[cfg(target_os = "macos")]
+	if let Err(e) = app.run_on_main_thread(move || dock::set_badge(summary.running_jobs)) {
+		error!(?e, "Failed to set dock badge;");
+	}
+
+	// Windows has no count badge, only overlay icons we'd have to draw, the progress bar stands in
+}
+
+#[cfg(target_os = "macos")]
+mod dock {
+	use objc2::{class, msg_send, runtime::AnyObject};
+	use objc2_foundation::NSString;
+
+	/// Sets the badge of the dock tile to the job count, hidden when there are none.
+	/// AppKit must be called on the main thread.
+	pub fn set_badge(count: usize) {
+		let label = (count > 0).then(|| NSString::from_str(&count.to_string()));
+		let label = label
+			.as_deref()
+			.map_or(std::ptr::null(), |label| label as *const NSString);
+
+		// SAFETY: `sharedApplication` and `dockTile` never return nil, `setBadgeLabel:` takes
+		// nil to clear the badge
+		unsafe {
+			let app: *mut AnyObject = msg_send![class!(NSApplication), sharedApplication];
+			let dock_tile: *mut AnyObject = msg_send![app, dockTile];
+			let _: () = msg_send![dock_tile, setBadgeLabel: label];
+		}
+	}
+}
+
+#[cfg(target_os = "linux")]
+mod unity {
+	use std::collections::HashMap;
+
+	use dbus::{
+		arg::{RefArg, Variant},
+		blocking::LocalConnection,
+		Message,
+	};
+
+	/// Must match the name of the installed `.desktop` file
+	const APP_URI: &str = "application://spacedrive.desktop";
+
+	/// Sets the count badge through the `com.canonical.Unity.LauncherEntry` API, supported by
+	/// Unity, Plank, KDE's task manager and the GNOME dash-to-dock extension.
+	pub fn set_count(count: usize) -> Result<(), dbus::Error> {
+		let connection = LocalConnection::new_session()?;
+
+		let mut properties: HashMap<&str, Variant<Box<dyn RefArg>>> = HashMap::new();
+		properties.insert("count", Variant(Box::new(count as i64)));
+		properties.insert("count-visible", Variant(Box::new(count > 0)));
+
+		let message = Message::new_signal(
+			"/com/canonical/unity/launcherentry/spacedrive",
+			"com.canonical.Unity.LauncherEntry",
+			"Update",
+		)
+		.map_err(|e| dbus::Error::new_failed(&e))?
+		.append2(APP_URI, properties);
+
+		connection
+			.channel()
+			.send(message)
+			.map_err(|()| dbus::Error::new_failed("Failed to send launcher entry update"))?;
+		connection.channel().flush();
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn aggregates_running_jobs() {
+		let mut tracker = Tracker::default();
+		tracker.update(Uuid::new_v4(), 10, 5);
+		tracker.update(Uuid::new_v4(), 30, 5);
+
+		assert_eq!(
+			tracker.summary(),
+			Summary {
+				running_jobs: 2,
+				progress: Some(25)
+			}
+		);
+	}
+
+	#[test]
+	fn drops_jobs_once_they_finish() {
+		let mut tracker = Tracker::default();
+		let (finished, running) = (Uuid::new_v4(), Uuid::new_v4());
+		tracker.update(finished, 10, 5);
+		tracker.update(running, 10, 5);
+
+		tracker.finish(finished);
+
+		assert_eq!(
+			tracker.summary(),
+			Summary {
+				running_jobs: 1,
+				progress: Some(50)
+			}
+		);
+	}
+
+	#[test]
+	fn drops_finished_jobs() {
+		let mut tracker = Tracker::default();
+		tracker.update(Uuid::new_v4(), 10, 10);
+		tracker.update(Uuid::new_v4(), 0, 0);
+
+		assert_eq!(
+			tracker.summary(),
+			Summary {
+				running_jobs: 1,
+				progress: None
+			}
+		);
+	}
+}
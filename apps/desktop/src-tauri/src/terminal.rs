@@ -0,0 +1,469 @@
+//! "Open Terminal Here": opens a terminal emulator with its working directory set to a path.
+//!
+//! The terminals installed on the system are detected from a list of common ones per platform,
+//! the user can pick one of them in the settings, otherwise the platform default is used.
+
+use std::{
+	ffi::OsString,
+	path::{Path, PathBuf},
+	process::{Command, Stdio},
+};
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::{async_runtime::spawn_blocking, AppHandle, Manager};
+use tokio::sync::RwLock;
+use tracing::{error, warn};
+
+const STATE_FILE: &str = "terminal.json";
+
+/// How to tell a terminal which directory to start in
+#[derive(Debug, Clone, Copy)]
+#[allow(dead_code)] // Not every platform uses every variant
+enum Launch {
+	/// `open -a <app> <path>`, for macOS apps which open folders passed to them
+	MacApp(&'static str),
+	/// The executable is started with the given flag followed by the path, e.g. `--workdir <path>`
+	Flag(&'static str),
+	/// Same as `Flag` but as a single `--flag=<path>` argument
+	FlagEquals(&'static str),
+	/// The terminal starts in the working directory of its process
+	CurrentDir,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct KnownTerminal {
+	id: &'static str,
+	name: &'static str,
+	/// Executable on `PATH`, or the app bundle name on macOS
+	program: &'static str,
+	launch: Launch,
+}
+
+#[cfg(target_os = "macos")]
+const KNOWN_TERMINALS: &[KnownTerminal] = &[
+	KnownTerminal {
+		id: "terminal",
+		name: "Terminal",
+		program: "Terminal",
+		launch: Launch::MacApp("Terminal"),
+	},
+	KnownTerminal {
+		id: "iterm2",
+		name: "iTerm2",
+		program: "iTerm",
+		launch: Launch::MacApp("iTerm"),
+	},
+	KnownTerminal {
+		id: "warp",
+		name: "Warp",
+		program: "Warp",
+		launch: Launch::MacApp("Warp"),
+	},
+	KnownTerminal {
+		id: "ghostty",
+		name: "Ghostty",
+		program: "Ghostty",
+		launch: Launch::MacApp("Ghostty"),
+	},
+	KnownTerminal {
+		id: "wezterm",
+		name: "WezTerm",
+		program: "WezTerm",
+		launch: Launch::MacApp("WezTerm"),
+	},
+	KnownTerminal {
+		id: "kitty",
+		name: "kitty",
+		program: "kitty",
+		launch: Launch::MacApp("kitty"),
+	},
+	KnownTerminal {
+		id: "alacritty",
+		name: "Alacritty",
+		program: "Alacritty",
+		launch: Launch::MacApp("Alacritty"),
+	},
+];
+
+#[cfg(target_os = "windows")]
+const KNOWN_TERMINALS: &[KnownTerminal] = &[
+	KnownTerminal {
+		id: "windows-terminal",
+		name: "Windows Terminal",
+		program: "wt",
+		launch: Launch::Flag("-d"),
+	},
+	KnownTerminal {
+		id: "pwsh",
+		name: "PowerShell",
+		program: "pwsh",
+		launch: Launch::CurrentDir,
+	},
+	KnownTerminal {
+		id: "powershell",
+		name: "Windows PowerShell",
+		program: "powershell",
+		launch: Launch::CurrentDir,
+	},
+	KnownTerminal {
+		id: "cmd",
+		name: "Command Prompt",
+		program: "cmd",
+		launch: Launch::CurrentDir,
+	},
+	KnownTerminal {
+		id: "wezterm",
+		name: "WezTerm",
+		program: "wezterm-gui",
+		launch: Launch::Flag("start --cwd"),
+	},
+	KnownTerminal {
+		id: "alacritty",
+		name: "Alacritty",
+		program: "alacritty",
+		launch: Launch::Flag("--working-directory"),
+	},
+];
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const KNOWN_TERMINALS: &[KnownTerminal] = &[
+	KnownTerminal {
+		id: "gnome-terminal",
+		name: "GNOME Terminal",
+		program: "gnome-terminal",
+		launch: Launch::FlagEquals("--working-directory"),
+	},
+	KnownTerminal {
+		id: "gnome-console",
+		name: "Console",
+		program: "kgx",
+		launch: Launch::FlagEquals("--working-directory"),
+	},
+	KnownTerminal {
+		id: "konsole",
+		name: "Konsole",
+		program: "konsole",
+		launch: Launch::Flag("--workdir"),
+	},
+	KnownTerminal {
+		id: "xfce4-terminal",
+		name: "Xfce Terminal",
+		program: "xfce4-terminal",
+		launch: Launch::FlagEquals("--working-directory"),
+	},
+	KnownTerminal {
+		id: "tilix",
+		name: "Tilix",
+		program: "tilix",
+		launch: Launch::FlagEquals("--working-directory"),
+	},
+	KnownTerminal {
+		id: "ptyxis",
+		name: "Ptyxis",
+		program: "ptyxis",
+		launch: Launch::FlagEquals("--working-directory"),
+	},
+	KnownTerminal {
+		id: "ghostty",
+		name: "Ghostty",
+		program: "ghostty",
+		launch: Launch::FlagEquals("--working-directory"),
+	},
+	KnownTerminal {
+		id: "wezterm",
+		name: "WezTerm",
+		program: "wezterm",
+		launch: Launch::Flag("start --cwd"),
+	},
+	KnownTerminal {
+		id: "kitty",
+		name: "kitty",
+		program: "kitty",
+		launch: Launch::Flag("--directory"),
+	},
+	KnownTerminal {
+		id: "alacritty",
+		name: "Alacritty",
+		program: "alacritty",
+		launch: Launch::Flag("--working-directory"),
+	},
+	KnownTerminal {
+		id: "foot",
+		name: "foot",
+		program: "foot",
+		launch: Launch::FlagEquals("--working-directory"),
+	},
+	// Debian's alternatives symlink to the system default terminal
+	KnownTerminal {
+		id: "x-terminal-emulator",
+		name: "Default terminal",
+		program: "x-terminal-emulator",
+		launch: Launch::CurrentDir,
+	},
+	KnownTerminal {
+		id: "xterm",
+		name: "XTerm",
+		program: "xterm",
+		launch: Launch::CurrentDir,
+	},
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct Terminal {
+	pub id: String,
+	pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminalSettings {
+	/// Terminals detected on this system, in order of preference
+	pub available: Vec<Terminal>,
+	/// The terminal picked by the user, the first available one is used when unset
+	pub preferred: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PersistedState {
+	preferred: Option<String>,
+}
+
+pub struct Terminals {
+	state_path: PathBuf,
+	state: RwLock<PersistedState>,
+}
+
+impl Terminals {
+	pub async fn load(app: &AppHandle) -> Self {
+		let state_path = app
+			.path()
+			.app_data_dir()
+			.unwrap_or_else(|_| PathBuf::from("./"))
+			.join(STATE_FILE);
+
+		let state = match tokio::fs::read(&state_path).await {
+			Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+				warn!(?e, "Corrupted terminal settings, using the defaults;");
+				PersistedState::default()
+			}),
+			Err(_) => PersistedState::default(),
+		};
+
+		Self {
+			state_path,
+			state: RwLock::new(state),
+		}
+	}
+}
+
+fn detect() -> Vec<&'static KnownTerminal> {
+	KNOWN_TERMINALS
+		.iter()
+		.filter(|terminal| is_installed(terminal))
+		.collect()
+}
+
+#[cfg(target_os = "macos")]
+fn is_installed(terminal: &KnownTerminal) -> bool {
+	let bundle = format!("{}.app", terminal.program);
+
+	// Terminal.app lives in /System/Applications since Catalina
+	[
+		PathBuf::from("/Applications"),
+		PathBuf::from("/System/Applications/Utilities"),
+		PathBuf::from("/Applications/Utilities"),
+	]
+	.into_iter()
+	.chain(std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Applications")))
+	.any(|dir| dir.join(&bundle).exists())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn is_installed(terminal: &KnownTerminal) -> bool {
+	find_in_path(terminal.program).is_some()
+}
+
+#[cfg(not(target_os = "macos"))]
+fn find_in_path(program: &str) -> Option<PathBuf> {
+	let file_name = if cfg!(windows) {
+		OsString::from(format!("{program}.exe"))
+	} else {
+		OsString::from(program)
+	};
+
+	std::env::var_os("PATH")
+		.iter()
+		.flat_map(std::env::split_paths)
+		.map(|dir| dir.join(&file_name))
+		.find(|path| path.is_file())
+}
+
+fn command_for(terminal: &KnownTerminal, dir: &Path) -> Command {
+	let (program, launch) = match terminal.launch {
+		Launch::MacApp(app) => {
+			let mut command = Command::new("open");
+			command.args(["-a", app]).arg(dir);
+			return command;
+		}
+		launch => (terminal.program, launch),
+	};
+
+	let mut command = Command::new(program);
+	command.current_dir(dir);
+
+	match launch {
+		Launch::Flag(flag) => {
+			command.args(flag.split(' ')).arg(dir);
+		}
+		Launch::FlagEquals(flag) => {
+			let mut arg = OsString::from(format!("{flag}="));
+			arg.push(dir);
+			command.arg(arg);
+		}
+		Launch::CurrentDir | Launch::MacApp(_) => {}
+	}
+
+	#[cfg(target_os = "windows")]
+	{
+		use std::os::windows::process::CommandExt;
+
+		// Shells need their own console window, they'd be invisible otherwise
+		const CREATE_NEW_CONSOLE: u32 = 0x00000010;
+		command.creation_flags(CREATE_NEW_CONSOLE);
+	}
+
+	command
+}
+
+fn open(preferred: Option<&str>, path: &Path) -> Result<(), String> {
+	// Opening a terminal "at" a file means opening it in the file's folder
+	let dir = if path.is_dir() {
+		path
+	} else {
+		path.parent()
+			.ok_or_else(|| format!("Invalid path: {}", path.display()))?
+	};
+
+	let available = detect();
+	let terminal = preferred
+		.and_then(|id| available.iter().find(|terminal| terminal.id == id))
+		.or_else(|| available.first())
+		.ok_or("No terminal emulator found")?;
+
+	command_for(terminal, dir)
+		.stdin(Stdio::null())
+		.stdout(Stdio::null())
+		.stderr(Stdio::null())
+		.spawn()
+		.map(|_| ())
+		.map_err(|e| {
+			error!(?e, terminal = terminal.id, "Failed to open terminal;");
+			format!("Failed to open {}: {e}", terminal.name)
+		})
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn get_terminal_settings(
+	state: tauri::State<'_, Terminals>,
+) -> Result<TerminalSettings, String> {
+	let available = spawn_blocking(detect)
+		.await
+		.map_err(|e| e.to_string())?
+		.into_iter()
+		.map(|terminal| Terminal {
+			id: terminal.id.into(),
+			name: terminal.name.into(),
+		})
+		.collect();
+
+	Ok(TerminalSettings {
+		available,
+		preferred: state.state.read().await.preferred.clone(),
+	})
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn set_preferred_terminal(
+	state: tauri::State<'_, Terminals>,
+	id: Option<String>,
+) -> Result<(), String> {
+	if let Some(id) = &id {
+		if !KNOWN_TERMINALS.iter().any(|terminal| terminal.id == id) {
+			return Err(format!("Unknown terminal: {id}"));
+		}
+	}
+
+	let mut state_guard = state.state.write().await;
+	state_guard.preferred = id;
+
+	let bytes = serde_json::to_vec(&*state_guard).map_err(|e| e.to_string())?;
+	// The app data dir doesn't exist until something is first written to it
+	if let Some(parent) = state.state_path.parent() {
+		tokio::fs::create_dir_all(parent)
+			.await
+			.map_err(|e| e.to_string())?;
+	}
+	tokio::fs::write(&state.state_path, bytes)
+		.await
+		.map_err(|e| e.to_string())
+}
+
+/// Opens the preferred terminal in `path`, or in its parent folder if it's a file
+#[tauri::command(async)]
+#[specta::specta]
+pub async fn open_terminal(
+	state: tauri::State<'_, Terminals>,
+	path: PathBuf,
+) -> Result<(), String> {
+	let preferred = state.state.read().await.preferred.clone();
+
+	spawn_blocking(move || open(preferred.as_deref(), &path))
+		.await
+		.map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn known_terminal_ids_are_unique() {
+		let mut ids = KNOWN_TERMINALS
+			.iter()
+			.map(|terminal| terminal.id)
+			.collect::<Vec<_>>();
+		ids.sort_unstable();
+		ids.dedup();
+
+		assert_eq!(ids.len(), KNOWN_TERMINALS.len());
+	}
+
+	#[test]
+	#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+	fn passes_the_directory_to_the_terminal() {
+		let dir = Path::new("/home/user/Documents");
+		let terminal = |id| {
+			KNOWN_TERMINALS
+				.iter()
+				.find(|terminal| terminal.id == id)
+				.unwrap()
+		};
+
+		let args = |id| {
+			command_for(terminal(id), dir)
+				.get_args()
+				.map(|arg| arg.to_string_lossy().into_owned())
+				.collect::<Vec<_>>()
+		};
+
+		assert_eq!(
+			args("gnome-terminal"),
+			["--working-directory=/home/user/Documents"]
+		);
+		assert_eq!(args("wezterm"), ["start", "--cwd", "/home/user/Documents"]);
+		assert!(args("xterm").is_empty());
+	}
+}
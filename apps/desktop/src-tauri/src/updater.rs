@@ -1,34 +1,131 @@
-use tauri::{plugin::TauriPlugin, Emitter, Runtime};
+use std::{
+	path::PathBuf,
+	sync::atomic::{AtomicBool, Ordering},
+};
+
+use tauri::{plugin::TauriPlugin, Emitter, Manager, RunEvent, Runtime};
 use tauri_plugin_updater::{Update as TauriPluginUpdate, UpdaterExt};
 use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+/// File in the app data directory holding this install's rollout bucket
+const ROLLOUT_BUCKET_FILE: &str = "update_rollout_bucket";
 
 #[derive(Debug, Clone, specta::Type, serde::Serialize)]
 pub struct Update {
 	pub version: String,
+	/// Release notes for the update, as published in the release feed
+	pub changelog: Option<String>,
+	/// Release date of the update in RFC 3339 format
+	pub date: Option<String>,
 }
 
 impl Update {
 	fn new(update: &TauriPluginUpdate) -> Self {
 		Self {
 			version: update.version.clone(),
+			changelog: update.body.clone(),
+			date: update.date.map(|date| date.to_string()),
 		}
 	}
 }
 
+/// An update which was downloaded but not yet installed
+struct DownloadedUpdate {
+	update: TauriPluginUpdate,
+	bytes: Vec<u8>,
+}
+
 #[derive(Default)]
 pub struct State {
 	install_lock: Mutex<()>,
+	downloaded: Mutex<Option<DownloadedUpdate>>,
+	install_on_quit: AtomicBool,
 }
 
 async fn get_update(app: tauri::AppHandle) -> Result<Option<TauriPluginUpdate>, String> {
-	app.updater_builder()
+	if crate::offline::is_offline(&app) {
+		return Err("Update checks are disabled in offline mode".into());
+	}
+
+	let bucket = rollout_bucket(&app).await;
+
+	let update = app
+		.updater_builder()
 		.header("X-Spacedrive-Version", "stable")
 		.map_err(|e| e.to_string())?
+		.header("X-Spacedrive-Rollout-Bucket", bucket.to_string())
+		.map_err(|e| e.to_string())?
 		.build()
 		.map_err(|e| e.to_string())?
 		.check()
 		.await
-		.map_err(|e| e.to_string())
+		.map_err(|e| e.to_string())?;
+
+	Ok(update.filter(|update| is_in_rollout(update, bucket)))
+}
+
+/// Releases can be rolled out to a percentage of installs by setting a `rollout` field (0-100) in the feed.
+/// Releases without one are available to everyone.
+fn is_in_rollout(update: &TauriPluginUpdate, bucket: u8) -> bool {
+	let Some(rollout) = rollout(&update.raw_json) else {
+		return true;
+	};
+
+	let in_rollout = u64::from(bucket) < rollout;
+	if !in_rollout {
+		info!(
+			version = %update.version,
+			rollout,
+			bucket,
+			"Update available but not yet rolled out to this install;"
+		);
+	}
+
+	in_rollout
+}
+
+/// The percentage of installs a release is rolled out to, if the feed limits it
+fn rollout(raw_json: &serde_json::Value) -> Option<u64> {
+	raw_json.get("rollout").and_then(|v| v.as_u64())
+}
+
+/// Gets the rollout bucket (0-99) for this install, generating and persisting one on first use.
+async fn rollout_bucket(app: &tauri::AppHandle) -> u8 {
+	let Ok(path) = app
+		.path()
+		.app_data_dir()
+		.map(|dir| dir.join(ROLLOUT_BUCKET_FILE))
+	else {
+		return 0;
+	};
+
+	read_or_create_rollout_bucket(path).await
+}
+
+async fn read_or_create_rollout_bucket(path: PathBuf) -> u8 {
+	if let Some(bucket) = tokio::fs::read_to_string(&path)
+		.await
+		.ok()
+		.and_then(|s| s.trim().parse::<u8>().ok())
+		.filter(|bucket| *bucket < 100)
+	{
+		return bucket;
+	}
+
+	let bucket = uuid::Uuid::new_v4().as_bytes()[0] % 100;
+	if let Err(e) = write_rollout_bucket(path, bucket).await {
+		warn!(?e, "Failed to persist update rollout bucket;");
+	}
+
+	bucket
+}
+
+async fn write_rollout_bucket(path: PathBuf, bucket: u8) -> std::io::Result<()> {
+	if let Some(parent) = path.parent() {
+		tokio::fs::create_dir_all(parent).await?;
+	}
+	tokio::fs::write(path, bucket.to_string()).await
 }
 
 #[derive(Clone, serde::Serialize, specta::Type)]
@@ -36,8 +133,21 @@ async fn get_update(app: tauri::AppHandle) -> Result<Option<TauriPluginUpdate>,
 pub enum UpdateEvent {
 	Loading,
 	Error(String),
-	UpdateAvailable { update: Update },
+	UpdateAvailable {
+		update: Update,
+	},
 	NoUpdateAvailable,
+	Downloading {
+		downloaded: u64,
+		total: Option<u64>,
+	},
+	Downloaded {
+		update: Update,
+	},
+	/// The downloaded update will be installed when the app quits
+	InstallScheduled {
+		update: Update,
+	},
 	Installing,
 }
 
@@ -69,6 +179,64 @@ pub async fn check_for_update(app: tauri::AppHandle) -> Result<Option<Update>, S
 	Ok(update)
 }
 
+/// Downloads the latest update without installing it, emitting progress on the `updater` event.
+#[tauri::command]
+#[specta::specta]
+pub async fn download_update(
+	app: tauri::AppHandle,
+	state: tauri::State<'_, State>,
+) -> Result<Update, String> {
+	let lock = match state.install_lock.try_lock() {
+		Ok(lock) => lock,
+		Err(_) => return Err("Update already downloading or installing".into()),
+	};
+
+	let update = download(&app, &state).await;
+	if let Err(e) = &update {
+		app.emit("updater", UpdateEvent::Error(e.clone())).ok();
+	}
+
+	drop(lock);
+
+	update
+}
+
+async fn download(app: &tauri::AppHandle, state: &State) -> Result<Update, String> {
+	if let Some(downloaded) = &*state.downloaded.lock().await {
+		return Ok(Update::new(&downloaded.update));
+	}
+
+	let update = get_update(app.clone())
+		.await?
+		.ok_or_else(|| "No update required".to_string())?;
+
+	let mut downloaded = 0u64;
+	let bytes = update
+		.download(
+			|chunk_length, total| {
+				downloaded += chunk_length as u64;
+				app.emit("updater", UpdateEvent::Downloading { downloaded, total })
+					.ok();
+			},
+			|| {},
+		)
+		.await
+		.map_err(|e| e.to_string())?;
+
+	let info = Update::new(&update);
+	app.emit(
+		"updater",
+		UpdateEvent::Downloaded {
+			update: info.clone(),
+		},
+	)
+	.ok();
+
+	*state.downloaded.lock().await = Some(DownloadedUpdate { update, bytes });
+
+	Ok(info)
+}
+
 #[tauri::command]
 #[specta::specta]
 pub async fn install_update(
@@ -82,18 +250,66 @@ pub async fn install_update(
 
 	app.emit("updater", UpdateEvent::Installing).ok();
 
-	get_update(app.clone())
-		.await?
-		.ok_or_else(|| "No update required".to_string())?
-		.download_and_install(|_, _| {}, || {})
-		.await
-		.map_err(|e| e.to_string())?;
+	let downloaded = state.downloaded.lock().await.take();
+	match downloaded {
+		// Reuse what `download_update` already fetched
+		Some(DownloadedUpdate { update, bytes }) => {
+			update.install(bytes).map_err(|e| e.to_string())?
+		}
+		None => get_update(app.clone())
+			.await?
+			.ok_or_else(|| "No update required".to_string())?
+			.download_and_install(|_, _| {}, || {})
+			.await
+			.map_err(|e| e.to_string())?,
+	}
 
 	drop(lock);
 
 	Ok(())
 }
 
+/// Defers installation of the downloaded update until the app quits, downloading it first if needed.
+#[tauri::command]
+#[specta::specta]
+pub async fn install_update_on_quit(
+	app: tauri::AppHandle,
+	state: tauri::State<'_, State>,
+	enabled: bool,
+) -> Result<(), String> {
+	if !enabled {
+		state.install_on_quit.store(false, Ordering::Relaxed);
+		return Ok(());
+	}
+
+	let update = download_update(app.clone(), state.clone()).await?;
+	state.install_on_quit.store(true, Ordering::Relaxed);
+
+	app.emit("updater", UpdateEvent::InstallScheduled { update })
+		.ok();
+
+	Ok(())
+}
+
+fn install_on_exit<R: Runtime>(app: &tauri::AppHandle<R>) {
+	let state = app.state::<State>();
+	if !state.install_on_quit.load(Ordering::Relaxed) {
+		return;
+	}
+
+	// Don't block the exit on an install that is already running
+	let Ok(mut downloaded) = state.downloaded.try_lock() else {
+		return;
+	};
+
+	if let Some(DownloadedUpdate { update, bytes }) = downloaded.take() {
+		info!(version = %update.version, "Installing deferred update before quitting;");
+		if let Err(e) = update.install(bytes) {
+			error!(?e, "Failed to install deferred update;");
+		}
+	}
+}
+
 pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
 	tauri::plugin::Builder::new("sd-updater")
 		.on_page_load(|window, _| {
@@ -109,9 +325,77 @@ pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
 					.expect("Failed to inject updater JS");
 			}
 		})
+		.on_event(|app, e| {
+			if let RunEvent::Exit = e {
+				install_on_exit(app);
+			}
+		})
 		.js_init_script(format!(
 			r#"window.__SD_DESKTOP_VERSION__ = "{}";"#,
 			env!("CARGO_PKG_VERSION")
 		))
 		.build()
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use serde_json::json;
+
+	#[test]
+	fn releases_without_rollout_are_for_everyone() {
+		assert_eq!(rollout(&json!({ "version": "0.5.0" })), None);
+		assert_eq!(
+			rollout(&json!({ "version": "0.5.0", "rollout": 25 })),
+			Some(25)
+		);
+		assert_eq!(
+			rollout(&json!({ "version": "0.5.0", "rollout": "25" })),
+			None
+		);
+	}
+
+	#[test]
+	fn rollout_bucket_is_kept_across_launches() {
+		let dir = std::env::temp_dir().join(format!("sd-updater-{}", uuid::Uuid::new_v4()));
+		let path = dir.join(ROLLOUT_BUCKET_FILE);
+
+		tauri::async_runtime::block_on(async {
+			let bucket = read_or_create_rollout_bucket(path.clone()).await;
+			assert!(bucket < 100);
+			assert_eq!(read_or_create_rollout_bucket(path.clone()).await, bucket);
+
+			// Out of range buckets are replaced
+			tokio::fs::write(&path, "250").await.unwrap();
+			assert!(read_or_create_rollout_bucket(path.clone()).await < 100);
+		});
+
+		std::fs::remove_dir_all(dir).ok();
+	}
+
+	#[test]
+	fn update_events_are_tagged_by_status() {
+		let update = Update {
+			version: "0.5.0".to_string(),
+			changelog: Some("Fixes".to_string()),
+			date: None,
+		};
+
+		assert_eq!(
+			serde_json::to_value(UpdateEvent::InstallScheduled { update }).unwrap(),
+			json!({
+				"status": "installScheduled",
+				"update": { "version": "0.5.0", "changelog": "Fixes", "date": null }
+			})
+		);
+		assert_eq!(
+			serde_json::to_value(UpdateEvent::Downloading {
+				downloaded: 10,
+				total: None
+			})
+			.unwrap(),
+			json!({ "status": "downloading", "downloaded": 10, "total": null })
+		);
+	}
+}
@@ -33,6 +33,7 @@ import Passwordless from 'supertokens-web-js/recipe/passwordless';
 import Session from 'supertokens-web-js/recipe/session';
 import ThirdParty from 'supertokens-web-js/recipe/thirdparty';
 import { explorerStore } from '@sd/interface/app/$libraryId/Explorer/store';
+import i18n from '@sd/interface/app/I18n';
 // TODO: Bring this back once upstream is fixed up.
 // const client = hooks.createClient({
 // 	links: [
@@ -48,7 +49,7 @@ import { useLocale } from '@sd/interface/hooks';
 import { AUTH_SERVER_URL, getTokens } from '@sd/interface/util';
 
 import { Transparent } from '../../../packages/assets/images';
-import { commands } from './commands';
+import { commands, events } from './commands';
 import { platform } from './platform';
 import { queryClient } from './query';
 import { createMemoryRouterWithHistory } from './router';
@@ -184,26 +185,27 @@ export default function App() {
 		const keybindListener = listen('keybind', (input) => {
 			document.dispatchEvent(new KeybindEvent(input.payload as string));
 		});
-		const deeplinkListener = listen('deeplink', async (data) => {
-			const payload = (data.payload as any).data as string;
-			if (!payload) return;
-			const json = JSON.parse(payload)[0];
-			if (!json) return;
-			//json output: "spacedrive://-/URL"
-			if (typeof json !== 'string') return;
-			if (!json.startsWith('spacedrive://-')) return;
-			const url = (json as string).split('://-/')[1];
-			if (!url) return;
-			document.dispatchEvent(new DeeplinkEvent(url));
+		// The backend parses and resolves `overdrive://` links, we only get the route to navigate to
+		const deeplinkListener = listen<{ data: string }>('deeplink', async ({ payload }) => {
+			if (!payload?.data) return;
+			document.dispatchEvent(new DeeplinkEvent(payload.data));
 		});
 		const fileDropListener = listen('tauri://drag-drop', async (data) => {
 			document.dispatchEvent(new FileDropEvent((data.payload as { paths: string[] }).paths));
 		});
+		// The language is picked by the backend, which follows the OS unless it was overridden
+		commands.getLocaleSettings().then((result) => {
+			if (result.status === 'ok') changeLanguage(result.data.current);
+		});
+		const localeListener = events.localeChangedEvent.listen(({ payload }) =>
+			changeLanguage(payload.locale)
+		);
 
 		return () => {
 			keybindListener.then((unlisten) => unlisten());
 			deeplinkListener.then((unlisten) => unlisten());
 			fileDropListener.then((unlisten) => unlisten());
+			localeListener.then((unlisten) => unlisten());
 		};
 	}, []);
 
@@ -223,6 +225,13 @@ export default function App() {
 	);
 }
 
+function changeLanguage(locale: string) {
+	if (i18n.language === locale) return;
+	// Keep the browser language detector in line for the next startup
+	localStorage.setItem('i18nextLng', locale);
+	i18n.changeLanguage(locale);
+}
+
 // we have a minimum delay between creating new tabs as react router can't handle creating tabs super fast
 const TAB_CREATE_DELAY = 150;
 
@@ -41,6 +41,71 @@ export const commands = {
 	async requestFdaMacos(): Promise<void> {
 		await TAURI_INVOKE('request_fda_macos');
 	},
+	async getAutostartSettings(): Promise<Result<AutostartSettings, string>> {
+		try {
+			return { status: 'ok', data: await TAURI_INVOKE('get_autostart_settings') };
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
+	async setAutostartEnabled(enabled: boolean): Promise<Result<null, string>> {
+		try {
+			return { status: 'ok', data: await TAURI_INVOKE('set_autostart_enabled', { enabled }) };
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
+	async setStartMinimized(startMinimized: boolean): Promise<Result<null, string>> {
+		try {
+			return {
+				status: 'ok',
+				data: await TAURI_INVOKE('set_start_minimized', { startMinimized })
+			};
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
+	async getLocaleSettings(): Promise<Result<LocaleSettings, null>> {
+		try {
+			return { status: 'ok', data: await TAURI_INVOKE('get_locale_settings') };
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
+	/**
+	 * Switches the app language, `None` goes back to following the OS
+	 */
+	async setLocale(preferred: string | null): Promise<Result<LocaleSettings, string>> {
+		try {
+			return { status: 'ok', data: await TAURI_INVOKE('set_locale', { preferred }) };
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
+	async getOfflineMode(): Promise<Result<boolean, null>> {
+		try {
+			return { status: 'ok', data: await TAURI_INVOKE('get_offline_mode') };
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
+	/**
+	 * Suspends or resumes p2p, cloud services and update checks
+	 */
+	async setOfflineMode(offline: boolean): Promise<Result<null, string>> {
+		try {
+			return { status: 'ok', data: await TAURI_INVOKE('set_offline_mode', { offline }) };
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
 	async openTrashInOsExplorer(): Promise<Result<null, null>> {
 		try {
 			return { status: 'ok', data: await TAURI_INVOKE('open_trash_in_os_explorer') };
@@ -49,6 +114,34 @@ export const commands = {
 			else return { status: 'error', error: e as any };
 		}
 	},
+	/**
+	 * Puts `paths` on the OS clipboard as files
+	 */
+	async writeFilesToClipboard(
+		paths: string[],
+		operation: ClipboardOperation
+	): Promise<Result<null, string>> {
+		try {
+			return {
+				status: 'ok',
+				data: await TAURI_INVOKE('write_files_to_clipboard', { paths, operation })
+			};
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
+	/**
+	 * Reads the files currently on the OS clipboard, if any
+	 */
+	async readFilesFromClipboard(): Promise<Result<ClipboardFiles | null, string>> {
+		try {
+			return { status: 'ok', data: await TAURI_INVOKE('read_files_from_clipboard') };
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
 	/**
 	 * Initiates a drag and drop operation with cursor position tracking
 	 *
@@ -159,6 +252,94 @@ export const commands = {
 			else return { status: 'error', error: e as any };
 		}
 	},
+	/**
+	 * Shows the native preview for `paths`: the Quick Look panel on macOS,
+	 * GNOME Sushi or QuickLook for Windows when installed, the default app otherwise.
+	 */
+	async quickLook(paths: string[]): Promise<Result<null, string>> {
+		try {
+			return { status: 'ok', data: await TAURI_INVOKE('quick_look', { paths }) };
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
+	async getFileAssociations(): Promise<Result<FileAssociations, string>> {
+		try {
+			return { status: 'ok', data: await TAURI_INVOKE('get_file_associations') };
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
+	async registerFileAssociations(extensions: string[]): Promise<Result<null, string>> {
+		try {
+			return {
+				status: 'ok',
+				data: await TAURI_INVOKE('register_file_associations', { extensions })
+			};
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
+	async unregisterFileAssociations(extensions: string[]): Promise<Result<null, string>> {
+		try {
+			return {
+				status: 'ok',
+				data: await TAURI_INVOKE('unregister_file_associations', { extensions })
+			};
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
+	/**
+	 * Makes folders open with us, e.g. when double clicking them on the desktop
+	 */
+	async setFolderHandler(enabled: boolean): Promise<Result<null, string>> {
+		try {
+			return { status: 'ok', data: await TAURI_INVOKE('set_folder_handler', { enabled }) };
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
+	async systemInfo(): Promise<Result<SystemInfo, string>> {
+		try {
+			return { status: 'ok', data: await TAURI_INVOKE('system_info') };
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
+	async getTerminalSettings(): Promise<Result<TerminalSettings, string>> {
+		try {
+			return { status: 'ok', data: await TAURI_INVOKE('get_terminal_settings') };
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
+	async setPreferredTerminal(id: string | null): Promise<Result<null, string>> {
+		try {
+			return { status: 'ok', data: await TAURI_INVOKE('set_preferred_terminal', { id }) };
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
+	/**
+	 * Opens the preferred terminal in `path`, or in its parent folder if it's a file
+	 */
+	async openTerminal(path: string): Promise<Result<null, string>> {
+		try {
+			return { status: 'ok', data: await TAURI_INVOKE('open_terminal', { path }) };
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
 	async lockAppTheme(themeType: AppThemeType): Promise<void> {
 		await TAURI_INVOKE('lock_app_theme', { themeType });
 	},
@@ -170,6 +351,17 @@ export const commands = {
 			else return { status: 'error', error: e as any };
 		}
 	},
+	/**
+	 * Downloads the latest update without installing it, emitting progress on the `updater` event.
+	 */
+	async downloadUpdate(): Promise<Result<Update, string>> {
+		try {
+			return { status: 'ok', data: await TAURI_INVOKE('download_update') };
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
+	},
 	async installUpdate(): Promise<Result<null, string>> {
 		try {
 			return { status: 'ok', data: await TAURI_INVOKE('install_update') };
@@ -177,6 +369,17 @@ export const commands = {
 			if (e instanceof Error) throw e;
 			else return { status: 'error', error: e as any };
 		}
+	},
+	/**
+	 * Defers installation of the downloaded update until the app quits, downloading it first if needed.
+	 */
+	async installUpdateOnQuit(enabled: boolean): Promise<Result<null, string>> {
+		try {
+			return { status: 'ok', data: await TAURI_INVOKE('install_update_on_quit', { enabled }) };
+		} catch (e) {
+			if (e instanceof Error) throw e;
+			else return { status: 'error', error: e as any };
+		}
 	}
 };
 
@@ -184,8 +387,12 @@ export const commands = {
 
 export const events = __makeEvents__<{
 	dragAndDropEvent: DragAndDropEvent;
+	localeChangedEvent: LocaleChangedEvent;
+	offlineModeChangedEvent: OfflineModeChangedEvent;
 }>({
-	dragAndDropEvent: 'drag-and-drop-event'
+	dragAndDropEvent: 'drag-and-drop-event',
+	localeChangedEvent: 'locale-changed-event',
+	offlineModeChangedEvent: 'offline-mode-changed-event'
 });
 
 /** user-defined constants **/
@@ -193,12 +400,82 @@ export const events = __makeEvents__<{
 /** user-defined types **/
 
 export type AppThemeType = 'Auto' | 'Light' | 'Dark';
+export type AutostartSettings = {
+	/**
+	 * Whether the app is registered to launch at login
+	 */
+	enabled: boolean;
+	/**
+	 * Whether launching at login keeps the window hidden, with only a tray icon
+	 */
+	startMinimized: boolean;
+};
 export type CallbackResult = { result: WrappedDragResult; cursorPos: WrappedCursorPosition };
+export type ClipboardFiles = { operation: ClipboardOperation; paths: string[] };
+export type ClipboardOperation = 'Copy' | 'Cut';
+export type CpuInfo = { brand: string | null; physicalCores: number | null; logicalCores: number };
+export type DiskPermission = 'FullDisk' | 'RemovableVolumes';
+export type DiskPermissionStatus = 'Granted' | 'Denied' | 'Unknown' | 'NotRequired';
+export type DiskSummary = {
+	name: string;
+	mountPoint: string;
+	fileSystem: string;
+	diskType: string;
+	totalBytes: string;
+	availableBytes: string;
+	readOnly: boolean;
+};
 export type DragAndDropEvent =
 	| { type: 'Hovered'; paths: string[]; x: number; y: number }
 	| { type: 'Dropped'; paths: string[]; x: number; y: number }
 	| { type: 'Cancelled' };
 export type EphemeralFileOpenResult = { t: 'Ok'; c: string } | { t: 'Err'; c: string };
+export type FileAssociation = {
+	extension: string;
+	kind: FileAssociationKind;
+	/**
+	 * Whether we registered ourselves for this file type
+	 */
+	registered: boolean;
+	/**
+	 * Whether files of this type currently open with us
+	 */
+	isDefault: boolean;
+	/**
+	 * The app which opens files of this type, if it's not us. Registering would take over from it.
+	 */
+	conflict: string | null;
+};
+export type FileAssociationKind = 'Image' | 'Video' | 'Audio' | 'Document';
+export type FileAssociations = {
+	fileTypes: FileAssociation[];
+	/**
+	 * Whether folders open with us
+	 */
+	folderHandler: boolean;
+	/**
+	 * On platforms where apps can't make themselves the default (Windows), registering
+	 * only offers us as a choice and the user has to pick us in the system settings.
+	 */
+	requiresUserConfirmation: boolean;
+};
+export type LocaleChangedEvent = { locale: string };
+export type LocaleSettings = {
+	/**
+	 * The locale reported by the OS, as is
+	 */
+	system: string | null;
+	/**
+	 * The locale picked by the user, the system one is followed when unset
+	 */
+	preferred: string | null;
+	/**
+	 * The locale the app is displayed in
+	 */
+	current: string;
+	available: string[];
+};
+export type MemoryInfo = { totalBytes: string; availableBytes: string };
 export type MenuEvent =
 	| 'NewLibrary'
 	| 'NewFile'
@@ -218,7 +495,11 @@ export type MenuEvent =
 	| 'Cut'
 	| 'Paste'
 	| 'Duplicate'
-	| 'SelectAll';
+	| 'SelectAll'
+	| 'QuickLook'
+	| 'OpenTerminal'
+	| 'ToggleOfflineMode';
+export type OfflineModeChangedEvent = { offline: boolean };
 export type OpenFilePathResult =
 	| { t: 'NoLibrary' }
 	| { t: 'NoFile'; c: number }
@@ -226,11 +507,50 @@ export type OpenFilePathResult =
 	| { t: 'AllGood'; c: number }
 	| { t: 'Internal'; c: string };
 export type OpenWithApplication = { url: string; name: string };
+export type OsInfo = {
+	name: string | null;
+	version: string | null;
+	kernelVersion: string | null;
+	arch: string;
+};
 export type RevealItem =
 	| { Location: { id: number } }
 	| { FilePath: { id: number } }
 	| { Ephemeral: { path: string } };
-export type Update = { version: string };
+export type SystemInfo = {
+	appVersion: string;
+	os: OsInfo;
+	cpu: CpuInfo;
+	memory: MemoryInfo;
+	/**
+	 * Names of the graphics adapters, empty if they couldn't be detected
+	 */
+	gpus: string[];
+	disks: DiskSummary[];
+	permissions: { [key in DiskPermission]: DiskPermissionStatus };
+};
+export type Terminal = { id: string; name: string };
+export type TerminalSettings = {
+	/**
+	 * Terminals detected on this system, in order of preference
+	 */
+	available: Terminal[];
+	/**
+	 * The terminal picked by the user, the first available one is used when unset
+	 */
+	preferred: string | null;
+};
+export type Update = {
+	version: string;
+	/**
+	 * Release notes for the update, as published in the release feed
+	 */
+	changelog: string | null;
+	/**
+	 * Release date of the update in RFC 3339 format
+	 */
+	date: string | null;
+};
 export type WrappedCursorPosition = { x: number; y: number };
 export type WrappedDragResult = 'Dropped' | 'Cancel';
 
@@ -1,4 +1,4 @@
-import { invoke } from '@tauri-apps/api/core';
+import { convertFileSrc, invoke } from '@tauri-apps/api/core';
 import { homeDir } from '@tauri-apps/api/path';
 import { confirm, open as dialogOpen, save as dialogSave } from '@tauri-apps/plugin-dialog';
 import { type } from '@tauri-apps/plugin-os';
@@ -45,11 +45,11 @@ function constructServerUrl(urlSuffix: string) {
 
 export const platform = {
 	platform: 'tauri',
+	// Served by the `odthumb` protocol registered in `asset_protocol.rs`
 	getThumbnailUrlByThumbKey: (thumbKey) =>
-		constructServerUrl(
-			`/thumbnail/${encodeURIComponent(
-				thumbKey.base_directory_str
-			)}/${encodeURIComponent(thumbKey.shard_hex)}/${encodeURIComponent(thumbKey.cas_id)}.webp`
+		convertFileSrc(
+			`${thumbKey.base_directory_str}/${thumbKey.shard_hex}/${thumbKey.cas_id}.webp`,
+			'odthumb'
 		),
 	getFileUrl: (libraryId, locationLocalId, filePathId) =>
 		constructServerUrl(`/file/${libraryId}/${locationLocalId}/${filePathId}`),
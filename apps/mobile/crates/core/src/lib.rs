@@ -0,0 +1,131 @@
+//! FFI surface of the core for the native iOS and Android shells, generated with uniffi.
+//!
+//! The shells detect storage themselves, through the platform APIs the core can't reach from a
+//! sandbox, and hand it over by implementing [`VolumeProvider`]. On Android the provider can pass
+//! what `StorageManager` lists through [`android_volumes`]. The shells get the volume events back
+//! through a [`VolumeEventListener`].
+//!
+//! Bindings are generated from the built library:
+//! `cargo run -p sd-mobile-core --bin uniffi-bindgen generate --library <lib> --language swift`.
+
+use sd_core::{api::volumes::VolumeError, Node};
+
+use std::sync::Arc;
+
+use tokio::runtime::Handle;
+use tracing::info;
+
+mod volumes;
+
+pub use volumes::{
+	android_volumes, AndroidStorageVolume, MobileDiskType, MobileMountType, MobileVolume,
+	MobileVolumeEvent, VolumeEventListener, VolumeProvider, VolumeSubscription,
+};
+
+uniffi::setup_scaffolding!();
+
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+pub enum MobileError {
+	#[error("Failed to start the core: {message}")]
+	Startup { message: String },
+	/// `code` is one of the stable volume error codes, like `NOT_MOUNTED`
+	#[error("{code}: {message}")]
+	Volume { code: String, message: String },
+	/// A call into the native shell failed
+	#[error("Native callback failed: {message}")]
+	Callback { message: String },
+}
+
+impl From<uniffi::UnexpectedUniFFICallbackError> for MobileError {
+	fn from(e: uniffi::UnexpectedUniFFICallbackError) -> Self {
+		Self::Callback { message: e.reason }
+	}
+}
+
+impl From<VolumeError> for MobileError {
+	fn from(e: VolumeError) -> Self {
+		Self::Volume {
+			code: e.code().to_string(),
+			message: e.to_string(),
+		}
+	}
+}
+
+/// A running node, there should be one per app
+#[derive(uniffi::Object)]
+pub struct Core {
+	node: Arc<Node>,
+	platform: Arc<volumes::ForeignPlatform>,
+	/// For the tasks started from the calls that aren't async
+	runtime: Handle,
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+impl Core {
+	/// Starts the node in `data_dir`, with the volumes reported by `provider`
+	#[uniffi::constructor]
+	pub async fn start(
+		data_dir: String,
+		provider: Arc<dyn VolumeProvider>,
+	) -> Result<Arc<Self>, MobileError> {
+		let platform = Arc::new(volumes::ForeignPlatform::new(provider));
+
+		let (node, _router) = Node::new_with_volume_platform(&data_dir, platform.clone())
+			.await
+			.map_err(|e| MobileError::Startup {
+				message: e.to_string(),
+			})?;
+
+		info!("Mobile core started;");
+
+		Ok(Arc::new(Self {
+			node,
+			platform,
+			runtime: Handle::current(),
+		}))
+	}
+
+	/// To call whenever the storage changes, the core asks the provider for the volumes again
+	pub fn volumes_changed(&self) {
+		self.platform.notify();
+	}
+
+	pub async fn list_volumes(&self) -> Result<Vec<MobileVolume>, MobileError> {
+		Ok(self
+			.node
+			.volumes
+			.list_volumes()
+			.await?
+			.into_iter()
+			.map(MobileVolume::from)
+			.collect())
+	}
+
+	/// Unmounts through the provider the volume mounted at `mount_point`
+	pub async fn unmount_volume(&self, mount_point: String) -> Result<(), MobileError> {
+		let fingerprint = self
+			.node
+			.volumes
+			.list_volumes()
+			.await?
+			.into_iter()
+			.find(|volume| volume.mount_point.as_os_str() == mount_point.as_str())
+			.and_then(|volume| volume.fingerprint)
+			.ok_or_else(|| VolumeError::NotMounted(mount_point.into()))?;
+
+		Ok(self.node.volumes.unmount_volume(fingerprint).await?)
+	}
+
+	/// Sends the volume events to `listener`, starting with the volumes currently present
+	pub fn subscribe(&self, listener: Arc<dyn VolumeEventListener>) -> Arc<VolumeSubscription> {
+		VolumeSubscription::start(
+			&self.runtime,
+			self.node.volumes.subscribe().into_stream(),
+			listener,
+		)
+	}
+
+	pub async fn shutdown(&self) {
+		self.node.shutdown().await;
+	}
+}
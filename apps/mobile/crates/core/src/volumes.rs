@@ -0,0 +1,685 @@
+//! Volumes as seen by the native shells, and the glue turning their provider into a platform for
+//! the volume manager.
+
+use crate::MobileError;
+
+use sd_core::api::volumes::{
+	DiskType, FileSystem, HealthStatus, LowSpaceThreshold, MountType, Volume, VolumeError,
+	VolumeEvent, VolumePlatform, WatcherBackend,
+};
+
+use std::{
+	fmt,
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use tokio::{runtime::Handle, sync::mpsc, task::JoinHandle};
+use tracing::warn;
+
+/// The `StorageVolume.getState` of the volumes Android lets apps read and write
+const STATE_MOUNTED: &str = "mounted";
+const STATE_MOUNTED_READ_ONLY: &str = "mounted_ro";
+
+/// Where Android mounts the volumes, each in a directory named after its uuid
+const ANDROID_STORAGE_DIR: &str = "/storage";
+
+/// Implemented by the shells, on top of the storage APIs of the platform
+#[uniffi::export(with_foreign)]
+pub trait VolumeProvider: Send + Sync {
+	/// Every volume currently available to the app
+	fn volumes(&self) -> Result<Vec<MobileVolume>, MobileError>;
+
+	fn unmount(&self, mount_point: String) -> Result<(), MobileError>;
+}
+
+#[uniffi::export(with_foreign)]
+pub trait VolumeEventListener: Send + Sync {
+	fn on_event(&self, event: MobileVolumeEvent);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum MobileMountType {
+	System,
+	External,
+	Network,
+	Virtual,
+	DiskImage,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum MobileDiskType {
+	Ssd,
+	Hdd,
+	Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum MobileHealthStatus {
+	Failing,
+	Warning,
+	Healthy,
+	Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum MobileLowSpaceThreshold {
+	Percentage { percent: u8 },
+	Bytes { bytes: u64 },
+}
+
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct MobileVolume {
+	/// Set by the core, left empty by providers
+	pub fingerprint: Option<Vec<u8>>,
+	pub name: String,
+	pub mount_point: String,
+	pub mount_type: MobileMountType,
+	pub disk_type: MobileDiskType,
+	/// Name of the file system, like `apfs` or `ext4`
+	pub file_system: String,
+	pub total_bytes: u64,
+	pub available_bytes: u64,
+	pub read_only: bool,
+	/// The UUID or serial number of the file system, the fingerprint of the volume is derived from
+	/// it when set
+	#[uniffi(default = None)]
+	pub serial: Option<String>,
+}
+
+/// A volume as the `StorageManager` of Android describes it, from `getStorageVolumes`. The
+/// Kotlin shell hands them to [`android_volumes`] to report them from its provider.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct AndroidStorageVolume {
+	/// Like `1234-ABCD` for SD cards and USB drives, missing for the primary storage
+	pub uuid: Option<String>,
+	/// Shown to the user, like `SanDisk SD card`
+	pub description: String,
+	/// Missing for the volumes that aren't mounted, and before Android 11: the primary storage is
+	/// left out there unless given `Environment.getExternalStorageDirectory`
+	pub directory: Option<String>,
+	pub is_removable: bool,
+	/// Like `mounted`, `mounted_ro` or `unmounted`
+	pub state: String,
+	/// From `StatFs` on the directory, 0 when it can't be read
+	pub total_bytes: u64,
+	pub available_bytes: u64,
+}
+
+/// The volumes Android apps can use, out of what `StorageManager` lists. The primary storage
+/// is the system volume, the removable ones are external.
+#[uniffi::export]
+pub fn android_volumes(volumes: Vec<AndroidStorageVolume>) -> Vec<MobileVolume> {
+	volumes
+		.into_iter()
+		.filter_map(|volume| {
+			let read_only = match volume.state.as_str() {
+				STATE_MOUNTED => false,
+				STATE_MOUNTED_READ_ONLY => true,
+				// Being checked, ejected or unreadable
+				_ => return None,
+			};
+			// The directory is only exposed from Android 11, before it the volumes are mounted
+			// after their uuid
+			let mount_point = volume.directory.or_else(|| {
+				volume
+					.uuid
+					.as_ref()
+					.map(|uuid| format!("{ANDROID_STORAGE_DIR}/{uuid}"))
+			})?;
+
+			Some(MobileVolume {
+				fingerprint: None,
+				name: volume.description,
+				mount_point,
+				mount_type: if volume.is_removable {
+					MobileMountType::External
+				} else {
+					MobileMountType::System
+				},
+				disk_type: MobileDiskType::Unknown,
+				// Android doesn't tell, the SD cards and USB drives are usually FAT or exFAT
+				file_system: String::new(),
+				total_bytes: volume.total_bytes,
+				available_bytes: volume.available_bytes,
+				read_only,
+				serial: volume.uuid,
+			})
+		})
+		.collect()
+}
+
+#[derive(Debug, Clone, uniffi::Enum)]
+pub enum MobileVolumeEvent {
+	Added {
+		volume: MobileVolume,
+	},
+	Removed {
+		volume: MobileVolume,
+	},
+	Updated {
+		old: MobileVolume,
+		new: MobileVolume,
+	},
+	Remounted {
+		fingerprint: Vec<u8>,
+		read_only: bool,
+	},
+	SpaceChanged {
+		fingerprint: Vec<u8>,
+		available_bytes: u64,
+	},
+	SpeedTested {
+		fingerprint: Vec<u8>,
+		read_speed: u64,
+		write_speed: u64,
+	},
+	MountChanged {
+		fingerprint: Vec<u8>,
+		is_mounted: bool,
+	},
+	Error {
+		fingerprint: Vec<u8>,
+		code: String,
+		message: String,
+	},
+	FillingUp {
+		fingerprint: Vec<u8>,
+		name: String,
+		days_until_full: u32,
+	},
+	HealthChanged {
+		fingerprint: Vec<u8>,
+		name: String,
+		status: MobileHealthStatus,
+		temperature_celsius: Option<i32>,
+		reallocated_sectors: Option<u64>,
+	},
+	/// Quarantined after too many I/O errors in a row, the jobs on it were paused
+	Degraded {
+		volume: MobileVolume,
+		errors: u32,
+	},
+	LowSpace {
+		volume: MobileVolume,
+		threshold: MobileLowSpaceThreshold,
+	},
+}
+
+impl From<LowSpaceThreshold> for MobileLowSpaceThreshold {
+	fn from(threshold: LowSpaceThreshold) -> Self {
+		match threshold {
+			LowSpaceThreshold::Percentage(percent) => Self::Percentage { percent },
+			LowSpaceThreshold::Bytes(bytes) => Self::Bytes { bytes: bytes.0 },
+		}
+	}
+}
+
+impl From<HealthStatus> for MobileHealthStatus {
+	fn from(status: HealthStatus) -> Self {
+		match status {
+			HealthStatus::Failing => Self::Failing,
+			HealthStatus::Warning => Self::Warning,
+			HealthStatus::Healthy => Self::Healthy,
+			HealthStatus::Unknown => Self::Unknown,
+		}
+	}
+}
+
+impl From<MountType> for MobileMountType {
+	fn from(mount_type: MountType) -> Self {
+		match mount_type {
+			MountType::System => Self::System,
+			MountType::External => Self::External,
+			MountType::Network => Self::Network,
+			MountType::Virtual => Self::Virtual,
+			MountType::DiskImage => Self::DiskImage,
+		}
+	}
+}
+
+impl From<MobileMountType> for MountType {
+	fn from(mount_type: MobileMountType) -> Self {
+		match mount_type {
+			MobileMountType::System => Self::System,
+			MobileMountType::External => Self::External,
+			MobileMountType::Network => Self::Network,
+			MobileMountType::Virtual => Self::Virtual,
+			MobileMountType::DiskImage => Self::DiskImage,
+		}
+	}
+}
+
+impl From<DiskType> for MobileDiskType {
+	fn from(disk_type: DiskType) -> Self {
+		match disk_type {
+			DiskType::SSD => Self::Ssd,
+			// Only Macs have them
+			DiskType::HDD | DiskType::Fusion => Self::Hdd,
+			DiskType::Unknown => Self::Unknown,
+		}
+	}
+}
+
+impl From<MobileDiskType> for DiskType {
+	fn from(disk_type: MobileDiskType) -> Self {
+		match disk_type {
+			MobileDiskType::Ssd => Self::SSD,
+			MobileDiskType::Hdd => Self::HDD,
+			MobileDiskType::Unknown => Self::Unknown,
+		}
+	}
+}
+
+impl From<Volume> for MobileVolume {
+	fn from(volume: Volume) -> Self {
+		Self {
+			fingerprint: volume.fingerprint.map(|fingerprint| fingerprint.0),
+			name: volume.name,
+			mount_point: volume.mount_point.to_string_lossy().to_string(),
+			mount_type: volume.mount_type.into(),
+			disk_type: volume.disk_type.into(),
+			file_system: volume.file_system.to_string(),
+			total_bytes: volume.total_bytes_capacity.0,
+			available_bytes: volume.total_bytes_available.0,
+			read_only: volume.read_only,
+			serial: volume.serial,
+		}
+	}
+}
+
+impl From<MobileVolume> for Volume {
+	fn from(volume: MobileVolume) -> Self {
+		let mount_point = PathBuf::from(volume.mount_point);
+		let mut converted = Volume::new(
+			volume.name,
+			volume.mount_type.into(),
+			mount_point.clone(),
+			vec![mount_point],
+			volume.disk_type.into(),
+			FileSystem::from_string(&volume.file_system),
+			volume.total_bytes,
+			volume.available_bytes,
+			volume.read_only,
+		);
+		converted.serial = volume.serial;
+
+		converted
+	}
+}
+
+impl From<VolumeEvent> for MobileVolumeEvent {
+	fn from(event: VolumeEvent) -> Self {
+		match event {
+			VolumeEvent::VolumeAdded(volume) => Self::Added {
+				volume: volume.into(),
+			},
+			VolumeEvent::VolumeRemoved(volume) => Self::Removed {
+				volume: volume.into(),
+			},
+			VolumeEvent::VolumeUpdated { old, new } => Self::Updated {
+				old: old.into(),
+				new: new.into(),
+			},
+			VolumeEvent::VolumeRemounted {
+				fingerprint,
+				read_only,
+			} => Self::Remounted {
+				fingerprint: fingerprint.0,
+				read_only,
+			},
+			VolumeEvent::VolumeSpaceChanged {
+				fingerprint,
+				total_bytes_available,
+			} => Self::SpaceChanged {
+				fingerprint: fingerprint.0,
+				available_bytes: total_bytes_available.0,
+			},
+			VolumeEvent::VolumeSpeedTested {
+				fingerprint,
+				read_speed,
+				write_speed,
+			} => Self::SpeedTested {
+				fingerprint: fingerprint.0,
+				read_speed,
+				write_speed,
+			},
+			VolumeEvent::VolumeMountChanged {
+				fingerprint,
+				is_mounted,
+			} => Self::MountChanged {
+				fingerprint: fingerprint.0,
+				is_mounted,
+			},
+			VolumeEvent::VolumeError { fingerprint, error } => Self::Error {
+				fingerprint: fingerprint.0,
+				code: error.code.to_string(),
+				message: error.message,
+			},
+			VolumeEvent::VolumeFillingUp {
+				fingerprint,
+				name,
+				days_until_full,
+			} => Self::FillingUp {
+				fingerprint: fingerprint.0,
+				name,
+				days_until_full,
+			},
+			VolumeEvent::VolumeHealthChanged {
+				fingerprint,
+				name,
+				health,
+			} => Self::HealthChanged {
+				fingerprint: fingerprint.0,
+				name,
+				status: health.status.into(),
+				temperature_celsius: health.temperature_celsius,
+				reallocated_sectors: health.reallocated_sectors,
+			},
+			VolumeEvent::VolumeDegraded { volume, errors } => Self::Degraded {
+				volume: volume.into(),
+				errors,
+			},
+			VolumeEvent::LowSpace { volume, threshold } => Self::LowSpace {
+				volume: volume.into(),
+				threshold: threshold.into(),
+			},
+		}
+	}
+}
+
+/// The volume manager's view of the shell's [`VolumeProvider`]
+pub(crate) struct ForeignPlatform {
+	provider: Arc<dyn VolumeProvider>,
+	watchers: Mutex<Vec<mpsc::Sender<()>>>,
+}
+
+impl fmt::Debug for ForeignPlatform {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("ForeignPlatform").finish_non_exhaustive()
+	}
+}
+
+impl ForeignPlatform {
+	pub(crate) fn new(provider: Arc<dyn VolumeProvider>) -> Self {
+		Self {
+			provider,
+			watchers: Mutex::new(vec![]),
+		}
+	}
+
+	/// Wakes the watchers up, forgetting the ones that stopped listening
+	pub(crate) fn notify(&self) {
+		self.watchers
+			.lock()
+			.expect("foreign platform lock poisoned")
+			.retain(|check_tx| {
+				!matches!(
+					check_tx.try_send(()),
+					Err(mpsc::error::TrySendError::Closed(()))
+				)
+			});
+	}
+
+	/// Calls into the shell off the runtime, native code is free to block
+	async fn call<T: Send + 'static>(
+		&self,
+		f: impl FnOnce(&dyn VolumeProvider) -> Result<T, MobileError> + Send + 'static,
+	) -> Result<T, VolumeError> {
+		let provider = Arc::clone(&self.provider);
+		tokio::task::spawn_blocking(move || f(provider.as_ref()))
+			.await?
+			.map_err(|e| VolumeError::DeviceError(e.to_string()))
+	}
+}
+
+#[async_trait]
+impl VolumePlatform for ForeignPlatform {
+	async fn get_volumes(&self) -> Result<Vec<Volume>, VolumeError> {
+		let volumes = self.call(|provider| provider.volumes()).await?;
+		Ok(volumes.into_iter().map(Volume::from).collect())
+	}
+
+	async fn unmount_volume(&self, path: &Path) -> Result<(), VolumeError> {
+		let mount_point = path.to_string_lossy().to_string();
+		self.call(move |provider| provider.unmount(mount_point))
+			.await?;
+
+		// The shell may not report the change itself
+		self.notify();
+
+		Ok(())
+	}
+
+	/// The shell reports the changes itself, polling is left to the watcher
+	async fn watch(
+		&self,
+		backend: WatcherBackend,
+		check_tx: mpsc::Sender<()>,
+	) -> Result<Option<JoinHandle<()>>, VolumeError> {
+		if backend != WatcherBackend::Native {
+			return Err(VolumeError::UnsupportedPlatform {
+				operation: "watching volumes without the shell",
+			});
+		}
+
+		self.watchers
+			.lock()
+			.expect("foreign platform lock poisoned")
+			.push(check_tx);
+
+		Ok(None)
+	}
+}
+
+/// Stops sending events to the listener once cancelled or dropped
+#[derive(uniffi::Object)]
+pub struct VolumeSubscription {
+	task: JoinHandle<()>,
+}
+
+impl VolumeSubscription {
+	pub(crate) fn start(
+		runtime: &Handle,
+		events: impl Stream<Item = VolumeEvent> + Send + 'static,
+		listener: Arc<dyn VolumeEventListener>,
+	) -> Arc<Self> {
+		let task = runtime.spawn(async move {
+			let mut events = std::pin::pin!(events);
+			while let Some(event) = events.next().await {
+				let listener = Arc::clone(&listener);
+				let event = MobileVolumeEvent::from(event);
+
+				if let Err(e) = tokio::task::spawn_blocking(move || listener.on_event(event)).await
+				{
+					warn!(?e, "Volume event listener failed;");
+				}
+			}
+		});
+
+		Arc::new(Self { task })
+	}
+}
+
+#[uniffi::export]
+impl VolumeSubscription {
+	pub fn cancel(&self) {
+		self.task.abort();
+	}
+}
+
+impl Drop for VolumeSubscription {
+	fn drop(&mut self) {
+		self.task.abort();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::time::Duration;
+
+	struct FakeProvider(Vec<MobileVolume>);
+
+	impl VolumeProvider for FakeProvider {
+		fn volumes(&self) -> Result<Vec<MobileVolume>, MobileError> {
+			Ok(self.0.clone())
+		}
+
+		fn unmount(&self, mount_point: String) -> Result<(), MobileError> {
+			if self
+				.0
+				.iter()
+				.any(|volume| volume.mount_point == mount_point)
+			{
+				Ok(())
+			} else {
+				Err(MobileError::Callback {
+					message: format!("{mount_point} isn't mounted"),
+				})
+			}
+		}
+	}
+
+	fn sd_card() -> MobileVolume {
+		MobileVolume {
+			fingerprint: None,
+			name: "SD card".to_string(),
+			mount_point: "/storage/1234-ABCD".to_string(),
+			mount_type: MobileMountType::External,
+			disk_type: MobileDiskType::Unknown,
+			file_system: "exfat".to_string(),
+			total_bytes: 64_000_000_000,
+			available_bytes: 12_000_000_000,
+			read_only: false,
+			serial: Some("1234-ABCD".to_string()),
+		}
+	}
+
+	#[test]
+	fn volumes_keep_what_the_shell_reported() {
+		let volume = MobileVolume::from(Volume::from(sd_card()));
+
+		assert_eq!(volume.name, "SD card");
+		assert_eq!(volume.mount_point, "/storage/1234-ABCD");
+		assert_eq!(volume.mount_type, MobileMountType::External);
+		assert_eq!(volume.disk_type, MobileDiskType::Unknown);
+		assert_eq!(volume.total_bytes, 64_000_000_000);
+		assert_eq!(volume.available_bytes, 12_000_000_000);
+		assert!(!volume.read_only);
+		assert_eq!(volume.serial.as_deref(), Some("1234-ABCD"));
+	}
+
+	fn android_volume(
+		uuid: Option<&str>,
+		directory: Option<&str>,
+		state: &str,
+	) -> AndroidStorageVolume {
+		AndroidStorageVolume {
+			uuid: uuid.map(str::to_string),
+			description: "SanDisk SD card".to_string(),
+			directory: directory.map(str::to_string),
+			is_removable: uuid.is_some(),
+			state: state.to_string(),
+			total_bytes: 64_000_000_000,
+			available_bytes: 12_000_000_000,
+		}
+	}
+
+	#[test]
+	fn android_volumes_are_reported_with_their_directory() {
+		let volumes = android_volumes(vec![
+			android_volume(None, Some("/storage/emulated/0"), "mounted"),
+			android_volume(Some("1234-ABCD"), Some("/storage/1234-ABCD"), "mounted_ro"),
+		]);
+
+		assert_eq!(volumes.len(), 2);
+
+		assert_eq!(volumes[0].mount_point, "/storage/emulated/0");
+		assert_eq!(volumes[0].mount_type, MobileMountType::System);
+		assert!(!volumes[0].read_only);
+		assert_eq!(volumes[0].serial, None);
+
+		assert_eq!(volumes[1].name, "SanDisk SD card");
+		assert_eq!(volumes[1].mount_point, "/storage/1234-ABCD");
+		assert_eq!(volumes[1].mount_type, MobileMountType::External);
+		assert_eq!(volumes[1].disk_type, MobileDiskType::Unknown);
+		assert_eq!(volumes[1].total_bytes, 64_000_000_000);
+		assert_eq!(volumes[1].available_bytes, 12_000_000_000);
+		assert!(volumes[1].read_only);
+		assert_eq!(volumes[1].serial.as_deref(), Some("1234-ABCD"));
+	}
+
+	#[test]
+	fn android_volumes_without_directory_are_mounted_after_their_uuid() {
+		let volumes = android_volumes(vec![
+			android_volume(Some("1234-ABCD"), None, "mounted"),
+			// The primary storage before Android 11, nowhere to find it
+			android_volume(None, None, "mounted"),
+		]);
+
+		assert_eq!(volumes.len(), 1);
+		assert_eq!(volumes[0].mount_point, "/storage/1234-ABCD");
+	}
+
+	#[test]
+	fn android_volumes_that_cant_be_used_are_left_out() {
+		let volumes = android_volumes(
+			[
+				"unmounted",
+				"checking",
+				"ejecting",
+				"bad_removal",
+				"removed",
+				"",
+			]
+			.into_iter()
+			.map(|state| android_volume(Some("1234-ABCD"), Some("/storage/1234-ABCD"), state))
+			.collect(),
+		);
+
+		assert!(volumes.is_empty());
+	}
+
+	#[tokio::test]
+	async fn platform_reports_the_volumes_of_the_shell() {
+		let platform = ForeignPlatform::new(Arc::new(FakeProvider(vec![sd_card()])));
+
+		let volumes = platform.get_volumes().await.unwrap();
+		assert_eq!(volumes.len(), 1);
+		assert_eq!(volumes[0].mount_point, PathBuf::from("/storage/1234-ABCD"));
+
+		assert!(platform
+			.unmount_volume(Path::new("/storage/other"))
+			.await
+			.is_err());
+	}
+
+	#[tokio::test]
+	async fn unmounting_wakes_the_native_watchers_only() {
+		let platform = ForeignPlatform::new(Arc::new(FakeProvider(vec![sd_card()])));
+
+		assert!(platform
+			.watch(WatcherBackend::Poll, mpsc::channel(1).0)
+			.await
+			.is_err());
+
+		let (check_tx, mut check_rx) = mpsc::channel(1);
+		platform
+			.watch(WatcherBackend::Native, check_tx)
+			.await
+			.unwrap();
+		platform
+			.unmount_volume(Path::new("/storage/1234-ABCD"))
+			.await
+			.unwrap();
+
+		tokio::time::timeout(Duration::from_secs(1), check_rx.recv())
+			.await
+			.unwrap()
+			.unwrap();
+	}
+}
@@ -0,0 +1,109 @@
+//! Synthetic mount tables, and a platform provider building volumes out of them instead of asking the OS
+
+use sd_core::api::volumes::{mountinfo, DiskType, FileSystem, MountType, Volume};
+
+use std::{fmt::Write, ops::Range, path::Path};
+
+/// A desktop with a few internal disks, a USB drive, an SD card, a network share and the usual virtual mounts
+///
+/// In `/proc/self/mountinfo` format, the capacities are in [`DESKTOP_CAPACITIES`].
+pub const DESKTOP_MOUNTS: &str = include_str!("mountinfo");
+
+/// Capacity of the volumes of [`DESKTOP_MOUNTS`] by mount point, in GiB
+const DESKTOP_CAPACITIES: [(&str, u64); 7] = [
+	("/", 512),
+	("/boot/efi", 1),
+	("/home", 2048),
+	("/mnt/archive", 4096),
+	("/media/user/BACKUP", 1024),
+	("/run/media/user/SD CARD", 128),
+	("/mnt/photos", 8192),
+];
+
+const VIRTUAL_FILESYSTEMS: [&str; 8] = [
+	"sysfs",
+	"proc",
+	"devtmpfs",
+	"tmpfs",
+	"cgroup2",
+	"securityfs",
+	"squashfs",
+	"overlay",
+];
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+/// Capacity of the removable drives added by [`mount_table`], in GiB
+const DRIVE_CAPACITY: u64 = 64;
+
+/// Mount id of the drives added by [`mount_table`], past the ones of the desktop table
+const FIRST_DRIVE_MOUNT_ID: usize = 100;
+
+/// The desktop table plus one removable drive per index in `drives`, in the format of [`DESKTOP_MOUNTS`]
+///
+/// Shifting the range simulates hotplug: `0..100` then `1..101` unplugs a drive and plugs a new one.
+pub fn mount_table(drives: Range<usize>) -> String {
+	let mut table = DESKTOP_MOUNTS.to_string();
+	for drive in drives {
+		let mount_id = FIRST_DRIVE_MOUNT_ID + drive;
+		let _ = writeln!(
+			table,
+			"{mount_id} 25 8:{drive} / /media/user/DRIVE\\040{drive} rw,nosuid,nodev,relatime shared:{mount_id} - exfat /dev/sdx{drive} rw,uid=1000,gid=1000"
+		);
+	}
+	table
+}
+
+pub struct MockPlatform {
+	table: Vec<u8>,
+}
+
+impl MockPlatform {
+	pub fn new(table: &str) -> Self {
+		Self {
+			table: table.as_bytes().to_vec(),
+		}
+	}
+
+	/// Plays the part of `get_volumes`, parsing the table on every call like the Linux platform
+	/// reads `/proc/self/mountinfo` on every scan
+	pub fn get_volumes(&self) -> Vec<Volume> {
+		mountinfo::entries(&self.table)
+			.map(|entry| entry.expect("every line of the fixture is valid"))
+			.filter(|entry| !VIRTUAL_FILESYSTEMS.contains(&entry.file_system.as_str()))
+			.map(|entry| {
+				let source = entry.source.to_string_lossy();
+				let mount_type = if entry.mount_point.starts_with("/media")
+					|| entry.mount_point.starts_with("/run/media")
+				{
+					MountType::External
+				} else if source.starts_with("//") {
+					MountType::Network
+				} else {
+					MountType::System
+				};
+
+				let total_bytes_capacity = capacity_gib(&entry.mount_point) * GIB;
+
+				Volume::new(
+					source.rsplit('/').next().unwrap_or(&source).to_string(),
+					mount_type,
+					entry.mount_point.clone(),
+					vec![entry.mount_point.clone()],
+					DiskType::Unknown,
+					FileSystem::from_string(&entry.file_system),
+					total_bytes_capacity,
+					total_bytes_capacity / 2,
+					entry.is_read_only(),
+				)
+			})
+			.collect()
+	}
+}
+
+fn capacity_gib(mount_point: &Path) -> u64 {
+	DESKTOP_CAPACITIES
+		.iter()
+		.find(|(desktop_mount_point, _)| mount_point == Path::new(desktop_mount_point))
+		.map_or(DRIVE_CAPACITY, |(_, capacity)| *capacity)
+}
@@ -0,0 +1,101 @@
+//! Volume detection hot paths: listing volumes, fingerprinting them and diffing them on hotplug.
+//!
+//! Run with `cargo bench -p sd-core --bench volumes`. The fixtures are Linux mount tables, read
+//! with the parser of the Linux platform, so the benches using them only run there.
+
+use sd_core::api::volumes::{diff_volumes, VolumeFingerprint, VolumePlatform};
+use sd_core_prisma_helpers::DevicePubId;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use uuid::Uuid;
+
+#[cfg(target_os = "linux")]
+mod fixtures;
+
+#[cfg(target_os = "linux")]
+use fixtures::{mount_table, MockPlatform};
+
+/// Removable drives on top of the desktop fixture, from a laptop to a storage server
+#[cfg(target_os = "linux")]
+const DRIVES: [usize; 3] = [10, 100, 1_000];
+
+fn get_volumes(c: &mut Criterion) {
+	let mut group = c.benchmark_group("get_volumes");
+
+	// The real thing, to keep an eye on what the platform calls cost on the machine running the benches
+	{
+		let runtime = tokio::runtime::Runtime::new().expect("failed to start the tokio runtime");
+		let platform = runtime.block_on(sd_core::api::volumes::system_platform());
+		group.bench_function("system", |b| {
+			b.to_async(&runtime).iter(|| platform.get_volumes())
+		});
+	}
+
+	#[cfg(target_os = "linux")]
+	for drives in DRIVES {
+		let platform = MockPlatform::new(&mount_table(0..drives));
+		group.throughput(Throughput::Elements(drives as u64));
+		group.bench_with_input(BenchmarkId::new("mock", drives), &platform, |b, platform| {
+			b.iter(|| platform.get_volumes())
+		});
+	}
+
+	group.finish();
+}
+
+#[cfg(target_os = "linux")]
+fn fingerprint(c: &mut Criterion) {
+	let mut group = c.benchmark_group("fingerprint");
+	let device_id = DevicePubId::from(Uuid::now_v7());
+
+	for drives in DRIVES {
+		let volumes = MockPlatform::new(&mount_table(0..drives)).get_volumes();
+		group.throughput(Throughput::Elements(volumes.len() as u64));
+		group.bench_with_input(BenchmarkId::from_parameter(drives), &volumes, |b, volumes| {
+			b.iter(|| {
+				volumes
+					.iter()
+					.map(|volume| VolumeFingerprint::new(&device_id, volume))
+					.collect::<Vec<_>>()
+			})
+		});
+	}
+
+	group.finish();
+}
+
+#[cfg(target_os = "linux")]
+fn diff(c: &mut Criterion) {
+	let mut group = c.benchmark_group("diff");
+	let device_id = DevicePubId::from(Uuid::now_v7());
+
+	for drives in DRIVES {
+		let known = MockPlatform::new(&mount_table(0..drives)).get_volumes();
+		// One drive unplugged and another one plugged in since the last check
+		let hotplugged = MockPlatform::new(&mount_table(1..drives + 1)).get_volumes();
+
+		assert_eq!(diff_volumes(&device_id, &known, &hotplugged).len(), 2);
+
+		group.throughput(Throughput::Elements(known.len() as u64));
+
+		group.bench_with_input(BenchmarkId::new("unchanged", drives), &known, |b, known| {
+			b.iter(|| diff_volumes(&device_id, known, known))
+		});
+
+		group.bench_with_input(
+			BenchmarkId::new("hotplug", drives),
+			&(known, hotplugged),
+			|b, (known, hotplugged)| {
+				b.iter(|| diff_volumes(&device_id, known, hotplugged))
+			},
+		);
+	}
+
+	group.finish();
+}
+
+#[cfg(target_os = "linux")]
+criterion_group!(benches, get_volumes, fingerprint, diff);
+#[cfg(not(target_os = "linux"))]
+criterion_group!(benches, get_volumes);
+criterion_main!(benches);
@@ -1,5 +1,7 @@
 use std::process::Command;
 
+// Build scripts don't run on the async runtime
+#[allow(clippy::disallowed_methods)]
 fn main() {
 	let output = Command::new("git")
 		.args(["rev-parse", "--short", "HEAD"])
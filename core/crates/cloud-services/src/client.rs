@@ -1,8 +1,16 @@
 use crate::p2p::{NotifyUser, UserResponse};
 
 use sd_cloud_schema::{Client, Service, ServicesALPN};
-
-use std::{net::SocketAddr, sync::Arc, time::Duration};
+use sd_old_p2p::{Bandwidth, Direction};
+
+use std::{
+	net::SocketAddr,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
 
 use futures::Stream;
 use iroh::relay::RelayUrl;
@@ -52,6 +60,8 @@ pub struct CloudServices {
 	user_response_tx: flume::Sender<UserResponse>,
 	pub(crate) user_response_rx: flume::Receiver<UserResponse>,
 	pub has_bootstrapped: Arc<Mutex<bool>>,
+	offline: AtomicBool,
+	bandwidth: Arc<Bandwidth>,
 }
 
 impl CloudServices {
@@ -59,12 +69,16 @@ impl CloudServices {
 	/// The client will try to connect to the cloud services on a best effort basis, as the user
 	/// might not be connected to the internet.
 	/// If the client fails to connect, it will try again the next time it's used.
+	/// When starting `offline`, no connection is attempted until [`Self::set_offline`] is called.
+	/// Sync messages are held to the global limit of `bandwidth`, the one of p2p.
 	pub async fn new(
 		get_cloud_api_address: impl IntoUrl + Send,
 		cloud_p2p_relay_url: impl IntoUrl + Send,
 		cloud_p2p_dns_pkarr_url: impl IntoUrl + Send,
 		cloud_p2p_dns_origin_name: String,
 		domain_name: String,
+		offline: bool,
+		bandwidth: Arc<Bandwidth>,
 	) -> Result<Self, Error> {
 		let mut http_client_builder = reqwest::Client::builder().timeout(Duration::from_secs(3));
 
@@ -93,21 +107,21 @@ impl CloudServices {
 			.into_url()
 			.map_err(Error::InvalidUrl)?;
 
-		let client_state = match Self::init_client(
-			&http_client,
-			get_cloud_api_address.clone(),
-			domain_name.clone(),
-		)
-		.await
-		{
-			Ok(client) => Arc::new(RwLock::new(ClientState::Connected(client))),
-			Err(e) => {
-				warn!(
-					?e,
-					"Failed to initialize cloud services client; \
-						This is a best effort and we will continue in Not Connected mode"
-				);
-				Arc::new(RwLock::new(ClientState::NotConnected))
+		let client_state = if offline {
+			Arc::new(RwLock::new(ClientState::NotConnected))
+		} else {
+			match Self::init_client(&http_client, get_cloud_api_address.clone(), domain_name.clone())
+				.await
+			{
+				Ok(client) => Arc::new(RwLock::new(ClientState::Connected(client))),
+				Err(e) => {
+					warn!(
+						?e,
+						"Failed to initialize cloud services client; \
+							This is a best effort and we will continue in Not Connected mode"
+					);
+					Arc::new(RwLock::new(ClientState::NotConnected))
+				}
 			}
 		};
 
@@ -133,9 +147,16 @@ impl CloudServices {
 			user_response_tx,
 			user_response_rx,
 			has_bootstrapped: Arc::default(),
+			offline: AtomicBool::new(offline),
+			bandwidth,
 		})
 	}
 
+	/// Waits for the sync messages pushed or pulled to fit in the bandwidth limit
+	pub(crate) async fn pace(&self, direction: Direction, bytes: usize) {
+		self.bandwidth.pace(direction, bytes).await;
+	}
+
 	pub fn stream_user_notifications(&self) -> impl Stream<Item = NotifyUser> + '_ {
 		self.notify_user_rx.stream()
 	}
@@ -272,6 +293,10 @@ impl CloudServices {
 	/// Available routes documented in
 	/// [`sd_cloud_schema::Service`](https://github.com/spacedriveapp/cloud-services-schema).
 	pub async fn client(&self) -> Result<CloudServicesClient, Error> {
+		if self.is_offline() {
+			return Err(Error::Offline);
+		}
+
 		if let ClientState::Connected(client) = { &*self.client_state.read().await } {
 			return Ok(client.clone());
 		}
@@ -288,6 +313,20 @@ impl CloudServices {
 		Ok(client)
 	}
 
+	/// Whether a client is connected, without trying to connect like [`Self::client`] does
+	pub async fn is_connected(&self) -> bool {
+		matches!(&*self.client_state.read().await, ClientState::Connected(_))
+	}
+
+	/// While offline, [`Self::client`] and [`Self::cloud_p2p`] refuse to hand out connections
+	pub fn set_offline(&self, offline: bool) {
+		self.offline.store(offline, Ordering::Relaxed);
+	}
+
+	pub fn is_offline(&self) -> bool {
+		self.offline.load(Ordering::Relaxed)
+	}
+
 	pub async fn set_key_manager(&self, key_manager: KeyManager) {
 		self.key_manager
 			.write()
@@ -310,6 +349,10 @@ impl CloudServices {
 	}
 
 	pub async fn cloud_p2p(&self) -> Result<Arc<CloudP2P>, Error> {
+		if self.is_offline() {
+			return Err(Error::Offline);
+		}
+
 		self.cloud_p2p
 			.read()
 			.await
@@ -335,6 +378,8 @@ mod tests {
 			"http://pkarr.localhost:9999/",
 			"dns.localhost:9999".to_string(),
 			"localhost".to_string(),
+			false,
+			Arc::default(),
 		)
 		.await
 		.unwrap()
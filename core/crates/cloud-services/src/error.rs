@@ -83,6 +83,8 @@ pub enum Error {
 	DhtDiscoveryInit(anyhow::Error),
 
 	// Communication errors
+	#[error("Cloud services are unavailable while offline mode is enabled")]
+	Offline,
 	#[error("Failed to communicate with RPC backend: {0}")]
 	RpcCommunication(#[from] rpc::Error<QuinnConnector<Response, Request>>),
 	#[error("Failed to communicate with RPC sync backend: {0}")]
@@ -177,6 +179,8 @@ pub enum Error {
 	ReadNonceStreamDecryption(io::Error),
 	#[error("Incomplete download bytes sync messages")]
 	IncompleteDownloadBytesSyncMessages,
+	#[error("Incomplete sync messages envelope")]
+	IncompleteSyncMessagesEnvelope,
 	#[error("Timed out while waiting to recive thumbnail data")]
 	ThumbnailRequestTimeout,
 
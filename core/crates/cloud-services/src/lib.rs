@@ -43,7 +43,7 @@ pub use p2p::{
 	CloudP2P, JoinSyncGroupResponse, JoinedLibraryCreateArgs, NotifyUser, Ticket, UserResponse,
 };
 pub use sync::{
-	declare_actors as declare_cloud_sync, SyncActors as CloudSyncActors,
+	declare_actors as declare_cloud_sync, outbox_depth, OutboxDepth, SyncActors as CloudSyncActors,
 	SyncActorsState as CloudSyncActorsState,
 };
 
@@ -0,0 +1,229 @@
+//! Envelopes, the batches of sync messages sealed for each device of the group on top of the
+//! transport.
+//!
+//! The operations of a batch are encrypted with the key of the library's sync group, and that key
+//! is wrapped for every other device of the group with the secret the sending device shares with
+//! it, from their cloud P2P keys. A device gets the keys of the group when it joins, and the ones
+//! rotated since, or missed, it unwraps from the next batch of each device. The devices of the
+//! group are fetched again every few minutes, so the ones added meanwhile get it wrapped too, and
+//! right away once a device is revoked. The revoked devices never get it, even while the group
+//! still lists them.
+//!
+//! The batches sent before envelopes don't start with [`MAGIC`], they're taken as they are.
+
+use crate::{CloudServices, Error};
+
+use sd_cloud_schema::{
+	devices, sync::groups, Client, NodeId, Request, Response, SecretKey as IrohSecretKey,
+};
+use sd_core_sync::revocation::Revocations;
+
+use sd_crypto::cloud::secret_key::SecretKey;
+
+use std::time::Duration;
+
+use quic_rpc::transport::quinn::QuinnConnector;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+const MAGIC: &[u8; 4] = b"SDE1";
+
+/// How long the devices of the group are kept before being fetched again
+const DEVICES_CACHE_DURATION: Duration = Duration::from_secs(60 * 5);
+
+#[derive(Serialize, Deserialize)]
+struct WrappedKey {
+	device_pub_id: devices::PubId,
+	/// The key of the group, sealed with the secret the sending device shares with this one
+	sealed: Vec<u8>,
+}
+
+/// Seals an encrypted batch, wrapping its key for all the devices but this one and the revoked
+/// ones. It's laid out as [`MAGIC`], the length of the wrapped keys as a little endian `u32`, the
+/// wrapped keys and the batch.
+pub(super) fn seal(
+	iroh_secret_key: &IrohSecretKey,
+	current_device_pub_id: devices::PubId,
+	devices: &[(devices::PubId, NodeId)],
+	is_revoked: impl Fn(&devices::PubId) -> bool,
+	key: &SecretKey,
+	payload: Vec<u8>,
+) -> Result<Vec<u8>, Error> {
+	let wrapped_keys = devices
+		.iter()
+		.filter(|(device_pub_id, _)| {
+			*device_pub_id != current_device_pub_id && !is_revoked(device_pub_id)
+		})
+		.map(|(device_pub_id, node_id)| {
+			let mut sealed = key.as_ref().to_vec();
+			iroh_secret_key.shared(node_id).seal(&mut sealed);
+
+			WrappedKey {
+				device_pub_id: *device_pub_id,
+				sealed,
+			}
+		})
+		.collect::<Vec<_>>();
+
+	let wrapped_keys =
+		rmp_serde::to_vec(&wrapped_keys).map_err(Error::SerializationFailureToPushSyncMessages)?;
+	#[allow(clippy::cast_possible_truncation)]
+	// SAFETY: a wrapped key takes less than a hundred bytes and a group has a handful of devices
+	let wrapped_keys_len = wrapped_keys.len() as u32;
+
+	let mut bytes =
+		Vec::with_capacity(MAGIC.len() + size_of::<u32>() + wrapped_keys.len() + payload.len());
+	bytes.extend_from_slice(MAGIC);
+	bytes.extend_from_slice(&wrapped_keys_len.to_le_bytes());
+	bytes.extend(wrapped_keys);
+	bytes.extend(payload);
+
+	Ok(bytes)
+}
+
+/// The encrypted batch of an envelope, with its key when it was wrapped for this device by a
+/// sender it knows
+pub(super) fn open(
+	iroh_secret_key: &IrohSecretKey,
+	current_device_pub_id: devices::PubId,
+	sender: Option<&NodeId>,
+	bytes: Vec<u8>,
+) -> Result<(Vec<u8>, Option<SecretKey>), Error> {
+	let wrapped_keys_len = bytes
+		.strip_prefix(MAGIC)
+		.and_then(|rest| rest.get(..size_of::<u32>()))
+		.map(|len| u32::from_le_bytes(len.try_into().expect("we took the right amount")) as usize);
+	let Some(wrapped_keys_len) = wrapped_keys_len else {
+		return Ok((bytes, None));
+	};
+
+	let header_len = MAGIC.len() + size_of::<u32>();
+	let Some(wrapped_keys) = bytes.get(header_len..header_len + wrapped_keys_len) else {
+		return Err(Error::IncompleteSyncMessagesEnvelope);
+	};
+	let wrapped_keys = rmp_serde::from_slice::<Vec<WrappedKey>>(wrapped_keys)
+		.map_err(Error::DeserializationFailureToPullSyncMessages)?;
+
+	let mut payload = bytes;
+	payload.drain(..header_len + wrapped_keys_len);
+
+	let key = sender.and_then(|sender| {
+		let mut key = wrapped_keys
+			.into_iter()
+			.find(|wrapped| wrapped.device_pub_id == current_device_pub_id)?
+			.sealed;
+
+		if let Err(e) = iroh_secret_key.shared(sender).open(&mut key) {
+			warn!(?e, "Failed to unwrap the key of a sync messages envelope;");
+			return None;
+		}
+
+		SecretKey::try_from(key.as_slice()).ok()
+	});
+
+	Ok((payload, key))
+}
+
+/// The devices of a group with their cloud P2P ids, along with when they were fetched and the
+/// [`Revocations::generation`] back then
+#[derive(Debug, Default)]
+pub(super) struct GroupDevices(Option<(Instant, u64, Vec<(devices::PubId, NodeId)>)>);
+
+impl GroupDevices {
+	pub(super) async fn get(
+		&mut self,
+		group_pub_id: groups::PubId,
+		cloud_services: &CloudServices,
+		cloud_client: &Client<QuinnConnector<Response, Request>>,
+		revocations: &Revocations,
+	) -> Result<&[(devices::PubId, NodeId)], Error> {
+		let generation = revocations.generation();
+		let fresh = matches!(
+			&self.0,
+			Some((fetched_at, fetched_generation, _))
+				if fetched_at.elapsed() < DEVICES_CACHE_DURATION && *fetched_generation == generation
+		);
+		if !fresh {
+			debug!("Fetching devices connection ids for group");
+
+			let groups::get::Response(groups::get::ResponseKind::DevicesConnectionIds(devices)) =
+				cloud_client
+					.sync()
+					.groups()
+					.get(groups::get::Request {
+						access_token: cloud_services.token_refresher.get_access_token().await?,
+						pub_id: group_pub_id,
+						kind: groups::get::RequestKind::DevicesConnectionIds,
+					})
+					.await??
+			else {
+				unreachable!("Only DevicesConnectionIds response is expected, as we requested it");
+			};
+
+			self.0 = Some((Instant::now(), generation, devices));
+		}
+
+		Ok(self
+			.0
+			.as_ref()
+			.map(|(_, _, devices)| devices.as_slice())
+			.unwrap_or_default())
+	}
+
+	/// Fetches the devices again next time, for a device that joined since
+	pub(super) fn invalidate(&mut self) {
+		self.0 = None;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use sd_crypto::CryptoRng;
+
+	use uuid::Uuid;
+
+	#[test]
+	fn test_seal_skips_revoked_devices() {
+		let mut rng = CryptoRng::new().unwrap();
+		let key = SecretKey::generate(&mut rng);
+
+		let (sender, kept, revoked) = (
+			IrohSecretKey::generate(),
+			IrohSecretKey::generate(),
+			IrohSecretKey::generate(),
+		);
+		let (sender_pub_id, kept_pub_id, revoked_pub_id) = (
+			devices::PubId(Uuid::from_u128(1)),
+			devices::PubId(Uuid::from_u128(2)),
+			devices::PubId(Uuid::from_u128(3)),
+		);
+
+		// Right after the revocation, the group still lists the revoked device
+		let devices = [
+			(sender_pub_id, sender.public()),
+			(kept_pub_id, kept.public()),
+			(revoked_pub_id, revoked.public()),
+		];
+		let sealed = seal(
+			&sender,
+			sender_pub_id,
+			&devices,
+			|device_pub_id| *device_pub_id == revoked_pub_id,
+			&key,
+			b"batch".to_vec(),
+		)
+		.unwrap();
+
+		let (payload, kept_key) =
+			open(&kept, kept_pub_id, Some(&sender.public()), sealed.clone()).unwrap();
+		assert_eq!(payload, b"batch");
+		assert_eq!(kept_key.unwrap().as_ref(), key.as_ref());
+
+		let (_, revoked_key) =
+			open(&revoked, revoked_pub_id, Some(&sender.public()), sealed).unwrap();
+		assert!(revoked_key.is_none());
+	}
+}
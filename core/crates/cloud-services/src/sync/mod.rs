@@ -4,7 +4,7 @@ use sd_core_sync::SyncManager;
 
 use sd_actors::{ActorsCollection, IntoActor};
 use sd_cloud_schema::sync::groups;
-use sd_crypto::CryptoRng;
+use sd_crypto::{CryptoRng, SeedableRng};
 
 use std::{
 	fmt,
@@ -16,10 +16,14 @@ use std::{
 use futures_concurrency::future::TryJoin;
 use tokio::sync::Notify;
 
+mod envelope;
 mod ingest;
+mod outbox;
 mod receive;
 mod send;
 
+pub use outbox::{outbox_depth, OutboxDepth};
+
 use ingest::Ingester;
 use receive::Receiver;
 use send::Sender;
@@ -84,8 +88,10 @@ pub async fn declare_actors(
 	actors_state: &SyncActorsState,
 	sync_group_pub_id: groups::PubId,
 	sync: SyncManager,
-	rng: CryptoRng,
+	mut rng: CryptoRng,
 ) -> Result<Arc<ReceiveAndIngestNotifiers>, Error> {
+	let receiver_rng = CryptoRng::from_seed(rng.generate_fixed());
+
 	let (sender, receiver) = (
 		Sender::new(
 			sync_group_pub_id,
@@ -103,6 +109,7 @@ pub async fn declare_actors(
 			Arc::clone(&actors_state.receiver_and_ingester_notifiers),
 			Arc::clone(&actors_state.receive_active),
 			Arc::clone(&actors_state.state_change_notifier),
+			receiver_rng,
 		),
 	)
 		.try_join()
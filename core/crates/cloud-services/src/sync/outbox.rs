@@ -0,0 +1,324 @@
+//! Outbox, the batches of sync messages waiting to be pushed to the cloud.
+//!
+//! The sender queues every batch it encrypts in the `sync_outbox` table before pushing it, so the
+//! ones it couldn't push, with the cloud unreachable or the device offline, survive a restart. They
+//! are pushed oldest first and one failing holds the ones after it back, so the cloud still gets
+//! them in order, it's tried again with exponential backoff and some jitter. A batch gets sealed in
+//! its envelope when it's pushed, for the devices the group has by then.
+//!
+//! A batch that can't ever be pushed, failing [`MAX_ATTEMPTS`] times or with an error retrying
+//! won't fix, is dead lettered: it stays in the table to be looked at, but stops holding the
+//! others back.
+
+use crate::Error;
+
+use sd_cloud_schema::{
+	devices,
+	sync::{groups, KeyHash},
+};
+use sd_core_sync::{SyncManager, NTP64};
+use sd_crypto::{CryptoRng, RngCore};
+use sd_prisma::prisma::{device, sync_outbox, PrismaClient, SortOrder};
+use sd_utils::uuid_to_bytes;
+
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use specta::Type;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// Waited after the first failure, doubling after each of the next ones
+const BASE_BACKOFF: Duration = Duration::from_secs(5);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// Failed pushes before a batch is dead lettered, about 11 hours of backoff
+const MAX_ATTEMPTS: u32 = 20;
+
+sync_outbox::select!(batch_to_push {
+	id
+	key_hash
+	operations_count
+	start_time
+	end_time
+	encrypted_messages
+	attempts
+	next_attempt_at
+});
+
+/// An encrypted batch, before it's sealed in its envelope
+pub(super) struct Batch {
+	pub key_hash: KeyHash,
+	pub operations_count: u32,
+	pub start_time: DateTime<Utc>,
+	pub end_time: DateTime<Utc>,
+	pub last_timestamp: NTP64,
+	pub encrypted_messages: Vec<u8>,
+}
+
+/// The batches a peer of the sync group is still waiting for, every batch goes to all of them
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct OutboxDepth {
+	pub device_pub_id: devices::PubId,
+	pub device_name: Option<String>,
+	pub group_pub_id: groups::PubId,
+	pub batches: u32,
+	pub operations: u64,
+	/// Of the oldest batch, the one holding the others back
+	pub attempts: u32,
+	pub next_attempt_at: Option<DateTime<Utc>>,
+	pub last_error: Option<String>,
+	/// Batches given up on, the peer won't get their operations
+	pub dead_letters: u32,
+}
+
+pub(super) async fn enqueue(
+	db: &PrismaClient,
+	group_pub_id: groups::PubId,
+	batch: Batch,
+) -> Result<(), Error> {
+	#[allow(clippy::cast_possible_wrap)]
+	// SAFETY: we had to store using i64 due to SQLite limitations
+	let last_timestamp = batch.last_timestamp.as_u64() as i64;
+
+	db.sync_outbox()
+		.create(
+			group_pub_id.0.as_bytes().to_vec(),
+			batch.key_hash.0,
+			i32::try_from(batch.operations_count).unwrap_or(i32::MAX),
+			batch.start_time.fixed_offset(),
+			batch.end_time.fixed_offset(),
+			last_timestamp,
+			batch.encrypted_messages,
+			vec![],
+		)
+		.select(sync_outbox::select!({ id }))
+		.exec()
+		.await
+		.map_err(sd_core_sync::Error::from)?;
+
+	Ok(())
+}
+
+/// The timestamp of the last operation queued for the group, the cloud having the ones before it
+pub(super) async fn latest_timestamp(
+	db: &PrismaClient,
+	group_pub_id: groups::PubId,
+) -> Result<Option<NTP64>, Error> {
+	Ok(db
+		.sync_outbox()
+		.find_first(vec![sync_outbox::group_pub_id::equals(
+			group_pub_id.0.as_bytes().to_vec(),
+		)])
+		.order_by(sync_outbox::last_timestamp::order(SortOrder::Desc))
+		.select(sync_outbox::select!({ last_timestamp }))
+		.exec()
+		.await
+		.map_err(sd_core_sync::Error::from)?
+		.map(|batch| {
+			#[allow(clippy::cast_sign_loss)]
+			// SAFETY: we had to store using i64 due to SQLite limitations
+			NTP64(batch.last_timestamp as u64)
+		}))
+}
+
+/// The oldest batch of the group that wasn't dead lettered
+pub(super) async fn next(
+	db: &PrismaClient,
+	group_pub_id: groups::PubId,
+) -> Result<Option<batch_to_push::Data>, Error> {
+	Ok(db
+		.sync_outbox()
+		.find_first(vec![
+			sync_outbox::group_pub_id::equals(group_pub_id.0.as_bytes().to_vec()),
+			sync_outbox::dead_lettered_at::equals(None),
+		])
+		.order_by(sync_outbox::id::order(SortOrder::Asc))
+		.select(batch_to_push::select())
+		.exec()
+		.await
+		.map_err(sd_core_sync::Error::from)?)
+}
+
+/// Takes a batch out once it was pushed
+pub(super) async fn remove(db: &PrismaClient, id: sync_outbox::id::Type) -> Result<(), Error> {
+	db.sync_outbox()
+		.delete_many(vec![sync_outbox::id::equals(id)])
+		.exec()
+		.await
+		.map_err(sd_core_sync::Error::from)?;
+
+	Ok(())
+}
+
+/// Records a failed push of the batch, returning how long until it's tried again or `None` when
+/// it was dead lettered
+pub(super) async fn postpone(
+	db: &PrismaClient,
+	batch: &batch_to_push::Data,
+	error: &Error,
+	rng: &mut CryptoRng,
+) -> Result<Option<Duration>, Error> {
+	let attempts = u32::try_from(batch.attempts).unwrap_or_default() + 1;
+
+	if attempts >= MAX_ATTEMPTS || is_permanent(error) {
+		db.sync_outbox()
+			.update(
+				sync_outbox::id::equals(batch.id),
+				vec![
+					sync_outbox::attempts::increment(1),
+					sync_outbox::last_error::set(Some(error.to_string())),
+					sync_outbox::dead_lettered_at::set(Some(Utc::now().fixed_offset())),
+				],
+			)
+			.select(sync_outbox::select!({ id }))
+			.exec()
+			.await
+			.map_err(sd_core_sync::Error::from)?;
+
+		warn!(
+			attempts,
+			operations_count = batch.operations_count,
+			?error,
+			"Gave up on pushing a batch of sync messages, dead lettered it;"
+		);
+
+		return Ok(None);
+	}
+
+	let wait = backoff(attempts, rng);
+
+	db.sync_outbox()
+		.update(
+			sync_outbox::id::equals(batch.id),
+			vec![
+				sync_outbox::attempts::increment(1),
+				sync_outbox::next_attempt_at::set((Utc::now() + wait).fixed_offset()),
+				sync_outbox::last_error::set(Some(error.to_string())),
+			],
+		)
+		.select(sync_outbox::select!({ id }))
+		.exec()
+		.await
+		.map_err(sd_core_sync::Error::from)?;
+
+	debug!(
+		attempts,
+		?wait,
+		"Postponed pushing a batch of sync messages;"
+	);
+
+	Ok(Some(wait))
+}
+
+/// The errors the batch fails with on every attempt, like the key it was encrypted with being gone
+const fn is_permanent(error: &Error) -> bool {
+	matches!(error, Error::MissingKeyHash)
+}
+
+/// The doubling backoff for the attempt, scaled by a random factor between a half and one so the
+/// devices that lost the cloud together don't come back together
+fn backoff(attempts: u32, rng: &mut CryptoRng) -> Duration {
+	let backoff = BASE_BACKOFF
+		.saturating_mul(1 << attempts.saturating_sub(1).min(16))
+		.min(MAX_BACKOFF);
+
+	backoff.mul_f64(0.5 + 0.5 * f64::from(rng.next_u32()) / f64::from(u32::MAX))
+}
+
+/// The batches waiting in the outbox for each peer, the other devices of the library that weren't
+/// revoked
+pub async fn outbox_depth(sync: &SyncManager) -> Result<Vec<OutboxDepth>, Error> {
+	let current_device_pub_id = uuid_to_bytes(&Uuid::from(&sync.device_pub_id));
+
+	let peers = sync
+		.db
+		.device()
+		.find_many(vec![
+			device::date_deleted::equals(None),
+			device::date_revoked::equals(None),
+		])
+		.select(device::select!({ pub_id name }))
+		.exec()
+		.await
+		.map_err(sd_core_sync::Error::from)?
+		.into_iter()
+		.filter(|peer| peer.pub_id != current_device_pub_id)
+		.collect::<Vec<_>>();
+
+	let mut depth_per_group = HashMap::<_, GroupDepth>::new();
+
+	for batch in sync
+		.db
+		.sync_outbox()
+		.find_many(vec![])
+		.order_by(sync_outbox::id::order(SortOrder::Asc))
+		.select(sync_outbox::select!({
+			group_pub_id
+			operations_count
+			attempts
+			next_attempt_at
+			last_error
+			dead_lettered_at
+		}))
+		.exec()
+		.await
+		.map_err(sd_core_sync::Error::from)?
+	{
+		let Ok(group_uuid) = Uuid::from_slice(&batch.group_pub_id) else {
+			continue;
+		};
+
+		let depth = depth_per_group.entry(group_uuid).or_default();
+
+		if batch.dead_lettered_at.is_some() {
+			depth.dead_letters += 1;
+			continue;
+		}
+
+		// The first live batch is the head of the queue
+		if depth.batches == 0 {
+			depth.attempts = u32::try_from(batch.attempts).unwrap_or_default();
+			depth.next_attempt_at = Some(batch.next_attempt_at.to_utc());
+			depth.last_error = batch.last_error;
+		}
+
+		depth.batches += 1;
+		depth.operations += u64::try_from(batch.operations_count).unwrap_or_default();
+	}
+
+	Ok(depth_per_group
+		.into_iter()
+		.flat_map(|(group_uuid, depth)| {
+			peers.iter().filter_map(move |peer| {
+				let depth = depth.clone();
+				Uuid::from_slice(&peer.pub_id)
+					.ok()
+					.map(|peer_uuid| OutboxDepth {
+						device_pub_id: devices::PubId(peer_uuid),
+						device_name: peer.name.clone(),
+						group_pub_id: groups::PubId(group_uuid),
+						batches: depth.batches,
+						operations: depth.operations,
+						attempts: depth.attempts,
+						next_attempt_at: depth.next_attempt_at,
+						last_error: depth.last_error,
+						dead_letters: depth.dead_letters,
+					})
+			})
+		})
+		.collect())
+}
+
+/// What the outbox of a group holds, the same for all of its peers
+#[derive(Debug, Clone, Default)]
+struct GroupDepth {
+	batches: u32,
+	operations: u64,
+	attempts: u32,
+	next_attempt_at: Option<DateTime<Utc>>,
+	last_error: Option<String>,
+	dead_letters: u32,
+}
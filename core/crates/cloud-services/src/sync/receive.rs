@@ -6,7 +6,7 @@ use sd_cloud_schema::{
 		groups,
 		messages::{pull, MessagesCollection},
 	},
-	Client, Request, Response,
+	Client, NodeId, Request, Response,
 };
 use sd_core_sync::{
 	cloud_crdt_op_db, CRDTOperation, CompressedCRDTOperationsPerModel, SyncManager,
@@ -16,12 +16,16 @@ use sd_actors::{Actor, Stopper};
 use sd_crypto::{
 	cloud::{OneShotDecryption, SecretKey, StreamDecryption},
 	primitives::{EncryptedBlock, StreamNonce},
+	CryptoRng,
 };
+use sd_old_p2p::Direction;
 use sd_prisma::prisma::PrismaClient;
+use sd_utils::uuid_to_bytes;
 
 use std::{
 	collections::{hash_map::Entry, HashMap},
 	future::IntoFuture,
+	mem,
 	path::Path,
 	sync::{
 		atomic::{AtomicBool, Ordering},
@@ -38,7 +42,10 @@ use tokio::{fs, io, sync::Notify, time::sleep};
 use tracing::{debug, error, instrument, warn};
 use uuid::Uuid;
 
-use super::{ReceiveAndIngestNotifiers, SyncActors, ONE_MINUTE};
+use super::{
+	envelope::{self, GroupDevices},
+	ReceiveAndIngestNotifiers, SyncActors, ONE_MINUTE,
+};
 
 const CLOUD_SYNC_DATA_KEEPER_DIRECTORY: &str = "cloud_sync_data_keeper";
 
@@ -55,6 +62,8 @@ pub struct Receiver {
 	notifiers: Arc<ReceiveAndIngestNotifiers>,
 	active: Arc<AtomicBool>,
 	active_notifier: Arc<Notify>,
+	devices: GroupDevices,
+	rng: CryptoRng,
 }
 
 impl Actor<SyncActors> for Receiver {
@@ -109,6 +118,7 @@ impl Receiver {
 		notifiers: Arc<ReceiveAndIngestNotifiers>,
 		active: Arc<AtomicBool>,
 		active_notify: Arc<Notify>,
+		rng: CryptoRng,
 	) -> Result<Self, Error> {
 		let (keeper, cloud_client, key_manager) = (
 			LastTimestampKeeper::load(data_dir.as_ref(), sync_group_pub_id),
@@ -129,6 +139,8 @@ impl Receiver {
 			notifiers,
 			active,
 			active_notifier: active_notify,
+			devices: GroupDevices::default(),
+			rng,
 		})
 	}
 
@@ -179,7 +191,7 @@ impl Receiver {
 			"Handling new sync messages collections",
 		);
 
-		for message in new_messages.into_iter().filter(|message| {
+		for mut message in new_messages.into_iter().filter(|message| {
 			if message.original_device_pub_id == self.device_pub_id {
 				warn!("Received sync message from the current device, need to check backend, this is a bug!");
 				false
@@ -194,13 +206,29 @@ impl Receiver {
 				"Handling new sync messages",
 			);
 
-			let (device_pub_id, timestamp) = handle_single_message(
-				self.sync_group_pub_id,
-				message,
-				&self.key_manager,
-				&self.sync,
-			)
-			.await?;
+			self.cloud_services
+				.pace(Direction::Download, message.encrypted_messages.len())
+				.await;
+
+			// Skipped for good, moving past them
+			let (device_pub_id, timestamp) = if self
+				.sync
+				.revocations
+				.is_revoked(&uuid_to_bytes(&message.original_device_pub_id.0))
+			{
+				debug!("Rejected sync messages of a revoked device");
+				(message.original_device_pub_id, message.end_time)
+			} else {
+				self.open_envelope(&mut message).await?;
+
+				handle_single_message(
+					self.sync_group_pub_id,
+					message,
+					&self.key_manager,
+					&self.sync,
+				)
+				.await?
+			};
 
 			match self.keeper.timestamps.entry(device_pub_id) {
 				Entry::Occupied(mut entry) => {
@@ -222,6 +250,84 @@ impl Receiver {
 
 		Ok(())
 	}
+
+	/// Swaps the envelope of a batch for what it holds, keeping the key wrapped in it when this
+	/// device didn't have it yet
+	async fn open_envelope(&mut self, message: &mut MessagesCollection) -> Result<(), Error> {
+		let sender = self.node_id_of(message.original_device_pub_id).await?;
+
+		let (payload, key) = envelope::open(
+			&self.key_manager.iroh_secret_key().await,
+			self.device_pub_id,
+			sender.as_ref(),
+			mem::take(&mut message.encrypted_messages),
+		)?;
+		message.encrypted_messages = payload;
+
+		let Some(key) = key else {
+			return Ok(());
+		};
+
+		if self
+			.key_manager
+			.get_key(self.sync_group_pub_id, &message.key_hash)
+			.await
+			.is_none()
+			&& blake3::hash(key.as_ref()).to_hex().as_str() == message.key_hash.0
+		{
+			debug!(
+				key_hash = message.key_hash.0,
+				"Got a key of the group from a sync messages envelope"
+			);
+
+			self.key_manager
+				.add_key_with_hash(
+					self.sync_group_pub_id,
+					key,
+					message.key_hash.clone(),
+					&mut self.rng,
+				)
+				.await?;
+		}
+
+		Ok(())
+	}
+
+	/// The cloud P2P id of a device of the group, fetching the devices again for one that joined
+	/// since they were
+	async fn node_id_of(&mut self, device_pub_id: devices::PubId) -> Result<Option<NodeId>, Error> {
+		let find = |devices: &[(devices::PubId, NodeId)]| {
+			devices
+				.iter()
+				.find_map(|(pub_id, node_id)| (*pub_id == device_pub_id).then_some(*node_id))
+		};
+
+		let devices = self
+			.devices
+			.get(
+				self.sync_group_pub_id,
+				&self.cloud_services,
+				&self.cloud_client,
+				&self.sync.revocations,
+			)
+			.await?;
+		if let Some(node_id) = find(devices) {
+			return Ok(Some(node_id));
+		}
+
+		self.devices.invalidate();
+
+		Ok(find(
+			self.devices
+				.get(
+					self.sync_group_pub_id,
+					&self.cloud_services,
+					&self.cloud_client,
+					&self.sync.revocations,
+				)
+				.await?,
+		))
+	}
 }
 
 #[instrument(
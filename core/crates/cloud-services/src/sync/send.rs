@@ -6,7 +6,7 @@ use sd_actors::{Actor, Stopper};
 use sd_cloud_schema::{
 	devices,
 	error::{ClientSideError, NotFoundError},
-	sync::{groups, messages},
+	sync::{groups, messages, KeyHash},
 	Client, Request, Response,
 };
 use sd_crypto::{
@@ -14,7 +14,8 @@ use sd_crypto::{
 	primitives::EncryptedBlock,
 	CryptoRng, SeedableRng,
 };
-use sd_utils::{datetime_to_timestamp, timestamp_to_datetime};
+use sd_old_p2p::Direction;
+use sd_utils::{datetime_to_timestamp, timestamp_to_datetime, uuid_to_bytes};
 
 use std::{
 	future::IntoFuture,
@@ -34,10 +35,14 @@ use tokio::{
 	sync::{broadcast, Notify},
 	time::sleep,
 };
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 use uuid::Uuid;
 
-use super::{SyncActors, ONE_MINUTE};
+use super::{
+	envelope::{self, GroupDevices},
+	outbox::{self, Batch},
+	SyncActors, ONE_MINUTE,
+};
 
 const TEN_SECONDS: Duration = Duration::from_secs(10);
 
@@ -51,6 +56,8 @@ enum RaceNotifiedOrStopped {
 enum LoopStatus {
 	SentMessages,
 	Idle,
+	/// Pushing the outbox failed, it's tried again after this long
+	Postponed(Duration),
 }
 
 type LatestTimestamp = NTP64;
@@ -66,6 +73,7 @@ pub struct Sender {
 	state_notify: Arc<Notify>,
 	rng: CryptoRng,
 	maybe_latest_timestamp: Option<LatestTimestamp>,
+	devices: GroupDevices,
 }
 
 impl Actor<SyncActors> for Sender {
@@ -93,6 +101,24 @@ impl Actor<SyncActors> for Sender {
 
 				Ok(LoopStatus::Idle) => {}
 
+				Ok(LoopStatus::Postponed(wait)) => {
+					self.state_notify.notify_waiters();
+
+					if matches!(
+						(
+							sleep(wait).map(|()| RaceNotifiedOrStopped::Notified),
+							stop.into_future().map(|()| RaceNotifiedOrStopped::Stopped),
+						)
+							.race()
+							.await,
+						RaceNotifiedOrStopped::Stopped
+					) {
+						break;
+					}
+
+					continue;
+				}
+
 				Err(e) => {
 					error!(?e, "Error during cloud sync sender actor iteration");
 					sleep(ONE_MINUTE).await;
@@ -143,6 +169,7 @@ impl Sender {
 			state_notify,
 			rng,
 			maybe_latest_timestamp: None,
+			devices: GroupDevices::default(),
 		})
 	}
 
@@ -165,8 +192,6 @@ impl Sender {
 			current_latest_timestamp
 		));
 
-		let mut status = LoopStatus::Idle;
-
 		let mut new_latest_timestamp = current_latest_timestamp;
 
 		debug!(
@@ -202,52 +227,161 @@ impl Sender {
 			let encrypted_messages =
 				encrypt_messages(&secret_key, &mut self.rng, messages_bytes).await?;
 
-			let encrypted_messages_size = encrypted_messages.len();
-
-			debug!(
-				operations_count,
-				encrypted_messages_size, "Sending sync messages to cloud",
-			);
-
-			self.cloud_client
-				.sync()
-				.messages()
-				.push(messages::push::Request {
-					access_token: self
-						.cloud_services
-						.token_refresher
-						.get_access_token()
-						.await?,
-					group_pub_id: self.sync_group_pub_id,
-					device_pub_id: current_device_pub_id,
+			outbox::enqueue(
+				&self.sync.db,
+				self.sync_group_pub_id,
+				Batch {
 					key_hash: key_hash.clone(),
 					operations_count,
-					time_range: (start_time, end_time),
+					start_time,
+					end_time,
+					last_timestamp: new_latest_timestamp,
 					encrypted_messages,
-				})
-				.await??;
-
-			debug!(
-				operations_count,
-				encrypted_messages_size, "Sent sync messages to cloud",
-			);
+				},
+			)
+			.await?;
 
-			status = LoopStatus::SentMessages;
+			debug!(operations_count, "Queued sync messages in the outbox");
 		}
 
 		self.maybe_latest_timestamp = Some(new_latest_timestamp);
 
+		let status = self.push_outbox(current_device_pub_id).await?;
+
 		debug!("Finished cloud sender actor loop iteration");
 
 		Ok(status)
 	}
 
+	/// Pushes the batches of the outbox oldest first, stopping at the first one that fails
+	async fn push_outbox(
+		&mut self,
+		current_device_pub_id: devices::PubId,
+	) -> Result<LoopStatus, Error> {
+		let mut status = LoopStatus::Idle;
+
+		while let Some(batch) = outbox::next(&self.sync.db, self.sync_group_pub_id).await? {
+			// Still backing off from an attempt before a restart
+			if let Some(wait) = (batch.next_attempt_at.to_utc() - Utc::now())
+				.to_std()
+				.ok()
+				.filter(|wait| !wait.is_zero())
+			{
+				return Ok(LoopStatus::Postponed(wait));
+			}
+
+			if let Err(e) = self.push_batch(current_device_pub_id, &batch).await {
+				match outbox::postpone(&self.sync.db, &batch, &e, &mut self.rng).await? {
+					Some(wait) => {
+						warn!(
+							?e,
+							?wait,
+							"Failed to push sync messages to cloud, retrying later;"
+						);
+
+						return Ok(LoopStatus::Postponed(wait));
+					}
+
+					// Dead lettered, the batches after it go on
+					None => continue,
+				}
+			}
+
+			outbox::remove(&self.sync.db, batch.id).await?;
+
+			status = LoopStatus::SentMessages;
+		}
+
+		Ok(status)
+	}
+
+	async fn push_batch(
+		&mut self,
+		current_device_pub_id: devices::PubId,
+		batch: &outbox::batch_to_push::Data,
+	) -> Result<(), Error> {
+		let key_hash = KeyHash(batch.key_hash.clone());
+		let secret_key = self
+			.key_manager
+			.get_key(self.sync_group_pub_id, &key_hash)
+			.await
+			.ok_or(Error::MissingKeyHash)?;
+
+		let iroh_secret_key = self.key_manager.iroh_secret_key().await;
+		let devices = self
+			.devices
+			.get(
+				self.sync_group_pub_id,
+				&self.cloud_services,
+				&self.cloud_client,
+				&self.sync.revocations,
+			)
+			.await?;
+
+		let encrypted_messages = envelope::seal(
+			&iroh_secret_key,
+			current_device_pub_id,
+			devices,
+			|device_pub_id| {
+				self.sync
+					.revocations
+					.is_revoked(&uuid_to_bytes(&device_pub_id.0))
+			},
+			&secret_key,
+			batch.encrypted_messages.clone(),
+		)?;
+
+		let encrypted_messages_size = encrypted_messages.len();
+
+		#[allow(clippy::cast_sign_loss)]
+		let operations_count = batch.operations_count as u32;
+
+		debug!(
+			operations_count,
+			encrypted_messages_size, "Sending sync messages to cloud",
+		);
+
+		self.cloud_services
+			.pace(Direction::Upload, encrypted_messages_size)
+			.await;
+
+		self.cloud_client
+			.sync()
+			.messages()
+			.push(messages::push::Request {
+				access_token: self
+					.cloud_services
+					.token_refresher
+					.get_access_token()
+					.await?,
+				group_pub_id: self.sync_group_pub_id,
+				device_pub_id: current_device_pub_id,
+				key_hash,
+				operations_count,
+				time_range: (batch.start_time.to_utc(), batch.end_time.to_utc()),
+				encrypted_messages,
+			})
+			.await??;
+
+		debug!(
+			operations_count,
+			encrypted_messages_size, "Sent sync messages to cloud",
+		);
+
+		Ok(())
+	}
+
 	async fn get_latest_timestamp(
 		&self,
 		current_device_pub_id: devices::PubId,
 	) -> Result<LatestTimestamp, Error> {
 		if let Some(latest_timestamp) = &self.maybe_latest_timestamp {
 			Ok(*latest_timestamp)
+		} else if let Some(latest_timestamp) =
+			outbox::latest_timestamp(&self.sync.db, self.sync_group_pub_id).await?
+		{
+			// The cloud only has the operations from before the ones still queued
+			Ok(latest_timestamp)
 		} else {
 			let latest_time = match self
 				.cloud_client
@@ -83,6 +83,8 @@ pub enum NonCriticalFileIdentifierError {
 	},
 	#[error("file path without is_dir field: <file_path_id='{0}'>")]
 	FilePathWithoutIsDirField(file_path::id::Type),
+	#[error("the device failed to read: {0}")]
+	DeviceFailure(String),
 }
 
 #[derive(Debug, Clone)]
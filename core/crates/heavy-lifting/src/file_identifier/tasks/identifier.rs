@@ -438,9 +438,13 @@ fn handle_non_critical_errors(
 	let formatted_error = format!("<file_path_pub_id='{file_path_pub_id}', error={e}>");
 
 	errors.push(
-		file_identifier::NonCriticalFileIdentifierError::FailedToExtractFileMetadata(
-			formatted_error,
-		)
+		if e.is_device_failure() {
+			file_identifier::NonCriticalFileIdentifierError::DeviceFailure(formatted_error)
+		} else {
+			file_identifier::NonCriticalFileIdentifierError::FailedToExtractFileMetadata(
+				formatted_error,
+			)
+		}
 		.into(),
 	);
 }
@@ -111,6 +111,19 @@ pub enum NonCriticalIndexerError {
 	DispatchKeepWalking(String),
 	#[error("missing file_path data on database: {0}")]
 	MissingFilePathData(String),
+	#[error("the device failed to read or write: {0}")]
+	DeviceFailure(String),
+}
+
+impl NonCriticalIndexerError {
+	/// [`NonCriticalIndexerError::DeviceFailure`] when the device failed, `other` otherwise
+	fn from_io(e: &FileIOError, other: fn(String) -> Self) -> Self {
+		if e.is_device_failure() {
+			Self::DeviceFailure(e.to_string())
+		} else {
+			other(e.to_string())
+		}
+	}
 }
 
 fn chunk_db_queries<'db, 'iso>(
@@ -1,7 +1,10 @@
 use crate::{indexer, Error};
 
 use sd_core_file_path_helper::{FilePathMetadata, IsolatedFilePathDataParts};
-use sd_core_sync::SyncManager;
+use sd_core_sync::{
+	selective::{self, SyncRules},
+	SyncManager,
+};
 
 use sd_prisma::{
 	prisma::{device, file_path, location, PrismaClient},
@@ -73,7 +76,7 @@ impl Task<Error> for Saver {
 		use file_path::{
 			create_unchecked, date_created, date_indexed, date_modified, device, device_id,
 			extension, hidden, inode, is_dir, location, location_id, materialized_path, name,
-			size_in_bytes_bytes,
+			size_in_bytes_bytes, sync_mode,
 		};
 
 		let start_time = Instant::now();
@@ -88,6 +91,10 @@ impl Task<Error> for Saver {
 			..
 		} = self;
 
+		let rules = SyncRules::load(db, *location_id)
+			.await
+			.map_err(indexer::Error::from)?;
+
 		let (create_crdt_ops, paths): (Vec<_>, Vec<_>) = walked_entries
 			.drain(..)
 			.map(
@@ -118,7 +125,9 @@ impl Task<Error> for Saver {
 						new file_paths and they were not identified yet"
 					);
 
-					let (sync_params, db_params) = [
+					let mode = rules.mode_for(materialized_path, name, is_dir);
+
+					let (sync_params, mut db_params) = [
 						(
 							sync_entry!(
 								prisma_sync::location::SyncId {
@@ -150,13 +159,17 @@ impl Task<Error> for Saver {
 					]
 					.into_iter()
 					.unzip::<_, _, Vec<_>, Vec<_>>();
+					db_params.push(sync_mode::set(mode.to_db()));
 
 					(
-						sync.shared_create(
-							prisma_sync::file_path::SyncId {
-								pub_id: pub_id.to_db(),
-							},
-							sync_params,
+						selective::strip(
+							sync.shared_create(
+								prisma_sync::file_path::SyncId {
+									pub_id: pub_id.to_db(),
+								},
+								sync_params,
+							),
+							mode,
 						),
 						create_unchecked(pub_id.into(), db_params),
 					)
@@ -164,7 +177,7 @@ impl Task<Error> for Saver {
 			)
 			.unzip();
 
-		if create_crdt_ops.is_empty() && paths.is_empty() {
+		if paths.is_empty() {
 			return Ok(ExecStatus::Done(
 				Output {
 					saved_count: 0,
@@ -174,17 +187,18 @@ impl Task<Error> for Saver {
 			));
 		}
 
+		// The ones under local only directories have no operation
+		let create_crdt_ops = create_crdt_ops.into_iter().flatten().collect::<Vec<_>>();
+		let query = db.file_path().create_many(paths).skip_duplicates();
+
 		#[allow(clippy::cast_sign_loss)]
-		let saved_count = sync
-			.write_ops(
-				db,
-				(
-					create_crdt_ops,
-					db.file_path().create_many(paths).skip_duplicates(),
-				),
-			)
-			.await
-			.map_err(indexer::Error::from)? as u64;
+		let saved_count = if create_crdt_ops.is_empty() {
+			query.exec().await.map_err(indexer::Error::from)?
+		} else {
+			sync.write_ops(db, (create_crdt_ops, query))
+				.await
+				.map_err(indexer::Error::from)?
+		} as u64;
 
 		let save_duration = start_time.elapsed();
 
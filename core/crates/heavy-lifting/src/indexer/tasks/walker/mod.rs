@@ -229,8 +229,9 @@ where
 							}
 							Err(e) => {
 								errors.push(NonCriticalError::Indexer(
-									indexer::NonCriticalIndexerError::FailedDirectoryEntry(
-										FileIOError::from((&path, e)).to_string(),
+									indexer::NonCriticalIndexerError::from_io(
+										&FileIOError::from((&path, e)),
+										indexer::NonCriticalIndexerError::FailedDirectoryEntry,
 									),
 								));
 							}
@@ -627,8 +628,9 @@ async fn collect_metadata(
 			fs::metadata(&current_path)
 				.await
 				.map_err(|e| {
-					indexer::NonCriticalIndexerError::Metadata(
-						FileIOError::from((&current_path, e)).to_string(),
+					indexer::NonCriticalIndexerError::from_io(
+						&FileIOError::from((&current_path, e)),
+						indexer::NonCriticalIndexerError::Metadata,
 					)
 				})
 				.and_then(|metadata| {
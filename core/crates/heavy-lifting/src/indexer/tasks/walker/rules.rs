@@ -80,8 +80,9 @@ pub(super) async fn process_rules_results(
 				fs::metadata(&ancestor_path)
 					.await
 					.map_err(|e| {
-						indexer::NonCriticalIndexerError::Metadata(
-							FileIOError::from((&ancestor_path, e)).to_string(),
+						indexer::NonCriticalIndexerError::from_io(
+							&FileIOError::from((&ancestor_path, e)),
+							indexer::NonCriticalIndexerError::Metadata,
 						)
 					})
 					.and_then(|metadata| {
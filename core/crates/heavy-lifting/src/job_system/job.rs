@@ -12,6 +12,7 @@ use std::{
 	fmt,
 	hash::{Hash, Hasher},
 	marker::PhantomData,
+	mem,
 	ops::{Deref, DerefMut},
 	panic::AssertUnwindSafe,
 	path::Path,
@@ -60,6 +61,8 @@ pub enum JobName {
 	Delete,
 	Erase,
 	FileValidator,
+	VolumeMaintenance,
+	VolumeBreakdown,
 }
 
 pub enum ReturnStatus {
@@ -294,6 +297,31 @@ impl JobOutput {
 			non_critical_errors: report.non_critical_errors.clone(),
 		}
 	}
+
+	#[must_use]
+	pub const fn id(&self) -> JobId {
+		self.id
+	}
+
+	#[must_use]
+	pub const fn status(&self) -> Status {
+		self.status
+	}
+
+	#[must_use]
+	pub const fn job_name(&self) -> JobName {
+		self.job_name
+	}
+
+	#[must_use]
+	pub fn metadata(&self) -> &[ReportMetadata] {
+		&self.metadata
+	}
+
+	#[must_use]
+	pub fn non_critical_errors(&self) -> &[NonCriticalError] {
+		&self.non_critical_errors
+	}
 }
 
 #[derive(Debug, Serialize, Type)]
@@ -682,6 +710,8 @@ pub trait DynJob<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>>:
 
 	fn next_jobs(&self) -> &VecDeque<Box<dyn DynJob<OuterCtx, JobCtx>>>;
 
+	fn take_next_jobs(&mut self) -> VecDeque<Box<dyn DynJob<OuterCtx, JobCtx>>>;
+
 	async fn serialize(self: Box<Self>) -> Result<Option<Vec<u8>>, rmp_serde::encode::Error>;
 
 	fn dispatch(
@@ -734,6 +764,10 @@ where
 		&self.next_jobs
 	}
 
+	fn take_next_jobs(&mut self) -> VecDeque<Box<dyn DynJob<OuterCtx, JobCtx>>> {
+		mem::take(&mut self.next_jobs)
+	}
+
 	async fn serialize(self: Box<Self>) -> Result<Option<Vec<u8>>, rmp_serde::encode::Error> {
 		self.job.serialize().await
 	}
@@ -9,7 +9,10 @@ use std::{
 	collections::hash_map::HashMap,
 	panic,
 	path::{Path, PathBuf},
-	sync::Arc,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
 };
 
 use async_channel as chan;
@@ -21,6 +24,7 @@ use uuid::Uuid;
 
 mod error;
 pub mod job;
+mod queue;
 pub mod report;
 mod runner;
 mod store;
@@ -50,6 +54,7 @@ pub struct JobSystem<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> {
 	msgs_tx: chan::Sender<RunnerMessage<OuterCtx, JobCtx>>,
 	job_outputs_rx: chan::Receiver<(JobId, Result<JobOutput, Error>)>,
 	store_jobs_file: Arc<PathBuf>,
+	max_jobs_per_ctx: Arc<AtomicUsize>,
 	runner_handle: RefCell<Option<JoinHandle<()>>>,
 }
 
@@ -63,9 +68,11 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystem<OuterCtx, J
 		let (msgs_tx, msgs_rx) = chan::bounded(8);
 
 		let store_jobs_file = Arc::new(data_directory.as_ref().join(PENDING_JOBS_FILE));
+		let max_jobs_per_ctx = Arc::new(AtomicUsize::new(usize::MAX));
 
 		let runner_handle = RefCell::new(Some(spawn({
 			let store_jobs_file = Arc::clone(&store_jobs_file);
+			let max_jobs_per_ctx = Arc::clone(&max_jobs_per_ctx);
 			async move {
 				trace!("Job System Runner starting...");
 				while let Err(e) = spawn({
@@ -75,6 +82,7 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystem<OuterCtx, J
 					let job_done_rx = job_done_rx.clone();
 					let job_outputs_tx = job_outputs_tx.clone();
 					let msgs_rx = msgs_rx.clone();
+					let max_jobs_per_ctx = Arc::clone(&max_jobs_per_ctx);
 
 					async move {
 						run(
@@ -82,6 +90,7 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystem<OuterCtx, J
 								base_dispatcher,
 								job_return_status_tx,
 								job_outputs_tx,
+								max_jobs_per_ctx,
 							),
 							store_jobs_file.as_ref(),
 							msgs_rx,
@@ -109,10 +118,29 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystem<OuterCtx, J
 			msgs_tx,
 			job_outputs_rx,
 			store_jobs_file,
+			max_jobs_per_ctx,
 			runner_handle,
 		}
 	}
 
+	/// Limits how many jobs run at the same time for each [`OuterContext`], the others wait in a
+	/// queue until a slot frees up. Jobs resumed from disk aren't limited, they were already
+	/// running when the system was shutdown.
+	///
+	/// # Panics
+	///
+	/// Panics if `max_jobs` is zero or if internal channels are unexpectedly closed
+	pub async fn set_max_jobs_per_ctx(&self, max_jobs: usize) {
+		assert!(max_jobs > 0, "max_jobs must be greater than 0");
+
+		if self.max_jobs_per_ctx.swap(max_jobs, Ordering::Relaxed) < max_jobs {
+			self.msgs_tx
+				.send(RunnerMessage::DispatchQueuedJobs)
+				.await
+				.expect("runner msgs channel unexpectedly closed on dispatch queued jobs request");
+		}
+	}
+
 	pub async fn init(
 		&self,
 		previously_existing_contexts: &HashMap<Uuid, OuterCtx>,
@@ -0,0 +1,219 @@
+use std::collections::{HashMap, VecDeque};
+
+use uuid::Uuid;
+
+use super::JobId;
+
+/// What a library should do with its next free slot.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum Next<T> {
+	/// Resume a paused job that was asked to resume while the library was at its limit
+	Resume(JobId),
+	/// Start a job that was queued while the library was at its limit
+	Start(JobId, T),
+}
+
+/// Jobs of each library waiting for a free slot, in the order they got there.
+///
+/// Paused jobs asked to resume go before the queued ones, as they already ran once.
+#[derive(Debug)]
+pub(super) struct JobQueue<T> {
+	resumes_by_ctx_id: HashMap<Uuid, VecDeque<JobId>>,
+	jobs_by_ctx_id: HashMap<Uuid, VecDeque<(JobId, T)>>,
+}
+
+impl<T> Default for JobQueue<T> {
+	fn default() -> Self {
+		Self {
+			resumes_by_ctx_id: HashMap::new(),
+			jobs_by_ctx_id: HashMap::new(),
+		}
+	}
+}
+
+impl<T> JobQueue<T> {
+	pub(super) fn push(&mut self, ctx_id: Uuid, job_id: JobId, job: T) {
+		self.jobs_by_ctx_id
+			.entry(ctx_id)
+			.or_default()
+			.push_back((job_id, job));
+	}
+
+	pub(super) fn push_resume(&mut self, ctx_id: Uuid, job_id: JobId) {
+		let resumes = self.resumes_by_ctx_id.entry(ctx_id).or_default();
+		if !resumes.contains(&job_id) {
+			resumes.push_back(job_id);
+		}
+	}
+
+	/// Takes what should use the next slot of the library, if it has one free.
+	pub(super) fn next(&mut self, ctx_id: Uuid, running: usize, max: usize) -> Option<Next<T>> {
+		if running >= max {
+			return None;
+		}
+
+		if let Some(job_id) = pop_front(&mut self.resumes_by_ctx_id, ctx_id) {
+			return Some(Next::Resume(job_id));
+		}
+
+		pop_front(&mut self.jobs_by_ctx_id, ctx_id).map(|(job_id, job)| Next::Start(job_id, job))
+	}
+
+	pub(super) fn contains(&self, job_id: JobId) -> bool {
+		self.jobs_by_ctx_id
+			.values()
+			.flatten()
+			.any(|(queued_id, _)| *queued_id == job_id)
+	}
+
+	/// Takes a queued job out, for when it's canceled before ever getting a slot.
+	pub(super) fn remove(&mut self, job_id: JobId) -> Option<T> {
+		let (_, job) = remove_where(&mut self.jobs_by_ctx_id, |(id, _)| *id == job_id)?;
+		Some(job)
+	}
+
+	/// Forgets a pending resume, for when the job is paused, canceled or shutdown again.
+	pub(super) fn remove_resume(&mut self, job_id: JobId) -> bool {
+		remove_where(&mut self.resumes_by_ctx_id, |id| *id == job_id).is_some()
+	}
+
+	pub(super) fn ctx_ids(&self) -> Vec<Uuid> {
+		let mut ctx_ids = self
+			.resumes_by_ctx_id
+			.keys()
+			.chain(self.jobs_by_ctx_id.keys())
+			.copied()
+			.collect::<Vec<_>>();
+		ctx_ids.sort_unstable();
+		ctx_ids.dedup();
+		ctx_ids
+	}
+
+	/// Takes all the queued jobs out, pending resumes are dropped as their jobs are paused anyway.
+	pub(super) fn drain(&mut self) -> HashMap<Uuid, VecDeque<(JobId, T)>> {
+		self.resumes_by_ctx_id.clear();
+		std::mem::take(&mut self.jobs_by_ctx_id)
+	}
+
+	pub(super) fn is_empty(&self) -> bool {
+		self.resumes_by_ctx_id.is_empty() && self.jobs_by_ctx_id.is_empty()
+	}
+}
+
+fn pop_front<V>(queues: &mut HashMap<Uuid, VecDeque<V>>, ctx_id: Uuid) -> Option<V> {
+	let queue = queues.get_mut(&ctx_id)?;
+	let value = queue.pop_front();
+	if queue.is_empty() {
+		queues.remove(&ctx_id);
+	}
+	value
+}
+
+fn remove_where<V>(
+	queues: &mut HashMap<Uuid, VecDeque<V>>,
+	predicate: impl Fn(&V) -> bool,
+) -> Option<V> {
+	let (ctx_id, value) = queues.iter_mut().find_map(|(ctx_id, queue)| {
+		queue
+			.iter()
+			.position(&predicate)
+			.and_then(|idx| queue.remove(idx))
+			.map(|value| (*ctx_id, value))
+	})?;
+
+	if queues.get(&ctx_id).is_some_and(VecDeque::is_empty) {
+		queues.remove(&ctx_id);
+	}
+
+	Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_queued_jobs_wait_for_a_free_slot() {
+		let ctx_id = Uuid::new_v4();
+		let job_id = JobId::new_v4();
+		let mut queue = JobQueue::default();
+
+		queue.push(ctx_id, job_id, "job");
+
+		assert!(queue.contains(job_id));
+		assert_eq!(queue.next(ctx_id, 2, 2), None);
+		assert!(queue.contains(job_id));
+
+		assert_eq!(queue.next(ctx_id, 1, 2), Some(Next::Start(job_id, "job")));
+		assert!(queue.is_empty());
+	}
+
+	#[test]
+	fn test_queued_jobs_are_dispatched_in_order_per_library() {
+		let (ctx_a, ctx_b) = (Uuid::new_v4(), Uuid::new_v4());
+		let (first, second, other) = (JobId::new_v4(), JobId::new_v4(), JobId::new_v4());
+		let mut queue = JobQueue::default();
+
+		queue.push(ctx_a, first, "first");
+		queue.push(ctx_b, other, "other");
+		queue.push(ctx_a, second, "second");
+
+		assert_eq!(queue.next(ctx_a, 0, 1), Some(Next::Start(first, "first")));
+		assert_eq!(queue.next(ctx_a, 0, 1), Some(Next::Start(second, "second")));
+		assert_eq!(queue.next(ctx_a, 0, 1), None);
+		assert_eq!(queue.ctx_ids(), vec![ctx_b]);
+		assert_eq!(queue.next(ctx_b, 0, 1), Some(Next::Start(other, "other")));
+	}
+
+	#[test]
+	fn test_canceled_queued_job_is_never_dispatched() {
+		let ctx_id = Uuid::new_v4();
+		let (canceled, kept) = (JobId::new_v4(), JobId::new_v4());
+		let mut queue = JobQueue::default();
+
+		queue.push(ctx_id, canceled, "canceled");
+		queue.push(ctx_id, kept, "kept");
+
+		assert_eq!(queue.remove(canceled), Some("canceled"));
+		assert_eq!(queue.remove(canceled), None);
+		assert!(!queue.contains(canceled));
+
+		assert_eq!(queue.next(ctx_id, 0, 1), Some(Next::Start(kept, "kept")));
+		assert_eq!(queue.next(ctx_id, 0, 1), None);
+	}
+
+	#[test]
+	fn test_resume_at_the_limit_waits_and_goes_first() {
+		let ctx_id = Uuid::new_v4();
+		let (paused, queued) = (JobId::new_v4(), JobId::new_v4());
+		let mut queue = JobQueue::default();
+
+		queue.push(ctx_id, queued, "queued");
+		queue.push_resume(ctx_id, paused);
+		queue.push_resume(ctx_id, paused);
+
+		assert_eq!(queue.next(ctx_id, 1, 1), None);
+
+		assert_eq!(queue.next(ctx_id, 0, 1), Some(Next::Resume(paused)));
+		assert!(!queue.remove_resume(paused));
+		assert_eq!(
+			queue.next(ctx_id, 0, 1),
+			Some(Next::Start(queued, "queued"))
+		);
+		assert!(queue.is_empty());
+	}
+
+	#[test]
+	fn test_paused_again_job_drops_its_pending_resume() {
+		let ctx_id = Uuid::new_v4();
+		let paused = JobId::new_v4();
+		let mut queue = JobQueue::<&str>::default();
+
+		queue.push_resume(ctx_id, paused);
+
+		assert!(queue.remove_resume(paused));
+		assert!(!queue.remove_resume(paused));
+		assert_eq!(queue.next(ctx_id, 0, 1), None);
+		assert!(queue.is_empty());
+	}
+}
@@ -9,6 +9,10 @@ use std::{
 	mem,
 	path::Path,
 	pin::pin,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
 	time::Duration,
 };
 
@@ -31,6 +35,7 @@ use uuid::Uuid;
 
 use super::{
 	job::{DynJob, JobHandle, JobName, JobOutput, OuterContext, ReturnStatus},
+	queue::{JobQueue, Next},
 	report::{self, ReportOutputMetadata},
 	store::{StoredJob, StoredJobEntry},
 	Command, JobId, JobSystemError, SerializedTasks,
@@ -73,6 +78,13 @@ pub(super) enum RunnerMessage<OuterCtx: OuterContext, JobCtx: JobContext<OuterCt
 		ctx_id: Uuid,
 		ack_tx: oneshot::Sender<bool>,
 	},
+	DispatchQueuedJobs,
+}
+
+struct QueuedJob<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> {
+	location_id: location::id::Type,
+	dyn_job: Box<dyn DynJob<OuterCtx, JobCtx>>,
+	ctx: OuterCtx,
 }
 
 struct JobsWorktables {
@@ -88,6 +100,8 @@ pub(super) struct JobSystemRunner<OuterCtx: OuterContext, JobCtx: JobContext<Out
 	base_dispatcher: BaseTaskDispatcher<Error>,
 	handles: HashMap<JobId, JobHandle<OuterCtx, JobCtx>>,
 	worktables: JobsWorktables,
+	max_jobs_per_ctx: Arc<AtomicUsize>,
+	queue: JobQueue<QueuedJob<OuterCtx, JobCtx>>,
 	job_return_status_tx: chan::Sender<(JobId, Result<ReturnStatus, Error>)>,
 	job_outputs_tx: chan::Sender<(JobId, Result<JobOutput, Error>)>,
 }
@@ -97,6 +111,7 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystemRunner<Outer
 		base_dispatcher: BaseTaskDispatcher<Error>,
 		job_return_status_tx: chan::Sender<(JobId, Result<ReturnStatus, Error>)>,
 		job_outputs_tx: chan::Sender<(JobId, Result<JobOutput, Error>)>,
+		max_jobs_per_ctx: Arc<AtomicUsize>,
 	) -> Self {
 		Self {
 			on_shutdown_mode: false,
@@ -109,6 +124,8 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystemRunner<Outer
 				running_jobs_set: HashSet::with_capacity(JOBS_INITIAL_CAPACITY),
 				jobs_to_store_by_ctx_id: HashMap::new(),
 			},
+			max_jobs_per_ctx,
+			queue: JobQueue::default(),
 			job_return_status_tx,
 			job_outputs_tx,
 		}
@@ -121,14 +138,155 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystemRunner<Outer
 		dyn_job: Box<dyn DynJob<OuterCtx, JobCtx>>,
 		ctx: OuterCtx,
 		maybe_existing_tasks: Option<SerializedTasks>,
+	) -> Result<(), JobSystemError> {
+		let job_name = dyn_job.job_name();
+
+		let job_hash = dyn_job.hash();
+		if let Some(&already_running_id) = self.worktables.job_hashes.get(&job_hash) {
+			return Err(JobSystemError::AlreadyRunning {
+				new_id: job_id,
+				already_running_id,
+				job_name,
+			});
+		}
+
+		// Resumed jobs were running when the node stopped, so they were already under the limit
+		if maybe_existing_tasks.is_none() && !self.has_free_slot(ctx.id()) {
+			return self.enqueue_job(job_id, location_id, dyn_job, ctx).await;
+		}
+
+		self.worktables.job_hashes.insert(job_hash, job_id);
+		self.worktables.job_hashes_by_id.insert(job_id, job_hash);
+
+		self.start_job(job_id, location_id, dyn_job, ctx, maybe_existing_tasks)
+			.await
+	}
+
+	async fn enqueue_job(
+		&mut self,
+		job_id: JobId,
+		location_id: location::id::Type,
+		mut dyn_job: Box<dyn DynJob<OuterCtx, JobCtx>>,
+		ctx: OuterCtx,
+	) -> Result<(), JobSystemError> {
+		let job_hash = dyn_job.hash();
+		let now = Utc::now();
+
+		// The reports are created right away, so the queued jobs show up in the frontend and can
+		// be stored with the others on shutdown
+		dyn_job.report_mut().create(ctx.db(), now).await?;
+
+		let mut next_jobs = dyn_job.take_next_jobs();
+		for (idx, next_job) in next_jobs.iter_mut().enumerate() {
+			next_job
+				.report_mut()
+				.create(ctx.db(), now + Duration::from_secs((idx + 1) as u64))
+				.await?;
+		}
+		dyn_job.set_next_jobs(next_jobs);
+
+		self.worktables.job_hashes.insert(job_hash, job_id);
+		self.worktables.job_hashes_by_id.insert(job_id, job_hash);
+
+		trace!(
+			%job_id,
+			job_name = %dyn_job.job_name(),
+			"Library is at its jobs limit, queueing job;",
+		);
+
+		ctx.invalidate_query("jobs.reports");
+
+		self.queue.push(
+			ctx.id(),
+			job_id,
+			QueuedJob {
+				location_id,
+				dyn_job,
+				ctx,
+			},
+		);
+
+		Ok(())
+	}
+
+	fn running_jobs(&self, ctx_id: Uuid) -> usize {
+		self.handles
+			.values()
+			.filter(|handle| handle.ctx.id() == ctx_id && handle.is_running)
+			.count()
+	}
+
+	fn has_free_slot(&self, ctx_id: Uuid) -> bool {
+		self.running_jobs(ctx_id) < self.max_jobs_per_ctx.load(Ordering::Relaxed)
+	}
+
+	async fn dispatch_queued_jobs(&mut self, ctx_id: Uuid) {
+		while let Some(next) = self.queue.next(
+			ctx_id,
+			self.running_jobs(ctx_id),
+			self.max_jobs_per_ctx.load(Ordering::Relaxed),
+		) {
+			match next {
+				Next::Resume(job_id) => self.resume_paused_job(job_id).await,
+				Next::Start(
+					job_id,
+					QueuedJob {
+						location_id,
+						dyn_job,
+						ctx,
+					},
+				) => {
+					if let Err(e) = self
+						.start_job(job_id, location_id, dyn_job, ctx, None)
+						.await
+					{
+						error!(%job_id, ?e, "Failed to start queued job;");
+					}
+				}
+			}
+		}
+	}
+
+	async fn resume_paused_job(&mut self, job_id: JobId) {
+		// The job may have been canceled or finished while waiting for its slot
+		let Some(handle) = self
+			.handles
+			.get_mut(&job_id)
+			.filter(|handle| !handle.is_running)
+		else {
+			return;
+		};
+
+		let (ack_tx, ack_rx) = oneshot::channel();
+		handle.is_running = true;
+		handle.send_command(Command::Resume, ack_tx).await;
+		handle.ctx.invalidate_query("jobs.isActive");
+		handle.ctx.invalidate_query("jobs.reports");
+
+		if let Ok(Err(e)) = ack_rx.await {
+			error!(%job_id, ?e, "Failed to resume job waiting for a free slot;");
+		}
+	}
+
+	async fn dispatch_all_queued_jobs(&mut self) {
+		for ctx_id in self.queue.ctx_ids() {
+			self.dispatch_queued_jobs(ctx_id).await;
+		}
+	}
+
+	async fn start_job(
+		&mut self,
+		job_id: JobId,
+		location_id: location::id::Type,
+		dyn_job: Box<dyn DynJob<OuterCtx, JobCtx>>,
+		ctx: OuterCtx,
+		maybe_existing_tasks: Option<SerializedTasks>,
 	) -> Result<(), JobSystemError> {
 		let Self {
 			base_dispatcher,
 			handles,
 			worktables:
 				JobsWorktables {
-					job_hashes,
-					job_hashes_by_id,
 					running_jobs_by_job_id,
 					running_jobs_set,
 					..
@@ -139,21 +297,9 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystemRunner<Outer
 
 		let job_name = dyn_job.job_name();
 
-		let job_hash = dyn_job.hash();
-		if let Some(&already_running_id) = job_hashes.get(&job_hash) {
-			return Err(JobSystemError::AlreadyRunning {
-				new_id: job_id,
-				already_running_id,
-				job_name,
-			});
-		}
-
 		running_jobs_by_job_id.insert(job_id, (job_name, location_id));
 		running_jobs_set.insert((job_name, location_id));
 
-		job_hashes.insert(job_hash, job_id);
-		job_hashes_by_id.insert(job_id, job_hash);
-
 		let mut handle = if maybe_existing_tasks.is_some() {
 			dyn_job.resume(
 				base_dispatcher.clone(),
@@ -196,7 +342,10 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystemRunner<Outer
 		if let Some(handle) = self.handles.get_mut(&job_id) {
 			match (command, handle.is_running) {
 				(Command::Pause, false) => {
-					warn!("Tried to pause a job already paused");
+					// Unless it was waiting for a slot to resume, then it stays paused
+					if !self.queue.remove_resume(job_id) {
+						warn!("Tried to pause a job already paused");
+					}
 					return ack_tx.send(Ok(())).expect(
 						"ack channel closed before sending response to already paused job",
 					);
@@ -212,14 +361,37 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystemRunner<Outer
 			match command {
 				Command::Pause | Command::Cancel | Command::Shutdown => {
 					handle.is_running = false;
+					self.queue.remove_resume(job_id);
 				}
 				Command::Resume => {
-					handle.is_running = true;
+					let ctx_id = handle.ctx.id();
+					if !self.has_free_slot(ctx_id) {
+						trace!(%job_id, "Library is at its jobs limit, queueing resume;");
+						self.queue.push_resume(ctx_id, job_id);
+						return ack_tx
+							.send(Ok(()))
+							.expect("ack channel closed before sending queued resume response");
+					}
 				}
 			}
+
+			let handle = self.handles.get_mut(&job_id).expect("it must be here");
+			if matches!(command, Command::Resume) {
+				handle.is_running = true;
+			}
 			handle.send_command(command, ack_tx).await;
 			handle.ctx.invalidate_query("jobs.isActive");
 			handle.ctx.invalidate_query("jobs.reports");
+
+			// A paused job frees its slot for the next queued one
+			if matches!(command, Command::Pause) {
+				let ctx_id = handle.ctx.id();
+				self.dispatch_queued_jobs(ctx_id).await;
+			}
+		} else if matches!(command, Command::Cancel) && self.is_queued(job_id) {
+			ack_tx
+				.send(self.cancel_queued_job(job_id).await)
+				.expect("ack channel closed before sending cancel response");
 		} else {
 			error!("Job not found");
 			ack_tx
@@ -230,8 +402,34 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystemRunner<Outer
 		}
 	}
 
+	fn is_queued(&self, job_id: JobId) -> bool {
+		self.queue.contains(job_id)
+	}
+
+	async fn cancel_queued_job(&mut self, job_id: JobId) -> Result<(), JobSystemError> {
+		let Some(QueuedJob {
+			mut dyn_job, ctx, ..
+		}) = self.queue.remove(job_id)
+		else {
+			return Err(JobSystemError::NotFound(job_id));
+		};
+
+		if let Some(job_hash) = self.worktables.job_hashes_by_id.remove(&job_id) {
+			self.worktables.job_hashes.remove(&job_hash);
+		}
+
+		let report = dyn_job.report_mut();
+		report.status = report::Status::Canceled;
+		report.update(ctx.db()).await?;
+
+		ctx.invalidate_query("jobs.reports");
+
+		Ok(())
+	}
+
 	fn is_empty(&self) -> bool {
 		self.handles.is_empty()
+			&& self.queue.is_empty()
 			&& self.worktables.job_hashes.is_empty()
 			&& self.worktables.job_hashes_by_id.is_empty()
 	}
@@ -283,6 +481,7 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystemRunner<Outer
 
 		let mut handle = handles.remove(&job_id).expect("it must be here");
 		handle.run_time += handle.start_time.elapsed();
+		let ctx_id = handle.ctx.id();
 
 		handle
 			.ctx
@@ -382,9 +581,70 @@ impl<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>> JobSystemRunner<Outer
 		handle.ctx.invalidate_query("jobs.isActive");
 		handle.ctx.invalidate_query("jobs.reports");
 
+		if !*on_shutdown_mode {
+			self.dispatch_queued_jobs(ctx_id).await;
+		}
+
 		Ok(())
 	}
 
+	/// Moves the jobs still waiting for a slot to the ones stored on shutdown, they never ran so
+	/// they don't have any tasks to serialize.
+	async fn store_queued_jobs(&mut self) {
+		for (ctx_id, queue) in self.queue.drain() {
+			for (
+				job_id,
+				QueuedJob {
+					location_id,
+					mut dyn_job,
+					..
+				},
+			) in queue
+			{
+				if let Some(job_hash) = self.worktables.job_hashes_by_id.remove(&job_id) {
+					self.worktables.job_hashes.remove(&job_hash);
+				}
+
+				let job_name = dyn_job.job_name();
+				let name = dyn_job.report_mut().name;
+				let next_jobs = dyn_job.take_next_jobs();
+
+				match dyn_job.serialize().await {
+					Ok(Some(serialized_job)) => {
+						self.worktables
+							.jobs_to_store_by_ctx_id
+							.entry(ctx_id)
+							.or_default()
+							.push(StoredJobEntry {
+								location_id,
+								root_job: StoredJob {
+									id: job_id,
+									run_time: Duration::ZERO,
+									name,
+									serialized_job,
+								},
+								next_jobs: serialize_next_jobs_to_shutdown(
+									job_id, job_name, next_jobs,
+								)
+								.await
+								.unwrap_or_default(),
+							});
+
+						debug!(%name, "Queued job was serialized;");
+					}
+
+					Ok(None) => {
+						debug!(%name, "Queued job isn't resumable, dropping it;");
+					}
+
+					Err(e) => {
+						error!(%job_id, ?e, "Failed to serialize queued job;");
+					}
+				}
+			}
+		}
+	}
+
 	fn clean_memory(&mut self) {
 		if self.handles.capacity() > JOBS_INITIAL_CAPACITY
 			&& self.handles.len() < JOBS_INITIAL_CAPACITY
@@ -672,18 +932,22 @@ pub(super) async fn run<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>>(
 				runner.on_shutdown_mode = true;
 				// Consuming all pending return status messages
 				if !runner.is_empty() {
-					let mut job_return_status_stream = pin!(job_return_status_rx_to_shutdown);
+					runner.store_queued_jobs().await;
 
-					runner.dispatch_shutdown_command_to_jobs().await;
+					if runner.total_jobs() > 0 {
+						let mut job_return_status_stream = pin!(job_return_status_rx_to_shutdown);
 
-					debug!(
-						total_jobs = runner.total_jobs(),
-						"Waiting for jobs to shutdown before shutting down the job system...;",
-					);
+						runner.dispatch_shutdown_command_to_jobs().await;
 
-					while let Some((job_id, status)) = job_return_status_stream.next().await {
-						if let Err(e) = runner.process_return_status(job_id, status).await {
-							error!(?e, "Failed to process return status before shutting down;");
+						debug!(
+							total_jobs = runner.total_jobs(),
+							"Waiting for jobs to shutdown before shutting down the job system...;",
+						);
+
+						while let Some((job_id, status)) = job_return_status_stream.next().await {
+							if let Err(e) = runner.process_return_status(job_id, status).await {
+								error!(?e, "Failed to process return status before shutting down;");
+							}
 						}
 					}
 
@@ -696,6 +960,10 @@ pub(super) async fn run<OuterCtx: OuterContext, JobCtx: JobContext<OuterCtx>>(
 				return;
 			}
 
+			StreamMessage::RunnerMessage(RunnerMessage::DispatchQueuedJobs) => {
+				runner.dispatch_all_queued_jobs().await;
+			}
+
 			StreamMessage::RunnerMessage(RunnerMessage::CheckIfJobsAreRunning {
 				job_names,
 				location_id,
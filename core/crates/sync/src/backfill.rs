@@ -5,22 +5,208 @@ use sd_prisma::{
 	},
 	prisma_sync,
 };
-use sd_sync::{option_sync_entry, sync_entry, OperationFactory};
+use sd_sync::{option_sync_entry, sync_entry, CRDTOperation, OperationFactory};
 use sd_utils::chain_optional_iter;
 
-use std::future::Future;
+use std::{
+	future::Future,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Mutex, PoisonError,
+	},
+	time::Duration,
+};
 
+use chrono::{DateTime, Utc};
 use futures_concurrency::future::TryJoin;
-use tokio::time::Instant;
-use tracing::{debug, instrument};
+use serde::Serialize;
+use specta::Type;
+use tokio::{
+	sync::{futures::Notified, Notify},
+	time::Instant,
+};
+use tracing::{debug, info, instrument, warn};
+
+use super::{
+	crdt_op_unchecked_db, model_name,
+	selective::{self, SyncMode},
+	Error, ModelId, SyncManager,
+};
+
+#[derive(Debug, Clone, Default, Serialize, Type)]
+pub struct BackfillProgress {
+	pub running: bool,
+	pub paused: bool,
+	/// In the order they're backfilled
+	pub models: Vec<ModelProgress>,
+	/// From how fast the records were backfilled so far, unknown until some are
+	pub estimated_completion: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct ModelProgress {
+	pub model: String,
+	pub total: u32,
+	pub applied: u32,
+	pub remaining: u32,
+}
+
+/// How far the backfill is, with the pause the user can hold it on between two pages of records.
+/// Each page is written on its own, so a paused backfill holds no lock on the database, and a
+/// failed one takes the operations it wrote back out of the log so the next one starts over.
+#[derive(Debug, Default)]
+pub struct BackfillState {
+	inner: Mutex<BackfillInner>,
+	paused: AtomicBool,
+	resumed: Notify,
+	changed: Notify,
+}
+
+#[derive(Debug, Default)]
+struct BackfillInner {
+	running: bool,
+	models: Vec<(ModelId, ModelProgress)>,
+	/// Since when it runs without a pause, with how long it ran before it
+	active_since: Option<Instant>,
+	active_before: Duration,
+}
+
+impl BackfillState {
+	#[must_use]
+	pub fn progress(&self) -> BackfillProgress {
+		let inner = self.lock();
+
+		let (applied, remaining) =
+			inner
+				.models
+				.iter()
+				.fold((0, 0), |(applied, remaining), (_, model)| {
+					(
+						applied + u64::from(model.applied),
+						remaining + u64::from(model.remaining),
+					)
+				});
+		let active = inner.active_before
+			+ inner
+				.active_since
+				.map_or(Duration::ZERO, |since| since.elapsed());
+
+		BackfillProgress {
+			running: inner.running,
+			paused: self.paused.load(Ordering::Relaxed),
+			models: inner
+				.models
+				.iter()
+				.map(|(_, model)| model.clone())
+				.collect(),
+			estimated_completion: (inner.running && applied > 0)
+				.then(|| {
+					#[allow(clippy::cast_precision_loss)] // It's only an estimate
+					let left = active.as_secs_f64() * remaining as f64 / applied as f64;
+					Duration::try_from_secs_f64(left).ok()
+				})
+				.flatten()
+				.and_then(|left| chrono::Duration::from_std(left).ok())
+				.map(|left| Utc::now() + left),
+		}
+	}
 
-use super::{crdt_op_unchecked_db, Error, SyncManager};
+	/// Resolves once the progress changed
+	pub fn changed(&self) -> Notified<'_> {
+		self.changed.notified()
+	}
+
+	pub fn pause(&self) {
+		let mut inner = self.lock();
+		if let Some(since) = inner.active_since.take() {
+			inner.active_before += since.elapsed();
+		}
+		drop(inner);
+
+		self.paused.store(true, Ordering::Relaxed);
+		self.changed.notify_waiters();
+	}
+
+	pub fn resume(&self) {
+		let mut inner = self.lock();
+		if inner.running && inner.active_since.is_none() {
+			inner.active_since = Some(Instant::now());
+		}
+		drop(inner);
+
+		self.paused.store(false, Ordering::Relaxed);
+		self.resumed.notify_waiters();
+		self.changed.notify_waiters();
+	}
+
+	fn start(&self, totals: impl IntoIterator<Item = (ModelId, i64)>) {
+		let paused = self.paused.load(Ordering::Relaxed);
+		*self.lock() = BackfillInner {
+			running: true,
+			models: totals
+				.into_iter()
+				.map(|(model_id, total)| {
+					let total = u32::try_from(total).unwrap_or(u32::MAX);
+					(
+						model_id,
+						ModelProgress {
+							model: model_name(model_id)
+								.map_or_else(|| model_id.to_string(), ToString::to_string),
+							total,
+							applied: 0,
+							remaining: total,
+						},
+					)
+				})
+				.collect(),
+			active_since: (!paused).then(Instant::now),
+			active_before: Duration::ZERO,
+		};
+		self.changed.notify_waiters();
+	}
+
+	fn advance(&self, model_id: ModelId, count: usize) {
+		let count = u32::try_from(count).unwrap_or(u32::MAX);
+		if let Some((_, model)) = self
+			.lock()
+			.models
+			.iter_mut()
+			.find(|(id, _)| *id == model_id)
+		{
+			model.applied = model.applied.saturating_add(count).min(model.total);
+			model.remaining = model.total - model.applied;
+		}
+		self.changed.notify_waiters();
+	}
+
+	fn finish(&self) {
+		let mut inner = self.lock();
+		inner.running = false;
+		inner.active_since = None;
+		drop(inner);
+
+		self.paused.store(false, Ordering::Relaxed);
+		self.changed.notify_waiters();
+	}
+
+	async fn wait_if_paused(&self) {
+		loop {
+			let resumed = self.resumed.notified();
+			if !self.paused.load(Ordering::Relaxed) {
+				break;
+			}
+			resumed.await;
+		}
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<'_, BackfillInner> {
+		self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
 
 /// Takes all the syncable data in the database and generates [`CRDTOperations`] for it.
 /// This is a requirement before the library can sync.
 pub async fn backfill_operations(sync: &SyncManager) -> Result<(), Error> {
-	let _lock_guard = sync.sync_lock.lock().await;
-
 	let db = &sync.db;
 
 	let local_device = db
@@ -32,44 +218,147 @@ pub async fn backfill_operations(sync: &SyncManager) -> Result<(), Error> {
 
 	let local_device_id = local_device.id;
 
-	db._transaction()
-		.with_timeout(9_999_999_999)
-		.run(|db| async move {
-			debug!("backfill started");
-			let start = Instant::now();
-			db.crdt_operation()
-				.delete_many(vec![crdt_operation::device_pub_id::equals(
-					sync.device_pub_id.to_db(),
-				)])
-				.exec()
-				.await?;
-
-			backfill_device(&db, sync, local_device).await?;
-
-			(
-				backfill_volumes(&db, sync, local_device_id),
-				paginate_tags(&db, sync),
-				paginate_locations(&db, sync, local_device_id),
-				paginate_objects(&db, sync, local_device_id),
-				paginate_labels(&db, sync),
-			)
-				.try_join()
-				.await?;
-
-			(
-				paginate_exif_datas(&db, sync, local_device_id),
-				paginate_file_paths(&db, sync, local_device_id),
-				paginate_tags_on_objects(&db, sync, local_device_id),
-				paginate_labels_on_objects(&db, sync, local_device_id),
-			)
-				.try_join()
-				.await?;
-
-			debug!(elapsed = ?start.elapsed(), "backfill ended");
-
-			Ok(())
-		})
-		.await
+	sync.backfill.start(
+		count_records(db, local_device_id)
+			.await?
+			.into_iter()
+			.filter(|(model_id, _)| sync.toggles.is_enabled(*model_id)),
+	);
+	info!("Backfill started;");
+
+	let res = backfill(sync, local_device).await;
+	if res.is_err() {
+		if let Err(e) = remove_operations(sync).await {
+			warn!(?e, "Failed to remove the operations of a failed backfill;");
+		}
+	}
+
+	sync.backfill.finish();
+
+	res
+}
+
+async fn backfill(sync: &SyncManager, local_device: device::Data) -> Result<(), Error> {
+	let db = &sync.db;
+	let local_device_id = local_device.id;
+
+	debug!("backfill started");
+	let start = Instant::now();
+
+	{
+		let _lock_guard = sync.sync_lock.lock().await;
+		remove_operations(sync).await?;
+		backfill_device(db, sync, local_device).await?;
+	}
+
+	(
+		backfill_volumes(db, sync, local_device_id),
+		paginate_tags(db, sync),
+		paginate_locations(db, sync, local_device_id),
+		paginate_objects(db, sync, local_device_id),
+		paginate_labels(db, sync),
+	)
+		.try_join()
+		.await?;
+
+	(
+		paginate_exif_datas(db, sync, local_device_id),
+		paginate_file_paths(db, sync, local_device_id),
+		paginate_tags_on_objects(db, sync, local_device_id),
+		paginate_labels_on_objects(db, sync, local_device_id),
+	)
+		.try_join()
+		.await?;
+
+	sync.toggles
+		.reject_backfilled(db, &sync.device_pub_id)
+		.await?;
+
+	debug!(elapsed = ?start.elapsed(), "backfill ended");
+
+	Ok(())
+}
+
+/// The operations of this device, the backfill writes them all again
+async fn remove_operations(sync: &SyncManager) -> Result<(), Error> {
+	sync.db
+		.crdt_operation()
+		.delete_many(vec![crdt_operation::device_pub_id::equals(
+			sync.device_pub_id.to_db(),
+		)])
+		.exec()
+		.await?;
+
+	Ok(())
+}
+
+/// How many records of each model the backfill goes through, in its order
+pub(crate) async fn count_records(
+	db: &PrismaClient,
+	device_id: device::id::Type,
+) -> Result<Vec<(ModelId, i64)>, Error> {
+	let (
+		volumes,
+		tags,
+		locations,
+		objects,
+		labels,
+		exif_datas,
+		file_paths,
+		tags_on_objects,
+		labels_on_objects,
+	) = (
+		db.volume()
+			.count(vec![volume::device_id::equals(Some(device_id))])
+			.exec(),
+		db.tag().count(vec![]).exec(),
+		db.location()
+			.count(vec![location::device_id::equals(Some(device_id))])
+			.exec(),
+		db.object()
+			.count(vec![
+				object::device_id::equals(Some(device_id)),
+				selective::synced_object(),
+			])
+			.exec(),
+		db.label().count(vec![]).exec(),
+		db.exif_data()
+			.count(vec![
+				exif_data::device_id::equals(Some(device_id)),
+				exif_data::object::is(vec![selective::synced_object()]),
+			])
+			.exec(),
+		db.file_path()
+			.count(vec![file_path::device_id::equals(Some(device_id))])
+			.exec(),
+		db.tag_on_object()
+			.count(vec![
+				tag_on_object::device_id::equals(Some(device_id)),
+				tag_on_object::object::is(vec![selective::synced_object()]),
+			])
+			.exec(),
+		db.label_on_object()
+			.count(vec![
+				label_on_object::device_id::equals(Some(device_id)),
+				label_on_object::object::is(vec![selective::synced_object()]),
+			])
+			.exec(),
+	)
+		.try_join()
+		.await?;
+
+	Ok(vec![
+		(prisma_sync::device::MODEL_ID, 1),
+		(prisma_sync::volume::MODEL_ID, volumes),
+		(prisma_sync::tag::MODEL_ID, tags),
+		(prisma_sync::location::MODEL_ID, locations),
+		(prisma_sync::object::MODEL_ID, objects),
+		(prisma_sync::label::MODEL_ID, labels),
+		(prisma_sync::exif_data::MODEL_ID, exif_datas),
+		(prisma_sync::file_path::MODEL_ID, file_paths),
+		(prisma_sync::tag_on_object::MODEL_ID, tags_on_objects),
+		(prisma_sync::label_on_object::MODEL_ID, labels_on_objects),
+	])
 }
 
 #[instrument(skip(db, sync), err)]
@@ -92,12 +381,15 @@ async fn backfill_device(
 					option_sync_entry!(local_device.timestamp, device::timestamp),
 					option_sync_entry!(local_device.date_created, device::date_created),
 					option_sync_entry!(local_device.date_deleted, device::date_deleted),
+					option_sync_entry!(local_device.date_revoked, device::date_revoked),
 				],
 			),
 		))?])
 		.exec()
 		.await?;
 
+	sync.backfill.advance(prisma_sync::device::MODEL_ID, 1);
+
 	Ok(())
 }
 
@@ -107,52 +399,79 @@ async fn backfill_volumes(
 	sync: &SyncManager,
 	device_id: device::id::Type,
 ) -> Result<(), Error> {
-	let Some(volume) = db
+	let volumes = db
 		.volume()
-		.find_first(vec![volume::device_id::equals(Some(device_id))])
+		.find_many(vec![volume::device_id::equals(Some(device_id))])
 		.include(volume::include!({device: select { pub_id }}))
 		.exec()
-		.await?
-	else {
+		.await?;
+	if volumes.is_empty() {
 		// Nothing to do
 		return Ok(());
-	};
+	}
+	let count = volumes.len();
 
+	let _lock_guard = sync.sync_lock.lock().await;
 	db.crdt_operation()
-		.create_many(vec![crdt_op_unchecked_db(&sync.shared_create(
-			prisma_sync::volume::SyncId {
-				pub_id: volume.pub_id,
-			},
-			chain_optional_iter(
-				[
-					sync_entry!(volume.name, volume::name),
-					sync_entry!(volume.mount_type, volume::mount_type),
-					sync_entry!(volume.mount_point, volume::mount_point),
-					sync_entry!(volume.is_mounted, volume::is_mounted),
-					sync_entry!(volume.disk_type, volume::disk_type),
-					sync_entry!(volume.file_system, volume::file_system),
-					sync_entry!(volume.read_only, volume::read_only),
-					sync_entry!(volume.error_status, volume::error_status),
-					sync_entry!(volume.total_bytes_capacity, volume::total_bytes_capacity),
-					sync_entry!(volume.total_bytes_available, volume::total_bytes_available),
-				],
-				[option_sync_entry!(
-					volume.device.map(|device| {
-						prisma_sync::device::SyncId {
-							pub_id: device.pub_id,
-						}
-					}),
-					volume::device
-				)],
-			),
-		))?])
+		.create_many(
+			volumes
+				.into_iter()
+				.map(|volume| {
+					crdt_op_unchecked_db(&sync.shared_create(
+						prisma_sync::volume::SyncId {
+							pub_id: volume.pub_id,
+						},
+						chain_optional_iter(
+							[],
+							[
+								option_sync_entry!(volume.name, volume::name),
+								option_sync_entry!(volume.mount_type, volume::mount_type),
+								option_sync_entry!(volume.mount_point, volume::mount_point),
+								option_sync_entry!(volume.is_mounted, volume::is_mounted),
+								option_sync_entry!(volume.disk_type, volume::disk_type),
+								option_sync_entry!(volume.file_system, volume::file_system),
+								option_sync_entry!(volume.read_only, volume::read_only),
+								option_sync_entry!(volume.error_status, volume::error_status),
+								option_sync_entry!(volume.serial, volume::serial),
+								option_sync_entry!(
+									volume.total_bytes_capacity,
+									volume::total_bytes_capacity
+								),
+								option_sync_entry!(
+									volume.total_bytes_available,
+									volume::total_bytes_available
+								),
+								option_sync_entry!(volume.read_speed_mbps, volume::read_speed_mbps),
+								option_sync_entry!(
+									volume.write_speed_mbps,
+									volume::write_speed_mbps
+								),
+								option_sync_entry!(volume.fingerprint, volume::fingerprint),
+								option_sync_entry!(volume.date_last_seen, volume::date_last_seen),
+								option_sync_entry!(
+									volume.device.map(|device| {
+										prisma_sync::device::SyncId {
+											pub_id: device.pub_id,
+										}
+									}),
+									volume::device
+								),
+							],
+						),
+					))
+				})
+				.collect::<Result<Vec<_>, _>>()?,
+		)
 		.exec()
 		.await?;
 
+	sync.backfill.advance(prisma_sync::volume::MODEL_ID, count);
+
 	Ok(())
 }
 
 async fn paginate<T, E1, E2, E3, GetterFut, OperationsFut>(
+	(sync, model_id): (&SyncManager, ModelId),
 	getter: impl Fn(i32) -> GetterFut + Send,
 	id: impl Fn(&T) -> i32 + Send,
 	operations: impl Fn(Vec<T>) -> Result<OperationsFut, E3> + Send,
@@ -172,15 +491,22 @@ where
 			break;
 		};
 
+		// Between two pages, nothing waits on the backfill while it's paused
+		sync.backfill.wait_if_paused().await;
+
+		let _lock_guard = sync.sync_lock.lock().await;
 		let items = getter(cursor).await?;
 		next_cursor = items.last().map(&id);
+		let count = items.len();
 		operations(items)?.await?;
+		sync.backfill.advance(model_id, count);
 	}
 
 	Ok(())
 }
 
 async fn paginate_relation<T, E1, E2, E3, GetterFut, OperationsFut>(
+	(sync, model_id): (&SyncManager, ModelId),
 	getter: impl Fn(i32, i32) -> GetterFut + Send,
 	id: impl Fn(&T) -> (i32, i32) + Send,
 	operations: impl Fn(Vec<T>) -> Result<OperationsFut, E3> + Send,
@@ -200,9 +526,15 @@ where
 			break;
 		};
 
+		// Between two pages, nothing waits on the backfill while it's paused
+		sync.backfill.wait_if_paused().await;
+
+		let _lock_guard = sync.sync_lock.lock().await;
 		let items = getter(cursor.0, cursor.1).await?;
 		next_cursor = items.last().map(&id);
+		let count = items.len();
 		operations(items)?.await?;
+		sync.backfill.advance(model_id, count);
 	}
 
 	Ok(())
@@ -211,6 +543,7 @@ where
 #[instrument(skip(db, sync), err)]
 async fn paginate_tags(db: &PrismaClient, sync: &SyncManager) -> Result<(), Error> {
 	paginate(
+		(sync, prisma_sync::tag::MODEL_ID),
 		|cursor| {
 			db.tag()
 				.find_many(vec![tag::id::gt(cursor)])
@@ -249,6 +582,7 @@ async fn paginate_locations(
 	device_id: device::id::Type,
 ) -> Result<(), Error> {
 	paginate(
+		(sync, prisma_sync::location::MODEL_ID),
 		|cursor| {
 			db.location()
 				.find_many(vec![
@@ -318,11 +652,13 @@ async fn paginate_objects(
 	device_id: device::id::Type,
 ) -> Result<(), Error> {
 	paginate(
+		(sync, prisma_sync::object::MODEL_ID),
 		|cursor| {
 			db.object()
 				.find_many(vec![
 					object::id::gt(cursor),
 					object::device_id::equals(Some(device_id)),
+					selective::synced_object(),
 				])
 				.order_by(object::id::order(SortOrder::Asc))
 				.take(1000)
@@ -375,11 +711,13 @@ async fn paginate_exif_datas(
 	device_id: device::id::Type,
 ) -> Result<(), Error> {
 	paginate(
+		(sync, prisma_sync::exif_data::MODEL_ID),
 		|cursor| {
 			db.exif_data()
 				.find_many(vec![
 					exif_data::id::gt(cursor),
 					exif_data::device_id::equals(Some(device_id)),
+					exif_data::object::is(vec![selective::synced_object()]),
 				])
 				.order_by(exif_data::id::order(SortOrder::Asc))
 				.take(1000)
@@ -439,6 +777,7 @@ async fn paginate_file_paths(
 	device_id: device::id::Type,
 ) -> Result<(), Error> {
 	paginate(
+		(sync, prisma_sync::file_path::MODEL_ID),
 		|cursor| {
 			db.file_path()
 				.find_many(vec![
@@ -446,68 +785,14 @@ async fn paginate_file_paths(
 					file_path::device_id::equals(Some(device_id)),
 				])
 				.order_by(file_path::id::order(SortOrder::Asc))
-				.include(file_path::include!({
-					location: select { pub_id }
-					object: select { pub_id }
-					device: select { pub_id }
-				}))
+				.include(file_path_to_sync::include())
 				.exec()
 		},
 		|o| o.id,
 		|file_paths| {
 			file_paths
 				.into_iter()
-				.map(|fp| {
-					sync.shared_create(
-						prisma_sync::file_path::SyncId { pub_id: fp.pub_id },
-						chain_optional_iter(
-							[],
-							[
-								option_sync_entry!(fp.is_dir, file_path::is_dir),
-								option_sync_entry!(fp.cas_id, file_path::cas_id),
-								option_sync_entry!(
-									fp.integrity_checksum,
-									file_path::integrity_checksum
-								),
-								option_sync_entry!(
-									fp.location.map(|l| {
-										prisma_sync::location::SyncId { pub_id: l.pub_id }
-									}),
-									file_path::location
-								),
-								option_sync_entry!(
-									fp.object.map(|o| {
-										prisma_sync::object::SyncId { pub_id: o.pub_id }
-									}),
-									file_path::object
-								),
-								option_sync_entry!(
-									fp.materialized_path,
-									file_path::materialized_path
-								),
-								option_sync_entry!(fp.name, file_path::name),
-								option_sync_entry!(fp.extension, file_path::extension),
-								option_sync_entry!(fp.hidden, file_path::hidden),
-								option_sync_entry!(
-									fp.size_in_bytes_bytes,
-									file_path::size_in_bytes_bytes
-								),
-								option_sync_entry!(fp.inode, file_path::inode),
-								option_sync_entry!(fp.date_created, file_path::date_created),
-								option_sync_entry!(fp.date_modified, file_path::date_modified),
-								option_sync_entry!(fp.date_indexed, file_path::date_indexed),
-								option_sync_entry!(
-									fp.device.map(|device| {
-										prisma_sync::device::SyncId {
-											pub_id: device.pub_id,
-										}
-									}),
-									file_path::device
-								),
-							],
-						),
-					)
-				})
+				.filter_map(|fp| file_path_create(sync, fp))
 				.map(|o| crdt_op_unchecked_db(&o))
 				.collect::<Result<Vec<_>, _>>()
 				.map(|creates| db.crdt_operation().create_many(creates).exec())
@@ -523,12 +808,14 @@ async fn paginate_tags_on_objects(
 	device_id: device::id::Type,
 ) -> Result<(), Error> {
 	paginate_relation(
+		(sync, prisma_sync::tag_on_object::MODEL_ID),
 		|group_id, item_id| {
 			db.tag_on_object()
 				.find_many(vec![
 					tag_on_object::tag_id::gt(group_id),
 					tag_on_object::object_id::gt(item_id),
 					tag_on_object::device_id::equals(Some(device_id)),
+					tag_on_object::object::is(vec![selective::synced_object()]),
 				])
 				.order_by(tag_on_object::tag_id::order(SortOrder::Asc))
 				.order_by(tag_on_object::object_id::order(SortOrder::Asc))
@@ -580,6 +867,7 @@ async fn paginate_tags_on_objects(
 #[instrument(skip(db, sync), err)]
 async fn paginate_labels(db: &PrismaClient, sync: &SyncManager) -> Result<(), Error> {
 	paginate(
+		(sync, prisma_sync::label::MODEL_ID),
 		|cursor| {
 			db.label()
 				.find_many(vec![label::id::gt(cursor)])
@@ -617,12 +905,14 @@ async fn paginate_labels_on_objects(
 	device_id: device::id::Type,
 ) -> Result<(), Error> {
 	paginate_relation(
+		(sync, prisma_sync::label_on_object::MODEL_ID),
 		|group_id, item_id| {
 			db.label_on_object()
 				.find_many(vec![
 					label_on_object::label_id::gt(group_id),
 					label_on_object::object_id::gt(item_id),
 					label_on_object::device_id::equals(Some(device_id)),
+					label_on_object::object::is(vec![selective::synced_object()]),
 				])
 				.order_by(label_on_object::label_id::order(SortOrder::Asc))
 				.order_by(label_on_object::object_id::order(SortOrder::Asc))
@@ -667,3 +957,60 @@ async fn paginate_labels_on_objects(
 	)
 	.await
 }
+
+file_path::include!(file_path_to_sync {
+	location: select { pub_id }
+	object: select { pub_id }
+	device: select { pub_id }
+});
+
+/// The operation creating a file path with every field it has, or what its sync mode lets sync of
+/// them
+pub(crate) fn file_path_create(
+	sync: &SyncManager,
+	fp: file_path_to_sync::Data,
+) -> Option<CRDTOperation> {
+	let mode = SyncMode::from_db(fp.sync_mode);
+
+	selective::strip(
+		sync.shared_create(
+			prisma_sync::file_path::SyncId { pub_id: fp.pub_id },
+			chain_optional_iter(
+				[],
+				[
+					option_sync_entry!(fp.is_dir, file_path::is_dir),
+					option_sync_entry!(fp.cas_id, file_path::cas_id),
+					option_sync_entry!(fp.integrity_checksum, file_path::integrity_checksum),
+					option_sync_entry!(
+						fp.location
+							.map(|l| { prisma_sync::location::SyncId { pub_id: l.pub_id } }),
+						file_path::location
+					),
+					option_sync_entry!(
+						fp.object
+							.map(|o| { prisma_sync::object::SyncId { pub_id: o.pub_id } }),
+						file_path::object
+					),
+					option_sync_entry!(fp.materialized_path, file_path::materialized_path),
+					option_sync_entry!(fp.name, file_path::name),
+					option_sync_entry!(fp.extension, file_path::extension),
+					option_sync_entry!(fp.hidden, file_path::hidden),
+					option_sync_entry!(fp.size_in_bytes_bytes, file_path::size_in_bytes_bytes),
+					option_sync_entry!(fp.inode, file_path::inode),
+					option_sync_entry!(fp.date_created, file_path::date_created),
+					option_sync_entry!(fp.date_modified, file_path::date_modified),
+					option_sync_entry!(fp.date_indexed, file_path::date_indexed),
+					option_sync_entry!(
+						fp.device.map(|device| {
+							prisma_sync::device::SyncId {
+								pub_id: device.pub_id,
+							}
+						}),
+						file_path::device
+					),
+				],
+			),
+		),
+		mode,
+	)
+}
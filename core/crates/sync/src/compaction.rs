@@ -0,0 +1,329 @@
+//! Compaction, dropping the operations of the log nothing needs anymore.
+//!
+//! An update is superseded once newer operations of its record set every field it sets, or delete
+//! the record, the newest value of each field being all ingestion goes by. Only the superseded
+//! updates older than the retention window, and acknowledged by every other device, are dropped. A
+//! device's clock is moved past the operations it ingests, so the ones older than the newest
+//! operation of each other device are taken as acknowledged. A device that stopped syncing holds
+//! compaction back until it's removed, and one that never sent an operation holds all of it back.
+//! Creates and deletes are kept, the devices that never had a record still need them.
+
+use sd_prisma::prisma::{crdt_operation, device, PrismaClient, SortOrder};
+use sd_sync::CRDTOperationData;
+
+use std::{
+	cmp::Reverse,
+	collections::{HashMap, HashSet},
+	sync::{Mutex, PoisonError},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use specta::Type;
+use tracing::{debug, instrument};
+use uhlc::NTP64;
+
+use super::{DevicePubId, Error, SyncManager};
+
+/// Updates looked at in one go
+const BATCH_SIZE: i64 = 1000;
+
+crdt_operation::select!(operation_to_compact {
+	id
+	timestamp
+	model
+	record_id
+	kind
+	data
+	device_pub_id
+});
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct CompactionReport {
+	pub removed: u32,
+	/// What the removed operations took in the database, the file itself only shrinks once SQLite
+	/// reuses or vacuums their pages
+	pub reclaimed_bytes: u64,
+	/// The operations from before this were looked at, the start of the retention window unless the
+	/// other devices acknowledged less
+	pub cutoff: DateTime<Utc>,
+	pub finished_at: DateTime<Utc>,
+}
+
+/// Since the library was opened
+#[derive(Debug, Clone, Default, Serialize, Type)]
+pub struct CompactionStats {
+	pub runs: u32,
+	pub removed: u64,
+	pub reclaimed_bytes: u64,
+	pub last_run: Option<CompactionReport>,
+}
+
+#[derive(Debug, Default)]
+pub struct Compaction {
+	/// Held by the run in progress, so a manual one waits for the periodic one
+	running: tokio::sync::Mutex<()>,
+	stats: Mutex<CompactionStats>,
+}
+
+impl Compaction {
+	pub fn stats(&self) -> CompactionStats {
+		self.stats
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.clone()
+	}
+}
+
+/// Drops the superseded updates from before the cutoff, see the [module docs](self)
+#[instrument(skip(sync), err)]
+pub async fn compact(sync: &SyncManager, retention: Duration) -> Result<CompactionReport, Error> {
+	let _running = sync.compaction.running.lock().await;
+
+	let db = &sync.db;
+	let cutoff = cutoff(sync, retention).await?;
+
+	let mut removed = 0;
+	let mut reclaimed_bytes = 0;
+	let mut cursor = 0;
+
+	loop {
+		let updates = db
+			.crdt_operation()
+			.find_many(vec![
+				crdt_operation::id::gt(cursor),
+				#[allow(clippy::cast_possible_wrap)]
+				// SAFETY: we had to store using i64 due to SQLite limitations
+				crdt_operation::timestamp::lt(cutoff.as_u64() as i64),
+				crdt_operation::kind::starts_with("u".to_string()),
+			])
+			.order_by(crdt_operation::id::order(SortOrder::Asc))
+			.take(BATCH_SIZE)
+			.select(crdt_operation::select!({ id model record_id }))
+			.exec()
+			.await?;
+
+		let Some(last) = updates.last() else {
+			break;
+		};
+		cursor = last.id;
+
+		let candidates = updates
+			.iter()
+			.map(|update| update.id)
+			.collect::<HashSet<_>>();
+		let mut records_per_model = HashMap::<_, HashSet<_>>::new();
+		for update in updates {
+			records_per_model
+				.entry(update.model)
+				.or_default()
+				.insert(update.record_id);
+		}
+
+		let superseded = superseded(db, records_per_model, &candidates).await?;
+		if superseded.is_empty() {
+			continue;
+		}
+
+		reclaimed_bytes += superseded.iter().map(|(_, size)| size).sum::<u64>();
+
+		let count = db
+			.crdt_operation()
+			.delete_many(vec![crdt_operation::id::in_vec(
+				superseded.into_iter().map(|(id, _)| id).collect(),
+			)])
+			.exec()
+			.await?;
+
+		#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+		// SAFETY: at most a batch of operations is deleted at once
+		let count = count as u32;
+		removed += count;
+	}
+
+	let report = CompactionReport {
+		removed,
+		reclaimed_bytes,
+		cutoff: DateTime::from(cutoff.to_system_time()),
+		finished_at: Utc::now(),
+	};
+
+	debug!(removed, reclaimed_bytes, "Compacted the sync operations;");
+
+	{
+		let mut stats = sync
+			.compaction
+			.stats
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner);
+		stats.runs += 1;
+		stats.removed += u64::from(removed);
+		stats.reclaimed_bytes += reclaimed_bytes;
+		stats.last_run = Some(report.clone());
+	}
+
+	Ok(report)
+}
+
+/// The oldest of the start of the retention window and the newest operation of each other device,
+/// taken from the library's devices as the ones paired since it was opened have no timestamp yet
+async fn cutoff(sync: &SyncManager, retention: Duration) -> Result<NTP64, Error> {
+	let window_start = NTP64::from(
+		SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.saturating_sub(retention),
+	);
+
+	let other_devices = sync
+		.db
+		.device()
+		.find_many(vec![device::pub_id::not(sync.device_pub_id.to_db())])
+		.select(device::select!({ pub_id }))
+		.exec()
+		.await?;
+
+	let timestamp_per_device = sync.timestamp_per_device.read().await;
+
+	Ok(other_devices
+		.iter()
+		.map(|device| {
+			timestamp_per_device
+				.get(&DevicePubId::from(&device.pub_id))
+				.copied()
+				// Nothing of it was ingested, so it acknowledged nothing
+				.unwrap_or(NTP64(0))
+		})
+		.fold(window_start, NTP64::min))
+}
+
+/// The candidates newer operations of their record made useless, with the bytes they take
+async fn superseded(
+	db: &PrismaClient,
+	records_per_model: HashMap<crdt_operation::model::Type, HashSet<Vec<u8>>>,
+	candidates: &HashSet<crdt_operation::id::Type>,
+) -> Result<Vec<(crdt_operation::id::Type, u64)>, Error> {
+	let mut ops_per_record = HashMap::<_, Vec<_>>::new();
+	for op in db
+		._batch(
+			records_per_model
+				.into_iter()
+				.map(|(model, record_ids)| {
+					db.crdt_operation()
+						.find_many(vec![
+							crdt_operation::model::equals(model),
+							crdt_operation::record_id::in_vec(record_ids.into_iter().collect()),
+						])
+						.select(operation_to_compact::select())
+				})
+				.collect::<Vec<_>>(),
+		)
+		.await?
+		.into_iter()
+		.flatten()
+	{
+		ops_per_record
+			.entry((op.model, op.record_id.clone()))
+			.or_default()
+			.push(op);
+	}
+
+	let mut superseded = vec![];
+	for mut ops in ops_per_record.into_values() {
+		ops.sort_unstable_by_key(|op| Reverse((op.timestamp, op.id)));
+
+		let mut set_later = HashSet::new();
+		let mut deleted_later = false;
+
+		for op in ops {
+			if op.kind == "d" {
+				deleted_later = true;
+			} else if let Some(fields) = op.kind.strip_prefix("u:") {
+				let fields = fields
+					.split(':')
+					.filter(|field| !field.is_empty())
+					.map(ToString::to_string)
+					.collect::<Vec<_>>();
+
+				if candidates.contains(&op.id)
+					&& (deleted_later || fields.iter().all(|field| set_later.contains(field)))
+				{
+					superseded.push((op.id, size(&op)));
+				}
+
+				set_later.extend(fields);
+			} else if let CRDTOperationData::Create(fields) =
+				rmp_serde::from_slice::<CRDTOperationData>(&op.data)?
+			{
+				set_later.extend(fields.into_keys());
+			}
+		}
+	}
+
+	Ok(superseded)
+}
+
+/// The bytes of the columns of an operation
+fn size(op: &operation_to_compact::Data) -> u64 {
+	// The id, timestamp and model integers
+	const INTEGERS_SIZE: usize = 3 * 8;
+
+	(INTEGERS_SIZE + op.record_id.len() + op.kind.len() + op.data.len() + op.device_pub_id.len())
+		as u64
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::test_utils::{add_device, TestLibrary};
+
+	const RETENTION: Duration = Duration::from_secs(60 * 60);
+
+	#[tokio::test]
+	async fn test_cutoff_is_the_window_start_without_other_devices() {
+		let library = TestLibrary::new().await;
+
+		// Converting from and to NTP64 rounds a bit
+		let slack = Duration::from_millis(1);
+
+		let before = SystemTime::now() - RETENTION - slack;
+		let cutoff = cutoff(&library.sync, RETENTION)
+			.await
+			.unwrap()
+			.to_system_time();
+		let after = SystemTime::now() - RETENTION + slack;
+
+		assert!(before <= cutoff && cutoff <= after);
+	}
+
+	#[tokio::test]
+	async fn test_cutoff_is_held_back_by_other_devices_timestamps() {
+		let library = TestLibrary::new().await;
+		let other_device_pub_id = DevicePubId::new();
+		add_device(&library.db, &other_device_pub_id).await;
+
+		library
+			.sync
+			.timestamp_per_device
+			.write()
+			.await
+			.insert(other_device_pub_id, NTP64(42));
+
+		assert_eq!(cutoff(&library.sync, RETENTION).await.unwrap(), NTP64(42));
+	}
+
+	#[tokio::test]
+	async fn test_device_without_operations_holds_compaction_back() {
+		let library = TestLibrary::new().await;
+		// Paired after the library was opened, so there is no timestamp of it
+		add_device(&library.db, &DevicePubId::new()).await;
+
+		assert_eq!(cutoff(&library.sync, RETENTION).await.unwrap(), NTP64(0));
+
+		let report = compact(&library.sync, RETENTION).await.unwrap();
+		assert_eq!(report.removed, 0);
+		assert_eq!(report.cutoff, DateTime::<Utc>::from(UNIX_EPOCH));
+	}
+}
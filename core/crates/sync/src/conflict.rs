@@ -0,0 +1,510 @@
+//! Conflicts, a field two devices changed without either having the change of the other.
+//!
+//! The newest change of a field wins when operations are ingested, so the one made earlier is lost
+//! without anybody knowing. It can only be told apart from a change made after seeing the other on
+//! the device whose change won: an operation older than one already applied was made before its
+//! device had it. Those are recorded in the `sync_conflict` table with both values, until the user
+//! keeps one of them or both.
+
+use sd_core_prisma_helpers::DevicePubId;
+
+use sd_prisma::{
+	prisma::{crdt_operation, device, sync_conflict, PrismaClient, SortOrder},
+	prisma_sync::{self, ModelSyncData},
+};
+use sd_sync::{CRDTOperation, CRDTOperationData, ModelId, OperationFactory};
+use sd_utils::uuid_to_bytes;
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	sync::atomic::{AtomicBool, Ordering},
+};
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tracing::{debug, instrument, warn};
+use uhlc::NTP64;
+use uuid::Uuid;
+
+use super::{
+	db_operation::write_crdt_op_to_db, model_name, selective, Error, SyncEvent, SyncManager,
+};
+
+/// The models whose records can be copied to keep both versions, they have no other unique field
+/// than their ID
+const COPYABLE_MODELS: [ModelId; 1] = [prisma_sync::tag::MODEL_ID];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+	/// The value of this device stays, it's the one the record already has
+	KeepMine,
+	/// The value of the other device replaces it on every device
+	KeepTheirs,
+	/// The record keeps the value of this device, and a copy of it named after the other device
+	/// gets theirs
+	KeepBoth,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct SyncConflict {
+	pub id: sync_conflict::id::Type,
+	/// The model of the record, like `tag` or `file_path`
+	pub model: String,
+	pub record_id: serde_json::Value,
+	pub field: String,
+	pub mine: serde_json::Value,
+	pub theirs: serde_json::Value,
+	pub theirs_device_pub_id: Vec<u8>,
+	pub theirs_device_name: Option<String>,
+	pub date_created: DateTime<FixedOffset>,
+}
+
+/// A field an ingested operation lost to a newer change of this device
+#[derive(Debug)]
+pub(crate) struct Found {
+	pub(crate) field: String,
+	pub(crate) mine: rmpv::Value,
+	pub(crate) mine_timestamp: NTP64,
+	pub(crate) theirs: rmpv::Value,
+	pub(crate) theirs_timestamp: NTP64,
+}
+
+/// Records the conflicts of an ingestion, so [`SyncEvent::Conflicted`] is sent once for all of them
+#[derive(Debug)]
+pub(crate) struct Detector {
+	local_device_pub_id: Vec<u8>,
+	found: AtomicBool,
+}
+
+impl Detector {
+	pub(crate) fn new(local_device_pub_id: &DevicePubId) -> Self {
+		Self {
+			local_device_pub_id: local_device_pub_id.to_db(),
+			found: AtomicBool::new(false),
+		}
+	}
+
+	pub(crate) fn is_local(&self, device_pub_id: &[u8]) -> bool {
+		self.local_device_pub_id == device_pub_id
+	}
+
+	pub(crate) fn found_any(&self) -> bool {
+		self.found.load(Ordering::Relaxed)
+	}
+
+	/// Records the conflicts of a record, replacing the ones already recorded for its fields
+	pub(crate) async fn record(
+		&self,
+		db: &PrismaClient,
+		model_id: ModelId,
+		record_id: &rmpv::Value,
+		theirs_device_pub_id: &DevicePubId,
+		conflicts: Vec<Found>,
+	) -> Result<(), Error> {
+		if conflicts.is_empty() {
+			return Ok(());
+		}
+
+		let record_id = rmp_serde::to_vec(record_id)?;
+		let theirs_device_pub_id = theirs_device_pub_id.to_db();
+
+		let upserts = conflicts
+			.into_iter()
+			.map(
+				|Found {
+				     field,
+				     mine,
+				     mine_timestamp,
+				     theirs,
+				     theirs_timestamp,
+				 }| {
+					let mine = rmp_serde::to_vec(&mine)?;
+					let theirs = rmp_serde::to_vec(&theirs)?;
+					#[allow(clippy::cast_possible_wrap)]
+					// SAFETY: we have to store using i64 due to SQLite limitations
+					let (mine_timestamp, theirs_timestamp) = (
+						mine_timestamp.as_u64() as i64,
+						theirs_timestamp.as_u64() as i64,
+					);
+
+					Ok::<_, Error>(db.sync_conflict().upsert(
+						sync_conflict::model_record_id_field(
+							i32::from(model_id),
+							record_id.clone(),
+							field.clone(),
+						),
+						sync_conflict::create(
+							i32::from(model_id),
+							record_id.clone(),
+							field,
+							mine.clone(),
+							mine_timestamp,
+							theirs.clone(),
+							theirs_timestamp,
+							theirs_device_pub_id.clone(),
+							vec![],
+						),
+						vec![
+							sync_conflict::mine::set(mine),
+							sync_conflict::mine_timestamp::set(mine_timestamp),
+							sync_conflict::theirs::set(theirs),
+							sync_conflict::theirs_timestamp::set(theirs_timestamp),
+							sync_conflict::theirs_device_pub_id::set(theirs_device_pub_id.clone()),
+						],
+					))
+				},
+			)
+			.collect::<Result<Vec<_>, _>>()?;
+
+		debug!(
+			model_id,
+			conflicts = upserts.len(),
+			"Recorded sync conflicts;"
+		);
+
+		db._batch(upserts).await?;
+		self.found.store(true, Ordering::Relaxed);
+
+		Ok(())
+	}
+}
+
+/// The conflicts still waiting for the user, the newest first
+pub async fn list(db: &PrismaClient) -> Result<Vec<SyncConflict>, Error> {
+	let conflicts = db
+		.sync_conflict()
+		.find_many(vec![])
+		.order_by(sync_conflict::date_created::order(SortOrder::Desc))
+		.exec()
+		.await?;
+
+	let device_names = db
+		.device()
+		.find_many(vec![device::pub_id::in_vec(
+			conflicts
+				.iter()
+				.map(|conflict| conflict.theirs_device_pub_id.clone())
+				.collect(),
+		)])
+		.select(device::select!({ pub_id name }))
+		.exec()
+		.await?
+		.into_iter()
+		.filter_map(|device| device.name.map(|name| (device.pub_id, name)))
+		.collect::<HashMap<_, _>>();
+
+	Ok(conflicts
+		.into_iter()
+		.map(|conflict| SyncConflict {
+			id: conflict.id,
+			model: ModelId::try_from(conflict.model)
+				.ok()
+				.and_then(model_name)
+				.map_or_else(|| conflict.model.to_string(), ToString::to_string),
+			record_id: to_json(&conflict.record_id),
+			field: conflict.field,
+			mine: to_json(&conflict.mine),
+			theirs: to_json(&conflict.theirs),
+			theirs_device_name: device_names.get(&conflict.theirs_device_pub_id).cloned(),
+			theirs_device_pub_id: conflict.theirs_device_pub_id,
+			date_created: conflict.date_created,
+		})
+		.collect())
+}
+
+/// Settles a conflict, writing the operation the resolution takes for the other devices to get it
+#[instrument(skip(sync), err)]
+pub async fn resolve(
+	sync: &SyncManager,
+	id: sync_conflict::id::Type,
+	resolution: ConflictResolution,
+) -> Result<(), Error> {
+	let db = &sync.db;
+
+	let conflict = db
+		.sync_conflict()
+		.find_unique(sync_conflict::id::equals(id))
+		.exec()
+		.await?
+		.ok_or(Error::ConflictNotFound(id))?;
+
+	#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+	// SAFETY: we will not have more than 2^16 models and we had to store using signed integers due
+	// to SQLite limitations
+	let model_id = conflict.model as ModelId;
+	let record_id = rmp_serde::from_slice::<rmpv::Value>(&conflict.record_id)?;
+	let theirs = rmp_serde::from_slice::<rmpv::Value>(&conflict.theirs)?;
+
+	match resolution {
+		ConflictResolution::KeepMine => {}
+		ConflictResolution::KeepTheirs => {
+			let op = new_op(
+				sync,
+				model_id,
+				record_id,
+				CRDTOperationData::Update(BTreeMap::from([(conflict.field.clone(), theirs)])),
+			);
+			apply(sync, op).await?;
+		}
+		ConflictResolution::KeepBoth => {
+			if !COPYABLE_MODELS.contains(&model_id) {
+				return Err(Error::KeepBothUnsupported(model_id));
+			}
+
+			let mut fields = current_fields(db, model_id, &conflict.record_id).await?;
+			fields.insert(conflict.field.clone(), theirs);
+
+			let device_name = db
+				.device()
+				.find_unique(device::pub_id::equals(
+					conflict.theirs_device_pub_id.clone(),
+				))
+				.select(device::select!({ name }))
+				.exec()
+				.await?
+				.and_then(|device| device.name)
+				.unwrap_or_else(|| "conflict".to_string());
+			if let Some(rmpv::Value::String(name)) = fields.get_mut("name") {
+				if let Some(name_str) = name.as_str() {
+					*name = format!("{name_str} ({device_name})").into();
+				}
+			}
+
+			let copy_id = rmp_serde::from_slice::<rmpv::Value>(&rmp_serde::to_vec_named(
+				&prisma_sync::tag::SyncId {
+					pub_id: uuid_to_bytes(&Uuid::now_v7()),
+				},
+			)?)?;
+
+			apply(
+				sync,
+				new_op(sync, model_id, copy_id, CRDTOperationData::Create(fields)),
+			)
+			.await?;
+		}
+	}
+
+	db.sync_conflict()
+		.delete(sync_conflict::id::equals(id))
+		.exec()
+		.await?;
+
+	Ok(())
+}
+
+fn new_op(
+	sync: &SyncManager,
+	model_id: ModelId,
+	record_id: rmpv::Value,
+	data: CRDTOperationData,
+) -> CRDTOperation {
+	CRDTOperation {
+		device_pub_id: sync.get_device_pub_id(),
+		timestamp: *sync.get_clock().new_timestamp().get_time(),
+		model_id,
+		record_id,
+		data,
+	}
+}
+
+/// Applies an operation of this device to the database, writing it for the other devices unless the
+/// sync rules keep it here or its model is disabled
+async fn apply(sync: &SyncManager, op: CRDTOperation) -> Result<(), Error> {
+	let db = &sync.db;
+	let to_write = selective::honor(db, sync.toggles.retain_enabled(vec![op.clone()]))
+		.await?
+		.pop();
+
+	let lock_guard = sync.sync_lock.lock().await;
+
+	db._transaction()
+		.with_timeout(30 * 10000)
+		.with_max_wait(30 * 10000)
+		.run(|db| async move {
+			ModelSyncData::from_op(op)?.exec(&db).await?;
+
+			match to_write {
+				Some(op) => write_crdt_op_to_db(&op, &db).await,
+				None => Ok(()),
+			}
+		})
+		.await?;
+
+	drop(lock_guard);
+
+	if sync.tx.send(SyncEvent::Created).is_err() {
+		warn!("failed to send created message on conflict resolution");
+	}
+
+	Ok(())
+}
+
+/// The fields of a record as its operations left them
+async fn current_fields(
+	db: &PrismaClient,
+	model_id: ModelId,
+	record_id: &[u8],
+) -> Result<BTreeMap<String, rmpv::Value>, Error> {
+	let ops = db
+		.crdt_operation()
+		.find_many(vec![
+			crdt_operation::model::equals(i32::from(model_id)),
+			crdt_operation::record_id::equals(record_id.to_vec()),
+		])
+		.order_by(crdt_operation::timestamp::order(SortOrder::Asc))
+		.select(crdt_operation::select!({ data }))
+		.exec()
+		.await?;
+
+	let mut fields = BTreeMap::new();
+	for op in ops {
+		match rmp_serde::from_slice::<CRDTOperationData>(&op.data)? {
+			CRDTOperationData::Create(data) | CRDTOperationData::Update(data) => {
+				fields.extend(data)
+			}
+			CRDTOperationData::Delete => fields.clear(),
+		}
+	}
+
+	Ok(fields)
+}
+
+/// A msgpack value for the frontend, null for the ones JSON can't hold like maps without string keys
+fn to_json(value: &[u8]) -> serde_json::Value {
+	rmp_serde::from_slice::<rmpv::Value>(value)
+		.ok()
+		.and_then(|value| serde_json::to_value(value).ok())
+		.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::test_utils::{add_device, TestLibrary};
+
+	use sd_prisma::prisma::tag;
+	use sd_sync::sync_entry;
+
+	/// A tag this device named "mine" while the other device named it "theirs"
+	async fn conflicted_tag(library: &TestLibrary) -> (tag::id::Type, sync_conflict::id::Type) {
+		let db = &library.db;
+		let other_device_pub_id = DevicePubId::new();
+		add_device(db, &other_device_pub_id).await;
+
+		let pub_id = Uuid::now_v7().as_bytes().to_vec();
+		let op = library.sync.shared_create(
+			prisma_sync::tag::SyncId {
+				pub_id: pub_id.clone(),
+			},
+			[sync_entry!("mine", tag::name)],
+		);
+		let record_id = rmp_serde::to_vec(&op.record_id).unwrap();
+		let tag = library
+			.sync
+			.write_op(
+				db,
+				op,
+				db.tag()
+					.create(pub_id, vec![tag::name::set(Some("mine".to_string()))]),
+			)
+			.await
+			.unwrap();
+
+		let conflict = db
+			.sync_conflict()
+			.create(
+				i32::from(prisma_sync::tag::MODEL_ID),
+				record_id,
+				tag::name::NAME.to_string(),
+				rmp_serde::to_vec(&rmpv::Value::from("mine")).unwrap(),
+				2,
+				rmp_serde::to_vec(&rmpv::Value::from("theirs")).unwrap(),
+				1,
+				other_device_pub_id.to_db(),
+				vec![],
+			)
+			.exec()
+			.await
+			.unwrap();
+
+		(tag.id, conflict.id)
+	}
+
+	async fn tag_name(db: &PrismaClient, id: tag::id::Type) -> Option<String> {
+		db.tag()
+			.find_unique(tag::id::equals(id))
+			.exec()
+			.await
+			.unwrap()
+			.unwrap()
+			.name
+	}
+
+	async fn tag_operations(db: &PrismaClient) -> i64 {
+		db.crdt_operation()
+			.count(vec![crdt_operation::model::equals(i32::from(
+				prisma_sync::tag::MODEL_ID,
+			))])
+			.exec()
+			.await
+			.unwrap()
+	}
+
+	#[tokio::test]
+	async fn test_keep_mine_leaves_the_record_alone() {
+		let library = TestLibrary::new().await;
+		let (tag_id, conflict_id) = conflicted_tag(&library).await;
+
+		resolve(&library.sync, conflict_id, ConflictResolution::KeepMine)
+			.await
+			.unwrap();
+
+		assert_eq!(tag_name(&library.db, tag_id).await.as_deref(), Some("mine"));
+		assert_eq!(tag_operations(&library.db).await, 1);
+		assert!(list(&library.db).await.unwrap().is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_keep_theirs_applies_and_syncs_their_value() {
+		let library = TestLibrary::new().await;
+		let (tag_id, conflict_id) = conflicted_tag(&library).await;
+
+		assert_eq!(list(&library.db).await.unwrap().len(), 1);
+
+		resolve(&library.sync, conflict_id, ConflictResolution::KeepTheirs)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			tag_name(&library.db, tag_id).await.as_deref(),
+			Some("theirs")
+		);
+		assert_eq!(tag_operations(&library.db).await, 2);
+		assert!(list(&library.db).await.unwrap().is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_keep_theirs_of_a_disabled_model_stays_on_this_device() {
+		let library = TestLibrary::new().await;
+		let (tag_id, conflict_id) = conflicted_tag(&library).await;
+
+		library
+			.sync
+			.toggles
+			.set_disabled(&["tag".to_string()])
+			.unwrap();
+
+		resolve(&library.sync, conflict_id, ConflictResolution::KeepTheirs)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			tag_name(&library.db, tag_id).await.as_deref(),
+			Some("theirs")
+		);
+		assert_eq!(tag_operations(&library.db).await, 1);
+	}
+}
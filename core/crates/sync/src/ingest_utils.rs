@@ -1,7 +1,7 @@
 use sd_core_prisma_helpers::DevicePubId;
 
 use sd_prisma::{
-	prisma::{crdt_operation, PrismaClient},
+	prisma::{crdt_operation, PrismaClient, SortOrder},
 	prisma_sync::ModelSyncData,
 };
 use sd_sync::{
@@ -16,17 +16,23 @@ use tracing::{debug, instrument, trace, warn};
 use uhlc::{Timestamp, HLC, NTP64};
 use uuid::Uuid;
 
-use super::{db_operation::write_crdt_op_to_db, Error, TimestampPerDevice};
+use super::{
+	conflict::{self, Detector},
+	db_operation::write_crdt_op_to_db,
+	Error, TimestampPerDevice,
+};
 
 crdt_operation::select!(crdt_operation_id { id });
 
 // where the magic happens
-#[instrument(skip(clock, ops), fields(operations_count = %ops.len()), err)]
+#[instrument(skip(clock, conflicts, ops), fields(operations_count = %ops.len()), err)]
+#[allow(clippy::too_many_arguments)]
 pub async fn process_crdt_operations(
 	clock: &HLC,
 	timestamp_per_device: &TimestampPerDevice,
 	sync_lock: Arc<Mutex<()>>,
 	db: &PrismaClient,
+	conflicts: &Detector,
 	device_pub_id: DevicePubId,
 	model_id: ModelId,
 	(record_id, mut ops): (RecordId, Vec<CompressedCRDTOperation>),
@@ -138,7 +144,9 @@ pub async fn process_crdt_operations(
 						crdt_operation::record_id::equals(rmp_serde::to_vec(&record_id)?),
 						crdt_operation::kind::starts_with("u".to_string()),
 					])
-					.select(crdt_operation::select!({ kind timestamp })),
+					// Newest first, for the change a field lost to to be the one it has
+					.order_by(crdt_operation::timestamp::order(SortOrder::Desc))
+					.select(crdt_operation::select!({ kind timestamp device_pub_id data })),
 			))
 			.await?;
 
@@ -147,7 +155,19 @@ pub async fn process_crdt_operations(
 			return Ok(());
 		}
 
+		let mut found = vec![];
+
 		for candidate in possible_newer_updates_count {
+			// Only the changes of this device can conflict, the other devices find their own
+			let mine = if conflicts.is_local(&candidate.device_pub_id) {
+				match rmp_serde::from_slice(&candidate.data)? {
+					CRDTOperationData::Update(fields_and_values) => Some(fields_and_values),
+					_ => None,
+				}
+			} else {
+				None
+			};
+
 			// The first element is "u" meaning that this is an update, so we skip it
 			for key in candidate
 				.kind
@@ -163,7 +183,23 @@ pub async fn process_crdt_operations(
 						*new_timestamp < NTP64(candidate.timestamp as u64)
 					}
 				}) {
-					data.remove(key);
+					let Some((theirs, theirs_timestamp)) = data.remove(key) else {
+						continue;
+					};
+
+					if let Some(mine) = mine.as_ref().and_then(|mine| mine.get(key)) {
+						if *mine != theirs {
+							found.push(conflict::Found {
+								field: key.to_string(),
+								mine: mine.clone(),
+								#[allow(clippy::cast_sign_loss)]
+								// we need to store as i64 due to SQLite limitations
+								mine_timestamp: NTP64(candidate.timestamp as u64),
+								theirs,
+								theirs_timestamp,
+							});
+						}
+					}
 				}
 			}
 
@@ -172,6 +208,10 @@ pub async fn process_crdt_operations(
 			}
 		}
 
+		conflicts
+			.record(db, model_id, &record_id, &device_pub_id, found)
+			.await?;
+
 		handle_crdt_updates(db, &sync_lock, &device_pub_id, model_id, record_id, data).await?;
 	}
 
@@ -28,7 +28,7 @@
 #![allow(clippy::missing_errors_doc, clippy::module_name_repetitions)]
 
 use sd_prisma::{
-	prisma::{cloud_crdt_operation, crdt_operation},
+	prisma::{cloud_crdt_operation, crdt_operation, sync_conflict},
 	prisma_sync,
 };
 use sd_utils::uuid_to_bytes;
@@ -38,9 +38,18 @@ use std::{collections::HashMap, sync::Arc};
 use tokio::{sync::RwLock, task::JoinError};
 
 pub mod backfill;
+pub mod compaction;
+pub mod conflict;
 mod db_operation;
 mod ingest_utils;
 mod manager;
+pub mod preview;
+pub mod revocation;
+pub mod selective;
+pub mod toggles;
+
+#[cfg(test)]
+mod test_utils;
 
 pub use db_operation::{from_cloud_crdt_ops, from_crdt_ops, write_crdt_op_to_db};
 pub use manager::Manager as SyncManager;
@@ -50,6 +59,8 @@ pub use uhlc::NTP64;
 pub enum SyncEvent {
 	Ingested,
 	Created,
+	/// Ingested operations lost to newer changes of this device, see [`conflict`]
+	Conflicted,
 }
 
 pub use sd_core_prisma_helpers::DevicePubId;
@@ -79,6 +90,14 @@ pub enum Error {
 	DeviceNotFound(DevicePubId),
 	#[error("processes crdt task panicked")]
 	ProcessCrdtPanic(JoinError),
+	#[error("sync conflict not found: <id={0}>")]
+	ConflictNotFound(sync_conflict::id::Type),
+	#[error("both versions of a record can't be kept for this model: <model_id={0}>")]
+	KeepBothUnsupported(ModelId),
+	#[error("the current device can't be revoked")]
+	RevokeCurrentDevice,
+	#[error("sync can't be turned off for this model: <model={0}>")]
+	UntoggleableModel(String),
 }
 
 impl From<Error> for rspc::Error {
@@ -89,6 +108,12 @@ impl From<Error> for rspc::Error {
 				rspc::ErrorCode::BadRequest,
 				format!("Invalid model id <id={id}>"),
 			),
+			Error::ConflictNotFound(_) | Error::DeviceNotFound(_) => {
+				Self::new(rspc::ErrorCode::NotFound, e.to_string())
+			}
+			Error::KeepBothUnsupported(_)
+			| Error::RevokeCurrentDevice
+			| Error::UntoggleableModel(_) => Self::new(rspc::ErrorCode::BadRequest, e.to_string()),
 			_ => Self::with_cause(
 				rspc::ErrorCode::InternalServerError,
 				"Internal sync error".to_string(),
@@ -98,6 +123,27 @@ impl From<Error> for rspc::Error {
 	}
 }
 
+/// The synced models with their names for the frontend, in the order they're ingested
+pub(crate) const MODELS: [(ModelId, &str); 10] = [
+	(prisma_sync::device::MODEL_ID, "device"),
+	(prisma_sync::volume::MODEL_ID, "volume"),
+	(prisma_sync::tag::MODEL_ID, "tag"),
+	(prisma_sync::location::MODEL_ID, "location"),
+	(prisma_sync::object::MODEL_ID, "object"),
+	(prisma_sync::label::MODEL_ID, "label"),
+	(prisma_sync::exif_data::MODEL_ID, "exif_data"),
+	(prisma_sync::file_path::MODEL_ID, "file_path"),
+	(prisma_sync::tag_on_object::MODEL_ID, "tag_on_object"),
+	(prisma_sync::label_on_object::MODEL_ID, "label_on_object"),
+];
+
+/// The name of a synced model, for the frontend
+pub(crate) fn model_name(model_id: ModelId) -> Option<&'static str> {
+	MODELS
+		.into_iter()
+		.find_map(|(id, name)| (id == model_id).then_some(name))
+}
+
 pub fn crdt_op_db(op: &CRDTOperation) -> Result<crdt_operation::Create, Error> {
 	Ok(crdt_operation::Create {
 		timestamp: {
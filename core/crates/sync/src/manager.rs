@@ -34,9 +34,15 @@ use uhlc::{HLCBuilder, HLC};
 use uuid::Uuid;
 
 use super::{
+	backfill::BackfillState,
+	compaction::Compaction,
+	conflict::Detector,
 	crdt_op_db,
 	db_operation::{from_cloud_crdt_ops, from_crdt_ops},
 	ingest_utils::{bulk_ingest_create_only_ops, process_crdt_operations},
+	revocation::Revocations,
+	selective,
+	toggles::ModelToggles,
 	Error, SyncEvent, TimestampPerDevice, NTP64,
 };
 
@@ -53,6 +59,10 @@ pub struct Manager {
 	pub clock: Arc<HLC>,
 	pub active: Arc<AtomicBool>,
 	pub active_notify: Arc<Notify>,
+	pub backfill: Arc<BackfillState>,
+	pub compaction: Arc<Compaction>,
+	pub revocations: Arc<Revocations>,
+	pub toggles: Arc<ModelToggles>,
 	pub(crate) sync_lock: Arc<Mutex<()>>,
 	pub(crate) available_parallelism: usize,
 }
@@ -121,6 +131,8 @@ impl Manager {
 			})
 			.collect();
 
+		let revocations = Arc::new(Revocations::load(&db).await?);
+
 		let (tx, rx) = broadcast::channel(64);
 
 		Ok((
@@ -140,6 +152,10 @@ impl Manager {
 				emit_messages_flag,
 				active: Arc::default(),
 				active_notify: Arc::default(),
+				backfill: Arc::default(),
+				compaction: Arc::default(),
+				revocations,
+				toggles: Arc::default(),
 				sync_lock: Arc::new(Mutex::default()),
 				available_parallelism: std::thread::available_parallelism()
 					.map_or(1, std::num::NonZero::get),
@@ -168,7 +184,11 @@ impl Manager {
 	}
 
 	#[instrument(skip(self))]
-	async fn ingest_by_model(&self, model_id: ModelId) -> Result<usize, Error> {
+	async fn ingest_by_model(
+		&self,
+		model_id: ModelId,
+		conflicts: &Arc<Detector>,
+	) -> Result<usize, Error> {
 		let mut total_count = 0;
 
 		let mut buckets = (0..self.available_parallelism)
@@ -284,6 +304,7 @@ impl Manager {
 						let db = Arc::clone(&self.db);
 						let device_pub_id = device_pub_id.into();
 						let sync_lock = Arc::clone(&self.sync_lock);
+						let conflicts = Arc::clone(conflicts);
 
 						Some(async move {
 							let count = ops.len();
@@ -293,6 +314,7 @@ impl Manager {
 								&timestamp_per_device,
 								sync_lock,
 								&db,
+								&conflicts,
 								device_pub_id,
 								model_id,
 								(record_id, ops),
@@ -366,17 +388,27 @@ impl Manager {
 
 	pub async fn ingest_ops(&self) -> Result<usize, Error> {
 		let mut total_count = 0;
+		let conflicts = Arc::new(Detector::new(&self.device_pub_id));
+
+		self.revocations.reject_pending(&self.db).await?;
+		self.toggles.reject_pending(&self.db).await?;
 
 		// WARN: this order here exists because sync messages MUST be processed in this exact order
 		// due to relationship dependencies between these tables.
-		total_count += self.ingest_by_model(prisma_sync::device::MODEL_ID).await?;
+		total_count += self
+			.ingest_by_model(prisma_sync::device::MODEL_ID, &conflicts)
+			.await?;
+
+		// The devices just ingested may have revoked others, whose operations are left out
+		self.revocations.reload(&self.db).await?;
+		self.revocations.reject_pending(&self.db).await?;
 
 		total_count += [
-			self.ingest_by_model(prisma_sync::volume::MODEL_ID),
-			self.ingest_by_model(prisma_sync::tag::MODEL_ID),
-			self.ingest_by_model(prisma_sync::location::MODEL_ID),
-			self.ingest_by_model(prisma_sync::object::MODEL_ID),
-			self.ingest_by_model(prisma_sync::label::MODEL_ID),
+			self.ingest_by_model(prisma_sync::volume::MODEL_ID, &conflicts),
+			self.ingest_by_model(prisma_sync::tag::MODEL_ID, &conflicts),
+			self.ingest_by_model(prisma_sync::location::MODEL_ID, &conflicts),
+			self.ingest_by_model(prisma_sync::object::MODEL_ID, &conflicts),
+			self.ingest_by_model(prisma_sync::label::MODEL_ID, &conflicts),
 		]
 		.try_join()
 		.await?
@@ -384,10 +416,10 @@ impl Manager {
 		.sum::<usize>();
 
 		total_count += [
-			self.ingest_by_model(prisma_sync::exif_data::MODEL_ID),
-			self.ingest_by_model(prisma_sync::file_path::MODEL_ID),
-			self.ingest_by_model(prisma_sync::tag_on_object::MODEL_ID),
-			self.ingest_by_model(prisma_sync::label_on_object::MODEL_ID),
+			self.ingest_by_model(prisma_sync::exif_data::MODEL_ID, &conflicts),
+			self.ingest_by_model(prisma_sync::file_path::MODEL_ID, &conflicts),
+			self.ingest_by_model(prisma_sync::tag_on_object::MODEL_ID, &conflicts),
+			self.ingest_by_model(prisma_sync::label_on_object::MODEL_ID, &conflicts),
 		]
 		.try_join()
 		.await?
@@ -398,6 +430,10 @@ impl Manager {
 			warn!("failed to send ingested message on `ingest_ops`");
 		}
 
+		if conflicts.found_any() && self.tx.send(SyncEvent::Conflicted).is_err() {
+			warn!("failed to send conflicted message on `ingest_ops`");
+		}
+
 		Ok(total_count)
 	}
 
@@ -418,7 +454,26 @@ impl Manager {
 			return Err(Error::EmptyOperations);
 		}
 
-		let ret = if self.emit_messages_flag.load(atomic::Ordering::Relaxed) {
+		let ops = if self.emit_messages_flag.load(atomic::Ordering::Relaxed) {
+			selective::honor(tx, self.toggles.retain_enabled(ops)).await?
+		} else {
+			ops
+		};
+
+		self.write_honored_ops(tx, (ops, queries)).await
+	}
+
+	/// Writes the operations without going by the sync rules, running the queries alone when
+	/// there are none
+	pub(crate) async fn write_honored_ops<'item, Q>(
+		&self,
+		tx: &PrismaClient,
+		(ops, queries): (Vec<CRDTOperation>, Q),
+	) -> Result<Q::ReturnValue, Error>
+	where
+		Q: prisma_client_rust::BatchItem<'item, ReturnValue: Send> + Send,
+	{
+		let ret = if !ops.is_empty() && self.emit_messages_flag.load(atomic::Ordering::Relaxed) {
 			let lock_guard = self.sync_lock.lock().await;
 
 			let (res, _) = tx
@@ -460,6 +515,19 @@ impl Manager {
 	where
 		Q: prisma_client_rust::BatchItem<'item, ReturnValue: Send> + Send,
 	{
+		let op = if self.emit_messages_flag.load(atomic::Ordering::Relaxed) {
+			selective::honor(tx, self.toggles.retain_enabled(vec![op]))
+				.await?
+				.pop()
+		} else {
+			Some(op)
+		};
+
+		// Kept on this device by the sync rules or as its model isn't synced
+		let Some(op) = op else {
+			return Ok(tx._batch(vec![query]).await?.remove(0));
+		};
+
 		let ret = if self.emit_messages_flag.load(atomic::Ordering::Relaxed) {
 			let lock_guard = self.sync_lock.lock().await;
 
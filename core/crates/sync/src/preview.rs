@@ -0,0 +1,131 @@
+//! Preview, what connecting the library to another device would send it, without writing anything.
+//!
+//! The records of this device are counted as the backfill goes through them, the file paths by how
+//! much of them the sync rules of their location let through. Only records are synced, the sizes
+//! are of the files their paths stand for, what the other device would see of them. The models
+//! whose sync is turned off are left out.
+
+use sd_prisma::{
+	prisma::{device, file_path, SortOrder},
+	prisma_sync,
+};
+use sd_utils::db::size_in_bytes_from_db;
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use specta::Type;
+use tracing::instrument;
+
+use super::{backfill, model_name, selective::SyncMode, Error, SyncManager};
+
+/// File paths looked at in one go
+const BATCH_SIZE: i64 = 1000;
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct SyncPreview {
+	/// In the order they'd be synced, without the file paths kept on this device
+	pub models: Vec<ModelPreview>,
+	/// One for each sync mode
+	pub file_paths: Vec<FilePathsPreview>,
+	pub total_records: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct ModelPreview {
+	pub model: String,
+	pub records: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct FilePathsPreview {
+	pub mode: SyncMode,
+	pub count: u32,
+	/// Of the files, directories left out as their size is the one of what's in them
+	pub bytes: u64,
+}
+
+/// What would be synced from this device under the current rules, see the [module docs](self)
+#[instrument(skip(sync), err)]
+pub async fn preview(sync: &SyncManager) -> Result<SyncPreview, Error> {
+	let db = &sync.db;
+
+	let device_id = db
+		.device()
+		.find_unique(device::pub_id::equals(sync.device_pub_id.to_db()))
+		.select(device::select!({ id }))
+		.exec()
+		.await?
+		.ok_or(Error::DeviceNotFound(sync.device_pub_id.clone()))?
+		.id;
+
+	let mut file_paths = HashMap::<_, (u32, u64)>::new();
+	let mut cursor = 0;
+
+	loop {
+		let batch = db
+			.file_path()
+			.find_many(vec![
+				file_path::device_id::equals(Some(device_id)),
+				file_path::id::gt(cursor),
+			])
+			.order_by(file_path::id::order(SortOrder::Asc))
+			.take(BATCH_SIZE)
+			.select(file_path::select!({ id is_dir size_in_bytes_bytes sync_mode }))
+			.exec()
+			.await?;
+
+		let Some(last) = batch.last() else {
+			break;
+		};
+		cursor = last.id;
+
+		for file_path in batch {
+			let (count, bytes) = file_paths
+				.entry(SyncMode::from_db(file_path.sync_mode))
+				.or_default();
+			*count += 1;
+
+			if !file_path.is_dir.unwrap_or_default() {
+				*bytes += file_path
+					.size_in_bytes_bytes
+					.filter(|size| size.len() == size_of::<u64>())
+					.map_or(0, |size| size_in_bytes_from_db(&size));
+			}
+		}
+	}
+
+	let local_only = file_paths
+		.get(&SyncMode::LocalOnly)
+		.map_or(0, |(count, _)| *count);
+
+	let models = backfill::count_records(db, device_id)
+		.await?
+		.into_iter()
+		.filter(|(model_id, _)| sync.toggles.is_enabled(*model_id))
+		.map(|(model_id, records)| {
+			let mut records = u32::try_from(records).unwrap_or(u32::MAX);
+			if model_id == prisma_sync::file_path::MODEL_ID {
+				records = records.saturating_sub(local_only);
+			}
+
+			ModelPreview {
+				model: model_name(model_id)
+					.map_or_else(|| model_id.to_string(), ToString::to_string),
+				records,
+			}
+		})
+		.collect::<Vec<_>>();
+
+	Ok(SyncPreview {
+		total_records: models.iter().map(|model| u64::from(model.records)).sum(),
+		models,
+		file_paths: [SyncMode::Full, SyncMode::MetadataOnly, SyncMode::LocalOnly]
+			.into_iter()
+			.map(|mode| {
+				let (count, bytes) = file_paths.get(&mode).copied().unwrap_or_default();
+				FilePathsPreview { mode, count, bytes }
+			})
+			.collect(),
+	})
+}
@@ -0,0 +1,155 @@
+//! Revocation, a device of the library that's no longer trusted.
+//!
+//! A revoked device gets a `date_revoked`, written as an operation so the other devices learn it
+//! with their next sync and stop trusting it too. The operations of a revoked device are rejected
+//! from then on, the ones waiting to be ingested included, what was ingested before stays. A device
+//! can't be revoked back, its own operations trying to would be rejected as well.
+
+use sd_core_prisma_helpers::DevicePubId;
+
+use sd_prisma::{
+	prisma::{cloud_crdt_operation, device, PrismaClient},
+	prisma_sync,
+};
+use sd_sync::{sync_entry, OperationFactory};
+
+use std::{
+	collections::HashSet,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		PoisonError, RwLock,
+	},
+};
+
+use chrono::Utc;
+use tracing::{debug, instrument};
+
+use super::{Error, SyncManager};
+
+/// The pub ids of the revoked devices, as they're stored
+#[derive(Debug, Default)]
+pub struct Revocations {
+	revoked: RwLock<HashSet<device::pub_id::Type>>,
+	/// Bumped every time a device is revoked, for the ones caching what the revoked devices knew
+	generation: AtomicU64,
+}
+
+impl Revocations {
+	pub(crate) async fn load(db: &PrismaClient) -> Result<Self, Error> {
+		let this = Self::default();
+		this.reload(db).await?;
+
+		Ok(this)
+	}
+
+	/// Picks up the devices revoked by the operations of the other devices
+	pub(crate) async fn reload(&self, db: &PrismaClient) -> Result<(), Error> {
+		let revoked = db
+			.device()
+			.find_many(vec![device::date_revoked::not(None)])
+			.select(device::select!({ pub_id }))
+			.exec()
+			.await?
+			.into_iter()
+			.map(|device| device.pub_id)
+			.collect::<HashSet<_>>();
+
+		let mut current = self.revoked.write().unwrap_or_else(PoisonError::into_inner);
+		if *current != revoked {
+			*current = revoked;
+			self.generation.fetch_add(1, Ordering::Release);
+		}
+
+		Ok(())
+	}
+
+	pub fn is_revoked(&self, device_pub_id: &[u8]) -> bool {
+		self.revoked
+			.read()
+			.unwrap_or_else(PoisonError::into_inner)
+			.contains(device_pub_id)
+	}
+
+	/// Changes whenever a device gets revoked
+	pub fn generation(&self) -> u64 {
+		self.generation.load(Ordering::Acquire)
+	}
+
+	fn insert(&self, device_pub_id: device::pub_id::Type) {
+		if self
+			.revoked
+			.write()
+			.unwrap_or_else(PoisonError::into_inner)
+			.insert(device_pub_id)
+		{
+			self.generation.fetch_add(1, Ordering::Release);
+		}
+	}
+
+	/// Drops the operations of the revoked devices still waiting to be ingested, returning how many
+	pub(crate) async fn reject_pending(&self, db: &PrismaClient) -> Result<i64, Error> {
+		let revoked = self
+			.revoked
+			.read()
+			.unwrap_or_else(PoisonError::into_inner)
+			.iter()
+			.cloned()
+			.collect::<Vec<_>>();
+		if revoked.is_empty() {
+			return Ok(0);
+		}
+
+		Ok(db
+			.cloud_crdt_operation()
+			.delete_many(vec![cloud_crdt_operation::device_pub_id::in_vec(revoked)])
+			.exec()
+			.await?)
+	}
+}
+
+/// Revokes a device of the library, doing nothing if it already was
+#[instrument(skip(sync), err)]
+pub async fn revoke(sync: &SyncManager, device_pub_id: &DevicePubId) -> Result<(), Error> {
+	if *device_pub_id == sync.device_pub_id {
+		return Err(Error::RevokeCurrentDevice);
+	}
+
+	let db = &sync.db;
+	let pub_id = device_pub_id.to_db();
+
+	let device = db
+		.device()
+		.find_unique(device::pub_id::equals(pub_id.clone()))
+		.select(device::select!({ date_revoked }))
+		.exec()
+		.await?
+		.ok_or_else(|| Error::DeviceNotFound(device_pub_id.clone()))?;
+
+	if device.date_revoked.is_none() {
+		let date_revoked = Utc::now().fixed_offset();
+
+		sync.write_op(
+			db,
+			sync.shared_update(
+				prisma_sync::device::SyncId {
+					pub_id: pub_id.clone(),
+				},
+				[sync_entry!(date_revoked, device::date_revoked)],
+			),
+			db.device()
+				.update(
+					device::pub_id::equals(pub_id.clone()),
+					vec![device::date_revoked::set(Some(date_revoked))],
+				)
+				.select(device::select!({ id })),
+		)
+		.await?;
+	}
+
+	sync.revocations.insert(pub_id);
+	let rejected = sync.revocations.reject_pending(db).await?;
+
+	debug!(rejected, "Revoked a device;");
+
+	Ok(())
+}
@@ -0,0 +1,624 @@
+//! Selective sync, the directories of a location that are kept on this device or synced without
+//! their content.
+//!
+//! A [`SyncRule`] applies to everything under its directory, the innermost rule winning. Paths are
+//! tagged with the mode of their rule when they're indexed, and [`honor`] drops or trims the
+//! operations of the tagged ones before they're written. The content of a file path is what links
+//! it to the file itself, its `cas_id`, `integrity_checksum` and object.
+//!
+//! An object linked only to file paths that aren't synced with their content stays on this device
+//! too, along with its exif data and the tags and labels on it, as the other devices would have
+//! nothing pointing to it.
+
+use sd_prisma::{
+	prisma::{device, file_path, location, object, sync_rule, PrismaClient, SortOrder},
+	prisma_sync,
+};
+use sd_sync::{option_sync_entry, CRDTOperation, CRDTOperationData, OperationFactory};
+
+use std::collections::{HashMap, HashSet};
+
+use prisma_client_rust::or;
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tracing::{debug, instrument};
+
+use super::{
+	backfill::{file_path_create, file_path_to_sync},
+	Error, SyncManager,
+};
+
+/// The fields left out for [`SyncMode::MetadataOnly`]
+const CONTENT_FIELDS: [&str; 3] = [
+	file_path::cas_id::NAME,
+	file_path::integrity_checksum::NAME,
+	file_path::object::NAME,
+];
+
+/// File paths retagged at once by [`retag`]
+const RETAG_BATCH_SIZE: i64 = 1000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMode {
+	#[default]
+	Full = 0,
+	/// The other devices see the file paths, not what's in them
+	MetadataOnly = 1,
+	/// Nothing is synced, the file paths stay on this device
+	LocalOnly = 2,
+}
+
+impl SyncMode {
+	/// How a file path is tagged with it, the ones synced fully aren't
+	#[must_use]
+	pub const fn to_db(self) -> Option<i32> {
+		match self {
+			Self::Full => None,
+			mode => Some(mode as i32),
+		}
+	}
+
+	/// Unknown modes are taken as [`Self::LocalOnly`], so nothing is synced by mistake
+	#[must_use]
+	pub const fn from_db(mode: Option<i32>) -> Self {
+		match mode {
+			None | Some(0) => Self::Full,
+			Some(1) => Self::MetadataOnly,
+			Some(_) => Self::LocalOnly,
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Type)]
+pub struct SyncRule {
+	pub id: sync_rule::id::Type,
+	/// The materialized path of the directory, like `/Photos/Raw/`
+	pub path: String,
+	pub mode: SyncMode,
+}
+
+impl From<sync_rule::Data> for SyncRule {
+	fn from(rule: sync_rule::Data) -> Self {
+		Self {
+			id: rule.id,
+			path: rule.path,
+			mode: SyncMode::from_db(Some(rule.mode)),
+		}
+	}
+}
+
+/// The sync rules of a location
+#[derive(Debug, Clone, Default)]
+pub struct SyncRules(Vec<SyncRule>);
+
+impl SyncRules {
+	pub async fn load(db: &PrismaClient, location_id: location::id::Type) -> Result<Self, Error> {
+		Ok(Self(
+			db.sync_rule()
+				.find_many(vec![sync_rule::location_id::equals(location_id)])
+				.exec()
+				.await?
+				.into_iter()
+				.map(Into::into)
+				.collect(),
+		))
+	}
+
+	/// The mode of a file path, from the innermost rule over it
+	#[must_use]
+	pub fn mode_for(&self, materialized_path: &str, name: &str, is_dir: bool) -> SyncMode {
+		let dir_path;
+		let path = if is_dir {
+			dir_path = format!("{materialized_path}{name}/");
+			&dir_path
+		} else {
+			materialized_path
+		};
+
+		self.0
+			.iter()
+			.filter(|rule| path.starts_with(&rule.path))
+			.max_by_key(|rule| rule.path.len())
+			.map_or(SyncMode::Full, |rule| rule.mode)
+	}
+}
+
+/// A rule path as every materialized path is written, between slashes
+#[must_use]
+pub fn normalize_path(path: &str) -> String {
+	let path = path.trim_matches('/');
+	if path.is_empty() {
+		"/".to_string()
+	} else {
+		format!("/{path}/")
+	}
+}
+
+/// What of a file path operation `mode` lets sync, nothing when it's left with nothing to change
+#[must_use]
+pub fn strip(mut op: CRDTOperation, mode: SyncMode) -> Option<CRDTOperation> {
+	match mode {
+		SyncMode::Full => Some(op),
+		SyncMode::LocalOnly => None,
+		SyncMode::MetadataOnly => {
+			match &mut op.data {
+				CRDTOperationData::Create(fields) => {
+					fields.retain(|field, _| !CONTENT_FIELDS.contains(&field.as_str()));
+				}
+				CRDTOperationData::Update(fields) => {
+					fields.retain(|field, _| !CONTENT_FIELDS.contains(&field.as_str()));
+					if fields.is_empty() {
+						return None;
+					}
+				}
+				CRDTOperationData::Delete => {}
+			}
+
+			Some(op)
+		}
+	}
+}
+
+/// Drops the operations of the file paths kept on this device and the content of the ones synced
+/// without it, along with the operations of the objects only they link to, the others are left as
+/// they are
+pub(crate) async fn honor(
+	db: &PrismaClient,
+	ops: Vec<CRDTOperation>,
+) -> Result<Vec<CRDTOperation>, Error> {
+	let pub_ids = ops.iter().filter_map(file_path_pub_id).collect::<Vec<_>>();
+	let object_pub_ids = ops.iter().filter_map(object_pub_id).collect::<HashSet<_>>();
+	if pub_ids.is_empty() && object_pub_ids.is_empty() {
+		return Ok(ops);
+	}
+
+	let modes = if pub_ids.is_empty() {
+		HashMap::new()
+	} else {
+		db.file_path()
+			.find_many(vec![
+				file_path::pub_id::in_vec(pub_ids),
+				file_path::sync_mode::not(None),
+			])
+			.select(file_path::select!({ pub_id sync_mode }))
+			.exec()
+			.await?
+			.into_iter()
+			.map(|file_path| (file_path.pub_id, SyncMode::from_db(file_path.sync_mode)))
+			.collect::<HashMap<_, _>>()
+	};
+
+	let local_objects = if object_pub_ids.is_empty() {
+		HashSet::new()
+	} else {
+		let mut links = db
+			.file_path()
+			.find_many(vec![file_path::object::is(vec![object::pub_id::in_vec(
+				object_pub_ids.into_iter().collect(),
+			)])])
+			.select(file_path::select!({ pub_id sync_mode object: select { pub_id } }))
+			.exec()
+			.await?
+			.into_iter()
+			.filter_map(|file_path| {
+				file_path.object.map(|object| {
+					(
+						file_path.pub_id,
+						(object.pub_id, SyncMode::from_db(file_path.sync_mode)),
+					)
+				})
+			})
+			.collect::<HashMap<_, _>>();
+
+		// The objects linked by these operations aren't in the database yet
+		for (pub_id, object_pub_id) in ops.iter().filter_map(linked_object) {
+			match object_pub_id {
+				Some(object_pub_id) => {
+					let mode = modes.get(&pub_id).copied().unwrap_or_default();
+					links.insert(pub_id, (object_pub_id, mode));
+				}
+				None => {
+					links.remove(&pub_id);
+				}
+			}
+		}
+
+		local_objects(links.into_values())
+	};
+
+	if modes.is_empty() && local_objects.is_empty() {
+		return Ok(ops);
+	}
+
+	Ok(ops
+		.into_iter()
+		.filter(|op| object_pub_id(op).map_or(true, |pub_id| !local_objects.contains(&pub_id)))
+		.filter_map(
+			|op| match file_path_pub_id(&op).and_then(|pub_id| modes.get(&pub_id)) {
+				Some(mode) => strip(op, *mode),
+				None => Some(op),
+			},
+		)
+		.collect())
+}
+
+/// The objects synced, the ones linked only to file paths that aren't synced with their content
+/// are kept on this device
+pub(crate) fn synced_object() -> object::WhereParam {
+	or![
+		object::file_paths::none(vec![]),
+		object::file_paths::some(vec![file_path::sync_mode::equals(None)]),
+	]
+}
+
+/// The objects all the links of are to file paths that aren't synced with their content
+fn local_objects(
+	links: impl IntoIterator<Item = (object::pub_id::Type, SyncMode)>,
+) -> HashSet<object::pub_id::Type> {
+	let mut local = HashMap::new();
+	for (pub_id, mode) in links {
+		*local.entry(pub_id).or_insert(true) &= mode != SyncMode::Full;
+	}
+
+	local
+		.into_iter()
+		.filter_map(|(pub_id, local)| local.then_some(pub_id))
+		.collect()
+}
+
+fn file_path_pub_id(op: &CRDTOperation) -> Option<file_path::pub_id::Type> {
+	if op.model_id != prisma_sync::file_path::MODEL_ID {
+		return None;
+	}
+
+	rmpv::ext::from_value::<prisma_sync::file_path::SyncId>(op.record_id.clone())
+		.ok()
+		.map(|id| id.pub_id)
+}
+
+/// The object an operation is about, for the object itself, its exif data and the tags and labels
+/// on it
+fn object_pub_id(op: &CRDTOperation) -> Option<object::pub_id::Type> {
+	let record_id = op.record_id.clone();
+
+	match op.model_id {
+		prisma_sync::object::MODEL_ID => {
+			rmpv::ext::from_value::<prisma_sync::object::SyncId>(record_id)
+				.ok()
+				.map(|id| id.pub_id)
+		}
+		prisma_sync::exif_data::MODEL_ID => {
+			rmpv::ext::from_value::<prisma_sync::exif_data::SyncId>(record_id)
+				.ok()
+				.map(|id| id.object.pub_id)
+		}
+		prisma_sync::tag_on_object::MODEL_ID => {
+			rmpv::ext::from_value::<prisma_sync::tag_on_object::SyncId>(record_id)
+				.ok()
+				.map(|id| id.object.pub_id)
+		}
+		prisma_sync::label_on_object::MODEL_ID => {
+			rmpv::ext::from_value::<prisma_sync::label_on_object::SyncId>(record_id)
+				.ok()
+				.map(|id| id.object.pub_id)
+		}
+		_ => None,
+	}
+}
+
+/// The object a file path operation links it to, `None` inside for one unlinking or deleting it
+fn linked_object(
+	op: &CRDTOperation,
+) -> Option<(file_path::pub_id::Type, Option<object::pub_id::Type>)> {
+	let object = match &op.data {
+		CRDTOperationData::Create(fields) | CRDTOperationData::Update(fields) => {
+			Some(fields.get(file_path::object::NAME)?)
+		}
+		CRDTOperationData::Delete => None,
+	};
+
+	file_path_pub_id(op).map(|pub_id| {
+		(
+			pub_id,
+			object.and_then(|object| {
+				rmpv::ext::from_value::<prisma_sync::object::SyncId>(object.clone())
+					.ok()
+					.map(|id| id.pub_id)
+			}),
+		)
+	})
+}
+
+/// Whether a location is one of this device's, the only ones it can set sync rules on
+pub async fn is_local_location(
+	sync: &SyncManager,
+	location_id: location::id::Type,
+) -> Result<bool, Error> {
+	let count = sync
+		.db
+		.location()
+		.count(vec![
+			location::id::equals(location_id),
+			location::device::is(vec![device::pub_id::equals(sync.device_pub_id.to_db())]),
+		])
+		.exec()
+		.await?;
+
+	Ok(count > 0)
+}
+
+/// Tags the file paths of a location again after its rules changed, sending the other devices
+/// what they weren't sent before and deleting the paths kept on this device from them. They keep
+/// the content they were already sent of the paths now synced without it.
+///
+/// Only this device's file paths are retagged, the ones of other devices are theirs to sync.
+#[instrument(skip(sync), err)]
+pub async fn retag(sync: &SyncManager, location_id: location::id::Type) -> Result<u64, Error> {
+	let db = &sync.db;
+	let rules = SyncRules::load(db, location_id).await?;
+	let device_pub_id = sync.device_pub_id.to_db();
+
+	let mut retagged = 0;
+	let mut cursor = 0;
+
+	loop {
+		let file_paths = db
+			.file_path()
+			.find_many(vec![
+				file_path::location_id::equals(Some(location_id)),
+				file_path::device::is(vec![device::pub_id::equals(device_pub_id.clone())]),
+				file_path::id::gt(cursor),
+			])
+			.order_by(file_path::id::order(SortOrder::Asc))
+			.take(RETAG_BATCH_SIZE)
+			.include(file_path_to_sync::include())
+			.exec()
+			.await?;
+
+		let Some(last) = file_paths.last() else {
+			break;
+		};
+		cursor = last.id;
+
+		let mut ops = vec![];
+		let mut ids_per_mode = HashMap::<_, Vec<_>>::new();
+
+		for mut fp in file_paths {
+			let old = SyncMode::from_db(fp.sync_mode);
+			let new = rules.mode_for(
+				fp.materialized_path.as_deref().unwrap_or("/"),
+				fp.name.as_deref().unwrap_or_default(),
+				fp.is_dir.unwrap_or_default(),
+			);
+			if old == new {
+				continue;
+			}
+
+			ids_per_mode.entry(new).or_default().push(fp.id);
+
+			let pub_id = prisma_sync::file_path::SyncId {
+				pub_id: fp.pub_id.clone(),
+			};
+			match (old, new) {
+				(_, SyncMode::LocalOnly) => ops.push(sync.shared_delete(pub_id)),
+				(SyncMode::LocalOnly, _) => {
+					fp.sync_mode = new.to_db();
+					ops.extend(file_path_create(sync, fp));
+				}
+				(SyncMode::MetadataOnly, SyncMode::Full) => {
+					let content = [
+						option_sync_entry!(fp.cas_id, file_path::cas_id),
+						option_sync_entry!(fp.integrity_checksum, file_path::integrity_checksum),
+						option_sync_entry!(
+							fp.object
+								.map(|o| prisma_sync::object::SyncId { pub_id: o.pub_id }),
+							file_path::object
+						),
+					]
+					.into_iter()
+					.flatten()
+					.collect::<Vec<_>>();
+
+					if !content.is_empty() {
+						ops.push(sync.shared_update(pub_id, content));
+					}
+				}
+				_ => {}
+			}
+		}
+
+		let queries = ids_per_mode
+			.into_iter()
+			.map(|(mode, ids)| {
+				retagged += ids.len() as u64;
+				db.file_path().update_many(
+					vec![file_path::id::in_vec(ids)],
+					vec![file_path::sync_mode::set(mode.to_db())],
+				)
+			})
+			.collect::<Vec<_>>();
+
+		if ops.is_empty() {
+			db._batch(queries).await?;
+		} else {
+			// Written as they are, `honor` would go by the modes they're leaving
+			sync.write_honored_ops(db, (ops, queries)).await?;
+		}
+	}
+
+	debug!(retagged, "Retagged the file paths of a location;");
+
+	Ok(retagged)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::test_utils::{add_device, add_file_path, add_location, TestLibrary};
+
+	use sd_sync::{sync_entry, DevicePubId};
+
+	use uhlc::HLC;
+	use uuid::Uuid;
+
+	struct Factory(HLC);
+
+	impl OperationFactory for Factory {
+		fn get_clock(&self) -> &HLC {
+			&self.0
+		}
+
+		fn get_device_pub_id(&self) -> DevicePubId {
+			Uuid::nil()
+		}
+	}
+
+	fn object_id(pub_id: &[u8]) -> prisma_sync::object::SyncId {
+		prisma_sync::object::SyncId {
+			pub_id: pub_id.to_vec(),
+		}
+	}
+
+	#[test]
+	fn test_object_pub_id_of_object_models() {
+		let factory = Factory(HLC::default());
+		let pub_id = b"object".to_vec();
+
+		let ops = [
+			factory.shared_create(object_id(&pub_id), []),
+			factory.shared_update(object_id(&pub_id), [sync_entry!(true, object::favorite)]),
+			factory.shared_create(
+				prisma_sync::exif_data::SyncId {
+					object: object_id(&pub_id),
+				},
+				[],
+			),
+			factory.relation_create(
+				prisma_sync::tag_on_object::SyncId {
+					tag: prisma_sync::tag::SyncId {
+						pub_id: b"tag".to_vec(),
+					},
+					object: object_id(&pub_id),
+				},
+				[],
+			),
+			factory.relation_delete(prisma_sync::label_on_object::SyncId {
+				label: prisma_sync::label::SyncId {
+					name: "label".to_string(),
+				},
+				object: object_id(&pub_id),
+			}),
+		];
+		for op in &ops {
+			assert_eq!(object_pub_id(op), Some(pub_id.clone()), "{op:?}");
+		}
+
+		let file_path = factory.shared_update(
+			prisma_sync::file_path::SyncId {
+				pub_id: b"file_path".to_vec(),
+			},
+			[sync_entry!(object_id(&pub_id), file_path::object)],
+		);
+		assert_eq!(object_pub_id(&file_path), None);
+	}
+
+	#[test]
+	fn test_linked_object() {
+		let factory = Factory(HLC::default());
+		let file_path_id = || prisma_sync::file_path::SyncId {
+			pub_id: b"file_path".to_vec(),
+		};
+
+		let linking = factory.shared_update(
+			file_path_id(),
+			[sync_entry!(object_id(b"object"), file_path::object)],
+		);
+		assert_eq!(
+			linked_object(&linking),
+			Some((b"file_path".to_vec(), Some(b"object".to_vec())))
+		);
+
+		let deleting = factory.shared_delete(file_path_id());
+		assert_eq!(
+			linked_object(&deleting),
+			Some((b"file_path".to_vec(), None))
+		);
+
+		let renaming =
+			factory.shared_update(file_path_id(), [sync_entry!("name", file_path::name)]);
+		assert_eq!(linked_object(&renaming), None);
+	}
+
+	#[tokio::test]
+	async fn test_retag_leaves_other_devices_alone() {
+		let library = TestLibrary::new().await;
+		let db = &library.db;
+		let other_device = add_device(db, &crate::DevicePubId::new()).await;
+
+		let local = add_location(db, &library.device).await;
+		let local_path = add_file_path(db, &local, &library.device, "/Private/", "a.txt").await;
+
+		// Synced in from the other device, with one of its file paths
+		let remote = add_location(db, &other_device).await;
+		let remote_path = add_file_path(db, &remote, &other_device, "/Private/", "b.txt").await;
+
+		assert!(is_local_location(&library.sync, local.id).await.unwrap());
+		assert!(!is_local_location(&library.sync, remote.id).await.unwrap());
+
+		for location in [&local, &remote] {
+			db.sync_rule()
+				.create(
+					"/Private/".to_string(),
+					SyncMode::LocalOnly as i32,
+					location::id::equals(location.id),
+					vec![],
+				)
+				.exec()
+				.await
+				.unwrap();
+		}
+
+		assert_eq!(retag(&library.sync, local.id).await.unwrap(), 1);
+		assert_eq!(retag(&library.sync, remote.id).await.unwrap(), 0);
+
+		let sync_mode = |id| async move {
+			db.file_path()
+				.find_unique(file_path::id::equals(id))
+				.exec()
+				.await
+				.unwrap()
+				.unwrap()
+				.sync_mode
+		};
+		assert_eq!(sync_mode(local_path.id).await, SyncMode::LocalOnly.to_db());
+		assert_eq!(sync_mode(remote_path.id).await, None);
+
+		// Only the local file path was deleted from the other devices
+		let deleted = db
+			.crdt_operation()
+			.find_many(vec![])
+			.exec()
+			.await
+			.unwrap()
+			.into_iter()
+			.map(|op| file_path_pub_id(&crate::from_crdt_ops(op).unwrap()).unwrap())
+			.collect::<Vec<_>>();
+		assert_eq!(deleted, vec![local_path.pub_id]);
+	}
+
+	#[test]
+	fn test_local_objects() {
+		let local = local_objects([
+			(b"local".to_vec(), SyncMode::LocalOnly),
+			(b"local".to_vec(), SyncMode::MetadataOnly),
+			(b"synced".to_vec(), SyncMode::LocalOnly),
+			(b"synced".to_vec(), SyncMode::Full),
+			(b"full".to_vec(), SyncMode::Full),
+		]);
+
+		assert_eq!(local, HashSet::from([b"local".to_vec()]));
+	}
+}
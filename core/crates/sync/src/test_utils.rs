@@ -0,0 +1,94 @@
+//! A library database and sync manager for the tests
+
+use sd_prisma::prisma::{device, file_path, location, PrismaClient};
+use sd_utils::db::load_and_migrate;
+
+use std::sync::{atomic::AtomicBool, Arc};
+
+use tempfile::TempDir;
+use uuid::Uuid;
+
+use super::{DevicePubId, SyncManager};
+
+pub struct TestLibrary {
+	pub sync: SyncManager,
+	pub db: Arc<PrismaClient>,
+	pub device: device::Data,
+	// Kept until the test is done with the database
+	_dir: TempDir,
+}
+
+impl TestLibrary {
+	/// A library of this device, emitting sync operations
+	pub async fn new() -> Self {
+		let dir = tempfile::tempdir().expect("failed to create a temporary directory");
+		let db_url = format!(
+			"file:{}?connection_limit=1",
+			dir.path().join("library.db").display()
+		);
+		let db = Arc::new(
+			load_and_migrate(&db_url)
+				.await
+				.expect("failed to load the db"),
+		);
+
+		let device_pub_id = DevicePubId::new();
+		let device = add_device(&db, &device_pub_id).await;
+		let (sync, _) = SyncManager::new(
+			Arc::clone(&db),
+			&device_pub_id,
+			Arc::new(AtomicBool::new(true)),
+		)
+		.await
+		.expect("failed to create the sync manager");
+
+		Self {
+			sync,
+			db,
+			device,
+			_dir: dir,
+		}
+	}
+}
+
+pub async fn add_device(db: &PrismaClient, pub_id: &DevicePubId) -> device::Data {
+	db.device()
+		.create(pub_id.to_db(), vec![])
+		.exec()
+		.await
+		.expect("failed to create the device")
+}
+
+pub async fn add_location(db: &PrismaClient, device: &device::Data) -> location::Data {
+	db.location()
+		.create(
+			Uuid::now_v7().as_bytes().to_vec(),
+			vec![location::device::connect(device::id::equals(device.id))],
+		)
+		.exec()
+		.await
+		.expect("failed to create the location")
+}
+
+pub async fn add_file_path(
+	db: &PrismaClient,
+	location: &location::Data,
+	device: &device::Data,
+	materialized_path: &str,
+	name: &str,
+) -> file_path::Data {
+	db.file_path()
+		.create(
+			Uuid::now_v7().as_bytes().to_vec(),
+			vec![
+				file_path::location::connect(location::id::equals(location.id)),
+				file_path::device::connect(device::id::equals(device.id)),
+				file_path::materialized_path::set(Some(materialized_path.to_string())),
+				file_path::name::set(Some(name.to_string())),
+				file_path::is_dir::set(Some(false)),
+			],
+		)
+		.exec()
+		.await
+		.expect("failed to create the file path")
+}
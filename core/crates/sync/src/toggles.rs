@@ -0,0 +1,128 @@
+//! Toggles, the models a library can stop syncing.
+//!
+//! The changes this device makes to the records of a disabled model aren't written as operations,
+//! nor backfilled, and the operations other devices send for it are dropped instead of ingested.
+//! Neither are kept for later, once a model is enabled again only its changes from then on sync, a
+//! backfill sends the rest. The device model is always synced, the other devices need it.
+
+use sd_prisma::{
+	prisma::{cloud_crdt_operation, crdt_operation, PrismaClient},
+	prisma_sync,
+};
+use sd_sync::CRDTOperation;
+
+use std::{
+	collections::HashSet,
+	sync::{PoisonError, RwLock},
+};
+
+use serde::Serialize;
+use specta::Type;
+
+use super::{DevicePubId, Error, ModelId, MODELS};
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct ModelToggle {
+	pub model: String,
+	pub enabled: bool,
+}
+
+/// The disabled models, the library config keeps their names
+#[derive(Debug, Default)]
+pub struct ModelToggles(RwLock<HashSet<ModelId>>);
+
+impl ModelToggles {
+	/// Replaces the disabled models, leaving them as they were if a name isn't one of a model
+	/// that can be disabled
+	pub fn set_disabled(&self, names: &[String]) -> Result<(), Error> {
+		let disabled = names
+			.iter()
+			.map(|name| {
+				MODELS
+					.into_iter()
+					.find(|(model_id, model)| {
+						*model_id != prisma_sync::device::MODEL_ID && model == name
+					})
+					.map(|(model_id, _)| model_id)
+					.ok_or_else(|| Error::UntoggleableModel(name.clone()))
+			})
+			.collect::<Result<_, _>>()?;
+
+		*self.0.write().unwrap_or_else(PoisonError::into_inner) = disabled;
+
+		Ok(())
+	}
+
+	#[must_use]
+	pub fn is_enabled(&self, model_id: ModelId) -> bool {
+		!self
+			.0
+			.read()
+			.unwrap_or_else(PoisonError::into_inner)
+			.contains(&model_id)
+	}
+
+	/// Every model but the device, in the order they're synced
+	#[must_use]
+	pub fn list(&self) -> Vec<ModelToggle> {
+		MODELS
+			.into_iter()
+			.filter(|(model_id, _)| *model_id != prisma_sync::device::MODEL_ID)
+			.map(|(model_id, model)| ModelToggle {
+				model: model.to_string(),
+				enabled: self.is_enabled(model_id),
+			})
+			.collect()
+	}
+
+	fn disabled(&self) -> Vec<i32> {
+		self.0
+			.read()
+			.unwrap_or_else(PoisonError::into_inner)
+			.iter()
+			.copied()
+			.map(i32::from)
+			.collect()
+	}
+
+	pub(crate) fn retain_enabled(&self, mut ops: Vec<CRDTOperation>) -> Vec<CRDTOperation> {
+		ops.retain(|op| self.is_enabled(op.model_id));
+		ops
+	}
+
+	/// Drops the operations of the disabled models waiting to be ingested, returning how many
+	pub(crate) async fn reject_pending(&self, db: &PrismaClient) -> Result<i64, Error> {
+		let disabled = self.disabled();
+		if disabled.is_empty() {
+			return Ok(0);
+		}
+
+		Ok(db
+			.cloud_crdt_operation()
+			.delete_many(vec![cloud_crdt_operation::model::in_vec(disabled)])
+			.exec()
+			.await?)
+	}
+
+	/// Drops the operations the backfill generated for the disabled models of this device
+	pub(crate) async fn reject_backfilled(
+		&self,
+		db: &PrismaClient,
+		device_pub_id: &DevicePubId,
+	) -> Result<(), Error> {
+		let disabled = self.disabled();
+		if disabled.is_empty() {
+			return Ok(());
+		}
+
+		db.crdt_operation()
+			.delete_many(vec![
+				crdt_operation::device_pub_id::equals(device_pub_id.to_db()),
+				crdt_operation::model::in_vec(disabled),
+			])
+			.exec()
+			.await?;
+
+		Ok(())
+	}
+}
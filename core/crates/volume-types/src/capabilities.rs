@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// What this device can do with its volumes, so the frontend hides the actions that would fail
+/// instead of reporting the failure once clicked
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct DeviceCapabilities {
+	/// The OS reports volume changes, without it they are found by the periodic checks
+	pub native_watch: bool,
+	/// The health data of the drives can be read, like their SMART attributes
+	pub smart: bool,
+	/// The operations the user isn't allowed to do can ask for their password instead of failing
+	pub elevation: bool,
+	pub mount: bool,
+	pub unmount: bool,
+	pub eject: bool,
+	pub format: bool,
+	/// Network shares, like SMB or NFS ones, can be mounted
+	pub network_shares: bool,
+	/// Disk images, like ISO or DMG files, can be attached as volumes
+	pub disk_images: bool,
+}
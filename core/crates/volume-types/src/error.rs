@@ -0,0 +1,118 @@
+use crate::LocalizedMessage;
+
+use std::{collections::BTreeMap, fmt};
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// Stable error codes, for the frontend to branch on and to pick a translated message
+///
+/// These are part of the API, existing codes must not be renamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum VolumeErrorCode {
+	AlreadyExists,
+	Cancelled,
+	CredentialsUnavailable,
+	Database,
+	DeviceBusy,
+	DeviceError,
+	InsufficientSpace,
+	Internal,
+	InvalidArgument,
+	InvalidConfiguration,
+	InvalidDiskImage,
+	InvalidFingerprint,
+	InvalidShareUrl,
+	InvalidSnapshot,
+	Io,
+	JobsRunning,
+	Locked,
+	MountFailed,
+	NotFound,
+	NotMounted,
+	OperationFailed,
+	PermissionDenied,
+	ReadOnly,
+	ResourceExhausted,
+	SnapshotNotFound,
+	SpeedTestFailed,
+	SystemVolume,
+	Timeout,
+	UnmountFailed,
+	UnsupportedPlatform,
+	WatchInitFailed,
+	WatcherFailed,
+}
+
+impl VolumeErrorCode {
+	pub const fn as_str(self) -> &'static str {
+		match self {
+			VolumeErrorCode::AlreadyExists => "ALREADY_EXISTS",
+			VolumeErrorCode::Cancelled => "CANCELLED",
+			VolumeErrorCode::CredentialsUnavailable => "CREDENTIALS_UNAVAILABLE",
+			VolumeErrorCode::Database => "DATABASE",
+			VolumeErrorCode::DeviceBusy => "DEVICE_BUSY",
+			VolumeErrorCode::DeviceError => "DEVICE_ERROR",
+			VolumeErrorCode::InsufficientSpace => "INSUFFICIENT_SPACE",
+			VolumeErrorCode::Internal => "INTERNAL",
+			VolumeErrorCode::InvalidArgument => "INVALID_ARGUMENT",
+			VolumeErrorCode::InvalidConfiguration => "INVALID_CONFIGURATION",
+			VolumeErrorCode::InvalidDiskImage => "INVALID_DISK_IMAGE",
+			VolumeErrorCode::InvalidFingerprint => "INVALID_FINGERPRINT",
+			VolumeErrorCode::InvalidShareUrl => "INVALID_SHARE_URL",
+			VolumeErrorCode::InvalidSnapshot => "INVALID_SNAPSHOT",
+			VolumeErrorCode::Io => "IO",
+			VolumeErrorCode::JobsRunning => "JOBS_RUNNING",
+			VolumeErrorCode::Locked => "LOCKED",
+			VolumeErrorCode::MountFailed => "MOUNT_FAILED",
+			VolumeErrorCode::NotFound => "NOT_FOUND",
+			VolumeErrorCode::NotMounted => "NOT_MOUNTED",
+			VolumeErrorCode::OperationFailed => "OPERATION_FAILED",
+			VolumeErrorCode::PermissionDenied => "PERMISSION_DENIED",
+			VolumeErrorCode::ReadOnly => "READ_ONLY",
+			VolumeErrorCode::ResourceExhausted => "RESOURCE_EXHAUSTED",
+			VolumeErrorCode::SnapshotNotFound => "SNAPSHOT_NOT_FOUND",
+			VolumeErrorCode::SpeedTestFailed => "SPEED_TEST_FAILED",
+			VolumeErrorCode::SystemVolume => "SYSTEM_VOLUME",
+			VolumeErrorCode::Timeout => "TIMEOUT",
+			VolumeErrorCode::UnmountFailed => "UNMOUNT_FAILED",
+			VolumeErrorCode::UnsupportedPlatform => "UNSUPPORTED_PLATFORM",
+			VolumeErrorCode::WatchInitFailed => "WATCH_INIT_FAILED",
+			VolumeErrorCode::WatcherFailed => "WATCHER_FAILED",
+		}
+	}
+
+	/// Translation key of the user-facing message, in `interface/locales/*/common.json`
+	pub fn message_key(self) -> String {
+		format!("volume_error_{}", self.as_str().to_lowercase())
+	}
+}
+
+impl fmt::Display for VolumeErrorCode {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+/// A volume error as sent to the frontend, built from the core's `VolumeError`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct VolumeErrorInfo {
+	pub code: VolumeErrorCode,
+	/// Translation key, the frontend falls back to `message` when it has none
+	pub message_key: String,
+	/// Values for the placeholders of the translation, like `path`
+	pub params: BTreeMap<String, String>,
+	/// Untranslated description, with the underlying cause
+	pub message: String,
+	pub path: Option<String>,
+}
+
+impl VolumeErrorInfo {
+	pub fn localized(&self) -> LocalizedMessage {
+		LocalizedMessage {
+			key: self.message_key.clone(),
+			params: self.params.clone(),
+		}
+	}
+}
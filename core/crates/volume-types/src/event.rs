@@ -0,0 +1,160 @@
+use crate::{
+	ByteSize, DegradedMode, HealthStatus, LocalizedMessage, LowSpaceThreshold, Volume,
+	VolumeErrorInfo, VolumeFingerprint, VolumeHealth,
+};
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// Events emitted by the Volume Manager when volume state changes
+#[derive(Debug, Clone, Type, Deserialize, Serialize)]
+pub enum VolumeEvent {
+	/// Emitted when a new volume is discovered and added
+	VolumeAdded(Volume),
+	/// Emitted when a volume is removed from the system
+	VolumeRemoved(Volume),
+	/// Emitted when a volume's properties are updated
+	VolumeUpdated { old: Volume, new: Volume },
+	/// Emitted after `VolumeUpdated` when the volume was remounted read-only, or writable
+	VolumeRemounted {
+		fingerprint: VolumeFingerprint,
+		read_only: bool,
+	},
+	/// Emitted after `VolumeUpdated` when the free space of the volume changed. The capacity is
+	/// part of the fingerprint, a volume growing is removed and added again.
+	VolumeSpaceChanged {
+		fingerprint: VolumeFingerprint,
+		total_bytes_available: ByteSize,
+	},
+	/// Emitted when a volume's speed test completes
+	VolumeSpeedTested {
+		fingerprint: VolumeFingerprint,
+		read_speed: u64,
+		write_speed: u64,
+	},
+	/// Emitted when a volume's mount status changes
+	VolumeMountChanged {
+		fingerprint: VolumeFingerprint,
+		is_mounted: bool,
+	},
+	/// Emitted when a volume encounters an error
+	VolumeError {
+		fingerprint: VolumeFingerprint,
+		error: VolumeErrorInfo,
+	},
+	/// Emitted when the usage trend of a volume says it fills up within the configured horizon
+	VolumeFillingUp {
+		fingerprint: VolumeFingerprint,
+		name: String,
+		days_until_full: u32,
+	},
+	/// Emitted when the drive of a volume reports another health status, not when it stops
+	/// reporting one
+	VolumeHealthChanged {
+		fingerprint: VolumeFingerprint,
+		name: String,
+		health: VolumeHealth,
+	},
+	/// Emitted when a tracked volume is quarantined after too many I/O errors in a row, the jobs
+	/// on its locations are paused
+	VolumeDegraded { volume: Volume, errors: u32 },
+	/// Emitted when the free space of a tracked volume drops below the configured threshold, again
+	/// only once it went back above it
+	LowSpace {
+		volume: Volume,
+		threshold: LowSpaceThreshold,
+	},
+}
+
+impl VolumeEvent {
+	/// What happened, for the frontend to show in the user's language
+	pub fn message(&self) -> LocalizedMessage {
+		let volume_message = |key: &str, volume: &Volume| {
+			LocalizedMessage::new(key)
+				.param("name", &volume.name)
+				.param("mount_point", volume.mount_point.display())
+		};
+
+		match self {
+			VolumeEvent::VolumeAdded(volume) => volume_message("volume_event_added", volume),
+			VolumeEvent::VolumeRemoved(volume) => volume_message("volume_event_removed", volume),
+			VolumeEvent::VolumeUpdated { new, .. } => volume_message("volume_event_updated", new),
+			VolumeEvent::VolumeRemounted {
+				fingerprint,
+				read_only,
+			} => LocalizedMessage::new(if *read_only {
+				"volume_event_remounted_read_only"
+			} else {
+				"volume_event_remounted_writable"
+			})
+			.param("fingerprint", fingerprint),
+			VolumeEvent::VolumeSpaceChanged {
+				fingerprint,
+				total_bytes_available,
+			} => LocalizedMessage::new("volume_event_space_changed")
+				.param("fingerprint", fingerprint)
+				.param("available", total_bytes_available),
+			VolumeEvent::VolumeSpeedTested {
+				fingerprint,
+				read_speed,
+				write_speed,
+			} => LocalizedMessage::new("volume_event_speed_tested")
+				.param("fingerprint", fingerprint)
+				.param("read_speed", read_speed)
+				.param("write_speed", write_speed),
+			VolumeEvent::VolumeMountChanged {
+				fingerprint,
+				is_mounted,
+			} => LocalizedMessage::new(if *is_mounted {
+				"volume_event_mounted"
+			} else {
+				"volume_event_unmounted"
+			})
+			.param("fingerprint", fingerprint),
+			VolumeEvent::VolumeError { fingerprint, error } => {
+				error.localized().param("fingerprint", fingerprint)
+			}
+			VolumeEvent::VolumeFillingUp {
+				fingerprint,
+				name,
+				days_until_full,
+			} => LocalizedMessage::new("volume_event_filling_up")
+				.param("fingerprint", fingerprint)
+				.param("name", name)
+				.param("days", days_until_full),
+			VolumeEvent::VolumeHealthChanged {
+				fingerprint,
+				name,
+				health,
+			} => LocalizedMessage::new(match health.status {
+				HealthStatus::Failing => "volume_event_health_failing",
+				HealthStatus::Warning => "volume_event_health_warning",
+				HealthStatus::Healthy | HealthStatus::Unknown => "volume_event_health_healthy",
+			})
+			.param("fingerprint", fingerprint)
+			.param("name", name),
+			VolumeEvent::VolumeDegraded { volume, errors } => {
+				volume_message("volume_event_degraded", volume).param("errors", errors)
+			}
+			VolumeEvent::LowSpace { volume, .. } => {
+				volume_message("volume_event_low_space", volume)
+					.param("available", volume.total_bytes_available)
+			}
+		}
+	}
+}
+
+/// Where a volume scan is at, so lists can fill in while slow mounts are still being probed
+#[derive(Debug, Clone, Type, Deserialize, Serialize)]
+pub enum ScanProgress {
+	/// A mount was probed, `volume` is `None` when it turned out not to be a volume
+	Probed {
+		processed: u32,
+		total: u32,
+		volume: Option<Volume>,
+	},
+	/// Every mount was probed, with what the scan had to do without
+	Finished { degraded_mode: DegradedMode },
+	/// The scan stopped, the volumes probed so far aren't registered
+	Failed(VolumeErrorInfo),
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// What the drive says about itself, from most to least worrying
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+	/// The drive failed its self-assessment, its data should be copied off it
+	Failing,
+	/// The drive passes but some attributes are wearing, like remapped sectors
+	Warning,
+	Healthy,
+	/// The drive doesn't report its health, like most USB enclosures
+	Unknown,
+}
+
+/// The S.M.A.R.T. data of the drive a volume is on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct VolumeHealth {
+	pub status: HealthStatus,
+	pub temperature_celsius: Option<i32>,
+	/// Sectors the drive moved elsewhere after they failed, a growing count means the disk wears
+	pub reallocated_sectors: Option<u64>,
+	pub power_on_hours: Option<u64>,
+}
+
+impl VolumeHealth {
+	pub const UNKNOWN: Self = Self {
+		status: HealthStatus::Unknown,
+		temperature_celsius: None,
+		reallocated_sectors: None,
+		power_on_hours: None,
+	};
+}
@@ -0,0 +1,29 @@
+//! Volume data types shared by the core and its clients.
+//!
+//! Only data lives here, detection, persistence and the volume manager stay in `sd-core`, so the
+//! web frontend can depend on this crate when building for wasm.
+
+mod capabilities;
+mod error;
+mod event;
+mod health;
+mod message;
+mod plan;
+mod settings;
+mod size;
+mod topology;
+mod volume;
+
+pub use capabilities::DeviceCapabilities;
+pub use error::{VolumeErrorCode, VolumeErrorInfo};
+pub use event::{ScanProgress, VolumeEvent};
+pub use health::{HealthStatus, VolumeHealth};
+pub use message::LocalizedMessage;
+pub use plan::{BusyProcess, OperationPlan, PlanStep, PredictedOutcome, VolumeOperation};
+pub use settings::VolumeSettings;
+pub use size::{ByteSize, LowSpaceThreshold, ParseByteSizeError, UnitPolicy};
+pub use topology::{MemberState, PoolHealth, PoolKind, PoolMember, VolumeTopology};
+pub use volume::{
+	DegradedMode, DiskType, EncryptionKind, FileSystem, MissingVolumeData, MountOptions, MountOwner,
+	MountType, UnmountedDevice, Volume, VolumeFingerprint,
+};
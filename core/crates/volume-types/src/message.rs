@@ -0,0 +1,69 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// A user-facing message, for the frontend to translate
+///
+/// `key` is a translation key of `interface/locales/*/common.json`, `params` fill its
+/// `{{placeholders}}`. The core has the English of every key for the clients without translations.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct LocalizedMessage {
+	pub key: String,
+	pub params: BTreeMap<String, String>,
+}
+
+impl LocalizedMessage {
+	pub fn new(key: impl Into<String>) -> Self {
+		Self {
+			key: key.into(),
+			params: BTreeMap::new(),
+		}
+	}
+
+	pub fn param(mut self, name: &str, value: impl fmt::Display) -> Self {
+		self.params.insert(name.to_string(), value.to_string());
+		self
+	}
+
+	/// Fills the placeholders of a translation of the key, leaving the unknown ones as they are
+	pub fn render(&self, template: &str) -> String {
+		let mut rendered = String::with_capacity(template.len());
+		let mut rest = template;
+
+		while let Some(start) = rest.find("{{") {
+			let Some(len) = rest[start..].find("}}") else {
+				break;
+			};
+			let placeholder = &rest[start..start + len + 2];
+
+			rendered.push_str(&rest[..start]);
+			match self.params.get(placeholder[2..len].trim()) {
+				Some(value) => rendered.push_str(value),
+				None => rendered.push_str(placeholder),
+			}
+			rest = &rest[start + len + 2..];
+		}
+		rendered.push_str(rest);
+
+		rendered
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn renders_the_known_params() {
+		let message = LocalizedMessage::new("volume_event_added")
+			.param("name", "USB")
+			.param("mount_point", "/media/usb");
+
+		assert_eq!(
+			message.render("{{name}} was connected at {{ mount_point }}, {{missing}}"),
+			"USB was connected at /media/usb, {{missing}}"
+		);
+		assert_eq!(message.render("Unclosed {{name"), "Unclosed {{name");
+	}
+}
@@ -0,0 +1,146 @@
+use crate::{ByteSize, FileSystem, Volume, VolumeErrorInfo};
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// The operations that take a volume away, which can be checked with a dry run first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum VolumeOperation {
+	Unmount,
+	/// Unmounts the volume and powers its drive off, so it can be unplugged
+	Eject,
+	/// Erases the volume with a new file system
+	Format {
+		file_system: FileSystem,
+	},
+}
+
+impl VolumeOperation {
+	/// For the messages, as in "`unmounting` is not supported"
+	pub fn gerund(&self) -> &'static str {
+		match self {
+			Self::Unmount => "unmounting",
+			Self::Eject => "ejecting",
+			Self::Format { .. } => "formatting",
+		}
+	}
+}
+
+/// What running an operation would do, in order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum PlanStep {
+	/// The user is asked for the privileges the following steps need
+	Authorize,
+	Unmount {
+		mount_point: PathBuf,
+	},
+	/// Detaches the file system while it's busy, it's unmounted for good once nothing uses it
+	LazyUnmount {
+		mount_point: PathBuf,
+	},
+	PowerOff {
+		device: Option<PathBuf>,
+	},
+	Format {
+		device: Option<PathBuf>,
+		file_system: FileSystem,
+	},
+}
+
+/// A process with files open on the volume, or running from it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct BusyProcess {
+	pub pid: u32,
+	pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(tag = "outcome", content = "error", rename_all = "snake_case")]
+pub enum PredictedOutcome {
+	Succeeds,
+	/// Succeeds once the user grants the privileges
+	NeedsAuthorization,
+	/// The operation would be refused, or fail
+	Fails(VolumeErrorInfo),
+}
+
+/// The steps an operation runs on a volume and what they are expected to lead to, from the
+/// checks it runs before doing anything
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct OperationPlan {
+	pub operation: VolumeOperation,
+	pub name: String,
+	pub mount_point: PathBuf,
+	pub steps: Vec<PlanStep>,
+	/// Only the processes of the current user are found, unless the core runs as root
+	pub busy: Vec<BusyProcess>,
+	/// The data that stops being reachable, or is erased by a format
+	pub bytes_affected: ByteSize,
+	pub outcome: PredictedOutcome,
+}
+
+impl OperationPlan {
+	/// A plan without steps yet, expected to succeed until a check says otherwise
+	pub fn new(operation: VolumeOperation, volume: &Volume) -> Self {
+		Self {
+			operation,
+			name: volume.name.clone(),
+			mount_point: volume.mount_point.clone(),
+			steps: Vec::new(),
+			busy: Vec::new(),
+			bytes_affected: ByteSize(
+				volume
+					.total_bytes_capacity
+					.0
+					.saturating_sub(volume.total_bytes_available.0),
+			),
+			outcome: PredictedOutcome::Succeeds,
+		}
+	}
+
+	pub fn needs_authorization(&self) -> bool {
+		self.steps.contains(&PlanStep::Authorize)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::{DiskType, MountType};
+
+	#[test]
+	fn plans_count_the_used_space_as_affected() {
+		let volume = Volume::new(
+			"USB".to_string(),
+			MountType::External,
+			PathBuf::from("/media/usb"),
+			vec![PathBuf::from("/media/usb")],
+			DiskType::SSD,
+			FileSystem::ExFAT,
+			1000,
+			400,
+			false,
+		);
+
+		let mut plan = OperationPlan::new(VolumeOperation::Eject, &volume);
+		assert_eq!(plan.bytes_affected, ByteSize(600));
+		assert!(!plan.needs_authorization());
+
+		plan.steps.push(PlanStep::Authorize);
+		assert!(plan.needs_authorization());
+
+		assert_eq!(
+			serde_json::to_value(&plan.steps[0]).unwrap(),
+			serde_json::json!({ "step": "authorize" })
+		);
+		assert_eq!(
+			serde_json::to_value(&plan.outcome).unwrap(),
+			serde_json::json!({ "outcome": "succeeds" })
+		);
+	}
+}
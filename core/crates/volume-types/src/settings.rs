@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// What the user set for a volume, kept by fingerprint so it follows the volume across mounts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct VolumeSettings {
+	/// Left out of the explorer, it's still listed in the volume settings to be shown again
+	pub is_hidden: bool,
+	/// The jobs writing to its locations are refused, whatever the volume is mounted as
+	pub force_read_only: bool,
+	/// Whether it's tracked as soon as a library loads, `null` only tracks the system volumes
+	pub auto_track: Option<bool>,
+}
+
+impl VolumeSettings {
+	/// Whether nothing was changed, these aren't kept
+	pub fn is_default(&self) -> bool {
+		*self == Self::default()
+	}
+}
@@ -0,0 +1,391 @@
+use std::{fmt, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use specta::Type;
+use thiserror::Error;
+
+/// Whether sizes are shown in powers of 1000 or of 1024
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitPolicy {
+	/// kB, MB, GB..., like disk vendors, macOS and most file managers on Linux
+	Decimal,
+	/// KiB, MiB, GiB..., like Windows and the command line tools
+	#[default]
+	Binary,
+}
+
+impl UnitPolicy {
+	const fn base(self) -> u64 {
+		match self {
+			UnitPolicy::Decimal => 1000,
+			UnitPolicy::Binary => 1024,
+		}
+	}
+
+	const fn units(self) -> [&'static str; 7] {
+		match self {
+			UnitPolicy::Decimal => ["B", "kB", "MB", "GB", "TB", "PB", "EB"],
+			UnitPolicy::Binary => ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"],
+		}
+	}
+}
+
+/// A number of bytes, like the capacity of a volume
+///
+/// Sent as a string of the number of bytes, as the frontend can't hold every u64 in a number.
+/// Parsing also accepts a unit suffix, like `1.5 GiB` or the `500M` of command line tools.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Type)]
+#[specta(transparent)]
+pub struct ByteSize(#[specta(type = String)] pub u64);
+
+impl ByteSize {
+	pub const fn as_u64(self) -> u64 {
+		self.0
+	}
+
+	/// Human readable, with one decimal past bytes: `1.5 GiB`, or `1.6 GB` in decimal
+	pub fn format(self, policy: UnitPolicy) -> String {
+		let base = policy.base() as f64;
+		let units = policy.units();
+
+		let mut value = self.0 as f64;
+		let mut unit = 0;
+		while value >= base && unit < units.len() - 1 {
+			value /= base;
+			unit += 1;
+		}
+
+		if unit == 0 {
+			format!("{} B", self.0)
+		} else {
+			format!("{value:.1} {}", units[unit])
+		}
+	}
+
+	/// Percentage of `total` this size is, 0 when `total` is empty
+	pub fn percent_of(self, total: ByteSize) -> u64 {
+		if total.0 == 0 {
+			0
+		} else {
+			(u128::from(self.0) * 100 / u128::from(total.0)) as u64
+		}
+	}
+}
+
+/// How little free space a volume can have left before it's low on space
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum LowSpaceThreshold {
+	/// Percentage of the capacity, from 0 to 100
+	Percentage(u8),
+	Bytes(ByteSize),
+}
+
+impl Default for LowSpaceThreshold {
+	fn default() -> Self {
+		Self::Percentage(10)
+	}
+}
+
+impl LowSpaceThreshold {
+	/// Whether `available` of `capacity` is below the threshold, never when the capacity is unknown
+	pub fn is_crossed(self, available: ByteSize, capacity: ByteSize) -> bool {
+		if capacity.0 == 0 {
+			return false;
+		}
+
+		match self {
+			Self::Percentage(percent) => available.percent_of(capacity) < u64::from(percent),
+			Self::Bytes(bytes) => available < bytes,
+		}
+	}
+}
+
+impl From<u64> for ByteSize {
+	fn from(bytes: u64) -> Self {
+		Self(bytes)
+	}
+}
+
+impl From<ByteSize> for u64 {
+	fn from(size: ByteSize) -> Self {
+		size.0
+	}
+}
+
+impl fmt::Display for ByteSize {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.format(UnitPolicy::default()))
+	}
+}
+
+impl FromStr for ByteSize {
+	type Err = ParseByteSizeError;
+
+	/// Parses sizes as people and tools write them: `1.5 TB`, `1,024 MB`, `1.024,5 kB`, `2 Gio`
+	///
+	/// When the separator can't be told apart, a single `.` is the decimal one, and so is a single
+	/// `,` unless exactly three digits follow it, as in `1,024`.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let trimmed = s.trim();
+		if trimmed.is_empty() {
+			return Err(ParseByteSizeError::Empty);
+		}
+
+		let (number, unit) = trimmed.split_at(
+			trimmed
+				.find(|c: char| c.is_alphabetic())
+				.unwrap_or(trimmed.len()),
+		);
+		let multiplier = unit_multiplier(unit.trim())
+			.ok_or_else(|| ParseByteSizeError::UnknownUnit(unit.trim().to_string()))?;
+		let (integer, fraction) = split_number(number.trim())
+			.ok_or_else(|| ParseByteSizeError::InvalidNumber(number.trim().to_string()))?;
+
+		let too_large = || ParseByteSizeError::TooLarge(trimmed.to_string());
+
+		// Exact for the integer part, floats would lose precision past 2^53
+		let whole = integer
+			.parse::<u64>()
+			.ok()
+			.and_then(|integer| integer.checked_mul(multiplier))
+			.ok_or_else(too_large)?;
+		let fraction = match fraction {
+			Some(fraction) => format!("0.{fraction}")
+				.parse::<f64>()
+				.map(|fraction| (fraction * multiplier as f64).round() as u64)
+				.unwrap_or_default(),
+			None => 0,
+		};
+
+		whole.checked_add(fraction).map(Self).ok_or_else(too_large)
+	}
+}
+
+/// Why a size couldn't be parsed
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseByteSizeError {
+	#[error("no size given")]
+	Empty,
+	#[error("{0:?} isn't a number")]
+	InvalidNumber(String),
+	#[error("unknown size unit {0:?}")]
+	UnknownUnit(String),
+	#[error("{0} is more bytes than can be counted")]
+	TooLarge(String),
+}
+
+/// Characters that only ever group digits: `1 024`, `1'024` in Switzerland, `1_024` in code
+const GROUP_SEPARATORS: [char; 5] = [' ', '\u{a0}', '\u{202f}', '\'', '_'];
+
+/// Splits a number into its integer digits, without the group separators, and its decimal ones
+fn split_number(number: &str) -> Option<(String, Option<&str>)> {
+	let decimal = match (number.rfind('.'), number.rfind(',')) {
+		(Some(dot), Some(comma)) => Some(dot.max(comma)),
+		(Some(dot), None) => (number.matches('.').count() == 1).then_some(dot),
+		(None, Some(comma)) => {
+			let decimals = &number[comma + 1..];
+			let is_grouping = number.matches(',').count() > 1
+				|| (decimals.len() == 3 && decimals.chars().all(|c| c.is_ascii_digit()));
+			(!is_grouping).then_some(comma)
+		}
+		(None, None) => None,
+	};
+
+	let (integer, fraction) = match decimal {
+		Some(decimal) => (&number[..decimal], Some(&number[decimal + 1..])),
+		None => (number, None),
+	};
+
+	if let Some(fraction) = fraction {
+		if fraction.is_empty() || !fraction.chars().all(|c| c.is_ascii_digit()) {
+			return None;
+		}
+	}
+
+	// `.5`, the integer part may only be left out before decimals
+	if integer.is_empty() {
+		return fraction.map(|fraction| ("0".to_string(), Some(fraction)));
+	}
+
+	let groups = integer
+		.split(|c: char| c == '.' || c == ',' || GROUP_SEPARATORS.contains(&c))
+		.collect::<Vec<_>>();
+	let is_digits = |group: &str| !group.is_empty() && group.chars().all(|c| c.is_ascii_digit());
+	let is_grouped = groups.len() == 1
+		|| (groups[0].len() <= 3 && groups[1..].iter().all(|group| group.len() == 3));
+	if !is_grouped || !groups.iter().all(|group| is_digits(group)) {
+		return None;
+	}
+
+	Some((groups.concat(), fraction))
+}
+
+/// Bytes in a unit: `KiB` is binary, `kB` is decimal and a bare `K` is binary as in `df` and `lsblk`
+///
+/// The French octets work the same: `Kio`, `ko` and `o`.
+fn unit_multiplier(unit: &str) -> Option<u64> {
+	let lowercase = unit.to_lowercase();
+	if matches!(lowercase.as_str(), "byte" | "bytes" | "octet" | "octets") {
+		return Some(1);
+	}
+
+	let (prefix, base) = if let Some(prefix) = lowercase
+		.strip_suffix("ib")
+		.or_else(|| lowercase.strip_suffix("io"))
+	{
+		(prefix, 1024)
+	} else if let Some(prefix) = lowercase
+		.strip_suffix('b')
+		.or_else(|| lowercase.strip_suffix('o'))
+	{
+		(prefix, 1000)
+	} else {
+		(lowercase.as_str(), 1024)
+	};
+
+	let exponent = match prefix {
+		"" => 0,
+		"k" => 1,
+		"m" => 2,
+		"g" => 3,
+		"t" => 4,
+		"p" => 5,
+		"e" => 6,
+		_ => return None,
+	};
+
+	Some(u64::pow(base, exponent))
+}
+
+impl Serialize for ByteSize {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_str(&self.0)
+	}
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		struct Visitor;
+
+		impl de::Visitor<'_> for Visitor {
+			type Value = ByteSize;
+
+			fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+				f.write_str("a number of bytes, or a string of one with an optional unit")
+			}
+
+			fn visit_u64<E: de::Error>(self, bytes: u64) -> Result<Self::Value, E> {
+				Ok(ByteSize(bytes))
+			}
+
+			fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+				s.parse().map_err(E::custom)
+			}
+		}
+
+		deserializer.deserialize_any(Visitor)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_unit_suffixes() {
+		let parse = |s: &str| s.parse::<ByteSize>().map(ByteSize::as_u64);
+
+		assert_eq!(parse("1234"), Ok(1234));
+		assert_eq!(parse("2 KiB"), Ok(2048));
+		assert_eq!(parse("2kB"), Ok(2000));
+		assert_eq!(parse("1.5G"), Ok(3 * 512 * 1024 * 1024));
+		assert_eq!(parse(" 4 TB "), Ok(4_000_000_000_000));
+		assert_eq!(parse("2 Gio"), Ok(2 * 1024 * 1024 * 1024));
+		assert_eq!(parse("512 bytes"), Ok(512));
+	}
+
+	#[test]
+	fn parses_numbers_of_any_locale() {
+		let parse = |s: &str| s.parse::<ByteSize>().map(ByteSize::as_u64);
+
+		assert_eq!(parse("1.5 TB"), Ok(1_500_000_000_000));
+		assert_eq!(parse("1,5 TB"), Ok(1_500_000_000_000));
+		assert_eq!(parse("1,024 MB"), Ok(1_024_000_000));
+		assert_eq!(parse("1,0245 MB"), Ok(1_024_500));
+		assert_eq!(parse("1,024.5 kB"), Ok(1_024_500));
+		assert_eq!(parse("1.024,5 kB"), Ok(1_024_500));
+		assert_eq!(parse("1.024.000 B"), Ok(1_024_000));
+		assert_eq!(parse("1 024 KiB"), Ok(1024 * 1024));
+		assert_eq!(parse("1\u{a0}024 KiB"), Ok(1024 * 1024));
+		assert_eq!(parse("1'024 B"), Ok(1024));
+		assert_eq!(parse(".5 KiB"), Ok(512));
+		// Exact past the precision of floats
+		assert_eq!(parse("18446744073709551615"), Ok(u64::MAX));
+	}
+
+	#[test]
+	fn refuses_garbage() {
+		let parse = |s: &str| s.parse::<ByteSize>();
+
+		assert_eq!(parse("  "), Err(ParseByteSizeError::Empty));
+		assert_eq!(
+			parse("12 parsecs"),
+			Err(ParseByteSizeError::UnknownUnit("parsecs".to_string()))
+		);
+		for number in [
+			"GB", "-5", "1,,024", "1,02,4", "1.5.5", "1,5.", "1 2 MB", "1.",
+		] {
+			assert!(
+				matches!(parse(number), Err(ParseByteSizeError::InvalidNumber(_))),
+				"{number:?} was parsed"
+			);
+		}
+		assert!(matches!(
+			parse("99999999999 EiB"),
+			Err(ParseByteSizeError::TooLarge(_))
+		));
+	}
+
+	#[test]
+	fn formats_with_the_policy() {
+		let size = ByteSize(1_500_000_000);
+
+		assert_eq!(size.format(UnitPolicy::Binary), "1.4 GiB");
+		assert_eq!(size.format(UnitPolicy::Decimal), "1.5 GB");
+		assert_eq!(ByteSize(512).format(UnitPolicy::Binary), "512 B");
+	}
+
+	#[test]
+	fn crosses_low_space_thresholds() {
+		let capacity = ByteSize(1000);
+
+		assert!(LowSpaceThreshold::Percentage(10).is_crossed(ByteSize(99), capacity));
+		assert!(!LowSpaceThreshold::Percentage(10).is_crossed(ByteSize(100), capacity));
+		assert!(LowSpaceThreshold::Bytes(ByteSize(200)).is_crossed(ByteSize(150), capacity));
+		assert!(!LowSpaceThreshold::Bytes(ByteSize(200)).is_crossed(ByteSize(200), capacity));
+		assert!(!LowSpaceThreshold::default().is_crossed(ByteSize(0), ByteSize(0)));
+
+		assert_eq!(
+			serde_json::from_str::<LowSpaceThreshold>(r#"{ "bytes": "5 GiB" }"#).unwrap(),
+			LowSpaceThreshold::Bytes(ByteSize(5 * 1024 * 1024 * 1024))
+		);
+	}
+
+	#[test]
+	fn is_sent_as_a_string() {
+		assert_eq!(
+			serde_json::to_value(ByteSize(u64::MAX)).unwrap(),
+			serde_json::json!(u64::MAX.to_string())
+		);
+		assert_eq!(
+			serde_json::from_str::<ByteSize>(r#""1 MiB""#).unwrap(),
+			ByteSize(1024 * 1024)
+		);
+		assert_eq!(
+			serde_json::from_str::<ByteSize>("42").unwrap(),
+			ByteSize(42)
+		);
+	}
+}
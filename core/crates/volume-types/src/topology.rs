@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// What pools the devices of a volume together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolKind {
+	/// Linux software RAID, an md array
+	Raid,
+	/// A logical volume of LVM
+	Lvm,
+	/// A dataset of a ZFS pool
+	Zfs,
+}
+
+/// How the pool is doing as a whole, from most to least worrying
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolHealth {
+	/// Data was lost or the pool can't be read, like an array missing more members than it can
+	/// do without
+	Failed,
+	/// Members are missing or failed, the data is intact but one more failure can lose it
+	Degraded,
+	/// A member is being rebuilt, the pool is degraded until it's done
+	Rebuilding,
+	Healthy,
+	Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum MemberState {
+	Active,
+	/// Standing by to replace a member that fails
+	Spare,
+	Rebuilding,
+	Faulty,
+	/// Expected by the pool but not there
+	Missing,
+	Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct PoolMember {
+	/// The device, like `/dev/sda1`, or the name the tool gives a missing one
+	pub device: String,
+	pub state: MemberState,
+}
+
+/// The pool a volume is on, for the volumes on more than a plain partition
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct VolumeTopology {
+	pub kind: PoolKind,
+	/// The array, logical volume or pool, like `md0`, `vg0/home` or `tank`
+	pub name: String,
+	/// The layout as the tool names it, like `raid5`, `linear` or `mirror`
+	pub level: String,
+	/// Members that can fail without losing data, 0 for the layouts without redundancy
+	pub redundancy: u32,
+	pub health: PoolHealth,
+	pub members: Vec<PoolMember>,
+}
+
+impl VolumeTopology {
+	pub fn is_degraded(&self) -> bool {
+		matches!(
+			self.health,
+			PoolHealth::Failed | PoolHealth::Degraded | PoolHealth::Rebuilding
+		)
+	}
+}
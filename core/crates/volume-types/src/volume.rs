@@ -0,0 +1,554 @@
+use crate::{ByteSize, VolumeTopology};
+
+use std::{
+	fmt,
+	path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use strum_macros::Display;
+
+/// A fingerprint of a volume, used to identify it when it is not persisted in the database
+#[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord, Type)]
+pub struct VolumeFingerprint(pub Vec<u8>);
+
+impl VolumeFingerprint {
+	/// Hashes what identifies the volume on the device with this public id: its serial when the
+	/// platform read one, so the fingerprint stays the same wherever the volume is mounted, its
+	/// properties otherwise. The mounts of a part of a file system, like btrfs subvolumes and
+	/// bind mounts, share its serial and are told apart by their [`Volume::mount_root`].
+	pub fn new(device_pub_id: &[u8], volume: &Volume) -> Self {
+		let Some(serial) = volume.serial.as_deref().filter(|serial| !serial.is_empty()) else {
+			return Self::from_properties(device_pub_id, volume);
+		};
+
+		let mut hasher = blake3::Hasher::new();
+		hasher.update(device_pub_id);
+		hasher.update(b"serial:");
+		hasher.update(serial.as_bytes());
+		// Left out for the whole file system, which keeps the fingerprint it had before
+		if let Some(root) = volume.mount_root.as_deref().filter(|root| *root != "/") {
+			hasher.update(b"root:");
+			hasher.update(root.as_bytes());
+		}
+		Self(hasher.finalize().as_bytes().to_vec())
+	}
+
+	/// Hashes the properties of the volume alone, the mount point among them. Volumes were all
+	/// fingerprinted this way before their serials were read.
+	pub fn from_properties(device_pub_id: &[u8], volume: &Volume) -> Self {
+		// Hash the device ID, mount point, name, total bytes capacity, and file system
+		let mut hasher = blake3::Hasher::new();
+		hasher.update(device_pub_id);
+		hasher.update(volume.mount_point.to_string_lossy().as_bytes());
+		hasher.update(volume.name.as_bytes());
+		hasher.update(&volume.total_bytes_capacity.0.to_be_bytes());
+		hasher.update(volume.file_system.to_string().as_bytes());
+		// These are all properties that are unique to a volume and unlikely to change
+		// If a .spacedrive file is found in the volume, and is fingerprint does not match,
+		// but the `pub_id` is the same, we can update the values and regenerate the fingerprint
+		// preserving the tracked instance of the volume
+		Self(hasher.finalize().as_bytes().to_vec())
+	}
+}
+
+impl fmt::Display for VolumeFingerprint {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", hex::encode(&self.0))
+	}
+}
+
+impl Serialize for VolumeFingerprint {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		// Convert to hex string when serializing
+		serializer.serialize_str(&hex::encode(&self.0))
+	}
+}
+
+impl<'de> Deserialize<'de> for VolumeFingerprint {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: serde::Deserializer<'de>,
+	{
+		let s = String::deserialize(deserializer)?;
+		hex::decode(s)
+			.map(VolumeFingerprint)
+			.map_err(serde::de::Error::custom)
+	}
+}
+
+/// Volume data the platform couldn't provide, usually in containers and locked down environments
+#[derive(
+	Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Type,
+)]
+pub enum MissingVolumeData {
+	/// No disks were reported, the volumes come from the mount table
+	DiskList,
+	/// The capacity of some volumes couldn't be read and is reported as 0
+	Capacity,
+	/// sysfs isn't readable, disk types are unknown and removable drives may show as system ones
+	DeviceInfo,
+	/// The mount options couldn't be read, volumes are assumed to be writable
+	ReadOnlyStatus,
+}
+
+/// What the volumes were listed without, empty when nothing is missing
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct DegradedMode {
+	pub missing: Vec<MissingVolumeData>,
+}
+
+impl DegradedMode {
+	pub fn new(missing: impl IntoIterator<Item = MissingVolumeData>) -> Self {
+		let mut missing = missing.into_iter().collect::<Vec<_>>();
+		missing.sort();
+		missing.dedup();
+		Self { missing }
+	}
+
+	pub fn is_degraded(&self) -> bool {
+		!self.missing.is_empty()
+	}
+}
+
+/// The user a volume was mounted for, on machines shared by several users
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Type)]
+pub struct MountOwner {
+	pub uid: u32,
+	/// Login name, unless the user database doesn't know the uid
+	pub name: Option<String>,
+	/// Seats the user is logged in on, like `seat0`, empty when they aren't
+	pub seats: Vec<String>,
+}
+
+/// A partition or disk with a file system nothing mounted, which can be mounted by its device
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Type)]
+pub struct UnmountedDevice {
+	/// The device node, like `/dev/sdb1` or `/dev/disk4s2`, or the volume GUID path on Windows
+	pub device: PathBuf,
+	/// Label of the file system, if it has one
+	pub label: Option<String>,
+	pub file_system: FileSystem,
+	pub total_bytes_capacity: ByteSize,
+}
+
+/// How a device is mounted
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Type)]
+#[serde(default)]
+pub struct MountOptions {
+	pub read_only: bool,
+}
+/// Represents a physical or virtual storage volume in the system
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct Volume {
+	/// Fingerprint of the volume as a hash of its properties, stored with the volume once it's
+	/// tracked. Used as the unique identifier for a volume in this module
+	pub fingerprint: Option<VolumeFingerprint>,
+	/// Database ID (None if not yet committed to database)
+	pub id: Option<i32>,
+	/// Unique public identifier
+	pub pub_id: Option<Vec<u8>>,
+	/// Database ID of the device this volume is attached to, if any
+	pub device_id: Option<i32>,
+
+	/// Human-readable volume name
+	pub name: String,
+	/// Type of mount (system, external, etc)
+	pub mount_type: MountType,
+	/// Path where the volume is mounted
+	#[specta(type = Vec<String>)]
+	pub mount_point: PathBuf,
+	/// for APFS volumes like Macintosh HD, additional mount points are returned
+	#[specta(type = Vec<String>)]
+	pub mount_points: Vec<PathBuf>,
+	/// Whether the volume is currently mounted
+	pub is_mounted: bool,
+	/// Type of storage device (SSD, HDD, etc)
+	pub disk_type: DiskType,
+	/// Filesystem type (NTFS, EXT4, etc)
+	pub file_system: FileSystem,
+	/// Whether the volume is mounted read-only
+	pub read_only: bool,
+	/// User the volume was mounted for, none for the mounts of the whole system
+	#[serde(default)]
+	pub owner: Option<MountOwner>,
+	/// Whether the current user can open the volume, the mounts of other users usually can't be.
+	/// Such volumes aren't tracked automatically.
+	#[serde(default = "accessible_by_default")]
+	pub is_accessible: bool,
+	/// How the volume is encrypted, `None` for the plain ones and when the platform can't tell
+	#[serde(default)]
+	pub encryption: Option<EncryptionKind>,
+	/// Whether the volume is encrypted and waits to be unlocked, its files can't be read until then
+	#[serde(default)]
+	pub is_locked: bool,
+	/// Current error status if any
+	pub error_status: Option<String>,
+	/// The UUID or serial number of the file system, or of the partition without one, the same
+	/// wherever the volume is mounted
+	#[serde(default)]
+	pub serial: Option<String>,
+	/// The directory of the file system that's mounted, `/` for all of it, like a btrfs subvolume,
+	/// the source of a bind mount or the share of a mapped network drive. `None` when the platform
+	/// doesn't tell.
+	#[serde(default)]
+	pub mount_root: Option<String>,
+	/// The RAID array, LVM volume group or ZFS pool the volume is on, `None` on a plain partition
+	/// and on the platforms that don't tell
+	#[serde(default)]
+	pub topology: Option<VolumeTopology>,
+	/// Quarantined after failing to read or write too many times in a row, the jobs on it are
+	/// paused. Cleared once it's mounted again.
+	#[serde(default)]
+	pub is_degraded: bool,
+
+	// Performance metrics
+	/// Read speed in megabytes per second
+	pub read_speed_mbps: Option<u64>,
+	/// Write speed in megabytes per second
+	pub write_speed_mbps: Option<u64>,
+	/// Total storage capacity
+	pub total_bytes_capacity: ByteSize,
+	/// Available storage space
+	pub total_bytes_available: ByteSize,
+}
+
+// We can use this to see if a volume has changed
+impl PartialEq for Volume {
+	fn eq(&self, other: &Self) -> bool {
+		self.name == other.name
+            && self.disk_type == other.disk_type
+            && self.file_system == other.file_system
+			&& self.mount_type == other.mount_type
+			&& self.mount_point == other.mount_point
+			// Check if any mount points overlap
+			&& (self.mount_points.iter().any(|mp| other.mount_points.contains(mp))
+			|| other.mount_points.iter().any(|mp| self.mount_points.contains(mp)))
+			&& self.is_mounted == other.is_mounted
+			&& self.read_only == other.read_only
+			&& self.owner == other.owner
+			&& self.is_accessible == other.is_accessible
+			&& self.encryption == other.encryption
+			&& self.is_locked == other.is_locked
+			&& self.error_status == other.error_status
+			&& self.serial == other.serial
+			&& self.topology == other.topology
+			&& self.is_degraded == other.is_degraded
+			&& self.total_bytes_capacity == other.total_bytes_capacity
+			&& self.total_bytes_available == other.total_bytes_available
+	}
+}
+
+impl Eq for Volume {}
+
+/// Volumes from before ownership was detected were all mounted for the current user
+fn accessible_by_default() -> bool {
+	true
+}
+
+impl Volume {
+	/// Creates a new Volume instance from detected system volume information
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		name: String,
+		mount_type: MountType,
+		mount_point: PathBuf,
+		mount_points: Vec<PathBuf>,
+		disk_type: DiskType,
+		file_system: FileSystem,
+		total_bytes_capacity: u64,
+		total_bytes_available: u64,
+		read_only: bool,
+	) -> Self {
+		Self {
+			id: None,
+			pub_id: None,
+			device_id: None,
+			name,
+			mount_type,
+			mount_point,
+			mount_points,
+			is_mounted: true,
+			disk_type,
+			file_system,
+			read_only,
+			owner: None,
+			is_accessible: true,
+			encryption: None,
+			is_locked: false,
+			error_status: None,
+			serial: None,
+			mount_root: None,
+			topology: None,
+			is_degraded: false,
+			read_speed_mbps: None,
+			write_speed_mbps: None,
+			total_bytes_capacity: ByteSize(total_bytes_capacity),
+			total_bytes_available: ByteSize(total_bytes_available),
+			fingerprint: None,
+		}
+	}
+
+	/// Check if a path is under any of this volume's mount points
+	pub fn contains_path(&self, path: &Path) -> bool {
+		self.mount_points.iter().any(|mp| path.starts_with(mp))
+	}
+
+	/// Merge system detected volume with database volume, preferring system values for hardware info
+	pub fn merge_with_db(system_volume: &Volume, db_volume: &Volume) -> Volume {
+		Volume {
+			// Keep system-detected hardware properties
+			mount_point: system_volume.mount_point.clone(),
+			mount_points: system_volume.mount_points.clone(),
+			total_bytes_capacity: system_volume.total_bytes_capacity,
+			total_bytes_available: system_volume.total_bytes_available,
+			disk_type: system_volume.disk_type.clone(),
+			file_system: system_volume.file_system.clone(),
+			mount_type: system_volume.mount_type.clone(),
+			is_mounted: system_volume.is_mounted,
+			fingerprint: system_volume.fingerprint.clone(),
+			name: system_volume.name.clone(),
+			read_only: system_volume.read_only,
+			owner: system_volume.owner.clone(),
+			is_accessible: system_volume.is_accessible,
+			encryption: system_volume.encryption,
+			is_locked: system_volume.is_locked,
+			error_status: system_volume.error_status.clone(),
+			serial: system_volume.serial.clone(),
+			mount_root: system_volume.mount_root.clone(),
+			topology: system_volume.topology.clone(),
+			is_degraded: system_volume.is_degraded,
+			// Speeds are only measured once in a while, the last known ones are better than none
+			read_speed_mbps: system_volume.read_speed_mbps.or(db_volume.read_speed_mbps),
+			write_speed_mbps: system_volume
+				.write_speed_mbps
+				.or(db_volume.write_speed_mbps),
+
+			// Keep database-tracked properties and metadata
+			id: db_volume.id,
+			device_id: db_volume.device_id,
+			pub_id: db_volume.pub_id.clone(),
+		}
+	}
+
+	pub fn is_volume_tracked(&self) -> bool {
+		self.pub_id.is_some()
+	}
+}
+
+/// Represents the type of physical storage device
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Hash, PartialEq, Eq, Display)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum DiskType {
+	/// Solid State Drive
+	SSD,
+	/// Hard Disk Drive
+	HDD,
+	/// Apple Fusion Drive, an SSD and a hard disk merged into one volume
+	Fusion,
+	/// Unknown or virtual disk type
+	Unknown,
+}
+
+impl DiskType {
+	pub fn from_string(disk_type: &str) -> Self {
+		match disk_type.to_uppercase().as_str() {
+			"SSD" => Self::SSD,
+			"HDD" => Self::HDD,
+			"FUSION" => Self::Fusion,
+			_ => Self::Unknown,
+		}
+	}
+}
+
+/// The full disk encryption a volume is protected with
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Type, Hash, PartialEq, Eq, Display)]
+pub enum EncryptionKind {
+	/// Linux Unified Key Setup, opened through dm-crypt
+	Luks,
+	/// Windows BitLocker, or BitLocker To Go on removable drives
+	BitLocker,
+	/// FileVault, or the APFS encryption it's built on for the volumes it doesn't manage
+	FileVault,
+}
+
+/// Represents the filesystem type of the volume
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Hash, PartialEq, Eq, Display)]
+pub enum FileSystem {
+	/// Windows NTFS filesystem
+	NTFS,
+	/// FAT32 filesystem
+	FAT32,
+	/// Linux EXT4 filesystem
+	EXT4,
+	/// Apple APFS filesystem
+	APFS,
+	/// Apple HFS+ filesystem, or Mac OS Extended
+	HFSPlus,
+	/// ExFAT filesystem
+	ExFAT,
+	/// Other/unknown filesystem type
+	Other(String),
+}
+
+impl FileSystem {
+	pub fn from_string(fs: &str) -> Self {
+		match fs.to_uppercase().as_str() {
+			"NTFS" => FileSystem::NTFS,
+			"FAT32" => FileSystem::FAT32,
+			"EXT4" => FileSystem::EXT4,
+			"APFS" => FileSystem::APFS,
+			"HFS" | "HFS+" | "HFSPLUS" => FileSystem::HFSPlus,
+			"EXFAT" => FileSystem::ExFAT,
+			other => FileSystem::Other(other.to_string()),
+		}
+	}
+}
+
+/// Represents how the volume is mounted in the system
+#[derive(Serialize, Deserialize, Debug, Clone, Type, Hash, PartialEq, Eq, Display)]
+pub enum MountType {
+	/// System/boot volume
+	System,
+	/// External/removable volume
+	External,
+	/// Network-attached volume
+	Network,
+	/// Virtual/container volume
+	Virtual,
+	/// A disk image attached as a device, like an ISO or a DMG file
+	DiskImage,
+}
+
+impl MountType {
+	pub fn from_string(mount_type: &str) -> Self {
+		match mount_type.to_uppercase().as_str() {
+			"SYSTEM" => Self::System,
+			"EXTERNAL" => Self::External,
+			"NETWORK" => Self::Network,
+			"VIRTUAL" => Self::Virtual,
+			"DISKIMAGE" => Self::DiskImage,
+			_ => Self::System,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn mount_types_are_read_back_as_stored() {
+		for mount_type in [MountType::External, MountType::DiskImage] {
+			assert_eq!(MountType::from_string(&mount_type.to_string()), mount_type);
+		}
+	}
+
+	#[test]
+	fn fingerprints_are_sent_as_hex() {
+		let fingerprint = VolumeFingerprint(vec![0xde, 0xad, 0xbe, 0xef]);
+		let json = serde_json::to_value(&fingerprint).unwrap();
+
+		assert_eq!(json, serde_json::json!("deadbeef"));
+		assert_eq!(
+			serde_json::from_value::<VolumeFingerprint>(json).unwrap(),
+			fingerprint
+		);
+	}
+
+	#[test]
+	fn volumes_saved_before_ownership_are_accessible() {
+		let mut volume = Volume::new(
+			"USB".to_string(),
+			MountType::External,
+			PathBuf::from("/media/usb"),
+			vec![PathBuf::from("/media/usb")],
+			DiskType::SSD,
+			FileSystem::ExFAT,
+			1000,
+			400,
+			false,
+		);
+		volume.is_accessible = false;
+
+		let mut json = serde_json::to_value(&volume).unwrap();
+		let fields = json.as_object_mut().unwrap();
+		fields.remove("owner");
+		fields.remove("is_accessible");
+
+		let volume = serde_json::from_value::<Volume>(json).unwrap();
+		assert!(volume.is_accessible);
+		assert_eq!(volume.owner, None);
+	}
+
+	#[test]
+	fn serials_keep_the_fingerprint_when_the_mount_point_changes() {
+		let device_pub_id = [1; 16];
+		let mut volume = Volume::new(
+			"USB".to_string(),
+			MountType::External,
+			PathBuf::from("D:\\"),
+			vec![PathBuf::from("D:\\")],
+			DiskType::SSD,
+			FileSystem::ExFAT,
+			1000,
+			400,
+			false,
+		);
+		let former = VolumeFingerprint::new(&device_pub_id, &volume);
+		assert_eq!(
+			former,
+			VolumeFingerprint::from_properties(&device_pub_id, &volume)
+		);
+
+		volume.serial = Some("4A2C-91F0".to_string());
+		let fingerprint = VolumeFingerprint::new(&device_pub_id, &volume);
+		assert_ne!(fingerprint, former);
+
+		volume.mount_point = PathBuf::from("E:\\");
+		assert_eq!(VolumeFingerprint::new(&device_pub_id, &volume), fingerprint);
+		assert_ne!(VolumeFingerprint::new(&[2; 16], &volume), fingerprint);
+	}
+
+	#[test]
+	fn mounts_of_the_same_file_system_get_their_own_fingerprints() {
+		let device_pub_id = [1; 16];
+		let mount = |mount_point: &str, mount_root: Option<&str>| {
+			let mut volume = Volume::new(
+				"nvme0n1p2".to_string(),
+				MountType::System,
+				PathBuf::from(mount_point),
+				vec![PathBuf::from(mount_point)],
+				DiskType::SSD,
+				FileSystem::Other("btrfs".to_string()),
+				1000,
+				400,
+				false,
+			);
+			volume.serial = Some("0f1e2d3c-4b5a-6978-8796-a5b4c3d2e1f0".to_string());
+			volume.mount_root = mount_root.map(ToString::to_string);
+			VolumeFingerprint::new(&device_pub_id, &volume)
+		};
+
+		let whole = mount("/mnt/pool", Some("/"));
+		let root = mount("/", Some("/@"));
+		let home = mount("/home", Some("/@home"));
+		let snapshot = mount("/.snapshots/1/snapshot", Some("/@snapshots/1/snapshot"));
+		let bind = mount("/srv/photos", Some("/@home/user/Photos"));
+
+		let fingerprints = [&whole, &root, &home, &snapshot, &bind];
+		for (i, fingerprint) in fingerprints.iter().enumerate() {
+			for other in &fingerprints[i + 1..] {
+				assert_ne!(fingerprint, other);
+			}
+		}
+
+		// The whole file system keeps the fingerprint from before roots were read
+		assert_eq!(whole, mount("/mnt/pool", None));
+		assert_eq!(home, mount("/mnt/home", Some("/@home")));
+	}
+}
@@ -0,0 +1,18 @@
+//! Feeds arbitrary mount tables to the parser: `cargo +nightly fuzz run mountinfo` from `core`.
+//!
+//! Besides not panicking, every line that parses must give the same entry once written back.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Built on its own, the parser only depends on std and the core takes long to build for fuzzing
+#[path = "../../src/volume/mountinfo.rs"]
+#[allow(dead_code)]
+mod mountinfo;
+
+fuzz_target!(|contents: &[u8]| {
+	for entry in mountinfo::entries(contents).flatten() {
+		assert_eq!(mountinfo::parse_line(&entry.to_line()), Ok(entry));
+	}
+});
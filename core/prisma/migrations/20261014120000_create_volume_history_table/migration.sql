@@ -0,0 +1,12 @@
+-- CreateTable
+CREATE TABLE "volume_history" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "fingerprint" BLOB NOT NULL,
+    "kind" TEXT NOT NULL,
+    "recorded_at" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "total_bytes_capacity" BIGINT,
+    "total_bytes_available" BIGINT
+);
+
+-- CreateIndex
+CREATE INDEX "volume_history_fingerprint_recorded_at_idx" ON "volume_history"("fingerprint", "recorded_at");
@@ -0,0 +1,6 @@
+-- AlterTable
+ALTER TABLE "volume" ADD COLUMN "date_last_seen" DATETIME;
+ALTER TABLE "volume" ADD COLUMN "fingerprint" BLOB;
+
+-- CreateIndex
+CREATE INDEX "volume_fingerprint_idx" ON "volume"("fingerprint");
@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "volume" ADD COLUMN "serial" TEXT;
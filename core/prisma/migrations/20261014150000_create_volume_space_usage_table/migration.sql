@@ -0,0 +1,13 @@
+-- CreateTable
+CREATE TABLE "volume_space_usage" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "fingerprint" BLOB NOT NULL,
+    "directory" TEXT NOT NULL,
+    "category" TEXT NOT NULL,
+    "total_bytes" BIGINT NOT NULL,
+    "files_count" BIGINT NOT NULL,
+    "computed_at" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "volume_space_usage_fingerprint_directory_category_key" ON "volume_space_usage"("fingerprint", "directory", "category");
@@ -0,0 +1,15 @@
+-- CreateTable
+CREATE TABLE "sync_rule" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "path" TEXT NOT NULL,
+    "mode" INTEGER NOT NULL,
+    "date_created" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "location_id" INTEGER NOT NULL,
+    CONSTRAINT "sync_rule_location_id_fkey" FOREIGN KEY ("location_id") REFERENCES "location" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "sync_rule_location_id_path_key" ON "sync_rule"("location_id", "path");
+
+-- AlterTable
+ALTER TABLE "file_path" ADD COLUMN "sync_mode" INTEGER;
@@ -0,0 +1,16 @@
+-- CreateTable
+CREATE TABLE "sync_conflict" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "model" INTEGER NOT NULL,
+    "record_id" BLOB NOT NULL,
+    "field" TEXT NOT NULL,
+    "mine" BLOB NOT NULL,
+    "mine_timestamp" BIGINT NOT NULL,
+    "theirs" BLOB NOT NULL,
+    "theirs_timestamp" BIGINT NOT NULL,
+    "theirs_device_pub_id" BLOB NOT NULL,
+    "date_created" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "sync_conflict_model_record_id_field_key" ON "sync_conflict"("model", "record_id", "field");
@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "device" ADD COLUMN "date_revoked" DATETIME;
@@ -0,0 +1,18 @@
+-- CreateTable
+CREATE TABLE "sync_outbox" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "group_pub_id" BLOB NOT NULL,
+    "key_hash" TEXT NOT NULL,
+    "operations_count" INTEGER NOT NULL,
+    "start_time" DATETIME NOT NULL,
+    "end_time" DATETIME NOT NULL,
+    "last_timestamp" BIGINT NOT NULL,
+    "encrypted_messages" BLOB NOT NULL,
+    "attempts" INTEGER NOT NULL DEFAULT 0,
+    "next_attempt_at" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "last_error" TEXT,
+    "date_created" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- CreateIndex
+CREATE INDEX "sync_outbox_group_pub_id_idx" ON "sync_outbox"("group_pub_id");
@@ -0,0 +1,8 @@
+-- CreateTable
+CREATE TABLE "quarantined_record" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "model" TEXT NOT NULL,
+    "record" BLOB NOT NULL,
+    "reason" TEXT NOT NULL,
+    "date_created" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
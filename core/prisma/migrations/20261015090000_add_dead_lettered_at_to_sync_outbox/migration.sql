@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "sync_outbox" ADD COLUMN "dead_lettered_at" DATETIME;
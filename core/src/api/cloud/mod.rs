@@ -23,13 +23,14 @@ use futures::{FutureExt, StreamExt};
 use futures_concurrency::future::TryJoin;
 use rspc::alpha::AlphaRouter;
 use tracing::{debug, error, instrument};
+use uuid::Uuid;
 
 use super::{utils::library, Ctx, R};
 
 mod devices;
 mod libraries;
 mod locations;
-mod sync_groups;
+pub(super) mod sync_groups;
 mod thumbnails;
 
 async fn try_get_cloud_services_client(
@@ -372,9 +373,52 @@ async fn initialize_cloud_sync(
 		.await
 		.ok_or(LibraryManagerError::LibraryNotFound)?;
 
+	// Opened from a backup or an archive, it's left out of the group it came from
+	if library.is_read_only().await {
+		debug!("Skipping cloud sync of a read-only library");
+		return Ok(());
+	}
+
 	library.init_cloud_sync(node, group_pub_id).await
 }
 
+/// Starts the cloud sync of a library left out when the cloud services bootstrapped, as it was
+/// read-only then, if it's in a sync group
+pub(crate) async fn resume_cloud_sync(node: &Node, library_id: Uuid) -> Result<(), rspc::Error> {
+	// Otherwise the bootstrap starts it along with the other libraries
+	if !node
+		.cloud_services
+		.has_bootstrapped
+		.try_lock()
+		.map(|lock| *lock)
+		.unwrap_or(false)
+	{
+		return Ok(());
+	}
+
+	let (client, access_token) = get_client_and_access_token(node).await?;
+
+	let groups::list::Response(groups) = handle_comm_error(
+		client
+			.sync()
+			.groups()
+			.list(groups::list::Request { access_token })
+			.await,
+		"Failed to list sync groups",
+	)??;
+
+	let Some(group) = groups
+		.into_iter()
+		.find(|group| group.library.pub_id.0 == library_id)
+	else {
+		return Ok(());
+	};
+
+	initialize_cloud_sync(group.pub_id, group.library, node)
+		.await
+		.map_err(Into::into)
+}
+
 async fn get_client_and_access_token(
 	node: &Node,
 ) -> Result<(Client<QuinnConnector<Response, Request>>, auth::AccessToken), rspc::Error> {
@@ -238,46 +238,7 @@ pub fn mount() -> AlphaRouter<Ctx> {
 				     group_pub_id,
 				     to_remove_device_pub_id,
 				 }: CloudSyncGroupsRemoveDeviceArgs| async move {
-					use groups::remove_device::Request;
-
-					let ((client, access_token), current_device_pub_id, mut rng, key_manager) = (
-						super::get_client_and_access_token(&node),
-						node.config.get().map(|config| Ok(config.id.into())),
-						node.master_rng
-							.lock()
-							.map(|mut rng| Ok(CryptoRng::from_seed(rng.generate_fixed()))),
-						node.cloud_services
-							.key_manager()
-							.map(|res| res.map_err(Into::into)),
-					)
-						.try_join()
-						.await?;
-
-					let new_key = SecretKey::generate(&mut rng);
-					let new_key_hash = KeyHash(blake3::hash(new_key.as_ref()).to_hex().to_string());
-
-					key_manager
-						.add_key_with_hash(group_pub_id, new_key, new_key_hash.clone(), &mut rng)
-						.await?;
-
-					super::handle_comm_error(
-						client
-							.sync()
-							.groups()
-							.remove_device(Request {
-								access_token,
-								group_pub_id,
-								new_key_hash,
-								current_device_pub_id,
-								to_remove_device_pub_id,
-							})
-							.await,
-						"Failed to remove device from sync group;",
-					)??;
-
-					debug!(%to_remove_device_pub_id, %group_pub_id, "Removed device");
-
-					Ok(())
+					remove_device(&node, group_pub_id, to_remove_device_pub_id).await
 				},
 			)
 		})
@@ -402,3 +363,52 @@ impl JoinedSyncGroupReceiver {
 		});
 	}
 }
+
+/// Removes a device from a sync group, rotating the key of the group so it can't decrypt the
+/// messages sent from then on
+pub(crate) async fn remove_device(
+	node: &Node,
+	group_pub_id: groups::PubId,
+	to_remove_device_pub_id: devices::PubId,
+) -> Result<(), rspc::Error> {
+	use groups::remove_device::Request;
+
+	let ((client, access_token), current_device_pub_id, mut rng, key_manager) = (
+		super::get_client_and_access_token(node),
+		node.config.get().map(|config| Ok(config.id.into())),
+		node.master_rng
+			.lock()
+			.map(|mut rng| Ok(CryptoRng::from_seed(rng.generate_fixed()))),
+		node.cloud_services
+			.key_manager()
+			.map(|res| res.map_err(Into::into)),
+	)
+		.try_join()
+		.await?;
+
+	let new_key = SecretKey::generate(&mut rng);
+	let new_key_hash = KeyHash(blake3::hash(new_key.as_ref()).to_hex().to_string());
+
+	key_manager
+		.add_key_with_hash(group_pub_id, new_key, new_key_hash.clone(), &mut rng)
+		.await?;
+
+	super::handle_comm_error(
+		client
+			.sync()
+			.groups()
+			.remove_device(Request {
+				access_token,
+				group_pub_id,
+				new_key_hash,
+				current_device_pub_id,
+				to_remove_device_pub_id,
+			})
+			.await,
+		"Failed to remove device from sync group;",
+	)??;
+
+	debug!(%to_remove_device_pub_id, %group_pub_id, "Removed device");
+
+	Ok(())
+}
@@ -1,12 +1,13 @@
-use crate::node::HardwareModel;
+use crate::{invalidate_query, node::HardwareModel};
 use rspc::alpha::AlphaRouter;
-use sd_cloud_schema::devices::DeviceOS;
+use sd_cloud_schema::{devices::DeviceOS, sync::groups};
 use sd_core_prisma_helpers::DevicePubId;
+use sd_core_sync::revocation;
 use sd_prisma::prisma::device;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use specta::Type;
 
-use super::{utils::library, Ctx, R};
+use super::{cloud::sync_groups, utils::library, Ctx, R};
 
 #[derive(Type, Serialize, Clone, Debug)]
 pub struct Device {
@@ -16,10 +17,19 @@ pub struct Device {
 	pub os: DeviceOS,
 	pub hardware_model: HardwareModel,
 	pub date_created: chrono::DateTime<chrono::FixedOffset>,
+	/// Its operations are rejected since then
+	pub date_revoked: Option<chrono::DateTime<chrono::FixedOffset>>,
 
 	pub is_current_device: bool,
 }
 
+#[derive(Type, Deserialize)]
+pub struct RevokeDeviceArgs {
+	pub pub_id: DevicePubId,
+	/// The cloud sync group of the library, the device is removed from it and its key rotated
+	pub sync_group_pub_id: Option<groups::PubId>,
+}
+
 impl From<(device::Data, &DevicePubId)> for Device {
 	fn from((d, current_device_pub_id): (device::Data, &DevicePubId)) -> Self {
 		let pub_id = DevicePubId::from(d.pub_id);
@@ -40,25 +50,47 @@ impl From<(device::Data, &DevicePubId)> for Device {
 				.try_into()
 				.expect("is not actually optional"),
 			date_created: d.date_created.expect("is not actually optional"),
+			date_revoked: d.date_revoked,
 		}
 	}
 }
 
 pub(crate) fn mount() -> AlphaRouter<Ctx> {
-	R.router().procedure(
-		"list",
-		R.with2(library())
-			.query(|(node, library), _: ()| async move {
-				let current_device_pub_id = node.config.get().await.id;
-				Ok(library
-					.db
-					.device()
-					.find_many(vec![])
-					.exec()
-					.await?
-					.into_iter()
-					.map(|d| Device::from((d, &current_device_pub_id)))
-					.collect::<Vec<_>>())
-			}),
-	)
+	R.router()
+		.procedure(
+			"list",
+			R.with2(library())
+				.query(|(node, library), _: ()| async move {
+					let current_device_pub_id = node.config.get().await.id;
+					Ok(library
+						.db
+						.device()
+						.find_many(vec![])
+						.exec()
+						.await?
+						.into_iter()
+						.map(|d| Device::from((d, &current_device_pub_id)))
+						.collect::<Vec<_>>())
+				}),
+		)
+		.procedure(
+			"revoke",
+			R.with2(library()).mutation(
+				|(node, library),
+				 RevokeDeviceArgs {
+				     pub_id,
+				     sync_group_pub_id,
+				 }: RevokeDeviceArgs| async move {
+					revocation::revoke(&library.sync, &pub_id).await?;
+
+					if let Some(group_pub_id) = sync_group_pub_id {
+						sync_groups::remove_device(&node, group_pub_id, pub_id.into()).await?;
+					}
+
+					invalidate_query!(library, "devices.list");
+
+					Ok(())
+				},
+			),
+		)
 }
@@ -12,6 +12,7 @@ use crate::{
 		// media::{exif_media_data_from_prisma_data, ffmpeg_data_from_prisma_data},
 	},
 	old_job::OldJob,
+	volume::settings::ensure_writable,
 };
 
 use sd_core_file_path_helper::{FilePathError, IsolatedFilePathData};
@@ -271,12 +272,14 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				pub name: Option<String>,
 			}
 			R.with2(library()).mutation(
-				|(_, library),
+				|(node, library),
 				 CreateFolderArgs {
 				     location_id,
 				     sub_path,
 				     name,
 				 }: CreateFolderArgs| async move {
+					ensure_writable(&node, &library, vec![location_id]).await?;
+
 					let mut path =
 						get_location_path_from_location_id(&library.db, location_id).await?;
 
@@ -302,13 +305,15 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				pub context: FileCreateContextTypes,
 			}
 			R.with2(library()).mutation(
-				|(_, library),
+				|(node, library),
 				 CreateFileArgs {
 				     location_id,
 				     sub_path,
 				     context,
 				     name,
 				 }: CreateFileArgs| async move {
+					ensure_writable(&node, &library, vec![location_id]).await?;
+
 					let mut path =
 						get_location_path_from_location_id(&library.db, location_id).await?;
 
@@ -445,7 +450,10 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				.mutation(|(node, library), args: OldFileDeleterJobInit| async move {
 					match args.file_path_ids.len() {
 						0 => Ok(()),
+						// Skips the job, so it's checked the way the job would be
 						1 => {
+							ensure_writable(&node, &library, vec![args.location_id]).await?;
+
 							let (maybe_location, maybe_file_path) = library
 								.db
 								._batch((
@@ -545,7 +553,10 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 
 					match args.file_path_ids.len() {
 						0 => Ok(()),
+						// Skips the job, so it's checked the way the job would be
 						1 => {
+							ensure_writable(&node, &library, vec![args.location_id]).await?;
+
 							let (maybe_location, maybe_file_path) = library
 								.db
 								._batch((
@@ -608,9 +619,11 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				quality_percentage: Option<i32>, // 1% - 125%
 			}
 			R.with2(library())
-				.mutation(|(_, library), args: ConvertImageArgs| async move {
+				.mutation(|(node, library), args: ConvertImageArgs| async move {
 					// TODO:(fogodev) I think this will have to be a Job due to possibly being too much CPU Bound for rspc
 
+					ensure_writable(&node, &library, vec![args.location_id]).await?;
+
 					let location_path =
 						get_location_path_from_location_id(&library.db, args.location_id).await?;
 
@@ -946,7 +959,9 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 			}
 
 			R.with2(library()).mutation(
-				|(_, library), RenameFileArgs { location_id, kind }: RenameFileArgs| async move {
+				|(node, library), RenameFileArgs { location_id, kind }: RenameFileArgs| async move {
+					ensure_writable(&node, &library, vec![location_id]).await?;
+
 					let location_path =
 						get_location_path_from_location_id(&library.db, location_id).await?;
 
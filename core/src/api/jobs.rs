@@ -4,11 +4,12 @@ use crate::{
 	location::{find_location, LocationError},
 	object::validation::old_validator_job::OldObjectValidatorJobInit,
 	old_job::{JobStatus, OldJob, OldJobReport},
+	volume,
 };
 
 use sd_core_heavy_lifting::{
 	file_identifier::FileIdentifier, job_system::report, media_processor::job::MediaProcessor,
-	JobId, JobSystemError, Report,
+	JobId, JobName, JobOutput, JobSystemError, Report,
 };
 
 use sd_prisma::prisma::{job, location, SortOrder};
@@ -33,6 +34,38 @@ use super::{utils::library, CoreEvent, Ctx, R};
 
 const TEN_MINUTES: Duration = Duration::from_secs(60 * 10);
 
+/// Emitted on the event bus when a job from the job system stops running, successfully or not
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct JobFinishedEvent {
+	pub id: JobId,
+	/// `None` if the job failed before producing an output
+	pub name: Option<JobName>,
+	pub status: report::Status,
+	pub non_critical_errors_count: usize,
+	pub error: Option<String>,
+}
+
+impl JobFinishedEvent {
+	pub(crate) fn new(id: JobId, res: Result<JobOutput, sd_core_heavy_lifting::Error>) -> Self {
+		match res {
+			Ok(output) => Self {
+				id,
+				name: Some(output.job_name()),
+				status: output.status(),
+				non_critical_errors_count: output.non_critical_errors().len(),
+				error: None,
+			},
+			Err(e) => Self {
+				id,
+				name: None,
+				status: report::Status::Failed,
+				non_critical_errors_count: 0,
+				error: Some(e.to_string()),
+			},
+		}
+	}
+}
+
 pub(crate) fn mount() -> AlphaRouter<Ctx> {
 	R.router()
 		.procedure("progress", {
@@ -317,6 +350,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					let Some(location) = find_location(&library, id).exec().await? else {
 						return Err(LocationError::IdNotFound(id).into());
 					};
+					volume::quarantine::ensure_healthy(&node, &library, vec![id]).await?;
 
 					node.job_system
 						.dispatch(
@@ -396,6 +430,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					let Some(location) = find_location(&library, id).exec().await? else {
 						return Err(LocationError::IdNotFound(id).into());
 					};
+					volume::quarantine::ensure_healthy(&node, &library, vec![id]).await?;
 
 					node.job_system
 						.dispatch(
@@ -1,14 +1,17 @@
 use super::utils::library;
 use super::{Ctx, SanitizedNodeConfig, R};
+use crate::node::{client_key, CLOUD_TOKENS_KEY};
 use once_cell::sync::Lazy;
 use rspc::{alpha::AlphaRouter, ErrorCode};
 use sd_crypto::cookie::CookieCipher;
+use serde::Deserialize;
 use serde_json::{json, Map, Value};
+use specta::Type;
 use std::path::Path;
 use std::sync::Arc;
 use tokio::io::AsyncWriteExt;
 use tokio::sync::RwLock;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
 static CACHE: Lazy<RwLock<Option<String>>> = Lazy::new(|| RwLock::new(None));
 
@@ -129,12 +132,23 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 			R.query(move |node, _: ()| {
 				let cipher_cache = cipher_cache.clone();
 				async move {
-					let cache_guard = CACHE.read().await;
-					if let Some(cached_data) = cache_guard.clone().as_ref() {
+					if let Some(cached_data) = CACHE.read().await.clone() {
 						debug!("Returning cached data");
-						return Ok(cached_data.clone());
+						return Ok(cached_data);
 					}
 
+					let keyring_available = match node.secrets.get(CLOUD_TOKENS_KEY).await {
+						Ok(Some(tokens)) => {
+							*CACHE.write().await = Some(tokens.clone());
+							return Ok(tokens);
+						}
+						Ok(None) => true,
+						Err(e) => {
+							warn!(?e, "Failed to read cloud tokens from the credential store;");
+							false
+						}
+					};
+
 					let base_dir = node.config.data_directory();
 					// Remove /dev from Path
 					let base_dir = if base_dir.ends_with("dev") {
@@ -175,6 +189,15 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 							"Failed to convert data to string".to_string(),
 						)
 					})?;
+
+					// Move tokens saved by older versions into the credential store
+					if keyring_available {
+						match node.secrets.set(CLOUD_TOKENS_KEY, &de_data).await {
+							Ok(()) => write_file(&path, b"").await?,
+							Err(e) => warn!(?e, "Failed to migrate cloud tokens to the credential store;"),
+						}
+					}
+
 					Ok(de_data)
 				}
 			})
@@ -188,6 +211,19 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					// let path = sanitize_path(&base_dir, Path::new(".sdks"))?;
 					let path = base_dir.join(".sdks");
 
+					match node.secrets.set(CLOUD_TOKENS_KEY, &args).await {
+						Ok(()) => {
+							// Don't leave a copy of the tokens behind in the file
+							write_file(&path, b"").await?;
+							*CACHE.write().await = Some(args);
+							debug!("Saved data to the credential store");
+							return Ok(());
+						}
+						Err(e) => {
+							warn!(?e, "Failed to save cloud tokens to the credential store, falling back to file;");
+						}
+					}
+
 					// Read and decrypt existing data if it exists
 					let existing_decrypted = if let Ok(existing_data) = read_file(&path).await {
 						let cipher = get_cipher(&node, cipher_cache.clone()).await?;
@@ -243,6 +279,32 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				}
 			})
 		})
+		// Secrets are never read back by the frontend, and only the network share credentials
+		// can be changed by it
+		.procedure("setSecret", {
+			#[derive(Deserialize, Type)]
+			pub struct SetSecretArgs {
+				pub key: String,
+				pub secret: String,
+			}
+
+			R.mutation(
+				|node, SetSecretArgs { key, secret }: SetSecretArgs| async move {
+					node.secrets
+						.set(client_key(&key)?, &secret)
+						.await
+						.map_err(Into::into)
+				},
+			)
+		})
+		.procedure("deleteSecret", {
+			R.mutation(|node, key: String| async move {
+				node.secrets
+					.delete(client_key(&key)?)
+					.await
+					.map_err(Into::into)
+			})
+		})
 		.procedure("saveEmailAddress", {
 			R.with2(library())
 				.mutation(move |(node, library), args: String| async move {
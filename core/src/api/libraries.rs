@@ -1,7 +1,10 @@
 use crate::{
 	api::CoreEvent,
 	invalidate_query,
-	library::{update_library_statistics, Library, LibraryConfig, LibraryName},
+	library::{
+		aggregator::LibraryBreakdown, archive, update_library_statistics, verify, Library,
+		LibraryConfig, LibraryName,
+	},
 	location::{scan_location, LocationCreateArgs, ScanState},
 	util::MaybeUndefined,
 	Node,
@@ -17,6 +20,7 @@ use sd_utils::{db::size_in_bytes_from_db, u64_to_frontend};
 use std::{
 	collections::{hash_map::Entry, HashMap},
 	convert::identity,
+	path::PathBuf,
 	pin::pin,
 	sync::{
 		atomic::{AtomicBool, Ordering},
@@ -46,7 +50,10 @@ use tokio_stream::wrappers::IntervalStream;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
-use super::{utils::library, Ctx, R};
+use super::{
+	utils::{library, read_only_library},
+	Ctx, R,
+};
 
 const ONE_MINUTE: Duration = Duration::from_secs(60);
 const TWO_MINUTES: Duration = Duration::from_secs(60 * 2);
@@ -126,9 +133,11 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 			})
 		})
 		.procedure("statistics", {
-			#[derive(Serialize, Deserialize, Type)]
+			#[derive(Serialize, Type)]
 			pub struct StatisticsResponse {
 				statistics: Option<statistics::Data>,
+				/// From the aggregator of the library, `null` until its first pass is done
+				breakdown: Option<LibraryBreakdown>,
 			}
 			R.with2(library())
 				.query(|(node, library), _: ()| async move {
@@ -140,6 +149,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						.exec()
 						.await?;
 					debug!(?statistics, "Got statistics for library");
+					let breakdown = library.aggregator.latest();
 
 					match STATISTICS_UPDATERS.lock().await.entry(library.id) {
 						Entry::Occupied(entry) => {
@@ -157,7 +167,10 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						}
 					}
 
-					Ok(StatisticsResponse { statistics })
+					Ok(StatisticsResponse {
+						statistics,
+						breakdown,
+					})
 				})
 		})
 		.procedure("kindStatistics", {
@@ -459,6 +472,12 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				     name,
 				     description,
 				 }: EditLibraryArgs| async move {
+					if let Some(library) = node.libraries.get_library(&id).await {
+						if library.is_read_only().await {
+							return Err(read_only_library());
+						}
+					}
+
 					Ok(node
 						.libraries
 						.edit(id, name, description, MaybeUndefined::Undefined, None)
@@ -472,6 +491,16 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				node.libraries.delete(&id).await.map_err(Into::into)
 			}),
 		)
+		// Without the library middleware, that rejects the mutations of read-only libraries
+		.procedure(
+			"makeWritable",
+			R.mutation(|node, id: Uuid| async move {
+				node.libraries
+					.make_writable(&id, &node)
+					.await
+					.map_err(Into::into)
+			}),
+		)
 		.procedure(
 			"actors",
 			R.with2(library()).subscription(|(_, library), _: ()| {
@@ -488,6 +517,55 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				}
 			}),
 		)
+		.procedure("export", {
+			#[derive(Type, Deserialize)]
+			pub struct ExportLibraryArgs {
+				/// Where the archive is written, a `.sdlibrary` file
+				pub path: PathBuf,
+			}
+
+			R.with2(library())
+				.mutation(|(node, library), ExportLibraryArgs { path }| async move {
+					Ok(archive::export(&node, &library, path).await?)
+				})
+		})
+		.procedure("import", {
+			#[derive(Type, Deserialize)]
+			pub struct ImportLibraryArgs {
+				pub path: PathBuf,
+				/// Opens it as a guest library, to be looked through without changing it
+				#[serde(default)]
+				pub read_only: bool,
+			}
+
+			R.mutation(|node, ImportLibraryArgs { path, read_only }| async move {
+				let library = archive::import(&node, path, read_only).await?;
+
+				invalidate_query!(library, "library.list");
+
+				Ok(LibraryConfigWrapped::from_library(&library).await)
+			})
+		})
+		.procedure("verify", {
+			#[derive(Type, Deserialize)]
+			pub struct VerifyArgs {
+				/// Repairs what's found instead of only reporting it
+				pub repair: bool,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), VerifyArgs { repair }| async move {
+					let report = verify::verify(&library, repair).await?;
+
+					if report.repaired {
+						invalidate_query!(library, "search.paths");
+						invalidate_query!(library, "search.objects");
+						library.aggregator.request();
+					}
+
+					Ok(report)
+				})
+		})
 		.procedure(
 			"vacuumDb",
 			R.with2(library())
@@ -14,8 +14,11 @@ use sd_core_indexer_rules::IndexerRuleCreateArgs;
 use sd_core_prisma_helpers::{
 	file_path_for_frontend, label_with_objects, location_with_indexer_rules, object_with_file_paths,
 };
+use sd_core_sync::selective::{self, SyncMode, SyncRule};
 
-use sd_prisma::prisma::{file_path, indexer_rule, indexer_rules_in_location, location, SortOrder};
+use sd_prisma::prisma::{
+	file_path, indexer_rule, indexer_rules_in_location, location, sync_rule, SortOrder,
+};
 
 use std::path::{Path, PathBuf};
 
@@ -180,6 +183,14 @@ impl ExplorerItem {
 	}
 }
 
+#[derive(Type, Deserialize)]
+pub struct SyncRuleSetArgs {
+	pub location_id: location::id::Type,
+	/// The directory relative to the location, like `/Photos/Raw/`
+	pub path: String,
+	pub mode: SyncMode,
+}
+
 pub(crate) fn mount() -> AlphaRouter<Ctx> {
 	R.router()
 		.procedure("list", {
@@ -465,6 +476,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 			})
 		})
 		.merge("indexer_rules.", mount_indexer_rule_routes())
+		.merge("sync_rules.", mount_sync_rule_routes())
 }
 
 fn mount_indexer_rule_routes() -> AlphaRouter<Ctx> {
@@ -559,3 +571,104 @@ fn mount_indexer_rule_routes() -> AlphaRouter<Ctx> {
 				})
 		})
 }
+
+fn mount_sync_rule_routes() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.with2(library())
+				.query(|(_, library), location_id: location::id::Type| async move {
+					Ok(library
+						.db
+						.sync_rule()
+						.find_many(vec![sync_rule::location_id::equals(location_id)])
+						.order_by(sync_rule::path::order(SortOrder::Asc))
+						.exec()
+						.await?
+						.into_iter()
+						.map(SyncRule::from)
+						.collect::<Vec<_>>())
+				})
+		})
+		// Adds the rule of a directory or changes its mode, then tags its file paths again
+		.procedure("set", {
+			R.with2(library()).mutation(
+				|(_, library),
+				 SyncRuleSetArgs {
+				     location_id,
+				     path,
+				     mode,
+				 }| async move {
+					if !selective::is_local_location(&library.sync, location_id).await? {
+						return Err(rspc::Error::new(
+							ErrorCode::Forbidden,
+							format!("Location <id={location_id}> is another device's"),
+						));
+					}
+
+					let path = selective::normalize_path(&path);
+
+					library
+						.db
+						.sync_rule()
+						.upsert(
+							sync_rule::location_id_path(location_id, path.clone()),
+							sync_rule::create(
+								path,
+								mode as i32,
+								location::id::equals(location_id),
+								vec![],
+							),
+							vec![sync_rule::mode::set(mode as i32)],
+						)
+						.exec()
+						.await?;
+
+					selective::retag(&library.sync, location_id).await?;
+
+					invalidate_query!(library, "locations.sync_rules.list");
+					invalidate_query!(library, "search.paths");
+
+					Ok(())
+				},
+			)
+		})
+		.procedure("delete", {
+			R.with2(library()).mutation(
+				|(_, library), sync_rule_id: sync_rule::id::Type| async move {
+					let Some(rule) = library
+						.db
+						.sync_rule()
+						.find_unique(sync_rule::id::equals(sync_rule_id))
+						.exec()
+						.await?
+					else {
+						return Err(rspc::Error::new(
+							ErrorCode::NotFound,
+							format!("Sync rule <id={sync_rule_id}> not found"),
+						));
+					};
+
+					if !selective::is_local_location(&library.sync, rule.location_id).await? {
+						return Err(rspc::Error::new(
+							ErrorCode::Forbidden,
+							format!("Location <id={}> is another device's", rule.location_id),
+						));
+					}
+
+					library
+						.db
+						.sync_rule()
+						.delete(sync_rule::id::equals(sync_rule_id))
+						.exec()
+						.await?;
+
+					selective::retag(&library.sync, rule.location_id).await?;
+
+					invalidate_query!(library, "locations.sync_rules.list");
+					invalidate_query!(library, "search.paths");
+
+					Ok(())
+				},
+			)
+		})
+}
@@ -0,0 +1,48 @@
+pub use crate::node::{LogFilters, LogLevel, Logs, LogsError, MAX_CAPTURE_DURATION};
+
+use std::time::Duration;
+
+use rspc::alpha::AlphaRouter;
+use serde::Deserialize;
+use specta::Type;
+
+use super::{Ctx, R};
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("filters", {
+			R.query(|_, _: ()| async move { Logs::get().map(Logs::filters).map_err(Into::into) })
+		})
+		.procedure("setLevel", {
+			#[derive(Deserialize, Type)]
+			pub struct SetLogLevelArgs {
+				/// A tracing target, e.g. `sd_core::volume`
+				pub module: String,
+				/// `null` goes back to the level the app started with
+				pub level: Option<LogLevel>,
+			}
+
+			R.mutation(|_, SetLogLevelArgs { module, level }| async move {
+				Logs::get()
+					.and_then(|logs| logs.set_level(&module, level))
+					.map_err(Into::into)
+			})
+		})
+		.procedure("capture", {
+			#[derive(Deserialize, Type)]
+			pub struct CaptureLogsArgs {
+				pub module: String,
+				pub seconds: u32,
+			}
+
+			R.mutation(|_, CaptureLogsArgs { module, seconds }| async move {
+				match Logs::get() {
+					Ok(logs) => logs
+						.capture(&module, Duration::from_secs(seconds.into()))
+						.await
+						.map_err(Into::into),
+					Err(e) => Err(e.into()),
+				}
+			})
+		})
+}
@@ -2,6 +2,7 @@ use crate::{
 	invalidate_query,
 	library::LibraryId,
 	node::{
+		self,
 		config::{is_in_docker, NodeConfig, NodeConfigP2P, NodePreferences},
 		HardwareModel,
 	},
@@ -25,7 +26,7 @@ use specta::Type;
 use tracing::warn;
 
 mod backups;
-mod cloud;
+pub(crate) mod cloud;
 mod devices;
 mod ephemeral_files;
 mod files;
@@ -34,6 +35,7 @@ mod keys;
 mod labels;
 mod libraries;
 pub mod locations;
+pub mod logs;
 mod models;
 mod nodes;
 pub mod notifications;
@@ -46,6 +48,10 @@ pub mod utils;
 pub mod volumes;
 mod web_api;
 
+pub use crate::node::{
+	CoreHealth, JobsHealth, Severity, SyncHealth, VolumesHealth, WatcherHealth,
+};
+pub use jobs::JobFinishedEvent;
 use libraries::KindStatistic;
 use utils::{InvalidRequests, InvalidateOperationEvent};
 
@@ -66,25 +72,49 @@ pub enum CoreEvent {
 	},
 	UpdatedKindStatistic(KindStatistic, LibraryId),
 	JobProgress(JobProgressEvent),
+	JobFinished(JobFinishedEvent),
 	InvalidateOperation(InvalidateOperationEvent),
+	OfflineModeChanged {
+		offline: bool,
+	},
 }
 
 /// All of the feature flags provided by the core itself. The frontend has it's own set of feature flags!
 ///
 /// If you want a variant of this to show up on the frontend it must be added to `backendFeatures` in `useFeatureFlag.tsx`
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Type)]
+///
+/// Subsystems check them with [`Node::feature_flags`](crate::Node::feature_flags).
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize, Type)]
 #[serde(rename_all = "camelCase")]
-pub enum BackendFeature {}
+pub enum BackendFeature {
+	/// Start tracking volumes in the library as soon as they are plugged in
+	AutoTracking,
+	/// Poll S.M.A.R.T. data of the drives backing the volumes
+	SmartMonitoring,
+	/// Browse the volumes of other devices over p2p
+	RemoteVolumeBrowsing,
+}
+
+impl BackendFeature {
+	pub const ALL: [Self; 3] = [
+		Self::AutoTracking,
+		Self::SmartMonitoring,
+		Self::RemoteVolumeBrowsing,
+	];
+
+	/// The name used by the frontend and in `SD_FEATURES`
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::AutoTracking => "autoTracking",
+			Self::SmartMonitoring => "smartMonitoring",
+			Self::RemoteVolumeBrowsing => "remoteVolumeBrowsing",
+		}
+	}
 
-// impl BackendFeature {
-// 	pub fn restore(&self, node: &Node) {
-// 		match self {
-// 			BackendFeature::CloudSync => {
-// 				node.cloud_sync_flag.store(true, Ordering::Relaxed);
-// 			}
-// 		}
-// 	}
-// }
+	pub fn from_name(name: &str) -> Option<Self> {
+		Self::ALL.into_iter().find(|feature| feature.name() == name)
+	}
+}
 
 /// A version of [`NodeConfig`] that is safe to share with the frontend
 #[derive(Debug, Serialize, Deserialize, Clone, Type)]
@@ -144,7 +174,9 @@ pub(crate) fn mount() -> Arc<Router> {
 		})
 		.procedure("nodeState", {
 			R.query(|node, _: ()| async move {
-				let config = SanitizedNodeConfig::from(node.config.get().await);
+				let mut config = SanitizedNodeConfig::from(node.config.get().await);
+				// What's in effect, `SD_FEATURES` overrides included
+				config.features = node.feature_flags.enabled();
 
 				Ok(NodeState {
 					device_model: Some(config.hardware_model.to_string()),
@@ -160,6 +192,16 @@ pub(crate) fn mount() -> Arc<Router> {
 				})
 			})
 		})
+		.procedure("coreHealth", {
+			R.query(|node, _: ()| async move { Ok(node::core_health(&node).await) })
+		})
+		// Lets the frontend hide the volume actions this platform doesn't support
+		.procedure("deviceCapabilities", {
+			R.query(|node, _: ()| async move { Ok(node.volumes.capabilities()) })
+		})
+		.procedure("featureFlags", {
+			R.query(|node, _: ()| async move { Ok(node.feature_flags.list()) })
+		})
 		.procedure("toggleFeatureFlag", {
 			R.mutation(|node, feature: BackendFeature| async move {
 				let config = node.config.get().await;
@@ -181,15 +223,11 @@ pub(crate) fn mount() -> Arc<Router> {
 				}
 				.map_err(|e| rspc::Error::new(ErrorCode::InternalServerError, e.to_string()))?;
 
+				node.feature_flags.set(feature, enabled);
 				warn!("Feature {:?} is now {}", feature, enabled);
 
-				// match feature {
-				// 	BackendFeature::CloudSync => {
-				// 		node.cloud_sync_flag.store(enabled, Ordering::Relaxed);
-				// 	}
-				// }
-
 				invalidate_query!(node; node, "nodeState");
+				invalidate_query!(node; node, "featureFlags");
 
 				Ok(())
 			})
@@ -203,6 +241,7 @@ pub(crate) fn mount() -> Arc<Router> {
 		.merge("tags.", tags::mount())
 		.merge("labels.", labels::mount())
 		.merge("locations.", locations::mount())
+		.merge("logs.", logs::mount())
 		.merge("ephemeralFiles.", ephemeral_files::mount())
 		.merge("files.", files::mount())
 		.merge("jobs.", jobs::mount())
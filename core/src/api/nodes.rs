@@ -109,6 +109,55 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						.collect::<Vec<_>>())
 				})
 		})
+		.procedure("setPreventSleepDuringJobs", {
+			R.mutation(|node, enabled: bool| async move {
+				node.config
+					.update_preferences(|preferences| {
+						preferences.prevent_sleep_during_jobs = enabled;
+					})
+					.await
+					.map_err(|e| {
+						error!(?e, "Failed to update sleep prevention preference;");
+						rspc::Error::with_cause(
+							ErrorCode::InternalServerError,
+							"Failed to update sleep prevention preference".to_string(),
+							e,
+						)
+					})?;
+
+				invalidate_query!(node; node, "nodeState");
+
+				Ok(())
+			})
+		})
+		.procedure("setOfflineMode", {
+			R.mutation(|node, offline: bool| async move {
+				node.set_offline(offline).await.map_err(|e| {
+					error!(?e, "Failed to update offline mode;");
+					rspc::Error::with_cause(
+						ErrorCode::InternalServerError,
+						"Failed to update offline mode".to_string(),
+						e,
+					)
+				})
+			})
+		})
+		.procedure("setTelemetry", {
+			R.mutation(|node, enabled: bool| async move {
+				node.set_telemetry(enabled).await.map_err(|e| {
+					error!(?e, "Failed to update telemetry preference;");
+					rspc::Error::with_cause(
+						ErrorCode::InternalServerError,
+						"Failed to update telemetry preference".to_string(),
+						e,
+					)
+				})?;
+
+				invalidate_query!(node; node, "nodeState");
+
+				Ok(())
+			})
+		})
 		.procedure("updateThumbnailerPreferences", {
 			#[derive(Deserialize, Type)]
 			pub struct UpdateThumbnailerPreferences {
@@ -1,15 +1,17 @@
 //! DEPRICATED FOR NEW SYSTEM. DO NOT USE THIS API
-use crate::old_p2p::{
-	operations, ConnectionMethod, DiscoveryMethod, Header, P2PEvent, PeerMetadata,
+use crate::{
+	invalidate_query,
+	old_p2p::{operations, ConnectionMethod, DiscoveryMethod, Header, P2PEvent, PeerMetadata},
 };
 
-use sd_old_p2p::{PeerConnectionCandidate, RemoteIdentity};
+use sd_old_p2p::{BandwidthLimit, PeerConnectionCandidate, RemoteIdentity};
 
 use rspc::{alpha::AlphaRouter, ErrorCode};
 use serde::Deserialize;
 use specta::Type;
-use std::{path::PathBuf, sync::PoisonError};
+use std::{collections::HashMap, path::PathBuf, sync::PoisonError};
 use tokio::io::AsyncWriteExt;
+use tracing::error;
 use uuid::Uuid;
 
 use super::{Ctx, R};
@@ -148,6 +150,47 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 			R.mutation(|node, id: Uuid| async move {
 				node.p2p.cancel_spacedrop(id).await;
 
+				Ok(())
+			})
+		})
+		// Spacedrop, library files and remote rspc, cloud sync only to the global one
+		.procedure("setTransferBandwidth", {
+			#[derive(Type, Deserialize)]
+			pub struct SetTransferBandwidthArgs {
+				limit: Option<BandwidthLimit>,
+				/// Replaces the limits of every peer, the ones left out get none of their own
+				peer_limits: Option<HashMap<RemoteIdentity, BandwidthLimit>>,
+				unmetered_sync_only: Option<bool>,
+			}
+
+			R.mutation(|node, args: SetTransferBandwidthArgs| async move {
+				node.config
+					.write(|config| {
+						if let Some(limit) = args.limit {
+							config.p2p.transfer_bandwidth = limit;
+						}
+						if let Some(peer_limits) = args.peer_limits {
+							config.p2p.peer_transfer_bandwidth = peer_limits;
+						}
+						if let Some(unmetered_sync_only) = args.unmetered_sync_only {
+							config.p2p.unmetered_sync_only = unmetered_sync_only;
+						}
+					})
+					.await
+					.map_err(|e| {
+						error!(?e, "Failed to write config;");
+						rspc::Error::new(
+							ErrorCode::InternalServerError,
+							"error updating config".into(),
+						)
+					})?;
+
+				// Open streams pick the new limits up on their next read or write
+				node.p2p.on_node_config_change().await;
+				node.metered.recheck();
+
+				invalidate_query!(node; node, "nodeState");
+
 				Ok(())
 			})
 		})
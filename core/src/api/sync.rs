@@ -1,9 +1,36 @@
-use rspc::alpha::AlphaRouter;
+use crate::{invalidate_query, util::MaybeUndefined};
+
+use sd_core_cloud_services::outbox_depth;
+use sd_core_sync::{
+	compaction,
+	conflict::{self, ConflictResolution},
+	preview,
+};
+
+use sd_prisma::prisma::sync_conflict;
+
 use std::sync::atomic::Ordering;
 
-use crate::util::MaybeUndefined;
+use rspc::alpha::AlphaRouter;
+use serde::Deserialize;
+use specta::Type;
+
+use super::{
+	utils::{library, InvalidateOperationEvent},
+	CoreEvent, Ctx, R,
+};
+
+#[derive(Type, Deserialize)]
+pub struct ResolveConflictArgs {
+	pub id: sync_conflict::id::Type,
+	pub resolution: ConflictResolution,
+}
 
-use super::{utils::library, Ctx, R};
+#[derive(Type, Deserialize)]
+pub struct SetModelSyncArgs {
+	pub model: String,
+	pub enabled: bool,
+}
 
 pub(crate) fn mount() -> AlphaRouter<Ctx> {
 	R.router()
@@ -75,4 +102,110 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					}
 				})
 		})
+		.procedure("compact", {
+			R.with2(library())
+				.mutation(|(node, library), _: ()| async move {
+					let retention = node.core_config.get().sync.retention();
+					Ok(compaction::compact(&library.sync, retention).await?)
+				})
+		})
+		.procedure("compaction", {
+			R.with2(library())
+				.query(|(_, library), _: ()| async move { Ok(library.sync.compaction.stats()) })
+		})
+		.procedure("outbox", {
+			R.with2(library())
+				.query(|(_, library), _: ()| async move { Ok(outbox_depth(&library.sync).await?) })
+		})
+		.procedure("preview", {
+			R.with2(library()).query(|(_, library), _: ()| async move {
+				Ok(preview::preview(&library.sync).await?)
+			})
+		})
+		.merge("backfill.", mount_backfill_routes())
+		.merge("conflicts.", mount_conflict_routes())
+		.merge("models.", mount_model_routes())
+}
+
+fn mount_backfill_routes() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("progress", {
+			R.with2(library())
+				.subscription(|(_, library), _: ()| async move {
+					async_stream::stream! {
+						let backfill = &library.sync.backfill;
+
+						loop {
+							// Before reading it, to not miss a change in between
+							let changed = backfill.changed();
+							yield backfill.progress();
+							changed.await;
+						}
+					}
+				})
+		})
+		.procedure("pause", {
+			R.with2(library())
+				.mutation(|(_, library), _: ()| async move {
+					library.sync.backfill.pause();
+					Ok(())
+				})
+		})
+		.procedure("resume", {
+			R.with2(library())
+				.mutation(|(_, library), _: ()| async move {
+					library.sync.backfill.resume();
+					Ok(())
+				})
+		})
+}
+
+fn mount_conflict_routes() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.with2(library())
+				.query(|(_, library), _: ()| async move { Ok(conflict::list(&library.db).await?) })
+		})
+		.procedure("resolve", {
+			R.with2(library()).mutation(
+				|(node, library), ResolveConflictArgs { id, resolution }| async move {
+					conflict::resolve(&library.sync, id, resolution).await?;
+
+					// The record changed, whatever its model is
+					node.emit(CoreEvent::InvalidateOperation(
+						InvalidateOperationEvent::all(),
+					));
+
+					Ok(())
+				},
+			)
+		})
+}
+
+fn mount_model_routes() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.with2(library())
+				.query(|(_, library), _: ()| async move { Ok(library.sync.toggles.list()) })
+		})
+		.procedure("set", {
+			R.with2(library()).mutation(
+				|(_, library), SetModelSyncArgs { model, enabled }| async move {
+					let mut disabled_models = library.config().await.sync.disabled_models;
+					disabled_models.retain(|disabled| *disabled != model);
+					if !enabled {
+						disabled_models.push(model);
+					}
+
+					library.sync.toggles.set_disabled(&disabled_models)?;
+					library
+						.update_config(|config| config.sync.disabled_models = disabled_models)
+						.await?;
+
+					invalidate_query!(library, "sync.models.list");
+
+					Ok(())
+				},
+			)
+		})
 }
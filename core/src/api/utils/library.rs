@@ -5,6 +5,7 @@ use rspc::{
 		unstable::{MwArgMapper, MwArgMapperMiddleware},
 		MwV3,
 	},
+	internal::ProcedureKind,
 	ErrorCode,
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
@@ -48,6 +49,18 @@ pub(crate) fn library() -> impl MwV3<Ctx, NewCtx = (Ctx, Arc<Library>)> {
 				)
 			})?;
 
+		if matches!(mw.req.kind, ProcedureKind::Mutation) && library.is_read_only().await {
+			return Err(read_only_library());
+		}
+
 		Ok(mw.next((ctx, library)))
 	})
 }
+
+/// Returned for the mutations of read-only libraries
+pub(crate) fn read_only_library() -> rspc::Error {
+	rspc::Error::new(
+		ErrorCode::Forbidden,
+		"This library is read-only, make it writable to change it.".to_string(),
+	)
+}
@@ -1,8 +1,5 @@
 use std::path::Path;
 
-// #[cfg(not(any(target_os = "ios", target_os = "android")))]
-// use keyring::Entry;
-
 use tokio::{fs, io};
 
 mod invalidate;
@@ -1,19 +1,178 @@
-use super::{utils::library, Ctx, R};
-use crate::volume::{VolumeEvent, VolumeFingerprint};
+use super::{utils::library, BackendFeature, Ctx, R};
+pub use crate::volume::{
+	capacity_forecast, diff_volumes, remote_volumes, space_breakdown, system_platform,
+	volume_history, BusyProcess, ByteSize, CapacityForecast, CategoryUsage, DegradedMode,
+	DeviceCapabilities, DirectoryUsage, DiscoveredShare, DiskType, ElevatedOperation, Elevation,
+	ElevationBackend, ElevationScope, EncryptionKind, FileSystem, FileSystemSnapshot, HealthStatus,
+	HistoryRange, LowSpaceThreshold, MemberState, MissingVolumeData, MountOptions, MountOwner,
+	MountType, NetworkShare, OperationPlan, PlanStep, PoolHealth, PoolKind, PoolMember,
+	PredictedOutcome, RemoteVolume, ScanProgress, ShareCredentials, ShareProtocol, ShareServer,
+	SpaceBreakdown, SpaceCategory, SpaceUsage, UnitPolicy, UnmountedDevice, Volume, VolumeError,
+	VolumeErrorCode, VolumeErrorInfo, VolumeEvent, VolumeFingerprint, VolumeHealth,
+	VolumeHistoryEntry, VolumeHistoryKind, VolumeOperation, VolumePlatform, VolumeSettings,
+	VolumeSnapshot, VolumeTopology, WatcherBackend, WatcherStatus, LARGE_CHANGE,
+};
+
+#[cfg(feature = "test-utils")]
+pub use crate::volume::{MockStep, MockVolumeProvider};
+
+#[cfg(all(unix, not(any(target_os = "ios", target_os = "android"))))]
+pub use crate::volume::{socket_path, ControlRequest, ControlResponse, CONTROL_SOCKET};
+
+#[cfg(target_os = "freebsd")]
+pub use crate::volume::FreeBsdPlatform;
+#[cfg(target_os = "macos")]
+pub use crate::volume::MacOsPlatform;
+#[cfg(any(target_os = "ios", target_os = "android"))]
+pub use crate::volume::MobilePlatform;
+#[cfg(not(any(
+	target_os = "linux",
+	target_os = "macos",
+	target_os = "windows",
+	target_os = "freebsd",
+	target_os = "ios",
+	target_os = "android"
+)))]
+pub use crate::volume::SysinfoPlatform;
+#[cfg(target_os = "windows")]
+pub use crate::volume::WindowsPlatform;
+#[cfg(target_os = "linux")]
+pub use crate::volume::{LinuxBackend, LinuxPlatform};
+
+#[cfg(target_os = "linux")]
+pub use crate::volume::mountinfo;
+use crate::{
+	invalidate_query,
+	node::{CoreConfigError, VolumeWatcherConfig},
+	old_job::OldJob,
+	volume::{
+		breakdown::OldVolumeBreakdownJobInit,
+		maintenance::{MaintenanceTask, OldVolumeMaintenanceJobInit},
+		release, settings,
+	},
+	Node,
+};
 use rspc::alpha::AlphaRouter;
 use serde::Deserialize;
 use specta::Type;
+use std::{collections::BTreeSet, path::PathBuf, sync::Arc, time::Instant};
+
+/// The volume to unmount or eject, a dry run only returns the plan
+#[derive(Deserialize, Type)]
+pub struct VolumeOperationArgs {
+	fingerprint: VolumeFingerprint,
+	#[serde(default)]
+	dry_run: bool,
+}
+
+/// A snapshot of the volume to mount or unmount
+#[derive(Deserialize, Type)]
+pub struct SnapshotArgs {
+	fingerprint: VolumeFingerprint,
+	id: String,
+}
+
+async fn run_operation(
+	node: &Node,
+	fingerprint: VolumeFingerprint,
+	operation: VolumeOperation,
+	dry_run: bool,
+) -> Result<OperationPlan, rspc::Error> {
+	if !dry_run {
+		node.telemetry.feature(match operation {
+			VolumeOperation::Unmount => "volumes.unmount",
+			VolumeOperation::Eject => "volumes.eject",
+			VolumeOperation::Format { .. } => "volumes.format",
+		});
+	}
+
+	release::run_operation(node, fingerprint, operation, dry_run)
+		.await
+		.map_err(|e| {
+			node.telemetry.error("volume", e.code().as_str());
+			e.into()
+		})
+}
+
+/// Saved in the core config, the watcher adds or removes the volumes as soon as it changes
+async fn update_ignored_paths(
+	node: &Node,
+	f: impl FnOnce(&mut BTreeSet<PathBuf>),
+) -> Result<(), rspc::Error> {
+	node.core_config
+		.update(|config| f(&mut config.volumes.ignored_paths))
+		.await
+		.map_err(|e| {
+			rspc::Error::with_cause(
+				rspc::ErrorCode::InternalServerError,
+				"Failed to save the ignored volume paths".to_string(),
+				e,
+			)
+		})
+}
+
+/// Saved in the core config, a volume set back to the defaults is dropped from it
+async fn update_settings(
+	node: &Node,
+	fingerprint: VolumeFingerprint,
+	settings: VolumeSettings,
+) -> Result<(), rspc::Error> {
+	node.core_config
+		.update(|config| {
+			if settings.is_default() {
+				config.volumes.settings.remove(&fingerprint);
+			} else {
+				config.volumes.settings.insert(fingerprint, settings);
+			}
+		})
+		.await
+		.map_err(|e| {
+			rspc::Error::with_cause(
+				rspc::ErrorCode::InternalServerError,
+				"Failed to save the volume settings".to_string(),
+				e,
+			)
+		})
+}
+
+/// Saved in the core config, the watcher follows without restarting
+async fn update_watcher_config(
+	node: &Node,
+	watcher: VolumeWatcherConfig,
+) -> Result<(), rspc::Error> {
+	node.core_config
+		.update(|config| config.watcher = watcher)
+		.await
+		.map_err(|e| match e {
+			CoreConfigError::Invalid(reason) => {
+				rspc::Error::new(rspc::ErrorCode::BadRequest, reason.to_string())
+			}
+			e => rspc::Error::with_cause(
+				rspc::ErrorCode::InternalServerError,
+				"Failed to save the volume watcher config".to_string(),
+				e,
+			),
+		})
+}
 
 pub(crate) fn mount() -> AlphaRouter<Ctx> {
 	R.router()
+		.procedure("degradedMode", {
+			R.query(|node, _: ()| async move { Ok(node.volumes.degraded_mode()) })
+		})
 		.procedure(
 			"list",
 			R.with2(library())
 				.query(|(node, library), _: ()| async move {
+					let start = Instant::now();
 					match node.volumes.list_system_volumes(library).await {
-						Ok(volumes) => Ok(volumes),
+						Ok(volumes) => {
+							node.telemetry.timing("volumes.list", start.elapsed());
+							Ok(settings::without_hidden(&node, volumes))
+						}
 						Err(e) => {
 							tracing::error!("Error listing volumes: {:?}", e);
+							node.telemetry.error("volume", e.code().as_str());
 							Err(e.into())
 						}
 					}
@@ -29,25 +188,149 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					);
 
 					node.volumes
-						.track_volume(fingerprint, library)
+						.track_volume(fingerprint.clone(), Arc::clone(&library))
 						.await
 						.map_err(|e| {
 							tracing::error!("Failed to track volume: {:?}", e);
-							e.into()
-						})
+							rspc::Error::from(e)
+						})?;
+					invalidate_query!(library, "volumes.list");
+					invalidate_query!(library, "volumes.listForLibrary");
+
+					// Measures the volume speeds in the job queue, the volume is tracked either way
+					if let Err(e) = OldJob::new(OldVolumeMaintenanceJobInit {
+						fingerprints: vec![fingerprint],
+						tasks: vec![MaintenanceTask::SpeedTest],
+					})
+					.spawn(&node, &library)
+					.await
+					{
+						tracing::warn!(?e, "Failed to queue the speed test of the tracked volume;");
+					}
+
+					Ok(())
 				},
 			),
 		)
+		.procedure(
+			"untrack",
+			R.with2(library()).mutation(
+				|(node, library), fingerprint: VolumeFingerprint| async move {
+					node.volumes
+						.untrack_volume(fingerprint, Arc::clone(&library))
+						.await?;
+
+					invalidate_query!(library, "volumes.list");
+					invalidate_query!(library, "volumes.listForLibrary");
+					Ok(())
+				},
+			),
+		)
+		// Runs right away, unlike the speed tests of the maintenance job
+		.procedure("speedTest", {
+			R.with2(library()).mutation(
+				|(node, library), fingerprint: VolumeFingerprint| async move {
+					node.telemetry.feature("volumes.speed_test");
+					let volume = node
+						.volumes
+						.speed_test(fingerprint, Arc::clone(&library))
+						.await
+						.map_err(|e| {
+							node.telemetry.error("volume", e.code().as_str());
+							rspc::Error::from(e)
+						})?;
+
+					invalidate_query!(library, "volumes.list");
+					Ok(volume)
+				},
+			)
+		})
+		// The maintenance job reads it too, without asking for privileges when it can't
+		.procedure("checkHealth", {
+			R.with2(library())
+				.mutation(|(node, _), fingerprint: VolumeFingerprint| async move {
+					node.telemetry.feature("volumes.check_health");
+					node.volumes.health(fingerprint).await.map_err(|e| {
+						node.telemetry.error("volume", e.code().as_str());
+						rspc::Error::from(e)
+					})
+				})
+		})
+		.procedure("capacityForecast", {
+			#[derive(Deserialize, Type)]
+			pub struct CapacityForecastArgs {
+				fingerprint: VolumeFingerprint,
+			}
+
+			R.with2(library()).query(
+				|(_, library), CapacityForecastArgs { fingerprint }: CapacityForecastArgs| async move {
+					Ok(capacity_forecast(&library.db, &fingerprint, chrono::Utc::now()).await?)
+				},
+			)
+		})
+		.procedure("history", {
+			#[derive(Deserialize, Type)]
+			pub struct VolumeHistoryArgs {
+				fingerprint: VolumeFingerprint,
+				#[serde(default)]
+				range: HistoryRange,
+			}
+
+			R.with2(library()).query(
+				|(_, library), VolumeHistoryArgs { fingerprint, range }: VolumeHistoryArgs| async move {
+					Ok(volume_history(&library.db, &fingerprint, range).await?)
+				},
+			)
+		})
+		.procedure("spaceBreakdown", {
+			#[derive(Deserialize, Type)]
+			pub struct SpaceBreakdownArgs {
+				fingerprint: VolumeFingerprint,
+			}
+
+			R.with2(library()).query(
+				|(_, library), SpaceBreakdownArgs { fingerprint }: SpaceBreakdownArgs| async move {
+					Ok(space_breakdown(&library.db, &fingerprint).await?)
+				},
+			)
+		})
+		// Walks the whole volume in the job queue, the breakdown is kept up to date from there
+		.procedure("computeSpaceBreakdown", {
+			R.with2(library()).mutation(
+				|(node, library), fingerprint: VolumeFingerprint| async move {
+					node.telemetry.feature("volumes.space_breakdown");
+					OldJob::new(OldVolumeBreakdownJobInit {
+						fingerprint,
+						directories: vec![],
+					})
+					.spawn(&node, &library)
+					.await
+					.map_err(rspc::Error::from)
+				},
+			)
+		})
 		.procedure(
 			"listForLibrary",
 			R.with2(library())
 				.query(|(node, library), _: ()| async move {
-					node.volumes
-						.list_library_volumes(library)
-						.await
-						.map_err(Into::into)
+					let volumes = node.volumes.list_library_volumes(library).await?;
+					Ok(settings::without_hidden(&node, volumes))
 				}),
 		)
+		// Synced from the other devices of the library
+		.procedure("listRemote", {
+			R.with2(library())
+				.query(|(node, library), _: ()| async move {
+					node.feature_flags
+						.ensure_enabled(BackendFeature::RemoteVolumeBrowsing)?;
+
+					let volumes =
+						remote_volumes(&library.db, &library.sync.device_pub_id.to_db()).await?;
+					Ok(settings::without_hidden_by(&node, volumes, |remote| {
+						&remote.volume
+					}))
+				})
+		})
 		// .procedure(
 		// 	"listByDevice",
 		// 	R.with2(library())
@@ -58,25 +341,253 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 		// 				.map_err(Into::into)
 		// 		}),
 		// )
-		.procedure(
-			"unmount",
+		.procedure("unmount", {
 			R.with2(library())
-				.mutation(|(node, _), fingerprint: Vec<u8>| async move {
-					node.volumes
-						.unmount_volume(VolumeFingerprint(fingerprint).into())
-						.await
-						.map_err(Into::into)
-				}),
-		)
-		.procedure("events", {
-			R.with2(library()).subscription(|(node, library), _: ()| {
-				Ok(async_stream::stream! {
-						let mut event_bus_rx = node.volumes.subscribe();
+				.mutation(|(node, _), args: VolumeOperationArgs| async move {
+					run_operation(
+						&node,
+						args.fingerprint,
+						VolumeOperation::Unmount,
+						args.dry_run,
+					)
+					.await
+				})
+		})
+		.procedure("eject", {
+			R.with2(library())
+				.mutation(|(node, _), args: VolumeOperationArgs| async move {
+					run_operation(
+						&node,
+						args.fingerprint,
+						VolumeOperation::Eject,
+						args.dry_run,
+					)
+					.await
+				})
+		})
+		.procedure("format", {
+			#[derive(Deserialize, Type)]
+			pub struct FormatVolumeArgs {
+				fingerprint: VolumeFingerprint,
+				file_system: FileSystem,
+				#[serde(default)]
+				dry_run: bool,
+			}
 
-						while let Ok(event) = event_bus_rx.recv().await {
-							yield event;
-						}
+			R.with2(library())
+				.mutation(|(node, _), args: FormatVolumeArgs| async move {
+					let operation = VolumeOperation::Format {
+						file_system: args.file_system,
+					};
+					run_operation(&node, args.fingerprint, operation, args.dry_run).await
+				})
+		})
+		// The credentials are stored beforehand with `keys.setSecret`
+		.procedure("mountNetworkShare", {
+			#[derive(Deserialize, Type)]
+			pub struct MountNetworkShareArgs {
+				url: String,
+				#[serde(default)]
+				mount_point: Option<PathBuf>,
+			}
+
+			R.mutation(|node, args: MountNetworkShareArgs| async move {
+				node.telemetry.feature("volumes.mount_network_share");
+				node.volumes
+					.mount_network_share(&args.url, args.mount_point)
+					.await
+					.map_err(|e| {
+						node.telemetry.error("volume", e.code().as_str());
+						e.into()
+					})
+			})
+		})
+		.procedure("attachDiskImage", {
+			#[derive(Deserialize, Type)]
+			pub struct AttachDiskImageArgs {
+				path: PathBuf,
+				#[serde(default)]
+				read_only: bool,
+			}
+
+			R.mutation(|node, args: AttachDiskImageArgs| async move {
+				node.telemetry.feature("volumes.attach_disk_image");
+				node.volumes
+					.attach_disk_image(args.path, args.read_only)
+					.await
+					.map_err(|e| {
+						node.telemetry.error("volume", e.code().as_str());
+						e.into()
+					})
+			})
+		})
+		.procedure("listSnapshots", {
+			R.query(|node, fingerprint: VolumeFingerprint| async move {
+				node.telemetry.feature("volumes.list_snapshots");
+				node.volumes.list_snapshots(fingerprint).await.map_err(|e| {
+					node.telemetry.error("volume", e.code().as_str());
+					e.into()
+				})
+			})
+		})
+		.procedure("mountSnapshot", {
+			R.mutation(|node, args: SnapshotArgs| async move {
+				node.telemetry.feature("volumes.mount_snapshot");
+				node.volumes
+					.mount_snapshot(args.fingerprint, args.id)
+					.await
+					.map_err(|e| {
+						node.telemetry.error("volume", e.code().as_str());
+						e.into()
+					})
+			})
+		})
+		.procedure("unmountSnapshot", {
+			R.mutation(|node, args: SnapshotArgs| async move {
+				node.volumes
+					.unmount_snapshot(args.fingerprint, args.id)
+					.await
+					.map_err(|e| {
+						node.telemetry.error("volume", e.code().as_str());
+						e.into()
+					})
+			})
+		})
+		.procedure("discoverShareServers", {
+			R.query(|node, _: ()| async move {
+				node.telemetry.feature("volumes.discover_share_servers");
+				Ok(node.volumes.discover_share_servers().await)
+			})
+		})
+		.procedure("listNetworkShares", {
+			#[derive(Deserialize, Type)]
+			pub struct ListNetworkSharesArgs {
+				host: String,
+				protocol: ShareProtocol,
+			}
+
+			R.query(|node, args: ListNetworkSharesArgs| async move {
+				node.volumes
+					.list_network_shares(&args.host, args.protocol)
+					.await
+					.map_err(Into::into)
+			})
+		})
+		.procedure("listUnmounted", {
+			R.query(|node, _: ()| async move {
+				node.volumes.unmounted_devices().await.map_err(Into::into)
+			})
+		})
+		.procedure("mount", {
+			#[derive(Deserialize, Type)]
+			pub struct MountVolumeArgs {
+				device: PathBuf,
+				#[serde(default)]
+				options: MountOptions,
+			}
+
+			R.mutation(|node, args: MountVolumeArgs| async move {
+				node.telemetry.feature("volumes.mount");
+				let volume = node
+					.volumes
+					.mount_volume(&args.device, args.options)
+					.await
+					.map_err(|e| {
+						node.telemetry.error("volume", e.code().as_str());
+						rspc::Error::from(e)
+					})?;
+
+				invalidate_query!(node; node, "volumes.listUnmounted");
+				Ok(volume)
+			})
+		})
+		// The volumes mounted below these paths were dismissed and aren't listed, across restarts
+		.procedure("listIgnoredPaths", {
+			R.query(|node, _: ()| async move {
+				Ok(node
+					.core_config
+					.get()
+					.volumes
+					.ignored_paths
+					.into_iter()
+					.collect::<Vec<_>>())
+			})
+		})
+		.procedure("ignorePath", {
+			R.mutation(|node, path: PathBuf| async move {
+				node.telemetry.feature("volumes.ignore_path");
+				update_ignored_paths(&node, |ignored_paths| {
+					ignored_paths.insert(path);
+				})
+				.await
+			})
+		})
+		.procedure("unignorePath", {
+			R.mutation(|node, path: PathBuf| async move {
+				update_ignored_paths(&node, |ignored_paths| {
+					ignored_paths.remove(&path);
+				})
+				.await
+			})
+		})
+		// The ones left out of `list`, so they can be shown again
+		.procedure("listHidden", {
+			R.with2(library())
+				.query(|(node, library), _: ()| async move {
+					let config = node.core_config.get().volumes;
+					let mut volumes = node.volumes.list_system_volumes(library).await?;
+					volumes.retain(|volume| {
+						volume
+							.fingerprint
+							.as_ref()
+							.is_some_and(|fingerprint| config.settings_of(fingerprint).is_hidden)
+					});
+					Ok(volumes)
 				})
+		})
+		.procedure("settings", {
+			R.query(|node, fingerprint: VolumeFingerprint| async move {
+				Ok(node.core_config.get().volumes.settings_of(&fingerprint))
+			})
+		})
+		// Kept across libraries and mounts, the jobs already running aren't stopped
+		.procedure("updateSettings", {
+			#[derive(Deserialize, Type)]
+			pub struct UpdateVolumeSettingsArgs {
+				fingerprint: VolumeFingerprint,
+				settings: VolumeSettings,
+			}
+
+			R.with2(library()).mutation(
+				|(node, library), args: UpdateVolumeSettingsArgs| async move {
+					node.telemetry.feature("volumes.update_settings");
+					update_settings(&node, args.fingerprint, args.settings).await?;
+
+					invalidate_query!(library, "volumes.list");
+					invalidate_query!(library, "volumes.listHidden");
+					Ok(())
+				},
+			)
+		})
+		.procedure("watcherConfig", {
+			R.query(|node, _: ()| async move { Ok(node.core_config.get().watcher) })
+		})
+		// Debounce, backend and extra watched paths, applied by the running watcher
+		.procedure("updateWatcherConfig", {
+			R.mutation(|node, watcher: VolumeWatcherConfig| async move {
+				update_watcher_config(&node, watcher).await
 			})
 		})
+		// Attached to bug reports, so the environment can be replayed with the mock provider
+		.procedure("snapshot", {
+			R.query(|node, _: ()| async move { Ok(node.volumes.snapshot().await?) })
+		})
+		// Fills the volume list in while slow mounts are still being probed
+		.procedure("scan", {
+			R.subscription(|node, _: ()| async move { node.volumes.scan_with_progress() })
+		})
+		.procedure("events", {
+			R.with2(library())
+				.subscription(|(node, _), _: ()| Ok(node.volumes.subscribe().into_stream()))
+		})
 }
@@ -146,6 +146,51 @@ async fn request_to_remote_node(
 	response.into_response()
 }
 
+/// Serves the thumbnail at `path`, relative to the thumbnails directory, with support for
+/// range requests and caching. Also used by the desktop app's custom protocol.
+pub async fn serve_thumbnail(
+	node: &Node,
+	path: &str,
+	request: axum::http::request::Parts,
+) -> Result<Response<Body>, Response<Body>> {
+	let thumbnail_path = node.config.data_directory().join("thumbnails");
+	let path = thumbnail_path.join(path);
+
+	// Prevent directory traversal attacks (Eg. requesting `../../../etc/passwd`)
+	// For now we only support `webp` thumbnails.
+	(path.starts_with(&thumbnail_path)
+		&& !path
+			.components()
+			.any(|component| component == std::path::Component::ParentDir)
+		&& path.extension() == Some(WEBP_EXTENSION.as_ref()))
+	.then_some(())
+	.ok_or_else(|| not_found(()))?;
+
+	let file = File::open(&path).await.map_err(|e| {
+		InfallibleResponse::builder()
+			.status(if e.kind() == io::ErrorKind::NotFound {
+				StatusCode::NOT_FOUND
+			} else {
+				StatusCode::INTERNAL_SERVER_ERROR
+			})
+			.body(Body::from(""))
+	})?;
+	let metadata = file.metadata().await;
+	serve_file(
+		file,
+		metadata,
+		request,
+		InfallibleResponse::builder()
+			.header("Content-Type", HeaderValue::from_static("image/webp"))
+			// Thumbnails are addressed by the content they were generated from, so they never change
+			.header(
+				header::CACHE_CONTROL,
+				HeaderValue::from_static("public, max-age=31536000, immutable"),
+			),
+	)
+	.await
+}
+
 async fn get_or_init_lru_entry(
 	state: &LocalState,
 	extract::Path((lib_id, loc_id, path_id)): ExtractedPath,
@@ -230,34 +275,7 @@ pub fn base_router() -> Router<LocalState> {
 				|State(state): State<LocalState>,
 				 extract::Path(path): extract::Path<String>,
 				 request: Request<Body>| async move {
-					let thumbnail_path = state.node.config.data_directory().join("thumbnails");
-					let path = thumbnail_path.join(path);
-
-					// Prevent directory traversal attacks (Eg. requesting `../../../etc/passwd`)
-					// For now we only support `webp` thumbnails.
-					(path.starts_with(&thumbnail_path)
-						&& path.extension() == Some(WEBP_EXTENSION.as_ref()))
-					.then_some(())
-					.ok_or_else(|| not_found(()))?;
-
-					let file = File::open(&path).await.map_err(|e| {
-						InfallibleResponse::builder()
-							.status(if e.kind() == io::ErrorKind::NotFound {
-								StatusCode::NOT_FOUND
-							} else {
-								StatusCode::INTERNAL_SERVER_ERROR
-							})
-							.body(Body::from(""))
-					})?;
-					let metadata = file.metadata().await;
-					serve_file(
-						file,
-						metadata,
-						request.into_parts().0,
-						InfallibleResponse::builder()
-							.header("Content-Type", HeaderValue::from_static("image/webp")),
-					)
-					.await
+					serve_thumbnail(&state.node, &path, request.into_parts().0).await
 				},
 			),
 		)
@@ -13,15 +13,18 @@ use sd_core_prisma_helpers::CasId;
 use sd_crypto::CryptoRng;
 use sd_task_system::TaskSystem;
 use sd_utils::error::FileIOError;
-use volume::VolumeManagerActor;
+use volume::{VolumeManagerActor, VolumePlatform};
 
 use std::{
+	collections::HashMap,
 	fmt,
 	path::{Path, PathBuf},
+	pin::pin,
 	sync::Arc,
 };
 
 use chrono::{DateTime, Utc};
+use futures::StreamExt;
 use futures_concurrency::future::Join;
 use thiserror::Error;
 use tokio::{
@@ -29,13 +32,8 @@ use tokio::{
 	sync::{broadcast, Mutex},
 };
 use tracing::{error, info, warn};
-use tracing_appender::{
-	non_blocking::{NonBlocking, WorkerGuard},
-	rolling::{RollingFileAppender, Rotation},
-};
-use tracing_subscriber::{
-	filter::FromEnvError, fmt::format::Format, prelude::*, registry, EnvFilter,
-};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::filter::FromEnvError;
 
 pub mod api;
 mod context;
@@ -63,11 +61,20 @@ use sd_core_cloud_services::AUTH_SERVER_URL;
 pub struct Node {
 	pub data_dir: PathBuf,
 	pub config: Arc<config::Manager>,
+	pub core_config: Arc<node::CoreConfigManager>,
 	pub libraries: Arc<library::Libraries>,
 	pub volumes: Arc<volume::Volumes>,
+	pub power: Arc<node::PowerManager>,
+	pub offline: Arc<node::OfflineMode>,
+	pub metered: Arc<node::MeteredNetwork>,
+	pub telemetry: Arc<node::Telemetry>,
+	pub feature_flags: Arc<node::FeatureFlags>,
+	pub secrets: node::SecretStore,
 	pub locations: location::Locations,
 	pub p2p: Arc<old_p2p::P2PManager>,
 	pub event_bus: (broadcast::Sender<CoreEvent>, broadcast::Receiver<CoreEvent>),
+	/// Typed events published by the subsystems, see [`node::EventBus`]
+	pub events: Arc<node::EventBus>,
 	pub notifications: Notifications,
 	pub task_system: TaskSystem<sd_core_heavy_lifting::Error>,
 	pub job_system: JobSystem<NodeContext, JobContext<NodeContext>>,
@@ -88,6 +95,14 @@ impl fmt::Debug for Node {
 
 impl Node {
 	pub async fn new(data_dir: impl AsRef<Path>) -> Result<(Arc<Node>, Arc<Router>), NodeError> {
+		Self::new_with_volume_platform(data_dir, volume::system_platform().await).await
+	}
+
+	/// For shells that find the volumes themselves, like the mobile apps through their native APIs
+	pub async fn new_with_volume_platform(
+		data_dir: impl AsRef<Path>,
+		volume_platform: Arc<dyn VolumePlatform>,
+	) -> Result<(Arc<Node>, Arc<Router>), NodeError> {
 		let data_dir = data_dir.as_ref();
 
 		info!(data_directory = %data_dir.display(), "Starting core;");
@@ -99,9 +114,11 @@ impl Node {
 		let _ = fs::create_dir_all(&data_dir).await;
 
 		let event_bus = broadcast::channel(1024);
+		let events = Arc::new(node::EventBus::new());
 		let config = config::Manager::new(data_dir.to_path_buf())
 			.await
 			.map_err(NodeError::FailedToInitializeConfig)?;
+		let core_config = Arc::new(node::CoreConfigManager::load(data_dir).await);
 
 		let (locations, locations_actor) = location::Locations::new();
 		let (old_jobs, jobs_actor) = old_job::OldJobs::new();
@@ -155,26 +172,61 @@ impl Node {
 			.await
 			.map_err(NodeError::P2PManager)?;
 
-		let device_id = config.get().await.id;
+		let node_config = config.get().await;
+		let device_id = node_config.id;
+		let power = Arc::new(node::PowerManager::new(
+			node_config.preferences.prevent_sleep_during_jobs,
+		));
+		let offline = Arc::new(node::OfflineMode::new(node_config.preferences.offline));
+		let metered = Arc::new(node::MeteredNetwork::new());
+		let telemetry = Arc::new(
+			node::Telemetry::new(
+				data_dir,
+				node_config.preferences.telemetry,
+				core_config.watch(),
+			)
+			.await,
+		);
+		let feature_flags = Arc::new(node::FeatureFlags::new(&node_config.features));
+		let secrets = node::SecretStore::new(&device_id);
 		let volume_ctx = volume::VolumeManagerContext {
 			device_id: device_id.clone().into(),
 			library_event_tx: libraries.rx.clone(),
+			config: core_config.watch(),
+			platform: Arc::new(volume::CachedPlatform::new(
+				volume_platform,
+				core_config.watch(),
+			)),
+			events: Arc::clone(&events),
+			secrets: secrets.clone(),
+			feature_flags: Arc::clone(&feature_flags),
 		};
 
 		let (volumes, volume_manager_actor) = VolumeManagerActor::new(Arc::new(volume_ctx)).await?;
 
 		let volumes = Arc::new(volumes);
 
+		// Cloud sync is held to the bandwidth limits of p2p
+		let bandwidth = Arc::clone(p2p.p2p.bandwidth());
+
 		let node = Arc::new(Node {
 			data_dir: data_dir.to_path_buf(),
 			job_system: JobSystem::new(task_system.get_dispatcher(), data_dir),
 			task_system,
 			volumes,
+			power,
+			offline,
+			metered,
+			telemetry,
+			feature_flags,
+			secrets,
 			locations,
 			notifications: notifications::Notifications::new(),
 			p2p,
 			config,
+			core_config,
 			event_bus,
+			events,
 			libraries,
 			cloud_services: Arc::new(
 				CloudServices::new(
@@ -183,6 +235,8 @@ impl Node {
 					cloud_p2p_dns_pkarr_url,
 					cloud_p2p_dns_origin_name,
 					cloud_services_domain_name,
+					node_config.preferences.offline,
+					bandwidth,
 				)
 				.await?,
 			),
@@ -219,25 +273,66 @@ impl Node {
 		jobs_actor.start(node.clone());
 		volume_manager_actor.start(device_id).await;
 
+		let mut job_contexts = HashMap::new();
+		for library in node.libraries.get_all().await {
+			// Read-only libraries don't resume their jobs
+			if !library.is_read_only().await {
+				job_contexts.insert(
+					library.id,
+					NodeContext {
+						library,
+						node: Arc::clone(&node),
+					},
+				);
+			}
+		}
 		node.job_system
-			.init(
-				&node
-					.libraries
-					.get_all()
-					.await
-					.into_iter()
-					.map(|library| {
-						(
-							library.id,
-							NodeContext {
-								library,
-								node: Arc::clone(&node),
-							},
-						)
-					})
-					.collect(),
-			)
-			.await?;
+			.set_max_jobs_per_ctx(node.core_config.get().jobs.max_workers)
+			.await;
+		node.job_system.init(&job_contexts).await?;
+		tokio::spawn({
+			let mut config_rx = node.core_config.watch();
+			let node = Arc::downgrade(&node);
+			async move {
+				while config_rx.changed().await.is_ok() {
+					let max_workers = config_rx.borrow_and_update().jobs.max_workers;
+					let Some(node) = node.upgrade() else {
+						break;
+					};
+					node.job_system.set_max_jobs_per_ctx(max_workers).await;
+				}
+			}
+		});
+
+		if let Err(e) = node.core_config.start() {
+			warn!(?e, "Failed to watch the core config, changes will need a restart;");
+		}
+		node.power.start(&node);
+		node.offline.start(&node);
+		node.metered.start(&node);
+		node.telemetry.start(&node);
+		volume::maintenance::start(&node);
+		library::compaction::start(&node);
+		library::backups::start(&node);
+		#[cfg(all(unix, not(any(target_os = "ios", target_os = "android"))))]
+		volume::control::start(&node);
+
+		tokio::spawn({
+			let node = Arc::clone(&node);
+			async move {
+				let mut outputs = pin!(node.job_system.receive_job_outputs());
+				while let Some((job_id, res)) = outputs.next().await {
+					volume::quarantine::on_job_output(&node, &res).await;
+					if let Ok(output) = &res {
+						volume::breakdown::on_job_output(&node, output);
+						library::aggregator::on_job_output(&node, output).await;
+					}
+					node.emit(CoreEvent::JobFinished(api::JobFinishedEvent::new(
+						job_id, res,
+					)));
+				}
+			}
+		});
 
 		start_p2p(
 			node.clone(),
@@ -266,62 +361,7 @@ impl Node {
 	}
 
 	pub fn init_logger(data_dir: impl AsRef<Path>) -> Result<WorkerGuard, FromEnvError> {
-		let (logfile, guard) = NonBlocking::new(
-			RollingFileAppender::builder()
-				.filename_prefix("sd.log")
-				.rotation(Rotation::DAILY)
-				.max_log_files(4)
-				.build(data_dir.as_ref().join("logs"))
-				.expect("Error setting up log file!"),
-		);
-
-		// Set a default if the user hasn't set an override
-		if std::env::var("RUST_LOG") == Err(std::env::VarError::NotPresent) {
-			let level = if cfg!(debug_assertions) {
-				"debug"
-			} else {
-				"info"
-			};
-
-			std::env::set_var(
-				"RUST_LOG",
-				format!(
-					"info,\
-					iroh_net=info,\
-					sd_core={level},\
-					sd_p2p={level},\
-					sd_core_heavy_lifting={level},\
-					sd_task_system={level},\
-					sd_ai={level}"
-				),
-			);
-		}
-
-		let registry = registry();
-
-		let registry = registry
-			.with(
-				tracing_subscriber::fmt::layer()
-					.with_file(true)
-					.with_line_number(true)
-					.with_ansi(false)
-					.with_target(true)
-					.with_writer(logfile)
-					.with_filter(EnvFilter::from_default_env()),
-			)
-			.with(
-				tracing_subscriber::fmt::layer()
-					.with_file(true)
-					.with_line_number(true)
-					.with_writer(std::io::stdout)
-					.event_format(Format::default().pretty())
-					.with_filter(EnvFilter::from_default_env()),
-			);
-
-		#[cfg(target_os = "android")]
-		let registry = registry.with(tracing_android::layer("com.spacedrive.app").unwrap());
-
-		registry.init();
+		let guard = node::logging::init(data_dir.as_ref())?;
 
 		std::panic::set_hook(Box::new(move |panic| {
 			use std::backtrace::{Backtrace, BacktraceStatus};
@@ -356,6 +396,8 @@ impl Node {
 			self.old_jobs.shutdown(),
 			self.p2p.shutdown(),
 			self.job_system.shutdown(),
+			self.volumes.shutdown(),
+			self.telemetry.shutdown(),
 		)
 			.join()
 			.await;
@@ -363,6 +405,32 @@ impl Node {
 		info!("Spacedrive Core shutdown successful!");
 	}
 
+	/// Retries scans that didn't complete on every library, to be called when the platform
+	/// reports that we gained access to paths we were previously denied.
+	pub async fn retry_incomplete_scans(self: &Arc<Self>) {
+		for library in self.libraries.get_all().await {
+			if let Err(e) = location::rescan_incomplete_locations(self, &library).await {
+				error!(?e, library_id = %library.id, "Failed to retry incomplete scans;");
+			}
+		}
+	}
+
+	/// Turns offline mode on or off, persisting it in the node preferences
+	pub async fn set_offline(&self, offline: bool) -> Result<(), NodeError> {
+		self.config
+			.update_preferences(|preferences| preferences.offline = offline)
+			.await
+			.map_err(NodeError::FailedToUpdateConfig)
+	}
+
+	/// Opts in or out of anonymous telemetry, persisting it in the node preferences
+	pub async fn set_telemetry(&self, enabled: bool) -> Result<(), NodeError> {
+		self.config
+			.update_preferences(|preferences| preferences.telemetry = enabled)
+			.await
+			.map_err(NodeError::FailedToUpdateConfig)
+	}
+
 	pub(crate) fn emit(&self, event: CoreEvent) {
 		if let Err(e) = self.event_bus.0.send(event) {
 			warn!(?e, "Error sending event to event bus;");
@@ -411,6 +479,8 @@ impl Node {
 pub enum NodeError {
 	#[error("NodeError::FailedToInitializeConfig({0})")]
 	FailedToInitializeConfig(config::NodeConfigError),
+	#[error("failed to update node config: {0}")]
+	FailedToUpdateConfig(config::NodeConfigError),
 	#[error("failed to initialize library manager: {0}")]
 	FailedToInitializeLibraryManager(#[from] library::LibraryManagerError),
 	#[error("failed to initialize location manager: {0}")]
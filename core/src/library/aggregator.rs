@@ -0,0 +1,571 @@
+//! Aggregator, the statistics of a library for its dashboard, kept up to date in the background.
+//!
+//! Going through every file path of a large library takes a while, so it isn't done when the
+//! statistics are asked for. The aggregator of each library goes through its file paths in batches
+//! once it's loaded, keeping what the ones of each location add up to. A little after a job over a
+//! location finishes only the file paths of that location are gone through again, the jobs that
+//! can span locations still go through all of them. The `library.statistics` query returns what
+//! the last pass found. Files linked to an object one of the paths before them was linked to count
+//! as duplicates, directories aren't counted. The aggregator stops once its library is deleted.
+
+use crate::{
+	api::utils::get_size,
+	invalidate_query,
+	library::Library,
+	volume::{Volume, VolumeFingerprint},
+	Node,
+};
+
+use sd_core_heavy_lifting::{
+	job_system::report::{ReportInputMetadata, ReportMetadata},
+	media_processor::get_thumbnails_directory,
+	JobName, JobOutput,
+};
+use sd_file_ext::kind::ObjectKind;
+use sd_prisma::prisma::{file_path, location, object, PrismaClient, SortOrder};
+use sd_utils::db::size_in_bytes_from_db;
+
+use std::{
+	collections::{HashMap, HashSet},
+	mem,
+	path::Path,
+	sync::{Arc, Mutex, PoisonError, RwLock},
+	time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use specta::Type;
+use strum::IntoEnumIterator;
+use tokio::{sync::Notify, time::sleep};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+use super::LibraryManagerError;
+
+/// File paths looked at in one go
+const BATCH_SIZE: i64 = 1000;
+
+/// Between two batches, so a pass doesn't hold the database back from the jobs
+const BATCH_DELAY: Duration = Duration::from_millis(20);
+
+/// Waited after a pass is requested, the jobs that finish meanwhile make a single pass
+const SETTLE_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct LibraryBreakdown {
+	pub total_files: u64,
+	pub total_bytes: u64,
+	/// Of the first file of each object and of the files without one
+	pub unique_bytes: u64,
+	/// Of the files after the first of each object
+	pub duplicate_bytes: u64,
+	/// The kinds with files, the unidentified ones as `Unknown`
+	pub kinds: Vec<KindBreakdown>,
+	pub locations: Vec<LocationBreakdown>,
+	/// The tracked volumes with a location, each location counted on the innermost one
+	pub volumes: Vec<VolumeBreakdown>,
+	/// The thumbnails generated for the library
+	pub thumbnails_bytes: u64,
+	pub computed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct KindBreakdown {
+	pub kind: i32,
+	pub name: String,
+	pub files: u64,
+	pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct LocationBreakdown {
+	pub location_id: location::id::Type,
+	pub name: Option<String>,
+	pub files: u64,
+	pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct VolumeBreakdown {
+	pub fingerprint: Option<VolumeFingerprint>,
+	pub name: String,
+	pub files: u64,
+	pub bytes: u64,
+}
+
+/// Of every library, see the [module docs](self)
+#[derive(Debug, Default)]
+pub struct Aggregator {
+	latest: RwLock<Option<LibraryBreakdown>>,
+	pending: Mutex<Pending>,
+	requested: Notify,
+	cancel: CancellationToken,
+}
+
+/// The file paths the next pass goes through
+#[derive(Debug, Default)]
+struct Pending {
+	all: bool,
+	locations: HashSet<location::id::Type>,
+}
+
+impl Aggregator {
+	/// From the last pass, `None` until the first one is done
+	pub fn latest(&self) -> Option<LibraryBreakdown> {
+		self.latest
+			.read()
+			.unwrap_or_else(PoisonError::into_inner)
+			.clone()
+	}
+
+	/// Asks for a pass over every file path, the ones asked for before it starts make a single one
+	pub fn request(&self) {
+		self.pending
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.all = true;
+		self.requested.notify_one();
+	}
+
+	/// Asks for a pass over the file paths of a location
+	pub fn request_location(&self, location_id: location::id::Type) {
+		self.pending
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.locations
+			.insert(location_id);
+		self.requested.notify_one();
+	}
+
+	/// Stops the aggregator for good, for when its library is deleted
+	pub(crate) fn stop(&self) {
+		self.cancel.cancel();
+	}
+
+	fn take_pending(&self) -> Pending {
+		mem::take(&mut *self.pending.lock().unwrap_or_else(PoisonError::into_inner))
+	}
+}
+
+/// Starts the aggregator of the library, making its first pass right away
+pub(crate) fn start(node: &Arc<Node>, library: &Arc<Library>) {
+	let node = Arc::clone(node);
+	let library = Arc::clone(library);
+	tokio::spawn(async move {
+		let cancel = library.aggregator.cancel.clone();
+		tokio::select! {
+			() = cancel.cancelled() => {
+				debug!(library_id = %library.id, "Stopped the aggregator of the library;");
+			}
+			() = run(&node, &library) => {}
+		}
+	});
+}
+
+async fn run(node: &Node, library: &Arc<Library>) {
+	let mut partials = HashMap::new();
+	let mut pending = Pending {
+		all: true,
+		locations: HashSet::new(),
+	};
+
+	loop {
+		let failed = match update(node, library, &mut partials, pending).await {
+			Ok(breakdown) => {
+				debug!(
+					library_id = %library.id,
+					total_files = breakdown.total_files,
+					"Aggregated library statistics;",
+				);
+
+				*library
+					.aggregator
+					.latest
+					.write()
+					.unwrap_or_else(PoisonError::into_inner) = Some(breakdown);
+				invalidate_query!(library, "library.statistics");
+
+				false
+			}
+			Err(e) => {
+				warn!(?e, library_id = %library.id, "Failed to aggregate library statistics;");
+				true
+			}
+		};
+
+		library.aggregator.requested.notified().await;
+		sleep(SETTLE_DELAY).await;
+
+		pending = library.aggregator.take_pending();
+		// What a failed pass went through may be partly updated
+		pending.all |= failed;
+	}
+}
+
+/// Requests a pass from the aggregators of the libraries having the location of the job, or of all
+/// of them for the jobs without one
+pub(crate) async fn on_job_output(node: &Arc<Node>, output: &JobOutput) {
+	let within_location = match output.job_name() {
+		JobName::Indexer
+		| JobName::FileIdentifier
+		| JobName::MediaProcessor
+		| JobName::Delete
+		| JobName::Erase => true,
+		// Their targets can be in other locations
+		JobName::Copy | JobName::Move => false,
+		_ => return,
+	};
+
+	let location_pub_id = output
+		.metadata()
+		.iter()
+		.find_map(|metadata| match metadata {
+			ReportMetadata::Input(ReportInputMetadata::Location(data)) => Some(data.pub_id.clone()),
+			_ => None,
+		});
+
+	for library in node.libraries.get_all().await {
+		let Some(location_pub_id) = &location_pub_id else {
+			library.aggregator.request();
+			continue;
+		};
+
+		match library
+			.db
+			.location()
+			.find_unique(location::pub_id::equals(location_pub_id.clone()))
+			.select(location::select!({ id }))
+			.exec()
+			.await
+		{
+			Ok(Some(location)) if within_location => {
+				library.aggregator.request_location(location.id);
+			}
+			Ok(Some(_)) => library.aggregator.request(),
+			Ok(None) => {}
+			Err(e) => {
+				warn!(?e, library_id = %library.id, "Failed to find the location of a job;");
+			}
+		}
+	}
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Totals {
+	files: u64,
+	bytes: u64,
+}
+
+impl Totals {
+	fn add(&mut self, bytes: u64) {
+		self.files += 1;
+		self.bytes += bytes;
+	}
+
+	fn merge(&mut self, other: Self) {
+		self.files += other.files;
+		self.bytes += other.bytes;
+	}
+}
+
+/// The files linked to an object
+#[derive(Debug, Clone, Copy)]
+struct ObjectFiles {
+	first: file_path::id::Type,
+	first_bytes: u64,
+	bytes: u64,
+}
+
+impl ObjectFiles {
+	fn merge(&mut self, other: Self) {
+		if other.first < self.first {
+			self.first = other.first;
+			self.first_bytes = other.first_bytes;
+		}
+		self.bytes += other.bytes;
+	}
+}
+
+/// What the file paths of a location, or the ones without a location, add up to
+#[derive(Debug, Default)]
+struct Partial {
+	totals: Totals,
+	kinds: HashMap<i32, Totals>,
+	objects: HashMap<object::id::Type, ObjectFiles>,
+}
+
+impl Partial {
+	fn add(
+		&mut self,
+		file_path_id: file_path::id::Type,
+		kind: i32,
+		object_id: Option<object::id::Type>,
+		bytes: u64,
+	) {
+		self.totals.add(bytes);
+		self.kinds.entry(kind).or_default().add(bytes);
+		if let Some(object_id) = object_id {
+			self.objects
+				.entry(object_id)
+				.and_modify(|files| files.bytes += bytes)
+				.or_insert(ObjectFiles {
+					first: file_path_id,
+					first_bytes: bytes,
+					bytes,
+				});
+		}
+	}
+
+	fn merge(&mut self, other: &Self) {
+		self.totals.merge(other.totals);
+		for (kind, totals) in &other.kinds {
+			self.kinds.entry(*kind).or_default().merge(*totals);
+		}
+		for (object_id, files) in &other.objects {
+			self.objects
+				.entry(*object_id)
+				.and_modify(|merged| merged.merge(*files))
+				.or_insert(*files);
+		}
+	}
+
+	/// Of the files after the first of each object
+	fn duplicate_bytes(&self) -> u64 {
+		self.objects
+			.values()
+			.map(|files| files.bytes - files.first_bytes)
+			.sum()
+	}
+}
+
+/// Goes through the pending file paths again, then puts what all of them add up to together
+async fn update(
+	node: &Node,
+	library: &Arc<Library>,
+	partials: &mut HashMap<Option<location::id::Type>, Partial>,
+	pending: Pending,
+) -> Result<LibraryBreakdown, LibraryManagerError> {
+	if pending.all {
+		*partials = scan(&library.db, None).await?;
+	} else {
+		for location_id in pending.locations {
+			let mut scanned = scan(&library.db, Some(location_id)).await?;
+
+			match scanned.remove(&Some(location_id)) {
+				Some(partial) => partials.insert(Some(location_id), partial),
+				None => partials.remove(&Some(location_id)),
+			};
+		}
+	}
+
+	breakdown(node, library, partials).await
+}
+
+/// Goes through the file paths of the location, or all of them
+async fn scan(
+	db: &PrismaClient,
+	location_id: Option<location::id::Type>,
+) -> Result<HashMap<Option<location::id::Type>, Partial>, LibraryManagerError> {
+	let mut partials = HashMap::<_, Partial>::new();
+	let mut cursor = 0;
+
+	loop {
+		let batch = db
+			.file_path()
+			.find_many(
+				location_id
+					.map(|location_id| file_path::location_id::equals(Some(location_id)))
+					.into_iter()
+					.chain([file_path::id::gt(cursor)])
+					.collect(),
+			)
+			.order_by(file_path::id::order(SortOrder::Asc))
+			.take(BATCH_SIZE)
+			.select(file_path::select!({
+				id
+				location_id
+				is_dir
+				size_in_bytes_bytes
+				object: select { id kind }
+			}))
+			.exec()
+			.await?;
+
+		let Some(last) = batch.last() else {
+			break;
+		};
+		cursor = last.id;
+
+		for file_path in batch {
+			if file_path.is_dir.unwrap_or_default() {
+				continue;
+			}
+
+			let bytes = file_path
+				.size_in_bytes_bytes
+				.filter(|size| size.len() == size_of::<u64>())
+				.map_or(0, |size| size_in_bytes_from_db(&size));
+
+			partials.entry(file_path.location_id).or_default().add(
+				file_path.id,
+				file_path
+					.object
+					.as_ref()
+					.and_then(|object| object.kind)
+					.unwrap_or(ObjectKind::Unknown as i32),
+				file_path.object.map(|object| object.id),
+				bytes,
+			);
+		}
+
+		sleep(BATCH_DELAY).await;
+	}
+
+	Ok(partials)
+}
+
+async fn breakdown(
+	node: &Node,
+	library: &Arc<Library>,
+	partials: &mut HashMap<Option<location::id::Type>, Partial>,
+) -> Result<LibraryBreakdown, LibraryManagerError> {
+	let db = &library.db;
+
+	let existing_locations = db
+		.location()
+		.find_many(vec![])
+		.select(location::select!({ id name path }))
+		.exec()
+		.await?;
+
+	// The file paths of the removed locations went with them
+	let location_ids = existing_locations
+		.iter()
+		.map(|location| location.id)
+		.collect::<HashSet<_>>();
+	partials.retain(|location_id, _| match location_id {
+		Some(location_id) => location_ids.contains(location_id),
+		None => true,
+	});
+
+	let mut library_partial = Partial::default();
+	for partial in partials.values() {
+		library_partial.merge(partial);
+	}
+	let Partial { totals, kinds, .. } = &library_partial;
+	let duplicate_bytes = library_partial.duplicate_bytes();
+
+	let volumes = node
+		.volumes
+		.list_library_volumes(Arc::clone(library))
+		.await
+		.unwrap_or_else(|e| {
+			warn!(
+				?e,
+				"Failed to list the volumes of the library for its statistics;"
+			);
+			vec![]
+		});
+
+	let mut per_volume = HashMap::<usize, Totals>::new();
+	let locations = existing_locations
+		.into_iter()
+		.filter_map(|location| {
+			let totals = partials.get(&Some(location.id))?.totals;
+
+			if let Some(volume) = location
+				.path
+				.as_deref()
+				.and_then(|path| volume_of(&volumes, Path::new(path)))
+			{
+				per_volume.entry(volume).or_default().merge(totals);
+			}
+
+			Some(LocationBreakdown {
+				location_id: location.id,
+				name: location.name,
+				files: totals.files,
+				bytes: totals.bytes,
+			})
+		})
+		.collect();
+
+	let thumbnails_bytes =
+		get_size(get_thumbnails_directory(&node.data_dir).join(library.id.to_string()))
+			.await
+			.unwrap_or(0);
+
+	let mut volumes_breakdown = per_volume
+		.into_iter()
+		.map(|(index, Totals { files, bytes })| VolumeBreakdown {
+			fingerprint: volumes[index].fingerprint.clone(),
+			name: volumes[index].name.clone(),
+			files,
+			bytes,
+		})
+		.collect::<Vec<_>>();
+	volumes_breakdown.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+
+	Ok(LibraryBreakdown {
+		total_files: totals.files,
+		total_bytes: totals.bytes,
+		unique_bytes: totals.bytes - duplicate_bytes,
+		duplicate_bytes,
+		kinds: ObjectKind::iter()
+			.filter_map(|kind| {
+				let Totals { files, bytes } = kinds.get(&(kind as i32)).copied()?;
+
+				Some(KindBreakdown {
+					kind: kind as i32,
+					name: kind.to_string(),
+					files,
+					bytes,
+				})
+			})
+			.collect(),
+		locations,
+		volumes: volumes_breakdown,
+		thumbnails_bytes,
+		computed_at: Utc::now(),
+	})
+}
+
+/// The index of the innermost volume `path` is on
+fn volume_of(volumes: &[Volume], path: &Path) -> Option<usize> {
+	volumes
+		.iter()
+		.enumerate()
+		.filter(|(_, volume)| path.starts_with(&volume.mount_point))
+		.max_by_key(|(_, volume)| volume.mount_point.components().count())
+		.map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_duplicates_are_counted_across_locations() {
+		let kind = ObjectKind::Image as i32;
+
+		let mut first_location = Partial::default();
+		first_location.add(1, kind, Some(1), 10);
+		first_location.add(4, kind, Some(2), 40);
+
+		let mut second_location = Partial::default();
+		second_location.add(2, kind, Some(2), 20);
+		second_location.add(3, kind, Some(1), 30);
+		second_location.add(5, kind, None, 50);
+
+		let mut library = Partial::default();
+		library.merge(&first_location);
+		library.merge(&second_location);
+
+		assert_eq!(library.totals.files, 5);
+		assert_eq!(library.totals.bytes, 150);
+		assert_eq!(library.kinds[&kind].files, 5);
+		// The first file of object 1 is in the first location, of object 2 in the second one
+		assert_eq!(library.duplicate_bytes(), 30 + 40);
+	}
+}
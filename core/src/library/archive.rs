@@ -0,0 +1,394 @@
+//! Archives, a library packed in a single file to move it to another machine without pairing.
+//!
+//! An archive is a gzipped tar of a manifest, the library config and a snapshot of its database,
+//! locations, objects, tags, saved searches and sync operations included. The manifest has the
+//! version of the format and the blake3 checksum of the other entries, which are checked before
+//! anything is imported. The library keeps its id, so a machine that has it can't import it again.
+
+use crate::Node;
+
+use sd_utils::error::{FileIOError, NonUtf8PathError};
+
+use std::{
+	fs::File,
+	io::{self, BufReader, BufWriter},
+	path::{Path, PathBuf},
+	sync::Arc,
+};
+
+use chrono::{DateTime, Utc};
+use flate2::{bufread::GzDecoder, write::GzEncoder, Compression};
+use prisma_client_rust::{raw, PrismaValue};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tar::{Archive, Builder, Header};
+use tempfile::tempdir;
+use thiserror::Error;
+use tokio::{fs, task::spawn_blocking};
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+use super::{Library, LibraryConfig, LibraryConfigError, LibraryManagerError};
+
+/// Bumped whenever the layout changes, archives from newer versions aren't imported
+const VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const CONFIG: &str = "library.sdlibrary";
+const DATABASE: &str = "library.db";
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct ArchiveManifest {
+	pub version: u32,
+	pub library_id: Uuid,
+	pub library_name: String,
+	pub exported_at: DateTime<Utc>,
+	pub entries: Vec<ArchiveEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct ArchiveEntry {
+	pub name: String,
+	pub size: u64,
+	/// The blake3 hash of the entry, in hex
+	pub checksum: String,
+}
+
+#[derive(Error, Debug)]
+pub enum LibraryArchiveError {
+	#[error("library archive version {0} isn't supported, it's from a newer version of the app")]
+	UnsupportedVersion(u32),
+	#[error("library archive is missing its '{0}' entry")]
+	MissingEntry(&'static str),
+	#[error("library archive entry '{0}' doesn't match its checksum")]
+	ChecksumMismatch(String),
+	#[error("library already exists, remove it before importing it again")]
+	LibraryAlreadyExists,
+	#[error("malformed library archive manifest: {0}")]
+	Manifest(#[from] serde_json::Error),
+	#[error("database error: {0}")]
+	Database(#[from] prisma_client_rust::QueryError),
+	#[error("library archive task panicked")]
+	TaskPanicked(#[from] tokio::task::JoinError),
+
+	#[error(transparent)]
+	LibraryManager(#[from] LibraryManagerError),
+	#[error(transparent)]
+	LibraryConfig(#[from] LibraryConfigError),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+	#[error(transparent)]
+	NonUtf8Path(#[from] NonUtf8PathError),
+}
+
+impl From<LibraryArchiveError> for rspc::Error {
+	fn from(e: LibraryArchiveError) -> Self {
+		match e {
+			LibraryArchiveError::UnsupportedVersion(_)
+			| LibraryArchiveError::MissingEntry(_)
+			| LibraryArchiveError::ChecksumMismatch(_)
+			| LibraryArchiveError::Manifest(_) => {
+				Self::with_cause(rspc::ErrorCode::BadRequest, e.to_string(), e)
+			}
+			LibraryArchiveError::LibraryAlreadyExists => {
+				Self::new(rspc::ErrorCode::Conflict, e.to_string())
+			}
+			_ => Self::with_cause(rspc::ErrorCode::InternalServerError, e.to_string(), e),
+		}
+	}
+}
+
+/// Writes the archive of the library to `path`, returning its manifest
+#[instrument(skip(node, library), fields(library_id = %library.id), err)]
+pub async fn export(
+	node: &Node,
+	library: &Library,
+	path: PathBuf,
+) -> Result<ArchiveManifest, LibraryArchiveError> {
+	let temp_dir = tempdir().map_err(|e| {
+		FileIOError::from((
+			"/tmp",
+			e,
+			"Failed to get a temporary directory to export library",
+		))
+	})?;
+
+	// A consistent snapshot of the database, that's being written to meanwhile
+	let db_path = temp_dir.path().join(DATABASE);
+	library
+		.db
+		._execute_raw(raw!(
+			"VACUUM INTO {}",
+			PrismaValue::String(
+				db_path
+					.to_str()
+					.ok_or_else(|| NonUtf8PathError(db_path.clone().into_boxed_path()))?
+					.to_string()
+			)
+		))
+		.exec()
+		.await?;
+
+	let config_path = node
+		.libraries
+		.libraries_dir
+		.join(format!("{}.sdlibrary", library.id));
+
+	let library_id = library.id;
+	let library_name = library.config().await.name.to_string();
+
+	let manifest = spawn_blocking(move || {
+		let entries = [(CONFIG, config_path), (DATABASE, db_path)];
+
+		let manifest = ArchiveManifest {
+			version: VERSION,
+			library_id,
+			library_name,
+			exported_at: Utc::now(),
+			entries: entries
+				.iter()
+				.map(|(name, path)| {
+					checksum(path).map(|(size, checksum)| ArchiveEntry {
+						name: (*name).to_string(),
+						size,
+						checksum,
+					})
+				})
+				.collect::<Result<_, _>>()?,
+		};
+
+		write_archive(&path, &manifest, &entries)?;
+
+		Ok::<_, LibraryArchiveError>(manifest)
+	})
+	.await??;
+
+	info!(entries = manifest.entries.len(), "Exported library;");
+
+	Ok(manifest)
+}
+
+fn write_archive(
+	path: &Path,
+	manifest: &ArchiveManifest,
+	entries: &[(&str, PathBuf)],
+) -> Result<(), LibraryArchiveError> {
+	let manifest_bytes = serde_json::to_vec_pretty(manifest)?;
+
+	let file = File::create(path)
+		.map_err(|e| FileIOError::from((path, e, "Failed to create library archive")))?;
+	let mut tar = Builder::new(GzEncoder::new(BufWriter::new(file), Compression::default()));
+
+	let mut header = Header::new_gnu();
+	header.set_size(manifest_bytes.len() as u64);
+	header.set_mode(0o644);
+	header.set_mtime(u64::try_from(manifest.exported_at.timestamp()).unwrap_or_default());
+	header.set_cksum();
+
+	tar.append_data(&mut header, MANIFEST, manifest_bytes.as_slice())
+		.map_err(|e| FileIOError::from((path, e, "Failed to write library archive manifest")))?;
+
+	for (name, entry_path) in entries {
+		tar.append_path_with_name(entry_path, name)
+			.map_err(|e| FileIOError::from((path, e, "Failed to write library archive entry")))?;
+	}
+
+	tar.into_inner()
+		.and_then(GzEncoder::finish)
+		.and_then(|mut writer| io::Write::flush(&mut writer))
+		.map_err(|e| FileIOError::from((path, e, "Failed to finish library archive")))?;
+
+	Ok(())
+}
+
+/// Loads the library of the archive at `path`, after checking all of its entries, `read_only`
+/// opens it as a guest library
+#[instrument(skip(node), err)]
+pub async fn import(
+	node: &Arc<Node>,
+	path: PathBuf,
+	read_only: bool,
+) -> Result<Arc<Library>, LibraryArchiveError> {
+	let temp_dir = tempdir().map_err(|e| {
+		FileIOError::from((
+			"/tmp",
+			e,
+			"Failed to get a temporary directory to import library",
+		))
+	})?;
+	let temp_dir_path = temp_dir.path().to_path_buf();
+
+	let manifest = read_archive(&path, &temp_dir_path).await?;
+
+	let id = manifest.library_id;
+	let config_path = node.libraries.libraries_dir.join(format!("{id}.sdlibrary"));
+	let db_path = node.libraries.libraries_dir.join(format!("{id}.db"));
+
+	if node.libraries.get_library(&id).await.is_some()
+		|| fs::try_exists(&config_path).await.unwrap_or(true)
+	{
+		return Err(LibraryArchiveError::LibraryAlreadyExists);
+	}
+
+	for (name, restored_path) in [(CONFIG, &config_path), (DATABASE, &db_path)] {
+		fs::copy(temp_dir.path().join(name), restored_path)
+			.await
+			.map_err(|e| {
+				FileIOError::from((restored_path, e, "Failed to restore library archive entry"))
+			})?;
+	}
+
+	let loaded = async {
+		LibraryConfig::set_read_only(&config_path, read_only).await?;
+
+		Ok::<_, LibraryArchiveError>(
+			node.libraries
+				.load(id, &db_path, &config_path, None, None, true, node)
+				.await?,
+		)
+	}
+	.await;
+
+	match loaded {
+		Ok(library) => {
+			info!(%id, "Imported library;");
+
+			Ok(library)
+		}
+		Err(e) => {
+			// Leaving nothing behind, so importing it again isn't taken as a duplicate
+			let _ = fs::remove_file(&config_path).await;
+			let _ = fs::remove_file(&db_path).await;
+
+			Err(e)
+		}
+	}
+}
+
+/// Unpacks the archive in `dir`, returning its manifest once every entry matches it
+async fn read_archive(path: &Path, dir: &Path) -> Result<ArchiveManifest, LibraryArchiveError> {
+	spawn_blocking({
+		let (path, dir) = (path.to_path_buf(), dir.to_path_buf());
+		move || unpack(&path, &dir)
+	})
+	.await??;
+
+	let manifest = serde_json::from_slice::<ArchiveManifest>(
+		&fs::read(dir.join(MANIFEST))
+			.await
+			.map_err(|_| LibraryArchiveError::MissingEntry(MANIFEST))?,
+	)?;
+
+	if manifest.version > VERSION {
+		return Err(LibraryArchiveError::UnsupportedVersion(manifest.version));
+	}
+
+	for name in [CONFIG, DATABASE] {
+		if !manifest.entries.iter().any(|entry| entry.name == name) {
+			return Err(LibraryArchiveError::MissingEntry(name));
+		}
+	}
+
+	let dir = dir.to_path_buf();
+	spawn_blocking(move || {
+		for entry in &manifest.entries {
+			if checksum(&dir.join(&entry.name))? != (entry.size, entry.checksum.clone()) {
+				return Err(LibraryArchiveError::ChecksumMismatch(entry.name.clone()));
+			}
+		}
+
+		Ok(manifest)
+	})
+	.await?
+}
+
+/// Unpacks the archive at `path` in `dir`. Blocking.
+fn unpack(path: &Path, dir: &Path) -> Result<(), FileIOError> {
+	let file = File::open(path)
+		.map_err(|e| FileIOError::from((path, e, "Failed to open library archive")))?;
+
+	Archive::new(GzDecoder::new(BufReader::new(file)))
+		.unpack(dir)
+		.map_err(|e| FileIOError::from((path, e, "Failed to unpack library archive")))
+}
+
+/// The size and blake3 hash of a file
+fn checksum(path: &Path) -> Result<(u64, String), FileIOError> {
+	let mut file = File::open(path)
+		.map_err(|e| FileIOError::from((path, e, "Failed to open library archive entry")))?;
+
+	let mut hasher = blake3::Hasher::new();
+	let size = io::copy(&mut file, &mut hasher)
+		.map_err(|e| FileIOError::from((path, e, "Failed to hash library archive entry")))?;
+
+	Ok((size, hasher.finalize().to_hex().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn archive_of(dir: &Path, db: &[u8]) -> (ArchiveManifest, PathBuf) {
+		let entries = [(CONFIG, dir.join("config")), (DATABASE, dir.join("db"))];
+		std::fs::write(&entries[0].1, b"{}").unwrap();
+		std::fs::write(&entries[1].1, db).unwrap();
+
+		let manifest = ArchiveManifest {
+			version: VERSION,
+			library_id: Uuid::new_v4(),
+			library_name: "Test Library".to_string(),
+			exported_at: Utc::now(),
+			entries: entries
+				.iter()
+				.map(|(name, path)| {
+					let (size, checksum) = checksum(path).unwrap();
+					ArchiveEntry {
+						name: (*name).to_string(),
+						size,
+						checksum,
+					}
+				})
+				.collect(),
+		};
+
+		let path = dir.join("library.sdlibrary");
+		write_archive(&path, &manifest, &entries).unwrap();
+
+		(manifest, path)
+	}
+
+	#[tokio::test]
+	async fn test_archive_roundtrip() {
+		let dir = tempdir().unwrap();
+		let (manifest, path) = archive_of(dir.path(), b"database");
+
+		let unpacked = tempdir().unwrap();
+		let read = read_archive(&path, unpacked.path()).await.unwrap();
+
+		assert_eq!(read.library_id, manifest.library_id);
+		assert_eq!(
+			fs::read(unpacked.path().join(DATABASE)).await.unwrap(),
+			b"database"
+		);
+	}
+
+	#[tokio::test]
+	async fn test_archive_checksum_mismatch() {
+		let dir = tempdir().unwrap();
+		let (mut manifest, _) = archive_of(dir.path(), b"database");
+
+		manifest.entries[1].checksum = blake3::hash(b"other").to_hex().to_string();
+		let entries = [
+			(CONFIG, dir.path().join("config")),
+			(DATABASE, dir.path().join("db")),
+		];
+		let path = dir.path().join("tampered.sdlibrary");
+		write_archive(&path, &manifest, &entries).unwrap();
+
+		let unpacked = tempdir().unwrap();
+		assert!(matches!(
+			read_archive(&path, unpacked.path()).await,
+			Err(LibraryArchiveError::ChecksumMismatch(name)) if name == DATABASE
+		));
+	}
+}
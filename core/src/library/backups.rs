@@ -0,0 +1,649 @@
+//! Backups, snapshots of a library kept in the data directory of the node to restore it from.
+//!
+//! A backup is a [`Header`] followed by a gzipped tar of the library config and a snapshot of its
+//! database, taken with `VACUUM INTO` so it's consistent while the library is in use. The header
+//! has the blake3 checksum of what follows it, checked once the backup is written and again before
+//! it's restored, the backups from before checksums are restored as they are. On top of the ones
+//! made by hand, every library is backed up on the schedule of the core config, and only the latest
+//! scheduled backups are kept, up to its retention.
+
+use crate::{
+	invalidate_query,
+	node::{Replay, Topic},
+	Node,
+};
+
+use sd_utils::error::{FileIOError, NonUtf8PathError};
+
+use std::{
+	cmp,
+	fs::File,
+	io::{self, BufReader, BufWriter, Read, Write},
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use flate2::{bufread::GzDecoder, write::GzEncoder, Compression};
+use prisma_client_rust::{raw, PrismaValue};
+use serde::{Serialize, Serializer};
+use specta::Type;
+use tar::{Archive, Builder};
+use tempfile::tempdir;
+use thiserror::Error;
+use tokio::{fs, task::spawn_blocking, time::timeout};
+use tracing::{debug, info, instrument, warn};
+use uuid::Uuid;
+
+use super::{Library, LibraryConfig, LibraryConfigError, LibraryManagerError};
+
+/// In the data directory of the node
+pub const BACKUPS_DIR: &str = "backups";
+
+/// How often the libraries are checked for a scheduled backup, the ones missed while the node was
+/// off are made on the first check after
+const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+const MAGIC_V1: &[u8; 6] = b"sdbkp1";
+/// Adds the checksum and whether the backup was scheduled
+const MAGIC: &[u8; 6] = b"sdbkp2";
+
+const CONFIG: &str = "library.sdlibrary";
+const DATABASE: &str = "library.db";
+
+#[derive(Error, Debug)]
+pub enum BackupError {
+	#[error("library manager error: {0}")]
+	LibraryManager(#[from] LibraryManagerError),
+	#[error("malformed header")]
+	MalformedHeader,
+	#[error("backup doesn't match its checksum, it's corrupted")]
+	ChecksumMismatch,
+	#[error("Library already exists, please remove it and try again!")]
+	LibraryAlreadyExists,
+	#[error("database error: {0}")]
+	Database(#[from] prisma_client_rust::QueryError),
+	#[error("backup task panicked")]
+	TaskPanicked(#[from] tokio::task::JoinError),
+
+	#[error(transparent)]
+	LibraryConfig(#[from] LibraryConfigError),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+	#[error(transparent)]
+	NonUtf8Path(#[from] NonUtf8PathError),
+}
+
+impl From<BackupError> for rspc::Error {
+	fn from(e: BackupError) -> Self {
+		match e {
+			BackupError::MalformedHeader | BackupError::ChecksumMismatch => {
+				Self::with_cause(rspc::ErrorCode::BadRequest, e.to_string(), e)
+			}
+			BackupError::LibraryAlreadyExists => {
+				Self::new(rspc::ErrorCode::Conflict, e.to_string())
+			}
+			_ => Self::with_cause(rspc::ErrorCode::InternalServerError, e.to_string(), e),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Type)]
+pub struct Header {
+	// Backup unique id
+	pub id: Uuid,
+	// Time since epoch the backup was created at
+	#[specta(type = String)]
+	#[serde(serialize_with = "as_string")]
+	pub timestamp: u128,
+	// Library id
+	pub library_id: Uuid,
+	// Library display name
+	pub library_name: String,
+	/// Made on the schedule, only these are rotated
+	pub scheduled: bool,
+	/// The blake3 hash of the archive after the header in hex, `null` for the older backups
+	#[specta(type = Option<String>)]
+	#[serde(serialize_with = "as_hex")]
+	pub checksum: Option<[u8; blake3::OUT_LEN]>,
+}
+
+fn as_string<T: ToString, S>(x: &T, s: S) -> Result<S::Ok, S::Error>
+where
+	S: Serializer,
+{
+	s.serialize_str(&x.to_string())
+}
+
+fn as_hex<S>(checksum: &Option<[u8; blake3::OUT_LEN]>, s: S) -> Result<S::Ok, S::Error>
+where
+	S: Serializer,
+{
+	match checksum {
+		Some(checksum) => s.serialize_str(blake3::Hash::from(*checksum).to_hex().as_str()),
+		None => s.serialize_none(),
+	}
+}
+
+impl Header {
+	fn write(&self, file: &mut impl Write) -> Result<(), io::Error> {
+		file.write_all(MAGIC)?;
+		file.write_all(&self.id.to_bytes_le())?;
+		file.write_all(&self.timestamp.to_le_bytes())?;
+		file.write_all(&self.library_id.to_bytes_le())?;
+		file.write_all(&[u8::from(self.scheduled)])?;
+		file.write_all(&self.checksum.unwrap_or_default())?;
+		{
+			let bytes = &self.library_name.as_bytes()
+				[..cmp::min(u32::MAX as usize, self.library_name.len())];
+			file.write_all(&(bytes.len() as u32).to_le_bytes())?;
+			file.write_all(bytes)?;
+		}
+
+		Ok(())
+	}
+
+	/// Leaves the reader at the start of the archive
+	fn read(file: &mut impl Read, path: impl AsRef<Path>) -> Result<Self, BackupError> {
+		let path = path.as_ref();
+		let io_err = |e: io::Error| FileIOError::from((path, e, "Failed to read backup header"));
+
+		let mut magic = [0u8; 6];
+		file.read_exact(&mut magic).map_err(io_err)?;
+		let has_checksum = match &magic {
+			MAGIC => true,
+			MAGIC_V1 => false,
+			_ => return Err(BackupError::MalformedHeader),
+		};
+
+		let mut buf = [0u8; 16 + 16 + 16];
+		file.read_exact(&mut buf).map_err(io_err)?;
+
+		let (scheduled, checksum) = if has_checksum {
+			let mut buf = [0u8; 1 + blake3::OUT_LEN];
+			file.read_exact(&mut buf).map_err(io_err)?;
+
+			(
+				buf[0] != 0,
+				Some(buf[1..].try_into().expect("we read the right amount")),
+			)
+		} else {
+			(false, None)
+		};
+
+		let library_name = {
+			let mut len = [0u8; 4];
+			file.read_exact(&mut len).map_err(io_err)?;
+
+			let mut name = vec![0; u32::from_le_bytes(len) as usize];
+			file.read_exact(&mut name).map_err(io_err)?;
+
+			String::from_utf8(name).map_err(|_| BackupError::MalformedHeader)?
+		};
+
+		Ok(Self {
+			id: Uuid::from_bytes_le(buf[..16].try_into().expect("we read the right amount")),
+			timestamp: u128::from_le_bytes(
+				buf[16..32].try_into().expect("we read the right amount"),
+			),
+			library_id: Uuid::from_bytes_le(
+				buf[32..48].try_into().expect("we read the right amount"),
+			),
+			library_name,
+			scheduled,
+			checksum,
+		})
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct Backup {
+	#[serde(flatten)]
+	pub header: Header,
+	pub path: PathBuf,
+}
+
+/// Published while a backup is restored, new subscribers get the restores still going on
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct RestoreProgress {
+	pub path: PathBuf,
+	pub step: RestoreStep,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum RestoreStep {
+	Verifying,
+	Unpacking,
+	Copying,
+	Loading,
+	Done,
+	Failed { error: String },
+}
+
+impl Topic for RestoreProgress {
+	const NAME: &'static str = "backups.restore";
+
+	fn replay(&self) -> Replay {
+		let key = self.path.to_string_lossy().to_string();
+		match self.step {
+			RestoreStep::Done | RestoreStep::Failed { .. } => Replay::Forget(key),
+			_ => Replay::Keep(key),
+		}
+	}
+}
+
+/// The backups in `dir`, newest first, the files that aren't backups are skipped
+pub async fn list(dir: impl AsRef<Path>) -> Result<Vec<Backup>, BackupError> {
+	let dir = dir.as_ref().to_path_buf();
+
+	spawn_blocking(move || {
+		let read_dir = match std::fs::read_dir(&dir) {
+			Ok(read_dir) => read_dir,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+			Err(e) => {
+				return Err(FileIOError::from((&dir, e, "Failed to read backups directory")).into())
+			}
+		};
+
+		let mut backups = vec![];
+
+		for entry in read_dir {
+			let path = entry
+				.map_err(|e| FileIOError::from((&dir, e, "Failed to read next entry to backup")))?
+				.path();
+			if !path.is_file() {
+				continue;
+			}
+
+			match open(&path).and_then(|mut file| Header::read(&mut file, &path)) {
+				Ok(header) => backups.push(Backup { header, path }),
+				Err(e) => debug!(?e, path = %path.display(), "Skipped a file that isn't a backup;"),
+			}
+		}
+
+		backups.sort_by(|a, b| b.header.timestamp.cmp(&a.header.timestamp));
+
+		Ok(backups)
+	})
+	.await?
+}
+
+/// Backs the library up in the backups directory, checking the backup once it's written
+#[instrument(skip(node, library), fields(library_id = %library.id), err)]
+pub async fn create(
+	node: &Node,
+	library: &Library,
+	id: Uuid,
+	scheduled: bool,
+) -> Result<Backup, BackupError> {
+	let backups_dir = node.data_dir.join(BACKUPS_DIR);
+	fs::create_dir_all(&backups_dir)
+		.await
+		.map_err(|e| FileIOError::from((&backups_dir, e)))?;
+
+	let temp_dir = tempdir().map_err(|e| {
+		FileIOError::from((
+			"/tmp",
+			e,
+			"Failed to get a temporary directory to do a backup",
+		))
+	})?;
+
+	// A consistent snapshot of the database, that's being written to meanwhile
+	let db_path = temp_dir.path().join(DATABASE);
+	library
+		.db
+		._execute_raw(raw!(
+			"VACUUM INTO {}",
+			PrismaValue::String(
+				db_path
+					.to_str()
+					.ok_or_else(|| NonUtf8PathError(db_path.clone().into_boxed_path()))?
+					.to_string()
+			)
+		))
+		.exec()
+		.await?;
+
+	let mut header = Header {
+		id,
+		timestamp: now(),
+		library_id: library.id,
+		library_name: library.config().await.name.to_string(),
+		scheduled,
+		checksum: None,
+	};
+
+	let config_path = node
+		.libraries
+		.libraries_dir
+		.join(format!("{}.sdlibrary", library.id));
+	let path = backups_dir.join(format!("{id}.bkp"));
+
+	let backup = spawn_blocking(move || {
+		let archive_path = temp_dir.path().join("backup.tar.gz");
+		write_archive(&archive_path, &[(CONFIG, config_path), (DATABASE, db_path)])?;
+		header.checksum = Some(checksum(&mut open(&archive_path)?, &archive_path)?);
+
+		// Header. We do this so the file is self-sufficient.
+		let mut archive = open(&archive_path)?;
+		let mut file = BufWriter::new(
+			File::create(&path)
+				.map_err(|e| FileIOError::from((&path, e, "Failed to create backup file")))?,
+		);
+		header
+			.write(&mut file)
+			.and_then(|()| io::copy(&mut archive, &mut file))
+			.and_then(|_| file.flush())
+			.map_err(|e| FileIOError::from((&path, e, "Failed to write backup file")))?;
+
+		Ok::<_, BackupError>(Backup { header, path })
+	})
+	.await??;
+
+	if let Err(e) = verify(&backup.path).await {
+		let _ = fs::remove_file(&backup.path).await;
+		return Err(e);
+	}
+
+	Ok(backup)
+}
+
+fn write_archive(path: &Path, entries: &[(&str, PathBuf)]) -> Result<(), FileIOError> {
+	let file =
+		File::create(path).map_err(|e| FileIOError::from((path, e, "Failed to create backup")))?;
+	let mut tar = Builder::new(GzEncoder::new(BufWriter::new(file), Compression::default()));
+
+	for (name, entry_path) in entries {
+		tar.append_path_with_name(entry_path, name).map_err(|e| {
+			FileIOError::from((
+				entry_path,
+				e,
+				"Failed to append file to out backup tar.gz file",
+			))
+		})?;
+	}
+
+	tar.into_inner()
+		.and_then(GzEncoder::finish)
+		.and_then(|mut writer| writer.flush())
+		.map_err(|e| FileIOError::from((path, e, "Failed to finish backup tar.gz file")))
+}
+
+/// Checks the backup at `path` against the checksum in its header, returning the header
+pub async fn verify(path: impl AsRef<Path>) -> Result<Header, BackupError> {
+	let path = path.as_ref().to_path_buf();
+
+	spawn_blocking(move || {
+		let mut file = open(&path)?;
+		let header = Header::read(&mut file, &path)?;
+
+		match header.checksum {
+			Some(expected) if checksum(&mut file, &path)? != expected => {
+				Err(BackupError::ChecksumMismatch)
+			}
+			_ => Ok(header),
+		}
+	})
+	.await?
+}
+
+/// Restores the library of the backup at `path`, publishing a [`RestoreProgress`] for every step,
+/// `read_only` opens it as a guest library
+#[instrument(skip(node), err)]
+pub async fn restore(
+	node: &Arc<Node>,
+	path: PathBuf,
+	read_only: bool,
+) -> Result<Header, BackupError> {
+	let progress = node.events.publisher::<RestoreProgress>();
+	let step = |step| {
+		progress.send(RestoreProgress {
+			path: path.clone(),
+			step,
+		})
+	};
+
+	let res = restore_backup(node, &path, read_only, &step).await;
+
+	match &res {
+		Ok(Header { id, library_id, .. }) => {
+			info!(%id, %library_id, "Restored backup for library!");
+			step(RestoreStep::Done);
+		}
+		Err(e) => step(RestoreStep::Failed {
+			error: e.to_string(),
+		}),
+	}
+
+	res
+}
+
+async fn restore_backup(
+	node: &Arc<Node>,
+	path: &Path,
+	read_only: bool,
+	step: &impl Fn(RestoreStep),
+) -> Result<Header, BackupError> {
+	step(RestoreStep::Verifying);
+	let header = verify(path).await?;
+
+	// TODO: Actually handle restoring into a library that exists. For now it's easier to error out.
+	let None = node.libraries.get_library(&header.library_id).await else {
+		return Err(BackupError::LibraryAlreadyExists);
+	};
+
+	step(RestoreStep::Unpacking);
+	let temp_dir = tempdir().map_err(|e| {
+		FileIOError::from((
+			"/tmp",
+			e,
+			"Failed to get a temporary directory to restore backup",
+		))
+	})?;
+
+	let backup_path = path.to_path_buf();
+	let temp_dir_path = temp_dir.path().to_path_buf();
+	spawn_blocking(move || {
+		let mut file = open(&backup_path)?;
+		Header::read(&mut file, &backup_path)?;
+
+		Archive::new(GzDecoder::new(file))
+			.unpack(&temp_dir_path)
+			.map_err(|e| {
+				FileIOError::from((&temp_dir_path, e, "Failed to unpack backup compressed data"))
+			})?;
+
+		Ok::<_, BackupError>(())
+	})
+	.await??;
+
+	step(RestoreStep::Copying);
+	let library_config_restored_path = node
+		.libraries
+		.libraries_dir
+		.join(format!("{}.sdlibrary", header.library_id));
+	let db_restored_path = node
+		.libraries
+		.libraries_dir
+		.join(format!("{}.db", header.library_id));
+
+	for (name, restored_path) in [
+		(CONFIG, &library_config_restored_path),
+		(DATABASE, &db_restored_path),
+	] {
+		fs::copy(temp_dir.path().join(name), restored_path)
+			.await
+			.map_err(|e| {
+				FileIOError::from((restored_path, e, "Failed to restore file from backup"))
+			})?;
+	}
+	LibraryConfig::set_read_only(&library_config_restored_path, read_only).await?;
+
+	step(RestoreStep::Loading);
+	node.libraries
+		.load(
+			header.library_id,
+			db_restored_path,
+			library_config_restored_path,
+			None,
+			None,
+			true,
+			node,
+		)
+		.await?;
+
+	Ok(header)
+}
+
+/// Backs every library up on the schedule of the core config, see the [module docs](self)
+pub(crate) fn start(node: &Arc<Node>) {
+	let node = Arc::clone(node);
+	let mut config = node.core_config.watch();
+	tokio::spawn(async move {
+		loop {
+			let backups = config.borrow_and_update().backups.clone();
+			let Some(interval) = backups.interval() else {
+				// Turned off, waiting for it to be turned on again
+				if config.changed().await.is_err() {
+					break;
+				}
+				continue;
+			};
+
+			for library in node.libraries.get_all().await {
+				if let Err(e) = back_up_if_due(&node, &library, interval, backups.retention).await {
+					warn!(?e, library_id = %library.id, "Failed to make a scheduled backup;");
+				}
+			}
+
+			// Checking again right away when the schedule changes
+			if let Ok(Err(_)) = timeout(CHECK_INTERVAL, config.changed()).await {
+				break;
+			}
+		}
+	});
+}
+
+async fn back_up_if_due(
+	node: &Node,
+	library: &Library,
+	interval: Duration,
+	retention: u32,
+) -> Result<(), BackupError> {
+	let mut backups = list(node.data_dir.join(BACKUPS_DIR))
+		.await?
+		.into_iter()
+		.filter(|backup| backup.header.scheduled && backup.header.library_id == library.id)
+		.collect::<Vec<_>>();
+
+	if !is_due(&backups, interval, now()) {
+		return Ok(());
+	}
+
+	let backup = create(node, library, Uuid::new_v4(), true).await?;
+	info!(backup_id = %backup.header.id, "Made a scheduled backup;");
+	backups.insert(0, backup);
+
+	for expired in backups.iter().skip(retention as usize) {
+		match fs::remove_file(&expired.path).await {
+			Ok(()) => debug!(backup_id = %expired.header.id, "Removed an expired backup;"),
+			Err(e) => {
+				warn!(?e, path = %expired.path.display(), "Failed to remove an expired backup;")
+			}
+		}
+	}
+
+	invalidate_query!(library, "backups.getAll");
+	invalidate_query!(library, "backups.list");
+
+	Ok(())
+}
+
+/// Whether the latest of the scheduled backups, newest first, is older than the interval
+fn is_due(backups: &[Backup], interval: Duration, now: u128) -> bool {
+	backups.first().map_or(true, |latest| {
+		now.saturating_sub(latest.header.timestamp) >= interval.as_millis()
+	})
+}
+
+/// Milliseconds since the epoch
+fn now() -> u128 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.expect("Time went backwards")
+		.as_millis()
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, BackupError> {
+	File::open(path)
+		.map(BufReader::new)
+		.map_err(|e| FileIOError::from((path, e, "Failed to open backup file")).into())
+}
+
+/// The blake3 hash of what's left of the reader
+fn checksum(file: &mut impl Read, path: &Path) -> Result<[u8; blake3::OUT_LEN], FileIOError> {
+	let mut hasher = blake3::Hasher::new();
+	io::copy(file, &mut hasher)
+		.map_err(|e| FileIOError::from((path, e, "Failed to hash backup file")))?;
+
+	Ok(*hasher.finalize().as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn header() -> Header {
+		Header {
+			id: Uuid::new_v4(),
+			timestamp: 1234567890,
+			library_id: Uuid::new_v4(),
+			library_name: "Test Library".to_string(),
+			scheduled: true,
+			checksum: Some(*blake3::hash(b"archive").as_bytes()),
+		}
+	}
+
+	#[test]
+	fn test_backup_header() {
+		let original = header();
+
+		let mut buf = Vec::new();
+		original.write(&mut buf).unwrap();
+
+		let decoded = Header::read(&mut buf.as_slice(), "").unwrap();
+		assert_eq!(original, decoded);
+	}
+
+	#[test]
+	fn test_backup_header_v1() {
+		let original = header();
+
+		let mut buf = MAGIC_V1.to_vec();
+		buf.extend(original.id.to_bytes_le());
+		buf.extend(original.timestamp.to_le_bytes());
+		buf.extend(original.library_id.to_bytes_le());
+		buf.extend((original.library_name.len() as u32).to_le_bytes());
+		buf.extend(original.library_name.as_bytes());
+
+		let decoded = Header::read(&mut buf.as_slice(), "").unwrap();
+		assert_eq!(decoded.library_name, original.library_name);
+		assert!(!decoded.scheduled);
+		assert_eq!(decoded.checksum, None);
+	}
+
+	#[test]
+	fn test_backup_is_due() {
+		let day = Duration::from_secs(24 * 60 * 60);
+		let backups = [Backup {
+			header: header(),
+			path: PathBuf::new(),
+		}];
+
+		assert!(is_due(&[], day, 0));
+		assert!(!is_due(&backups, day, 1234567890 + day.as_millis() - 1));
+		assert!(is_due(&backups, day, 1234567890 + day.as_millis()));
+	}
+}
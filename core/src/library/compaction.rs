@@ -0,0 +1,44 @@
+use crate::Node;
+
+use sd_core_sync::compaction::compact;
+
+use std::sync::Arc;
+
+use tokio::time::sleep;
+use tracing::{debug, warn};
+
+/// Compacts the sync operations of every library once per compaction interval
+pub(crate) fn start(node: &Arc<Node>) {
+	let node = Arc::clone(node);
+	let mut config = node.core_config.watch();
+	tokio::spawn(async move {
+		loop {
+			let Some(interval) = config.borrow_and_update().sync.compaction_interval() else {
+				// Turned off, waiting for it to be turned on again
+				if config.changed().await.is_err() {
+					break;
+				}
+				continue;
+			};
+			sleep(interval).await;
+
+			let retention = config.borrow().sync.retention();
+			for library in node.libraries.get_all().await {
+				if library.is_read_only().await {
+					continue;
+				}
+
+				match compact(&library.sync, retention).await {
+					Ok(report) => debug!(
+						library_id = %library.id,
+						removed = report.removed,
+						"Compacted sync operations;",
+					),
+					Err(e) => {
+						warn!(?e, library_id = %library.id, "Failed to compact sync operations;")
+					}
+				}
+			}
+		}
+	});
+}
@@ -42,6 +42,12 @@ pub struct LibraryConfig {
 	// true = sync is enabled as either the library is new or it has been manually toggled on
 	#[serde(default)]
 	pub generate_sync_operations: Arc<AtomicBool>,
+	#[serde(default)]
+	pub sync: SyncConfig,
+	/// Guest libraries, opened from a backup or an archive to be looked through without changing
+	/// them
+	#[serde(default)]
+	pub read_only: bool,
 	version: LibraryConfigVersion,
 
 	#[serde(skip, default)]
@@ -50,6 +56,14 @@ pub struct LibraryConfig {
 	pub cloud_email_address: Option<String>,
 }
 
+/// What of the library is synced with its other devices
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type)]
+pub struct SyncConfig {
+	/// The models whose sync is turned off, by name like `tag`
+	#[serde(default)]
+	pub disabled_models: Vec<String>,
+}
+
 #[derive(
 	IntEnum,
 	Debug,
@@ -101,6 +115,8 @@ impl LibraryConfig {
 			version: Self::LATEST_VERSION,
 			cloud_id: None,
 			generate_sync_operations: Arc::new(AtomicBool::new(false)),
+			sync: SyncConfig::default(),
+			read_only: false,
 			config_path: path.as_ref().to_path_buf(),
 			cloud_email_address: None,
 		};
@@ -439,6 +455,26 @@ impl LibraryConfig {
 		Ok(loaded_config)
 	}
 
+	/// Sets if the library of the config at `path` is read-only, before it's loaded so a read-only
+	/// one never starts watching its locations or running its jobs
+	pub(crate) async fn set_read_only(
+		path: impl AsRef<Path>,
+		read_only: bool,
+	) -> Result<(), LibraryConfigError> {
+		let path = path.as_ref();
+
+		let mut config = serde_json::from_slice::<Map<String, Value>>(
+			&fs::read(path)
+				.await
+				.map_err(|e| FileIOError::from((path, e)))?,
+		)?;
+		config.insert("read_only".to_string(), Value::Bool(read_only));
+
+		fs::write(path, &serde_json::to_vec(&config)?)
+			.await
+			.map_err(|e| FileIOError::from((path, e)).into())
+	}
+
 	pub(crate) async fn save(&self, path: impl AsRef<Path>) -> Result<(), LibraryConfigError> {
 		let path = path.as_ref();
 		fs::write(path, &serde_json::to_vec(self)?)
@@ -25,7 +25,7 @@ use tokio::{fs, io, sync::broadcast, sync::RwLock};
 use tracing::{debug, warn};
 use uuid::Uuid;
 
-use super::{LibraryConfig, LibraryManagerError};
+use super::{aggregator::Aggregator, LibraryConfig, LibraryManagerError};
 
 pub struct Library {
 	/// id holds the ID of the current library.
@@ -51,6 +51,9 @@ pub struct Library {
 
 	pub cloud_sync_state: CloudSyncActorsState,
 	pub cloud_sync_actors: ActorsCollection<CloudSyncActors>,
+
+	/// The statistics of the library for its dashboard
+	pub aggregator: Aggregator,
 }
 
 impl Debug for Library {
@@ -87,6 +90,7 @@ impl Library {
 			event_bus_tx: node.event_bus.0.clone(),
 			cloud_sync_state: CloudSyncActorsState::default(),
 			cloud_sync_actors: ActorsCollection::default(),
+			aggregator: Aggregator::default(),
 		})
 	}
 
@@ -95,6 +99,11 @@ impl Library {
 		node: &Node,
 		sync_group_pub_id: groups::PubId,
 	) -> Result<(), LibraryManagerError> {
+		// Its sync operations, backfill and ingested changes would all write to it
+		if self.is_read_only().await {
+			return Err(LibraryManagerError::ReadOnly);
+		}
+
 		let rng = CryptoRng::from_seed(node.master_rng.lock().await.generate_fixed());
 
 		self.update_config(|config| {
@@ -121,13 +130,21 @@ impl Library {
 		)
 		.await?;
 
-		(
-			self.cloud_sync_actors.start(CloudSyncActors::Sender),
-			self.cloud_sync_actors.start(CloudSyncActors::Receiver),
-			self.cloud_sync_actors.start(CloudSyncActors::Ingester),
-		)
-			.join()
-			.await;
+		// On a metered network the sender and receiver are started once it isn't anymore
+		if node.metered.holds_sync() {
+			node.metered.hold_back(self.id);
+			self.cloud_sync_actors
+				.start(CloudSyncActors::Ingester)
+				.await;
+		} else {
+			(
+				self.cloud_sync_actors.start(CloudSyncActors::Sender),
+				self.cloud_sync_actors.start(CloudSyncActors::Receiver),
+				self.cloud_sync_actors.start(CloudSyncActors::Ingester),
+			)
+				.join()
+				.await;
+		}
 
 		debug!(library_id = %self.id, "Started cloud sync actors");
 
@@ -138,6 +155,12 @@ impl Library {
 		self.config.read().await.clone()
 	}
 
+	/// Read-only libraries can be looked through but not changed, their mutations are rejected,
+	/// their jobs don't run, their locations aren't watched and their database refuses writes
+	pub async fn is_read_only(&self) -> bool {
+		self.config.read().await.read_only
+	}
+
 	pub async fn update_config(
 		&self,
 		update_fn: impl FnOnce(&mut LibraryConfig),
@@ -43,6 +43,8 @@ pub enum LibraryManagerError {
 	MissingField(#[from] MissingFieldError),
 	#[error("Error in volumes: {0}")]
 	VolumeError(#[from] volume::VolumeError),
+	#[error("library is read-only, it can't sync")]
+	ReadOnly,
 
 	#[error(transparent)]
 	FileIO(#[from] FileIOError),
@@ -57,7 +59,10 @@ pub enum LibraryManagerError {
 impl From<LibraryManagerError> for rspc::Error {
 	fn from(error: LibraryManagerError) -> Self {
 		rspc::Error::with_cause(
-			rspc::ErrorCode::InternalServerError,
+			match error {
+				LibraryManagerError::ReadOnly => rspc::ErrorCode::Forbidden,
+				_ => rspc::ErrorCode::InternalServerError,
+			},
 			error.to_string(),
 			error,
 		)
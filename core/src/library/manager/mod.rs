@@ -47,7 +47,7 @@ mod error;
 
 pub mod pragmas;
 
-use pragmas::configure_pragmas;
+use pragmas::{configure_pragmas, set_query_only};
 
 pub use error::*;
 
@@ -62,6 +62,11 @@ pub enum LibraryManagerEvent {
 }
 
 /// is a singleton that manages all libraries for a node.
+///
+/// Every library of `libraries_dir` is loaded on startup and stays open next to the others, with
+/// its own database, sync manager and actors, location watchers and job queue. Switching libraries
+/// in the frontend doesn't tear anything down, the procedures take the id of the library they're
+/// for in their `LibraryArgs`.
 pub struct Libraries {
 	/// libraries_dir holds the path to the directory where libraries are stored.
 	pub libraries_dir: PathBuf,
@@ -320,6 +325,8 @@ impl Libraries {
 			.emit(LibraryManagerEvent::Delete(library.clone()))
 			.await;
 
+		library.aggregator.stop();
+
 		if let Ok(location_paths) = library
 			.db
 			.location()
@@ -567,6 +574,16 @@ impl Libraries {
 		)
 		.await?;
 
+		if let Err(e) = sync.toggles.set_disabled(&config.sync.disabled_models) {
+			warn!(?e, "Failed to load the models the library doesn't sync;");
+		}
+
+		let read_only = config.read_only;
+		// Whatever runs for the library from now on, its database isn't changed
+		if read_only {
+			set_query_only(&db, true).await?;
+		}
+
 		let library = Library::new(id, config, instance_id, identity, db, node, sync).await;
 
 		// This is an exception. Generally subscribe to this by `self.tx.subscribe`.
@@ -581,17 +598,35 @@ impl Libraries {
 			.await
 			.insert(library.id, Arc::clone(&library));
 
-		if should_seed {
+		super::aggregator::start(node, &library);
+
+		if should_seed && !read_only {
 			// library.orphan_remover.invoke().await;
 			sd_core_indexer_rules::seed::new_or_existing_library(&library.db).await?;
 		}
 
+		if read_only {
+			info!(%id, "Loaded read-only library, not watching its locations or resuming its jobs;");
+		} else {
+			self.start(&library, instance.id, node).await?;
+		}
+
+		Ok(library)
+	}
+
+	/// Watches the locations of the library and resumes its jobs
+	async fn start(
+		&self,
+		library: &Arc<Library>,
+		instance_id: instance::id::Type,
+		node: &Arc<Node>,
+	) -> Result<(), LibraryManagerError> {
 		for location in library
 			.db
 			.location()
 			.find_many(vec![
 				// TODO(N): This isn't gonna work with removable media and this will likely permanently break if the DB is restored from a backup.
-				location::instance_id::equals(Some(instance.id)),
+				location::instance_id::equals(Some(instance_id)),
 			])
 			.exec()
 			.await?
@@ -601,11 +636,48 @@ impl Libraries {
 			};
 		}
 
-		if let Err(e) = node.old_jobs.clone().cold_resume(node, &library).await {
+		if let Err(e) = node.old_jobs.clone().cold_resume(node, library).await {
 			error!(?e, "Failed to resume jobs for library;");
 		}
 
-		Ok(library)
+		Ok(())
+	}
+
+	/// Lets a read-only library be changed again, starting what loading it left out
+	pub async fn make_writable(
+		&self,
+		id: &Uuid,
+		node: &Arc<Node>,
+	) -> Result<(), LibraryManagerError> {
+		let library = self
+			.get_library(id)
+			.await
+			.ok_or(LibraryManagerError::LibraryNotFound)?;
+
+		if !library.is_read_only().await {
+			return Ok(());
+		}
+
+		set_query_only(&library.db, false).await?;
+		library
+			.update_config(|config| config.read_only = false)
+			.await?;
+		self.start(&library, library.config().await.instance_id, node)
+			.await?;
+
+		// Its volumes were left alone while it was read-only
+		node.volumes.initialize_library(Arc::clone(&library)).await;
+
+		// And so was its sync group
+		if let Err(e) = crate::api::cloud::resume_cloud_sync(node, library.id).await {
+			error!(?e, "Failed to start cloud sync of the writable library;");
+		}
+
+		info!(%id, "Made library writable;");
+
+		invalidate_query!(library, "library.list");
+
+		Ok(())
 	}
 
 	pub async fn update_instances(&self, library: Arc<Library>) {
@@ -644,6 +716,7 @@ async fn sync_rx_actor(
 			SyncEvent::Created => {
 				old_p2p::sync::originator(library.clone(), &library.sync, &node.p2p).await
 			}
+			SyncEvent::Conflicted => invalidate_query!(library, "sync.conflicts.list"),
 		}
 	}
 }
@@ -107,3 +107,48 @@ pub async fn configure_pragmas(db: &PrismaClient) -> Result<(), LibraryManagerEr
 
 	Ok(())
 }
+
+/// Read-only libraries refuse every write once loaded, whichever part of the core attempts it
+pub async fn set_query_only(
+	db: &PrismaClient,
+	query_only: bool,
+) -> Result<(), LibraryManagerError> {
+	if query_only {
+		execute_pragma::<()>(db, "PRAGMA query_only = ON;", "Set query only").await
+	} else {
+		execute_pragma::<()>(db, "PRAGMA query_only = OFF;", "Unset query only").await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use sd_utils::db::load_and_migrate;
+
+	use tempfile::tempdir;
+	use uuid::Uuid;
+
+	#[tokio::test]
+	async fn test_query_only_refuses_writes() {
+		let dir = tempdir().unwrap();
+		let db_url = format!(
+			"file:{}?connection_limit=1",
+			dir.path().join("library.db").display()
+		);
+		let db = load_and_migrate(&db_url).await.unwrap();
+		let create = || {
+			db.tag()
+				.create(Uuid::new_v4().as_bytes().to_vec(), vec![])
+				.exec()
+		};
+
+		set_query_only(&db, true).await.unwrap();
+		assert!(create().await.is_err());
+		assert_eq!(db.tag().count(vec![]).exec().await.unwrap(), 0);
+
+		set_query_only(&db, false).await.unwrap();
+		create().await.unwrap();
+		assert_eq!(db.tag().count(vec![]).exec().await.unwrap(), 1);
+	}
+}
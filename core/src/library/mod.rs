@@ -1,9 +1,14 @@
+pub(crate) mod aggregator;
+pub(crate) mod archive;
+pub(crate) mod backups;
+pub(crate) mod compaction;
 mod config;
 #[allow(clippy::module_inception)]
 mod library;
 mod manager;
 mod name;
 mod statistics;
+pub(crate) mod verify;
 
 pub use config::*;
 pub use library::*;
@@ -39,13 +39,13 @@ pub async fn update_library_statistics(
 
 	if total_capacity == 0 && available_capacity == 0 {
 		// Failed to fetch volume statistics from database, so we compute from local volumes
-		let volumes = crate::volume::get_volumes().await?;
+		let volumes = node.volumes.list_volumes().await?;
 
 		let mut local_total_capacity: u64 = 0;
 		let mut local_available_capacity: u64 = 0;
 		for volume in volumes {
-			local_total_capacity += volume.total_bytes_capacity;
-			local_available_capacity += volume.total_bytes_available;
+			local_total_capacity += volume.total_bytes_capacity.as_u64();
+			local_available_capacity += volume.total_bytes_available.as_u64();
 		}
 
 		total_capacity = local_total_capacity;
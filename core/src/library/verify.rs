@@ -0,0 +1,592 @@
+//! Verify, checking the database of a library is sound and repairing what can be repaired.
+//!
+//! SQLite checks its pages and the foreign keys of the tables, then the records that should never
+//! be there are looked for: objects without file paths, file paths without their location, and
+//! file paths with the same content linked to different objects. When asked to repair them, the
+//! file paths are linked to the oldest of their objects, the ones without location are taken out
+//! to the `quarantined_record` table and the objects without file paths are deleted, going through
+//! sync like any other change. Only the records of this device are looked at: the file paths of
+//! other devices' objects may be kept local to them by selective sync or a sync toggle, and their
+//! file paths may be missing a location that hasn't synced in or whose model is toggled off. A
+//! database SQLite finds corrupt isn't repaired, a backup is the way back from it.
+
+use crate::library::Library;
+
+use sd_core_sync::SyncManager;
+use sd_prisma::{
+	prisma::{
+		device, file_path, label_on_object, object, object_in_album, object_in_space,
+		quarantined_record, tag_on_object, PrismaClient,
+	},
+	prisma_sync,
+};
+use sd_sync::{sync_entry, OperationFactory};
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use prisma_client_rust::{or, raw, PrismaValue};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use thiserror::Error;
+use tracing::{info, instrument};
+
+/// Records read or written in one go
+const BATCH_SIZE: usize = 512;
+
+/// Reported by SQLite when nothing is wrong
+const INTEGRITY_OK: &str = "ok";
+
+#[derive(Debug, Clone, Default, Serialize, Type)]
+pub struct VerifyReport {
+	/// What SQLite found wrong with the database, empty when it's sound
+	pub integrity_errors: Vec<String>,
+	pub foreign_key_violations: Vec<ForeignKeyViolation>,
+	pub orphan_objects: u32,
+	/// Without a location or with one that doesn't exist anymore
+	pub paths_without_location: u32,
+	/// Contents whose file paths are linked to more than one object
+	pub conflicting_fingerprints: u32,
+	/// If the repairs were made, they aren't when asked not to or when the database is corrupt
+	pub repaired: bool,
+	pub relinked_paths: u32,
+	pub quarantined_paths: u32,
+	pub removed_objects: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct ForeignKeyViolation {
+	pub table: String,
+	/// The table the rows point at
+	pub parent: String,
+	pub rows: u32,
+}
+
+#[derive(Error, Debug)]
+pub enum VerifyError {
+	#[error("database error: {0}")]
+	Database(#[from] prisma_client_rust::QueryError),
+	#[error("failed to quarantine record: {0}")]
+	Quarantine(#[from] serde_json::Error),
+
+	#[error(transparent)]
+	Sync(#[from] sd_core_sync::Error),
+}
+
+impl From<VerifyError> for rspc::Error {
+	fn from(e: VerifyError) -> Self {
+		Self::with_cause(rspc::ErrorCode::InternalServerError, e.to_string(), e)
+	}
+}
+
+#[derive(Deserialize, Debug)]
+struct IntegrityCheck {
+	integrity_check: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ForeignKeyCheck {
+	table: String,
+	rowid: Option<i64>,
+	parent: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ConflictingCasId {
+	cas_id: String,
+}
+
+/// Checks the database of the library, repairing what it finds when `repair` is set, see the
+/// [module docs](self)
+#[instrument(skip(library), fields(library_id = %library.id), err)]
+pub async fn verify(library: &Library, repair: bool) -> Result<VerifyReport, VerifyError> {
+	check(&library.db, &library.sync, repair).await
+}
+
+async fn check(
+	db: &PrismaClient,
+	sync: &SyncManager,
+	repair: bool,
+) -> Result<VerifyReport, VerifyError> {
+	let integrity_errors = db
+		._query_raw::<IntegrityCheck>(raw!("PRAGMA integrity_check"))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|row| row.integrity_check)
+		.filter(|message| message != INTEGRITY_OK)
+		.collect::<Vec<_>>();
+
+	let foreign_key_checks = db
+		._query_raw::<ForeignKeyCheck>(raw!("PRAGMA foreign_key_check"))
+		.exec()
+		.await?;
+
+	let mut foreign_key_violations = BTreeMap::<(String, String), u32>::new();
+	let mut dangling_location_ids = HashSet::new();
+	for check in foreign_key_checks {
+		if let (Some(id), "file_path", "location") =
+			(check.rowid, check.table.as_str(), check.parent.as_str())
+		{
+			if let Ok(id) = file_path::id::Type::try_from(id) {
+				dangling_location_ids.insert(id);
+			}
+		}
+
+		*foreign_key_violations
+			.entry((check.table, check.parent))
+			.or_default() += 1;
+	}
+
+	let paths_without_location = db
+		.file_path()
+		.find_many(vec![
+			or![
+				file_path::id::in_vec(dangling_location_ids.into_iter().collect()),
+				file_path::location_id::equals(None),
+			],
+			local_file_paths(sync),
+		])
+		.select(file_path::select!({ id }))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|file_path| file_path.id)
+		.collect::<Vec<_>>();
+
+	let conflicting_cas_ids = db
+		._query_raw::<ConflictingCasId>(raw!(
+			"SELECT cas_id FROM file_path \
+			WHERE cas_id IS NOT NULL AND object_id IS NOT NULL \
+			AND device_id IN (SELECT id FROM device WHERE pub_id = {}) \
+			GROUP BY cas_id HAVING COUNT(DISTINCT object_id) > 1",
+			PrismaValue::Bytes(sync.device_pub_id.to_db())
+		))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|row| row.cas_id)
+		.collect::<Vec<_>>();
+
+	let mut report = VerifyReport {
+		foreign_key_violations: foreign_key_violations
+			.into_iter()
+			.map(|((table, parent), rows)| ForeignKeyViolation {
+				table,
+				parent,
+				rows,
+			})
+			.collect(),
+		orphan_objects: count(
+			db.object()
+				.count(orphan_objects_filter(sync))
+				.exec()
+				.await?,
+		),
+		paths_without_location: count(paths_without_location.len()),
+		conflicting_fingerprints: count(conflicting_cas_ids.len()),
+		integrity_errors,
+		..Default::default()
+	};
+
+	if !repair || !report.integrity_errors.is_empty() {
+		return Ok(report);
+	}
+
+	// Relinking first, so the objects it leaves without file paths are removed with the others
+	report.relinked_paths = relink_conflicting_paths(db, sync, conflicting_cas_ids).await?;
+	report.quarantined_paths = quarantine_paths(db, sync, paths_without_location).await?;
+	report.removed_objects = remove_orphan_objects(db, sync).await?;
+	report.repaired = true;
+
+	info!(
+		relinked_paths = report.relinked_paths,
+		quarantined_paths = report.quarantined_paths,
+		removed_objects = report.removed_objects,
+		"Repaired library database;",
+	);
+
+	Ok(report)
+}
+
+/// The file paths of this device, the only ones it repairs
+fn local_file_paths(sync: &SyncManager) -> file_path::WhereParam {
+	file_path::device::is(vec![device::pub_id::equals(sync.device_pub_id.to_db())])
+}
+
+/// Links the file paths of each content to the oldest of the objects they're linked to
+async fn relink_conflicting_paths(
+	db: &PrismaClient,
+	sync: &SyncManager,
+	cas_ids: Vec<String>,
+) -> Result<u32, VerifyError> {
+	let mut relinked = 0;
+
+	for cas_ids in cas_ids.chunks(BATCH_SIZE) {
+		let mut by_cas_id = HashMap::<_, Vec<_>>::new();
+		for file_path in db
+			.file_path()
+			.find_many(vec![
+				file_path::cas_id::in_vec(cas_ids.to_vec()),
+				local_file_paths(sync),
+			])
+			.select(file_path::select!({ pub_id cas_id object: select { id pub_id } }))
+			.exec()
+			.await?
+		{
+			if let (Some(cas_id), Some(object)) = (file_path.cas_id, file_path.object) {
+				by_cas_id
+					.entry(cas_id)
+					.or_default()
+					.push((file_path.pub_id, object));
+			}
+		}
+
+		let (ops, queries) = by_cas_id
+			.into_values()
+			.flat_map(|file_paths| {
+				let kept = file_paths
+					.iter()
+					.min_by_key(|(_, object)| object.id)
+					.map(|(_, object)| (object.id, object.pub_id.clone()));
+
+				file_paths
+					.into_iter()
+					.filter_map(move |(file_path_pub_id, object)| {
+						let (kept_id, kept_pub_id) = kept.clone()?;
+						(object.id != kept_id).then_some((file_path_pub_id, kept_pub_id))
+					})
+			})
+			.map(|(file_path_pub_id, object_pub_id)| {
+				(
+					sync.shared_update(
+						prisma_sync::file_path::SyncId {
+							pub_id: file_path_pub_id.clone(),
+						},
+						[sync_entry!(
+							prisma_sync::object::SyncId {
+								pub_id: object_pub_id.clone(),
+							},
+							file_path::object
+						)],
+					),
+					db.file_path()
+						.update(
+							file_path::pub_id::equals(file_path_pub_id),
+							vec![file_path::object::connect(object::pub_id::equals(
+								object_pub_id,
+							))],
+						)
+						.select(file_path::select!({ id })),
+				)
+			})
+			.unzip::<_, _, Vec<_>, Vec<_>>();
+
+		if ops.is_empty() {
+			continue;
+		}
+
+		relinked += count(queries.len());
+		sync.write_ops(db, (ops, queries)).await?;
+	}
+
+	Ok(relinked)
+}
+
+/// Copies the file paths to the `quarantined_record` table before deleting them
+async fn quarantine_paths(
+	db: &PrismaClient,
+	sync: &SyncManager,
+	ids: Vec<file_path::id::Type>,
+) -> Result<u32, VerifyError> {
+	let mut quarantined = 0;
+
+	for ids in ids.chunks(BATCH_SIZE) {
+		let file_paths = db
+			.file_path()
+			.find_many(vec![file_path::id::in_vec(ids.to_vec())])
+			.exec()
+			.await?;
+
+		if file_paths.is_empty() {
+			continue;
+		}
+
+		db.quarantined_record()
+			.create_many(
+				file_paths
+					.iter()
+					.map(|file_path| {
+						Ok(quarantined_record::create_unchecked(
+							"file_path".to_string(),
+							serde_json::to_vec(file_path)?,
+							"location is missing".to_string(),
+							vec![],
+						))
+					})
+					.collect::<Result<_, serde_json::Error>>()?,
+			)
+			.exec()
+			.await?;
+
+		quarantined += count(file_paths.len());
+
+		sync.write_ops(
+			db,
+			(
+				file_paths
+					.into_iter()
+					.map(|file_path| {
+						sync.shared_delete(prisma_sync::file_path::SyncId {
+							pub_id: file_path.pub_id,
+						})
+					})
+					.collect(),
+				db.file_path()
+					.delete_many(vec![file_path::id::in_vec(ids.to_vec())]),
+			),
+		)
+		.await?;
+	}
+
+	Ok(quarantined)
+}
+
+/// The objects of this device without file paths, another device's objects may have file paths
+/// that never reach this one
+fn orphan_objects_filter(sync: &SyncManager) -> Vec<object::WhereParam> {
+	vec![
+		object::file_paths::none(vec![]),
+		object::device::is(vec![device::pub_id::equals(sync.device_pub_id.to_db())]),
+	]
+}
+
+/// Deletes the objects of this device without file paths, with their tags and labels
+async fn remove_orphan_objects(db: &PrismaClient, sync: &SyncManager) -> Result<u32, VerifyError> {
+	let mut removed = 0;
+
+	loop {
+		let objects = db
+			.object()
+			.find_many(orphan_objects_filter(sync))
+			.take(BATCH_SIZE as i64)
+			.select(object::select!({
+				id
+				pub_id
+				tags: select { tag: select { pub_id } }
+				labels: select { label: select { name } }
+			}))
+			.exec()
+			.await?;
+
+		if objects.is_empty() {
+			break;
+		}
+
+		let ids = objects.iter().map(|object| object.id).collect::<Vec<_>>();
+
+		let tag_ops = objects
+			.iter()
+			.flat_map(|object| {
+				object.tags.iter().map(|tag_on_object| {
+					sync.relation_delete(prisma_sync::tag_on_object::SyncId {
+						tag: prisma_sync::tag::SyncId {
+							pub_id: tag_on_object.tag.pub_id.clone(),
+						},
+						object: prisma_sync::object::SyncId {
+							pub_id: object.pub_id.clone(),
+						},
+					})
+				})
+			})
+			.collect::<Vec<_>>();
+
+		if !tag_ops.is_empty() {
+			sync.write_ops(
+				db,
+				(
+					tag_ops,
+					db.tag_on_object()
+						.delete_many(vec![tag_on_object::object_id::in_vec(ids.clone())]),
+				),
+			)
+			.await?;
+		}
+
+		let label_ops = objects
+			.iter()
+			.flat_map(|object| {
+				object.labels.iter().map(|label_on_object| {
+					sync.relation_delete(prisma_sync::label_on_object::SyncId {
+						label: prisma_sync::label::SyncId {
+							name: label_on_object.label.name.clone(),
+						},
+						object: prisma_sync::object::SyncId {
+							pub_id: object.pub_id.clone(),
+						},
+					})
+				})
+			})
+			.collect::<Vec<_>>();
+
+		if !label_ops.is_empty() {
+			sync.write_ops(
+				db,
+				(
+					label_ops,
+					db.label_on_object()
+						.delete_many(vec![label_on_object::object_id::in_vec(ids.clone())]),
+				),
+			)
+			.await?;
+		}
+
+		// Spaces and albums aren't synced
+		remove_local_relations(db, &ids).await?;
+
+		removed += count(objects.len());
+
+		sync.write_ops(
+			db,
+			(
+				objects
+					.into_iter()
+					.map(|object| {
+						sync.shared_delete(prisma_sync::object::SyncId {
+							pub_id: object.pub_id,
+						})
+					})
+					.collect(),
+				db.object().delete_many(vec![object::id::in_vec(ids)]),
+			),
+		)
+		.await?;
+	}
+
+	Ok(removed)
+}
+
+async fn remove_local_relations(
+	db: &PrismaClient,
+	ids: &[object::id::Type],
+) -> Result<(), VerifyError> {
+	db._batch((
+		db.object_in_space()
+			.delete_many(vec![object_in_space::object_id::in_vec(ids.to_vec())]),
+		db.object_in_album()
+			.delete_many(vec![object_in_album::object_id::in_vec(ids.to_vec())]),
+	))
+	.await?;
+
+	Ok(())
+}
+
+fn count(n: impl TryInto<u32>) -> u32 {
+	n.try_into().unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use sd_core_prisma_helpers::DevicePubId;
+	use sd_utils::db::load_and_migrate;
+
+	use std::sync::{atomic::AtomicBool, Arc};
+
+	use tempfile::tempdir;
+	use uuid::Uuid;
+
+	async fn add_device(db: &PrismaClient, pub_id: &DevicePubId) -> device::Data {
+		db.device()
+			.create(pub_id.to_db(), vec![])
+			.exec()
+			.await
+			.unwrap()
+	}
+
+	async fn add_file_path(
+		db: &PrismaClient,
+		device: &device::Data,
+		name: &str,
+		object: Option<&object::Data>,
+	) -> file_path::Data {
+		let mut params = vec![
+			file_path::device::connect(device::id::equals(device.id)),
+			file_path::name::set(Some(name.to_string())),
+			file_path::cas_id::set(Some("content".to_string())),
+		];
+		if let Some(object) = object {
+			params.push(file_path::object::connect(object::id::equals(object.id)));
+		}
+
+		db.file_path()
+			.create(Uuid::new_v4().as_bytes().to_vec(), params)
+			.exec()
+			.await
+			.unwrap()
+	}
+
+	async fn add_object(db: &PrismaClient, device: &device::Data) -> object::Data {
+		db.object()
+			.create(
+				Uuid::new_v4().as_bytes().to_vec(),
+				vec![object::device::connect(device::id::equals(device.id))],
+			)
+			.exec()
+			.await
+			.unwrap()
+	}
+
+	#[tokio::test]
+	async fn test_repair_leaves_other_devices_paths_alone() {
+		let dir = tempdir().unwrap();
+		let db_url = format!(
+			"file:{}?connection_limit=1",
+			dir.path().join("library.db").display()
+		);
+		let db = Arc::new(load_and_migrate(&db_url).await.unwrap());
+
+		let device_pub_id = DevicePubId::new();
+		let device = add_device(&db, &device_pub_id).await;
+		let other_device = add_device(&db, &DevicePubId::new()).await;
+		let (sync, _) = SyncManager::new(
+			Arc::clone(&db),
+			&device_pub_id,
+			Arc::new(AtomicBool::new(true)),
+		)
+		.await
+		.unwrap();
+
+		// Without their location, which hasn't synced in yet for the other device's
+		let local = add_file_path(&db, &device, "local", None).await;
+		let remote = add_file_path(&db, &other_device, "remote", None).await;
+
+		// Linked to different objects by the other device
+		for name in ["first", "second"] {
+			let object = add_object(&db, &other_device).await;
+			add_file_path(&db, &other_device, name, Some(&object)).await;
+		}
+
+		let report = check(&db, &sync, true).await.unwrap();
+		assert!(report.repaired);
+		assert_eq!(report.paths_without_location, 1);
+		assert_eq!(report.quarantined_paths, 1);
+		assert_eq!(report.conflicting_fingerprints, 0);
+		assert_eq!(report.relinked_paths, 0);
+		assert_eq!(report.removed_objects, 0);
+
+		let exists = |id| {
+			let db = Arc::clone(&db);
+			async move {
+				db.file_path()
+					.find_unique(file_path::id::equals(id))
+					.exec()
+					.await
+					.unwrap()
+					.is_some()
+			}
+		};
+		assert!(!exists(local.id).await);
+		assert!(exists(remote.id).await);
+	}
+}
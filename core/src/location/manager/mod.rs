@@ -48,6 +48,7 @@ pub struct LocationManagementMessage {
 enum WatcherManagementMessageAction {
 	Pause,
 	Resume,
+	Flush,
 	IgnoreEventsForPath { path: PathBuf, ignore: bool },
 }
 
@@ -322,6 +323,17 @@ impl Locations {
 		.await
 	}
 
+	/// Waits for the watcher of the location to write the events it received to the library,
+	/// pausing it first keeps new ones from coming
+	pub async fn flush_watcher(
+		&self,
+		location_id: location::id::Type,
+		library: Arc<Library>,
+	) -> Result<(), LocationManagerError> {
+		self.watcher_management_message(location_id, library, WatcherManagementMessageAction::Flush)
+			.await
+	}
+
 	pub async fn temporary_watcher_pause(
 		&self,
 		location_id: location::id::Type,
@@ -21,7 +21,7 @@ use async_channel as chan;
 use futures::stream::StreamExt;
 use futures_concurrency::stream::Merge;
 use tokio::{
-	fs,
+	fs, spawn,
 	sync::oneshot,
 	time::{interval, MissedTickBehavior},
 };
@@ -258,7 +258,24 @@ impl Runner {
 		action: WatcherManagementMessageAction,
 		ack: oneshot::Sender<Result<(), LocationManagerError>>,
 	) {
-		ack.send(match action {
+		let res = match action {
+			WatcherManagementMessageAction::Flush => {
+				let key = (location_id, library.id);
+				if let Some(watcher) = self
+					.locations_watched
+					.get(&key)
+					.or_else(|| self.locations_unwatched.get(&key))
+				{
+					// Answered from its own task, the last events can take a few seconds
+					let flushed = watcher.flush().await;
+					spawn(async move {
+						flushed.await.ok();
+						ack.send(Ok(())).expect("Ack channel closed");
+					});
+					return;
+				}
+				Ok(())
+			}
 			WatcherManagementMessageAction::Pause => self.pause_watcher(location_id, library).await,
 			WatcherManagementMessageAction::Resume => {
 				self.resume_watcher(location_id, library).await
@@ -268,8 +285,9 @@ impl Runner {
 					.await;
 				Ok(())
 			}
-		})
-		.expect("Ack channel closed")
+		};
+
+		ack.send(res).expect("Ack channel closed")
 	}
 
 	async fn check_locations(
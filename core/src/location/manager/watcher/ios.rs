@@ -167,6 +167,14 @@ impl super::EventHandler for EventHandler {
 		Ok(())
 	}
 
+	fn has_pending(&self) -> bool {
+		!self.old_paths_map.is_empty()
+			|| !self.new_paths_map.is_empty()
+			|| !self.files_to_update.is_empty()
+			|| !self.reincident_to_update_files.is_empty()
+			|| !self.to_recalculate_size.is_empty()
+	}
+
 	async fn tick(&mut self) {
 		if self.last_events_eviction_check.elapsed() > HUNDRED_MILLIS {
 			if let Err(e) = self.handle_to_update_eviction().await {
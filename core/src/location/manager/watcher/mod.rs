@@ -21,6 +21,7 @@ use futures_concurrency::stream::Merge;
 use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
 use tokio::{
 	spawn,
+	sync::oneshot,
 	task::JoinHandle,
 	time::{interval_at, Instant, MissedTickBehavior},
 };
@@ -82,6 +83,9 @@ trait EventHandler: 'static {
 	/// As Event Handlers have some inner state, from time to time we need to call this tick method
 	/// so the event handler can update its state.
 	fn tick(&mut self) -> impl Future<Output = ()> + Send;
+
+	/// Whether some events wait for a later tick before being written to the library
+	fn has_pending(&self) -> bool;
 }
 
 #[derive(Debug)]
@@ -90,6 +94,7 @@ pub(super) struct LocationWatcher {
 	location_path: PathBuf,
 	watcher: RecommendedWatcher,
 	ignore_path_tx: chan::Sender<IgnorePath>,
+	flush_tx: chan::Sender<oneshot::Sender<()>>,
 	handle: Option<JoinHandle<()>>,
 	stop_tx: chan::Sender<()>,
 }
@@ -118,6 +123,7 @@ impl LocationWatcher {
 
 		let (events_tx, events_rx) = chan::unbounded();
 		let (ignore_path_tx, ignore_path_rx) = chan::bounded(8);
+		let (flush_tx, flush_rx) = chan::bounded(8);
 		let (stop_tx, stop_rx) = chan::bounded(1);
 
 		let watcher = RecommendedWatcher::new(
@@ -137,6 +143,7 @@ impl LocationWatcher {
 		let handle = spawn({
 			let events_rx = events_rx.clone();
 			let ignore_path_rx = ignore_path_rx.clone();
+			let flush_rx = flush_rx.clone();
 			let stop_rx = stop_rx.clone();
 			async move {
 				while let Err(e) = spawn(
@@ -147,6 +154,7 @@ impl LocationWatcher {
 						Arc::clone(&library),
 						events_rx.clone(),
 						ignore_path_rx.clone(),
+						flush_rx.clone(),
 						stop_rx.clone(),
 					)
 					.in_current_span(),
@@ -172,6 +180,7 @@ impl LocationWatcher {
 			location_path,
 			watcher,
 			ignore_path_tx,
+			flush_tx,
 			handle: Some(handle),
 			stop_tx,
 		})
@@ -184,11 +193,13 @@ impl LocationWatcher {
 		library: Arc<Library>,
 		events_rx: chan::Receiver<notify::Result<Event>>,
 		ignore_path_rx: chan::Receiver<IgnorePath>,
+		flush_rx: chan::Receiver<oneshot::Sender<()>>,
 		stop_rx: chan::Receiver<()>,
 	) {
 		enum StreamMessage {
 			NewEvent(notify::Result<Event>),
 			NewIgnorePath(IgnorePath),
+			Flush(oneshot::Sender<()>),
 			Tick,
 			Stop,
 		}
@@ -207,6 +218,10 @@ impl LocationWatcher {
 
 		let mut paths_to_ignore = HashSet::new();
 
+		// Answered once the received events are all written, the last ones need a few ticks
+		let mut flushes = vec![];
+		let queued_events = events_rx.clone();
+
 		let mut handler_tick_interval =
 			interval_at(Instant::now() + HUNDRED_MILLIS, HUNDRED_MILLIS);
 		// In case of doubt check: https://docs.rs/tokio/latest/tokio/time/enum.MissedTickBehavior.html
@@ -215,6 +230,7 @@ impl LocationWatcher {
 		let mut msg_stream = pin!((
 			events_rx.map(StreamMessage::NewEvent),
 			ignore_path_rx.map(StreamMessage::NewIgnorePath),
+			flush_rx.map(StreamMessage::Flush),
 			IntervalStream::new(handler_tick_interval).map(|_| StreamMessage::Tick),
 			stop_rx.map(|()| StreamMessage::Stop),
 		)
@@ -262,7 +278,20 @@ impl LocationWatcher {
 					}
 				}
 
-				StreamMessage::Tick => event_handler.tick().await,
+				StreamMessage::Flush(tx) => flushes.push(tx),
+
+				StreamMessage::Tick => {
+					event_handler.tick().await;
+
+					if !flushes.is_empty()
+						&& queued_events.is_empty()
+						&& !event_handler.has_pending()
+					{
+						for tx in flushes.drain(..) {
+							tx.send(()).ok();
+						}
+					}
+				}
 
 				StreamMessage::Stop => {
 					debug!("Stopping Location Manager event handler for location");
@@ -309,6 +338,16 @@ impl LocationWatcher {
 			.expect("Location watcher ignore path channel closed");
 	}
 
+	/// Resolves once the events received so far are written to the library
+	pub(super) async fn flush(&self) -> oneshot::Receiver<()> {
+		let (tx, rx) = oneshot::channel();
+		self.flush_tx
+			.send(tx)
+			.await
+			.expect("Location watcher flush channel closed");
+		rx
+	}
+
 	pub(super) fn check_path(&self, path: impl AsRef<Path>) -> bool {
 		self.location_path == path.as_ref()
 	}
@@ -1,4 +1,4 @@
-use crate::{context::NodeContext, invalidate_query, library::Library, Node};
+use crate::{context::NodeContext, invalidate_query, library::Library, volume, Node};
 
 use sd_core_file_path_helper::{
 	filter_existing_file_path_params, IsolatedFilePathData, IsolatedFilePathDataParts,
@@ -11,6 +11,7 @@ use sd_core_heavy_lifting::{
 	JobEnqueuer, JobId,
 };
 use sd_core_prisma_helpers::{location_with_indexer_rules, CasId};
+use sd_core_sync::selective::{self, SyncRules};
 
 use sd_prisma::{
 	prisma::{device, file_path, indexer_rules_in_location, instance, location, PrismaClient},
@@ -442,6 +443,18 @@ pub async fn scan_location(
 		return Ok(None);
 	}
 
+	if library.is_read_only().await {
+		warn!("Tried to scan a location of a read-only library");
+		return Ok(None);
+	}
+
+	if let Some(path) = &location.path {
+		if volume::quarantine::is_quarantined(node, path).await {
+			warn!("Tried to scan a location on a quarantined volume");
+			return Ok(None);
+		}
+	}
+
 	let location_id = location.id;
 	let ctx = NodeContext {
 		node: Arc::clone(node),
@@ -500,6 +513,33 @@ pub async fn scan_location(
 	Ok(Some(job_id))
 }
 
+/// Re-dispatches scans for every location of the library that didn't finish scanning,
+/// e.g. after the OS granted us access to folders we were previously denied.
+#[instrument(skip_all, fields(library_id = %library.id), err)]
+pub async fn rescan_incomplete_locations(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+) -> Result<(), LocationError> {
+	let locations = library
+		.db
+		.location()
+		.find_many(vec![location::scan_state::not(ScanState::Completed as i32)])
+		.include(location_with_indexer_rules::include())
+		.exec()
+		.await?;
+
+	for location in locations {
+		let location_id = location.id;
+		let scan_state = ScanState::try_from(location.scan_state)?;
+
+		if let Err(e) = scan_location(node, library, location, scan_state).await {
+			error!(?e, %location_id, "Failed to rescan incomplete location;");
+		}
+	}
+
+	Ok(())
+}
+
 #[instrument(
 	skip_all,
 	fields(
@@ -523,6 +563,18 @@ pub async fn scan_location_sub_path(
 		return Ok(None);
 	}
 
+	if library.is_read_only().await {
+		warn!("Tried to scan a location of a read-only library");
+		return Ok(None);
+	}
+
+	if let Some(path) = &location.path {
+		if volume::quarantine::is_quarantined(node, path).await {
+			warn!("Tried to scan a location on a quarantined volume");
+			return Ok(None);
+		}
+	}
+
 	let location_id = location.id;
 	let ctx = NodeContext {
 		node: Arc::clone(node),
@@ -579,6 +631,18 @@ pub async fn light_scan_location(
 		return Ok(());
 	}
 
+	if library.is_read_only().await {
+		warn!("Tried to scan a location of a read-only library");
+		return Ok(());
+	}
+
+	if let Some(path) = &location.path {
+		if volume::quarantine::is_quarantined(&node, path).await {
+			warn!("Tried to scan a location on a quarantined volume");
+			return Ok(());
+		}
+	}
+
 	let location_base_data = location::Data::from(&location);
 
 	let dispatcher = node.task_system.get_dispatcher();
@@ -1097,8 +1161,11 @@ pub async fn create_file_path(
 		))?;
 
 	let device_pub_id = sync.device_pub_id.to_db();
+	let mode = SyncRules::load(db, location.id)
+		.await?
+		.mode_for(materialized_path, name, is_dir);
 
-	let (sync_params, db_params) = [
+	let (sync_params, mut db_params) = [
 		(
 			sync_entry!(
 				prisma_sync::location::SyncId {
@@ -1137,19 +1204,16 @@ pub async fn create_file_path(
 	]
 	.into_iter()
 	.unzip::<_, _, Vec<_>, Vec<_>>();
+	db_params.push(file_path::sync_mode::set(mode.to_db()));
 
 	let pub_id = sd_utils::uuid_to_bytes(&Uuid::now_v7());
 
-	sync.write_op(
-		db,
-		sync.shared_create(
-			prisma_sync::file_path::SyncId {
-				pub_id: pub_id.clone(),
-			},
-			sync_params,
-		),
-		db.file_path().create(pub_id, db_params),
-	)
-	.await
-	.map_err(Into::into)
+	let query = db.file_path().create(pub_id.clone(), db_params);
+	match selective::strip(
+		sync.shared_create(prisma_sync::file_path::SyncId { pub_id }, sync_params),
+		mode,
+	) {
+		Some(op) => sync.write_op(db, op, query).await.map_err(Into::into),
+		None => query.exec().await.map_err(Into::into),
+	}
 }
@@ -6,11 +6,11 @@ use crate::{
 
 use sd_cloud_schema::devices::DeviceOS;
 use sd_core_sync::DevicePubId;
-use sd_old_p2p::Identity;
+use sd_old_p2p::{BandwidthLimit, Identity, RemoteIdentity};
 use sd_utils::error::FileIOError;
 
 use std::{
-	collections::HashSet,
+	collections::{HashMap, HashSet},
 	path::{Path, PathBuf},
 	sync::Arc,
 };
@@ -99,6 +99,16 @@ pub struct NodeConfigP2P {
 	/// which is why we use `String` not `SocketAddr`
 	#[serde(default)]
 	pub manual_peers: HashSet<String>,
+	/// The limit every transfer with a peer over p2p is held to, on top of the one of the peer.
+	/// Cloud sync is held to it too, though it doesn't go through p2p.
+	#[serde(default)]
+	pub transfer_bandwidth: BandwidthLimit,
+	/// The transfer limits of some peers, by their identity
+	#[serde(default)]
+	pub peer_transfer_bandwidth: HashMap<RemoteIdentity, BandwidthLimit>,
+	/// Hold sync back while the network is metered, like a phone hotspot
+	#[serde(default, skip_serializing_if = "skip_if_false")]
+	pub unmetered_sync_only: bool,
 }
 
 impl Default for NodeConfigP2P {
@@ -111,6 +121,9 @@ impl Default for NodeConfigP2P {
 			disable_relay: true,
 			enable_remote_access: false,
 			manual_peers: Default::default(),
+			transfer_bandwidth: BandwidthLimit::default(),
+			peer_transfer_bandwidth: HashMap::new(),
+			unmetered_sync_only: false,
 		}
 	}
 }
@@ -172,10 +185,33 @@ mod identity_serde {
 	}
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, Type)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Type)]
 pub struct NodePreferences {
 	// pub thumbnailer: ThumbnailerPreferences,
 	// TODO(fogodev): introduce preferences to choose how many worker the task system should have
+	/// Keep the system from going to sleep while jobs are running
+	#[serde(default = "default_prevent_sleep_during_jobs")]
+	pub prevent_sleep_during_jobs: bool,
+	/// Suspend p2p, cloud services and other traffic the app starts on its own
+	#[serde(default)]
+	pub offline: bool,
+	/// Share anonymous usage and error counts, off unless the user opts in
+	#[serde(default)]
+	pub telemetry: bool,
+}
+
+impl Default for NodePreferences {
+	fn default() -> Self {
+		Self {
+			prevent_sleep_during_jobs: default_prevent_sleep_during_jobs(),
+			offline: false,
+			telemetry: false,
+		}
+	}
+}
+
+const fn default_prevent_sleep_during_jobs() -> bool {
+	true
 }
 
 #[derive(
@@ -522,6 +558,11 @@ impl Manager {
 		self.data_directory_path.clone()
 	}
 
+	/// preferences_watcher returns a receiver notified every time the node preferences change.
+	pub(crate) fn preferences_watcher(&self) -> watch::Receiver<NodePreferences> {
+		self.preferences_watcher_tx.subscribe()
+	}
+
 	/// write allows the user to update the configuration. This is done in a closure while a Mutex lock is held so that the user can't cause a race condition if the config were to be updated in multiple parts of the app at the same time.
 	pub(crate) async fn write<F: FnOnce(&mut NodeConfig)>(
 		&self,
@@ -0,0 +1,640 @@
+//! Tunables of the core subsystems, read from `core_config.json` in the data directory.
+//!
+//! The file is watched and changes are distributed through a [`watch`] channel, so subsystems
+//! pick them up on their next iteration without a restart. A file that doesn't parse or has
+//! invalid values is ignored with a warning, and the last good config stays in use.
+
+use crate::volume::{LowSpaceThreshold, VolumeFingerprint, VolumeSettings, WatcherBackend};
+
+use sd_utils::error::FileIOError;
+
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use thiserror::Error;
+use tokio::{
+	fs,
+	sync::{self, mpsc, watch},
+	time::sleep,
+};
+use tracing::{debug, error, info, warn};
+
+pub const CORE_CONFIG_FILE: &str = "core_config.json";
+
+/// Editors tend to write a file in a few steps, we wait for them to settle before reading it
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CoreConfig {
+	pub volumes: VolumesConfig,
+	pub watcher: VolumeWatcherConfig,
+	pub jobs: JobsConfig,
+	pub telemetry: TelemetryConfig,
+	pub sync: SyncConfig,
+	pub backups: BackupsConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VolumesConfig {
+	/// How often tracked volumes are refreshed and cleaned up
+	pub maintenance_interval_secs: u64,
+	/// Pause between two steps of a maintenance job, so it doesn't hog the disks
+	pub maintenance_step_delay_ms: u64,
+	/// Volume scans closer than this to the previous one reuse its result, 0 disables the cache
+	pub scan_cache_ttl_ms: u64,
+	/// Whether the system volumes the user can't open, like the mounts of the other users of the
+	/// machine, are tracked automatically along with the others
+	pub auto_track_inaccessible: bool,
+	/// Volumes forecast to fill up within this many days are warned about, 0 turns it off
+	pub forecast_horizon_days: u32,
+	/// How often the S.M.A.R.T. health of the drives is read, 0 turns it off
+	pub health_interval_secs: u64,
+	/// Tracked volumes with less free space than this are warned about, `null` turns it off
+	pub low_space_threshold: Option<LowSpaceThreshold>,
+	/// How often the free space of the tracked volumes is read for the low space warnings
+	pub low_space_interval_secs: u64,
+	/// Tracked volumes whose jobs run into this many I/O errors in a row are quarantined, 0 turns
+	/// it off
+	pub io_error_threshold: u32,
+	/// The volumes mounted in these directories, or below, were dismissed by the user and aren't
+	/// listed
+	pub ignored_paths: BTreeSet<PathBuf>,
+	/// What the user set for each volume, the volumes left at the defaults aren't kept
+	pub settings: BTreeMap<VolumeFingerprint, VolumeSettings>,
+}
+
+impl Default for VolumesConfig {
+	fn default() -> Self {
+		Self {
+			maintenance_interval_secs: 5 * 60,
+			maintenance_step_delay_ms: 1_000,
+			scan_cache_ttl_ms: 2_000,
+			auto_track_inaccessible: false,
+			forecast_horizon_days: 14,
+			health_interval_secs: 6 * 60 * 60,
+			low_space_threshold: Some(LowSpaceThreshold::default()),
+			low_space_interval_secs: 60,
+			io_error_threshold: 5,
+			ignored_paths: BTreeSet::new(),
+			settings: BTreeMap::new(),
+		}
+	}
+}
+
+impl VolumesConfig {
+	pub fn maintenance_interval(&self) -> Duration {
+		Duration::from_secs(self.maintenance_interval_secs)
+	}
+
+	pub fn maintenance_step_delay(&self) -> Duration {
+		Duration::from_millis(self.maintenance_step_delay_ms)
+	}
+
+	pub fn scan_cache_ttl(&self) -> Duration {
+		Duration::from_millis(self.scan_cache_ttl_ms)
+	}
+
+	/// `None` when the health checks are turned off
+	pub fn health_interval(&self) -> Option<Duration> {
+		(self.health_interval_secs > 0).then(|| Duration::from_secs(self.health_interval_secs))
+	}
+
+	pub fn low_space_interval(&self) -> Duration {
+		Duration::from_secs(self.low_space_interval_secs)
+	}
+
+	pub fn is_ignored(&self, mount_point: &Path) -> bool {
+		self.ignored_paths
+			.iter()
+			.any(|ignored| mount_point.starts_with(ignored))
+	}
+
+	pub fn settings_of(&self, fingerprint: &VolumeFingerprint) -> VolumeSettings {
+		self.settings.get(fingerprint).copied().unwrap_or_default()
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct VolumeWatcherConfig {
+	/// OS events closer than this to the last volume check don't trigger a new one
+	pub debounce_ms: u64,
+	/// Where the OS events come from, the platform's own order when unset or unavailable. The
+	/// watcher switches over as soon as this changes.
+	pub backend: Option<WatcherBackend>,
+	/// How often the `poll` backend checks the volumes, the one the watcher falls back on when
+	/// none of the platform's starts
+	pub poll_interval_ms: u64,
+	/// Directories watched for mount points coming and going on top of the backend, for the
+	/// volumes mounted outside of where the platform looks, like `/srv/disks`
+	#[specta(type = Vec<String>)]
+	pub extra_paths: BTreeSet<PathBuf>,
+}
+
+impl Default for VolumeWatcherConfig {
+	fn default() -> Self {
+		Self {
+			debounce_ms: 100,
+			backend: None,
+			poll_interval_ms: 10_000,
+			extra_paths: BTreeSet::new(),
+		}
+	}
+}
+
+impl VolumeWatcherConfig {
+	pub fn debounce(&self) -> Duration {
+		Duration::from_millis(self.debounce_ms)
+	}
+
+	pub fn poll_interval(&self) -> Duration {
+		Duration::from_millis(self.poll_interval_ms)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JobsConfig {
+	/// Jobs running at the same time in each library, the others are queued. The node runs up to
+	/// this many per open library, but their tasks all share the task system, which has one
+	/// worker per CPU core.
+	pub max_workers: usize,
+}
+
+impl Default for JobsConfig {
+	fn default() -> Self {
+		Self { max_workers: 5 }
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+	pub flush_interval_secs: u64,
+	pub batch_size: usize,
+	/// Oldest events are dropped past this, so a machine that never gets to send doesn't grow the queue forever
+	pub max_queued_events: usize,
+}
+
+impl Default for TelemetryConfig {
+	fn default() -> Self {
+		Self {
+			flush_interval_secs: 15 * 60,
+			batch_size: 200,
+			max_queued_events: 5_000,
+		}
+	}
+}
+
+impl TelemetryConfig {
+	pub fn flush_interval(&self) -> Duration {
+		Duration::from_secs(self.flush_interval_secs)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncConfig {
+	/// How often the sync operations of every library are compacted, 0 turns it off
+	pub compaction_interval_secs: u64,
+	/// Superseded operations are kept at least this long, for the devices that only sync once in a
+	/// while
+	pub retention_days: u32,
+}
+
+impl Default for SyncConfig {
+	fn default() -> Self {
+		Self {
+			compaction_interval_secs: 6 * 60 * 60,
+			retention_days: 30,
+		}
+	}
+}
+
+impl SyncConfig {
+	/// `None` when compaction is turned off
+	pub fn compaction_interval(&self) -> Option<Duration> {
+		(self.compaction_interval_secs > 0)
+			.then(|| Duration::from_secs(self.compaction_interval_secs))
+	}
+
+	pub fn retention(&self) -> Duration {
+		Duration::from_secs(u64::from(self.retention_days) * 24 * 60 * 60)
+	}
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub enum BackupSchedule {
+	#[default]
+	Off,
+	Daily,
+	Weekly,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct BackupsConfig {
+	/// How often every library is backed up
+	pub schedule: BackupSchedule,
+	/// Scheduled backups kept for each library, the oldest are removed past this. The ones made by
+	/// hand aren't counted nor removed.
+	pub retention: u32,
+}
+
+impl Default for BackupsConfig {
+	fn default() -> Self {
+		Self {
+			schedule: BackupSchedule::Off,
+			retention: 7,
+		}
+	}
+}
+
+impl BackupsConfig {
+	/// `None` when scheduled backups are turned off
+	pub fn interval(&self) -> Option<Duration> {
+		const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+		match self.schedule {
+			BackupSchedule::Off => None,
+			BackupSchedule::Daily => Some(DAY),
+			BackupSchedule::Weekly => Some(DAY * 7),
+		}
+	}
+}
+
+impl CoreConfig {
+	fn validate(&self) -> Result<(), CoreConfigError> {
+		let checks = [
+			(
+				self.volumes.maintenance_interval_secs > 0,
+				"volumes.maintenance_interval_secs must be greater than 0",
+			),
+			(
+				self.volumes.low_space_interval_secs > 0,
+				"volumes.low_space_interval_secs must be greater than 0",
+			),
+			(
+				!matches!(
+					self.volumes.low_space_threshold,
+					Some(LowSpaceThreshold::Percentage(percent)) if percent > 100
+				),
+				"volumes.low_space_threshold can't be more than 100 percent",
+			),
+			(
+				self.watcher.poll_interval_ms > 0,
+				"watcher.poll_interval_ms must be greater than 0",
+			),
+			(
+				self.watcher
+					.extra_paths
+					.iter()
+					.all(|path| path.is_absolute()),
+				"watcher.extra_paths must be absolute",
+			),
+			(
+				self.jobs.max_workers > 0,
+				"jobs.max_workers must be greater than 0",
+			),
+			(
+				self.telemetry.flush_interval_secs > 0,
+				"telemetry.flush_interval_secs must be greater than 0",
+			),
+			(
+				self.telemetry.batch_size > 0,
+				"telemetry.batch_size must be greater than 0",
+			),
+			(
+				self.backups.retention > 0,
+				"backups.retention must be greater than 0",
+			),
+		];
+
+		checks
+			.into_iter()
+			.find(|(is_valid, _)| !is_valid)
+			.map_or(Ok(()), |(_, reason)| Err(CoreConfigError::Invalid(reason)))
+	}
+}
+
+#[derive(Error, Debug)]
+pub enum CoreConfigError {
+	#[error("invalid core config: {0}")]
+	Invalid(&'static str),
+	#[error(transparent)]
+	SerdeJson(#[from] serde_json::Error),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+	#[error("failed to watch the core config: {0}")]
+	Watch(#[from] notify::Error),
+}
+
+pub struct CoreConfigManager {
+	path: PathBuf,
+	tx: watch::Sender<CoreConfig>,
+	watcher: Mutex<Option<RecommendedWatcher>>,
+	/// Held from reading the config to sending it, so concurrent updates don't undo each other
+	write_lock: sync::Mutex<()>,
+}
+
+impl CoreConfigManager {
+	/// Never fails, the defaults are used if the file can't be read
+	pub(crate) async fn load(data_dir: impl AsRef<Path>) -> Self {
+		let path = data_dir.as_ref().join(CORE_CONFIG_FILE);
+
+		let config = match read(&path).await {
+			Ok(Some(config)) => config,
+			Ok(None) => {
+				// Written out so there is something to edit
+				let config = CoreConfig::default();
+				if let Err(e) = write(&path, &config).await {
+					warn!(?e, "Failed to write the default core config;");
+				}
+				config
+			}
+			Err(e) => {
+				warn!(?e, "Invalid core config, using the defaults;");
+				CoreConfig::default()
+			}
+		};
+
+		Self {
+			path,
+			tx: watch::Sender::new(config),
+			watcher: Mutex::new(None),
+			write_lock: sync::Mutex::new(()),
+		}
+	}
+
+	pub fn get(&self) -> CoreConfig {
+		self.tx.borrow().clone()
+	}
+
+	/// Subsystems keep one of these to follow changes
+	pub fn watch(&self) -> watch::Receiver<CoreConfig> {
+		self.tx.subscribe()
+	}
+
+	/// Changes the config and writes it to the file, the subsystems follow as with the edits of
+	/// the file. Nothing changes if the result is invalid or can't be written.
+	pub async fn update(&self, f: impl FnOnce(&mut CoreConfig)) -> Result<(), CoreConfigError> {
+		let _write_guard = self.write_lock.lock().await;
+
+		let mut config = self.get();
+		f(&mut config);
+		config.validate()?;
+		write(&self.path, &config).await?;
+
+		self.tx.send_if_modified(|current| {
+			if *current == config {
+				false
+			} else {
+				*current = config;
+				true
+			}
+		});
+
+		Ok(())
+	}
+
+	/// Reads the file again, returning whether the config changed
+	pub async fn reload(&self) -> Result<bool, CoreConfigError> {
+		let _write_guard = self.write_lock.lock().await;
+
+		let config = read(&self.path).await?.unwrap_or_default();
+
+		Ok(self.tx.send_if_modified(|current| {
+			if *current == config {
+				false
+			} else {
+				*current = config;
+				true
+			}
+		}))
+	}
+
+	pub(crate) fn start(self: &Arc<Self>) -> Result<(), CoreConfigError> {
+		let (events_tx, mut events_rx) = mpsc::channel(1);
+		let file_name = self.path.file_name().map(ToOwned::to_owned);
+
+		let mut watcher = RecommendedWatcher::new(
+			move |result: notify::Result<notify::Event>| match result {
+				Ok(event)
+					if event
+						.paths
+						.iter()
+						.any(|path| path.file_name() == file_name.as_deref()) =>
+				{
+					// A reload is already pending if the channel is full
+					let _ = events_tx.try_send(());
+				}
+				Ok(_) => {}
+				Err(e) => error!(?e, "Core config watcher error;"),
+			},
+			Config::default(),
+		)?;
+
+		// The parent is watched as editors often replace the file instead of writing to it
+		if let Some(dir) = self.path.parent() {
+			watcher.watch(dir, RecursiveMode::NonRecursive)?;
+		}
+
+		*self
+			.watcher
+			.lock()
+			.expect("core config watcher lock poisoned") = Some(watcher);
+
+		let this = Arc::clone(self);
+		tokio::spawn(async move {
+			while events_rx.recv().await.is_some() {
+				sleep(RELOAD_DEBOUNCE).await;
+				while events_rx.try_recv().is_ok() {}
+
+				match this.reload().await {
+					Ok(true) => info!("Core config reloaded;"),
+					Ok(false) => debug!("Core config file changed without changes to the config;"),
+					Err(e) => warn!(?e, "Invalid core config, keeping the current one;"),
+				}
+			}
+		});
+
+		Ok(())
+	}
+}
+
+async fn read(path: &Path) -> Result<Option<CoreConfig>, CoreConfigError> {
+	let bytes = match fs::read(path).await {
+		Ok(bytes) => bytes,
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+		Err(e) => return Err(FileIOError::from((path, e, "Failed to read core config")).into()),
+	};
+
+	let config = serde_json::from_slice::<CoreConfig>(&bytes)?;
+	config.validate()?;
+
+	Ok(Some(config))
+}
+
+async fn write(path: &Path, config: &CoreConfig) -> Result<(), CoreConfigError> {
+	fs::write(path, serde_json::to_vec_pretty(config)?)
+		.await
+		.map_err(|e| FileIOError::from((path, e, "Failed to write core config")).into())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn missing_fields_use_the_defaults() {
+		let config =
+			serde_json::from_str::<CoreConfig>(r#"{ "watcher": { "debounce_ms": 500 } }"#).unwrap();
+
+		assert_eq!(config.watcher.debounce(), Duration::from_millis(500));
+		assert_eq!(config.watcher.backend, None);
+		assert_eq!(config.jobs, JobsConfig::default());
+		assert!(config.validate().is_ok());
+	}
+
+	#[test]
+	fn rejects_invalid_values() {
+		let mut config = CoreConfig::default();
+		config.jobs.max_workers = 0;
+
+		assert!(matches!(
+			config.validate(),
+			Err(CoreConfigError::Invalid(_))
+		));
+
+		let mut config = CoreConfig::default();
+		config.watcher.extra_paths.insert(PathBuf::from("disks"));
+
+		assert!(matches!(
+			config.validate(),
+			Err(CoreConfigError::Invalid(_))
+		));
+
+		let mut config = CoreConfig::default();
+		config.volumes.low_space_threshold = Some(LowSpaceThreshold::Percentage(150));
+
+		assert!(matches!(
+			config.validate(),
+			Err(CoreConfigError::Invalid(_))
+		));
+	}
+
+	#[test]
+	fn volume_settings_are_keyed_by_fingerprint() {
+		let config = serde_json::from_str::<CoreConfig>(
+			r#"{ "volumes": { "settings": { "0a0b": { "is_hidden": true } } } }"#,
+		)
+		.unwrap();
+
+		let settings = config.volumes.settings_of(&VolumeFingerprint(vec![10, 11]));
+		assert!(settings.is_hidden);
+		assert!(!settings.force_read_only);
+		assert_eq!(settings.auto_track, None);
+		assert!(config
+			.volumes
+			.settings_of(&VolumeFingerprint(vec![1]))
+			.is_default());
+	}
+
+	#[tokio::test]
+	async fn reload_keeps_last_good_config() {
+		let dir = std::env::temp_dir().join(format!("sd-core-config-{}", uuid::Uuid::new_v4()));
+		fs::create_dir_all(&dir).await.unwrap();
+
+		let manager = CoreConfigManager::load(&dir).await;
+		assert_eq!(manager.get(), CoreConfig::default());
+		assert!(dir.join(CORE_CONFIG_FILE).exists());
+
+		let mut rx = manager.watch();
+		fs::write(
+			dir.join(CORE_CONFIG_FILE),
+			r#"{ "jobs": { "max_workers": 2 } }"#,
+		)
+		.await
+		.unwrap();
+		assert!(manager.reload().await.unwrap());
+		assert!(rx.has_changed().unwrap());
+		assert_eq!(rx.borrow_and_update().jobs.max_workers, 2);
+
+		fs::write(
+			dir.join(CORE_CONFIG_FILE),
+			r#"{ "jobs": { "max_workers": 0 } }"#,
+		)
+		.await
+		.unwrap();
+		assert!(manager.reload().await.is_err());
+		assert_eq!(manager.get().jobs.max_workers, 2);
+
+		fs::remove_dir_all(dir).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn updates_survive_a_restart() {
+		let dir = std::env::temp_dir().join(format!("sd-core-config-{}", uuid::Uuid::new_v4()));
+		fs::create_dir_all(&dir).await.unwrap();
+
+		let manager = CoreConfigManager::load(&dir).await;
+		let mut rx = manager.watch();
+		manager
+			.update(|config| {
+				config
+					.volumes
+					.ignored_paths
+					.insert(PathBuf::from("/media/alice"));
+			})
+			.await
+			.unwrap();
+		assert!(rx.has_changed().unwrap());
+		assert!(manager
+			.update(|config| config.jobs.max_workers = 0)
+			.await
+			.is_err());
+
+		let config = CoreConfigManager::load(&dir).await.get();
+		assert!(config.volumes.is_ignored(Path::new("/media/alice/USB")));
+		assert!(!config.volumes.is_ignored(Path::new("/media/alicia")));
+		assert_eq!(config.jobs.max_workers, JobsConfig::default().max_workers);
+
+		fs::remove_dir_all(dir).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn concurrent_updates_keep_each_other() {
+		let dir = std::env::temp_dir().join(format!("sd-core-config-{}", uuid::Uuid::new_v4()));
+		fs::create_dir_all(&dir).await.unwrap();
+
+		let manager = CoreConfigManager::load(&dir).await;
+		let paths = (0..16)
+			.map(|i| PathBuf::from(format!("/media/drive-{i}")))
+			.collect::<Vec<_>>();
+		futures::future::join_all(paths.iter().map(|path| {
+			manager.update(|config| {
+				config.volumes.ignored_paths.insert(path.clone());
+			})
+		}))
+		.await
+		.into_iter()
+		.collect::<Result<Vec<_>, _>>()
+		.unwrap();
+
+		for config in [manager.get(), CoreConfigManager::load(&dir).await.get()] {
+			assert!(paths.iter().all(|path| config.volumes.is_ignored(path)));
+		}
+
+		fs::remove_dir_all(dir).await.unwrap();
+	}
+}
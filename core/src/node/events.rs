@@ -0,0 +1,282 @@
+//! A single bus for the events subsystems publish, with one broadcast channel per typed topic.
+//!
+//! Subscribers get the latest state of the topic replayed before the live events, so one that
+//! joins late (a frontend reconnecting, a service starting after the volumes were scanned) doesn't
+//! have to query for what it missed. Which events make up that state is up to each topic, through
+//! [`Topic::replay`].
+
+use std::{
+	any::{Any, TypeId},
+	collections::{HashMap, VecDeque},
+	fmt,
+	sync::{Arc, Mutex},
+};
+
+use futures::Stream;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::warn;
+
+/// Events buffered per subscriber before the slowest ones start missing events
+const TOPIC_CAPACITY: usize = 1024;
+
+/// Replayed events kept per topic, the oldest are dropped past this
+const MAX_REPLAYED: usize = 512;
+
+/// What replaying a topic to a new subscriber should do with an event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Replay {
+	/// Only sent to the current subscribers
+	Skip,
+	/// Replayed, replacing the previous event with the same key
+	Keep(String),
+	/// Replayed under a new key, replacing the previous events with either key
+	Rekey { old: String, new: String },
+	/// Not replayed, and the previous event with the same key isn't either anymore
+	Forget(String),
+}
+
+pub trait Topic: fmt::Debug + Clone + Send + Sync + 'static {
+	/// Used in logs
+	const NAME: &'static str;
+
+	fn replay(&self) -> Replay {
+		Replay::Skip
+	}
+}
+
+struct Channel<T> {
+	tx: broadcast::Sender<T>,
+	/// Locked while sending, so a subscriber sees every event exactly once, replayed or live
+	replayed: Mutex<VecDeque<(String, T)>>,
+}
+
+impl<T: Topic> Channel<T> {
+	fn new() -> Self {
+		Self {
+			tx: broadcast::channel(TOPIC_CAPACITY).0,
+			replayed: Mutex::new(VecDeque::new()),
+		}
+	}
+
+	fn replayed(&self) -> std::sync::MutexGuard<'_, VecDeque<(String, T)>> {
+		self.replayed.lock().expect("event bus lock poisoned")
+	}
+}
+
+#[derive(Default)]
+pub struct EventBus {
+	topics: Mutex<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+}
+
+impl fmt::Debug for EventBus {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("EventBus").finish_non_exhaustive()
+	}
+}
+
+impl EventBus {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// A handle to publish on the topic, cheap to clone and to hand to other subsystems
+	pub fn publisher<T: Topic>(&self) -> Publisher<T> {
+		Publisher(self.channel())
+	}
+
+	pub fn publish<T: Topic>(&self, event: T) {
+		self.publisher().send(event);
+	}
+
+	/// Subscribes to the topic, starting with the events it replays
+	pub fn subscribe<T: Topic>(&self) -> Subscription<T> {
+		self.publisher().subscribe()
+	}
+
+	fn channel<T: Topic>(&self) -> Arc<Channel<T>> {
+		let mut topics = self.topics.lock().expect("event bus lock poisoned");
+		let channel = topics
+			.entry(TypeId::of::<T>())
+			.or_insert_with(|| Arc::new(Channel::<T>::new()) as Arc<dyn Any + Send + Sync>)
+			.clone();
+
+		channel
+			.downcast()
+			.expect("topics are keyed by their type id")
+	}
+}
+
+pub struct Publisher<T>(Arc<Channel<T>>);
+
+impl<T> Clone for Publisher<T> {
+	fn clone(&self) -> Self {
+		Self(Arc::clone(&self.0))
+	}
+}
+
+impl<T: Topic> fmt::Debug for Publisher<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("Publisher").field(&T::NAME).finish()
+	}
+}
+
+impl<T: Topic> Publisher<T> {
+	/// A publisher that isn't connected to any bus, for code that runs outside a node
+	pub fn detached() -> Self {
+		Self(Arc::new(Channel::new()))
+	}
+
+	pub fn send(&self, event: T) {
+		let mut replayed = self.0.replayed();
+
+		match event.replay() {
+			Replay::Skip => {}
+			Replay::Keep(key) => {
+				replayed.retain(|(existing, _)| *existing != key);
+				if replayed.len() == MAX_REPLAYED {
+					replayed.pop_front();
+				}
+				replayed.push_back((key, event.clone()));
+			}
+			Replay::Rekey { old, new } => {
+				replayed.retain(|(existing, _)| *existing != old && *existing != new);
+				if replayed.len() == MAX_REPLAYED {
+					replayed.pop_front();
+				}
+				replayed.push_back((new, event.clone()));
+			}
+			Replay::Forget(key) => replayed.retain(|(existing, _)| *existing != key),
+		}
+
+		// No subscribers isn't an error, the replayed events are there for the next ones
+		let _ = self.0.tx.send(event);
+	}
+
+	pub fn subscribe(&self) -> Subscription<T> {
+		let replayed = self.0.replayed();
+
+		Subscription {
+			replay: replayed.iter().map(|(_, event)| event.clone()).collect(),
+			rx: self.0.tx.subscribe(),
+		}
+	}
+}
+
+pub struct Subscription<T> {
+	replay: VecDeque<T>,
+	rx: broadcast::Receiver<T>,
+}
+
+impl<T: Topic> Subscription<T> {
+	/// The next event, the replayed ones first. `None` once the bus and every publisher of the
+	/// topic are gone, for the bus of a node that's when the node is dropped.
+	///
+	/// Subscribers too slow to keep up miss the oldest live events, a warning is logged when they
+	/// do.
+	pub async fn recv(&mut self) -> Option<T> {
+		if let Some(event) = self.replay.pop_front() {
+			return Some(event);
+		}
+
+		loop {
+			match self.rx.recv().await {
+				Ok(event) => return Some(event),
+				Err(RecvError::Lagged(missed)) => {
+					warn!(topic = T::NAME, %missed, "Event subscriber lagged behind;");
+				}
+				Err(RecvError::Closed) => return None,
+			}
+		}
+	}
+
+	/// For rspc subscriptions
+	pub fn into_stream(mut self) -> impl Stream<Item = T> + Send {
+		async_stream::stream! {
+			while let Some(event) = self.recv().await {
+				yield event;
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Debug, Clone, PartialEq)]
+	enum DriveEvent {
+		Plugged(&'static str),
+		Renamed {
+			old: &'static str,
+			new: &'static str,
+		},
+		Unplugged(&'static str),
+		Blinked,
+	}
+
+	impl Topic for DriveEvent {
+		const NAME: &'static str = "drive";
+
+		fn replay(&self) -> Replay {
+			match self {
+				Self::Plugged(name) => Replay::Keep(name.to_string()),
+				Self::Renamed { old, new } => Replay::Rekey {
+					old: old.to_string(),
+					new: new.to_string(),
+				},
+				Self::Unplugged(name) => Replay::Forget(name.to_string()),
+				Self::Blinked => Replay::Skip,
+			}
+		}
+	}
+
+	#[tokio::test]
+	async fn late_subscribers_get_the_latest_state_first() {
+		let bus = EventBus::new();
+
+		bus.publish(DriveEvent::Plugged("a"));
+		bus.publish(DriveEvent::Plugged("b"));
+		bus.publish(DriveEvent::Blinked);
+		bus.publish(DriveEvent::Unplugged("a"));
+
+		let mut subscription = bus.subscribe::<DriveEvent>();
+		bus.publish(DriveEvent::Blinked);
+
+		assert_eq!(subscription.recv().await, Some(DriveEvent::Plugged("b")));
+		assert_eq!(subscription.recv().await, Some(DriveEvent::Blinked));
+	}
+
+	#[tokio::test]
+	async fn renamed_events_are_forgotten_under_their_new_key() {
+		let bus = EventBus::new();
+
+		bus.publish(DriveEvent::Plugged("a"));
+		bus.publish(DriveEvent::Renamed { old: "a", new: "b" });
+		bus.publish(DriveEvent::Unplugged("b"));
+
+		let mut subscription = bus.subscribe::<DriveEvent>();
+		bus.publish(DriveEvent::Blinked);
+
+		assert_eq!(subscription.recv().await, Some(DriveEvent::Blinked));
+	}
+
+	#[tokio::test]
+	async fn topics_are_independent() {
+		#[derive(Debug, Clone, PartialEq)]
+		struct Other;
+
+		impl Topic for Other {
+			const NAME: &'static str = "other";
+		}
+
+		let bus = EventBus::new();
+		let mut drives = bus.subscribe::<DriveEvent>();
+		let mut others = bus.subscribe::<Other>();
+
+		bus.publish(Other);
+		bus.publish(DriveEvent::Blinked);
+
+		assert_eq!(others.recv().await, Some(Other));
+		assert_eq!(drives.recv().await, Some(DriveEvent::Blinked));
+	}
+}
@@ -0,0 +1,171 @@
+//! Backend feature flags, so experimental subsystems can ship dark and be turned on per node.
+//!
+//! Flags toggled by the user are persisted in the node config. `SD_FEATURES` overrides them for
+//! the lifetime of the process: a comma separated list of flags, prefixed with `-` to force one
+//! off, e.g. `SD_FEATURES=autoTracking,-smartMonitoring`.
+
+use crate::api::BackendFeature;
+
+use std::{
+	collections::{HashMap, HashSet},
+	sync::RwLock,
+};
+
+use rspc::ErrorCode;
+use serde::Serialize;
+use specta::Type;
+use tracing::warn;
+
+const OVERRIDES_ENV: &str = "SD_FEATURES";
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct FeatureFlagState {
+	pub feature: BackendFeature,
+	pub enabled: bool,
+	/// Forced by `SD_FEATURES`, toggling the flag has no effect until the override is removed
+	pub overridden: bool,
+}
+
+pub struct FeatureFlags {
+	enabled: RwLock<HashSet<BackendFeature>>,
+	overrides: HashMap<BackendFeature, bool>,
+}
+
+impl FeatureFlags {
+	pub(crate) fn new(persisted: &[BackendFeature]) -> Self {
+		let overrides = std::env::var(OVERRIDES_ENV)
+			.map(|value| parse_overrides(&value))
+			.unwrap_or_default();
+
+		Self::with_overrides(persisted, overrides)
+	}
+
+	fn with_overrides(persisted: &[BackendFeature], overrides: HashMap<BackendFeature, bool>) -> Self {
+		Self {
+			enabled: RwLock::new(persisted.iter().copied().collect()),
+			overrides,
+		}
+	}
+
+	pub fn is_enabled(&self, feature: BackendFeature) -> bool {
+		self.overrides.get(&feature).copied().unwrap_or_else(|| {
+			self.enabled
+				.read()
+				.expect("feature flags lock poisoned")
+				.contains(&feature)
+		})
+	}
+
+	/// For the routes of a gated feature, which refuse to run while it's off
+	pub(crate) fn ensure_enabled(&self, feature: BackendFeature) -> Result<(), rspc::Error> {
+		if self.is_enabled(feature) {
+			Ok(())
+		} else {
+			Err(rspc::Error::new(
+				ErrorCode::Forbidden,
+				format!("The {} feature is turned off", feature.name()),
+			))
+		}
+	}
+
+	/// Flags currently in effect, overrides included
+	pub fn enabled(&self) -> Vec<BackendFeature> {
+		BackendFeature::ALL
+			.into_iter()
+			.filter(|feature| self.is_enabled(*feature))
+			.collect()
+	}
+
+	pub fn list(&self) -> Vec<FeatureFlagState> {
+		BackendFeature::ALL
+			.into_iter()
+			.map(|feature| FeatureFlagState {
+				feature,
+				enabled: self.is_enabled(feature),
+				overridden: self.overrides.contains_key(&feature),
+			})
+			.collect()
+	}
+
+	/// Only updates the flags in memory, persisting them is up to the caller
+	pub(crate) fn set(&self, feature: BackendFeature, enabled: bool) {
+		let mut features = self.enabled.write().expect("feature flags lock poisoned");
+		if enabled {
+			features.insert(feature);
+		} else {
+			features.remove(&feature);
+		}
+	}
+}
+
+fn parse_overrides(value: &str) -> HashMap<BackendFeature, bool> {
+	value
+		.split(',')
+		.map(str::trim)
+		.filter(|name| !name.is_empty())
+		.filter_map(|name| {
+			let (name, enabled) = match name.strip_prefix('-') {
+				Some(name) => (name, false),
+				None => (name, true),
+			};
+
+			let feature = BackendFeature::from_name(name);
+			if feature.is_none() {
+				warn!(%name, "Unknown feature flag in {OVERRIDES_ENV};");
+			}
+
+			feature.map(|feature| (feature, enabled))
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_overrides() {
+		let overrides = parse_overrides("autoTracking, -smartMonitoring,,unknownFlag");
+
+		assert_eq!(overrides.len(), 2);
+		assert_eq!(overrides.get(&BackendFeature::AutoTracking), Some(&true));
+		assert_eq!(overrides.get(&BackendFeature::SmartMonitoring), Some(&false));
+	}
+
+	#[test]
+	fn overrides_win_over_persisted_flags() {
+		let flags = FeatureFlags::with_overrides(
+			&[BackendFeature::SmartMonitoring],
+			HashMap::from([(BackendFeature::SmartMonitoring, false)]),
+		);
+		assert!(!flags.is_enabled(BackendFeature::SmartMonitoring));
+
+		flags.set(BackendFeature::RemoteVolumeBrowsing, true);
+		assert_eq!(flags.enabled(), vec![BackendFeature::RemoteVolumeBrowsing]);
+		assert!(flags
+			.list()
+			.iter()
+			.any(|state| state.feature == BackendFeature::SmartMonitoring && state.overridden));
+	}
+
+	#[test]
+	fn disabled_flags_block_their_feature() {
+		let flags = FeatureFlags::with_overrides(
+			&[BackendFeature::AutoTracking],
+			HashMap::from([(BackendFeature::RemoteVolumeBrowsing, false)]),
+		);
+		assert!(flags
+			.ensure_enabled(BackendFeature::RemoteVolumeBrowsing)
+			.is_err());
+		assert!(flags.ensure_enabled(BackendFeature::AutoTracking).is_ok());
+
+		flags.set(BackendFeature::AutoTracking, false);
+		assert!(flags.ensure_enabled(BackendFeature::AutoTracking).is_err());
+
+		// Still forced off, whatever the user toggles
+		flags.set(BackendFeature::RemoteVolumeBrowsing, true);
+		assert!(flags
+			.ensure_enabled(BackendFeature::RemoteVolumeBrowsing)
+			.is_err());
+	}
+}
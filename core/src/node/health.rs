@@ -0,0 +1,257 @@
+//! One report of how the core is doing, for the status indicator of the desktop app and
+//! `sd-cli health`.
+//!
+//! Each subsystem gets its own severity, the report's is the worst of them. Gathering it doesn't
+//! change anything: no connection is opened to the cloud and no volume is rescanned.
+
+use crate::{
+	volume::{DegradedMode, WatcherStatus},
+	Node,
+};
+
+use sd_core_heavy_lifting::job_system::report;
+
+use std::sync::atomic::Ordering;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// Jobs waiting for a worker past which the queue is reported as backed up
+const JOB_BACKLOG_WARNING: u32 = 16;
+
+#[derive(
+	Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Type,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+	Ok,
+	/// Working, but with less than it should, like volumes without their capacity
+	Warning,
+	/// Not working, like a watcher that stopped
+	Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct CoreHealth {
+	/// The worst severity of the subsystems
+	pub severity: Severity,
+	pub checked_at: DateTime<Utc>,
+	pub watcher: WatcherHealth,
+	pub volumes: VolumesHealth,
+	pub sync: SyncHealth,
+	pub jobs: JobsHealth,
+}
+
+/// An error once the watcher stopped, a warning when its last check failed
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct WatcherHealth {
+	pub severity: Severity,
+	pub status: WatcherStatus,
+}
+
+/// A warning when the volumes were listed without some of their data
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct VolumesHealth {
+	pub severity: Severity,
+	pub degraded_mode: DegradedMode,
+}
+
+/// A warning when libraries sync but the cloud isn't reachable, offline mode aside
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct SyncHealth {
+	pub severity: Severity,
+	pub offline: bool,
+	pub cloud_connected: bool,
+	/// Libraries generating sync operations
+	pub syncing_libraries: u32,
+}
+
+/// A warning when more than [`JOB_BACKLOG_WARNING`] jobs wait for a worker
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct JobsHealth {
+	pub severity: Severity,
+	pub running: u32,
+	pub queued: u32,
+}
+
+impl WatcherHealth {
+	fn new(status: WatcherStatus) -> Self {
+		let severity = if !status.running {
+			Severity::Error
+		} else if status.last_error.is_some() {
+			Severity::Warning
+		} else {
+			Severity::Ok
+		};
+
+		Self { severity, status }
+	}
+}
+
+impl VolumesHealth {
+	fn new(degraded_mode: DegradedMode) -> Self {
+		Self {
+			severity: if degraded_mode.is_degraded() {
+				Severity::Warning
+			} else {
+				Severity::Ok
+			},
+			degraded_mode,
+		}
+	}
+}
+
+impl SyncHealth {
+	fn new(offline: bool, cloud_connected: bool, syncing_libraries: u32) -> Self {
+		Self {
+			severity: if syncing_libraries > 0 && !offline && !cloud_connected {
+				Severity::Warning
+			} else {
+				Severity::Ok
+			},
+			offline,
+			cloud_connected,
+			syncing_libraries,
+		}
+	}
+}
+
+impl JobsHealth {
+	fn new(running: u32, queued: u32) -> Self {
+		Self {
+			severity: if queued > JOB_BACKLOG_WARNING {
+				Severity::Warning
+			} else {
+				Severity::Ok
+			},
+			running,
+			queued,
+		}
+	}
+}
+
+impl CoreHealth {
+	fn new(
+		watcher: WatcherHealth,
+		volumes: VolumesHealth,
+		sync: SyncHealth,
+		jobs: JobsHealth,
+	) -> Self {
+		Self {
+			severity: [
+				watcher.severity,
+				volumes.severity,
+				sync.severity,
+				jobs.severity,
+			]
+			.into_iter()
+			.max()
+			.unwrap_or(Severity::Ok),
+			checked_at: Utc::now(),
+			watcher,
+			volumes,
+			sync,
+			jobs,
+		}
+	}
+}
+
+/// Gathers the status of the subsystems into one report
+pub async fn core_health(node: &Node) -> CoreHealth {
+	let watcher = match node.volumes.snapshot().await {
+		Ok(snapshot) => snapshot.watcher,
+		// The volume manager is gone, and its watcher along with it
+		Err(e) => WatcherStatus {
+			running: false,
+			last_error: Some(e.to_string()),
+			..Default::default()
+		},
+	};
+
+	let mut syncing_libraries = 0;
+	for library in node.libraries.get_all().await {
+		if library
+			.config()
+			.await
+			.generate_sync_operations
+			.load(Ordering::Relaxed)
+		{
+			syncing_libraries += 1;
+		}
+	}
+
+	let (mut running, mut queued) = (0, 0);
+	for report in node.job_system.get_active_reports().await.into_values() {
+		match report.status {
+			report::Status::Running => running += 1,
+			report::Status::Queued => queued += 1,
+			_ => {}
+		}
+	}
+	running += node.old_jobs.get_running_reports().await.len() as u32;
+	queued += node.old_jobs.queued_count().await as u32;
+
+	CoreHealth::new(
+		WatcherHealth::new(watcher),
+		VolumesHealth::new(node.volumes.degraded_mode()),
+		SyncHealth::new(
+			node.offline.is_offline(),
+			node.cloud_services.is_connected().await,
+			syncing_libraries,
+		),
+		JobsHealth::new(running, queued),
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::volume::MissingVolumeData;
+
+	fn watcher(running: bool, last_error: Option<&str>) -> WatcherHealth {
+		WatcherHealth::new(WatcherStatus {
+			running,
+			last_error: last_error.map(str::to_string),
+			..Default::default()
+		})
+	}
+
+	#[test]
+	fn the_worst_subsystem_sets_the_severity() {
+		let healthy = CoreHealth::new(
+			watcher(true, None),
+			VolumesHealth::new(DegradedMode::default()),
+			SyncHealth::new(false, true, 1),
+			JobsHealth::new(2, 0),
+		);
+		assert_eq!(healthy.severity, Severity::Ok);
+
+		let degraded = CoreHealth::new(
+			watcher(true, Some("mount table unreadable")),
+			VolumesHealth::new(DegradedMode::new([MissingVolumeData::Capacity])),
+			SyncHealth::new(false, true, 1),
+			JobsHealth::new(2, 0),
+		);
+		assert_eq!(degraded.watcher.severity, Severity::Warning);
+		assert_eq!(degraded.severity, Severity::Warning);
+
+		let stopped = CoreHealth::new(
+			watcher(false, None),
+			VolumesHealth::new(DegradedMode::default()),
+			SyncHealth::new(false, true, 1),
+			JobsHealth::new(2, JOB_BACKLOG_WARNING + 1),
+		);
+		assert_eq!(stopped.jobs.severity, Severity::Warning);
+		assert_eq!(stopped.severity, Severity::Error);
+	}
+
+	#[test]
+	fn sync_is_only_a_concern_when_it_should_reach_the_cloud() {
+		assert_eq!(SyncHealth::new(false, false, 1).severity, Severity::Warning);
+		// Offline on purpose, or nothing to sync
+		assert_eq!(SyncHealth::new(true, false, 1).severity, Severity::Ok);
+		assert_eq!(SyncHealth::new(false, false, 0).severity, Severity::Ok);
+	}
+}
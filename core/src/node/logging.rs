@@ -0,0 +1,543 @@
+//! Log management: the tracing subscriber, size-based rotation of the log files, per-module
+//! levels that can be changed at runtime, and short captures of a module at `TRACE` for
+//! diagnostics, which are kept out of the log files.
+//!
+//! Runtime levels are layered over `RUST_LOG` (or our defaults) and aren't persisted, a restart
+//! goes back to the base filter.
+
+use std::{
+	collections::BTreeMap,
+	fs::{self, File, OpenOptions},
+	io::{self, Write},
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex, OnceLock, RwLock},
+	time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use thiserror::Error;
+use tracing::{callsite::rebuild_interest_cache, info, Metadata};
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+use tracing_subscriber::{
+	filter::{filter_fn, FromEnvError},
+	fmt::{format::Format, MakeWriter},
+	prelude::*,
+	registry, reload, EnvFilter, Registry,
+};
+use uuid::Uuid;
+
+const LOG_FILE_NAME: &str = "sd.log";
+
+/// Rotated files are kept as `sd.log.1` (the most recent) up to `sd.log.{MAX_LOG_FILES - 1}`
+const MAX_LOG_FILES: usize = 4;
+
+const MAX_LOG_FILE_SIZE: u64 = 20 * 1024 * 1024;
+
+pub const MAX_CAPTURE_DURATION: Duration = Duration::from_secs(5 * 60);
+
+/// Captured logs past this size are dropped, a chatty module at `TRACE` can get big fast
+const MAX_CAPTURE_SIZE: usize = 8 * 1024 * 1024;
+
+static LOGS: OnceLock<Logs> = OnceLock::new();
+
+#[derive(Error, Debug)]
+pub enum LogsError {
+	#[error("the logger wasn't initialized")]
+	NotInitialized,
+	#[error("invalid module name: '{0}'")]
+	InvalidModule(String),
+	#[error("captures can last at most {} seconds", MAX_CAPTURE_DURATION.as_secs())]
+	CaptureTooLong,
+	#[error("failed to reload the log filter: {0}")]
+	Reload(#[from] reload::Error),
+}
+
+impl From<LogsError> for rspc::Error {
+	fn from(e: LogsError) -> Self {
+		let code = match e {
+			LogsError::NotInitialized | LogsError::Reload(_) => {
+				rspc::ErrorCode::InternalServerError
+			}
+			LogsError::InvalidModule(_) | LogsError::CaptureTooLong => rspc::ErrorCode::BadRequest,
+		};
+
+		rspc::Error::with_cause(code, e.to_string(), e)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Type)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+	Off,
+	Error,
+	Warn,
+	Info,
+	Debug,
+	Trace,
+}
+
+impl LogLevel {
+	fn as_directive(self) -> &'static str {
+		match self {
+			Self::Off => "off",
+			Self::Error => "error",
+			Self::Warn => "warn",
+			Self::Info => "info",
+			Self::Debug => "debug",
+			Self::Trace => "trace",
+		}
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct LogFilters {
+	/// `RUST_LOG` or the default directives the app started with
+	pub base: String,
+	/// Levels set at runtime, by module
+	pub overrides: BTreeMap<String, LogLevel>,
+	/// Modules being captured right now
+	pub capturing: Vec<String>,
+}
+
+struct Capture {
+	id: Uuid,
+	module: String,
+	output: Arc<Mutex<Vec<u8>>>,
+}
+
+pub struct Logs {
+	base: String,
+	overrides: RwLock<BTreeMap<String, LogLevel>>,
+	captures: Arc<RwLock<Vec<Capture>>>,
+	filter: reload::Handle<EnvFilter, Registry>,
+}
+
+impl Logs {
+	pub fn get() -> Result<&'static Self, LogsError> {
+		LOGS.get().ok_or(LogsError::NotInitialized)
+	}
+
+	pub fn filters(&self) -> LogFilters {
+		LogFilters {
+			base: self.base.clone(),
+			overrides: self
+				.overrides
+				.read()
+				.expect("log overrides lock poisoned")
+				.clone(),
+			capturing: self
+				.captures
+				.read()
+				.expect("log captures lock poisoned")
+				.iter()
+				.map(|capture| capture.module.clone())
+				.collect(),
+		}
+	}
+
+	/// Sets the level of `module` (e.g. `sd_core::volume`), `None` goes back to the base filter
+	pub fn set_level(&self, module: &str, level: Option<LogLevel>) -> Result<(), LogsError> {
+		validate_module(module)?;
+
+		{
+			let mut overrides = self.overrides.write().expect("log overrides lock poisoned");
+			match level {
+				Some(level) => overrides.insert(module.to_string(), level),
+				None => overrides.remove(module),
+			};
+		}
+
+		info!(%module, ?level, "Changed log level;");
+		self.reload()
+	}
+
+	/// Records everything `module` logs at `TRACE` for `duration`, returning the formatted lines
+	pub async fn capture(&self, module: &str, duration: Duration) -> Result<String, LogsError> {
+		validate_module(module)?;
+		if duration > MAX_CAPTURE_DURATION {
+			return Err(LogsError::CaptureTooLong);
+		}
+
+		let id = Uuid::new_v4();
+		let output = Arc::new(Mutex::new(Vec::new()));
+		self.captures
+			.write()
+			.expect("log captures lock poisoned")
+			.push(Capture {
+				id,
+				module: module.to_string(),
+				output: Arc::clone(&output),
+			});
+
+		// The capture filter's verdict is cached by callsite, it has to be recomputed on changes
+		rebuild_interest_cache();
+
+		info!(%module, ?duration, "Capturing logs;");
+		tokio::time::sleep(duration).await;
+
+		self.captures
+			.write()
+			.expect("log captures lock poisoned")
+			.retain(|capture| capture.id != id);
+		rebuild_interest_cache();
+
+		let output = std::mem::take(&mut *output.lock().expect("log capture lock poisoned"));
+		Ok(String::from_utf8_lossy(&output).into_owned())
+	}
+
+	fn reload(&self) -> Result<(), LogsError> {
+		let directives = directives(
+			&self.base,
+			&self.overrides.read().expect("log overrides lock poisoned"),
+		);
+
+		self.filter
+			.reload(EnvFilter::builder().parse_lossy(directives))
+			.map_err(Into::into)
+	}
+}
+
+/// Builds the filter of the log files and stdout, overrides go after the base so they win
+fn directives(base: &str, overrides: &BTreeMap<String, LogLevel>) -> String {
+	let mut directives = vec![base.to_string()];
+	directives.extend(
+		overrides
+			.iter()
+			.map(|(module, level)| format!("{module}={}", level.as_directive())),
+	);
+	directives.retain(|directive| !directive.is_empty());
+	directives.join(",")
+}
+
+fn validate_module(module: &str) -> Result<(), LogsError> {
+	let is_valid = !module.is_empty()
+		&& module.split("::").all(|segment| {
+			!segment.is_empty()
+				&& segment
+					.chars()
+					.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+		});
+
+	if is_valid {
+		Ok(())
+	} else {
+		Err(LogsError::InvalidModule(module.to_string()))
+	}
+}
+
+fn is_captured(captures: &RwLock<Vec<Capture>>, metadata: &Metadata<'_>) -> bool {
+	captures
+		.read()
+		.expect("log captures lock poisoned")
+		.iter()
+		.any(|capture| targets_module(metadata.target(), &capture.module))
+}
+
+fn targets_module(target: &str, module: &str) -> bool {
+	target
+		.strip_prefix(module)
+		.is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+/// Hands every log line to the captures interested in its target
+#[derive(Clone)]
+struct CaptureWriter {
+	captures: Arc<RwLock<Vec<Capture>>>,
+}
+
+struct CaptureLine {
+	captures: Arc<RwLock<Vec<Capture>>>,
+	target: String,
+	line: Vec<u8>,
+}
+
+impl<'a> MakeWriter<'a> for CaptureWriter {
+	type Writer = CaptureLine;
+
+	fn make_writer(&'a self) -> Self::Writer {
+		CaptureLine {
+			captures: Arc::clone(&self.captures),
+			target: String::new(),
+			line: Vec::new(),
+		}
+	}
+
+	fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+		CaptureLine {
+			captures: Arc::clone(&self.captures),
+			target: meta.target().to_string(),
+			line: Vec::new(),
+		}
+	}
+}
+
+impl Write for CaptureLine {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.line.extend_from_slice(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+impl Drop for CaptureLine {
+	fn drop(&mut self) {
+		let Ok(captures) = self.captures.read() else {
+			return;
+		};
+
+		for capture in captures
+			.iter()
+			.filter(|capture| targets_module(&self.target, &capture.module))
+		{
+			if let Ok(mut output) = capture.output.lock() {
+				if output.len() + self.line.len() <= MAX_CAPTURE_SIZE {
+					output.extend_from_slice(&self.line);
+				}
+			}
+		}
+	}
+}
+
+/// A log file that moves itself to `sd.log.1` once it reaches `max_size`
+struct RotatingFile {
+	dir: PathBuf,
+	file: File,
+	size: u64,
+	max_size: u64,
+}
+
+impl RotatingFile {
+	fn open(dir: impl Into<PathBuf>, max_size: u64) -> io::Result<Self> {
+		let dir = dir.into();
+		fs::create_dir_all(&dir)?;
+		remove_daily_logs(&dir);
+
+		let file = open_log(&dir)?;
+		let size = file.metadata()?.len();
+
+		Ok(Self {
+			dir,
+			file,
+			size,
+			max_size,
+		})
+	}
+
+	fn rotate(&mut self) -> io::Result<()> {
+		self.file.flush()?;
+
+		for index in (1..MAX_LOG_FILES).rev() {
+			let from = if index == 1 {
+				self.dir.join(LOG_FILE_NAME)
+			} else {
+				rotated_path(&self.dir, index - 1)
+			};
+			let to = rotated_path(&self.dir, index);
+
+			if from.exists() {
+				// Windows refuses to rename over an existing file
+				let _ = fs::remove_file(&to);
+				fs::rename(from, to)?;
+			}
+		}
+
+		self.file = open_log(&self.dir)?;
+		self.size = 0;
+
+		Ok(())
+	}
+}
+
+impl Write for RotatingFile {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+			self.rotate()?;
+		}
+
+		let written = self.file.write(buf)?;
+		self.size += written as u64;
+		Ok(written)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.file.flush()
+	}
+}
+
+fn open_log(dir: &Path) -> io::Result<File> {
+	OpenOptions::new()
+		.create(true)
+		.append(true)
+		.open(dir.join(LOG_FILE_NAME))
+}
+
+fn rotated_path(dir: &Path, index: usize) -> PathBuf {
+	dir.join(format!("{LOG_FILE_NAME}.{index}"))
+}
+
+/// Logs used to be rotated daily as `sd.log.YYYY-MM-DD`, those aren't cleaned up by the size rotation
+fn remove_daily_logs(dir: &Path) {
+	let Ok(entries) = fs::read_dir(dir) else {
+		return;
+	};
+
+	for entry in entries.flatten() {
+		let name = entry.file_name();
+		let Some(suffix) = name
+			.to_str()
+			.and_then(|name| name.strip_prefix(LOG_FILE_NAME))
+			.and_then(|rest| rest.strip_prefix('.'))
+		else {
+			continue;
+		};
+
+		if suffix.parse::<usize>().is_err() {
+			let _ = fs::remove_file(entry.path());
+		}
+	}
+}
+
+pub(crate) fn init(data_dir: &Path) -> Result<WorkerGuard, FromEnvError> {
+	let (logfile, guard) = NonBlocking::new(
+		RotatingFile::open(data_dir.join("logs"), MAX_LOG_FILE_SIZE)
+			.expect("Error setting up log file!"),
+	);
+
+	// Set a default if the user hasn't set an override
+	if std::env::var("RUST_LOG") == Err(std::env::VarError::NotPresent) {
+		let level = if cfg!(debug_assertions) {
+			"debug"
+		} else {
+			"info"
+		};
+
+		std::env::set_var(
+			"RUST_LOG",
+			format!(
+				"info,\
+				iroh_net=info,\
+				sd_core={level},\
+				sd_p2p={level},\
+				sd_core_heavy_lifting={level},\
+				sd_task_system={level},\
+				sd_ai={level}"
+			),
+		);
+	}
+
+	let base = std::env::var("RUST_LOG").unwrap_or_default();
+	let (filter, filter_handle) = reload::Layer::new(EnvFilter::try_from_default_env()?);
+
+	let captures = Arc::new(RwLock::new(Vec::new()));
+
+	// Captures get their own layer, so a module at `TRACE` doesn't flood the log files and stdout
+	let registry = registry()
+		.with(
+			tracing_subscriber::fmt::layer()
+				.with_file(true)
+				.with_line_number(true)
+				.with_ansi(false)
+				.with_target(true)
+				.with_writer(logfile)
+				.and_then(
+					tracing_subscriber::fmt::layer()
+						.with_file(true)
+						.with_line_number(true)
+						.with_writer(std::io::stdout)
+						.event_format(Format::default().pretty()),
+				)
+				.with_filter(filter),
+		)
+		.with(
+			tracing_subscriber::fmt::layer()
+				.with_file(true)
+				.with_line_number(true)
+				.with_ansi(false)
+				.with_target(true)
+				.with_writer(CaptureWriter {
+					captures: Arc::clone(&captures),
+				})
+				.with_filter(filter_fn({
+					let captures = Arc::clone(&captures);
+					move |metadata| is_captured(&captures, metadata)
+				})),
+		);
+
+	#[cfg(target_os = "android")]
+	let registry = registry.with(tracing_android::layer("com.spacedrive.app").unwrap());
+
+	registry.init();
+
+	let _ = LOGS.set(Logs {
+		base,
+		overrides: RwLock::default(),
+		captures,
+		filter: filter_handle,
+	});
+
+	Ok(guard)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn overrides_come_after_the_base() {
+		let overrides = BTreeMap::from([
+			("sd_core::volume".to_string(), LogLevel::Debug),
+			("iroh_net".to_string(), LogLevel::Off),
+		]);
+
+		assert_eq!(
+			directives("info,sd_core=info", &overrides),
+			"info,sd_core=info,iroh_net=off,sd_core::volume=debug"
+		);
+		assert_eq!(directives("", &BTreeMap::new()), "");
+	}
+
+	#[test]
+	fn matches_module_targets() {
+		assert!(targets_module("sd_core::volume", "sd_core::volume"));
+		assert!(targets_module("sd_core::volume::watcher", "sd_core::volume"));
+		assert!(!targets_module("sd_core::volumes", "sd_core::volume"));
+		assert!(!targets_module("sd_core", "sd_core::volume"));
+	}
+
+	#[test]
+	fn rejects_invalid_modules() {
+		assert!(validate_module("sd_core::volume").is_ok());
+		assert!(validate_module("sd_core=trace").is_err());
+		assert!(validate_module("sd_core,iroh").is_err());
+		assert!(validate_module("sd_core::").is_err());
+		assert!(validate_module("").is_err());
+	}
+
+	#[test]
+	fn rotates_by_size() {
+		let dir = std::env::temp_dir().join(format!("sd-logs-{}", Uuid::new_v4()));
+		fs::create_dir_all(&dir).unwrap();
+		fs::write(dir.join("sd.log.2024-01-01"), b"old").unwrap();
+
+		let mut file = RotatingFile::open(&dir, 1024).unwrap();
+		assert!(!dir.join("sd.log.2024-01-01").exists());
+
+		let line = [b'a'; 513];
+		for _ in 0..(MAX_LOG_FILES * 2) {
+			file.write_all(&line).unwrap();
+		}
+		file.flush().unwrap();
+
+		assert!(dir.join(LOG_FILE_NAME).exists());
+		for index in 1..MAX_LOG_FILES {
+			assert!(rotated_path(&dir, index).exists());
+		}
+		assert!(!rotated_path(&dir, MAX_LOG_FILES).exists());
+
+		fs::remove_dir_all(dir).unwrap();
+	}
+}
@@ -0,0 +1,184 @@
+//! Sync can be held back while the network is metered, like a phone hotspot or a capped plan, when
+//! the user only wants it on unmetered networks:
+//!  - the cloud sync sender and receiver of every library are stopped, changes keep being recorded
+//!  - the ones that were running are started again once the network is unmetered or the setting
+//!    is turned off, a library whose sync was stopped stays that way
+//!
+//! Whether the network is metered comes from NetworkManager on Linux, other platforms are taken as
+//! unmetered until they can tell.
+
+use crate::Node;
+
+use sd_core_cloud_services::CloudSyncActors;
+
+use std::{
+	collections::HashMap,
+	mem,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, Mutex, PoisonError,
+	},
+	time::Duration,
+};
+
+use futures_concurrency::future::Join;
+use tokio::{sync::Notify, time::sleep};
+use tracing::info;
+use uuid::Uuid;
+
+/// How often the network is checked, there's no following it for changes
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The actors held back, the others are left alone
+const HELD_ACTORS: [CloudSyncActors; 2] = [CloudSyncActors::Sender, CloudSyncActors::Receiver];
+
+pub struct MeteredNetwork {
+	metered: AtomicBool,
+	holding_sync: AtomicBool,
+	/// The actors each library had running when sync was held back, the only ones started again
+	held_actors: Mutex<HashMap<Uuid, Vec<CloudSyncActors>>>,
+	check: Notify,
+}
+
+impl MeteredNetwork {
+	pub(crate) fn new() -> Self {
+		Self {
+			metered: AtomicBool::new(false),
+			holding_sync: AtomicBool::new(false),
+			held_actors: Mutex::default(),
+			check: Notify::new(),
+		}
+	}
+
+	/// Whether the network was metered when it was last checked
+	pub fn is_metered(&self) -> bool {
+		self.metered.load(Ordering::Relaxed)
+	}
+
+	/// Whether cloud sync is held back, libraries starting their sync leave it stopped meanwhile
+	pub fn holds_sync(&self) -> bool {
+		self.holding_sync.load(Ordering::Relaxed)
+	}
+
+	/// Records the actors a library starting its sync left stopped, to start them with the others
+	pub(crate) fn hold_back(&self, library_id: Uuid) {
+		self.held_actors
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.insert(library_id, HELD_ACTORS.to_vec());
+	}
+
+	/// Checks the network again right away, after the setting changed
+	pub(crate) fn recheck(&self) {
+		self.check.notify_one();
+	}
+
+	pub(crate) fn start(self: &Arc<Self>, node: &Arc<Node>) {
+		let this = Arc::clone(self);
+		let node = Arc::clone(node);
+		tokio::spawn(async move {
+			loop {
+				let metered = platform::is_metered().await;
+				this.metered.store(metered, Ordering::Relaxed);
+
+				let hold = metered && node.config.get().await.p2p.unmetered_sync_only;
+				if this.holding_sync.swap(hold, Ordering::Relaxed) != hold {
+					info!(%hold, "Holding sync back on a metered network changed;");
+
+					if hold {
+						this.stop_sync(&node).await;
+					} else {
+						this.restart_sync(&node).await;
+					}
+				}
+
+				tokio::select! {
+					() = this.check.notified() => {}
+					() = sleep(CHECK_INTERVAL) => {}
+				}
+			}
+		});
+	}
+
+	async fn stop_sync(&self, node: &Node) {
+		for library in node.libraries.get_all().await {
+			let actors = &library.cloud_sync_actors;
+
+			let mut running = Vec::with_capacity(HELD_ACTORS.len());
+			for actor in HELD_ACTORS {
+				if actors.is_running(actor).await {
+					running.push(actor);
+				}
+			}
+
+			running
+				.iter()
+				.map(|actor| actors.stop(*actor))
+				.collect::<Vec<_>>()
+				.join()
+				.await;
+
+			self.held_actors
+				.lock()
+				.unwrap_or_else(PoisonError::into_inner)
+				.entry(library.id)
+				.or_default()
+				.extend(running);
+		}
+	}
+
+	async fn restart_sync(&self, node: &Node) {
+		let mut held_actors = mem::take(
+			&mut *self
+				.held_actors
+				.lock()
+				.unwrap_or_else(PoisonError::into_inner),
+		);
+
+		for library in node.libraries.get_all().await {
+			if let Some(held) = held_actors.remove(&library.id) {
+				let actors = &library.cloud_sync_actors;
+				held.iter()
+					.map(|actor| actors.start(*actor))
+					.collect::<Vec<_>>()
+					.join()
+					.await;
+			}
+		}
+	}
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+	use tokio::process::Command;
+
+	/// NetworkManager's `Metered` property, metered when it's 1 or guessed to be when it's 3
+	pub async fn is_metered() -> bool {
+		let Ok(output) = Command::new("busctl")
+			.args([
+				"--system",
+				"get-property",
+				"org.freedesktop.NetworkManager",
+				"/org/freedesktop/NetworkManager",
+				"org.freedesktop.NetworkManager",
+				"Metered",
+			])
+			.output()
+			.await
+		else {
+			return false;
+		};
+
+		matches!(
+			String::from_utf8_lossy(&output.stdout).trim(),
+			"u 1" | "u 3"
+		)
+	}
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+	pub async fn is_metered() -> bool {
+		false
+	}
+}
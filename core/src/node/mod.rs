@@ -1,6 +1,31 @@
 pub mod config;
+mod core_config;
+mod events;
+mod feature_flags;
 mod hardware;
+mod health;
+pub(crate) mod logging;
+mod metered;
+mod offline;
 mod platform;
+mod power;
+mod secrets;
+mod telemetry;
 
+pub use core_config::{
+	BackupSchedule, BackupsConfig, CoreConfig, CoreConfigError, CoreConfigManager,
+	VolumeWatcherConfig, CORE_CONFIG_FILE,
+};
+pub use events::{EventBus, Publisher, Replay, Subscription, Topic};
+pub use feature_flags::{FeatureFlagState, FeatureFlags};
 pub use hardware::*;
+pub use health::{
+	core_health, CoreHealth, JobsHealth, Severity, SyncHealth, VolumesHealth, WatcherHealth,
+};
+pub use logging::{LogFilters, LogLevel, Logs, LogsError, MAX_CAPTURE_DURATION};
 pub use platform::*;
+pub use metered::MeteredNetwork;
+pub use offline::OfflineMode;
+pub use power::PowerManager;
+pub use secrets::{client_key, network_share_key, SecretStore, SecretStoreError, CLOUD_TOKENS_KEY};
+pub use telemetry::{Telemetry, TelemetryEvent};
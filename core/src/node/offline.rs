@@ -0,0 +1,54 @@
+//! Offline mode keeps the core from reaching out to the network on its own:
+//!  - p2p listeners, mDNS discovery and relays are shut down, without touching the p2p settings
+//!  - cloud services refuse to hand out connections, so cloud sync and the cloud api stay quiet
+//!
+//! Apps are expected to gate their own traffic (e.g. update checks) with [`OfflineMode::is_offline`]
+//! and can follow changes through [`CoreEvent::OfflineModeChanged`].
+
+use crate::{api::CoreEvent, invalidate_query, Node};
+
+use std::sync::{
+	atomic::{AtomicBool, Ordering},
+	Arc,
+};
+
+use tracing::info;
+
+pub struct OfflineMode {
+	offline: AtomicBool,
+}
+
+impl OfflineMode {
+	pub(crate) fn new(offline: bool) -> Self {
+		Self {
+			offline: AtomicBool::new(offline),
+		}
+	}
+
+	pub fn is_offline(&self) -> bool {
+		self.offline.load(Ordering::Relaxed)
+	}
+
+	/// Follows the node preference, suspending or resuming the networked services when it changes
+	pub(crate) fn start(self: &Arc<Self>, node: &Arc<Node>) {
+		let this = Arc::clone(self);
+		let node = Arc::clone(node);
+		let mut preferences_rx = node.config.preferences_watcher();
+		tokio::spawn(async move {
+			while preferences_rx.changed().await.is_ok() {
+				let offline = preferences_rx.borrow().offline;
+				if this.offline.swap(offline, Ordering::Relaxed) == offline {
+					continue;
+				}
+
+				info!(%offline, "Offline mode changed;");
+
+				node.cloud_services.set_offline(offline);
+				node.p2p.on_node_config_change().await;
+
+				node.emit(CoreEvent::OfflineModeChanged { offline });
+				invalidate_query!(node; node, "nodeState");
+			}
+		});
+	}
+}
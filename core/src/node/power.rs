@@ -0,0 +1,286 @@
+//! Keeps the system awake while jobs are running, so long copies and indexing
+//! don't get interrupted by the machine going to sleep.
+//!
+//! Each platform has its own way of holding a power assertion:
+//!  - macOS: an IOKit `PreventUserIdleSystemSleep` assertion, held through `caffeinate`
+//!  - Windows: `SetThreadExecutionState` on a dedicated thread
+//!  - Linux: a `systemd-inhibit` sleep/idle inhibitor lock
+
+use crate::{api::CoreEvent, Node};
+
+use std::{
+	collections::HashMap,
+	io,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, Mutex,
+	},
+	time::{Duration, Instant},
+};
+
+use tokio::{sync::broadcast::error::RecvError, time::interval};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Old jobs don't announce when they finish, so their holders which didn't report progress
+/// for this long are released. The ones of the job system are released when they finish.
+const STALE_HOLDER_TIMEOUT: Duration = Duration::from_secs(2 * 60);
+
+const STALE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+const REASON: &str = "Spacedrive is running jobs";
+
+#[derive(Default)]
+struct State {
+	holders: HashMap<Uuid, Instant>,
+	assertion: Option<platform::Assertion>,
+}
+
+/// Holds a power assertion as long as there is at least one holder and preventing sleep is enabled
+pub struct PowerManager {
+	enabled: AtomicBool,
+	state: Mutex<State>,
+}
+
+impl PowerManager {
+	pub(crate) fn new(enabled: bool) -> Self {
+		Self {
+			enabled: AtomicBool::new(enabled),
+			state: Mutex::default(),
+		}
+	}
+
+	/// Keeps the system awake on behalf of `holder` until it is released
+	pub fn hold(&self, holder: Uuid) {
+		let mut state = self.state.lock().expect("power state lock poisoned");
+		state.holders.insert(holder, Instant::now());
+		self.sync(&mut state);
+	}
+
+	pub fn release(&self, holder: Uuid) {
+		let mut state = self.state.lock().expect("power state lock poisoned");
+		if state.holders.remove(&holder).is_some() {
+			self.sync(&mut state);
+		}
+	}
+
+	pub fn set_enabled(&self, enabled: bool) {
+		if self.enabled.swap(enabled, Ordering::Relaxed) != enabled {
+			let mut state = self.state.lock().expect("power state lock poisoned");
+			self.sync(&mut state);
+		}
+	}
+
+	pub fn is_enabled(&self) -> bool {
+		self.enabled.load(Ordering::Relaxed)
+	}
+
+	/// Whether we are currently preventing the system from sleeping
+	pub fn is_active(&self) -> bool {
+		self.state
+			.lock()
+			.expect("power state lock poisoned")
+			.assertion
+			.is_some()
+	}
+
+	/// Releases the holders gone quiet, except the ones `is_tracked` says announce when they finish
+	fn release_stale(&self, is_tracked: impl Fn(&Uuid) -> bool) {
+		let mut state = self.state.lock().expect("power state lock poisoned");
+		let count = state.holders.len();
+		state.holders.retain(|holder, last_seen| {
+			is_tracked(holder) || last_seen.elapsed() < STALE_HOLDER_TIMEOUT
+		});
+		if state.holders.len() != count {
+			self.sync(&mut state);
+		}
+	}
+
+	fn sync(&self, state: &mut State) {
+		let should_hold = self.is_enabled() && !state.holders.is_empty();
+
+		match (should_hold, state.assertion.is_some()) {
+			(true, false) => match platform::Assertion::acquire(REASON) {
+				Ok(assertion) => {
+					info!("Preventing system sleep while jobs are running;");
+					state.assertion = Some(assertion);
+				}
+				Err(e) if e.kind() == io::ErrorKind::Unsupported => {}
+				Err(e) => warn!(?e, "Failed to prevent system sleep;"),
+			},
+			(false, true) => {
+				info!("Allowing system sleep again;");
+				state.assertion = None;
+			}
+			_ => {}
+		}
+	}
+
+	/// Holds the assertion on behalf of running jobs and follows the node preference
+	pub(crate) fn start(self: &Arc<Self>, node: &Arc<Node>) {
+		let this = Arc::clone(self);
+		let mut preferences_rx = node.config.preferences_watcher();
+		tokio::spawn(async move {
+			while preferences_rx.changed().await.is_ok() {
+				let enabled = preferences_rx.borrow().prevent_sleep_during_jobs;
+				this.set_enabled(enabled);
+			}
+		});
+
+		let this = Arc::clone(self);
+		let node = Arc::clone(node);
+		let mut event_bus_rx = node.event_bus.0.subscribe();
+		tokio::spawn(async move {
+			let mut stale_check = interval(STALE_CHECK_INTERVAL);
+			loop {
+				tokio::select! {
+					event = event_bus_rx.recv() => match event {
+						Ok(CoreEvent::JobProgress(event)) => {
+							if event.task_count > 0 && event.completed_task_count >= event.task_count {
+								this.release(event.id);
+							} else {
+								this.hold(event.id);
+							}
+						}
+						Ok(CoreEvent::JobFinished(event)) => this.release(event.id),
+						Ok(_) => {}
+						Err(RecvError::Lagged(skipped)) => {
+							debug!(%skipped, "Power manager lagged behind the event bus;");
+						}
+						Err(RecvError::Closed) => break,
+					},
+					_ = stale_check.tick() => {
+						let active_jobs = node.job_system.get_active_reports().await;
+						this.release_stale(|holder| active_jobs.contains_key(holder));
+					}
+				}
+			}
+		});
+	}
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod platform {
+	use std::{
+		io,
+		process::{Child, Command, Stdio},
+	};
+
+	use tracing::warn;
+
+	/// The assertion lives as long as the child process
+	pub struct Assertion(Child);
+
+	impl Assertion {
+		#[cfg(target_os = "linux")]
+		pub fn acquire(reason: &str) -> io::Result<Self> {
+			Command::new("systemd-inhibit")
+				.args([
+					"--what=sleep:idle",
+					"--who=Spacedrive",
+					&format!("--why={reason}"),
+					"--mode=block",
+					"sleep",
+					"infinity",
+				])
+				.stdin(Stdio::null())
+				.stdout(Stdio::null())
+				.stderr(Stdio::null())
+				.spawn()
+				.map(Self)
+		}
+
+		#[cfg(target_os = "macos")]
+		pub fn acquire(_reason: &str) -> io::Result<Self> {
+			// `-w` makes caffeinate exit along with us, so the assertion can't outlive a crash
+			Command::new("caffeinate")
+				.args(["-i", "-w", &std::process::id().to_string()])
+				.stdin(Stdio::null())
+				.stdout(Stdio::null())
+				.stderr(Stdio::null())
+				.spawn()
+				.map(Self)
+		}
+	}
+
+	impl Drop for Assertion {
+		fn drop(&mut self) {
+			if let Err(e) = self.0.kill().and_then(|()| self.0.wait().map(|_| ())) {
+				warn!(?e, "Failed to release power assertion;");
+			}
+		}
+	}
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+	use std::{io, sync::mpsc, thread};
+
+	use windows::Win32::System::Power::{
+		SetThreadExecutionState, ES_CONTINUOUS, ES_SYSTEM_REQUIRED,
+	};
+
+	/// `SetThreadExecutionState` applies to the calling thread, so we park a thread
+	/// holding it until the assertion is dropped
+	pub struct Assertion {
+		release_tx: Option<mpsc::Sender<()>>,
+		thread: Option<thread::JoinHandle<()>>,
+	}
+
+	impl Assertion {
+		pub fn acquire(_reason: &str) -> io::Result<Self> {
+			let (release_tx, release_rx) = mpsc::channel::<()>();
+			let (acquired_tx, acquired_rx) = mpsc::sync_channel(1);
+
+			let thread = thread::Builder::new()
+				.name("power-assertion".into())
+				.spawn(move || {
+					// SAFETY: Only changes the execution state of the current thread
+					let previous =
+						unsafe { SetThreadExecutionState(ES_CONTINUOUS | ES_SYSTEM_REQUIRED) };
+					let acquired = previous.0 != 0;
+					let _ = acquired_tx.send(acquired);
+					if !acquired {
+						return;
+					}
+
+					// Returns once the sender is dropped
+					let _ = release_rx.recv();
+
+					// SAFETY: Same as above
+					unsafe { SetThreadExecutionState(ES_CONTINUOUS) };
+				})?;
+
+			if acquired_rx.recv().unwrap_or(false) {
+				Ok(Self {
+					release_tx: Some(release_tx),
+					thread: Some(thread),
+				})
+			} else {
+				Err(io::Error::last_os_error())
+			}
+		}
+	}
+
+	impl Drop for Assertion {
+		fn drop(&mut self) {
+			drop(self.release_tx.take());
+			if let Some(thread) = self.thread.take() {
+				let _ = thread.join();
+			}
+		}
+	}
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+mod platform {
+	use std::io;
+
+	pub struct Assertion;
+
+	impl Assertion {
+		pub fn acquire(_reason: &str) -> io::Result<Self> {
+			Err(io::ErrorKind::Unsupported.into())
+		}
+	}
+}
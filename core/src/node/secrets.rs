@@ -0,0 +1,169 @@
+//! Secrets kept in the OS credential store (Keychain on Apple platforms, Credential Manager
+//! on Windows and the Secret Service on Linux) instead of in our own config files.
+
+use rspc::ErrorCode;
+use thiserror::Error;
+use tokio::task::spawn_blocking;
+
+/// Key holding the cloud auth tokens, previously stored in the `.sdks` file
+pub const CLOUD_TOKENS_KEY: &str = "cloud_tokens";
+
+/// Prefix of the keys holding the credentials of network shares
+const NETWORK_SHARE_PREFIX: &str = "network_share:";
+
+/// Key holding the credentials used to mount the network share at `url`
+pub fn network_share_key(url: &str) -> String {
+	format!("{NETWORK_SHARE_PREFIX}{url}")
+}
+
+/// The only keys the frontend may set or delete, the others are only ever touched by the core
+const CLIENT_KEY_PREFIXES: &[&str] = &[NETWORK_SHARE_PREFIX];
+
+const MAX_KEY_LEN: usize = 256;
+
+#[derive(Error, Debug)]
+pub enum SecretStoreError {
+	#[error("invalid secret key: {0:?}")]
+	InvalidKey(String),
+	#[error("secret key not accessible to clients: {0:?}")]
+	ForbiddenKey(String),
+	#[error("no secure credential store available on this platform")]
+	Unsupported,
+	#[cfg(not(target_os = "android"))]
+	#[error("credential store error: {0}")]
+	Keyring(#[from] keyring::Error),
+	#[error("credential store task failed: {0}")]
+	Join(#[from] tokio::task::JoinError),
+}
+
+impl From<SecretStoreError> for rspc::Error {
+	fn from(e: SecretStoreError) -> Self {
+		let code = match e {
+			SecretStoreError::InvalidKey(_) => ErrorCode::BadRequest,
+			SecretStoreError::ForbiddenKey(_) => ErrorCode::Forbidden,
+			_ => ErrorCode::InternalServerError,
+		};
+
+		Self::with_cause(code, e.to_string(), e)
+	}
+}
+
+/// Every secret is stored under `service`, which is unique to this node so that
+/// multiple data directories on the same machine don't overwrite each other's secrets.
+#[derive(Debug, Clone)]
+pub struct SecretStore {
+	service: String,
+}
+
+impl SecretStore {
+	pub(crate) fn new(node_id: impl std::fmt::Display) -> Self {
+		Self {
+			service: format!("com.spacedrive.node.{node_id}"),
+		}
+	}
+
+	pub async fn get(&self, key: &str) -> Result<Option<String>, SecretStoreError> {
+		let (service, key) = (self.service.clone(), validate_key(key)?);
+		spawn_blocking(move || platform::get(&service, &key)).await?
+	}
+
+	pub async fn set(&self, key: &str, secret: &str) -> Result<(), SecretStoreError> {
+		let (service, key, secret) = (self.service.clone(), validate_key(key)?, secret.to_owned());
+		spawn_blocking(move || platform::set(&service, &key, &secret)).await?
+	}
+
+	/// Deleting a secret which doesn't exist is not an error
+	pub async fn delete(&self, key: &str) -> Result<(), SecretStoreError> {
+		let (service, key) = (self.service.clone(), validate_key(key)?);
+		spawn_blocking(move || platform::delete(&service, &key)).await?
+	}
+}
+
+/// Checks that a key coming from the frontend is one it may change, see [`CLIENT_KEY_PREFIXES`]
+pub fn client_key(key: &str) -> Result<&str, SecretStoreError> {
+	if !CLIENT_KEY_PREFIXES
+		.iter()
+		.any(|prefix| key.len() > prefix.len() && key.starts_with(prefix))
+	{
+		return Err(SecretStoreError::ForbiddenKey(key.to_owned()));
+	}
+
+	Ok(key)
+}
+
+fn validate_key(key: &str) -> Result<String, SecretStoreError> {
+	if key.is_empty() || key.len() > MAX_KEY_LEN || key.chars().any(char::is_control) {
+		return Err(SecretStoreError::InvalidKey(key.to_owned()));
+	}
+
+	Ok(key.to_owned())
+}
+
+#[cfg(not(target_os = "android"))]
+mod platform {
+	use super::SecretStoreError;
+
+	use keyring::Entry;
+
+	pub fn get(service: &str, key: &str) -> Result<Option<String>, SecretStoreError> {
+		match Entry::new(service, key)?.get_password() {
+			Ok(secret) => Ok(Some(secret)),
+			Err(keyring::Error::NoEntry) => Ok(None),
+			Err(e) => Err(e.into()),
+		}
+	}
+
+	pub fn set(service: &str, key: &str, secret: &str) -> Result<(), SecretStoreError> {
+		Entry::new(service, key)?
+			.set_password(secret)
+			.map_err(Into::into)
+	}
+
+	pub fn delete(service: &str, key: &str) -> Result<(), SecretStoreError> {
+		match Entry::new(service, key)?.delete_credential() {
+			Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+			Err(e) => Err(e.into()),
+		}
+	}
+}
+
+/// The Android Keystore isn't reachable from the core, every operation fails as unsupported so
+/// that callers fall back to their own storage instead of believing the secret was kept
+#[cfg(target_os = "android")]
+mod platform {
+	use super::SecretStoreError;
+
+	pub fn get(_service: &str, _key: &str) -> Result<Option<String>, SecretStoreError> {
+		Err(SecretStoreError::Unsupported)
+	}
+
+	pub fn set(_service: &str, _key: &str, _secret: &str) -> Result<(), SecretStoreError> {
+		Err(SecretStoreError::Unsupported)
+	}
+
+	pub fn delete(_service: &str, _key: &str) -> Result<(), SecretStoreError> {
+		Err(SecretStoreError::Unsupported)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn validates_keys() {
+		assert!(validate_key(CLOUD_TOKENS_KEY).is_ok());
+		assert!(validate_key(&network_share_key("smb://nas.local/media")).is_ok());
+		assert!(validate_key("").is_err());
+		assert!(validate_key("line\nbreak").is_err());
+		assert!(validate_key(&"a".repeat(MAX_KEY_LEN + 1)).is_err());
+	}
+
+	#[test]
+	fn scopes_client_keys() {
+		assert!(client_key(&network_share_key("smb://nas.local/media")).is_ok());
+		assert!(client_key(CLOUD_TOKENS_KEY).is_err());
+		assert!(client_key(NETWORK_SHARE_PREFIX).is_err());
+		assert!(client_key("some_other_app").is_err());
+	}
+}
@@ -0,0 +1,344 @@
+//! Opt-in, anonymous usage telemetry, so we can tell which features are used and which volume
+//! backends fail in the wild.
+//!
+//! Only static names go out: feature names, error codes and timings. Events can't carry paths,
+//! volume names or anything else coming from the user's machine, and there is no node or
+//! device id in the batches, only the app version and the OS.
+//!
+//! Events are queued locally and persisted in the data directory until they are sent in batches.
+//! Nothing is recorded unless the user opted in, `SD_TELEMETRY_DISABLED=true` turns it off no
+//! matter the preference, and builds without a `SD_TELEMETRY_URL` never send anything.
+
+use crate::{volume::VolumeEvent, Node};
+
+use super::CoreConfig;
+
+use std::{
+	collections::VecDeque,
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, Mutex,
+	},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{fs, sync::watch, time::sleep};
+use tracing::{debug, info, warn};
+
+const QUEUE_FILE: &str = "telemetry_queue.json";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Timestamps are rounded to the hour, finer ones would only help tell users apart
+const TIMESTAMP_GRANULARITY_SECS: u64 = 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TelemetryEvent {
+	/// A feature was used
+	Feature { name: String },
+	/// An operation failed, with the stable code of its error
+	Error { domain: String, code: String },
+	/// How long an operation took
+	Timing { name: String, millis: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct QueuedEvent {
+	#[serde(flatten)]
+	event: TelemetryEvent,
+	hour: u64,
+}
+
+#[derive(Serialize)]
+struct Batch<'a> {
+	app_version: &'static str,
+	os: &'static str,
+	arch: &'static str,
+	events: &'a [QueuedEvent],
+}
+
+pub struct Telemetry {
+	opted_in: AtomicBool,
+	queue_path: PathBuf,
+	queue: Mutex<VecDeque<QueuedEvent>>,
+	endpoint: Option<String>,
+	config: watch::Receiver<CoreConfig>,
+}
+
+impl Telemetry {
+	pub(crate) async fn new(
+		data_dir: impl AsRef<Path>,
+		opted_in: bool,
+		config: watch::Receiver<CoreConfig>,
+	) -> Self {
+		let queue_path = data_dir.as_ref().join(QUEUE_FILE);
+
+		let queue = match fs::read(&queue_path).await {
+			Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+				warn!(?e, "Corrupted telemetry queue, starting a new one;");
+				VecDeque::new()
+			}),
+			Err(_) => VecDeque::new(),
+		};
+
+		let endpoint = std::env::var("SD_TELEMETRY_URL")
+			.ok()
+			.or_else(|| option_env!("SD_TELEMETRY_URL").map(str::to_string))
+			.filter(|url| !url.is_empty());
+
+		Self {
+			opted_in: AtomicBool::new(opted_in),
+			queue_path,
+			queue: Mutex::new(queue),
+			endpoint,
+			config,
+		}
+	}
+
+	/// Whether events are being recorded, the user opted in and the kill switch isn't on
+	pub fn is_enabled(&self) -> bool {
+		self.opted_in.load(Ordering::Relaxed) && !kill_switch()
+	}
+
+	pub fn feature(&self, name: &'static str) {
+		self.record(TelemetryEvent::Feature {
+			name: name.to_string(),
+		});
+	}
+
+	/// Records a failure by its stable code, e.g. `error("volume", e.code().as_str())`
+	pub fn error(&self, domain: &'static str, code: &'static str) {
+		self.record(TelemetryEvent::Error {
+			domain: domain.to_string(),
+			code: code.to_string(),
+		});
+	}
+
+	pub fn timing(&self, name: &'static str, duration: Duration) {
+		self.record(TelemetryEvent::Timing {
+			name: name.to_string(),
+			millis: duration.as_millis().try_into().unwrap_or(u64::MAX),
+		});
+	}
+
+	fn record(&self, event: TelemetryEvent) {
+		if !self.is_enabled() {
+			return;
+		}
+
+		let max_queued_events = self.config.borrow().telemetry.max_queued_events;
+		let mut queue = self.queue.lock().expect("telemetry queue lock poisoned");
+		push(
+			&mut queue,
+			QueuedEvent {
+				event,
+				hour: hour(),
+			},
+			max_queued_events,
+		);
+	}
+
+	/// Follows the node preference, records volume failures and sends the queue in batches
+	pub(crate) fn start(self: &Arc<Self>, node: &Arc<Node>) {
+		if kill_switch() {
+			info!("Telemetry disabled by SD_TELEMETRY_DISABLED;");
+		}
+
+		tokio::spawn({
+			let this = Arc::clone(self);
+			let mut preferences_rx = node.config.preferences_watcher();
+			async move {
+				while preferences_rx.changed().await.is_ok() {
+					let opted_in = preferences_rx.borrow().telemetry;
+					if this.opted_in.swap(opted_in, Ordering::Relaxed) == opted_in {
+						continue;
+					}
+
+					info!(%opted_in, "Telemetry preference changed;");
+
+					// Opting out also throws away whatever wasn't sent yet
+					if !opted_in {
+						this.queue
+							.lock()
+							.expect("telemetry queue lock poisoned")
+							.clear();
+						if let Err(e) = fs::remove_file(&this.queue_path).await {
+							if e.kind() != std::io::ErrorKind::NotFound {
+								warn!(?e, "Failed to remove the telemetry queue;");
+							}
+						}
+					}
+				}
+			}
+		});
+
+		tokio::spawn({
+			let this = Arc::clone(self);
+			let mut volume_events_rx = node.volumes.subscribe();
+			async move {
+				while let Some(event) = volume_events_rx.recv().await {
+					if let VolumeEvent::VolumeError { error, .. } = event {
+						this.error("volume", error.code.as_str());
+					}
+				}
+			}
+		});
+
+		tokio::spawn({
+			let this = Arc::clone(self);
+			let node = Arc::clone(node);
+			async move {
+				let client = reqwest::Client::new();
+				loop {
+					let flush_interval = this.config.borrow().telemetry.flush_interval();
+					sleep(flush_interval).await;
+
+					if !this.is_enabled() {
+						continue;
+					}
+
+					if !node.offline.is_offline() {
+						if let Some(endpoint) = &this.endpoint {
+							this.send(&client, endpoint).await;
+						}
+					}
+
+					this.persist().await;
+				}
+			}
+		});
+	}
+
+	/// Writes the queue to disk, the events recorded since the last flush would be lost otherwise
+	pub(crate) async fn shutdown(&self) {
+		if self.is_enabled() {
+			self.persist().await;
+		}
+	}
+
+	async fn send(&self, client: &reqwest::Client, endpoint: &str) {
+		let batch_size = self.config.borrow().telemetry.batch_size;
+		loop {
+			let batch = {
+				let queue = self.queue.lock().expect("telemetry queue lock poisoned");
+				queue.iter().take(batch_size).cloned().collect::<Vec<_>>()
+			};
+
+			if batch.is_empty() {
+				return;
+			}
+
+			let res = client
+				.post(endpoint)
+				.timeout(REQUEST_TIMEOUT)
+				.json(&Batch {
+					app_version: env!("CARGO_PKG_VERSION"),
+					os: std::env::consts::OS,
+					arch: std::env::consts::ARCH,
+					events: &batch,
+				})
+				.send()
+				.await
+				.and_then(reqwest::Response::error_for_status);
+
+			// Failed batches stay queued for the next flush
+			if let Err(e) = res {
+				debug!(?e, "Failed to send telemetry;");
+				return;
+			}
+
+			debug!(count = batch.len(), "Sent telemetry batch;");
+			let mut queue = self.queue.lock().expect("telemetry queue lock poisoned");
+			let sent = batch.len().min(queue.len());
+			queue.drain(..sent);
+		}
+	}
+
+	async fn persist(&self) {
+		let bytes = {
+			let queue = self.queue.lock().expect("telemetry queue lock poisoned");
+			serde_json::to_vec(&*queue)
+		};
+
+		match bytes {
+			Ok(bytes) => {
+				if let Err(e) = fs::write(&self.queue_path, bytes).await {
+					warn!(?e, "Failed to persist the telemetry queue;");
+				}
+			}
+			Err(e) => warn!(?e, "Failed to serialize the telemetry queue;"),
+		}
+	}
+}
+
+/// Oldest events are dropped past `max`, so a machine that never gets to send doesn't grow the queue forever
+fn push(queue: &mut VecDeque<QueuedEvent>, event: QueuedEvent, max: usize) {
+	while queue.len() >= max.max(1) {
+		queue.pop_front();
+	}
+	queue.push_back(event);
+}
+
+fn kill_switch() -> bool {
+	std::env::var("SD_TELEMETRY_DISABLED").is_ok_and(|value| value == "true" || value == "1")
+}
+
+fn hour() -> u64 {
+	let secs = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|elapsed| elapsed.as_secs())
+		.unwrap_or_default();
+
+	secs - secs % TIMESTAMP_GRANULARITY_SECS
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn queue_drops_oldest_events() {
+		let mut queue = VecDeque::new();
+		for millis in 0..110 {
+			push(
+				&mut queue,
+				QueuedEvent {
+					event: TelemetryEvent::Timing {
+						name: "test".to_string(),
+						millis,
+					},
+					hour: 0,
+				},
+				100,
+			);
+		}
+
+		assert_eq!(queue.len(), 100);
+		assert_eq!(
+			queue.front().map(|queued| &queued.event),
+			Some(&TelemetryEvent::Timing {
+				name: "test".to_string(),
+				millis: 10
+			})
+		);
+	}
+
+	#[test]
+	fn queued_events_round_trip() {
+		let queued = QueuedEvent {
+			event: TelemetryEvent::Error {
+				domain: "volume".to_string(),
+				code: "DEVICE_BUSY".to_string(),
+			},
+			hour: hour(),
+		};
+
+		let json = serde_json::to_value(&queued).unwrap();
+		assert_eq!(json["kind"], "error");
+		assert_eq!(queued.hour % TIMESTAMP_GRANULARITY_SECS, 0);
+		assert_eq!(serde_json::from_value::<QueuedEvent>(json).unwrap(), queued);
+	}
+}
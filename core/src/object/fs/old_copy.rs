@@ -244,6 +244,10 @@ impl StatefulJob for OldFileCopierJobInit {
 		self.target_location_id
 	}
 
+	fn written_locations(&self) -> Vec<location::id::Type> {
+		vec![self.target_location_id]
+	}
+
 	async fn init(
 		&self,
 		ctx: &WorkerContext,
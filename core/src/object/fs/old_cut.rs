@@ -48,6 +48,10 @@ impl StatefulJob for OldFileCutterJobInit {
 		self.target_location_id
 	}
 
+	fn written_locations(&self) -> Vec<location::id::Type> {
+		vec![self.source_location_id, self.target_location_id]
+	}
+
 	async fn init(
 		&self,
 		ctx: &WorkerContext,
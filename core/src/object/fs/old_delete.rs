@@ -42,6 +42,10 @@ impl StatefulJob for OldFileDeleterJobInit {
 		self.location_id
 	}
 
+	fn written_locations(&self) -> Vec<location::id::Type> {
+		vec![self.location_id]
+	}
+
 	async fn init(
 		&self,
 		ctx: &WorkerContext,
@@ -4,6 +4,7 @@ use crate::{
 		fs::error::FileSystemJobsError, /*media::old_media_processor::MediaProcessorError,*/
 		/*old_file_identifier::FileIdentifierJobError,*/ validation::ValidatorError,
 	},
+	volume::VolumeError,
 };
 
 // use sd_crypto::Error as CryptoError;
@@ -62,6 +63,8 @@ pub enum JobError {
 	Validator(#[from] ValidatorError),
 	#[error(transparent)]
 	FileSystemJobsError(#[from] FileSystemJobsError),
+	#[error(transparent)]
+	Volume(#[from] VolumeError),
 	// #[error(transparent)]
 	// CryptoError(#[from] CryptoError),
 
@@ -89,6 +92,15 @@ pub enum JobManagerError {
 
 	#[error("missing-field: {0}")]
 	MissingField(#[from] MissingFieldError),
+
+	#[error("volume '{0}' is set read-only, jobs can't write to it")]
+	ReadOnlyVolume(String),
+
+	#[error("library is read-only, jobs can't run in it")]
+	ReadOnlyLibrary,
+
+	#[error("volume '{0}' is quarantined, it keeps failing to read or write")]
+	DegradedVolume(String),
 }
 
 impl From<JobManagerError> for rspc::Error {
@@ -114,6 +126,21 @@ impl From<JobManagerError> for rspc::Error {
 				"Missing field".to_string(),
 				value,
 			),
+			JobManagerError::ReadOnlyVolume(_) => Self::with_cause(
+				rspc::ErrorCode::BadRequest,
+				"The volume is set read-only".to_string(),
+				value,
+			),
+			JobManagerError::ReadOnlyLibrary => Self::with_cause(
+				rspc::ErrorCode::Forbidden,
+				"The library is read-only".to_string(),
+				value,
+			),
+			JobManagerError::DegradedVolume(_) => Self::with_cause(
+				rspc::ErrorCode::Conflict,
+				"The volume is quarantined".to_string(),
+				value,
+			),
 		}
 	}
 }
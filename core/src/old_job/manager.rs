@@ -8,10 +8,13 @@ use crate::{
 		validation::old_validator_job::OldObjectValidatorJobInit,
 	},
 	old_job::{worker::Worker, DynJob, JobError, OldJob},
+	volume::{
+		self, breakdown::OldVolumeBreakdownJobInit, maintenance::OldVolumeMaintenanceJobInit,
+	},
 	Node,
 };
 
-use sd_prisma::prisma::job;
+use sd_prisma::prisma::{job, location};
 
 use std::{
 	collections::{HashMap, HashSet, VecDeque},
@@ -26,8 +29,6 @@ use uuid::Uuid;
 
 use super::{JobIdentity, JobManagerError, JobStatus, OldJobReport, StatefulJob};
 
-const MAX_WORKERS: usize = 5;
-
 pub enum JobManagerEvent {
 	IngestJob(Arc<Library>, Box<dyn DynJob>),
 	Shutdown(oneshot::Sender<()>, Arc<OldJobs>),
@@ -65,9 +66,13 @@ impl Actor {
 
 /// JobManager handles queueing and executing jobs using the [`DynJob`]
 /// Handling persisting JobReports to the database, pause/resuming
+///
+/// Each open library has its own `jobs.max_workers` workers and its own queue, a busy library
+/// doesn't hold the jobs of the others back.
 pub struct OldJobs {
 	current_jobs_hashes: RwLock<HashSet<u64>>,
-	job_queue: RwLock<VecDeque<Box<dyn DynJob>>>,
+	/// Shared by the libraries, each job waits with the library it runs in
+	job_queue: RwLock<VecDeque<(Arc<Library>, Box<dyn DynJob>)>>,
 	running_workers: RwLock<HashMap<Uuid, Worker>>,
 	internal_sender: mpsc::UnboundedSender<JobManagerEvent>,
 }
@@ -105,8 +110,19 @@ impl OldJobs {
 		library: &Arc<Library>,
 		job: Box<OldJob<impl StatefulJob>>,
 	) -> Result<(), JobManagerError> {
+		// Even the jobs not writing files write their reports to the database
+		if library.is_read_only().await {
+			return Err(JobManagerError::ReadOnlyLibrary);
+		}
+
 		let job_hash = job.hash();
 
+		let written_locations = job.written_locations();
+		if !written_locations.is_empty() {
+			volume::quarantine::ensure_healthy(node, library, written_locations.clone()).await?;
+			volume::settings::ensure_writable(node, library, written_locations).await?;
+		}
+
 		if self.current_jobs_hashes.read().await.contains(&job_hash) {
 			return Err(JobManagerError::AlreadyRunningJob {
 				name: job.name(),
@@ -125,7 +141,8 @@ impl OldJobs {
 		skip_all,
 		fields(library_id = %library.id, job_name = %job.name(), job_hash = %job.hash()),
 	)]
-	/// Dispatches a job to a worker if under MAX_WORKERS limit, queues it otherwise.
+	/// Dispatches a job to a worker if its library is under the `jobs.max_workers` limit, queues it
+	/// otherwise.
 	async fn dispatch(
 		self: Arc<Self>,
 		node: &Arc<Node>,
@@ -138,7 +155,12 @@ impl OldJobs {
 			.take()
 			.expect("critical error: missing job on worker");
 
-		if running_workers.len() < MAX_WORKERS {
+		let library_workers = running_workers
+			.values()
+			.filter(|worker| worker.library_id == library.id)
+			.count();
+
+		if library_workers < node.core_config.get().jobs.max_workers {
 			info!("Running job");
 
 			let worker_id = job_report.parent_id.unwrap_or(job_report.id);
@@ -170,7 +192,10 @@ impl OldJobs {
 			// Put the report back, or it will be lost forever
 			*job.report_mut() = Some(job_report);
 
-			self.job_queue.write().await.push_back(job);
+			self.job_queue
+				.write()
+				.await
+				.push_back((Arc::clone(library), job));
 		}
 	}
 
@@ -184,11 +209,16 @@ impl OldJobs {
 		// remove worker from running workers and from current jobs hashes
 		self.current_jobs_hashes.write().await.remove(&job_hash);
 		self.running_workers.write().await.remove(&worker_id);
-		// continue queue
+		// continue queue, with the next job of the library the worker was freed in
 		let job = if next_job.is_some() {
 			next_job
 		} else {
-			self.job_queue.write().await.pop_front()
+			let mut job_queue = self.job_queue.write().await;
+			job_queue
+				.iter()
+				.position(|(queued_in, _)| queued_in.id == library.id)
+				.and_then(|index| job_queue.remove(index))
+				.map(|(_, job)| job)
 		};
 
 		if let Some(job) = job {
@@ -356,6 +386,11 @@ impl OldJobs {
 			.collect()
 	}
 
+	/// Jobs waiting for a worker of their library to free up
+	pub async fn queued_count(&self) -> usize {
+		self.job_queue.read().await.len()
+	}
+
 	/// Check if the manager currently has some active workers.
 	pub async fn has_active_workers(&self, library_id: Uuid) -> bool {
 		self.running_workers
@@ -365,6 +400,34 @@ impl OldJobs {
 			.any(|worker| worker.library_id == library_id && !worker.is_paused())
 	}
 
+	/// Whether a job of the library runs on one of `locations`, reading or writing its files
+	pub async fn has_location_jobs(
+		&self,
+		library_id: Uuid,
+		locations: &[location::id::Type],
+	) -> bool {
+		for worker in self.running_workers.read().await.values() {
+			if worker.library_id != library_id {
+				continue;
+			}
+			if worker
+				.written_locations
+				.iter()
+				.any(|location_id| locations.contains(location_id))
+			{
+				return true;
+			}
+			if worker
+				.who_am_i()
+				.await
+				.is_some_and(|identity| locations.contains(&identity.target_location))
+			{
+				return true;
+			}
+		}
+		false
+	}
+
 	pub async fn has_job_running(&self, predicate: impl Fn(JobIdentity) -> bool) -> bool {
 		for worker in self.running_workers.read().await.values() {
 			if worker.who_am_i().await.map(&predicate).unwrap_or(false) {
@@ -411,6 +474,8 @@ fn initialize_resumable_job(
 			OldFileCopierJobInit,
 			OldFileDeleterJobInit,
 			OldFileEraserJobInit,
+			OldVolumeMaintenanceJobInit,
+			OldVolumeBreakdownJobInit,
 		]
 	)
 }
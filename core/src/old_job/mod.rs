@@ -104,6 +104,12 @@ pub trait StatefulJob:
 	/// The location id where this job will act upon
 	fn target_location(&self) -> location::id::Type;
 
+	/// The locations whose files the job changes, it's refused when one is on a volume the user
+	/// forced read-only
+	fn written_locations(&self) -> Vec<location::id::Type> {
+		vec![]
+	}
+
 	/// is called for each step in the job. These steps are created in the `Self::init` method.
 	async fn execute_step(
 		&self,
@@ -136,6 +142,8 @@ pub trait DynJob: Send + Sync {
 	fn report(&self) -> &Option<OldJobReport>;
 	fn report_mut(&mut self) -> &mut Option<OldJobReport>;
 	fn name(&self) -> &'static str;
+	/// Empty once the job started running, its state moves into the worker
+	fn written_locations(&self) -> Vec<location::id::Type>;
 	async fn run(
 		&mut self,
 		ctx: WorkerContext,
@@ -401,6 +409,13 @@ impl<SJob: StatefulJob> DynJob for OldJob<SJob> {
 		<SJob as StatefulJob>::NAME
 	}
 
+	fn written_locations(&self) -> Vec<location::id::Type> {
+		self.state
+			.as_ref()
+			.map(|state| state.init.written_locations())
+			.unwrap_or_default()
+	}
+
 	#[instrument(skip_all, fields(job_name = %self.name()), err)]
 	#[allow(clippy::blocks_in_conditions)] // Due to `err` on instrument above
 	async fn run(
@@ -179,6 +179,8 @@ impl From<OldJobReport> for sd_core_heavy_lifting::job_system::report::Report {
 				"file_deleter" => JobName::Delete,
 				"file_eraser" => JobName::Erase,
 				"object_validator" => JobName::FileValidator,
+				"volume_maintainer" => JobName::VolumeMaintenance,
+				"volume_breakdown" => JobName::VolumeBreakdown,
 
 				// Already implemented in the new job system
 				"indexer" => JobName::Indexer,
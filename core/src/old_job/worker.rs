@@ -1,5 +1,7 @@
 use crate::{api::CoreEvent, invalidate_query, library::Library, Node};
 
+use sd_prisma::prisma::location;
+
 use std::{
 	fmt,
 	pin::pin,
@@ -114,6 +116,7 @@ impl WorkerContext {
 // once the job is complete the worker will exit
 pub struct Worker {
 	pub(super) library_id: Uuid,
+	pub(super) written_locations: Vec<location::id::Type>,
 	commands_tx: chan::Sender<WorkerCommand>,
 	report_watch_tx: Arc<watch::Sender<OldJobReport>>,
 	report_watch_rx: watch::Receiver<OldJobReport>,
@@ -132,6 +135,7 @@ impl Worker {
 		let (commands_tx, commands_rx) = chan::bounded(8);
 
 		let job_hash = job.hash();
+		let written_locations = job.written_locations();
 
 		let start_time = Utc::now();
 
@@ -174,6 +178,7 @@ impl Worker {
 
 		Ok(Self {
 			library_id,
+			written_locations,
 			commands_tx,
 			report_watch_tx,
 			report_watch_rx,
@@ -115,70 +115,50 @@ impl P2PManager {
 			tokio::spawn(async move {
 				let client = reqwest::Client::new();
 				loop {
-					match client
-						// FIXME(@fogodev): hardcoded URL for now as I'm moving stuff around
-						.get(format!("{}/api/p2p/relays", "https://app.spacedrive.com"))
-						.send()
-						.await
-					{
-						Ok(resp) => {
-							if resp.status() != 200 {
-								error!(
-									"Failed to pull p2p relay configuration: {} {:?}",
-									resp.status(),
-									resp.text().await
-								);
+					let node_config = node.config.get().await;
+					let config = if node_config.preferences.offline {
+						// Pulling the relays is network traffic of its own,
+						// so we go without them until back online
+						Some(vec![])
+					} else {
+						fetch_relay_config(&client).await.map(|config| {
+							node.p2p
+								.relay_config
+								.lock()
+								.unwrap_or_else(PoisonError::into_inner)
+								.clone_from(&config);
+
+							if !node_config.p2p.disabled && !node_config.p2p.disable_relay {
+								config
 							} else {
-								match resp.json::<Vec<RelayServerEntry>>().await {
-									Ok(config) => {
-										node.p2p
-											.relay_config
-											.lock()
-											.unwrap_or_else(PoisonError::into_inner)
-											.clone_from(&config);
-
-										let config = {
-											let node_config = node.config.get().await;
-											if !node_config.p2p.disabled
-												&& !node_config.p2p.disable_relay
-											{
-												config
-											} else {
-												vec![]
-											}
-										};
-										let no_relays = config.len();
-
-										this.listeners
-											.lock()
-											.unwrap_or_else(PoisonError::into_inner)
-											.relay = match this.quic_transport.set_relay_config(config).await {
-											Ok(_) => {
-												info!(
-													"Updated p2p relay configuration successfully."
-												);
-												if no_relays == 0 {
-													this.quic.disable();
-
-													ListenerState::NotListening
-												} else {
-													this.quic.enable();
-
-													ListenerState::Listening
-												}
-											}
-											Err(err) => ListenerState::Error {
-												error: err.to_string(),
-											},
-										};
-									}
-									Err(e) => {
-										error!(?e, "Failed to parse p2p relay configuration;")
-									}
+								vec![]
+							}
+						})
+					};
+
+					if let Some(config) = config {
+						let no_relays = config.len();
+
+						this.listeners
+							.lock()
+							.unwrap_or_else(PoisonError::into_inner)
+							.relay = match this.quic_transport.set_relay_config(config).await {
+							Ok(_) => {
+								info!("Updated p2p relay configuration successfully.");
+								if no_relays == 0 {
+									this.quic.disable();
+
+									ListenerState::NotListening
+								} else {
+									this.quic.enable();
+
+									ListenerState::Listening
 								}
 							}
-						}
-						Err(e) => error!(?e, "Error pulling p2p relay configuration;"),
+							Err(err) => ListenerState::Error {
+								error: err.to_string(),
+							},
+						};
 					}
 
 					tokio::select! {
@@ -200,6 +180,11 @@ impl P2PManager {
 
 		let config = self.node_config.get().await;
 
+		self.p2p.bandwidth().set_limits(
+			config.p2p.transfer_bandwidth,
+			&config.p2p.peer_transfer_bandwidth,
+		);
+
 		if config.p2p.discovery == P2PDiscoveryState::ContactsOnly {
 			PeerMetadata::remove(&mut self.p2p.metadata_mut());
 
@@ -215,8 +200,10 @@ impl P2PManager {
 		}
 
 		let port = config.p2p.port.get();
+		// Offline mode shuts everything down without touching the user's p2p settings
+		let disabled = config.p2p.disabled || config.preferences.offline;
 
-		let ipv4_port = (!config.p2p.disabled).then_some(port);
+		let ipv4_port = (!disabled).then_some(port);
 		info!(?ipv4_port, "Setting quic ipv4 listener;");
 		self.listeners
 			.lock()
@@ -232,13 +219,13 @@ impl P2PManager {
 				error: e.to_string(),
 			}
 		} else {
-			match !config.p2p.disabled {
+			match !disabled {
 				true => ListenerState::Listening,
 				false => ListenerState::NotListening,
 			}
 		};
 
-		let enable_ipv6 = !config.p2p.disabled && !config.p2p.disable_ipv6;
+		let enable_ipv6 = !disabled && !config.p2p.disable_ipv6;
 		let ipv6_port = enable_ipv6.then_some(port);
 		info!(?ipv6_port, "Setting quic ipv6 listener;");
 		self.listeners
@@ -264,7 +251,7 @@ impl P2PManager {
 		self.quic_transport
 			.set_manual_peer_addrs(config.p2p.manual_peers);
 
-		let should_revert = match (config.p2p.disabled, config.p2p.discovery) {
+		let should_revert = match (disabled, config.p2p.discovery) {
 			(true, _) | (_, P2PDiscoveryState::Disabled) => {
 				let mdns = {
 					let mut mdns = self.mdns.lock().unwrap_or_else(PoisonError::into_inner);
@@ -464,3 +451,32 @@ fn unwrap_infallible<T>(result: Result<T, Infallible>) -> T {
 		Err(err) => match err {},
 	}
 }
+
+async fn fetch_relay_config(client: &reqwest::Client) -> Option<Vec<RelayServerEntry>> {
+	// FIXME(@fogodev): hardcoded URL for now as I'm moving stuff around
+	let resp = match client
+		.get(format!("{}/api/p2p/relays", "https://app.spacedrive.com"))
+		.send()
+		.await
+	{
+		Ok(resp) => resp,
+		Err(e) => {
+			error!(?e, "Error pulling p2p relay configuration;");
+			return None;
+		}
+	};
+
+	if resp.status() != 200 {
+		error!(
+			"Failed to pull p2p relay configuration: {} {:?}",
+			resp.status(),
+			resp.text().await
+		);
+		return None;
+	}
+
+	resp.json::<Vec<RelayServerEntry>>()
+		.await
+		.map_err(|e| error!(?e, "Failed to parse p2p relay configuration;"))
+		.ok()
+}
@@ -0,0 +1,225 @@
+//! English for the message keys the core sends, for the clients without translations, like the
+//! CLI, and for logs.
+//!
+//! The table mirrors `interface/locales/en/common.json`, a test keeps both in sync.
+
+pub use sd_core_volume_types::LocalizedMessage;
+
+/// Sorted by key
+const ENGLISH: &[(&str, &str)] = &[
+	(
+		"volume_error_already_exists",
+		"A volume already exists at this location",
+	),
+	("volume_error_cancelled", "The operation was cancelled"),
+	(
+		"volume_error_credentials_unavailable",
+		"The saved credentials for {{url}} could not be read",
+	),
+	(
+		"volume_error_database",
+		"The volume could not be saved to the library",
+	),
+	(
+		"volume_error_device_busy",
+		"{{path}} is in use. Close any files open on it and try again",
+	),
+	("volume_error_device_error", "The device reported an error"),
+	(
+		"volume_error_insufficient_space",
+		"There is not enough free space on this volume",
+	),
+	(
+		"volume_error_internal",
+		"Something went wrong while talking to the system",
+	),
+	(
+		"volume_error_invalid_argument",
+		"{{argument}} can't be passed safely to {{command}}",
+	),
+	(
+		"volume_error_invalid_configuration",
+		"Invalid volume configuration",
+	),
+	(
+		"volume_error_invalid_disk_image",
+		"{{path}} is not a disk image that can be attached",
+	),
+	(
+		"volume_error_invalid_fingerprint",
+		"This volume could not be identified",
+	),
+	(
+		"volume_error_invalid_share_url",
+		"{{url}} is not a network share that can be mounted",
+	),
+	(
+		"volume_error_invalid_snapshot",
+		"This volume snapshot could not be loaded",
+	),
+	("volume_error_io", "The volume could not be read or written"),
+	(
+		"volume_error_jobs_running",
+		"Jobs are still working on {{path}}, wait for them to finish or cancel them",
+	),
+	(
+		"volume_error_locked",
+		"{{path}} is locked, unlock it to browse or index it",
+	),
+	("volume_error_mount_failed", "{{path}} could not be mounted"),
+	("volume_error_not_found", "The volume could not be found"),
+	("volume_error_not_mounted", "The volume is not mounted"),
+	(
+		"volume_error_operation_failed",
+		"The volume operation failed",
+	),
+	(
+		"volume_error_permission_denied",
+		"You don't have permission to access this volume",
+	),
+	("volume_error_read_only", "The volume is read-only"),
+	(
+		"volume_error_resource_exhausted",
+		"The system is out of resources, try again later",
+	),
+	(
+		"volume_error_snapshot_not_found",
+		"The snapshot {{id}} of {{path}} could not be found",
+	),
+	(
+		"volume_error_speed_test_failed",
+		"The speed test could not be completed",
+	),
+	(
+		"volume_error_system_volume",
+		"{{path}} is used by the system and can't be taken away",
+	),
+	(
+		"volume_error_timeout",
+		"The volume took too long to respond",
+	),
+	(
+		"volume_error_unmount_failed",
+		"{{path}} could not be ejected",
+	),
+	(
+		"volume_error_unsupported_platform",
+		"This isn't supported on your device",
+	),
+	(
+		"volume_error_watch_init_failed",
+		"Volume changes can't be detected on this system",
+	),
+	(
+		"volume_error_watcher_failed",
+		"Volume changes stopped being detected",
+	),
+	(
+		"volume_event_added",
+		"{{name}} was connected at {{mount_point}}",
+	),
+	(
+		"volume_event_degraded",
+		"{{name}} failed to read or write {{errors}} times in a row, the jobs on it were paused",
+	),
+	(
+		"volume_event_filling_up",
+		"{{name}} is expected to be full in {{days}} days",
+	),
+	(
+		"volume_event_health_failing",
+		"The drive of {{name}} is failing, copy its data elsewhere",
+	),
+	(
+		"volume_event_health_healthy",
+		"The drive of {{name}} is healthy",
+	),
+	(
+		"volume_event_health_warning",
+		"The drive of {{name}} is wearing out",
+	),
+	(
+		"volume_event_low_space",
+		"{{name}} is running out of space, {{available}} left",
+	),
+	("volume_event_mounted", "Volume {{fingerprint}} was mounted"),
+	(
+		"volume_event_remounted_read_only",
+		"Volume {{fingerprint}} was remounted read-only",
+	),
+	(
+		"volume_event_remounted_writable",
+		"Volume {{fingerprint}} was remounted writable",
+	),
+	(
+		"volume_event_removed",
+		"{{name}} was disconnected from {{mount_point}}",
+	),
+	(
+		"volume_event_space_changed",
+		"Volume {{fingerprint}} has {{available}} free",
+	),
+	(
+		"volume_event_speed_tested",
+		"Volume {{fingerprint}} reads at {{read_speed}} MB/s and writes at {{write_speed}} MB/s",
+	),
+	(
+		"volume_event_unmounted",
+		"Volume {{fingerprint}} was unmounted",
+	),
+	(
+		"volume_event_updated",
+		"{{name}} at {{mount_point}} changed",
+	),
+];
+
+/// The English translation of `key`, with its placeholders
+pub fn english_template(key: &str) -> Option<&'static str> {
+	ENGLISH
+		.binary_search_by_key(&key, |(entry, _)| entry)
+		.ok()
+		.map(|index| ENGLISH[index].1)
+}
+
+/// The message in English, the key itself when it has no translation
+pub fn to_english(message: &LocalizedMessage) -> String {
+	english_template(&message.key)
+		.map_or_else(|| message.key.clone(), |template| message.render(template))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::collections::HashMap;
+
+	#[test]
+	fn matches_the_english_locale() {
+		let locale = serde_json::from_str::<HashMap<String, String>>(include_str!(
+			"../../../interface/locales/en/common.json"
+		))
+		.unwrap();
+
+		assert!(ENGLISH.windows(2).all(|pair| pair[0].0 < pair[1].0));
+		for (key, english) in ENGLISH {
+			assert_eq!(
+				locale.get(*key).map(String::as_str),
+				Some(*english),
+				"{key}"
+			);
+		}
+	}
+
+	#[test]
+	fn renders_the_params() {
+		let message = LocalizedMessage::new("volume_error_device_busy").param("path", "/media/usb");
+		assert_eq!(
+			to_english(&message),
+			"/media/usb is in use. Close any files open on it and try again"
+		);
+		assert_eq!(
+			to_english(&LocalizedMessage::new("unknown_key")),
+			"unknown_key"
+		);
+	}
+}
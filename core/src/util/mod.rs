@@ -4,6 +4,7 @@ mod batched_stream;
 pub mod debug_initializer;
 mod infallible_request;
 mod maybe_undefined;
+pub mod messages;
 pub mod mpscrr;
 mod observable;
 mod unsafe_streamed_query;
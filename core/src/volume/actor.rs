@@ -1,21 +1,38 @@
 use super::{
 	error::VolumeError,
+	forecast::CapacityWarnings,
+	health::HealthStatuses,
+	history::VolumeHistoryRecorder,
+	image::{self, AttachedImages},
+	monitor::VolumeMonitor,
+	network::{self, MountedShares, NetworkShare},
+	plan,
+	quarantine::IoErrors,
+	snapshot::VolumeSnapshot,
 	speed::SpeedTest,
-	types::{Volume, VolumeEvent, VolumeOptions},
+	tracked,
+	types::{
+		MountOptions, OperationPlan, ScanProgress, Volume, VolumeEvent, VolumeOperation,
+		VolumeOptions, VolumeRecord,
+	},
 	volumes::Volumes,
-	watcher::VolumeWatcher,
+	watcher::{self, VolumeWatcher, WatcherStatus},
 	VolumeManagerContext, VolumeManagerState,
 };
 use crate::volume::types::VolumeFingerprint;
 use crate::{
+	api::BackendFeature,
 	library::{Library, LibraryManagerEvent},
+	node::{Publisher, Subscription},
 	volume::MountType,
 };
 use async_channel as chan;
 use sd_core_sync::DevicePubId;
-use sd_prisma::prisma::volume;
-use std::{sync::Arc, time::Duration};
-use tokio::sync::{broadcast, oneshot, Mutex, RwLock};
+use std::{
+	path::{Path, PathBuf},
+	sync::{Arc, OnceLock},
+};
+use tokio::sync::{mpsc, oneshot, RwLock};
 use tracing::{debug, error, info, trace, warn};
 
 const DEFAULT_CHANNEL_SIZE: usize = 128;
@@ -36,18 +53,36 @@ pub enum VolumeManagerMessage {
 		volume: Volume,
 		ack: oneshot::Sender<Result<(), VolumeError>>,
 	},
+	/// Mounts a device with a file system, like `/dev/sdb1`, returning the volume it became
 	MountVolume {
-		fingerprint: VolumeFingerprint,
-		ack: oneshot::Sender<Result<(), VolumeError>>,
+		device: PathBuf,
+		options: MountOptions,
+		ack: oneshot::Sender<Result<Volume, VolumeError>>,
 	},
-	UnmountVolume {
+	/// Mounts a network share, in a directory named after it unless a mount point is given
+	MountNetworkShare {
+		share: NetworkShare,
+		mount_point: Option<PathBuf>,
+		ack: oneshot::Sender<Result<Volume, VolumeError>>,
+	},
+	/// Attaches an ISO, DMG or IMG file and mounts it, returning the volume it became
+	AttachDiskImage {
+		image: PathBuf,
+		read_only: bool,
+		ack: oneshot::Sender<Result<Volume, VolumeError>>,
+	},
+	/// Unmounts, ejects or formats the volume, or only plans it for a dry run
+	RunOperation {
 		fingerprint: VolumeFingerprint,
-		ack: oneshot::Sender<Result<(), VolumeError>>,
+		operation: VolumeOperation,
+		dry_run: bool,
+		ack: oneshot::Sender<Result<OperationPlan, VolumeError>>,
 	},
+	/// Measures the read and write speeds of the volume, saved in the library when it's tracked
 	SpeedTest {
 		fingerprint: VolumeFingerprint,
 		library: Arc<Library>,
-		ack: oneshot::Sender<Result<(), VolumeError>>,
+		ack: oneshot::Sender<Result<Volume, VolumeError>>,
 	},
 	ListSystemVolumes {
 		library: Arc<Library>,
@@ -57,14 +92,70 @@ pub enum VolumeManagerMessage {
 		library: Arc<Library>,
 		ack: oneshot::Sender<Result<Vec<Volume>, VolumeError>>,
 	},
+	ListVolumes {
+		ack: oneshot::Sender<Vec<Volume>>,
+	},
+	/// Scans again, reporting each volume on `progress_tx` as it's probed
+	ScanVolumes {
+		progress_tx: mpsc::UnboundedSender<ScanProgress>,
+		ack: oneshot::Sender<Result<(), VolumeError>>,
+	},
+	Snapshot {
+		ack: oneshot::Sender<VolumeSnapshot>,
+	},
+	RestoreSnapshot {
+		snapshot: VolumeSnapshot,
+		ack: oneshot::Sender<Result<(), VolumeError>>,
+	},
+	/// Counts the I/O errors a job ran into on the volume holding `path`, answering with the
+	/// volume when it was just quarantined for them
+	ReportIoErrors {
+		path: PathBuf,
+		errors: u32,
+		ack: oneshot::Sender<Option<Volume>>,
+	},
+	/// The innermost mounted volume holding `path`, with its fingerprint
+	VolumeAt {
+		path: PathBuf,
+		ack: oneshot::Sender<Option<Volume>>,
+	},
+	/// The mounted volume with this fingerprint
+	GetVolume {
+		fingerprint: VolumeFingerprint,
+		ack: oneshot::Sender<Option<Volume>>,
+	},
+	/// Sent by the actor itself once a library is loaded, and when a read-only one is made
+	/// writable
+	InitializeLibrary {
+		library: Arc<Library>,
+	},
+	/// Sent by the actor itself once a library is deleted
+	CleanupLibrary {
+		library: Arc<Library>,
+	},
+	/// Sent by the actor itself every maintenance interval
+	Maintenance,
 }
 
 #[derive(Clone)]
 pub struct VolumeManagerActor {
 	state: Arc<RwLock<VolumeManagerState>>,
 	message_rx: chan::Receiver<VolumeManagerMessage>,
-	event_tx: broadcast::Sender<VolumeEvent>,
+	/// For the messages the actor sends itself, on library events and for maintenance
+	message_tx: chan::Sender<VolumeManagerMessage>,
+	event_tx: Publisher<VolumeEvent>,
 	ctx: Arc<VolumeManagerContext>,
+	watcher_status: Arc<RwLock<WatcherStatus>>,
+	/// Shared with [`Volumes`], which stops it on shutdown
+	watcher: Arc<OnceLock<Arc<VolumeWatcher>>>,
+	history: Arc<VolumeHistoryRecorder>,
+	capacity_warnings: Arc<CapacityWarnings>,
+	health_statuses: Arc<HealthStatuses>,
+	io_errors: Arc<IoErrors>,
+	/// The network shares mounted through the manager, mounted again when they drop
+	network_shares: Arc<MountedShares>,
+	/// Shared with [`Volumes`], which detaches them on shutdown
+	disk_images: Arc<AttachedImages>,
 }
 
 impl VolumeManagerActor {
@@ -78,19 +169,43 @@ impl VolumeManagerActor {
 		options: VolumeOptions,
 	) -> Result<(Volumes, Self), VolumeError> {
 		let (message_tx, message_rx) = chan::bounded(DEFAULT_CHANNEL_SIZE);
-		let (event_tx, _) = broadcast::channel(DEFAULT_CHANNEL_SIZE);
+		let event_tx = ctx.events.publisher::<VolumeEvent>();
 
-		let manager = Volumes::new(message_tx, event_tx.clone());
+		let watcher = Arc::<OnceLock<Arc<VolumeWatcher>>>::default();
+		let disk_images = Arc::<AttachedImages>::default();
+		let health_statuses = Arc::<HealthStatuses>::default();
+		let manager = Volumes::new(
+			message_tx.clone(),
+			event_tx.clone(),
+			Arc::clone(&ctx.platform),
+			Arc::clone(&watcher),
+			Arc::clone(&disk_images),
+			Arc::clone(&health_statuses),
+		);
 		debug!("Volume manager event_tx initialized");
-		let state =
-			VolumeManagerState::new(ctx.device_id.clone().into(), options, event_tx.clone());
+		let state = VolumeManagerState::new(
+			ctx.device_id.clone().into(),
+			options,
+			event_tx.clone(),
+			Arc::clone(&ctx.platform),
+			ctx.config.clone(),
+		);
 		debug!("Volume manager state initialized");
 
 		let actor = VolumeManagerActor {
 			state: Arc::new(RwLock::new(state)),
 			message_rx,
+			message_tx,
 			event_tx,
 			ctx,
+			watcher_status: Arc::default(),
+			watcher,
+			history: Arc::default(),
+			capacity_warnings: Arc::default(),
+			health_statuses,
+			io_errors: Arc::default(),
+			network_shares: Arc::default(),
+			disk_images,
 		};
 
 		Ok((manager, actor))
@@ -100,68 +215,33 @@ impl VolumeManagerActor {
 	/// It will scan volumes, start the watcher, start the maintenance task, and handle messages
 	pub async fn start(self, device_id: DevicePubId) {
 		info!("Volume manager actor started");
-		let self_arc = Arc::new(Mutex::new(self));
 
 		// Start event monitoring
-		let actor = self_arc.lock().await;
-		let event_rx = actor.event_tx.subscribe();
-		actor
-			.clone()
+		let event_rx = self.event_tx.subscribe();
+		self.clone()
 			.start_event_monitoring(event_rx, device_id.clone());
-		drop(actor);
 
-		// Handle messages
-		let self_arc_msg = Arc::clone(&self_arc);
-		tokio::spawn(async move {
-			let message_rx = self_arc_msg.lock().await.message_rx.clone();
-			while let Ok(msg) = message_rx.recv().await {
-				let self_arc_inner = Arc::clone(&self_arc_msg);
-				if let Err(e) = {
-					let mut actor = self_arc_inner.lock().await;
-					actor.handle_message(msg).await
-				} {
-					error!(?e, "Error handling volume manager message");
-				}
-			}
-		});
-
-		// Scan for volumes on startup
-		// unlock registry rwlock
-		let _ = self_arc.lock().await.scan_volumes().await;
-
-		// Subscribe to LibraryManagerEvent
-		let self_arc_subscribe = Arc::clone(&self_arc);
-		let rx = {
-			let actor = self_arc.lock().await;
-			actor.ctx.library_event_tx.clone()
-		};
+		// Library events and maintenance go through the mailbox like any other message
+		let message_tx = self.message_tx.clone();
+		let rx = self.ctx.library_event_tx.clone();
 		// This is a fire-and-forget subscription
 		tokio::spawn(async move {
 			if let Err(e) = rx
 				.subscribe(move |event| {
-					let self_arc_inner = Arc::clone(&self_arc_subscribe);
+					let message_tx = message_tx.clone();
 					async move {
 						debug!("Received library event: {:?}", event);
-						match event {
+						let msg = match event {
 							LibraryManagerEvent::Load(library) => {
-								debug!("Initializing volume manager for library");
-								if let Err(e) = {
-									let mut actor = self_arc_inner.lock().await;
-
-									actor.initialize_for_library(library.clone()).await
-								} {
-									error!(?e, "Failed to initialize volume manager for library");
-								}
+								VolumeManagerMessage::InitializeLibrary { library }
 							}
 							LibraryManagerEvent::Delete(library) => {
-								if let Err(e) = {
-									let mut actor = self_arc_inner.lock().await;
-									actor.handle_library_deletion(library).await
-								} {
-									error!(?e, "Failed to cleanup library volumes");
-								}
+								VolumeManagerMessage::CleanupLibrary { library }
 							}
-							_ => {}
+							_ => return,
+						};
+						if message_tx.send(msg).await.is_err() {
+							error!("Volume manager actor is gone, dropping library event;");
 						}
 					}
 				})
@@ -171,29 +251,73 @@ impl VolumeManagerActor {
 			}
 		});
 
-		let event_tx = self_arc.lock().await.event_tx.clone();
+		let (event_tx, config, platform, watcher_status, watcher_slot, state) = (
+			self.event_tx.clone(),
+			self.ctx.config.clone(),
+			Arc::clone(&self.ctx.platform),
+			Arc::clone(&self.watcher_status),
+			Arc::clone(&self.watcher),
+			Arc::clone(&self.state),
+		);
+		let maintenance_tx = self.message_tx.clone();
+
+		// The actor owns itself in this task, handling one message at a time, the replies go back
+		// on the oneshot channel of each message
+		let mut actor = self;
+
+		// Scan for volumes on startup, the messages sent meanwhile wait in the mailbox
+		if let Err(e) = actor.scan_volumes().await {
+			error!(?e, "Failed to scan volumes on startup");
+		}
+
+		tokio::spawn(async move {
+			let message_rx = actor.message_rx.clone();
+			while let Ok(msg) = message_rx.recv().await {
+				if let Err(e) = actor.handle_message(msg).await {
+					error!(?e, "Error handling volume manager message");
+				}
+			}
+		});
+
+		let registry = Arc::clone(&state.read().await.registry);
+
+		// Start the low space warnings
+		tokio::spawn(
+			VolumeMonitor::new(
+				device_id.clone(),
+				Arc::clone(&state),
+				Arc::clone(&platform),
+				event_tx.clone(),
+				config.clone(),
+			)
+			.run(),
+		);
 
-		// Start the volume watcher
-		let self_arc_watcher = Arc::clone(&self_arc);
+		// Start the volume watcher, it reads the registry on its own instead of locking the actor
+		let watcher = Arc::new(
+			VolumeWatcher::new(event_tx, config.clone(), platform).with_status(watcher_status),
+		);
+		if watcher_slot.set(Arc::clone(&watcher)).is_err() {
+			warn!("The volume watcher was already started;");
+		}
 		tokio::spawn(async move {
-			let watcher = VolumeWatcher::new(event_tx);
-			if let Err(e) = watcher
-				.start(device_id.clone(), self_arc_watcher.clone())
-				.await
-			{
+			if let Err(e) = watcher.start(device_id.clone(), registry).await {
 				error!(?e, "Failed to start watcher for volumes");
 				return;
 			}
 		});
 
 		// Start maintenance task
-		let self_arc_maintenance = Arc::clone(&self_arc);
 		tokio::spawn(async move {
 			loop {
-				tokio::time::sleep(Duration::from_secs(300)).await;
-				let mut actor = self_arc_maintenance.lock().await;
-				if let Err(e) = actor.perform_maintenance().await {
-					error!(?e, "Volume maintenance error");
+				let interval = config.borrow().volumes.maintenance_interval();
+				tokio::time::sleep(interval).await;
+				if maintenance_tx
+					.send(VolumeManagerMessage::Maintenance)
+					.await
+					.is_err()
+				{
+					break;
 				}
 			}
 		});
@@ -203,14 +327,49 @@ impl VolumeManagerActor {
 
 	fn start_event_monitoring(
 		self,
-		mut event_rx: broadcast::Receiver<VolumeEvent>,
+		mut event_rx: Subscription<VolumeEvent>,
 		device_pub_id: DevicePubId,
 	) {
 		tokio::spawn(async move {
 			debug!("Starting volume event monitoring");
-			while let Ok(event) = event_rx.recv().await {
+			while let Some(event) = event_rx.recv().await {
 				debug!("Volume event received: {:?}", event);
 
+				// The monitor refreshes the tracked volumes on its own, the watcher can report
+				// the same change again
+				if let VolumeEvent::VolumeUpdated { new, .. } = &event {
+					let fingerprint = VolumeFingerprint::new(&device_pub_id.to_db(), new);
+					let is_up_to_date = {
+						let state = self.state.read().await;
+						let registry = state.registry.read().await;
+						registry
+							.get_volume(&fingerprint)
+							.is_some_and(|known| watcher::is_up_to_date(known, new))
+					};
+					if is_up_to_date {
+						trace!(name = %new.name, "Volume update already applied;");
+						continue;
+					}
+				}
+
+				let recorded = self.history.on_event(&event, &device_pub_id.to_db()).await;
+				if let (Some((fingerprint, forecast)), VolumeEvent::VolumeUpdated { new, .. }) =
+					(recorded, &event)
+				{
+					let horizon = self.ctx.config.borrow().volumes.forecast_horizon_days;
+					if self
+						.capacity_warnings
+						.crossed(&fingerprint, &forecast, horizon)
+					{
+						info!(name = %new.name, ?forecast, "Volume is filling up;");
+						self.event_tx.send(VolumeEvent::VolumeFillingUp {
+							fingerprint,
+							name: new.name.clone(),
+							days_until_full: forecast.days_until_full.unwrap_or_default() as u32,
+						});
+					}
+				}
+
 				match event {
 					VolumeEvent::VolumeSpeedTested {
 						fingerprint,
@@ -240,11 +399,24 @@ impl VolumeManagerActor {
 
 						match event {
 							VolumeEvent::VolumeAdded(volume) => {
+								self.auto_track(&volume, device_pub_id.to_db());
 								registry.register_volume(volume);
 							}
 							VolumeEvent::VolumeRemoved(volume) => {
-								let fingerprint = VolumeFingerprint::new(&device_pub_id, &volume);
+								let fingerprint =
+									VolumeFingerprint::new(&device_pub_id.to_db(), &volume);
 								registry.remove_volume(&fingerprint);
+								self.health_statuses.forget(&fingerprint);
+								self.io_errors.forget(&fingerprint);
+
+								if self.network_shares.contains(&volume.mount_point) {
+									tokio::spawn(network::reconnect(
+										Arc::clone(&self.ctx.platform),
+										self.ctx.secrets.clone(),
+										Arc::clone(&self.network_shares),
+										volume.mount_point.clone(),
+									));
+								}
 							}
 							VolumeEvent::VolumeUpdated { old: _, new } => {
 								registry.update_volume(new);
@@ -255,11 +427,16 @@ impl VolumeManagerActor {
 							} => {
 								if let Some(volume) = registry.get_volume_mut(&fingerprint) {
 									volume.is_mounted = is_mounted;
+									// Mounted again, it gets another chance
+									if is_mounted {
+										volume.is_degraded = false;
+										self.io_errors.forget(&fingerprint);
+									}
 								}
 							}
 							VolumeEvent::VolumeError { fingerprint, error } => {
 								if let Some(volume) = registry.get_volume_mut(&fingerprint) {
-									volume.error_status = Some(error);
+									volume.error_status = Some(error.message);
 								}
 							}
 							_ => {}
@@ -271,60 +448,119 @@ impl VolumeManagerActor {
 		});
 	}
 
-	/// Syncs volume memory state with library database
+	/// Tracks a volume that was just plugged in in the loaded libraries, while auto-tracking is on
+	/// and unless its settings say otherwise
+	fn auto_track(&self, volume: &Volume, device_id: Vec<u8>) {
+		if volume.is_locked
+			|| !self
+				.ctx
+				.feature_flags
+				.is_enabled(BackendFeature::AutoTracking)
+		{
+			return;
+		}
+
+		let fingerprint = VolumeFingerprint::new(&device_id, volume);
+		let settings = self.ctx.config.borrow().volumes.settings_of(&fingerprint);
+		if !settings
+			.auto_track
+			.unwrap_or(volume.mount_type == MountType::External)
+		{
+			return;
+		}
+
+		// Sent through the mailbox, the event monitoring holds the state lock meanwhile
+		let (history, message_tx) = (Arc::clone(&self.history), self.message_tx.clone());
+		tokio::spawn(async move {
+			for library in history.libraries().await {
+				let (ack, rx) = oneshot::channel();
+				let msg = VolumeManagerMessage::TrackVolume {
+					fingerprint: fingerprint.clone(),
+					library,
+					ack,
+				};
+				if message_tx.send(msg).await.is_err() {
+					break;
+				}
+				if let Ok(Err(e)) = rx.await {
+					warn!(?e, %fingerprint, "Failed to track the volume plugged in;");
+				}
+			}
+		});
+	}
+
+	/// Syncs volume memory state with library database, the records of the mounted volumes are
+	/// brought up to date and the others marked as unmounted. Read-only libraries are left alone,
+	/// nor do they get the volume history, until they're made writable
 	pub async fn initialize_for_library(
 		&mut self,
 		library: Arc<Library>,
 	) -> Result<(), VolumeError> {
-		use sd_prisma::prisma::device;
-		let device_id = DevicePubId::from(self.ctx.device_id.clone());
+		if library.is_read_only().await {
+			debug!(library_id = %library.id, "Not tracking volumes in read-only library;");
+			return Ok(());
+		}
+
+		let device_id = DevicePubId::from(self.ctx.device_id.clone()).to_db();
+		self.history.add_library(Arc::clone(&library)).await;
 		let state = self.state.clone();
 		let state = state.write().await;
 
-		let db_device = library
-			.db
-			.device()
-			.find_unique(device::pub_id::equals(device_id.to_db()))
-			.exec()
-			.await?
-			.ok_or(VolumeError::DeviceNotFound(device_id.to_db()))?;
-
-		// Get volumes from database
-		let db_volumes = library
-			.db
-			.volume()
-			.find_many(vec![volume::device_id::equals(Some(db_device.id))])
-			.exec()
-			.await?
-			.into_iter()
-			.map(Volume::from)
-			.collect::<Vec<_>>();
+		let mut records = tracked::device_records(&library.db, &device_id).await?;
 
-		let registry_read = state.registry.read().await;
-		// Process each volume
-		for (fingerprint, volume) in registry_read.volumes() {
-			// Find matching database volume
-			if let Some(db_volume) = db_volumes
+		let config = self.ctx.config.borrow().volumes.clone();
+		// Copied out, the registry is written to while going through them
+		let mounted = state
+			.registry
+			.read()
+			.await
+			.volumes()
+			.map(|(fingerprint, volume)| (fingerprint.clone(), volume.clone()))
+			.collect::<Vec<_>>();
+		for (fingerprint, volume) in mounted {
+			let index = records
 				.iter()
-				.find(|db_vol| VolumeFingerprint::new(&device_id, db_vol) == *fingerprint)
-			{
-				// Update existing volume
-				let updated = Volume::merge_with_db(volume, db_volume);
-				let mut registry = state.registry.write().await;
-				registry.register_volume(updated.clone());
-			} else if volume.mount_type == MountType::System {
-				// Create new system volume in database
-				let created = volume.create(&library.db, device_id.to_db()).await?;
-			}
+				.position(|(record, _)| *record == fingerprint)
+				.or_else(|| {
+					// Tracked before its serial was read
+					let former = VolumeFingerprint::from_properties(&device_id, &volume);
+					records.iter().position(|(record, _)| *record == former)
+				});
+			let volume = match index {
+				Some(index) => {
+					let (recorded, record) = records.swap_remove(index);
+					if recorded != fingerprint {
+						tracked::refingerprint(&library, &recorded, &fingerprint).await?;
+					}
+					let updated = Volume::merge_with_db(&volume, &record);
+					updated.update(&library).await?;
+					updated
+				}
+				None if config.settings_of(&fingerprint).auto_track.unwrap_or(
+					volume.mount_type == MountType::System
+						&& !volume.is_locked
+						&& (volume.is_accessible || config.auto_track_inaccessible),
+				) =>
+				{
+					let created = volume.create(&library, device_id.clone()).await?;
+					Volume::merge_with_db(&volume, &created)
+				}
+				None => continue,
+			};
+			state.registry.write().await.register_volume(volume);
 		}
 
+		// Unplugged while the node wasn't running
+		let gone = records.into_iter().map(|(_, record)| record);
+		tracked::gone(&library, &gone.collect::<Vec<_>>()).await?;
+
 		Ok(())
 	}
 
 	async fn perform_maintenance(&mut self) -> Result<(), VolumeError> {
 		let mut state = self.state.write().await;
 
-		Ok(())
+		self.history.prune().await
 	}
 
 	async fn scan_volumes(&mut self) -> Result<(), VolumeError> {
@@ -340,7 +576,47 @@ impl VolumeManagerActor {
 				let _ = ack.send(result);
 			}
 			VolumeManagerMessage::ListLibraryVolumes { library, ack } => {
-				todo!();
+				let result = self.handle_list_library_volumes(library).await;
+				let _ = ack.send(result);
+			}
+			VolumeManagerMessage::ListVolumes { ack } => {
+				let _ = ack.send(self.get_volumes().await);
+			}
+			VolumeManagerMessage::ScanVolumes { progress_tx, ack } => {
+				let result = self
+					.state
+					.write()
+					.await
+					.scan_volumes_with_progress(progress_tx)
+					.await;
+				let _ = ack.send(result);
+			}
+			VolumeManagerMessage::Snapshot { ack } => {
+				let _ = ack.send(self.snapshot().await);
+			}
+			VolumeManagerMessage::RestoreSnapshot { snapshot, ack } => {
+				let _ = ack.send(self.restore_snapshot(snapshot).await);
+			}
+			VolumeManagerMessage::ReportIoErrors { path, errors, ack } => {
+				let _ = ack.send(self.report_io_errors(&path, errors).await);
+			}
+			VolumeManagerMessage::VolumeAt { path, ack } => {
+				let state = self.state.read().await;
+				let registry = state.registry.read().await;
+				let volume = registry
+					.volume_at(&path)
+					.map(|(fingerprint, volume)| Volume {
+						fingerprint: Some(fingerprint.clone()),
+						..volume.clone()
+					});
+				let _ = ack.send(volume);
+			}
+			VolumeManagerMessage::GetVolume { fingerprint, ack } => {
+				let volume = self.state.read().await.get_volume(&fingerprint).await;
+				let _ = ack.send(volume.map(|volume| Volume {
+					fingerprint: Some(fingerprint),
+					..volume
+				}));
 			}
 			VolumeManagerMessage::TrackVolume {
 				fingerprint,
@@ -354,12 +630,48 @@ impl VolumeManagerActor {
 				fingerprint,
 				library,
 				ack,
-			} => todo!(),
+			} => {
+				let result = self.handle_untrack_volume(library, fingerprint).await;
+				let _ = ack.send(result);
+			}
 			VolumeManagerMessage::UpdateVolume { volume, ack } => todo!(),
-			VolumeManagerMessage::MountVolume { fingerprint, ack } => todo!(),
-			VolumeManagerMessage::UnmountVolume { fingerprint, ack } => {
+			VolumeManagerMessage::MountVolume {
+				device,
+				options,
+				ack,
+			} => {
+				let result = self.handle_mount_volume(device, options).await;
+				let _ = ack.send(result);
+			}
+			VolumeManagerMessage::MountNetworkShare {
+				share,
+				mount_point,
+				ack,
+			} => {
+				let result = self.handle_mount_network_share(share, mount_point).await;
+				let _ = ack.send(result);
+			}
+			VolumeManagerMessage::AttachDiskImage {
+				image,
+				read_only,
+				ack,
+			} => {
+				let result = self.handle_attach_disk_image(image, read_only).await;
+				let _ = ack.send(result);
+			}
+			VolumeManagerMessage::RunOperation {
+				fingerprint,
+				operation,
+				dry_run,
+				ack,
+			} => {
 				let result = self
-					.handle_unmount_volume(fingerprint, self.ctx.device_id.clone().into())
+					.handle_operation(
+						fingerprint,
+						operation,
+						dry_run,
+						self.ctx.device_id.clone().into(),
+					)
 					.await;
 				let _ = ack.send(result);
 			}
@@ -367,7 +679,23 @@ impl VolumeManagerActor {
 				fingerprint,
 				ack,
 				library,
-			} => todo!(),
+			} => self.handle_speed_test(fingerprint, library, ack).await,
+			VolumeManagerMessage::InitializeLibrary { library } => {
+				debug!("Initializing volume manager for library");
+				if let Err(e) = self.initialize_for_library(library).await {
+					error!(?e, "Failed to initialize volume manager for library");
+				}
+			}
+			VolumeManagerMessage::CleanupLibrary { library } => {
+				if let Err(e) = self.handle_library_deletion(library).await {
+					error!(?e, "Failed to cleanup library volumes");
+				}
+			}
+			VolumeManagerMessage::Maintenance => {
+				if let Err(e) = self.perform_maintenance().await {
+					error!(?e, "Volume maintenance error");
+				}
+			}
 		}
 		Ok(())
 	}
@@ -386,10 +714,79 @@ impl VolumeManagerActor {
 			.await
 	}
 
+	/// The volumes tracked in the library, the unmounted ones included
+	async fn handle_list_library_volumes(
+		&self,
+		library: Arc<Library>,
+	) -> Result<Vec<Volume>, VolumeError> {
+		let volumes = self
+			.state
+			.read()
+			.await
+			.get_volumes_for_library(library)
+			.await?;
+
+		Ok(volumes
+			.into_iter()
+			.filter(Volume::is_volume_tracked)
+			.collect())
+	}
+
 	pub async fn get_volumes(&self) -> Vec<Volume> {
 		self.state.read().await.list_volumes().await
 	}
 
+	/// Captures the known volumes, what the platform couldn't report and the watcher status
+	pub async fn snapshot(&self) -> VolumeSnapshot {
+		VolumeSnapshot::new(
+			self.ctx.device_id.clone(),
+			self.get_volumes().await,
+			self.ctx.platform.degraded_mode(),
+			self.watcher_status.read().await.clone(),
+		)
+	}
+
+	/// Replaces the known volumes with the ones of the snapshot, until the next scan finds the real
+	/// ones again
+	pub async fn restore_snapshot(&self, snapshot: VolumeSnapshot) -> Result<(), VolumeError> {
+		snapshot.check_version()?;
+		self.state.write().await.restore(snapshot.volumes).await;
+		Ok(())
+	}
+
+	/// Counts the I/O errors on the tracked volume holding `path`, marking it degraded once they
+	/// reach the threshold
+	async fn report_io_errors(&self, path: &Path, errors: u32) -> Option<Volume> {
+		let threshold = self.ctx.config.borrow().volumes.io_error_threshold;
+		let (fingerprint, old) = {
+			let state = self.state.read().await;
+			let registry = state.registry.read().await;
+			registry
+				.volume_at(path)
+				.filter(|(_, volume)| volume.is_volume_tracked())
+				.map(|(fingerprint, volume)| (fingerprint.clone(), volume.clone()))?
+		};
+		if old.is_degraded {
+			return None;
+		}
+
+		let errors = self.io_errors.record(&fingerprint, errors, threshold)?;
+		warn!(name = %old.name, %errors, "Volume keeps failing to read or write, quarantining it;");
+
+		let mut new = old.clone();
+		new.is_degraded = true;
+		self.event_tx.send(VolumeEvent::VolumeUpdated {
+			old,
+			new: new.clone(),
+		});
+		self.event_tx.send(VolumeEvent::VolumeDegraded {
+			volume: new.clone(),
+			errors,
+		});
+
+		Some(new)
+	}
+
 	pub async fn volume_exists(&self, fingerprint: VolumeFingerprint) -> bool {
 		self.state.read().await.volume_exists(&fingerprint).await
 	}
@@ -452,64 +849,237 @@ impl VolumeManagerActor {
 		fingerprint: VolumeFingerprint,
 	) -> Result<(), VolumeError> {
 		let state = self.state.write().await;
-		let device_pub_id = self.ctx.device_id.clone();
+		let device_pub_id = DevicePubId::from(self.ctx.device_id.clone()).to_db();
 
 		// Find the volume in our current system volumes
 		let mut registry = state.registry.write().await;
-		let mut volume = match registry.get_volume_mut(&fingerprint) {
-			Some(v) => v.clone(),
-			None => return Err(VolumeError::InvalidFingerprint(fingerprint.clone())),
+		let Some(volume) = registry.get_volume_mut(&fingerprint) else {
+			return Err(VolumeError::InvalidFingerprint(fingerprint.clone()));
 		};
+		// It gets another fingerprint once unlocked, the record would be left behind
+		if volume.is_locked {
+			return Err(VolumeError::Locked(volume.mount_point.clone()));
+		}
+
+		// Tracking it again only brings the record up to date
+		let records = tracked::device_records(&library.db, &device_pub_id).await?;
+		let updated = match records.iter().find(|(record, _)| *record == fingerprint) {
+			Some((_, record)) => {
+				let updated = Volume::merge_with_db(volume, record);
+				updated.update(&library).await?;
+				updated
+			}
+			// Create in database with current device association, the speed test is left to a
+			// maintenance job queued by the caller
+			None => {
+				let created = volume.create(&library, device_pub_id).await?;
+				Volume::merge_with_db(volume, &created)
+			}
+		};
+		*volume = updated;
+
+		Ok(())
+	}
+
+	/// Deletes the records of the volume from the library, the volume stays listed as untracked
+	async fn handle_untrack_volume(
+		&mut self,
+		library: Arc<Library>,
+		fingerprint: VolumeFingerprint,
+	) -> Result<(), VolumeError> {
+		let device_id = DevicePubId::from(self.ctx.device_id.clone()).to_db();
+
+		let records = tracked::device_records(&library.db, &device_id)
+			.await?
+			.into_iter()
+			.filter(|(record, _)| *record == fingerprint)
+			.map(|(_, volume)| volume)
+			.collect::<Vec<_>>();
+		tracked::delete(&library, &records).await?;
 
-		// Create in database with current device association
-		volume.create(&library.db, device_pub_id.into()).await?;
+		let state = self.state.read().await;
+		if let Some(volume) = state.registry.write().await.get_volume_mut(&fingerprint) {
+			volume.id = None;
+			volume.pub_id = None;
+			volume.device_id = None;
+		}
+
+		Ok(())
+	}
+
+	/// The test runs apart so the other messages aren't held up by a slow drive, the speeds reach
+	/// the registry through the `VolumeSpeedTested` event
+	async fn handle_speed_test(
+		&self,
+		fingerprint: VolumeFingerprint,
+		library: Arc<Library>,
+		ack: oneshot::Sender<Result<Volume, VolumeError>>,
+	) {
+		let Some(mut volume) = self.state.read().await.get_volume(&fingerprint).await else {
+			let _ = ack.send(Err(VolumeError::NotFound(fingerprint)));
+			return;
+		};
 
-		// Spawn a background task to perform the speed test
 		let event_tx = self.event_tx.clone();
-		let mut volume = volume.clone();
 		tokio::spawn(async move {
-			if let Err(e) = volume.speed_test(None, Some(&event_tx)).await {
-				error!(?e, "Failed to perform speed test for volume");
+			let result: Result<_, VolumeError> = async {
+				volume.speed_test(None, Some(&event_tx)).await?;
+				if volume.is_volume_tracked() {
+					volume.update(&library).await?;
+				}
+				Ok(volume)
 			}
+			.await;
+			let _ = ack.send(result);
 		});
-
-		Ok(())
 	}
 
-	async fn handle_unmount_volume(
+	/// Runs the checks of the operation, then the operation itself unless it's a dry run or a
+	/// check failed
+	async fn handle_operation(
 		&mut self,
 		fingerprint: VolumeFingerprint,
+		operation: VolumeOperation,
+		dry_run: bool,
 		device_pub_id: DevicePubId,
-	) -> Result<(), VolumeError> {
+	) -> Result<OperationPlan, VolumeError> {
 		let state = self.state.read().await;
 		let volume = state
 			.get_volume(&fingerprint)
 			.await
 			.ok_or_else(|| VolumeError::NotFound(fingerprint.clone()))?;
 
-		if !volume.is_mounted {
-			return Err(VolumeError::NotMounted(volume.mount_point.clone()));
+		let (plan, checked) = plan::plan(&*self.ctx.platform, operation, &volume).await;
+		if dry_run {
+			return Ok(plan);
 		}
+		checked?;
 
-		// Call platform-specific unmount
-		super::os::unmount_volume(&volume.mount_point).await?;
+		// Taken away on purpose, a share isn't mounted again once it's gone
+		let share = self.network_shares.remove(&volume.mount_point);
+		// An image is detached along with its volume, or its device would keep holding the file
+		let image = match &plan.operation {
+			VolumeOperation::Unmount | VolumeOperation::Eject => {
+				self.disk_images.remove(&volume.mount_point)
+			}
+			VolumeOperation::Format { .. } => None,
+		};
 
-		let fingerprint = VolumeFingerprint::new(&device_pub_id, &volume);
+		// Call platform-specific operation
+		let res = match (&plan.operation, &image) {
+			(_, Some(image)) => self.ctx.platform.detach_disk_image(image).await,
+			(VolumeOperation::Unmount, None) => {
+				self.ctx.platform.unmount_volume(&volume.mount_point).await
+			}
+			(VolumeOperation::Eject, None) => {
+				self.ctx.platform.eject_volume(&volume.mount_point).await
+			}
+			(VolumeOperation::Format { file_system }, None) => {
+				self.ctx
+					.platform
+					.format_volume(&volume.mount_point, file_system)
+					.await
+			}
+		};
+		if res.is_err() {
+			if let Some(share) = share {
+				self.network_shares
+					.insert(volume.mount_point.clone(), share);
+			}
+			if let Some(image) = image {
+				self.disk_images.insert(image);
+			}
+		}
+		res?;
+
+		let fingerprint = VolumeFingerprint::new(&device_pub_id.to_db(), &volume);
 
 		// Emit unmount event
 		if let Some(pub_id) = volume.pub_id.as_ref() {
-			let _ = self.event_tx.send(VolumeEvent::VolumeMountChanged {
+			self.event_tx.send(VolumeEvent::VolumeMountChanged {
 				fingerprint,
 				is_mounted: false,
 			});
 		}
 
-		Ok(())
+		Ok(plan)
+	}
+
+	/// Mounts the share with the credentials stored for it and scans, returning the volume it
+	/// became. It's mounted again whenever it drops, until it's unmounted through the manager.
+	async fn handle_mount_network_share(
+		&mut self,
+		share: NetworkShare,
+		mount_point: Option<PathBuf>,
+	) -> Result<Volume, VolumeError> {
+		let mount_point = mount_point.unwrap_or_else(|| network::default_mount_point(&share));
+		network::mount(&*self.ctx.platform, &self.ctx.secrets, &share, &mount_point).await?;
+		self.network_shares.insert(mount_point.clone(), share);
+
+		self.scanned_volume_at(&mount_point)
+			.await?
+			.ok_or_else(|| VolumeError::MountFailed {
+				device: mount_point,
+				reason: "the share isn't listed after mounting it".to_string(),
+			})
+	}
+
+	/// Attaches the image and scans, returning the volume it became. It's detached when the node
+	/// shuts down unless it's unmounted through the manager first.
+	async fn handle_attach_disk_image(
+		&mut self,
+		image: PathBuf,
+		read_only: bool,
+	) -> Result<Volume, VolumeError> {
+		let attached = image::attach(&*self.ctx.platform, &image, read_only).await?;
+		let mount_point = attached.mount_point.clone();
+		self.disk_images.insert(attached);
+
+		self.scanned_volume_at(&mount_point)
+			.await?
+			.ok_or_else(|| VolumeError::MountFailed {
+				device: image,
+				reason: format!(
+					"nothing is listed at {} after attaching it",
+					mount_point.display()
+				),
+			})
+	}
+
+	/// Mounts the device and scans, the volume it became is published with `VolumeAdded`
+	async fn handle_mount_volume(
+		&mut self,
+		device: PathBuf,
+		options: MountOptions,
+	) -> Result<Volume, VolumeError> {
+		let mount_point = self.ctx.platform.mount_volume(&device, &options).await?;
+
+		self.scanned_volume_at(&mount_point)
+			.await?
+			.ok_or_else(|| VolumeError::MountFailed {
+				device,
+				reason: format!(
+					"nothing is listed at {} after mounting it",
+					mount_point.display()
+				),
+			})
+	}
+
+	/// Scans, returning the volume mounted at `mount_point` if there is one now
+	async fn scanned_volume_at(&self, mount_point: &Path) -> Result<Option<Volume>, VolumeError> {
+		let mut state = self.state.write().await;
+		state.scan_volumes().await?;
+		Ok(state
+			.list_volumes()
+			.await
+			.into_iter()
+			.find(|volume| volume.mount_point == mount_point))
 	}
 
 	async fn handle_library_deletion(&mut self, library: Arc<Library>) -> Result<(), VolumeError> {
 		// Clean up volumes associated with deleted library
 		let _state = self.state.write().await;
+		self.history.remove_library(library.id).await;
 
 		// TODO: Implement library deletion cleanup
 		// This might involve:
@@ -0,0 +1,761 @@
+//! How the space of the tracked volumes is used, by category of files and by top-level directory,
+//! for the treemap of each volume.
+//!
+//! The breakdown job walks every top-level directory of the volume in a step of its own, without
+//! crossing into the file systems mounted inside it, and replaces the usage recorded for that
+//! directory. When the indexer changes at least [`LARGE_CHANGE`] paths of a location on a volume
+//! with a breakdown, only the top-level directory the location is in is walked again.
+
+use super::{
+	error::VolumeError,
+	types::{ByteSize, Volume, VolumeFingerprint},
+};
+use crate::{
+	invalidate_query,
+	library::Library,
+	old_job::{
+		CurrentStep, JobError, JobInitOutput, JobReportUpdate, JobResult, JobRunErrors,
+		JobRunMetadata, JobStepOutput, OldJob, StatefulJob, WorkerContext,
+	},
+	Node,
+};
+
+use sd_core_heavy_lifting::{
+	job_system::report::{ReportInputMetadata, ReportMetadata, ReportOutputMetadata},
+	JobName, JobOutput,
+};
+use sd_file_ext::{extensions::Extension, kind::ObjectKind, magic::ExtensionPossibility};
+use sd_prisma::prisma::{location, volume_space_usage, PrismaClient, SortOrder};
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	ffi::OsStr,
+	fs, io,
+	path::{Component, Path, PathBuf},
+	sync::Arc,
+};
+
+use chrono::{DateTime, FixedOffset, SubsecRound, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use tokio::task;
+use tracing::{debug, warn};
+
+/// Paths an indexer run has to add, update or remove for the breakdown to be walked again
+pub const LARGE_CHANGE: u64 = 1000;
+
+/// The directories operating systems keep to themselves, at the root of the volume they run from
+const SYSTEM_DIRECTORIES: [&str; 24] = [
+	// Linux
+	"bin",
+	"boot",
+	"etc",
+	"lib",
+	"lib32",
+	"lib64",
+	"sbin",
+	"snap",
+	"usr",
+	"var",
+	// macOS
+	"Library",
+	"System",
+	"private",
+	"cores",
+	// Windows
+	"Windows",
+	"Program Files",
+	"Program Files (x86)",
+	"ProgramData",
+	"Recovery",
+	"$Recycle.Bin",
+	"System Volume Information",
+	"pagefile.sys",
+	"hiberfil.sys",
+	"swapfile.sys",
+];
+
+#[derive(
+	Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Type,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SpaceCategory {
+	/// Documents, text, books, code and the files configuring apps
+	Documents,
+	/// Pictures, videos, audio and 3D models
+	Media,
+	Archives,
+	/// The operating system and the programs
+	System,
+	Other,
+}
+
+impl SpaceCategory {
+	pub const fn as_str(self) -> &'static str {
+		match self {
+			Self::Documents => "documents",
+			Self::Media => "media",
+			Self::Archives => "archives",
+			Self::System => "system",
+			Self::Other => "other",
+		}
+	}
+
+	fn from_record(category: &str) -> Option<Self> {
+		[
+			Self::Documents,
+			Self::Media,
+			Self::Archives,
+			Self::System,
+			Self::Other,
+		]
+		.into_iter()
+		.find(|known| known.as_str() == category)
+	}
+
+	/// The category of a file outside of the system directories, from its extension
+	pub fn of(path: &Path) -> Self {
+		let Some(extension) = path
+			.extension()
+			.and_then(OsStr::to_str)
+			.and_then(Extension::from_str)
+		else {
+			return Self::Other;
+		};
+
+		// Conflicting extensions, like `ts` for videos and code, go with the first kind
+		let extension = match extension {
+			ExtensionPossibility::Known(extension) => extension,
+			ExtensionPossibility::Conflicts(mut extensions) => extensions.swap_remove(0),
+		};
+
+		match ObjectKind::from(extension) {
+			ObjectKind::Document
+			| ObjectKind::Text
+			| ObjectKind::Book
+			| ObjectKind::Code
+			| ObjectKind::Config
+			| ObjectKind::Database => Self::Documents,
+			ObjectKind::Image | ObjectKind::Video | ObjectKind::Audio | ObjectKind::Mesh => {
+				Self::Media
+			}
+			ObjectKind::Archive => Self::Archives,
+			ObjectKind::Executable => Self::System,
+			_ => Self::Other,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct SpaceUsage {
+	pub total_bytes: ByteSize,
+	pub files_count: u64,
+}
+
+impl SpaceUsage {
+	fn add(&mut self, other: Self) {
+		self.total_bytes.0 += other.total_bytes.0;
+		self.files_count += other.files_count;
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Type)]
+pub struct CategoryUsage {
+	pub category: SpaceCategory,
+	pub usage: SpaceUsage,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Type)]
+pub struct DirectoryUsage {
+	/// Relative to the mount point, empty for the files right in it
+	pub directory: String,
+	pub usage: SpaceUsage,
+	pub categories: Vec<CategoryUsage>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Type)]
+pub struct SpaceBreakdown {
+	/// When the oldest of the directories was walked, `None` before the first breakdown
+	pub computed_at: Option<DateTime<Utc>>,
+	pub usage: SpaceUsage,
+	pub categories: Vec<CategoryUsage>,
+	/// The largest first
+	pub directories: Vec<DirectoryUsage>,
+}
+
+/// Adds `usage` to the one of its category, keeping the categories in order
+fn add_to(categories: &mut Vec<CategoryUsage>, category: SpaceCategory, usage: SpaceUsage) {
+	match categories.binary_search_by_key(&category, |entry| entry.category) {
+		Ok(index) => categories[index].usage.add(usage),
+		Err(index) => categories.insert(index, CategoryUsage { category, usage }),
+	}
+}
+
+/// The last breakdown of the volume in the library
+pub async fn space_breakdown(
+	db: &PrismaClient,
+	fingerprint: &VolumeFingerprint,
+) -> Result<SpaceBreakdown, VolumeError> {
+	let records = db
+		.volume_space_usage()
+		.find_many(vec![volume_space_usage::fingerprint::equals(
+			fingerprint.0.clone(),
+		)])
+		.order_by(volume_space_usage::directory::order(SortOrder::Asc))
+		.exec()
+		.await?;
+
+	Ok(breakdown(records.into_iter().filter_map(|record| {
+		// Categories written by a newer version are skipped
+		let category = SpaceCategory::from_record(&record.category)?;
+		let usage = SpaceUsage {
+			total_bytes: ByteSize(record.total_bytes as u64),
+			files_count: record.files_count as u64,
+		};
+		Some((record.directory, category, usage, record.computed_at))
+	})))
+}
+
+/// Adds up the usage of each directory and category
+fn breakdown(
+	records: impl IntoIterator<Item = (String, SpaceCategory, SpaceUsage, DateTime<FixedOffset>)>,
+) -> SpaceBreakdown {
+	let mut breakdown = SpaceBreakdown::default();
+	let mut directories = BTreeMap::<String, DirectoryUsage>::new();
+
+	for (directory, category, usage, computed_at) in records {
+		let computed_at = computed_at.with_timezone(&Utc);
+		if breakdown
+			.computed_at
+			.map_or(true, |oldest| computed_at < oldest)
+		{
+			breakdown.computed_at = Some(computed_at);
+		}
+
+		breakdown.usage.add(usage);
+		add_to(&mut breakdown.categories, category, usage);
+
+		let entry = directories
+			.entry(directory.clone())
+			.or_insert_with(|| DirectoryUsage {
+				directory,
+				usage: SpaceUsage::default(),
+				categories: vec![],
+			});
+		entry.usage.add(usage);
+		add_to(&mut entry.categories, category, usage);
+	}
+
+	breakdown.directories = directories.into_values().collect();
+	breakdown
+		.directories
+		.sort_by(|a, b| b.usage.total_bytes.cmp(&a.usage.total_bytes));
+
+	breakdown
+}
+
+/// Walks `directory` of the volume mounted at `mount_point`, only the files right in the mount
+/// point when it's empty. Nothing is counted for a directory another file system is mounted on.
+/// Blocking.
+fn walk(mount_point: &Path, directory: &Path) -> io::Result<HashMap<SpaceCategory, SpaceUsage>> {
+	let mut usage = HashMap::<SpaceCategory, SpaceUsage>::new();
+	let root = fs::symlink_metadata(mount_point)?;
+	let is_system = directory
+		.to_str()
+		.is_some_and(|directory| SYSTEM_DIRECTORIES.contains(&directory));
+
+	let start = mount_point.join(directory);
+	let recursive = !directory.as_os_str().is_empty();
+	// Like `/proc` and `/sys` on `/`, they aren't on this volume
+	if recursive && !on_same_file_system(&root, &fs::symlink_metadata(&start)?) {
+		return Ok(usage);
+	}
+
+	let mut pending = vec![start];
+	while let Some(path) = pending.pop() {
+		// Directories the user can't open are left out, like the ones of other users
+		let Ok(entries) = fs::read_dir(&path) else {
+			continue;
+		};
+
+		for entry in entries.flatten() {
+			let Ok(metadata) = entry.metadata() else {
+				continue;
+			};
+			// Symbolic links point at files counted where they are
+			if metadata.is_symlink() {
+				continue;
+			}
+			if metadata.is_dir() {
+				if recursive && on_same_file_system(&root, &metadata) {
+					pending.push(entry.path());
+				}
+				continue;
+			}
+
+			let category = if is_system {
+				SpaceCategory::System
+			} else if !recursive
+				&& entry
+					.file_name()
+					.to_str()
+					.is_some_and(|name| SYSTEM_DIRECTORIES.contains(&name))
+			{
+				// The swap and hibernation files of Windows
+				SpaceCategory::System
+			} else {
+				SpaceCategory::of(&entry.path())
+			};
+			usage.entry(category).or_default().add(SpaceUsage {
+				total_bytes: ByteSize(metadata.len()),
+				files_count: 1,
+			});
+		}
+	}
+
+	Ok(usage)
+}
+
+#[cfg(unix)]
+fn on_same_file_system(root: &fs::Metadata, metadata: &fs::Metadata) -> bool {
+	use std::os::unix::fs::MetadataExt;
+
+	root.dev() == metadata.dev()
+}
+
+/// Windows mounts other volumes in directories too rarely to be worth opening each of them
+#[cfg(not(unix))]
+fn on_same_file_system(_: &fs::Metadata, _: &fs::Metadata) -> bool {
+	true
+}
+
+#[derive(Debug, Serialize, Deserialize, Hash, Type)]
+pub struct OldVolumeBreakdownJobInit {
+	pub fingerprint: VolumeFingerprint,
+	/// Top-level directories to walk again, relative to the mount point. The whole volume when
+	/// empty.
+	pub directories: Vec<PathBuf>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VolumeBreakdownJobData {
+	mount_point: PathBuf,
+	name: String,
+	started_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct VolumeBreakdownJobRunMetadata {
+	directories_walked: usize,
+	files_count: u64,
+	total_bytes: u64,
+}
+
+impl JobRunMetadata for VolumeBreakdownJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.directories_walked += new_data.directories_walked;
+		self.files_count += new_data.files_count;
+		self.total_bytes += new_data.total_bytes;
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for OldVolumeBreakdownJobInit {
+	type Data = VolumeBreakdownJobData;
+	/// The top-level directory to walk, empty for the files right in the mount point
+	type Step = PathBuf;
+	type RunMetadata = VolumeBreakdownJobRunMetadata;
+
+	const NAME: &'static str = "volume_breakdown";
+
+	fn target_location(&self) -> location::id::Type {
+		// Volumes aren't tied to a location, and location ids start at 1
+		0
+	}
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let volume = ctx
+			.node
+			.volumes
+			.list_system_volumes(Arc::clone(&ctx.library))
+			.await?
+			.into_iter()
+			.find(|volume| volume.fingerprint.as_ref() == Some(&self.fingerprint))
+			.ok_or_else(|| VolumeError::NotFound(self.fingerprint.clone()))?;
+		if !volume.is_volume_tracked() {
+			return Err(VolumeError::NotInDatabase.into());
+		}
+		if !volume.is_mounted {
+			return Err(VolumeError::NotMounted(volume.mount_point).into());
+		}
+
+		let steps = if self.directories.is_empty() {
+			let root = tokio::fs::symlink_metadata(&volume.mount_point)
+				.await
+				.map_err(VolumeError::from)?;
+			let mut steps = vec![PathBuf::new()];
+			let mut entries = tokio::fs::read_dir(&volume.mount_point)
+				.await
+				.map_err(VolumeError::from)?;
+			while let Some(entry) = entries.next_entry().await.map_err(VolumeError::from)? {
+				// The directories other file systems are mounted on aren't walked
+				if entry.metadata().await.is_ok_and(|metadata| {
+					metadata.is_dir() && on_same_file_system(&root, &metadata)
+				}) {
+					steps.push(PathBuf::from(entry.file_name()));
+				}
+			}
+			steps
+		} else {
+			self.directories.clone()
+		};
+
+		*data = Some(VolumeBreakdownJobData {
+			mount_point: volume.mount_point,
+			name: volume.name,
+			// Whole seconds, so none of the rows of this run is older once stored
+			started_at: Utc::now().trunc_subsecs(0),
+		});
+
+		Ok(steps.into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep { step, .. }: CurrentStep<'_, Self::Step>,
+		data: &Self::Data,
+		_: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let mut new_metadata = Self::RunMetadata::default();
+
+		ctx.progress(vec![JobReportUpdate::Message(format!(
+			"Measuring {}",
+			data.mount_point.join(step).display()
+		))]);
+
+		let directory = step.to_string_lossy().into_owned();
+		let walked = task::spawn_blocking({
+			let (mount_point, step) = (data.mount_point.clone(), step.clone());
+			move || walk(&mount_point, &step)
+		})
+		.await
+		.map_err(VolumeError::from)?;
+
+		let usage = match walked {
+			Ok(usage) => usage,
+			// Deleted since, its usage goes with it
+			Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+			Err(e) => {
+				warn!(?e, %directory, "Failed to walk the directory of the volume;");
+				return Ok((
+					new_metadata,
+					JobRunErrors(vec![format!(
+						"{}: {e}",
+						data.mount_point.join(step).display()
+					)]),
+				)
+					.into());
+			}
+		};
+
+		let db = &ctx.library.db;
+		db._batch((
+			db.volume_space_usage().delete_many(vec![
+				volume_space_usage::fingerprint::equals(self.fingerprint.0.clone()),
+				volume_space_usage::directory::equals(directory.clone()),
+			]),
+			db.volume_space_usage().create_many(
+				usage
+					.iter()
+					.map(|(category, usage)| {
+						volume_space_usage::create_unchecked(
+							self.fingerprint.0.clone(),
+							directory.clone(),
+							category.as_str().to_string(),
+							usage.total_bytes.0 as i64,
+							usage.files_count as i64,
+							vec![],
+						)
+					})
+					.collect(),
+			),
+		))
+		.await
+		.map_err(VolumeError::from)?;
+
+		new_metadata.directories_walked = 1;
+		for usage in usage.values() {
+			new_metadata.files_count += usage.files_count;
+			new_metadata.total_bytes += usage.total_bytes.0;
+		}
+
+		Ok(new_metadata.into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		// The directories a full walk didn't find anymore
+		if let (true, Some(data)) = (self.directories.is_empty(), data) {
+			ctx.library
+				.db
+				.volume_space_usage()
+				.delete_many(vec![
+					volume_space_usage::fingerprint::equals(self.fingerprint.0.clone()),
+					volume_space_usage::computed_at::lt(data.started_at.into()),
+				])
+				.exec()
+				.await
+				.map_err(VolumeError::from)?;
+		}
+
+		invalidate_query!(ctx.library, "volumes.spaceBreakdown");
+
+		Ok(Some(json!({
+			"init": self,
+			"volume": data.as_ref().map(|data| &data.name),
+			"run_metadata": run_metadata,
+		})))
+	}
+}
+
+/// Walks the top-level directory of the location again when the indexer changed a lot of it, if
+/// its volume has a breakdown. The walk is queued in the background.
+pub(crate) fn on_job_output(node: &Arc<Node>, output: &JobOutput) {
+	if output.job_name() != JobName::Indexer {
+		return;
+	}
+
+	let mut location = None;
+	let mut changed = 0;
+	for metadata in output.metadata() {
+		match metadata {
+			ReportMetadata::Input(ReportInputMetadata::Location(data)) => location = Some(data),
+			ReportMetadata::Output(ReportOutputMetadata::Metrics(metrics)) => {
+				changed = ["indexed_count", "updated_count", "removed_count"]
+					.iter()
+					.filter_map(|key| metrics.get(*key).and_then(serde_json::Value::as_u64))
+					.sum();
+			}
+			_ => {}
+		}
+	}
+	let Some(location) = location else {
+		return;
+	};
+	if changed < LARGE_CHANGE {
+		return;
+	}
+	let Some(path) = location.path.as_deref().map(PathBuf::from) else {
+		return;
+	};
+
+	let node = Arc::clone(node);
+	let location_pub_id = location.pub_id.clone();
+	tokio::spawn(async move {
+		for library in node.libraries.get_all().await {
+			if library.is_read_only().await {
+				continue;
+			}
+
+			let res = rerun(&node, &library, &location_pub_id, &path).await;
+			if let Err(e) = res {
+				warn!(?e, library_id = %library.id, "Failed to queue the volume breakdown;");
+			}
+		}
+	});
+}
+
+async fn rerun(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	location_pub_id: &[u8],
+	path: &Path,
+) -> Result<(), JobError> {
+	let exists = library
+		.db
+		.location()
+		.count(vec![location::pub_id::equals(location_pub_id.to_vec())])
+		.exec()
+		.await?;
+	if exists == 0 {
+		return Ok(());
+	}
+
+	let volumes = node
+		.volumes
+		.list_system_volumes(Arc::clone(library))
+		.await?;
+	let Some((fingerprint, directory)) = top_level_directory(&volumes, path) else {
+		return Ok(());
+	};
+
+	let has_breakdown = library
+		.db
+		.volume_space_usage()
+		.count(vec![volume_space_usage::fingerprint::equals(
+			fingerprint.0.clone(),
+		)])
+		.exec()
+		.await?;
+	if has_breakdown == 0 {
+		return Ok(());
+	}
+
+	debug!(%fingerprint, ?directory, "Walking the volume again after a large change;");
+	OldJob::new(OldVolumeBreakdownJobInit {
+		fingerprint,
+		directories: directory.into_iter().collect(),
+	})
+	.spawn(node, library)
+	.await
+}
+
+/// The tracked volume `path` is on, the innermost one, and the top-level directory of that volume
+/// the path is in. `None` for the directory when the path is the mount point.
+fn top_level_directory(
+	volumes: &[Volume],
+	path: &Path,
+) -> Option<(VolumeFingerprint, Option<PathBuf>)> {
+	let volume = volumes
+		.iter()
+		.filter(|volume| volume.is_volume_tracked() && path.starts_with(&volume.mount_point))
+		.max_by_key(|volume| volume.mount_point.components().count())?;
+
+	let directory = path
+		.strip_prefix(&volume.mount_point)
+		.ok()?
+		.components()
+		.find_map(|component| match component {
+			Component::Normal(name) => Some(PathBuf::from(name)),
+			_ => None,
+		});
+
+	Some((volume.fingerprint.clone()?, directory))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::volume::{DiskType, FileSystem, MountType};
+
+	fn tracked(mount_point: &str, fingerprint: u8) -> Volume {
+		let mut volume = Volume::new(
+			mount_point.to_string(),
+			MountType::External,
+			PathBuf::from(mount_point),
+			vec![PathBuf::from(mount_point)],
+			DiskType::SSD,
+			FileSystem::EXT4,
+			1000,
+			500,
+			false,
+		);
+		volume.pub_id = Some(vec![fingerprint]);
+		volume.fingerprint = Some(VolumeFingerprint(vec![fingerprint]));
+		volume
+	}
+
+	#[test]
+	fn sorts_files_by_their_extension() {
+		assert_eq!(
+			SpaceCategory::of(Path::new("report.PDF")),
+			SpaceCategory::Documents
+		);
+		assert_eq!(
+			SpaceCategory::of(Path::new("holiday.mp4")),
+			SpaceCategory::Media
+		);
+		assert_eq!(
+			SpaceCategory::of(Path::new("backup.zip")),
+			SpaceCategory::Archives
+		);
+		assert_eq!(
+			SpaceCategory::of(Path::new("no-extension")),
+			SpaceCategory::Other
+		);
+	}
+
+	#[test]
+	fn adds_up_the_directories_and_categories() {
+		let now = Utc::now().fixed_offset();
+		let usage = |total_bytes, files_count| SpaceUsage {
+			total_bytes: ByteSize(total_bytes),
+			files_count,
+		};
+
+		let breakdown = breakdown([
+			(
+				"docs".to_string(),
+				SpaceCategory::Documents,
+				usage(10, 2),
+				now,
+			),
+			(
+				"videos".to_string(),
+				SpaceCategory::Media,
+				usage(300, 3),
+				now,
+			),
+			("docs".to_string(), SpaceCategory::Media, usage(40, 1), now),
+			(String::new(), SpaceCategory::Other, usage(5, 1), now),
+		]);
+
+		assert_eq!(breakdown.usage, usage(355, 7));
+		assert_eq!(
+			breakdown.categories,
+			[
+				CategoryUsage {
+					category: SpaceCategory::Documents,
+					usage: usage(10, 2)
+				},
+				CategoryUsage {
+					category: SpaceCategory::Media,
+					usage: usage(340, 4)
+				},
+				CategoryUsage {
+					category: SpaceCategory::Other,
+					usage: usage(5, 1)
+				},
+			]
+		);
+		let directories = breakdown
+			.directories
+			.iter()
+			.map(|directory| (directory.directory.as_str(), directory.usage))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			directories,
+			[
+				("videos", usage(300, 3)),
+				("docs", usage(50, 3)),
+				("", usage(5, 1))
+			]
+		);
+	}
+
+	#[test]
+	fn finds_the_top_level_directory_of_a_location() {
+		let volumes = [tracked("/", 1), tracked("/media/usb", 2)];
+
+		assert_eq!(
+			top_level_directory(&volumes, Path::new("/home/alice/Photos")),
+			Some((VolumeFingerprint(vec![1]), Some(PathBuf::from("home"))))
+		);
+		assert_eq!(
+			top_level_directory(&volumes, Path::new("/media/usb")),
+			Some((VolumeFingerprint(vec![2]), None))
+		);
+		assert_eq!(top_level_directory(&volumes[1..], Path::new("/srv")), None);
+	}
+}
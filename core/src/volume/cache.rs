@@ -0,0 +1,395 @@
+//! Short lived cache in front of the platform, so the watcher, queries and path resolution asking
+//! for the volumes at about the same time share a single scan.
+//!
+//! Entries expire after `volumes.scan_cache_ttl_ms` and are dropped as soon as the platform
+//! reports a change, so a freshly plugged drive never waits for the TTL to show up.
+
+use crate::node::CoreConfig;
+
+use super::{
+	error::VolumeError,
+	fs_snapshot::FileSystemSnapshot,
+	image::AttachedImage,
+	network::{NetworkShare, ShareCredentials},
+	platform::{self, VolumePlatform},
+	types::{
+		DegradedMode, DeviceCapabilities, FileSystem, MountOptions, OperationPlan, ScanProgress,
+		UnmountedDevice, Volume, VolumeHealth, VolumeOperation,
+	},
+	watcher::WatcherBackend,
+};
+
+use std::{
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+};
+
+use async_trait::async_trait;
+use tokio::{
+	sync::{mpsc, watch, Mutex},
+	task::JoinHandle,
+	time::Instant,
+};
+use tracing::{error, trace};
+
+#[derive(Debug)]
+struct Scan {
+	volumes: Vec<Volume>,
+	scanned_at: Instant,
+	/// Value of the generation when the scan started
+	generation: u64,
+}
+
+#[derive(Debug)]
+pub struct CachedPlatform {
+	inner: Arc<dyn VolumePlatform>,
+	config: watch::Receiver<CoreConfig>,
+	/// Held across the scan, so concurrent callers wait for it instead of starting their own
+	last_scan: Mutex<Option<Scan>>,
+	/// Bumped on every invalidation, a scan running while it changes is already stale
+	generation: Arc<AtomicU64>,
+}
+
+impl CachedPlatform {
+	pub fn new(inner: Arc<dyn VolumePlatform>, config: watch::Receiver<CoreConfig>) -> Self {
+		Self {
+			inner,
+			config,
+			last_scan: Mutex::new(None),
+			generation: Arc::new(AtomicU64::new(0)),
+		}
+	}
+
+	/// Forces the next `get_volumes` call to scan again
+	pub fn invalidate(&self) {
+		self.generation.fetch_add(1, Ordering::AcqRel);
+	}
+
+	/// A cached scan is replayed on `progress_tx` all at once
+	async fn scan(
+		&self,
+		progress_tx: Option<mpsc::UnboundedSender<ScanProgress>>,
+	) -> Result<Vec<Volume>, VolumeError> {
+		let mut last_scan = self.last_scan.lock().await;
+
+		let ttl = self.config.borrow().volumes.scan_cache_ttl();
+		let generation = self.generation.load(Ordering::Acquire);
+
+		if let Some(scan) = last_scan
+			.as_ref()
+			.filter(|scan| scan.generation == generation && scan.scanned_at.elapsed() < ttl)
+		{
+			trace!("Reusing cached volume scan;");
+			if let Some(progress_tx) = &progress_tx {
+				platform::replay(&scan.volumes, progress_tx);
+			}
+			return Ok(scan.volumes.clone());
+		}
+
+		// Failed scans aren't cached, the next caller tries again
+		let volumes = match progress_tx {
+			Some(progress_tx) => self.inner.scan_with_progress(progress_tx).await?,
+			None => self.inner.get_volumes().await?,
+		};
+
+		*last_scan = Some(Scan {
+			volumes: volumes.clone(),
+			scanned_at: Instant::now(),
+			generation,
+		});
+
+		Ok(volumes)
+	}
+}
+
+#[async_trait]
+impl VolumePlatform for CachedPlatform {
+	async fn get_volumes(&self) -> Result<Vec<Volume>, VolumeError> {
+		self.scan(None).await
+	}
+
+	async fn scan_with_progress(
+		&self,
+		progress_tx: mpsc::UnboundedSender<ScanProgress>,
+	) -> Result<Vec<Volume>, VolumeError> {
+		self.scan(Some(progress_tx)).await
+	}
+
+	async fn unmount_volume(&self, path: &Path) -> Result<(), VolumeError> {
+		let res = self.inner.unmount_volume(path).await;
+		// Even a failed unmount may have changed something
+		self.invalidate();
+		res
+	}
+
+	async fn mount_volume(
+		&self,
+		device: &Path,
+		options: &MountOptions,
+	) -> Result<PathBuf, VolumeError> {
+		let res = self.inner.mount_volume(device, options).await;
+		self.invalidate();
+		res
+	}
+
+	async fn unmounted_devices(&self) -> Result<Vec<UnmountedDevice>, VolumeError> {
+		self.inner.unmounted_devices().await
+	}
+
+	async fn mount_network_share(
+		&self,
+		share: &NetworkShare,
+		mount_point: &Path,
+		credentials: Option<&ShareCredentials>,
+	) -> Result<(), VolumeError> {
+		let res = self
+			.inner
+			.mount_network_share(share, mount_point, credentials)
+			.await;
+		self.invalidate();
+		res
+	}
+
+	async fn attach_disk_image(
+		&self,
+		image: &Path,
+		read_only: bool,
+	) -> Result<AttachedImage, VolumeError> {
+		let res = self.inner.attach_disk_image(image, read_only).await;
+		self.invalidate();
+		res
+	}
+
+	async fn detach_disk_image(&self, image: &AttachedImage) -> Result<(), VolumeError> {
+		let res = self.inner.detach_disk_image(image).await;
+		self.invalidate();
+		res
+	}
+
+	async fn list_snapshots(
+		&self,
+		mount_point: &Path,
+	) -> Result<Vec<FileSystemSnapshot>, VolumeError> {
+		self.inner.list_snapshots(mount_point).await
+	}
+
+	async fn mount_snapshot(
+		&self,
+		mount_point: &Path,
+		snapshot: &FileSystemSnapshot,
+		target: &Path,
+	) -> Result<(), VolumeError> {
+		let res = self
+			.inner
+			.mount_snapshot(mount_point, snapshot, target)
+			.await;
+		self.invalidate();
+		res
+	}
+
+	async fn unmount_snapshot(&self, target: &Path) -> Result<(), VolumeError> {
+		let res = self.inner.unmount_snapshot(target).await;
+		self.invalidate();
+		res
+	}
+
+	async fn eject_volume(&self, path: &Path) -> Result<(), VolumeError> {
+		let res = self.inner.eject_volume(path).await;
+		self.invalidate();
+		res
+	}
+
+	async fn format_volume(
+		&self,
+		path: &Path,
+		file_system: &FileSystem,
+	) -> Result<(), VolumeError> {
+		let res = self.inner.format_volume(path, file_system).await;
+		self.invalidate();
+		res
+	}
+
+	async fn check_operation(
+		&self,
+		operation: &VolumeOperation,
+		path: &Path,
+		plan: &mut OperationPlan,
+	) -> Result<(), VolumeError> {
+		self.inner.check_operation(operation, path, plan).await
+	}
+
+	async fn health(&self, mount_point: &Path, elevate: bool) -> Result<VolumeHealth, VolumeError> {
+		self.inner.health(mount_point, elevate).await
+	}
+
+	async fn watch(
+		&self,
+		backend: WatcherBackend,
+		check_tx: mpsc::Sender<()>,
+	) -> Result<Option<JoinHandle<()>>, VolumeError> {
+		let (inner_tx, mut inner_rx) = mpsc::channel(1);
+		let inner = self.inner.watch(backend, inner_tx).await?;
+
+		let generation = Arc::clone(&self.generation);
+		Ok(Some(tokio::spawn(async move {
+			loop {
+				let requested = tokio::select! {
+					requested = inner_rx.recv() => requested.is_some(),
+					// The watcher is gone, or switched to another backend
+					() = check_tx.closed() => false,
+				};
+				if !requested {
+					break;
+				}
+
+				generation.fetch_add(1, Ordering::AcqRel);
+
+				// A check already queued will see the invalidation too
+				if let Err(mpsc::error::TrySendError::Closed(())) = check_tx.try_send(()) {
+					break;
+				}
+			}
+
+			// Stops the inner backend, which is over once this is
+			drop(inner_rx);
+			if let Some(inner) = inner {
+				if let Err(e) = inner.await {
+					error!(?e, ?backend, "The volume watcher backend panicked;");
+				}
+			}
+		})))
+	}
+
+	fn degraded_mode(&self) -> DegradedMode {
+		self.inner.degraded_mode()
+	}
+
+	fn capabilities(&self) -> DeviceCapabilities {
+		self.inner.capabilities()
+	}
+
+	fn watcher_backends(&self) -> Vec<WatcherBackend> {
+		self.inner.watcher_backends()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::volume::{MockStep, MockVolumeProvider, MountType};
+
+	use std::time::Duration;
+
+	use tokio::time::timeout;
+
+	fn cached(provider: &Arc<MockVolumeProvider>, ttl_ms: u64) -> CachedPlatform {
+		let mut config = CoreConfig::default();
+		config.volumes.scan_cache_ttl_ms = ttl_ms;
+		let (_, config) = watch::channel(config);
+
+		CachedPlatform::new(Arc::clone(provider) as Arc<dyn VolumePlatform>, config)
+	}
+
+	fn usb(name: &str) -> Volume {
+		MockVolumeProvider::volume(name, format!("/media/{name}"), MountType::External)
+	}
+
+	#[tokio::test]
+	async fn reuses_scans_until_invalidated() {
+		let provider = Arc::new(MockVolumeProvider::new([usb("a")]));
+		let platform = cached(&provider, 60_000);
+
+		assert_eq!(platform.get_volumes().await.unwrap().len(), 1);
+
+		provider.apply(MockStep::Plug(usb("b")));
+		assert_eq!(platform.get_volumes().await.unwrap().len(), 1);
+
+		platform.invalidate();
+		assert_eq!(platform.get_volumes().await.unwrap().len(), 2);
+	}
+
+	#[tokio::test]
+	async fn zero_ttl_disables_the_cache() {
+		let provider = Arc::new(MockVolumeProvider::new([usb("a")]));
+		let platform = cached(&provider, 0);
+
+		assert_eq!(platform.get_volumes().await.unwrap().len(), 1);
+
+		provider.apply(MockStep::Plug(usb("b")));
+		assert_eq!(platform.get_volumes().await.unwrap().len(), 2);
+	}
+
+	#[tokio::test]
+	async fn platform_events_invalidate_the_cache() {
+		let provider = Arc::new(MockVolumeProvider::new([usb("a")]));
+		let platform = cached(&provider, 60_000);
+
+		let (check_tx, mut check_rx) = mpsc::channel(1);
+		platform
+			.watch(WatcherBackend::Native, check_tx)
+			.await
+			.unwrap();
+
+		assert_eq!(platform.get_volumes().await.unwrap().len(), 1);
+
+		provider.apply(MockStep::Plug(usb("b")));
+		timeout(Duration::from_secs(5), check_rx.recv())
+			.await
+			.expect("no check triggered")
+			.unwrap();
+
+		assert_eq!(platform.get_volumes().await.unwrap().len(), 2);
+	}
+
+	#[tokio::test]
+	async fn disk_images_go_through_the_cache() {
+		let provider = Arc::new(MockVolumeProvider::new([usb("a")]));
+		let platform = cached(&provider, 60_000);
+
+		assert_eq!(platform.get_volumes().await.unwrap().len(), 1);
+
+		let image = platform
+			.attach_disk_image(Path::new("/images/backup.img"), true)
+			.await
+			.unwrap();
+		assert_eq!(
+			provider.attached_images(),
+			[(PathBuf::from("/images/backup.img"), true)]
+		);
+		assert_eq!(platform.get_volumes().await.unwrap().len(), 2);
+
+		platform.detach_disk_image(&image).await.unwrap();
+		assert_eq!(platform.get_volumes().await.unwrap().len(), 1);
+	}
+
+	#[tokio::test]
+	async fn cached_scans_still_report_progress() {
+		let provider = Arc::new(MockVolumeProvider::new([usb("a"), usb("b")]));
+		let platform = cached(&provider, 60_000);
+
+		for _ in 0..2 {
+			let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+			let volumes = platform.scan_with_progress(progress_tx).await.unwrap();
+			assert_eq!(volumes.len(), 2);
+
+			let mut processed = vec![];
+			while let Some(progress) = progress_rx.recv().await {
+				let ScanProgress::Probed {
+					processed: n,
+					total,
+					volume,
+				} = progress
+				else {
+					panic!("platforms only report probed mounts");
+				};
+				assert_eq!(total, 2);
+				assert!(volume.is_some());
+				processed.push(n);
+			}
+			assert_eq!(processed, [1, 2]);
+		}
+	}
+}
@@ -0,0 +1,514 @@
+//! Runs the system tools the platform backends rely on (udisksctl, lsblk, smartctl...).
+//!
+//! Every invocation goes through a shared executor, so a burst of hotplug events can't fork dozens
+//! of processes at once. Each command has its own limits on top of the global one: how many may run
+//! at the same time, how long to wait between two starts, how long one may run before it is killed
+//! and how much output is kept.
+//!
+//! Arguments are passed as they are, never through a shell, and checked first: see [`path_arg`] for
+//! the paths and [`check_args`] for what is refused in any argument.
+
+use super::error::VolumeError;
+
+use std::{
+	collections::HashMap,
+	ffi::{OsStr, OsString},
+	path::Path,
+	process::{ExitStatus, Stdio},
+	sync::{Arc, LazyLock, Mutex},
+	time::Duration,
+};
+
+use tokio::{
+	io::{self, AsyncRead, AsyncReadExt, AsyncWriteExt},
+	process::{ChildStdin, Command},
+	sync::{Mutex as AsyncMutex, Semaphore},
+	time::{sleep_until, timeout, Instant},
+};
+use tracing::{trace, warn};
+
+/// Processes running at the same time, all commands combined
+const MAX_CONCURRENT_COMMANDS: usize = 4;
+
+/// Longest path accepted as an argument, PATH_MAX on Linux
+const MAX_PATH_ARG: usize = 4096;
+
+static EXECUTOR: LazyLock<CommandExecutor> =
+	LazyLock::new(|| CommandExecutor::new(MAX_CONCURRENT_COMMANDS));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandLimits {
+	/// Invocations of this command running at the same time
+	pub max_concurrent: usize,
+	/// Minimum time between two starts of this command
+	pub min_interval: Duration,
+	/// The process is killed once it runs for longer than this
+	pub timeout: Duration,
+	/// Bytes kept from stdout and from stderr, the rest is read and discarded
+	pub max_output: usize,
+}
+
+impl CommandLimits {
+	pub const DEFAULT: Self = Self {
+		max_concurrent: 2,
+		min_interval: Duration::ZERO,
+		timeout: Duration::from_secs(10),
+		max_output: 64 * 1024,
+	};
+
+	/// Limits for the commands we know about
+	pub fn for_command(command: &str) -> Self {
+		match command {
+			// Called once per volume on every scan
+			"findmnt" => Self {
+				max_concurrent: 4,
+				timeout: Duration::from_secs(5),
+				..Self::DEFAULT
+			},
+			// Unmounting the same disks in parallel only makes the kernel busier, network shares take
+			// a while to answer
+			"mount" | "umount" | "eject" | "udisksctl" | "diskutil" | "hdiutil" => Self {
+				max_concurrent: 1,
+				timeout: Duration::from_secs(30),
+				..Self::DEFAULT
+			},
+			// Writing a file system on a large drive
+			"gdbus" => Self {
+				max_concurrent: 1,
+				timeout: Duration::from_secs(310),
+				..Self::DEFAULT
+			},
+			// Wait for the user to answer the authentication dialog
+			"pkcheck" | "pkexec" | "security" => Self {
+				max_concurrent: 1,
+				timeout: Duration::from_secs(120),
+				..Self::DEFAULT
+			},
+			// Servers that went away are only given up on after a while
+			"smbtree" | "smbclient" | "showmount" => Self {
+				max_concurrent: 2,
+				timeout: Duration::from_secs(20),
+				..Self::DEFAULT
+			},
+			// Called for the pooled volumes on every scan, which are given up on past the probe timeout
+			"mdadm" | "zpool" | "lvs" => Self {
+				max_concurrent: 2,
+				timeout: Duration::from_secs(2),
+				..Self::DEFAULT
+			},
+			// Spins disks up, so don't hammer them
+			"smartctl" => Self {
+				max_concurrent: 1,
+				min_interval: Duration::from_secs(1),
+				timeout: Duration::from_secs(30),
+				max_output: 256 * 1024,
+			},
+			_ => Self::DEFAULT,
+		}
+	}
+}
+
+/// What a command printed, capped to [`CommandLimits::max_output`]
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+	pub status: ExitStatus,
+	pub stdout: Vec<u8>,
+	pub stderr: Vec<u8>,
+	/// Some of the output went over the cap and was dropped
+	pub truncated: bool,
+}
+
+impl CommandOutput {
+	pub fn stdout_lossy(&self) -> String {
+		String::from_utf8_lossy(&self.stdout).into_owned()
+	}
+
+	pub fn stderr_lossy(&self) -> String {
+		String::from_utf8_lossy(&self.stderr).into_owned()
+	}
+}
+
+#[derive(Debug)]
+struct CommandSlot {
+	limits: CommandLimits,
+	permits: Semaphore,
+	/// When the next invocation may start, locked across the wait so starts are spaced out in order
+	next_start: AsyncMutex<Instant>,
+}
+
+#[derive(Debug)]
+pub struct CommandExecutor {
+	permits: Semaphore,
+	slots: Mutex<HashMap<&'static str, Arc<CommandSlot>>>,
+}
+
+impl CommandExecutor {
+	pub fn new(max_concurrent: usize) -> Self {
+		Self {
+			permits: Semaphore::new(max_concurrent),
+			slots: Mutex::new(HashMap::new()),
+		}
+	}
+
+	/// Runs `command` with the limits set for it, waiting for a free slot first
+	pub async fn run<I, S>(
+		&self,
+		command: &'static str,
+		args: I,
+	) -> Result<CommandOutput, VolumeError>
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<OsStr>,
+	{
+		self.run_with_limits(command, args, CommandLimits::for_command(command))
+			.await
+	}
+
+	/// Runs `command`, the limits only apply the first time the command goes through the executor
+	pub async fn run_with_limits<I, S>(
+		&self,
+		command: &'static str,
+		args: I,
+		limits: CommandLimits,
+	) -> Result<CommandOutput, VolumeError>
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<OsStr>,
+	{
+		self.spawn(command, args, limits, None).await
+	}
+
+	/// Runs `command` with `input` written to its stdin, for what it shouldn't get through its
+	/// arguments, which any user can read in `/proc`
+	pub async fn run_with_input<I, S>(
+		&self,
+		command: &'static str,
+		args: I,
+		input: &[u8],
+	) -> Result<CommandOutput, VolumeError>
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<OsStr>,
+	{
+		let limits = CommandLimits::for_command(command);
+		self.spawn(command, args, limits, Some(input)).await
+	}
+
+	async fn spawn<I, S>(
+		&self,
+		command: &'static str,
+		args: I,
+		limits: CommandLimits,
+		input: Option<&[u8]>,
+	) -> Result<CommandOutput, VolumeError>
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<OsStr>,
+	{
+		let args = args
+			.into_iter()
+			.map(|arg| arg.as_ref().to_os_string())
+			.collect::<Vec<_>>();
+		check_args(command, &args)?;
+
+		let slot = self.slot(command, limits);
+
+		let _command_permit = slot
+			.permits
+			.acquire()
+			.await
+			.map_err(|_| VolumeError::Cancelled)?;
+
+		{
+			let mut next_start = slot.next_start.lock().await;
+			sleep_until(*next_start).await;
+			*next_start = Instant::now() + slot.limits.min_interval;
+		}
+
+		let _permit = self
+			.permits
+			.acquire()
+			.await
+			.map_err(|_| VolumeError::Cancelled)?;
+
+		trace!(%command, "Running system command;");
+
+		let mut child = Command::new(command)
+			.args(args)
+			.stdin(if input.is_some() {
+				Stdio::piped()
+			} else {
+				Stdio::null()
+			})
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			// A timed out command must not outlive us
+			.kill_on_drop(true)
+			.spawn()
+			.map_err(|source| VolumeError::CommandFailed { command, source })?;
+
+		let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+			return Err(VolumeError::CommandFailed {
+				command,
+				source: io::Error::other("the output of the process isn't piped"),
+			});
+		};
+		let stdin = child.stdin.take();
+		let max_output = slot.limits.max_output;
+
+		let run = async {
+			let (_, (stdout, stdout_truncated), (stderr, stderr_truncated)) = tokio::try_join!(
+				write_input(stdin, input.unwrap_or_default()),
+				read_capped(stdout, max_output),
+				read_capped(stderr, max_output)
+			)?;
+			let status = child.wait().await?;
+
+			Ok::<_, io::Error>(CommandOutput {
+				status,
+				stdout,
+				stderr,
+				truncated: stdout_truncated || stderr_truncated,
+			})
+		};
+
+		match timeout(slot.limits.timeout, run).await {
+			Ok(Ok(output)) => {
+				if output.truncated {
+					warn!(%command, %max_output, "System command output was truncated;");
+				}
+				Ok(output)
+			}
+			Ok(Err(source)) => Err(VolumeError::CommandFailed { command, source }),
+			Err(_) => {
+				if let Err(e) = child.start_kill() {
+					warn!(?e, %command, "Failed to kill timed out system command;");
+				}
+				Err(VolumeError::CommandTimedOut {
+					command,
+					after: slot.limits.timeout,
+				})
+			}
+		}
+	}
+
+	fn slot(&self, command: &'static str, limits: CommandLimits) -> Arc<CommandSlot> {
+		let mut slots = self.slots.lock().expect("command executor lock poisoned");
+		Arc::clone(slots.entry(command).or_insert_with(|| {
+			Arc::new(CommandSlot {
+				limits,
+				permits: Semaphore::new(limits.max_concurrent.max(1)),
+				next_start: AsyncMutex::new(Instant::now()),
+			})
+		}))
+	}
+}
+
+/// Runs a system command through the executor shared by all platform backends
+pub async fn run<I, S>(command: &'static str, args: I) -> Result<CommandOutput, VolumeError>
+where
+	I: IntoIterator<Item = S>,
+	S: AsRef<OsStr>,
+{
+	EXECUTOR.run(command, args).await
+}
+
+/// Like [`run`], writing `input` to the stdin of the command
+pub async fn run_with_input<I, S>(
+	command: &'static str,
+	args: I,
+	input: &[u8],
+) -> Result<CommandOutput, VolumeError>
+where
+	I: IntoIterator<Item = S>,
+	S: AsRef<OsStr>,
+{
+	EXECUTOR.run_with_input(command, args, input).await
+}
+
+/// Checks a path before it is passed to `command`
+///
+/// Only absolute paths are accepted, so a mount point named like an option (`-f`) can't be taken
+/// for one. The path isn't converted to UTF-8, mount points that aren't valid UTF-8 are passed as
+/// they are.
+pub fn path_arg<'a>(command: &'static str, path: &'a Path) -> Result<&'a OsStr, VolumeError> {
+	let unsafe_argument = |reason| VolumeError::UnsafeArgument {
+		command,
+		argument: path.as_os_str().to_os_string(),
+		reason,
+	};
+
+	if !path.is_absolute() {
+		return Err(unsafe_argument("not an absolute path"));
+	}
+	if path.as_os_str().len() > MAX_PATH_ARG {
+		return Err(unsafe_argument("path is too long"));
+	}
+	check_arg(command, path.as_os_str())?;
+
+	Ok(path.as_os_str())
+}
+
+/// Refuses the arguments no system tool can take as they are
+pub fn check_args(command: &'static str, args: &[OsString]) -> Result<(), VolumeError> {
+	args.iter().try_for_each(|arg| check_arg(command, arg))
+}
+
+fn check_arg(command: &'static str, arg: &OsStr) -> Result<(), VolumeError> {
+	let bytes = arg.as_encoded_bytes();
+
+	let reason = if bytes.contains(&0) {
+		// Would end the argument early, or fail the spawn
+		Some("contains a NUL byte")
+	} else if bytes.iter().any(|byte| matches!(byte, b'\n' | b'\r')) {
+		// The tools we read paths back from print one per line
+		Some("contains a line break")
+	} else if cfg!(windows) && arg.to_str().is_none() {
+		// Elevated commands go through PowerShell, which only takes strings
+		Some("isn't valid Unicode")
+	} else {
+		None
+	};
+
+	match reason {
+		Some(reason) => Err(VolumeError::UnsafeArgument {
+			command,
+			argument: arg.to_os_string(),
+			reason,
+		}),
+		None => Ok(()),
+	}
+}
+
+/// Writes `input` then closes stdin, a command that exits without reading it all isn't an error
+async fn write_input(stdin: Option<ChildStdin>, input: &[u8]) -> io::Result<()> {
+	let Some(mut stdin) = stdin else {
+		return Ok(());
+	};
+
+	match stdin.write_all(input).await {
+		Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+		res => res,
+	}
+}
+
+/// Reads up to `max` bytes, then drains the rest so the process doesn't block on a full pipe
+async fn read_capped(reader: impl AsyncRead + Unpin, max: usize) -> io::Result<(Vec<u8>, bool)> {
+	let mut buf = Vec::new();
+	let mut limited = reader.take(max as u64);
+	limited.read_to_end(&mut buf).await?;
+
+	let dropped = io::copy(&mut limited.into_inner(), &mut io::sink()).await?;
+
+	Ok((buf, dropped > 0))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn caps_the_output() {
+		let executor = CommandExecutor::new(1);
+		let output = executor
+			.run_with_limits(
+				"sh",
+				["-c", "printf 'abcdefgh'"],
+				CommandLimits {
+					max_output: 4,
+					..CommandLimits::DEFAULT
+				},
+			)
+			.await
+			.unwrap();
+
+		assert!(output.status.success());
+		assert_eq!(output.stdout, b"abcd");
+		assert!(output.truncated);
+	}
+
+	#[tokio::test]
+	async fn writes_the_input_to_stdin() {
+		let executor = CommandExecutor::new(1);
+		let output = executor
+			.run_with_input("cat", std::iter::empty::<&str>(), b"password\n")
+			.await
+			.unwrap();
+
+		assert!(output.status.success());
+		assert_eq!(output.stdout, b"password\n");
+	}
+
+	#[tokio::test]
+	async fn kills_commands_that_run_too_long() {
+		let executor = CommandExecutor::new(1);
+		let res = executor
+			.run_with_limits(
+				"sleep",
+				["5"],
+				CommandLimits {
+					timeout: Duration::from_millis(50),
+					..CommandLimits::DEFAULT
+				},
+			)
+			.await;
+
+		assert!(matches!(
+			res,
+			Err(VolumeError::CommandTimedOut {
+				command: "sleep",
+				..
+			})
+		));
+	}
+
+	#[tokio::test]
+	async fn spaces_out_starts() {
+		let executor = CommandExecutor::new(4);
+		let limits = CommandLimits {
+			max_concurrent: 4,
+			min_interval: Duration::from_millis(100),
+			..CommandLimits::DEFAULT
+		};
+
+		let started = Instant::now();
+		let (first, second) = tokio::join!(
+			executor.run_with_limits("true", std::iter::empty::<&str>(), limits),
+			executor.run_with_limits("true", std::iter::empty::<&str>(), limits),
+		);
+		first.unwrap();
+		second.unwrap();
+
+		assert!(started.elapsed() >= Duration::from_millis(100));
+	}
+
+	#[test]
+	fn refuses_unsafe_paths() {
+		use std::os::unix::ffi::OsStrExt;
+
+		for path in ["-f", "mnt/usb", "/mnt/usb\n/", "/mnt/usb\0"] {
+			assert!(
+				matches!(
+					path_arg("umount", Path::new(path)),
+					Err(VolumeError::UnsafeArgument {
+						command: "umount",
+						..
+					})
+				),
+				"{path:?} was accepted"
+			);
+		}
+
+		// Latin-1 names from old USB sticks
+		let latin1 = Path::new(OsStr::from_bytes(b"/media/cl\xe9"));
+		assert_eq!(path_arg("umount", latin1).unwrap(), latin1.as_os_str());
+	}
+
+	#[tokio::test]
+	async fn checks_arguments_before_running() {
+		let executor = CommandExecutor::new(1);
+		let res = executor.run("echo", ["ok", "not\0ok"]).await;
+
+		assert!(matches!(res, Err(VolumeError::UnsafeArgument { .. })));
+	}
+}
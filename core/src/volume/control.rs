@@ -0,0 +1,293 @@
+//! Local control socket for the volume subsystem, so `sd-cli volumes` can script and debug a
+//! running node without the desktop app.
+//!
+//! The node listens on [`CONTROL_SOCKET`] in a directory of its data directory only its own user
+//! can open, and hangs up on the connections of other users.
+//! A connection sends one [`ControlRequest`] as a line of JSON and reads [`ControlResponse`]s, one
+//! per line: a single one for most requests, every volume event until it disconnects for `watch`.
+
+use crate::{node::CoreHealth, Node};
+
+use super::{
+	error::VolumeError,
+	release,
+	snapshot::VolumeSnapshot,
+	types::{OperationPlan, Volume, VolumeErrorInfo, VolumeEvent, VolumeOperation},
+};
+
+use std::{
+	io,
+	os::unix::fs::PermissionsExt,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{
+	io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+	net::{unix::OwnedWriteHalf, UnixListener, UnixStream},
+};
+use tracing::{debug, info, warn};
+
+/// File name of the socket, in [`CONTROL_DIR`]
+pub const CONTROL_SOCKET: &str = "volumes.sock";
+
+/// Directory of the socket, in the node's data directory
+const CONTROL_DIR: &str = "control";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlRequest {
+	/// The volumes currently known to the node
+	List,
+	/// Streams volume events, starting with the volumes currently present
+	Watch,
+	/// Unmounts the volume mounted at this path, or only plans it for a dry run
+	Unmount {
+		path: PathBuf,
+		#[serde(default)]
+		dry_run: bool,
+	},
+	/// Ejects the volume mounted at this path, or only plans it for a dry run
+	Eject {
+		path: PathBuf,
+		#[serde(default)]
+		dry_run: bool,
+	},
+	/// A snapshot of the volume state, with the degraded mode and watcher status
+	Diagnose,
+	/// The health of the core as a whole, not only of the volumes
+	Health,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum ControlResponse {
+	Volumes(Vec<Volume>),
+	Event(VolumeEvent),
+	Unmounted(PathBuf),
+	Ejected(PathBuf),
+	/// What an unmount or eject would do, for dry runs
+	Plan(Box<OperationPlan>),
+	Diagnosis(Box<VolumeSnapshot>),
+	Health(Box<CoreHealth>),
+	Error(VolumeErrorInfo),
+}
+
+impl From<Vec<Volume>> for ControlResponse {
+	fn from(volumes: Vec<Volume>) -> Self {
+		Self::Volumes(volumes)
+	}
+}
+
+impl From<PathBuf> for ControlResponse {
+	fn from(path: PathBuf) -> Self {
+		Self::Unmounted(path)
+	}
+}
+
+impl From<VolumeSnapshot> for ControlResponse {
+	fn from(snapshot: VolumeSnapshot) -> Self {
+		Self::Diagnosis(Box::new(snapshot))
+	}
+}
+
+pub fn socket_path(data_dir: impl AsRef<Path>) -> PathBuf {
+	data_dir.as_ref().join(CONTROL_DIR).join(CONTROL_SOCKET)
+}
+
+/// Serves the control socket for as long as the node runs
+pub(crate) fn start(node: &Arc<Node>) {
+	let node = Arc::clone(node);
+	tokio::spawn(async move {
+		let path = socket_path(&node.data_dir);
+		let listener = match bind(&path).await {
+			Ok(listener) => listener,
+			Err(e) => {
+				warn!(?e, path = %path.display(), "Failed to open the volume control socket;");
+				return;
+			}
+		};
+
+		info!(path = %path.display(), "Volume control socket listening;");
+
+		loop {
+			match listener.accept().await {
+				Ok((stream, _)) if is_same_user(&stream) => {
+					tokio::spawn(serve(Arc::clone(&node), stream));
+				}
+				Ok(_) => warn!("Refused a volume control connection from another user;"),
+				Err(e) => warn!(?e, "Failed to accept a volume control connection;"),
+			}
+		}
+	});
+}
+
+async fn bind(path: &Path) -> io::Result<UnixListener> {
+	// Unmounting doesn't ask for a password, so the socket can't be open to other users. Its
+	// directory keeps them out from the moment it's bound, before its own permissions are set.
+	if let Some(dir) = path.parent() {
+		tokio::fs::DirBuilder::new()
+			.recursive(true)
+			.mode(0o700)
+			.create(dir)
+			.await?;
+		tokio::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700)).await?;
+	}
+
+	// Left behind by a node that didn't shut down cleanly
+	match tokio::fs::remove_file(path).await {
+		Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+		_ => {}
+	}
+
+	let listener = UnixListener::bind(path)?;
+	tokio::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).await?;
+
+	Ok(listener)
+}
+
+/// Whether the other end of the connection runs as the same user as the node
+fn is_same_user(stream: &UnixStream) -> bool {
+	// SAFETY: geteuid can't fail
+	let uid = unsafe { libc::geteuid() };
+
+	stream.peer_cred().is_ok_and(|cred| cred.uid() == uid)
+}
+
+async fn serve(node: Arc<Node>, stream: UnixStream) {
+	let (reader, mut writer) = stream.into_split();
+
+	let request = match BufReader::new(reader).lines().next_line().await {
+		Ok(Some(line)) => match serde_json::from_str::<ControlRequest>(&line) {
+			Ok(request) => request,
+			Err(e) => {
+				debug!(?e, "Invalid volume control request;");
+				return;
+			}
+		},
+		Ok(None) => return,
+		Err(e) => {
+			debug!(?e, "Failed to read a volume control request;");
+			return;
+		}
+	};
+
+	debug!(?request, "Volume control request;");
+
+	let res = match request {
+		ControlRequest::Watch => watch(&node, &mut writer).await,
+		ControlRequest::List => respond(&mut writer, node.volumes.list_volumes().await).await,
+		ControlRequest::Unmount { path, dry_run } => {
+			let res = operate(&node, path, VolumeOperation::Unmount, dry_run).await;
+			respond(&mut writer, res).await
+		}
+		ControlRequest::Eject { path, dry_run } => {
+			let res = operate(&node, path, VolumeOperation::Eject, dry_run).await;
+			respond(&mut writer, res).await
+		}
+		ControlRequest::Diagnose => respond(&mut writer, node.volumes.snapshot().await).await,
+		ControlRequest::Health => {
+			let health = crate::node::core_health(&node).await;
+			send(&mut writer, &ControlResponse::Health(Box::new(health))).await
+		}
+	};
+
+	// The other end going away is how every watch ends
+	if let Err(e) = res {
+		debug!(?e, "Volume control connection closed;");
+	}
+}
+
+async fn operate(
+	node: &Node,
+	path: PathBuf,
+	operation: VolumeOperation,
+	dry_run: bool,
+) -> Result<ControlResponse, VolumeError> {
+	let fingerprint = node
+		.volumes
+		.list_volumes()
+		.await?
+		.into_iter()
+		.find(|volume| volume.mount_point == path)
+		.and_then(|volume| volume.fingerprint)
+		.ok_or_else(|| VolumeError::NotMounted(path.clone()))?;
+
+	let plan = release::run_operation(node, fingerprint, operation, dry_run).await?;
+
+	Ok(match plan.operation {
+		_ if dry_run => ControlResponse::Plan(Box::new(plan)),
+		VolumeOperation::Eject => ControlResponse::Ejected(path),
+		_ => ControlResponse::Unmounted(path),
+	})
+}
+
+async fn watch(node: &Node, writer: &mut OwnedWriteHalf) -> io::Result<()> {
+	let mut events = node.volumes.subscribe();
+	while let Some(event) = events.recv().await {
+		send(writer, &ControlResponse::Event(event)).await?;
+	}
+
+	Ok(())
+}
+
+async fn respond<T: Into<ControlResponse>>(
+	writer: &mut OwnedWriteHalf,
+	res: Result<T, VolumeError>,
+) -> io::Result<()> {
+	let response = res.map_or_else(
+		|e| ControlResponse::Error(VolumeErrorInfo::from(&e)),
+		Into::into,
+	);
+	send(writer, &response).await
+}
+
+async fn send(writer: &mut OwnedWriteHalf, response: &ControlResponse) -> io::Result<()> {
+	let mut line = serde_json::to_vec(response)?;
+	line.push(b'\n');
+	writer.write_all(&line).await
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use serde_json::json;
+
+	#[test]
+	fn requests_are_tagged_by_command() {
+		assert_eq!(
+			serde_json::to_value(ControlRequest::Unmount {
+				path: PathBuf::from("/media/usb"),
+				dry_run: false,
+			})
+			.unwrap(),
+			json!({ "command": "unmount", "path": "/media/usb", "dry_run": false })
+		);
+		// Sent by the clients from before dry runs
+		assert!(matches!(
+			serde_json::from_str(r#"{"command":"unmount","path":"/media/usb"}"#),
+			Ok(ControlRequest::Unmount { dry_run: false, .. })
+		));
+		assert!(matches!(
+			serde_json::from_str(r#"{"command":"list"}"#),
+			Ok(ControlRequest::List)
+		));
+	}
+
+	#[tokio::test]
+	async fn socket_is_only_open_to_its_user() {
+		let data_dir = tempfile::tempdir().unwrap();
+		let path = socket_path(data_dir.path());
+
+		let listener = bind(&path).await.unwrap();
+		let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+		assert_eq!(mode(path.parent().unwrap()), 0o700);
+		assert_eq!(mode(&path), 0o600);
+
+		let _client = UnixStream::connect(&path).await.unwrap();
+		let (stream, _) = listener.accept().await.unwrap();
+		assert!(is_same_user(&stream));
+	}
+}
@@ -0,0 +1,544 @@
+//! Elevated privileges for the volume operations that need them, like mounting, ejecting,
+//! formatting, reading SMART data or browsing snapshots.
+//!
+//! Each OS has its own authority: polkit, through the udisks2 actions, on Linux, Authorization
+//! Services on macOS and UAC on Windows. [`Elevation`] asks the one of the platform for the right to
+//! run an operation on a target, and remembers what was granted for a while so the user isn't asked
+//! again for every call. A grant only covers the operation and target it was asked for.
+
+use super::{
+	command::{self, CommandOutput},
+	error::VolumeError,
+};
+
+use std::{
+	collections::HashMap,
+	ffi::{OsStr, OsString},
+	fmt,
+	path::PathBuf,
+	sync::Mutex,
+	time::Duration,
+};
+
+use async_trait::async_trait;
+use tokio::{sync::Mutex as AsyncMutex, time::Instant};
+use tracing::debug;
+
+/// How long a grant is reused, polkit keeps `auth_admin_keep` authorizations for as long
+const GRANT_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// The volume operations that may need more privileges than the ones the app runs with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ElevatedOperation {
+	Eject,
+	/// Erasing a volume with a new file system
+	Format,
+	Mount,
+	/// Reading the health of a drive, which takes opening its raw device
+	Smart,
+	/// Listing and mounting the snapshots of a file system
+	Snapshots,
+	Unmount,
+}
+
+impl ElevatedOperation {
+	/// The udisks2 polkit action covering the operation
+	pub const fn polkit_action(self) -> &'static str {
+		match self {
+			ElevatedOperation::Eject => "org.freedesktop.udisks2.eject-media",
+			ElevatedOperation::Format => "org.freedesktop.udisks2.modify-device",
+			ElevatedOperation::Mount => "org.freedesktop.udisks2.filesystem-mount",
+			// smartctl runs through pkexec, udisks2 can't read the SMART data of NVMe drives
+			ElevatedOperation::Smart => "org.freedesktop.policykit.exec",
+			// Listing them takes root as well, it's asked for along with mounting one
+			ElevatedOperation::Snapshots => "org.freedesktop.udisks2.filesystem-mount-system",
+			ElevatedOperation::Unmount => "org.freedesktop.udisks2.filesystem-unmount-others",
+		}
+	}
+}
+
+impl fmt::Display for ElevatedOperation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			ElevatedOperation::Eject => "eject drives",
+			ElevatedOperation::Format => "format volumes",
+			ElevatedOperation::Mount => "mount volumes",
+			ElevatedOperation::Smart => "read SMART data",
+			ElevatedOperation::Snapshots => "browse snapshots",
+			ElevatedOperation::Unmount => "unmount volumes",
+		})
+	}
+}
+
+/// What a grant covers: an operation, on a single target when there is one
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ElevationScope {
+	pub operation: ElevatedOperation,
+	/// Mount point or device the operation runs on
+	pub target: Option<PathBuf>,
+}
+
+impl ElevationScope {
+	pub fn new(operation: ElevatedOperation, target: impl Into<PathBuf>) -> Self {
+		Self {
+			operation,
+			target: Some(target.into()),
+		}
+	}
+}
+
+/// The authority of a platform
+#[async_trait]
+pub trait ElevationBackend: fmt::Debug + Send + Sync + 'static {
+	/// Asks for the right to run the operation, prompting the user when the OS wants to
+	async fn authorize(&self, scope: &ElevationScope) -> Result<(), VolumeError>;
+
+	/// Whether `authorize` can ever succeed here
+	fn is_available(&self) -> bool {
+		true
+	}
+
+	/// The command to run in place of `command` to get the privileges `authorize` granted, the
+	/// same one when the tool checks them by itself
+	fn wrap(
+		&self,
+		_scope: &ElevationScope,
+		command: &'static str,
+		args: Vec<OsString>,
+	) -> (&'static str, Vec<OsString>) {
+		(command, args)
+	}
+}
+
+#[derive(Debug)]
+pub struct Elevation {
+	backend: Box<dyn ElevationBackend>,
+	ttl: Duration,
+	grants: Mutex<HashMap<ElevationScope, Instant>>,
+	/// One prompt at a time, the ones waiting may be answered by the current one
+	prompt: AsyncMutex<()>,
+}
+
+impl Default for Elevation {
+	fn default() -> Self {
+		Self::new(platform_backend())
+	}
+}
+
+impl Elevation {
+	pub fn new(backend: impl ElevationBackend) -> Self {
+		Self::with_ttl(backend, GRANT_TTL)
+	}
+
+	pub fn with_ttl(backend: impl ElevationBackend, ttl: Duration) -> Self {
+		Self {
+			backend: Box::new(backend),
+			ttl,
+			grants: Mutex::new(HashMap::new()),
+			prompt: AsyncMutex::new(()),
+		}
+	}
+
+	/// Whether the platform can grant privileges at all
+	pub fn is_available(&self) -> bool {
+		self.backend.is_available()
+	}
+
+	/// Makes sure the operation is allowed, asking the platform unless it was granted recently
+	pub async fn authorize(&self, scope: &ElevationScope) -> Result<(), VolumeError> {
+		if self.is_granted(scope) {
+			return Ok(());
+		}
+
+		let _prompt = self.prompt.lock().await;
+		if self.is_granted(scope) {
+			return Ok(());
+		}
+
+		debug!(?scope, "Asking for elevated privileges;");
+		self.backend.authorize(scope).await?;
+
+		self.grants
+			.lock()
+			.expect("elevation grants lock poisoned")
+			.insert(scope.clone(), Instant::now() + self.ttl);
+
+		Ok(())
+	}
+
+	/// Runs a system command with the privileges of `scope`, through the shared command executor
+	pub async fn run<I, S>(
+		&self,
+		scope: &ElevationScope,
+		command: &'static str,
+		args: I,
+	) -> Result<CommandOutput, VolumeError>
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<OsStr>,
+	{
+		let args = args
+			.into_iter()
+			.map(|arg| arg.as_ref().to_os_string())
+			.collect::<Vec<_>>();
+		// Checked before the backend wraps them, it may quote them into a script
+		command::check_args(command, &args)?;
+
+		self.authorize(scope).await?;
+
+		let (command, args) = self.backend.wrap(scope, command, args);
+
+		command::run(command, args).await
+	}
+
+	/// Runs a command, again with the privileges of `scope` if the user isn't allowed to run it by
+	/// themselves, `into_error` tells from its stderr why it failed
+	pub async fn run_or_elevate(
+		&self,
+		scope: &ElevationScope,
+		program: &'static str,
+		args: &[&OsStr],
+		into_error: impl Fn(&str) -> VolumeError,
+	) -> Result<String, VolumeError> {
+		let output = command::run(program, args).await?;
+		if output.status.success() {
+			return Ok(output.stdout_lossy());
+		}
+
+		let e = into_error(&output.stderr_lossy());
+		if !e.is_permission_denied() || !self.is_available() {
+			return Err(e);
+		}
+
+		let output = self.run(scope, program, args).await?;
+		if output.status.success() {
+			return Ok(output.stdout_lossy());
+		}
+
+		let e = into_error(&output.stderr_lossy());
+		if e.is_permission_denied() {
+			self.revoke(scope);
+		}
+
+		Err(e)
+	}
+
+	/// Forgets a grant, for when the OS turned out not to honor it anymore
+	pub fn revoke(&self, scope: &ElevationScope) {
+		self.grants
+			.lock()
+			.expect("elevation grants lock poisoned")
+			.remove(scope);
+	}
+
+	fn is_granted(&self, scope: &ElevationScope) -> bool {
+		let mut grants = self.grants.lock().expect("elevation grants lock poisoned");
+		let now = Instant::now();
+		grants.retain(|_, expires_at| *expires_at > now);
+
+		grants.contains_key(scope)
+	}
+}
+
+#[cfg(target_os = "linux")]
+fn platform_backend() -> impl ElevationBackend {
+	PolkitBackend
+}
+
+#[cfg(target_os = "macos")]
+fn platform_backend() -> impl ElevationBackend {
+	AuthorizationServicesBackend
+}
+
+#[cfg(target_os = "windows")]
+fn platform_backend() -> impl ElevationBackend {
+	UacBackend
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn platform_backend() -> impl ElevationBackend {
+	UnsupportedBackend
+}
+
+/// Checks the udisks2 actions with `pkcheck`
+///
+/// udisks2 keeps the authorization for the session a few minutes, so the `udisksctl` call that
+/// follows doesn't prompt again. The tools udisks2 doesn't run, like smartctl, go through `pkexec`,
+/// which prompts by itself.
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct PolkitBackend;
+
+#[cfg(target_os = "linux")]
+#[async_trait]
+impl ElevationBackend for PolkitBackend {
+	async fn authorize(&self, scope: &ElevationScope) -> Result<(), VolumeError> {
+		if scope.operation == ElevatedOperation::Smart {
+			return Ok(());
+		}
+
+		let pid = std::process::id().to_string();
+		let output = command::run(
+			"pkcheck",
+			[
+				"--action-id",
+				scope.operation.polkit_action(),
+				"--process",
+				&pid,
+				"--allow-user-interaction",
+			],
+		)
+		.await?;
+
+		match output.status.code() {
+			Some(0) => Ok(()),
+			// The authentication dialog was dismissed
+			Some(3) => Err(VolumeError::Cancelled),
+			_ => {
+				debug!(stderr = %output.stderr_lossy(), "polkit refused the authorization;");
+				Err(VolumeError::AuthorizationDenied {
+					operation: scope.operation,
+				})
+			}
+		}
+	}
+
+	fn wrap(
+		&self,
+		scope: &ElevationScope,
+		command: &'static str,
+		args: Vec<OsString>,
+	) -> (&'static str, Vec<OsString>) {
+		if scope.operation != ElevatedOperation::Smart {
+			return (command, args);
+		}
+
+		(
+			"pkexec",
+			[OsString::from(command)].into_iter().chain(args).collect(),
+		)
+	}
+}
+
+/// Runs the commands as root through `osascript`, which asks Authorization Services for an
+/// administrator's password
+///
+/// The prompt comes up as the command starts, there is nothing to ask for beforehand. The
+/// Authorization Services call that ran a tool as root is deprecated, what replaced it is a
+/// privileged helper installed with `SMAppService`, which has to be signed along with the app.
+#[cfg(target_os = "macos")]
+#[derive(Debug)]
+pub struct AuthorizationServicesBackend;
+
+#[cfg(target_os = "macos")]
+#[async_trait]
+impl ElevationBackend for AuthorizationServicesBackend {
+	async fn authorize(&self, _scope: &ElevationScope) -> Result<(), VolumeError> {
+		Ok(())
+	}
+
+	fn wrap(
+		&self,
+		_scope: &ElevationScope,
+		command: &'static str,
+		args: Vec<OsString>,
+	) -> (&'static str, Vec<OsString>) {
+		// Quoted for the shell, then the whole command line for AppleScript
+		let quote = |arg: &OsStr| format!("'{}'", arg.to_string_lossy().replace('\'', r"'\''"));
+		let command_line = [OsStr::new(command)]
+			.into_iter()
+			.chain(args.iter().map(OsString::as_os_str))
+			.map(quote)
+			.collect::<Vec<_>>()
+			.join(" ");
+		let script = format!(
+			"do shell script \"{}\" with administrator privileges",
+			command_line.replace('\\', r"\\").replace('"', r#"\""#)
+		);
+
+		(
+			"osascript",
+			vec![OsString::from("-e"), OsString::from(script)],
+		)
+	}
+}
+
+/// Runs the commands elevated through UAC
+///
+/// UAC prompts when the elevated process starts, there is nothing to ask for beforehand. The output
+/// of that process isn't ours to read, only its exit code.
+#[cfg(target_os = "windows")]
+#[derive(Debug)]
+pub struct UacBackend;
+
+#[cfg(target_os = "windows")]
+#[async_trait]
+impl ElevationBackend for UacBackend {
+	async fn authorize(&self, _scope: &ElevationScope) -> Result<(), VolumeError> {
+		Ok(())
+	}
+
+	fn wrap(
+		&self,
+		_scope: &ElevationScope,
+		command: &'static str,
+		args: Vec<OsString>,
+	) -> (&'static str, Vec<OsString>) {
+		let quote = |arg: &OsStr| format!("'{}'", arg.to_string_lossy().replace('\'', "''"));
+
+		let mut script = format!(
+			"$p = Start-Process -FilePath {} -Verb RunAs -Wait -PassThru",
+			quote(OsStr::new(command))
+		);
+		if !args.is_empty() {
+			let args = args
+				.iter()
+				.map(|arg| quote(arg.as_os_str()))
+				.collect::<Vec<_>>();
+			script.push_str(" -ArgumentList ");
+			script.push_str(&args.join(","));
+		}
+		// Start-Process succeeds as soon as the process started, whatever it exits with
+		script.push_str("; exit $p.ExitCode");
+
+		(
+			"powershell",
+			["-NoProfile", "-NonInteractive", "-Command"]
+				.into_iter()
+				.map(OsString::from)
+				.chain([OsString::from(script)])
+				.collect(),
+		)
+	}
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+#[derive(Debug)]
+pub struct UnsupportedBackend;
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+#[async_trait]
+impl ElevationBackend for UnsupportedBackend {
+	async fn authorize(&self, _scope: &ElevationScope) -> Result<(), VolumeError> {
+		Err(VolumeError::UnsupportedPlatform {
+			operation: "privilege elevation",
+		})
+	}
+
+	fn is_available(&self) -> bool {
+		false
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	};
+
+	#[derive(Debug)]
+	struct CountingBackend {
+		prompts: Arc<AtomicUsize>,
+		deny: bool,
+	}
+
+	#[async_trait]
+	impl ElevationBackend for CountingBackend {
+		async fn authorize(&self, scope: &ElevationScope) -> Result<(), VolumeError> {
+			self.prompts.fetch_add(1, Ordering::SeqCst);
+			if self.deny {
+				return Err(VolumeError::AuthorizationDenied {
+					operation: scope.operation,
+				});
+			}
+			Ok(())
+		}
+	}
+
+	#[tokio::test]
+	async fn grants_are_reused_for_their_scope_only() {
+		let prompts = Arc::new(AtomicUsize::new(0));
+		let elevation = Elevation::new(CountingBackend {
+			prompts: Arc::clone(&prompts),
+			deny: false,
+		});
+
+		let unmount_usb = ElevationScope::new(ElevatedOperation::Unmount, "/media/usb");
+		elevation.authorize(&unmount_usb).await.unwrap();
+		elevation.authorize(&unmount_usb).await.unwrap();
+		assert_eq!(prompts.load(Ordering::SeqCst), 1);
+
+		elevation
+			.authorize(&ElevationScope::new(ElevatedOperation::Eject, "/media/usb"))
+			.await
+			.unwrap();
+		elevation
+			.authorize(&ElevationScope::new(
+				ElevatedOperation::Unmount,
+				"/media/sd",
+			))
+			.await
+			.unwrap();
+		assert_eq!(prompts.load(Ordering::SeqCst), 3);
+
+		elevation.revoke(&unmount_usb);
+		elevation.authorize(&unmount_usb).await.unwrap();
+		assert_eq!(prompts.load(Ordering::SeqCst), 4);
+	}
+
+	#[tokio::test]
+	async fn expired_and_denied_authorizations_are_asked_again() {
+		let prompts = Arc::new(AtomicUsize::new(0));
+		let scope = ElevationScope::new(ElevatedOperation::Mount, "/dev/sdb1");
+
+		let expiring = Elevation::with_ttl(
+			CountingBackend {
+				prompts: Arc::clone(&prompts),
+				deny: false,
+			},
+			Duration::ZERO,
+		);
+		expiring.authorize(&scope).await.unwrap();
+		expiring.authorize(&scope).await.unwrap();
+		assert_eq!(prompts.load(Ordering::SeqCst), 2);
+
+		let denying = Elevation::new(CountingBackend {
+			prompts: Arc::clone(&prompts),
+			deny: true,
+		});
+		for _ in 0..2 {
+			assert!(matches!(
+				denying.authorize(&scope).await,
+				Err(VolumeError::AuthorizationDenied {
+					operation: ElevatedOperation::Mount
+				})
+			));
+		}
+		assert_eq!(prompts.load(Ordering::SeqCst), 4);
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn smart_data_is_read_through_pkexec() {
+		let args = vec![OsString::from("--json"), OsString::from("/dev/nvme0n1")];
+
+		let (command, wrapped) = PolkitBackend.wrap(
+			&ElevationScope::new(ElevatedOperation::Smart, "/dev/nvme0n1"),
+			"smartctl",
+			args.clone(),
+		);
+		assert_eq!(command, "pkexec");
+		assert_eq!(wrapped, ["smartctl", "--json", "/dev/nvme0n1"]);
+
+		let (command, unwrapped) = PolkitBackend.wrap(
+			&ElevationScope::new(ElevatedOperation::Mount, "/dev/sdb1"),
+			"udisksctl",
+			args.clone(),
+		);
+		assert_eq!(command, "udisksctl");
+		assert_eq!(unwrapped, args);
+	}
+}
@@ -1,9 +1,15 @@
 //! Error types for volume management operations
+use std::ffi::OsString;
 use std::fmt;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+
 use thiserror::Error;
 
-use super::types::VolumeFingerprint;
+use super::{
+	elevation::ElevatedOperation,
+	types::{BusyProcess, LocalizedMessage, VolumeErrorCode, VolumeErrorInfo, VolumeFingerprint},
+};
 
 /// Errors that can occur during volume operations
 #[derive(Error, Debug)]
@@ -51,6 +57,10 @@ pub enum VolumeError {
 	#[error("Database error: {0}")]
 	Database(#[from] prisma_client_rust::QueryError),
 
+	/// The sync operations of a record couldn't be written
+	#[error("Sync error: {0}")]
+	Sync(#[from] sd_core_sync::Error),
+
 	/// Device error
 	#[error("Device error: {0}")]
 	DeviceError(String),
@@ -71,6 +81,10 @@ pub enum VolumeError {
 	#[error("Volume is read-only: {}", .0.display())]
 	ReadOnly(PathBuf),
 
+	/// The volume is encrypted and locked, its files can't be read until the user unlocks it
+	#[error("Volume is locked: {}", .0.display())]
+	Locked(PathBuf),
+
 	/// Device not found
 	#[error("Device not found: {:?}", .0)]
 	DeviceNotFound(Vec<u8>),
@@ -94,17 +108,87 @@ pub enum VolumeError {
 	#[error("Volume watcher error: {0}")]
 	Watcher(#[from] WatcherError),
 
-	/// Platform-specific error
-	#[error("Platform error: {0}")]
-	Platform(String),
+	/// The platform watcher (inotify, FSEvents...) could not be started
+	#[error("Failed to initialize the volume watcher: {source}")]
+	WatchInitFailed {
+		#[source]
+		source: Box<dyn std::error::Error + Send + Sync>,
+	},
+
+	/// The operation isn't available on this platform
+	#[error("Volume {operation} is not supported on this platform")]
+	UnsupportedPlatform { operation: &'static str },
+
+	/// A system tool we rely on (findmnt, umount...) could not be run
+	#[error("Failed to run {command}: {source}")]
+	CommandFailed {
+		command: &'static str,
+		#[source]
+		source: std::io::Error,
+	},
+
+	/// An argument was refused before running a system tool, it could have been misread by it
+	#[error("Refusing to pass {argument:?} to {command}: {reason}")]
+	UnsafeArgument {
+		command: &'static str,
+		argument: OsString,
+		reason: &'static str,
+	},
+
+	/// A system tool ran for too long and was killed
+	#[error("{command} timed out after {after:?}")]
+	CommandTimedOut {
+		command: &'static str,
+		after: Duration,
+	},
+
+	/// Unmounting failed for a reason the platform didn't let us classify
+	#[error("Failed to unmount volume {}: {reason}", .path.display())]
+	UnmountFailed { path: PathBuf, reason: String },
+
+	/// Mounting the device failed for a reason the platform didn't let us classify
+	#[error("Failed to mount {}: {reason}", .device.display())]
+	MountFailed { device: PathBuf, reason: String },
+
+	/// A blocking platform query panicked or was cancelled
+	#[error("Volume task failed: {0}")]
+	TaskJoin(#[from] tokio::task::JoinError),
 
 	/// Permission denied
-	#[error("Permission denied for path: {}", .0.display())]
-	PermissionDenied(PathBuf),
+	#[error("Permission denied for path: {}", .path.display())]
+	PermissionDenied {
+		path: PathBuf,
+		#[source]
+		source: Option<std::io::Error>,
+	},
+
+	/// The OS, or the user when prompted, refused to elevate the privileges of the operation
+	#[error("Not authorized to {operation}")]
+	AuthorizationDenied { operation: ElevatedOperation },
+
+	/// The volume the OS runs from, which can't be unmounted, ejected or formatted
+	#[error("Volume is used by the system: {}", .0.display())]
+	SystemVolume(PathBuf),
+
+	/// The device is in use, usually by open files on an unmount
+	#[error("Device is busy: {}", .path.display())]
+	DeviceBusy {
+		path: PathBuf,
+		#[source]
+		source: Option<std::io::Error>,
+	},
+
+	/// Processes still use the volume, which isn't detached from under them before it's ejected
+	#[error("Volume {} is in use by {}", .path.display(), holders_list(.holders))]
+	InUse {
+		path: PathBuf,
+		/// Those of other users can't be listed without root
+		holders: Vec<BusyProcess>,
+	},
 
-	/// Volume is busy
-	#[error("Volume is busy: {}", .0.display())]
-	VolumeBusy(PathBuf),
+	/// Jobs still work on the locations on the volume, after waiting for them
+	#[error("Jobs are still running on volume {}", .path.display())]
+	JobsRunning { path: PathBuf },
 
 	/// Operation cancelled
 	#[error("Operation was cancelled")]
@@ -117,6 +201,26 @@ pub enum VolumeError {
 	/// Resource exhausted
 	#[error("Resource exhausted: {0}")]
 	ResourceExhausted(String),
+
+	/// A volume snapshot couldn't be read, or was written in a format we don't know
+	#[error("Invalid volume snapshot: {0}")]
+	InvalidSnapshot(String),
+
+	/// The URL doesn't name a network share we know how to mount
+	#[error("Invalid network share URL {url:?}: {reason}")]
+	InvalidShareUrl { url: String, reason: &'static str },
+
+	/// The credentials stored for a network share couldn't be read from the keyring
+	#[error("Credentials for {url} are unavailable: {reason}")]
+	CredentialsUnavailable { url: String, reason: String },
+
+	/// The file isn't a disk image this platform can attach
+	#[error("Invalid disk image {}: {reason}", .path.display())]
+	InvalidDiskImage { path: PathBuf, reason: &'static str },
+
+	/// The file system mounted at `path` doesn't keep a snapshot with this ID
+	#[error("No snapshot {id} of {}", .path.display())]
+	SnapshotNotFound { path: PathBuf, id: String },
 }
 
 /// Specific kinds of speed test errors
@@ -172,12 +276,18 @@ impl VolumeError {
 
 	/// Checks if the error is a timeout
 	pub fn is_timeout(&self) -> bool {
-		matches!(self, VolumeError::Timeout(_))
+		matches!(
+			self,
+			VolumeError::Timeout(_) | VolumeError::CommandTimedOut { .. }
+		)
 	}
 
 	/// Checks if the error is permission related
 	pub fn is_permission_denied(&self) -> bool {
-		matches!(self, VolumeError::PermissionDenied(_))
+		matches!(
+			self,
+			VolumeError::PermissionDenied { .. } | VolumeError::AuthorizationDenied { .. }
+		)
 	}
 
 	/// Checks if the error is space related
@@ -185,14 +295,22 @@ impl VolumeError {
 		matches!(self, VolumeError::InsufficientSpace { .. })
 	}
 
-	/// Checks if the operation can be retried
+	/// Checks if the operation can be retried, the failure may go away by itself
 	pub fn is_retriable(&self) -> bool {
-		matches!(
-			self,
+		match self {
 			VolumeError::Timeout(_)
-				| VolumeError::VolumeBusy(_)
-				| VolumeError::ResourceExhausted(_)
-		)
+			| VolumeError::CommandTimedOut { .. }
+			| VolumeError::DeviceBusy { .. }
+			| VolumeError::ResourceExhausted(_) => true,
+			VolumeError::Io(source) | VolumeError::CommandFailed { source, .. } => {
+				is_transient_io(source)
+			}
+			VolumeError::WatchInitFailed { source } => source
+				.downcast_ref::<std::io::Error>()
+				.is_some_and(is_transient_io),
+			VolumeError::WithContext { source, .. } => source.is_retriable(),
+			_ => false,
+		}
 	}
 
 	/// Gets the path associated with the error, if any
@@ -201,12 +319,227 @@ impl VolumeError {
 			VolumeError::VolumeExists(path)
 			| VolumeError::NotMounted(path)
 			| VolumeError::ReadOnly(path)
+			| VolumeError::Locked(path)
+			| VolumeError::SystemVolume(path)
 			| VolumeError::InsufficientSpace { path, .. }
-			| VolumeError::PermissionDenied(path)
-			| VolumeError::VolumeBusy(path) => Some(path),
+			| VolumeError::UnmountFailed { path, .. }
+			| VolumeError::MountFailed { device: path, .. }
+			| VolumeError::PermissionDenied { path, .. }
+			| VolumeError::DeviceBusy { path, .. }
+			| VolumeError::InUse { path, .. }
+			| VolumeError::JobsRunning { path }
+			| VolumeError::InvalidDiskImage { path, .. }
+			| VolumeError::SnapshotNotFound { path, .. } => Some(path),
+			VolumeError::WithContext { source, .. } => source.path(),
 			_ => None,
 		}
 	}
+
+	/// Stable, machine-readable code for this error
+	pub fn code(&self) -> VolumeErrorCode {
+		match self {
+			VolumeError::WithContext { source, .. } => source.code(),
+			VolumeError::OperationFailed(..) => VolumeErrorCode::OperationFailed,
+			VolumeError::Io(_) | VolumeError::DirectoryError(_) => VolumeErrorCode::Io,
+			VolumeError::Timeout(_) | VolumeError::CommandTimedOut { .. } => {
+				VolumeErrorCode::Timeout
+			}
+			VolumeError::NoMountPoint
+			| VolumeError::NotFound(_)
+			| VolumeError::NotInDatabase
+			| VolumeError::DeviceNotFound(_)
+			| VolumeError::NoDeviceFound => VolumeErrorCode::NotFound,
+			VolumeError::InvalidFingerprint(_) => VolumeErrorCode::InvalidFingerprint,
+			VolumeError::InvalidSnapshot(_) => VolumeErrorCode::InvalidSnapshot,
+			VolumeError::InvalidShareUrl { .. } => VolumeErrorCode::InvalidShareUrl,
+			VolumeError::InvalidDiskImage { .. } => VolumeErrorCode::InvalidDiskImage,
+			VolumeError::SnapshotNotFound { .. } => VolumeErrorCode::SnapshotNotFound,
+			VolumeError::CredentialsUnavailable { .. } => VolumeErrorCode::CredentialsUnavailable,
+			VolumeError::Database(_) | VolumeError::Sync(_) => VolumeErrorCode::Database,
+			VolumeError::DeviceError(_) => VolumeErrorCode::DeviceError,
+			VolumeError::VolumeExists(_) => VolumeErrorCode::AlreadyExists,
+			VolumeError::NotMounted(_) => VolumeErrorCode::NotMounted,
+			VolumeError::ReadOnly(_) => VolumeErrorCode::ReadOnly,
+			VolumeError::Locked(_) => VolumeErrorCode::Locked,
+			VolumeError::SystemVolume(_) => VolumeErrorCode::SystemVolume,
+			VolumeError::InsufficientSpace { .. } => VolumeErrorCode::InsufficientSpace,
+			VolumeError::SpeedTest { .. } => VolumeErrorCode::SpeedTestFailed,
+			VolumeError::Watcher(_) => VolumeErrorCode::WatcherFailed,
+			VolumeError::WatchInitFailed { .. } => VolumeErrorCode::WatchInitFailed,
+			VolumeError::UnsupportedPlatform { .. } => VolumeErrorCode::UnsupportedPlatform,
+			VolumeError::CommandFailed { .. } | VolumeError::TaskJoin(_) => {
+				VolumeErrorCode::Internal
+			}
+			VolumeError::UnsafeArgument { .. } => VolumeErrorCode::InvalidArgument,
+			VolumeError::UnmountFailed { .. } => VolumeErrorCode::UnmountFailed,
+			VolumeError::MountFailed { .. } => VolumeErrorCode::MountFailed,
+			VolumeError::PermissionDenied { .. } | VolumeError::AuthorizationDenied { .. } => {
+				VolumeErrorCode::PermissionDenied
+			}
+			VolumeError::DeviceBusy { .. } | VolumeError::InUse { .. } => {
+				VolumeErrorCode::DeviceBusy
+			}
+			VolumeError::JobsRunning { .. } => VolumeErrorCode::JobsRunning,
+			VolumeError::Cancelled => VolumeErrorCode::Cancelled,
+			VolumeError::InvalidConfiguration(_) => VolumeErrorCode::InvalidConfiguration,
+			VolumeError::ResourceExhausted(_) => VolumeErrorCode::ResourceExhausted,
+		}
+	}
+
+	/// Translation key and parameters of the user-facing message, the key is the one of the code
+	pub fn message(&self) -> LocalizedMessage {
+		if let VolumeError::WithContext { source, .. } = self {
+			return source.message();
+		}
+
+		let mut message = LocalizedMessage::new(self.code().message_key());
+		if let Some(path) = self.path() {
+			message = message.param("path", path.display());
+		}
+
+		match self {
+			VolumeError::InsufficientSpace {
+				needed, available, ..
+			} => message
+				.param("needed", needed)
+				.param("available", available),
+			VolumeError::CommandFailed { command, .. }
+			| VolumeError::CommandTimedOut { command, .. } => message.param("command", command),
+			VolumeError::UnsafeArgument {
+				command, argument, ..
+			} => message
+				.param("command", command)
+				.param("argument", argument.to_string_lossy()),
+			VolumeError::UnmountFailed { reason, .. } | VolumeError::MountFailed { reason, .. } => {
+				message.param("reason", reason)
+			}
+			VolumeError::UnsupportedPlatform { operation } => message.param("operation", operation),
+			VolumeError::AuthorizationDenied { operation } => message.param("operation", operation),
+			VolumeError::InvalidShareUrl { url, .. }
+			| VolumeError::CredentialsUnavailable { url, .. } => message.param("url", url),
+			VolumeError::SnapshotNotFound { id, .. } => message.param("id", id),
+			_ => message,
+		}
+	}
+
+	/// Maps a failed unmount to a specific error, from the errno or the message of the tool that ran it
+	pub(crate) fn from_unmount(path: impl Into<PathBuf>, stderr: &str) -> Self {
+		let path = path.into();
+		let reason = stderr.trim();
+		let lowercase = reason.to_lowercase();
+
+		// diskutil says a process holding the volume "dissented"
+		if lowercase.contains("busy") || lowercase.contains("dissented") {
+			VolumeError::DeviceBusy { path, source: None }
+		} else if is_permission_message(&lowercase) {
+			VolumeError::PermissionDenied { path, source: None }
+		} else if lowercase.contains("not mounted") {
+			VolumeError::NotMounted(path)
+		} else {
+			VolumeError::UnmountFailed {
+				path,
+				reason: reason.to_string(),
+			}
+		}
+	}
+
+	/// Maps a failed unmount system call on `path` to a specific error, from its errno
+	#[cfg(unix)]
+	pub(crate) fn from_unmount_errno(path: impl Into<PathBuf>, e: std::io::Error) -> Self {
+		let path = path.into();
+		match e.raw_os_error() {
+			Some(libc::EBUSY) => VolumeError::DeviceBusy {
+				path,
+				source: Some(e),
+			},
+			Some(libc::EPERM | libc::EACCES) => VolumeError::PermissionDenied {
+				path,
+				source: Some(e),
+			},
+			// Not a mount point, or nothing there at all
+			Some(libc::EINVAL | libc::ENOENT) => VolumeError::NotMounted(path),
+			_ => VolumeError::UnmountFailed {
+				path,
+				reason: e.to_string(),
+			},
+		}
+	}
+
+	/// Maps a failed mount of `device` to a specific error, from the message of the tool that ran it
+	pub(crate) fn from_mount(device: impl Into<PathBuf>, stderr: &str) -> Self {
+		let device = device.into();
+		let reason = stderr.trim();
+		let lowercase = reason.to_lowercase();
+
+		if lowercase.contains("busy") {
+			VolumeError::DeviceBusy {
+				path: device,
+				source: None,
+			}
+		} else if is_permission_message(&lowercase) {
+			VolumeError::PermissionDenied {
+				path: device,
+				source: None,
+			}
+		} else {
+			VolumeError::MountFailed {
+				device,
+				reason: reason.to_string(),
+			}
+		}
+	}
+}
+
+/// Whether a system call failed for lack of something another process is about to release
+fn is_transient_io(e: &std::io::Error) -> bool {
+	#[cfg(unix)]
+	if matches!(
+		e.raw_os_error(),
+		Some(libc::EBUSY | libc::EMFILE | libc::ENFILE | libc::ENOMEM)
+	) {
+		return true;
+	}
+
+	matches!(
+		e.kind(),
+		std::io::ErrorKind::Interrupted
+			| std::io::ErrorKind::WouldBlock
+			| std::io::ErrorKind::TimedOut
+	)
+}
+
+/// Whether the lowercased output of a system tool says it wasn't allowed to do its job
+fn is_permission_message(lowercase: &str) -> bool {
+	lowercase.contains("permission denied")
+		|| lowercase.contains("must be superuser")
+		|| lowercase.contains("operation not permitted")
+		|| lowercase.contains("not authorized")
+}
+
+/// The processes as `name (pid)`, or a placeholder when none of them could be read
+fn holders_list(holders: &[BusyProcess]) -> String {
+	if holders.is_empty() {
+		return "processes of other users".to_string();
+	}
+
+	holders
+		.iter()
+		.map(|BusyProcess { pid, name }| format!("{name} ({pid})"))
+		.collect::<Vec<_>>()
+		.join(", ")
+}
+
+impl From<&VolumeError> for VolumeErrorInfo {
+	fn from(err: &VolumeError) -> Self {
+		let LocalizedMessage { key, params } = err.message();
+		Self {
+			code: err.code(),
+			message_key: key,
+			params,
+			message: err.to_string(),
+			path: err.path().map(|path| path.to_string_lossy().to_string()),
+		}
+	}
 }
 
 // Implement conversion from VolumeError to rspc::Error for API responses
@@ -216,20 +549,36 @@ impl From<VolumeError> for rspc::Error {
 		let code = match &err {
 			VolumeError::NotInDatabase
 			| VolumeError::NoMountPoint
-			| VolumeError::InvalidFingerprint(_) => rspc::ErrorCode::NotFound,
+			| VolumeError::InvalidFingerprint(_)
+			| VolumeError::SnapshotNotFound { .. } => rspc::ErrorCode::NotFound,
+
+			VolumeError::PermissionDenied { .. }
+			| VolumeError::AuthorizationDenied { .. }
+			| VolumeError::SystemVolume(_) => rspc::ErrorCode::Forbidden,
 
-			VolumeError::PermissionDenied(_) => rspc::ErrorCode::Forbidden,
+			VolumeError::Timeout(_)
+			| VolumeError::CommandTimedOut { .. }
+			| VolumeError::DeviceBusy { .. } => rspc::ErrorCode::Timeout,
 
-			VolumeError::Timeout(_) | VolumeError::VolumeBusy(_) => rspc::ErrorCode::Timeout,
+			VolumeError::NotMounted(_)
+			| VolumeError::Locked(_)
+			| VolumeError::InUse { .. }
+			| VolumeError::JobsRunning { .. } => rspc::ErrorCode::Conflict,
 
 			VolumeError::InsufficientSpace { .. } => rspc::ErrorCode::PayloadTooLarge,
 
-			VolumeError::InvalidConfiguration(_) => rspc::ErrorCode::BadRequest,
+			VolumeError::InvalidConfiguration(_)
+			| VolumeError::InvalidSnapshot(_)
+			| VolumeError::InvalidShareUrl { .. }
+			| VolumeError::InvalidDiskImage { .. }
+			| VolumeError::UnsafeArgument { .. }
+			| VolumeError::UnsupportedPlatform { .. } => rspc::ErrorCode::BadRequest,
 
 			_ => rspc::ErrorCode::InternalServerError,
 		};
 
-		rspc::Error::with_cause(code, err.to_string(), err)
+		// The stable code leads the message so clients can tell errors apart without parsing the text
+		rspc::Error::with_cause(code, format!("{}: {err}", err.code()), err)
 	}
 }
 
@@ -250,9 +599,10 @@ impl<T> VolumeResultExt<T> for Result<T, VolumeError> {
 	fn with_path(self, path: impl AsRef<Path>) -> Result<T, VolumeError> {
 		self.map_err(|e| match e {
 			VolumeError::Io(io_err) => match io_err.kind() {
-				std::io::ErrorKind::PermissionDenied => {
-					VolumeError::PermissionDenied(path.as_ref().to_path_buf())
-				}
+				std::io::ErrorKind::PermissionDenied => VolumeError::PermissionDenied {
+					path: path.as_ref().to_path_buf(),
+					source: Some(io_err),
+				},
 				_ => VolumeError::DirectoryError(format!(
 					"Operation failed on path '{}': {}",
 					path.as_ref().display(),
@@ -293,6 +643,124 @@ mod tests {
 		assert!(!error.is_timeout());
 		assert!(error.path().is_some());
 	}
+
+	#[test]
+	fn test_permission_denied_keeps_source() {
+		let io_error = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+		let result: Result<(), VolumeError> = Err(VolumeError::from(io_error));
+		let error = result.with_path("/mnt/usb").unwrap_err();
+
+		assert_eq!(error.code(), VolumeErrorCode::PermissionDenied);
+		assert!(std::error::Error::source(&error).is_some());
+		assert_eq!(error.path(), Some(Path::new("/mnt/usb")));
+	}
+
+	#[test]
+	fn test_transient_io_is_retriable() {
+		let interrupted = std::io::Error::from(std::io::ErrorKind::Interrupted);
+		assert!(VolumeError::from(interrupted).is_retriable());
+
+		let not_found = std::io::Error::from(std::io::ErrorKind::NotFound);
+		assert!(!VolumeError::from(not_found).is_retriable());
+
+		#[cfg(unix)]
+		{
+			let busy = VolumeError::CommandFailed {
+				command: "umount",
+				source: std::io::Error::from_raw_os_error(libc::EBUSY),
+			};
+			assert!(busy.is_retriable());
+
+			let watch = VolumeError::WatchInitFailed {
+				source: Box::new(std::io::Error::from_raw_os_error(libc::EMFILE)),
+			};
+			let watch = VolumeError::WithContext {
+				context: "Watching /proc/self/mountinfo".to_string(),
+				source: Box::new(watch),
+			};
+			assert!(watch.is_retriable());
+		}
+	}
+
+	#[test]
+	fn test_unmount_errors_are_classified() {
+		let busy = VolumeError::from_unmount("/mnt/usb", "umount: /mnt/usb: target is busy.\n");
+		assert_eq!(busy.code(), VolumeErrorCode::DeviceBusy);
+		assert!(busy.is_retriable());
+
+		let not_mounted = VolumeError::from_unmount("/mnt/usb", "umount: /mnt/usb: not mounted.");
+		assert_eq!(not_mounted.code(), VolumeErrorCode::NotMounted);
+		assert!(!not_mounted.is_retriable());
+
+		let denied = VolumeError::from_unmount("/mnt/usb", "umount: /mnt/usb: must be superuser to unmount.");
+		assert_eq!(denied.code(), VolumeErrorCode::PermissionDenied);
+
+		let not_authorized = VolumeError::from_unmount(
+			"/mnt/usb",
+			"Error unmounting /dev/sdb1: GDBus.Error:org.freedesktop.UDisks2.Error.NotAuthorizedCanObtain: Not authorized to perform operation",
+		);
+		assert_eq!(not_authorized.code(), VolumeErrorCode::PermissionDenied);
+
+		let dissented = VolumeError::from_unmount(
+			"/Volumes/USB",
+			"Volume USB on disk4s1 failed to unmount: dissented by PID 412 (/usr/bin/python3)",
+		);
+		assert_eq!(dissented.code(), VolumeErrorCode::DeviceBusy);
+
+		let other = VolumeError::from_unmount("/mnt/usb", "umount: bad superblock");
+		assert_eq!(other.code(), VolumeErrorCode::UnmountFailed);
+
+		#[cfg(unix)]
+		{
+			let errno = |errno| {
+				VolumeError::from_unmount_errno(
+					"/mnt/usb",
+					std::io::Error::from_raw_os_error(errno),
+				)
+			};
+			assert!(errno(libc::EBUSY).is_retriable());
+			assert!(errno(libc::EPERM).is_permission_denied());
+			assert_eq!(errno(libc::EINVAL).code(), VolumeErrorCode::NotMounted);
+			assert_eq!(errno(libc::EIO).code(), VolumeErrorCode::UnmountFailed);
+		}
+	}
+
+	#[test]
+	fn test_busy_eject_names_the_holders() {
+		let in_use = VolumeError::InUse {
+			path: PathBuf::from("/mnt/usb"),
+			holders: vec![BusyProcess {
+				pid: 4242,
+				name: "vim".to_string(),
+			}],
+		};
+		assert_eq!(in_use.code(), VolumeErrorCode::DeviceBusy);
+		assert_eq!(
+			in_use.to_string(),
+			"Volume /mnt/usb is in use by vim (4242)"
+		);
+		assert!(!in_use.is_retriable());
+	}
+
+	#[test]
+	fn test_error_codes_are_stable() {
+		assert_eq!(
+			serde_json::to_value(VolumeErrorCode::WatchInitFailed).unwrap(),
+			serde_json::json!(VolumeErrorCode::WatchInitFailed.as_str())
+		);
+
+		let info = VolumeErrorInfo::from(&VolumeError::DeviceBusy {
+			path: PathBuf::from("/mnt/usb"),
+			source: None,
+		});
+		assert_eq!(info.code, VolumeErrorCode::DeviceBusy);
+		assert_eq!(info.message_key, "volume_error_device_busy");
+		assert_eq!(
+			info.params.get("path").map(String::as_str),
+			Some("/mnt/usb")
+		);
+		assert_eq!(info.path.as_deref(), Some("/mnt/usb"));
+	}
 }
 
 impl fmt::Display for SpeedTestErrorKind {
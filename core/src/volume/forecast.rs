@@ -0,0 +1,247 @@
+//! When the volumes are expected to fill up, from the trend of their usage history.
+//!
+//! The used space of the records in the last [`FORECAST_WINDOW`] is fitted with a least squares
+//! line, its slope is how fast the volume fills. A forecast needs [`MIN_SAMPLES`] records spread
+//! over at least [`MIN_SPAN`], fewer give trends too noisy to act on.
+
+use super::{
+	error::VolumeError,
+	history::{volume_history, HistoryRange, VolumeHistoryEntry},
+	types::{ByteSize, VolumeFingerprint},
+};
+
+use sd_prisma::prisma::PrismaClient;
+
+use std::{collections::HashSet, sync::Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// How far back the records are fitted, older trends say little about the coming days
+pub const FORECAST_WINDOW: Duration = Duration::days(30);
+
+/// Usage records a forecast needs
+pub const MIN_SAMPLES: usize = 3;
+
+/// Time the usage records of a forecast have to cover
+pub const MIN_SPAN: Duration = Duration::days(1);
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct CapacityForecast {
+	/// The usage records the trend was fitted on
+	pub samples: u32,
+	/// When the oldest of them was recorded
+	pub since: Option<DateTime<Utc>>,
+	/// How fast the used space grows, negative while the volume is freed up. `None` without
+	/// enough records for a trend.
+	pub bytes_per_day: Option<f64>,
+	/// The free space at the latest record
+	pub total_bytes_available: Option<ByteSize>,
+	/// `None` when the volume doesn't fill up at the current trend, or there is no trend
+	pub days_until_full: Option<f64>,
+	pub full_at: Option<DateTime<Utc>>,
+}
+
+/// The capacity forecast of the volume, from its history in the library
+pub async fn capacity_forecast(
+	db: &PrismaClient,
+	fingerprint: &VolumeFingerprint,
+	now: DateTime<Utc>,
+) -> Result<CapacityForecast, VolumeError> {
+	let range = HistoryRange {
+		from: Some(now - FORECAST_WINDOW),
+		to: Some(now),
+	};
+
+	Ok(forecast(
+		&volume_history(db, fingerprint, range).await?,
+		now,
+	))
+}
+
+/// Fits the used space of `entries`, oldest first, and extends the trend from `now`
+pub fn forecast(entries: &[VolumeHistoryEntry], now: DateTime<Utc>) -> CapacityForecast {
+	// Attaches count too, they carry the usage the volume came back with
+	let samples = entries
+		.iter()
+		.filter_map(|entry| {
+			let capacity = entry.total_bytes_capacity?.0;
+			let available = entry.total_bytes_available?.0;
+			Some((
+				entry.recorded_at,
+				capacity.saturating_sub(available),
+				available,
+			))
+		})
+		.collect::<Vec<_>>();
+
+	let mut forecast = CapacityForecast {
+		samples: samples.len() as u32,
+		since: samples.first().map(|(recorded_at, ..)| *recorded_at),
+		bytes_per_day: None,
+		total_bytes_available: samples.last().map(|(.., available)| ByteSize(*available)),
+		days_until_full: None,
+		full_at: None,
+	};
+
+	let (Some((first, ..)), Some((last, _, available))) = (samples.first(), samples.last()) else {
+		return forecast;
+	};
+	if samples.len() < MIN_SAMPLES || *last - *first < MIN_SPAN {
+		return forecast;
+	}
+
+	let points = samples
+		.iter()
+		.map(|(recorded_at, used, _)| {
+			let days = (*recorded_at - *first).num_seconds() as f64 / SECONDS_PER_DAY;
+			(days, *used as f64)
+		})
+		.collect::<Vec<_>>();
+	let Some(slope) = slope(&points) else {
+		return forecast;
+	};
+	forecast.bytes_per_day = Some(slope);
+
+	if slope > 0.0 {
+		// Counted from the latest record, the time since then already ate into the space left
+		let elapsed = (now - *last).num_seconds().max(0) as f64 / SECONDS_PER_DAY;
+		let days = (*available as f64 / slope - elapsed).max(0.0);
+		forecast.days_until_full = Some(days);
+		forecast.full_at = Duration::try_seconds((days * SECONDS_PER_DAY) as i64)
+			.and_then(|left| now.checked_add_signed(left));
+	}
+
+	forecast
+}
+
+/// The least squares slope of the points, `None` when they were all recorded at once
+fn slope(points: &[(f64, f64)]) -> Option<f64> {
+	let n = points.len() as f64;
+	let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+	let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+	let (covariance, variance) = points.iter().fold((0.0, 0.0), |(cov, var), (x, y)| {
+		let dx = x - mean_x;
+		(cov + dx * (y - mean_y), var + dx * dx)
+	});
+
+	(variance > 0.0).then(|| covariance / variance)
+}
+
+/// The volumes already warned about, so the warning goes out once each time a forecast comes
+/// within the horizon
+#[derive(Default)]
+pub(crate) struct CapacityWarnings(Mutex<HashSet<VolumeFingerprint>>);
+
+impl CapacityWarnings {
+	/// Whether the forecast just came within `horizon_days`, 0 turns the warnings off
+	pub fn crossed(
+		&self,
+		fingerprint: &VolumeFingerprint,
+		forecast: &CapacityForecast,
+		horizon_days: u32,
+	) -> bool {
+		let within = horizon_days > 0
+			&& forecast
+				.days_until_full
+				.is_some_and(|days| days <= f64::from(horizon_days));
+
+		let mut warned = self.0.lock().unwrap_or_else(|e| e.into_inner());
+		if within {
+			warned.insert(fingerprint.clone())
+		} else {
+			warned.remove(fingerprint);
+			false
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::volume::VolumeHistoryKind;
+
+	const GB: u64 = 1_000_000_000;
+
+	fn usage(recorded_at: DateTime<Utc>, available: u64) -> VolumeHistoryEntry {
+		VolumeHistoryEntry {
+			kind: VolumeHistoryKind::Usage,
+			recorded_at,
+			total_bytes_capacity: Some(ByteSize(100 * GB)),
+			total_bytes_available: Some(ByteSize(available)),
+		}
+	}
+
+	#[test]
+	fn extends_the_usage_trend() {
+		let start = Utc::now() - Duration::days(4);
+		let entries = (0..5)
+			.map(|day| usage(start + Duration::days(day), (50 - 2 * day as u64) * GB))
+			.collect::<Vec<_>>();
+
+		let forecast = forecast(&entries, start + Duration::days(4));
+		assert_eq!(forecast.samples, 5);
+		assert_eq!(forecast.since, Some(start));
+		assert_eq!(forecast.total_bytes_available, Some(ByteSize(42 * GB)));
+
+		let bytes_per_day = forecast.bytes_per_day.unwrap();
+		assert!((bytes_per_day - (2 * GB) as f64).abs() < 1.0);
+		let days = forecast.days_until_full.unwrap();
+		assert!((days - 21.0).abs() < 0.01);
+		assert!(forecast.full_at.is_some());
+
+		// A day without records since then is taken off
+		let later = super::forecast(&entries, start + Duration::days(5));
+		assert!((later.days_until_full.unwrap() - 20.0).abs() < 0.01);
+	}
+
+	#[test]
+	fn needs_a_growing_trend_over_enough_records() {
+		let now = Utc::now();
+
+		let few = [usage(now - Duration::days(2), 50 * GB), usage(now, 40 * GB)];
+		let forecast = forecast(&few, now);
+		assert_eq!(forecast.samples, 2);
+		assert_eq!(forecast.bytes_per_day, None);
+		assert_eq!(forecast.days_until_full, None);
+
+		let same_hour = (0..5)
+			.map(|minute| usage(now + Duration::minutes(minute), (50 - minute as u64) * GB))
+			.collect::<Vec<_>>();
+		assert_eq!(super::forecast(&same_hour, now).bytes_per_day, None);
+
+		let freeing = (0..5)
+			.map(|day| usage(now + Duration::days(day), (40 + day as u64) * GB))
+			.collect::<Vec<_>>();
+		let forecast = super::forecast(&freeing, now + Duration::days(4));
+		assert!(forecast.bytes_per_day.unwrap() < 0.0);
+		assert_eq!(forecast.days_until_full, None);
+		assert_eq!(forecast.full_at, None);
+	}
+
+	#[test]
+	fn warns_once_per_crossing() {
+		let warnings = CapacityWarnings::default();
+		let fingerprint = VolumeFingerprint(vec![1]);
+		let forecast = |days: Option<f64>| CapacityForecast {
+			samples: 10,
+			since: None,
+			bytes_per_day: Some(1.0),
+			total_bytes_available: None,
+			days_until_full: days,
+			full_at: None,
+		};
+
+		assert!(!warnings.crossed(&fingerprint, &forecast(Some(30.0)), 14));
+		assert!(warnings.crossed(&fingerprint, &forecast(Some(10.0)), 14));
+		assert!(!warnings.crossed(&fingerprint, &forecast(Some(9.0)), 14));
+		assert!(!warnings.crossed(&fingerprint, &forecast(None), 14));
+		assert!(warnings.crossed(&fingerprint, &forecast(Some(5.0)), 14));
+		assert!(!warnings.crossed(&VolumeFingerprint(vec![2]), &forecast(Some(5.0)), 0));
+	}
+}
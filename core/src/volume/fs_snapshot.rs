@@ -0,0 +1,212 @@
+//! The snapshots file systems keep of a volume, Btrfs snapshots and APFS local snapshots.
+//!
+//! They're listed with the tools of each file system, `btrfs subvolume list` on Linux and `tmutil
+//! listlocalsnapshots` on macOS, so users can get an older version of their files back from
+//! within the app. A snapshot is browsed from a read-only mount of its own, in a directory under
+//! the temporary directory, unless it's already reachable inside the volume like the Btrfs
+//! snapshots kept next to the subvolume they were taken of. The snapshots mounted here are
+//! unmounted when the node shuts down.
+
+use super::{
+	error::VolumeError,
+	platform::VolumePlatform,
+	types::{FileSystem, Volume, VolumeFingerprint},
+};
+
+use std::{collections::HashMap, path::PathBuf, sync::Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use specta::Type;
+use tracing::{info, warn};
+
+/// Where the snapshots are mounted, a directory for each of them
+const MOUNT_DIR: &str = "spacedrive-snapshots";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Type)]
+pub struct FileSystemSnapshot {
+	/// What the snapshot is mounted by, the subvolume ID on Btrfs and its name on APFS
+	pub id: String,
+	pub name: String,
+	/// Unknown for the APFS snapshots not taken by Time Machine
+	pub created_at: Option<DateTime<Utc>>,
+	/// Where it can be browsed without mounting it
+	pub path: Option<PathBuf>,
+}
+
+/// Whether the file system keeps snapshots the platforms can list
+pub fn supports_snapshots(file_system: &FileSystem) -> bool {
+	match file_system {
+		FileSystem::APFS => true,
+		FileSystem::Other(name) => name.eq_ignore_ascii_case("btrfs"),
+		_ => false,
+	}
+}
+
+/// The snapshots mounted through the core, by volume and snapshot ID
+#[derive(Debug, Default)]
+pub(crate) struct MountedSnapshots(Mutex<HashMap<(VolumeFingerprint, String), PathBuf>>);
+
+impl MountedSnapshots {
+	fn get(&self, fingerprint: &VolumeFingerprint, id: &str) -> Option<PathBuf> {
+		self.lock()
+			.get(&(fingerprint.clone(), id.to_string()))
+			.cloned()
+	}
+
+	fn insert(&self, fingerprint: &VolumeFingerprint, id: &str, mount_point: PathBuf) {
+		self.lock()
+			.insert((fingerprint.clone(), id.to_string()), mount_point);
+	}
+
+	fn remove(&self, fingerprint: &VolumeFingerprint, id: &str) -> Option<PathBuf> {
+		self.lock().remove(&(fingerprint.clone(), id.to_string()))
+	}
+
+	fn take_all(&self) -> Vec<PathBuf> {
+		self.lock()
+			.drain()
+			.map(|(_, mount_point)| mount_point)
+			.collect()
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<(VolumeFingerprint, String), PathBuf>> {
+		self.0.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+/// The snapshots of the volume, oldest first
+pub(super) async fn list(
+	platform: &dyn VolumePlatform,
+	volume: &Volume,
+) -> Result<Vec<FileSystemSnapshot>, VolumeError> {
+	if !supports_snapshots(&volume.file_system) {
+		return Err(VolumeError::UnsupportedPlatform {
+			operation: "listing the snapshots of this file system",
+		});
+	}
+	if !volume.is_mounted {
+		return Err(VolumeError::NotMounted(volume.mount_point.clone()));
+	}
+
+	let mut snapshots = platform.list_snapshots(&volume.mount_point).await?;
+	// The ones without a date go last, in the order the tool listed them
+	snapshots.sort_by_key(|snapshot| (snapshot.created_at.is_none(), snapshot.created_at));
+
+	Ok(snapshots)
+}
+
+/// Where the snapshot can be browsed, mounting it read-only if it isn't reachable yet
+pub(super) async fn mount(
+	platform: &dyn VolumePlatform,
+	mounted: &MountedSnapshots,
+	volume: &Volume,
+	fingerprint: &VolumeFingerprint,
+	id: &str,
+) -> Result<PathBuf, VolumeError> {
+	if let Some(mount_point) = mounted.get(fingerprint, id) {
+		return Ok(mount_point);
+	}
+
+	let snapshot = list(platform, volume)
+		.await?
+		.into_iter()
+		.find(|snapshot| snapshot.id == id)
+		.ok_or_else(|| VolumeError::SnapshotNotFound {
+			path: volume.mount_point.clone(),
+			id: id.to_string(),
+		})?;
+	if let Some(path) = snapshot.path.clone() {
+		return Ok(path);
+	}
+
+	let mount_point = mount_point_for(fingerprint, id);
+	tokio::fs::create_dir_all(&mount_point).await?;
+	if let Err(e) = platform
+		.mount_snapshot(&volume.mount_point, &snapshot, &mount_point)
+		.await
+	{
+		// Empty, nothing was mounted on it
+		let _ = tokio::fs::remove_dir(&mount_point).await;
+		return Err(e);
+	}
+
+	info!(id, mount_point = %mount_point.display(), "Mounted a snapshot;");
+	mounted.insert(fingerprint, id, mount_point.clone());
+
+	Ok(mount_point)
+}
+
+/// Unmounts a snapshot mounted through [`mount`], the ones reachable inside their volume are left
+/// alone
+pub(super) async fn unmount(
+	platform: &dyn VolumePlatform,
+	mounted: &MountedSnapshots,
+	fingerprint: &VolumeFingerprint,
+	id: &str,
+) -> Result<(), VolumeError> {
+	let Some(mount_point) = mounted.remove(fingerprint, id) else {
+		return Ok(());
+	};
+
+	if let Err(e) = platform.unmount_snapshot(&mount_point).await {
+		mounted.insert(fingerprint, id, mount_point);
+		return Err(e);
+	}
+	let _ = tokio::fs::remove_dir(&mount_point).await;
+
+	Ok(())
+}
+
+/// Unmounts the snapshots still mounted, for the node shutting down
+pub(super) async fn unmount_all(platform: &dyn VolumePlatform, mounted: &MountedSnapshots) {
+	for mount_point in mounted.take_all() {
+		match platform.unmount_snapshot(&mount_point).await {
+			Ok(()) => {
+				let _ = tokio::fs::remove_dir(&mount_point).await;
+			}
+			Err(e) => {
+				warn!(?e, mount_point = %mount_point.display(), "Failed to unmount a snapshot;")
+			}
+		}
+	}
+}
+
+/// A directory of its own for each snapshot, named after the volume and the snapshot ID
+fn mount_point_for(fingerprint: &VolumeFingerprint, id: &str) -> PathBuf {
+	let id = id.replace(
+		|c: char| !c.is_ascii_alphanumeric() && c != '-' && c != '.',
+		"_",
+	);
+	std::env::temp_dir()
+		.join(MOUNT_DIR)
+		.join(format!("{fingerprint}-{id}"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn only_btrfs_and_apfs_keep_snapshots() {
+		assert!(supports_snapshots(&FileSystem::APFS));
+		assert!(supports_snapshots(&FileSystem::Other("BTRFS".to_string())));
+		assert!(!supports_snapshots(&FileSystem::EXT4));
+		assert!(!supports_snapshots(&FileSystem::NTFS));
+	}
+
+	#[test]
+	fn snapshots_get_a_directory_of_their_own() {
+		let fingerprint = VolumeFingerprint(vec![0xab, 0xcd]);
+		let mount_point = mount_point_for(&fingerprint, "com.apple.TimeMachine/2024 ..");
+
+		assert_eq!(
+			mount_point.parent(),
+			Some(&*std::env::temp_dir().join(MOUNT_DIR))
+		);
+		assert_eq!(
+			mount_point.file_name().and_then(|name| name.to_str()),
+			Some("abcd-com.apple.TimeMachine_2024_..")
+		);
+	}
+}
@@ -0,0 +1,235 @@
+//! The S.M.A.R.T. health of the drives behind the volumes, read with `smartctl`.
+//!
+//! smartctl speaks to ATA, SCSI and NVMe drives alike and prints the same JSON for all of them.
+//! It needs to open the raw device, which takes root on most systems: when it can't, the health is
+//! unknown rather than an error the user has to act on, unless they asked for it and can elevate.
+//! The platforms find the drive a volume is on, see
+//! [`VolumePlatform::health`](super::VolumePlatform::health).
+
+use super::{
+	command,
+	elevation::{ElevatedOperation, Elevation, ElevationScope},
+	error::VolumeError,
+	types::{HealthStatus, VolumeFingerprint, VolumeHealth},
+};
+
+use std::{collections::HashMap, ffi::OsStr, path::Path, sync::Mutex};
+
+use serde::Deserialize;
+use tracing::debug;
+
+/// Bits of the smartctl exit status, see smartctl(8)
+const EXIT_OPEN_FAILED: i32 = 1 << 1;
+const EXIT_DISK_FAILING: i32 = 1 << 3;
+const EXIT_PREFAIL_THRESHOLD: i32 = 1 << 4;
+const EXIT_USAGE_THRESHOLD: i32 = 1 << 5;
+
+/// The ATA attribute counting the remapped sectors
+const REALLOCATED_SECTOR_COUNT: u32 = 5;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SmartctlOutput {
+	smart_status: Option<SmartStatus>,
+	temperature: Option<Temperature>,
+	power_on_time: Option<PowerOnTime>,
+	ata_smart_attributes: Option<AtaAttributes>,
+	nvme_smart_health_information_log: Option<NvmeHealthLog>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SmartStatus {
+	passed: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Temperature {
+	current: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct PowerOnTime {
+	hours: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AtaAttributes {
+	table: Vec<AtaAttribute>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AtaAttribute {
+	id: u32,
+	raw: AtaRawValue,
+}
+
+#[derive(Debug, Deserialize)]
+struct AtaRawValue {
+	value: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct NvmeHealthLog {
+	#[serde(default)]
+	critical_warning: u8,
+}
+
+/// Asks smartctl about the drive at `device`, a whole disk like `/dev/sda` rather than one of its
+/// partitions. It's asked again with `elevation` when it can't open the drive.
+pub(super) async fn smartctl(
+	device: &Path,
+	elevation: Option<&Elevation>,
+) -> Result<VolumeHealth, VolumeError> {
+	let args = [
+		OsStr::new("--json"),
+		OsStr::new("--all"),
+		command::path_arg("smartctl", device)?,
+	];
+	let mut output = command::run("smartctl", args).await?;
+
+	// The status is a bitmask, drives that fail their checks exit with an error too
+	let mut status = output.status.code().unwrap_or_default();
+	if let Some(elevation) = elevation.filter(|elevation| elevation.is_available()) {
+		if status & EXIT_OPEN_FAILED != 0 {
+			let scope = ElevationScope::new(ElevatedOperation::Smart, device);
+			output = elevation.run(&scope, "smartctl", args).await?;
+			status = output.status.code().unwrap_or_default();
+		}
+	}
+
+	if status & EXIT_OPEN_FAILED != 0 {
+		debug!(
+			?device,
+			stderr = %output.stderr_lossy(),
+			"smartctl couldn't open the drive;"
+		);
+		return Ok(VolumeHealth::UNKNOWN);
+	}
+
+	parse(&output.stdout, status)
+}
+
+/// The health in the JSON smartctl printed, `status` being its exit status
+pub(super) fn parse(json: &[u8], status: i32) -> Result<VolumeHealth, VolumeError> {
+	let output = serde_json::from_slice::<SmartctlOutput>(json)
+		.map_err(|e| VolumeError::DeviceError(format!("unexpected smartctl output: {e}")))?;
+
+	let reallocated_sectors = output.ata_smart_attributes.and_then(|attributes| {
+		attributes
+			.table
+			.into_iter()
+			.find(|attribute| attribute.id == REALLOCATED_SECTOR_COUNT)
+			.map(|attribute| attribute.raw.value)
+	});
+	let nvme_warning = output
+		.nvme_smart_health_information_log
+		.is_some_and(|log| log.critical_warning != 0);
+
+	let status = match output.smart_status {
+		Some(SmartStatus { passed: false }) => HealthStatus::Failing,
+		_ if status & EXIT_DISK_FAILING != 0 => HealthStatus::Failing,
+		_ if status & (EXIT_PREFAIL_THRESHOLD | EXIT_USAGE_THRESHOLD) != 0
+			|| nvme_warning
+			|| reallocated_sectors.is_some_and(|sectors| sectors > 0) =>
+		{
+			HealthStatus::Warning
+		}
+		Some(SmartStatus { passed: true }) => HealthStatus::Healthy,
+		None => HealthStatus::Unknown,
+	};
+
+	Ok(VolumeHealth {
+		status,
+		temperature_celsius: output.temperature.map(|temperature| temperature.current),
+		reallocated_sectors,
+		power_on_hours: output.power_on_time.map(|time| time.hours),
+	})
+}
+
+/// The last health status of each volume, so the changes are published once
+#[derive(Debug, Default)]
+pub(crate) struct HealthStatuses(Mutex<HashMap<VolumeFingerprint, HealthStatus>>);
+
+impl HealthStatuses {
+	/// Records the status, returning whether it changed. Drives that stop reporting their health
+	/// keep the last status they reported, the ones found healthy at first aren't worth telling.
+	pub fn changed(&self, fingerprint: &VolumeFingerprint, status: HealthStatus) -> bool {
+		if status == HealthStatus::Unknown {
+			return false;
+		}
+
+		let mut statuses = self.0.lock().unwrap_or_else(|e| e.into_inner());
+		match statuses.insert(fingerprint.clone(), status) {
+			Some(previous) => previous != status,
+			None => status != HealthStatus::Healthy,
+		}
+	}
+
+	pub fn forget(&self, fingerprint: &VolumeFingerprint) {
+		self.0
+			.lock()
+			.unwrap_or_else(|e| e.into_inner())
+			.remove(fingerprint);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reads_the_health_of_ata_and_nvme_drives() {
+		let ata = br#"{
+			"smart_status": { "passed": true },
+			"temperature": { "current": 38 },
+			"power_on_time": { "hours": 12034 },
+			"ata_smart_attributes": { "table": [
+				{ "id": 1, "name": "Raw_Read_Error_Rate", "raw": { "value": 0 } },
+				{ "id": 5, "name": "Reallocated_Sector_Ct", "raw": { "value": 8 } }
+			] }
+		}"#;
+		assert_eq!(
+			parse(ata, 0).unwrap(),
+			VolumeHealth {
+				status: HealthStatus::Warning,
+				temperature_celsius: Some(38),
+				reallocated_sectors: Some(8),
+				power_on_hours: Some(12034),
+			}
+		);
+
+		let nvme = br#"{
+			"smart_status": { "passed": true },
+			"temperature": { "current": 41 },
+			"nvme_smart_health_information_log": { "critical_warning": 0, "media_errors": 0 }
+		}"#;
+		let health = parse(nvme, 0).unwrap();
+		assert_eq!(health.status, HealthStatus::Healthy);
+		assert_eq!(health.reallocated_sectors, None);
+
+		let failing = br#"{ "smart_status": { "passed": false } }"#;
+		assert_eq!(
+			parse(failing, EXIT_DISK_FAILING).unwrap().status,
+			HealthStatus::Failing
+		);
+
+		// USB bridges that don't pass the commands through
+		assert_eq!(parse(b"{}", 1 << 2).unwrap(), VolumeHealth::UNKNOWN);
+		assert!(parse(b"Smartctl open device failed", 0).is_err());
+	}
+
+	#[test]
+	fn publishes_each_status_change_once() {
+		let statuses = HealthStatuses::default();
+		let fingerprint = VolumeFingerprint(vec![1]);
+
+		assert!(!statuses.changed(&fingerprint, HealthStatus::Healthy));
+		assert!(!statuses.changed(&fingerprint, HealthStatus::Healthy));
+		assert!(!statuses.changed(&fingerprint, HealthStatus::Unknown));
+		assert!(statuses.changed(&fingerprint, HealthStatus::Warning));
+		assert!(statuses.changed(&fingerprint, HealthStatus::Healthy));
+
+		statuses.forget(&fingerprint);
+		assert!(statuses.changed(&fingerprint, HealthStatus::Warning));
+	}
+}
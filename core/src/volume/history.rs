@@ -0,0 +1,335 @@
+//! When each volume was attached and detached, and how full it was, persisted in the libraries so
+//! the history survives restarts.
+//!
+//! Records are kept per fingerprint in every loaded library, whether the volume is tracked or not.
+//! Attaching and detaching are recorded on the volume events, usage at most once per
+//! [`USAGE_SAMPLE_INTERVAL`] while the volume changes and on every stats rollup of the maintenance
+//! job. Records past [`RETENTION`] are pruned by the maintenance task, and no volume keeps more
+//! than [`MAX_RECORDS_PER_VOLUME`].
+
+use super::{
+	error::VolumeError,
+	forecast::{capacity_forecast, CapacityForecast},
+	tracked,
+	types::{ByteSize, Volume, VolumeEvent, VolumeFingerprint},
+};
+use crate::library::Library;
+
+use sd_prisma::prisma::{volume_history, PrismaClient, SortOrder};
+
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::sync::RwLock;
+use tracing::warn;
+use uuid::Uuid;
+
+/// How long records are kept
+pub const RETENTION: Duration = Duration::days(180);
+
+/// Records kept per volume, the oldest go first
+pub const MAX_RECORDS_PER_VOLUME: i64 = 2000;
+
+/// Time between two usage records of the same volume
+pub const USAGE_SAMPLE_INTERVAL: Duration = Duration::hours(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum VolumeHistoryKind {
+	Attached,
+	Detached,
+	/// The space used while the volume stayed attached
+	Usage,
+}
+
+impl VolumeHistoryKind {
+	pub const fn as_str(self) -> &'static str {
+		match self {
+			Self::Attached => "attached",
+			Self::Detached => "detached",
+			Self::Usage => "usage",
+		}
+	}
+
+	fn from_record(kind: &str) -> Option<Self> {
+		[Self::Attached, Self::Detached, Self::Usage]
+			.into_iter()
+			.find(|k| k.as_str() == kind)
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct VolumeHistoryEntry {
+	pub kind: VolumeHistoryKind,
+	pub recorded_at: DateTime<Utc>,
+	pub total_bytes_capacity: Option<ByteSize>,
+	pub total_bytes_available: Option<ByteSize>,
+}
+
+/// The records to return, both ends included. An open end goes as far as the records do.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type)]
+pub struct HistoryRange {
+	pub from: Option<DateTime<Utc>>,
+	pub to: Option<DateTime<Utc>>,
+}
+
+impl VolumeHistoryEntry {
+	fn from_record(record: volume_history::Data) -> Option<Self> {
+		Some(Self {
+			kind: VolumeHistoryKind::from_record(&record.kind)?,
+			recorded_at: record.recorded_at.into(),
+			total_bytes_capacity: record.total_bytes_capacity.map(|b| ByteSize(b as u64)),
+			total_bytes_available: record.total_bytes_available.map(|b| ByteSize(b as u64)),
+		})
+	}
+}
+
+/// The records of the volume in `range`, oldest first
+pub async fn volume_history(
+	db: &PrismaClient,
+	fingerprint: &VolumeFingerprint,
+	range: HistoryRange,
+) -> Result<Vec<VolumeHistoryEntry>, VolumeError> {
+	let mut filters = vec![volume_history::fingerprint::equals(fingerprint.0.clone())];
+	if let Some(from) = range.from {
+		filters.push(volume_history::recorded_at::gte(from.into()));
+	}
+	if let Some(to) = range.to {
+		filters.push(volume_history::recorded_at::lte(to.into()));
+	}
+
+	Ok(db
+		.volume_history()
+		.find_many(filters)
+		.order_by(volume_history::recorded_at::order(SortOrder::Asc))
+		.exec()
+		.await?
+		.into_iter()
+		// Kinds written by a newer version are skipped
+		.filter_map(VolumeHistoryEntry::from_record)
+		.collect())
+}
+
+/// Adds a record, unless it would repeat the last attach or detach of the volume
+async fn record(
+	db: &PrismaClient,
+	fingerprint: &VolumeFingerprint,
+	kind: VolumeHistoryKind,
+	volume: &Volume,
+) -> Result<(), VolumeError> {
+	if kind != VolumeHistoryKind::Usage {
+		// Every start of the core reports the volumes present as added again
+		let last = db
+			.volume_history()
+			.find_first(vec![
+				volume_history::fingerprint::equals(fingerprint.0.clone()),
+				volume_history::kind::in_vec(vec![
+					VolumeHistoryKind::Attached.as_str().to_string(),
+					VolumeHistoryKind::Detached.as_str().to_string(),
+				]),
+			])
+			.order_by(volume_history::recorded_at::order(SortOrder::Desc))
+			.select(volume_history::select!({ kind }))
+			.exec()
+			.await?;
+
+		if last.is_some_and(|last| last.kind == kind.as_str()) {
+			return Ok(());
+		}
+	}
+
+	db.volume_history()
+		.create(
+			fingerprint.0.clone(),
+			kind.as_str().to_string(),
+			vec![
+				volume_history::total_bytes_capacity::set(Some(
+					volume.total_bytes_capacity.0 as i64,
+				)),
+				volume_history::total_bytes_available::set(Some(
+					volume.total_bytes_available.0 as i64,
+				)),
+			],
+		)
+		.exec()
+		.await?;
+
+	let overflow = db
+		.volume_history()
+		.find_many(vec![volume_history::fingerprint::equals(
+			fingerprint.0.clone(),
+		)])
+		.order_by(volume_history::recorded_at::order(SortOrder::Desc))
+		.skip(MAX_RECORDS_PER_VOLUME)
+		.select(volume_history::select!({ id }))
+		.exec()
+		.await?;
+
+	if !overflow.is_empty() {
+		db.volume_history()
+			.delete_many(vec![volume_history::id::in_vec(
+				overflow.into_iter().map(|r| r.id).collect(),
+			)])
+			.exec()
+			.await?;
+	}
+
+	Ok(())
+}
+
+/// Adds a usage record, so the volumes that don't change still have some
+pub(super) async fn record_usage(
+	db: &PrismaClient,
+	fingerprint: &VolumeFingerprint,
+	volume: &Volume,
+) -> Result<(), VolumeError> {
+	record(db, fingerprint, VolumeHistoryKind::Usage, volume).await
+}
+
+/// Deletes the records older than [`RETENTION`], returning how many there were
+pub async fn prune(db: &PrismaClient, now: DateTime<Utc>) -> Result<i64, VolumeError> {
+	Ok(db
+		.volume_history()
+		.delete_many(vec![volume_history::recorded_at::lt(
+			(now - RETENTION).into(),
+		)])
+		.exec()
+		.await?)
+}
+
+/// Writes the volume events to the history of the loaded libraries, and to the records of the
+/// volumes they track
+#[derive(Default)]
+pub(crate) struct VolumeHistoryRecorder {
+	libraries: RwLock<HashMap<Uuid, Arc<Library>>>,
+	last_usage: Mutex<HashMap<VolumeFingerprint, DateTime<Utc>>>,
+}
+
+impl VolumeHistoryRecorder {
+	pub async fn add_library(&self, library: Arc<Library>) {
+		self.libraries.write().await.insert(library.id, library);
+	}
+
+	pub async fn libraries(&self) -> Vec<Arc<Library>> {
+		self.libraries.read().await.values().cloned().collect()
+	}
+
+	pub async fn remove_library(&self, library_id: Uuid) {
+		self.libraries.write().await.remove(&library_id);
+	}
+
+	/// Records the attaches, detaches and usage changes among the volume events, returning the
+	/// forecast of the volume when its usage was recorded
+	pub async fn on_event(
+		&self,
+		event: &VolumeEvent,
+		device_pub_id: &[u8],
+	) -> Option<(VolumeFingerprint, CapacityForecast)> {
+		let (kind, volume) = match event {
+			VolumeEvent::VolumeAdded(volume) => (VolumeHistoryKind::Attached, volume),
+			VolumeEvent::VolumeRemoved(volume) => (VolumeHistoryKind::Detached, volume),
+			VolumeEvent::VolumeUpdated { old, new }
+				if old.total_bytes_available != new.total_bytes_available =>
+			{
+				(VolumeHistoryKind::Usage, new)
+			}
+			_ => return None,
+		};
+
+		let fingerprint = VolumeFingerprint::new(device_pub_id, volume);
+		let now = Utc::now();
+		if kind == VolumeHistoryKind::Usage && !self.sample_usage(&fingerprint, now) {
+			return None;
+		}
+
+		let libraries = self.libraries.read().await;
+		for library in libraries.values() {
+			if let Err(e) = record(&library.db, &fingerprint, kind, volume).await {
+				warn!(?e, library_id = %library.id, "Failed to record volume history;");
+			}
+			let is_mounted = kind != VolumeHistoryKind::Detached;
+			if let Err(e) = tracked::seen(library, &fingerprint, volume, is_mounted).await {
+				warn!(?e, library_id = %library.id, "Failed to update the tracked volume;");
+			}
+		}
+
+		if kind != VolumeHistoryKind::Usage {
+			return None;
+		}
+
+		// Every loaded library records the volume, any of them will do
+		let library = libraries.values().next()?;
+		match capacity_forecast(&library.db, &fingerprint, now).await {
+			Ok(forecast) => Some((fingerprint, forecast)),
+			Err(e) => {
+				warn!(?e, library_id = %library.id, "Failed to forecast volume capacity;");
+				None
+			}
+		}
+	}
+
+	/// Prunes the history of every loaded library
+	pub async fn prune(&self) -> Result<(), VolumeError> {
+		let now = Utc::now();
+		for library in self.libraries.read().await.values() {
+			prune(&library.db, now).await?;
+		}
+
+		self.last_usage
+			.lock()
+			.unwrap_or_else(|e| e.into_inner())
+			.retain(|_, sampled_at| now - *sampled_at < USAGE_SAMPLE_INTERVAL);
+
+		Ok(())
+	}
+
+	/// Whether a usage record is due for the volume, counting it as recorded if so
+	fn sample_usage(&self, fingerprint: &VolumeFingerprint, now: DateTime<Utc>) -> bool {
+		let mut last_usage = self.last_usage.lock().unwrap_or_else(|e| e.into_inner());
+		match last_usage.get(fingerprint) {
+			Some(sampled_at) if now - *sampled_at < USAGE_SAMPLE_INTERVAL => false,
+			_ => {
+				last_usage.insert(fingerprint.clone(), now);
+				true
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn usage_is_sampled_once_per_interval() {
+		let recorder = VolumeHistoryRecorder::default();
+		let fingerprint = VolumeFingerprint(vec![1, 2, 3]);
+		let now = Utc::now();
+
+		assert!(recorder.sample_usage(&fingerprint, now));
+		assert!(!recorder.sample_usage(&fingerprint, now + Duration::minutes(10)));
+		assert!(recorder.sample_usage(&VolumeFingerprint(vec![4]), now));
+		assert!(recorder.sample_usage(&fingerprint, now + USAGE_SAMPLE_INTERVAL));
+	}
+
+	#[test]
+	fn kinds_round_trip_through_their_records() {
+		for kind in [
+			VolumeHistoryKind::Attached,
+			VolumeHistoryKind::Detached,
+			VolumeHistoryKind::Usage,
+		] {
+			assert_eq!(VolumeHistoryKind::from_record(kind.as_str()), Some(kind));
+			assert_eq!(
+				serde_json::to_value(kind).unwrap(),
+				serde_json::json!(kind.as_str())
+			);
+		}
+		assert_eq!(VolumeHistoryKind::from_record("renamed"), None);
+	}
+}
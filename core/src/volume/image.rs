@@ -0,0 +1,193 @@
+//! Disk images attached as volumes, ISO, DMG and IMG files.
+//!
+//! The platform attaches the image as a device and mounts the file system on it: a loop device
+//! set up through udisks2 on Linux, hdiutil on macOS and Mount-DiskImage on Windows. The volume it
+//! becomes is listed as [`MountType::DiskImage`](super::MountType::DiskImage). The images attached
+//! through the core are remembered and detached when the node shuts down, so no device is left
+//! pointing at a file the user may move or delete once the app is gone. Unmounting or ejecting the
+//! volume detaches its image as well.
+
+use super::{
+	error::{VolumeError, VolumeResultExt},
+	platform::VolumePlatform,
+};
+
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+	sync::Mutex,
+};
+
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskImageFormat {
+	/// An optical disc, always attached read-only
+	Iso,
+	/// A macOS disk image
+	Dmg,
+	/// A raw copy of a disk or a partition
+	Img,
+}
+
+impl DiskImageFormat {
+	/// The format of a file from its extension
+	pub fn from_path(path: &Path) -> Option<Self> {
+		let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+		match extension.as_str() {
+			"iso" => Some(Self::Iso),
+			"dmg" => Some(Self::Dmg),
+			"img" => Some(Self::Img),
+			_ => None,
+		}
+	}
+
+	/// Whether the OS the core runs on can attach it, Windows only mounts ISO files
+	pub fn is_supported(self) -> bool {
+		match self {
+			Self::Iso => cfg!(any(
+				target_os = "linux",
+				target_os = "macos",
+				target_os = "windows"
+			)),
+			Self::Dmg => cfg!(target_os = "macos"),
+			Self::Img => cfg!(any(target_os = "linux", target_os = "macos")),
+		}
+	}
+}
+
+/// An image the platform attached, and where its file system was mounted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttachedImage {
+	pub image: PathBuf,
+	/// What it's detached with: the loop device on Linux, the whole disk on macOS and the image
+	/// itself on Windows
+	pub device: PathBuf,
+	pub mount_point: PathBuf,
+}
+
+/// The images attached through the core, by mount point
+#[derive(Debug, Default)]
+pub(crate) struct AttachedImages(Mutex<HashMap<PathBuf, AttachedImage>>);
+
+impl AttachedImages {
+	pub fn insert(&self, image: AttachedImage) {
+		self.lock().insert(image.mount_point.clone(), image);
+	}
+
+	pub fn remove(&self, mount_point: &Path) -> Option<AttachedImage> {
+		self.lock().remove(mount_point)
+	}
+
+	fn take_all(&self) -> Vec<AttachedImage> {
+		self.lock().drain().map(|(_, image)| image).collect()
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, AttachedImage>> {
+		self.0.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+/// Attaches the image and mounts its file system, read-only for the ISO files
+pub(super) async fn attach(
+	platform: &dyn VolumePlatform,
+	image: &Path,
+	read_only: bool,
+) -> Result<AttachedImage, VolumeError> {
+	if !platform.capabilities().disk_images {
+		return Err(VolumeError::UnsupportedPlatform {
+			operation: "attaching disk images",
+		});
+	}
+
+	let invalid = |reason| VolumeError::InvalidDiskImage {
+		path: image.to_path_buf(),
+		reason,
+	};
+	let format = DiskImageFormat::from_path(image)
+		.ok_or_else(|| invalid("expected an ISO, DMG or IMG file"))?;
+	if !format.is_supported() {
+		return Err(invalid("this platform can't attach images of this format"));
+	}
+
+	// The tools resolve relative paths from their own working directory
+	let image = tokio::fs::canonicalize(image)
+		.await
+		.map_err(VolumeError::from)
+		.with_path(image)?;
+	if !tokio::fs::metadata(&image)
+		.await
+		.map_err(VolumeError::from)
+		.with_path(&image)?
+		.is_file()
+	{
+		return Err(invalid("not a file"));
+	}
+
+	platform
+		.attach_disk_image(&image, read_only || format == DiskImageFormat::Iso)
+		.await
+}
+
+/// Detaches the images still attached, for the node shutting down
+pub(super) async fn detach_all(platform: &dyn VolumePlatform, images: &AttachedImages) {
+	for image in images.take_all() {
+		match platform.detach_disk_image(&image).await {
+			Ok(()) => info!(image = %image.image.display(), "Detached disk image;"),
+			Err(e) => warn!(?e, image = %image.image.display(), "Failed to detach disk image;"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::volume::MockVolumeProvider;
+
+	#[test]
+	fn tells_the_format_from_the_extension() {
+		assert_eq!(
+			DiskImageFormat::from_path(Path::new("/images/Ubuntu.ISO")),
+			Some(DiskImageFormat::Iso)
+		);
+		assert_eq!(
+			DiskImageFormat::from_path(Path::new("installer.dmg")),
+			Some(DiskImageFormat::Dmg)
+		);
+		assert_eq!(DiskImageFormat::from_path(Path::new("backup.tar")), None);
+		assert_eq!(DiskImageFormat::from_path(Path::new("img")), None);
+	}
+
+	#[tokio::test]
+	async fn detaches_what_is_left_on_shutdown() {
+		let dir = std::env::temp_dir().join(format!("sd-disk-image-{}", uuid::Uuid::new_v4()));
+		tokio::fs::create_dir_all(&dir).await.unwrap();
+		let iso = dir.join("disc.iso");
+		tokio::fs::write(&iso, b"").await.unwrap();
+
+		let platform = MockVolumeProvider::new([]);
+		let images = AttachedImages::default();
+		if DiskImageFormat::Iso.is_supported() {
+			let attached = attach(&platform, &iso, false).await.unwrap();
+			images.insert(attached.clone());
+			assert_eq!(platform.get_volumes().await.unwrap().len(), 1);
+			// ISO files can't be written to
+			assert!(platform.attached_images()[0].1);
+
+			detach_all(&platform, &images).await;
+			assert!(platform.get_volumes().await.unwrap().is_empty());
+			assert!(images.remove(&attached.mount_point).is_none());
+		}
+
+		assert!(matches!(
+			attach(&platform, &dir.join("missing.tar"), false).await,
+			Err(VolumeError::InvalidDiskImage { .. })
+		));
+		assert!(attach(&platform, &dir.join("missing.iso"), false)
+			.await
+			.is_err());
+
+		tokio::fs::remove_dir_all(&dir).await.unwrap();
+	}
+}
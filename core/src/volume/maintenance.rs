@@ -0,0 +1,331 @@
+//! Volume maintenance runs as a job, so speed tests, SMART polls and stats rollups show up in the
+//! job queue, can be paused or cancelled, and pick up where they left off after a restart.
+
+use crate::{
+	api::BackendFeature,
+	invalidate_query,
+	old_job::{
+		CurrentStep, JobError, JobInitOutput, JobReportUpdate, JobResult, JobRunErrors,
+		JobRunMetadata, JobStepOutput, OldJob, StatefulJob, WorkerContext,
+	},
+	Node,
+};
+
+use sd_prisma::prisma::location;
+
+use std::{hash::Hash, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use tokio::time::sleep;
+use tracing::{debug, warn};
+
+use super::{
+	error::VolumeError,
+	history,
+	speed::SpeedTest,
+	types::{MountType, Volume, VolumeFingerprint, VolumeRecord},
+};
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceTask {
+	/// Measures read and write speeds
+	SpeedTest,
+	/// Writes the current capacity, mount and error status to the library, and a usage record to
+	/// its history
+	StatsRollup,
+	/// Reads the SMART health of the drive behind the volume
+	HealthCheck,
+}
+
+impl MaintenanceTask {
+	/// Whether a routine run, on no volume in particular, does the task on the volume
+	fn is_routine_for(self, volume: &Volume) -> bool {
+		match self {
+			// Only the volumes that never were
+			Self::SpeedTest => volume.is_volume_tracked() && volume.read_speed_mbps.is_none(),
+			Self::StatsRollup => volume.is_volume_tracked(),
+			// The drives are worth watching whether their volumes are tracked or not
+			Self::HealthCheck => {
+				volume.is_mounted
+					&& matches!(volume.mount_type, MountType::System | MountType::External)
+			}
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Hash, Type)]
+pub struct OldVolumeMaintenanceJobInit {
+	/// Volumes to work on, every volume tracked in the library when empty
+	pub fingerprints: Vec<VolumeFingerprint>,
+	pub tasks: Vec<MaintenanceTask>,
+}
+
+impl OldVolumeMaintenanceJobInit {
+	/// The tasks to run on each of `volumes`, the ones in [`Self::fingerprints`] or the routine ones
+	fn steps(&self, volumes: &[Volume]) -> Vec<MaintenanceStep> {
+		let explicit = !self.fingerprints.is_empty();
+
+		volumes
+			.iter()
+			.filter_map(|volume| Some((volume.fingerprint.clone()?, volume)))
+			.flat_map(|(fingerprint, volume)| {
+				let selected = explicit && self.fingerprints.contains(&fingerprint);
+				self.tasks
+					.iter()
+					.filter(move |task| selected || (!explicit && task.is_routine_for(volume)))
+					.map(move |task| MaintenanceStep {
+						fingerprint: fingerprint.clone(),
+						task: *task,
+					})
+			})
+			.collect()
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MaintenanceStep {
+	fingerprint: VolumeFingerprint,
+	task: MaintenanceTask,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct VolumeMaintenanceJobRunMetadata {
+	speed_tested: usize,
+	rolled_up: usize,
+	health_checked: usize,
+	/// Volumes that went away before their turn
+	skipped: usize,
+}
+
+impl JobRunMetadata for VolumeMaintenanceJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.speed_tested += new_data.speed_tested;
+		self.rolled_up += new_data.rolled_up;
+		self.health_checked += new_data.health_checked;
+		self.skipped += new_data.skipped;
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for OldVolumeMaintenanceJobInit {
+	type Data = ();
+	type Step = MaintenanceStep;
+	type RunMetadata = VolumeMaintenanceJobRunMetadata;
+
+	const NAME: &'static str = "volume_maintainer";
+
+	fn target_location(&self) -> location::id::Type {
+		// Volumes aren't tied to a location, and location ids start at 1
+		0
+	}
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let volumes = ctx
+			.node
+			.volumes
+			.list_system_volumes(Arc::clone(&ctx.library))
+			.await?;
+
+		let steps = self.steps(&volumes);
+		if steps.is_empty() {
+			return Err(JobError::EarlyFinish {
+				name: Self::NAME.to_string(),
+				reason: "no volume to work on".to_string(),
+			});
+		}
+
+		*data = Some(());
+
+		Ok((Default::default(), steps).into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep { step, step_number }: CurrentStep<'_, Self::Step>,
+		_: &Self::Data,
+		_: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		// Maintenance is never urgent, leave the disks some room between steps
+		if step_number > 0 {
+			let delay = ctx.node.core_config.get().volumes.maintenance_step_delay();
+			sleep(delay).await;
+		}
+
+		let mut new_metadata = Self::RunMetadata::default();
+
+		let Some(mut volume) = find_volume(ctx, &step.fingerprint).await? else {
+			debug!(fingerprint = %step.fingerprint, "Volume went away before maintenance;");
+			new_metadata.skipped += 1;
+			return Ok(new_metadata.into());
+		};
+
+		ctx.progress(vec![JobReportUpdate::Message(format!(
+			"{} {}",
+			match step.task {
+				MaintenanceTask::SpeedTest => "Testing the speed of",
+				MaintenanceTask::StatsRollup => "Updating the stats of",
+				MaintenanceTask::HealthCheck => "Checking the health of",
+			},
+			volume.name
+		))]);
+
+		let res = match step.task {
+			MaintenanceTask::SpeedTest => volume
+				.speed_test(None, Some(&ctx.node.volumes.event_tx))
+				.await
+				.map(|_| ()),
+			// The manager already keeps the stats current, the record is brought up to date below
+			MaintenanceTask::StatsRollup => {
+				history::record_usage(&ctx.library.db, &step.fingerprint, &volume).await
+			}
+			MaintenanceTask::HealthCheck => match ctx
+				.node
+				.volumes
+				.check_health(step.fingerprint.clone(), &volume, false)
+				.await
+			{
+				// smartctl isn't there, or the platform can't tell
+				Err(VolumeError::UnsupportedPlatform { .. }) => Ok(()),
+				res => res.map(|_| ()),
+			},
+		};
+
+		if let Err(e) = res {
+			warn!(?e, %volume.name, "Volume maintenance step failed;");
+			return Ok((
+				new_metadata,
+				JobRunErrors(vec![format!("{}: {e}", volume.name)]),
+			)
+				.into());
+		}
+
+		if volume.is_volume_tracked() {
+			volume.update(&ctx.library).await?;
+		}
+
+		match step.task {
+			MaintenanceTask::SpeedTest => new_metadata.speed_tested += 1,
+			MaintenanceTask::StatsRollup => new_metadata.rolled_up += 1,
+			MaintenanceTask::HealthCheck => new_metadata.health_checked += 1,
+		}
+
+		Ok(new_metadata.into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		_: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		invalidate_query!(ctx.library, "volumes.list");
+
+		Ok(Some(json!({ "init": self, "run_metadata": run_metadata })))
+	}
+}
+
+async fn find_volume(
+	ctx: &WorkerContext,
+	fingerprint: &VolumeFingerprint,
+) -> Result<Option<Volume>, JobError> {
+	Ok(ctx
+		.node
+		.volumes
+		.list_system_volumes(Arc::clone(&ctx.library))
+		.await?
+		.into_iter()
+		.find(|volume| volume.fingerprint.as_ref() == Some(fingerprint)))
+}
+
+/// Queues a maintenance job in every library once per maintenance interval, and the SMART polls
+/// once per health interval
+pub(crate) fn start(node: &Arc<Node>) {
+	start_health_checks(Arc::clone(node));
+
+	let node = Arc::clone(node);
+	let config = node.core_config.watch();
+	tokio::spawn(async move {
+		loop {
+			let interval = config.borrow().volumes.maintenance_interval();
+			sleep(interval).await;
+
+			for library in node.libraries.get_all().await {
+				// Their jobs would be rejected anyway
+				if library.is_read_only().await {
+					continue;
+				}
+
+				let init = OldVolumeMaintenanceJobInit {
+					fingerprints: vec![],
+					tasks: vec![MaintenanceTask::StatsRollup, MaintenanceTask::SpeedTest],
+				};
+
+				// No job in the queue when there's nothing to do
+				match node.volumes.list_system_volumes(Arc::clone(&library)).await {
+					Ok(volumes) if init.steps(&volumes).is_empty() => continue,
+					Ok(_) => {}
+					Err(e) => {
+						debug!(?e, library_id = %library.id, "Skipped volume maintenance;");
+						continue;
+					}
+				}
+
+				// The previous run still going is fine, it will be picked up next time
+				if let Err(e) = OldJob::new(init).spawn(&node, &library).await {
+					debug!(?e, library_id = %library.id, "Skipped volume maintenance;");
+				}
+			}
+		}
+	});
+}
+
+/// The drives are the same for every library, their health is checked in a single writable one
+fn start_health_checks(node: Arc<Node>) {
+	let mut config = node.core_config.watch();
+	tokio::spawn(async move {
+		loop {
+			let interval = config.borrow().volumes.health_interval();
+			let Some(interval) = interval else {
+				if config.changed().await.is_err() {
+					break;
+				}
+				continue;
+			};
+			sleep(interval).await;
+
+			if !node
+				.feature_flags
+				.is_enabled(BackendFeature::SmartMonitoring)
+			{
+				continue;
+			}
+
+			let mut library = None;
+			for candidate in node.libraries.get_all().await {
+				if !candidate.is_read_only().await {
+					library = Some(candidate);
+					break;
+				}
+			}
+			let Some(library) = library else {
+				continue;
+			};
+
+			let job = OldJob::new(OldVolumeMaintenanceJobInit {
+				fingerprints: vec![],
+				tasks: vec![MaintenanceTask::HealthCheck],
+			});
+			if let Err(e) = job.spawn(&node, &library).await {
+				debug!(?e, library_id = %library.id, "Skipped the drive health checks;");
+			}
+		}
+	});
+}
@@ -0,0 +1,496 @@
+//! A scriptable [`VolumePlatform`] for tests, so volume-dependent logic can be exercised without
+//! plugging drives in.
+//!
+//! The provider starts with a set of volumes and an optional script of [`MockStep`]s. Each step is
+//! applied with [`MockVolumeProvider::advance`], which also notifies the watchers the same way an
+//! OS event would. Enabled with the `test-utils` feature outside of the core's own tests.
+
+use super::{
+	error::VolumeError,
+	image::AttachedImage,
+	network::{NetworkShare, ShareCredentials},
+	platform::VolumePlatform,
+	snapshot::VolumeSnapshot,
+	types::{
+		DegradedMode, DeviceCapabilities, DiskType, FileSystem, MountOptions, MountType,
+		UnmountedDevice, Volume,
+	},
+	watcher::WatcherBackend,
+};
+
+use std::{
+	collections::VecDeque,
+	io,
+	path::{Path, PathBuf},
+	sync::Mutex,
+};
+
+use async_trait::async_trait;
+use tokio::{sync::mpsc, task::JoinHandle};
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub enum MockStep {
+	/// Replaces every volume the platform reports
+	SetVolumes(Vec<Volume>),
+	/// A drive was plugged in
+	Plug(Volume),
+	/// The volume mounted at this path went away
+	Unplug(PathBuf),
+	/// The next `get_volumes` call fails, like a platform backend would
+	FailNextScan,
+	/// The platform starts reporting data as missing, an empty mode recovers
+	Degrade(DegradedMode),
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+	volumes: Vec<Volume>,
+	script: VecDeque<MockStep>,
+	fail_next_scan: bool,
+	degraded_mode: DegradedMode,
+	capabilities: DeviceCapabilities,
+	unmounted: Vec<PathBuf>,
+	ejected: Vec<PathBuf>,
+	/// Unmounted volumes, which can be mounted back from their device
+	detached: Vec<Volume>,
+	watcher_backends: Vec<WatcherBackend>,
+	/// Backends that fail to start
+	broken_watchers: Vec<WatcherBackend>,
+	/// URL and username of the network shares mounted, in order
+	mounted_shares: Vec<(String, Option<String>)>,
+	/// Share mounts left to fail, as if their server was down
+	unreachable_shares: u32,
+	/// Path and read-only flag of the disk images attached, in order
+	attached_images: Vec<(PathBuf, bool)>,
+	watchers: Vec<mpsc::Sender<()>>,
+}
+
+#[derive(Debug, Default)]
+pub struct MockVolumeProvider {
+	state: Mutex<MockState>,
+}
+
+impl MockVolumeProvider {
+	pub fn new(volumes: impl IntoIterator<Item = Volume>) -> Self {
+		Self {
+			state: Mutex::new(MockState {
+				volumes: volumes.into_iter().collect(),
+				// What the mock implements
+				capabilities: DeviceCapabilities {
+					native_watch: true,
+					mount: true,
+					unmount: true,
+					eject: true,
+					network_shares: true,
+					disk_images: true,
+					..Default::default()
+				},
+				watcher_backends: vec![WatcherBackend::Native],
+				..Default::default()
+			}),
+		}
+	}
+
+	/// Reports the volumes a user had, as captured in their snapshot
+	pub fn from_snapshot(snapshot: &VolumeSnapshot) -> Self {
+		let volumes = snapshot.volumes.iter().cloned().map(|mut volume| {
+			// Platforms don't know about fingerprints, the manager derives them
+			volume.fingerprint = None;
+			volume
+		});
+
+		let provider = Self::new(volumes);
+		provider.state().degraded_mode = snapshot.degraded_mode.clone();
+		provider
+	}
+
+	/// Queues steps to be played, one per [`advance`](Self::advance) call
+	pub fn with_script(self, steps: impl IntoIterator<Item = MockStep>) -> Self {
+		self.state().script.extend(steps);
+		self
+	}
+
+	/// Reports other capabilities, like the ones of a platform the test pretends to run on
+	pub fn with_capabilities(self, capabilities: DeviceCapabilities) -> Self {
+		self.state().capabilities = capabilities;
+		self
+	}
+
+	/// Offers other watcher backends, in the order given. The broken ones fail to start.
+	pub fn with_watcher_backends(
+		self,
+		backends: impl IntoIterator<Item = WatcherBackend>,
+		broken: impl IntoIterator<Item = WatcherBackend>,
+	) -> Self {
+		{
+			let mut state = self.state();
+			state.watcher_backends = backends.into_iter().collect();
+			state.broken_watchers = broken.into_iter().collect();
+		}
+		self
+	}
+
+	/// Fails the next `failures` share mounts, like a server that isn't back yet
+	pub fn with_unreachable_shares(self, failures: u32) -> Self {
+		self.state().unreachable_shares = failures;
+		self
+	}
+
+	/// A mounted 512 GiB ext4 SSD, half full, for tests that don't care about the details
+	pub fn volume(name: &str, mount_point: impl Into<PathBuf>, mount_type: MountType) -> Volume {
+		let mount_point = mount_point.into();
+		Volume::new(
+			name.to_string(),
+			mount_type,
+			mount_point.clone(),
+			vec![mount_point],
+			DiskType::SSD,
+			FileSystem::EXT4,
+			512 * GIB,
+			256 * GIB,
+			false,
+		)
+	}
+
+	/// Plays the next scripted step, returning `false` once the script is over
+	pub fn advance(&self) -> bool {
+		let step = self.state().script.pop_front();
+		step.map(|step| self.apply(step)).is_some()
+	}
+
+	/// Plays a step right away, outside of the script
+	pub fn apply(&self, step: MockStep) {
+		let mut state = self.state();
+		match step {
+			MockStep::SetVolumes(volumes) => state.volumes = volumes,
+			MockStep::Plug(volume) => state.volumes.push(volume),
+			MockStep::Unplug(mount_point) => {
+				state.volumes.retain(|volume| volume.mount_point != mount_point);
+			}
+			MockStep::FailNextScan => state.fail_next_scan = true,
+			MockStep::Degrade(degraded_mode) => state.degraded_mode = degraded_mode,
+		}
+
+		notify(&mut state);
+	}
+
+	/// The volumes currently reported
+	pub fn volumes(&self) -> Vec<Volume> {
+		self.state().volumes.clone()
+	}
+
+	/// Mount points of the volumes unmounted through the platform, in order
+	pub fn unmounted(&self) -> Vec<PathBuf> {
+		self.state().unmounted.clone()
+	}
+
+	/// URL and username of the network shares mounted through the platform, in order
+	pub fn mounted_shares(&self) -> Vec<(String, Option<String>)> {
+		self.state().mounted_shares.clone()
+	}
+
+	/// Path and read-only flag of the disk images attached through the platform, in order
+	pub fn attached_images(&self) -> Vec<(PathBuf, bool)> {
+		self.state().attached_images.clone()
+	}
+
+	/// Mount points of the volumes ejected through the platform, in order
+	pub fn ejected(&self) -> Vec<PathBuf> {
+		self.state().ejected.clone()
+	}
+
+	fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+		self.state.lock().expect("mock volume provider lock poisoned")
+	}
+}
+
+/// Removes the volume mounted at `path` from the reported ones
+fn take_mounted(state: &mut MockState, path: &Path) -> Result<Volume, VolumeError> {
+	let Some(index) = state
+		.volumes
+		.iter()
+		.position(|volume| volume.mount_point == path)
+	else {
+		return Err(VolumeError::NotMounted(path.to_path_buf()));
+	};
+
+	let volume = state.volumes.remove(index);
+	notify(state);
+
+	Ok(volume)
+}
+
+/// Wakes the watchers up, forgetting the ones that stopped listening
+fn notify(state: &mut MockState) {
+	state.watchers.retain(|check_tx| {
+		!matches!(
+			check_tx.try_send(()),
+			Err(mpsc::error::TrySendError::Closed(_))
+		)
+	});
+}
+
+#[async_trait]
+impl VolumePlatform for MockVolumeProvider {
+	async fn get_volumes(&self) -> Result<Vec<Volume>, VolumeError> {
+		let mut state = self.state();
+		if state.fail_next_scan {
+			state.fail_next_scan = false;
+			return Err(VolumeError::CommandFailed {
+				command: "mock",
+				source: io::Error::other("scripted failure"),
+			});
+		}
+
+		Ok(state.volumes.clone())
+	}
+
+	async fn unmount_volume(&self, path: &Path) -> Result<(), VolumeError> {
+		let mut state = self.state();
+		let volume = take_mounted(&mut state, path)?;
+		state.unmounted.push(path.to_path_buf());
+		state.detached.push(volume);
+
+		Ok(())
+	}
+
+	/// Mounts back a volume unmounted earlier, by its name
+	async fn mount_volume(
+		&self,
+		device: &Path,
+		options: &MountOptions,
+	) -> Result<PathBuf, VolumeError> {
+		let mut state = self.state();
+		let Some(index) = state
+			.detached
+			.iter()
+			.position(|volume| Path::new(&volume.name) == device)
+		else {
+			return Err(VolumeError::MountFailed {
+				device: device.to_path_buf(),
+				reason: "no such device".to_string(),
+			});
+		};
+
+		let mut volume = state.detached.remove(index);
+		volume.read_only |= options.read_only;
+		let mount_point = volume.mount_point.clone();
+		state.volumes.push(volume);
+		notify(&mut state);
+
+		Ok(mount_point)
+	}
+
+	/// The volumes unmounted earlier, their device being their name
+	async fn unmounted_devices(&self) -> Result<Vec<UnmountedDevice>, VolumeError> {
+		Ok(self
+			.state()
+			.detached
+			.iter()
+			.map(|volume| UnmountedDevice {
+				device: PathBuf::from(&volume.name),
+				label: None,
+				file_system: volume.file_system.clone(),
+				total_bytes_capacity: volume.total_bytes_capacity,
+			})
+			.collect())
+	}
+
+	/// Reports the share as a network volume named after its URL
+	async fn mount_network_share(
+		&self,
+		share: &NetworkShare,
+		mount_point: &Path,
+		credentials: Option<&ShareCredentials>,
+	) -> Result<(), VolumeError> {
+		let mut state = self.state();
+		if state.unreachable_shares > 0 {
+			state.unreachable_shares -= 1;
+			return Err(VolumeError::MountFailed {
+				device: mount_point.to_path_buf(),
+				reason: "host is down".to_string(),
+			});
+		}
+
+		let volume = Self::volume(&share.url, mount_point, MountType::Network);
+		state.volumes.push(volume);
+		state.mounted_shares.push((
+			share.url.clone(),
+			credentials.map(|credentials| credentials.username.to_string()),
+		));
+		notify(&mut state);
+
+		Ok(())
+	}
+
+	/// Reports the image as a volume named after its file, mounted in `/mnt`
+	async fn attach_disk_image(
+		&self,
+		image: &Path,
+		read_only: bool,
+	) -> Result<AttachedImage, VolumeError> {
+		let name = image
+			.file_stem()
+			.map(|stem| stem.to_string_lossy().to_string())
+			.unwrap_or_default();
+		let mount_point = Path::new("/mnt").join(&name);
+
+		let mut state = self.state();
+		let mut volume = Self::volume(&name, mount_point.clone(), MountType::DiskImage);
+		volume.read_only = read_only;
+		state.volumes.push(volume);
+		state.attached_images.push((image.to_path_buf(), read_only));
+		notify(&mut state);
+
+		Ok(AttachedImage {
+			image: image.to_path_buf(),
+			device: image.to_path_buf(),
+			mount_point,
+		})
+	}
+
+	async fn detach_disk_image(&self, image: &AttachedImage) -> Result<(), VolumeError> {
+		take_mounted(&mut self.state(), &image.mount_point).map(|_| ())
+	}
+
+	async fn eject_volume(&self, path: &Path) -> Result<(), VolumeError> {
+		let mut state = self.state();
+		take_mounted(&mut state, path)?;
+		state.ejected.push(path.to_path_buf());
+
+		Ok(())
+	}
+
+	async fn watch(
+		&self,
+		backend: WatcherBackend,
+		check_tx: mpsc::Sender<()>,
+	) -> Result<Option<JoinHandle<()>>, VolumeError> {
+		let mut state = self.state();
+		if state.broken_watchers.contains(&backend) {
+			return Err(VolumeError::WatchInitFailed {
+				source: Box::new(io::Error::other("scripted failure")),
+			});
+		}
+
+		state.watchers.push(check_tx);
+		Ok(None)
+	}
+
+	fn watcher_backends(&self) -> Vec<WatcherBackend> {
+		self.state().watcher_backends.clone()
+	}
+
+	fn degraded_mode(&self) -> DegradedMode {
+		self.state().degraded_mode.clone()
+	}
+
+	fn capabilities(&self) -> DeviceCapabilities {
+		self.state().capabilities
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn plays_the_script_in_order() {
+		let provider = MockVolumeProvider::new([MockVolumeProvider::volume(
+			"System",
+			"/",
+			MountType::System,
+		)])
+		.with_script([
+			MockStep::Plug(MockVolumeProvider::volume(
+				"USB",
+				"/media/usb",
+				MountType::External,
+			)),
+			MockStep::FailNextScan,
+			MockStep::Unplug("/media/usb".into()),
+		]);
+
+		let (check_tx, mut check_rx) = mpsc::channel(1);
+		provider
+			.watch(WatcherBackend::Native, check_tx)
+			.await
+			.unwrap();
+
+		assert!(provider.advance());
+		assert!(check_rx.try_recv().is_ok());
+		assert_eq!(provider.get_volumes().await.unwrap().len(), 2);
+
+		assert!(provider.advance());
+		assert!(provider.get_volumes().await.is_err());
+		assert_eq!(provider.get_volumes().await.unwrap().len(), 2);
+
+		assert!(provider.advance());
+		assert_eq!(provider.get_volumes().await.unwrap().len(), 1);
+
+		assert!(!provider.advance());
+	}
+
+	#[tokio::test]
+	async fn unmount_removes_the_volume() {
+		let provider = MockVolumeProvider::new([MockVolumeProvider::volume(
+			"USB",
+			"/media/usb",
+			MountType::External,
+		)]);
+
+		provider
+			.unmount_volume(Path::new("/media/usb"))
+			.await
+			.unwrap();
+
+		assert!(provider.volumes().is_empty());
+		assert_eq!(provider.unmounted(), vec![PathBuf::from("/media/usb")]);
+		assert!(matches!(
+			provider.unmount_volume(Path::new("/media/usb")).await,
+			Err(VolumeError::NotMounted(_))
+		));
+	}
+
+	#[tokio::test]
+	async fn unmounted_volumes_can_be_mounted_back_until_ejected() {
+		let provider = MockVolumeProvider::new([MockVolumeProvider::volume(
+			"/dev/sdb1",
+			"/media/usb",
+			MountType::External,
+		)]);
+
+		provider
+			.unmount_volume(Path::new("/media/usb"))
+			.await
+			.unwrap();
+		assert_eq!(
+			provider.unmounted_devices().await.unwrap()[0].device,
+			PathBuf::from("/dev/sdb1")
+		);
+		assert_eq!(
+			provider
+				.mount_volume(Path::new("/dev/sdb1"), &MountOptions::default())
+				.await
+				.unwrap(),
+			PathBuf::from("/media/usb")
+		);
+		assert!(provider.unmounted_devices().await.unwrap().is_empty());
+		assert_eq!(provider.volumes().len(), 1);
+
+		provider
+			.eject_volume(Path::new("/media/usb"))
+			.await
+			.unwrap();
+		assert!(provider.volumes().is_empty());
+		assert_eq!(provider.ejected(), vec![PathBuf::from("/media/usb")]);
+		assert!(matches!(
+			provider
+				.mount_volume(Path::new("/dev/sdb1"), &MountOptions::default())
+				.await,
+			Err(VolumeError::MountFailed { .. })
+		));
+	}
+}
@@ -5,31 +5,104 @@
 //! Volumes use a fingerprint to identify them as they sometimes are not persisted in the database
 //!
 pub(crate) mod actor;
+pub(crate) mod breakdown;
+mod cache;
+mod command;
+#[cfg(all(unix, not(any(target_os = "ios", target_os = "android"))))]
+pub(crate) mod control;
+mod elevation;
 mod error;
+mod forecast;
+mod fs_snapshot;
+mod health;
+mod history;
+mod image;
+pub(crate) mod maintenance;
+#[cfg(any(test, feature = "test-utils"))]
+mod mock;
+mod monitor;
+#[cfg(target_os = "linux")]
+pub mod mountinfo;
+mod network;
 mod os;
+mod plan;
+mod platform;
+pub(crate) mod quarantine;
+pub(crate) mod release;
+mod retry;
+pub(crate) mod settings;
+mod snapshot;
 mod speed;
 mod state;
+#[cfg(target_os = "linux")]
+mod topology;
+mod tracked;
 mod types;
 mod volumes;
 mod watcher;
 use crate::library::LibraryManagerEvent;
+use crate::node::{CoreConfig, EventBus, FeatureFlags, SecretStore};
 use crate::util::mpscrr;
+use std::sync::Arc;
+use tokio::sync::watch;
 
 pub use {
 	actor::VolumeManagerActor,
+	breakdown::{
+		space_breakdown, CategoryUsage, DirectoryUsage, SpaceBreakdown, SpaceCategory, SpaceUsage,
+		LARGE_CHANGE,
+	},
+	cache::CachedPlatform,
+	elevation::{ElevatedOperation, Elevation, ElevationBackend, ElevationScope},
 	error::VolumeError,
+	forecast::{capacity_forecast, CapacityForecast, FORECAST_WINDOW},
+	fs_snapshot::{supports_snapshots, FileSystemSnapshot},
+	history::{
+		volume_history, HistoryRange, VolumeHistoryEntry, VolumeHistoryKind,
+		MAX_RECORDS_PER_VOLUME, RETENTION, USAGE_SAMPLE_INTERVAL,
+	},
+	image::{AttachedImage, DiskImageFormat},
+	network::{
+		DiscoveredShare, NetworkShare, ShareCredentials, ShareProtocol, ShareServer,
+		DISCOVERY_TIMEOUT,
+	},
+	platform::{system_platform, VolumePlatform},
+	snapshot::{VolumeSnapshot, SNAPSHOT_VERSION},
 	state::VolumeManagerState,
+	tracked::{remote_volumes, RemoteVolume},
 	types::{
-		DiskType, FileSystem, MountType, Volume, VolumeEvent, VolumeFingerprint, VolumeOptions,
+		BusyProcess, ByteSize, DegradedMode, DeviceCapabilities, DiskType, EncryptionKind,
+		FileSystem, HealthStatus, LowSpaceThreshold, MemberState, MissingVolumeData, MountOptions,
+		MountOwner, MountType, OperationPlan, PlanStep, PoolHealth, PoolKind, PoolMember,
+		PredictedOutcome, ScanProgress, UnitPolicy, UnmountedDevice, Volume, VolumeErrorCode,
+		VolumeErrorInfo, VolumeEvent, VolumeFingerprint, VolumeHealth, VolumeOperation,
+		VolumeOptions, VolumeSettings, VolumeTopology,
 	},
 	volumes::Volumes,
+	watcher::{diff_volumes, WatcherBackend, WatcherStatus},
 };
 
+#[cfg(all(unix, not(any(target_os = "ios", target_os = "android"))))]
+pub use control::{socket_path, ControlRequest, ControlResponse, CONTROL_SOCKET};
+
+#[cfg(any(test, feature = "test-utils"))]
+pub use mock::{MockStep, MockVolumeProvider};
+
 #[derive(Clone)]
 pub struct VolumeManagerContext {
 	// Used for device identification
 	pub device_id: Vec<u8>,
 	pub library_event_tx: mpscrr::Receiver<LibraryManagerEvent, ()>,
+	/// Follows the core config, for the watcher debounce and maintenance interval
+	pub config: watch::Receiver<CoreConfig>,
+	/// Where volumes are read from, the OS outside of tests
+	pub platform: Arc<dyn VolumePlatform>,
+	/// Volume events are published on the node's bus
+	pub events: Arc<EventBus>,
+	/// Holds the credentials of the network shares
+	pub secrets: SecretStore,
+	/// Gates auto-tracking
+	pub feature_flags: Arc<FeatureFlags>,
 }
 
 // Extension trait for Volume operations that don't require actor communication
@@ -47,15 +120,29 @@ impl VolumeExt for Volume {
 	}
 
 	fn has_space(&self, required_bytes: u64) -> bool {
-		self.total_bytes_available >= required_bytes
+		self.total_bytes_available.0 >= required_bytes
 	}
 }
 
-// Re-export platform-specific volume detection
+#[cfg(target_os = "freebsd")]
+pub use os::freebsd::FreeBsdPlatform;
+#[cfg(not(any(
+	target_os = "linux",
+	target_os = "macos",
+	target_os = "windows",
+	target_os = "freebsd",
+	target_os = "ios",
+	target_os = "android"
+)))]
+pub use os::generic::SysinfoPlatform;
 #[cfg(target_os = "linux")]
-pub use os::linux::get_volumes;
+pub use os::linux::{LinuxBackend, LinuxPlatform};
+#[cfg(target_os = "macos")]
+pub use os::macos::MacOsPlatform;
+#[cfg(target_os = "windows")]
+pub use os::windows::WindowsPlatform;
 #[cfg(any(target_os = "ios", target_os = "android"))]
-pub use os::mobile::get_volumes;
+pub use os::mobile::MobilePlatform;
 
 // Internal utilities
 pub(crate) mod util {
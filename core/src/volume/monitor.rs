@@ -0,0 +1,204 @@
+//! Low disk space warnings for the tracked volumes.
+//!
+//! The watcher only sees the free space change along with the mounts, so the monitor reads it
+//! again on its own interval. The refreshed volumes go out as the watcher's updates would, the
+//! actor drops the ones the watcher reports again before recording them. Then the ones below
+//! the configured [`LowSpaceThreshold`] are warned about, once until they're back above it.
+//! Read-only volumes can't fill up and aren't warned about.
+
+use super::{
+	error::VolumeError,
+	platform::VolumePlatform,
+	state::VolumeManagerState,
+	types::{LowSpaceThreshold, Volume, VolumeEvent, VolumeFingerprint},
+	watcher::diff_volumes,
+};
+use crate::node::{CoreConfig, Publisher};
+
+use sd_core_sync::DevicePubId;
+
+use std::{
+	collections::{HashMap, HashSet},
+	sync::Arc,
+};
+
+use tokio::{
+	sync::{watch, RwLock},
+	time::sleep,
+};
+use tracing::{info, warn};
+
+pub(crate) struct VolumeMonitor {
+	device_id: DevicePubId,
+	state: Arc<RwLock<VolumeManagerState>>,
+	platform: Arc<dyn VolumePlatform>,
+	event_tx: Publisher<VolumeEvent>,
+	config: watch::Receiver<CoreConfig>,
+	warnings: LowSpaceWarnings,
+}
+
+impl VolumeMonitor {
+	pub fn new(
+		device_id: DevicePubId,
+		state: Arc<RwLock<VolumeManagerState>>,
+		platform: Arc<dyn VolumePlatform>,
+		event_tx: Publisher<VolumeEvent>,
+		config: watch::Receiver<CoreConfig>,
+	) -> Self {
+		Self {
+			device_id,
+			state,
+			platform,
+			event_tx,
+			config,
+			warnings: LowSpaceWarnings::default(),
+		}
+	}
+
+	pub async fn run(mut self) {
+		loop {
+			let interval = self.config.borrow().volumes.low_space_interval();
+			sleep(interval).await;
+
+			if let Err(e) = self.check().await {
+				warn!(
+					?e,
+					"Failed to refresh the free space of the tracked volumes;"
+				);
+			}
+		}
+	}
+
+	async fn check(&mut self) -> Result<(), VolumeError> {
+		let Some(threshold) = self.config.borrow().volumes.low_space_threshold else {
+			return Ok(());
+		};
+
+		let tracked = {
+			let state = self.state.read().await;
+			let registry = state.registry.read().await;
+			registry
+				.volumes()
+				.filter(|(_, volume)| volume.is_mounted && volume.is_volume_tracked())
+				.map(|(_, volume)| volume.clone())
+				.collect::<Vec<_>>()
+		};
+		if tracked.is_empty() {
+			self.warnings.0.clear();
+			return Ok(());
+		}
+
+		let refreshed = self.refresh(&tracked).await?;
+		// The ones unmounted or untracked in the meantime are warned about again when they're back
+		self.warnings
+			.0
+			.retain(|fingerprint| refreshed.contains_key(fingerprint));
+		for (fingerprint, volume) in refreshed {
+			if self.warnings.crossed(&fingerprint, &volume, threshold) {
+				info!(name = %volume.name, ?threshold, "Volume is low on space;");
+				self.event_tx
+					.send(VolumeEvent::LowSpace { volume, threshold });
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Reads the tracked volumes again, publishing the changes. The ones the platform doesn't
+	/// report anymore are left to the watcher.
+	async fn refresh(
+		&self,
+		tracked: &[Volume],
+	) -> Result<HashMap<VolumeFingerprint, Volume>, VolumeError> {
+		let device_id = self.device_id.to_db();
+		let fingerprint = |volume: &Volume| {
+			volume
+				.fingerprint
+				.clone()
+				.unwrap_or_else(|| VolumeFingerprint::new(&device_id, volume))
+		};
+
+		let mut volumes = tracked
+			.iter()
+			.map(|volume| (fingerprint(volume), volume.clone()))
+			.collect::<HashMap<_, _>>();
+
+		let discovered = self.platform.get_volumes().await?;
+		for event in diff_volumes(&self.device_id, tracked, &discovered) {
+			match &event {
+				VolumeEvent::VolumeAdded(_) | VolumeEvent::VolumeRemoved(_) => continue,
+				VolumeEvent::VolumeUpdated { new, .. } => {
+					volumes.insert(fingerprint(new), new.clone());
+				}
+				_ => {}
+			}
+			self.event_tx.send(event);
+		}
+
+		Ok(volumes)
+	}
+}
+
+/// The volumes already warned about, so the warning goes out once each time one drops below the
+/// threshold
+#[derive(Debug, Default)]
+struct LowSpaceWarnings(HashSet<VolumeFingerprint>);
+
+impl LowSpaceWarnings {
+	/// Whether the volume just dropped below the threshold
+	fn crossed(
+		&mut self,
+		fingerprint: &VolumeFingerprint,
+		volume: &Volume,
+		threshold: LowSpaceThreshold,
+	) -> bool {
+		let is_low = !volume.read_only
+			&& threshold.is_crossed(volume.total_bytes_available, volume.total_bytes_capacity);
+
+		if is_low {
+			self.0.insert(fingerprint.clone())
+		} else {
+			self.0.remove(fingerprint);
+			false
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::volume::{ByteSize, DiskType, FileSystem, MountType};
+
+	use std::path::PathBuf;
+
+	fn volume(available: u64, read_only: bool) -> Volume {
+		Volume::new(
+			"USB".to_string(),
+			MountType::External,
+			PathBuf::from("/media/usb"),
+			vec![],
+			DiskType::SSD,
+			FileSystem::EXT4,
+			1000,
+			available,
+			read_only,
+		)
+	}
+
+	#[test]
+	fn warns_once_each_time_a_volume_drops_below_the_threshold() {
+		let mut warnings = LowSpaceWarnings::default();
+		let fingerprint = VolumeFingerprint(vec![1]);
+		let threshold = LowSpaceThreshold::Bytes(ByteSize(100));
+
+		assert!(!warnings.crossed(&fingerprint, &volume(500, false), threshold));
+		assert!(warnings.crossed(&fingerprint, &volume(50, false), threshold));
+		assert!(!warnings.crossed(&fingerprint, &volume(40, false), threshold));
+		assert!(!warnings.crossed(&fingerprint, &volume(200, false), threshold));
+		assert!(warnings.crossed(&fingerprint, &volume(10, false), threshold));
+
+		let read_only = VolumeFingerprint(vec![2]);
+		assert!(!warnings.crossed(&read_only, &volume(0, true), threshold));
+	}
+}
@@ -0,0 +1,394 @@
+//! Parser for the mount table of `/proc/self/mountinfo`, see proc(5).
+//!
+//! A line reads `36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue`:
+//! mount id, parent id, device, root of the mount, mount point, mount options, any number of
+//! optional fields closed by a lone `-`, then the file system, the mount source and the super block
+//! options. The kernel escapes spaces, tabs, newlines and backslashes as octal (`\040`), every other
+//! byte is written as it is and the paths don't have to be UTF-8.
+//!
+//! Only std is used, so the fuzz target in `core/fuzz` can build this file on its own.
+
+use std::{
+	ffi::OsString,
+	fmt,
+	os::unix::ffi::{OsStrExt, OsStringExt},
+	path::PathBuf,
+};
+
+/// Fields up to the optional ones, which may be followed by the separator right away
+const FIXED_FIELDS: usize = 6;
+
+/// A line of the mount table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountEntry {
+	pub mount_id: u32,
+	pub parent_id: u32,
+	/// Major and minor number of the device, `0:x` for the file systems without one
+	pub device: (u32, u32),
+	/// Directory of the file system mounted at the mount point, `/` unless bind mounted
+	pub root: PathBuf,
+	pub mount_point: PathBuf,
+	/// Options of this mount
+	pub options: Vec<String>,
+	/// Propagation tags, like `shared:1` or `master:2`
+	pub optional_fields: Vec<String>,
+	pub file_system: String,
+	/// The device, or what the file system uses in place of one, like `tmpfs` or `server:/export`
+	pub source: OsString,
+	/// Options of the file system, shared by all its mounts
+	pub super_options: Vec<String>,
+}
+
+/// Why a line of the mount table couldn't be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+	/// The line ended before this field
+	MissingField(&'static str),
+	/// This field is empty or doesn't hold what it should, like a mount id that isn't a number
+	InvalidField(&'static str),
+	/// No lone `-` after the optional fields
+	MissingSeparator,
+}
+
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ParseError::MissingField(field) => write!(f, "missing {field}"),
+			ParseError::InvalidField(field) => write!(f, "invalid {field}"),
+			ParseError::MissingSeparator => f.write_str("missing the separator of optional fields"),
+		}
+	}
+}
+
+impl std::error::Error for ParseError {}
+
+impl MountEntry {
+	/// Whether this mount, or the file system under it, is read-only
+	pub fn is_read_only(&self) -> bool {
+		self.options
+			.iter()
+			.chain(&self.super_options)
+			.any(|option| option == "ro")
+	}
+
+	/// The line of the mount table for this entry, escaped as the kernel does
+	pub fn to_line(&self) -> Vec<u8> {
+		let options = |options: &[String]| {
+			let options = options
+				.iter()
+				.map(|option| escape(option.as_bytes(), b","))
+				.collect::<Vec<_>>();
+			options.join(&b',')
+		};
+
+		let mut fields = vec![
+			self.mount_id.to_string().into_bytes(),
+			self.parent_id.to_string().into_bytes(),
+			format!("{}:{}", self.device.0, self.device.1).into_bytes(),
+			escape(self.root.as_os_str().as_bytes(), b""),
+			escape(self.mount_point.as_os_str().as_bytes(), b""),
+			options(&self.options),
+		];
+		fields.extend(
+			self.optional_fields
+				.iter()
+				.map(|field| field.as_bytes().to_vec()),
+		);
+		fields.extend([
+			b"-".to_vec(),
+			escape(self.file_system.as_bytes(), b""),
+			escape(self.source.as_bytes(), b""),
+			options(&self.super_options),
+		]);
+
+		fields.join(&b' ')
+	}
+}
+
+/// Parses each line of the mount table, empty lines are skipped
+pub fn entries(contents: &[u8]) -> impl Iterator<Item = Result<MountEntry, ParseError>> + '_ {
+	contents
+		.split(|byte| *byte == b'\n')
+		.filter(|line| !line.is_empty())
+		.map(parse_line)
+}
+
+/// The entries of the mount table, without the lines that couldn't be read
+pub fn parse(contents: &[u8]) -> Vec<MountEntry> {
+	entries(contents).filter_map(Result::ok).collect()
+}
+
+pub fn parse_line(line: &[u8]) -> Result<MountEntry, ParseError> {
+	let fields = line.split(|byte| *byte == b' ').collect::<Vec<_>>();
+
+	let field = |index: usize, name: &'static str| match fields.get(index) {
+		None => Err(ParseError::MissingField(name)),
+		// Two spaces in a row, the kernel never writes an empty field
+		Some(field) if field.is_empty() => Err(ParseError::InvalidField(name)),
+		Some(field) => Ok(*field),
+	};
+
+	let mount_id = number(field(0, "mount id")?).ok_or(ParseError::InvalidField("mount id"))?;
+	let parent_id = number(field(1, "parent id")?).ok_or(ParseError::InvalidField("parent id"))?;
+	let device = field(2, "device")?;
+	let device = device
+		.iter()
+		.position(|byte| *byte == b':')
+		.and_then(|colon| Some((number(&device[..colon])?, number(&device[colon + 1..])?)))
+		.ok_or(ParseError::InvalidField("device"))?;
+	let root = PathBuf::from(OsString::from_vec(unescape(field(3, "root")?)));
+	let mount_point = PathBuf::from(OsString::from_vec(unescape(field(4, "mount point")?)));
+	let options = split_options(field(5, "mount options")?);
+
+	// The separator is a field of its own, looked for past the fixed fields as a path may be `-` too
+	let separator = fields
+		.iter()
+		.skip(FIXED_FIELDS)
+		.position(|field| *field == b"-")
+		.map(|position| position + FIXED_FIELDS)
+		.ok_or(ParseError::MissingSeparator)?;
+
+	let optional_fields = fields[FIXED_FIELDS..separator]
+		.iter()
+		.map(|field| {
+			if field.is_empty() {
+				Err(ParseError::InvalidField("optional fields"))
+			} else {
+				Ok(String::from_utf8_lossy(field).into_owned())
+			}
+		})
+		.collect::<Result<_, _>>()?;
+
+	// Newer kernels may add fields at the end, they are ignored
+	let file_system =
+		String::from_utf8_lossy(&unescape(field(separator + 1, "file system")?)).into_owned();
+	let source = OsString::from_vec(unescape(field(separator + 2, "mount source")?));
+	let super_options = split_options(field(separator + 3, "super options")?);
+
+	Ok(MountEntry {
+		mount_id,
+		parent_id,
+		device,
+		root,
+		mount_point,
+		options,
+		optional_fields,
+		file_system,
+		source,
+		super_options,
+	})
+}
+
+/// Only plain digits, `str::parse` would also take a sign
+fn number(field: &[u8]) -> Option<u32> {
+	if field.is_empty() || !field.iter().all(u8::is_ascii_digit) {
+		return None;
+	}
+
+	std::str::from_utf8(field).ok()?.parse().ok()
+}
+
+fn split_options(field: &[u8]) -> Vec<String> {
+	field
+		.split(|byte| *byte == b',')
+		.map(|option| String::from_utf8_lossy(&unescape(option)).into_owned())
+		.collect()
+}
+
+/// Turns the octal escapes back into bytes, a backslash that doesn't start one is kept
+fn unescape(field: &[u8]) -> Vec<u8> {
+	let mut unescaped = Vec::with_capacity(field.len());
+	let mut rest = field;
+
+	while let Some((&byte, tail)) = rest.split_first() {
+		let escaped = match tail {
+			[a @ b'0'..=b'3', b @ b'0'..=b'7', c @ b'0'..=b'7', ..] if byte == b'\\' => {
+				Some(((a - b'0') << 6) | ((b - b'0') << 3) | (c - b'0'))
+			}
+			_ => None,
+		};
+
+		match escaped {
+			Some(escaped) => {
+				unescaped.push(escaped);
+				rest = &tail[3..];
+			}
+			None => {
+				unescaped.push(byte);
+				rest = tail;
+			}
+		}
+	}
+
+	unescaped
+}
+
+/// Escapes what the kernel does, and `extra`, as octal
+fn escape(field: &[u8], extra: &[u8]) -> Vec<u8> {
+	let mut escaped = Vec::with_capacity(field.len());
+	for &byte in field {
+		if matches!(byte, b' ' | b'\t' | b'\n' | b'\\') || extra.contains(&byte) {
+			escaped.extend(format!("\\{byte:03o}").into_bytes());
+		} else {
+			escaped.push(byte);
+		}
+	}
+
+	escaped
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::path::Path;
+
+	fn line(line: &str) -> Result<MountEntry, ParseError> {
+		parse_line(line.as_bytes())
+	}
+
+	#[test]
+	fn parses_every_field() {
+		let entry =
+			line("36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue")
+				.unwrap();
+
+		assert_eq!(
+			entry,
+			MountEntry {
+				mount_id: 36,
+				parent_id: 35,
+				device: (98, 0),
+				root: PathBuf::from("/mnt1"),
+				mount_point: PathBuf::from("/mnt2"),
+				options: vec!["rw".to_string(), "noatime".to_string()],
+				optional_fields: vec!["master:1".to_string()],
+				file_system: "ext3".to_string(),
+				source: OsString::from("/dev/root"),
+				super_options: vec!["rw".to_string(), "errors=continue".to_string()],
+			}
+		);
+		assert!(!entry.is_read_only());
+	}
+
+	#[test]
+	fn takes_any_number_of_optional_fields() {
+		let none = line("22 1 8:2 / / rw - ext4 /dev/sda2 rw").unwrap();
+		assert!(none.optional_fields.is_empty());
+
+		let many =
+			line("22 1 8:2 / / rw shared:1 master:2 propagate_from:3 - ext4 /dev/sda2 rw").unwrap();
+		assert_eq!(
+			many.optional_fields,
+			["shared:1", "master:2", "propagate_from:3"]
+		);
+	}
+
+	#[test]
+	fn unescapes_the_paths() {
+		let entry =
+			line(r"61 22 8:17 / /media/My\040Drive\011\134x\055 ro - vfat /dev/sdb1 rw").unwrap();
+
+		assert_eq!(entry.mount_point, Path::new("/media/My Drive\t\\x-"));
+		assert!(entry.is_read_only());
+	}
+
+	#[test]
+	fn keeps_backslashes_that_are_not_escapes() {
+		let entry = line(r"61 22 8:17 / /a\b\12\400\+12\ rw - vfat /dev/sdb1 rw").unwrap();
+
+		assert_eq!(entry.mount_point, Path::new(r"/a\b\12\400\+12\"));
+	}
+
+	#[test]
+	fn keeps_paths_that_are_not_utf8() {
+		let entry = parse_line(b"61 22 8:17 / /media/cl\xe9 rw - vfat /dev/sdb1 rw").unwrap();
+
+		assert_eq!(entry.mount_point.as_os_str().as_bytes(), b"/media/cl\xe9");
+	}
+
+	#[test]
+	fn a_dash_in_a_path_is_not_the_separator() {
+		// Only a lone `-` is the separator, the kernel escapes the rest
+		let entry = line("61 22 8:17 / /mnt/a-b - - ext4 /dev/sdb1 rw").unwrap();
+
+		assert_eq!(entry.mount_point, Path::new("/mnt/a-b"));
+		assert_eq!(entry.options, ["-"]);
+		assert_eq!(entry.file_system, "ext4");
+	}
+
+	#[test]
+	fn refuses_malformed_lines() {
+		for (malformed, error) in [
+			("", ParseError::InvalidField("mount id")),
+			("22 1 8:2 / /", ParseError::MissingField("mount options")),
+			("22 1 8:2 / / rw shared:1", ParseError::MissingSeparator),
+			("22 1 8:2 / / rw -", ParseError::MissingField("file system")),
+			(
+				"22 1 8:2 / / rw - ext4 /dev/sda2",
+				ParseError::MissingField("super options"),
+			),
+			(
+				"x 1 8:2 / / rw - ext4 /dev/sda2 rw",
+				ParseError::InvalidField("mount id"),
+			),
+			(
+				"+2 1 8:2 / / rw - ext4 /dev/sda2 rw",
+				ParseError::InvalidField("mount id"),
+			),
+			(
+				"22 1 82 / / rw - ext4 /dev/sda2 rw",
+				ParseError::InvalidField("device"),
+			),
+			(
+				"22 1 8: / / rw - ext4 /dev/sda2 rw",
+				ParseError::InvalidField("device"),
+			),
+			(
+				"22 1 8:2 /  rw - ext4 /dev/sda2 rw",
+				ParseError::InvalidField("mount point"),
+			),
+			(
+				"22 1 8:2 / / rw  - ext4 /dev/sda2 rw",
+				ParseError::InvalidField("optional fields"),
+			),
+			(
+				"99999999999 1 8:2 / / rw - ext4 /dev/sda2 rw",
+				ParseError::InvalidField("mount id"),
+			),
+		] {
+			assert_eq!(line(malformed), Err(error), "{malformed:?}");
+		}
+	}
+
+	#[test]
+	fn skips_the_lines_it_cannot_read() {
+		let mounts = parse(
+			b"22 1 8:2 / / rw,relatime shared:1 - ext4 /dev/sda2 rw\n\
+			  \n\
+			  garbage\n\
+			  35 22 0:31 / /proc rw,nosuid - proc proc rw\n",
+		);
+
+		assert_eq!(mounts.len(), 2);
+		assert_eq!(mounts[1].file_system, "proc");
+	}
+
+	#[test]
+	fn escaped_lines_parse_back_to_the_same_entry() {
+		let entry = MountEntry {
+			mount_id: 7,
+			parent_id: 1,
+			device: (0, 42),
+			root: PathBuf::from("/sub dir"),
+			mount_point: PathBuf::from(OsString::from_vec(b"/mnt/\\tab\t\n-\xff".to_vec())),
+			options: vec!["rw".to_string(), "context=a,b".to_string()],
+			optional_fields: vec![],
+			file_system: "fuse.sshfs".to_string(),
+			source: OsString::from("user@host:/my files"),
+			super_options: vec!["ro".to_string()],
+		};
+
+		assert_eq!(parse_line(&entry.to_line()), Ok(entry));
+	}
+}
@@ -0,0 +1,147 @@
+//! The volume notifications of Windows, broadcast as `WM_DEVICECHANGE` to the top-level windows
+
+use super::common::{request_check, WATCH_WAKEUP};
+use super::*;
+
+use std::cell::RefCell;
+
+use tokio::sync::oneshot;
+use tracing::debug;
+// The crate, the `windows` module of `os` is in scope too
+use ::windows::{
+	core::w,
+	Win32::{
+		Foundation::{HWND, LPARAM, LRESULT, WPARAM},
+		System::LibraryLoader::GetModuleHandleW,
+		UI::WindowsAndMessaging::{
+			CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW,
+			MsgWaitForMultipleObjects, PeekMessageW, RegisterClassW, TranslateMessage,
+			DBT_DEVICEARRIVAL, DBT_DEVICEREMOVECOMPLETE, DBT_DEVTYP_VOLUME, DEV_BROADCAST_HDR,
+			HMENU, MSG, PM_REMOVE, QS_ALLINPUT, WINDOW_EX_STYLE, WINDOW_STYLE, WM_DEVICECHANGE,
+			WNDCLASSW,
+		},
+	},
+};
+
+thread_local! {
+	/// Where the window procedure sends the checks, it runs on the thread of the loop
+	static CHECK_TX: RefCell<Option<mpsc::Sender<()>>> = const { RefCell::new(None) };
+}
+
+/// Asks for a check whenever a volume arrives or goes away, drive letters and mapped network
+/// drives included. The notifications go to a hidden window, as the message-only ones don't
+/// get broadcasts, pumped until the watcher stops listening.
+pub async fn watch(check_tx: mpsc::Sender<()>) -> Result<Option<JoinHandle<()>>, VolumeError> {
+	let (started_tx, started_rx) = oneshot::channel();
+
+	let thread = task::spawn_blocking(move || {
+		let window = match create_window() {
+			Ok(window) => window,
+			Err(e) => {
+				let _ = started_tx.send(Err(VolumeError::WatchInitFailed {
+					source: Box::new(e),
+				}));
+				return;
+			}
+		};
+		CHECK_TX.with(|tx| *tx.borrow_mut() = Some(check_tx.clone()));
+		let _ = started_tx.send(Ok(()));
+
+		while !check_tx.is_closed() {
+			// SAFETY: no handles to wait on, only the messages of this thread
+			unsafe {
+				MsgWaitForMultipleObjects(
+					None,
+					false,
+					WATCH_WAKEUP.as_millis() as u32,
+					QS_ALLINPUT,
+				);
+			}
+
+			let mut message = MSG::default();
+			// SAFETY: `message` is a valid MSG to fill, for any window of this thread
+			while unsafe { PeekMessageW(&mut message, HWND::default(), 0, 0, PM_REMOVE) }.as_bool()
+			{
+				// SAFETY: a message just received by this thread
+				unsafe {
+					let _ = TranslateMessage(&message);
+					DispatchMessageW(&message);
+				}
+			}
+		}
+
+		// SAFETY: the window was created on this thread, nothing uses it after
+		unsafe {
+			let _ = DestroyWindow(window);
+		}
+		CHECK_TX.with(|tx| tx.borrow_mut().take());
+		debug!("Stopped listening to device changes;");
+	});
+
+	started_rx.await.unwrap_or_else(|_| {
+		Err(VolumeError::WatchInitFailed {
+			source: "the device change thread stopped before starting".into(),
+		})
+	})?;
+
+	Ok(Some(thread))
+}
+
+/// A window that is never shown, owned by the calling thread
+fn create_window() -> ::windows::core::Result<HWND> {
+	let class = w!("SpacedriveVolumeWatcher");
+
+	// SAFETY: plain calls with a static class name and window procedure
+	unsafe {
+		let instance = GetModuleHandleW(None)?;
+		// Fails once the class is registered, by an earlier watcher, creating the window
+		// tells whether it's usable
+		RegisterClassW(&WNDCLASSW {
+			lpfnWndProc: Some(window_procedure),
+			hInstance: instance.into(),
+			lpszClassName: class,
+			..Default::default()
+		});
+
+		CreateWindowExW(
+			WINDOW_EX_STYLE::default(),
+			class,
+			w!(""),
+			WINDOW_STYLE::default(),
+			0,
+			0,
+			0,
+			0,
+			HWND::default(),
+			HMENU::default(),
+			instance,
+			None,
+		)
+	}
+}
+
+extern "system" fn window_procedure(
+	window: HWND,
+	message: u32,
+	wparam: WPARAM,
+	lparam: LPARAM,
+) -> LRESULT {
+	if message == WM_DEVICECHANGE && is_volume_change(wparam, lparam) {
+		CHECK_TX.with(|tx| {
+			if let Some(check_tx) = &*tx.borrow() {
+				request_check(check_tx);
+			}
+		});
+	}
+
+	// SAFETY: the message is passed on as it was received
+	unsafe { DefWindowProcW(window, message, wparam, lparam) }
+}
+
+fn is_volume_change(event: WPARAM, header: LPARAM) -> bool {
+	matches!(event.0 as u32, DBT_DEVICEARRIVAL | DBT_DEVICEREMOVECOMPLETE)
+		&& header.0 != 0
+		// SAFETY: for these events the parameter points to the header of the device
+		&& unsafe { (*(header.0 as *const DEV_BROADCAST_HDR)).dbch_devicetype }
+			== DBT_DEVTYP_VOLUME
+}
@@ -0,0 +1,138 @@
+//! The disk events of macOS, from the DiskArbitration framework
+
+use super::common::{request_check, WATCH_WAKEUP};
+use super::*;
+
+use std::{ffi::c_void, ptr};
+
+use core_foundation::{
+	array::CFArrayRef,
+	base::{kCFAllocatorDefault, CFAllocatorRef, CFRelease, TCFType},
+	dictionary::CFDictionaryRef,
+	runloop::{kCFRunLoopDefaultMode, CFRunLoop, CFRunLoopRef},
+	string::CFStringRef,
+};
+use tokio::sync::oneshot;
+use tracing::debug;
+
+#[repr(C)]
+struct OpaqueSession([u8; 0]);
+
+type DASessionRef = *mut OpaqueSession;
+type DADiskRef = *const c_void;
+type DiskCallback = extern "C" fn(disk: DADiskRef, context: *mut c_void);
+type DescriptionCallback = extern "C" fn(disk: DADiskRef, keys: CFArrayRef, context: *mut c_void);
+
+#[link(name = "DiskArbitration", kind = "framework")]
+extern "C" {
+	fn DASessionCreate(allocator: CFAllocatorRef) -> DASessionRef;
+	fn DASessionScheduleWithRunLoop(
+		session: DASessionRef,
+		run_loop: CFRunLoopRef,
+		mode: CFStringRef,
+	);
+	fn DASessionUnscheduleFromRunLoop(
+		session: DASessionRef,
+		run_loop: CFRunLoopRef,
+		mode: CFStringRef,
+	);
+	fn DARegisterDiskAppearedCallback(
+		session: DASessionRef,
+		matching: CFDictionaryRef,
+		callback: DiskCallback,
+		context: *mut c_void,
+	);
+	fn DARegisterDiskDisappearedCallback(
+		session: DASessionRef,
+		matching: CFDictionaryRef,
+		callback: DiskCallback,
+		context: *mut c_void,
+	);
+	fn DARegisterDiskDescriptionChangedCallback(
+		session: DASessionRef,
+		matching: CFDictionaryRef,
+		watch: CFArrayRef,
+		callback: DescriptionCallback,
+		context: *mut c_void,
+	);
+}
+
+/// Asks for a check whenever a disk appears, disappears or has its description changed, which
+/// covers renames, mounts and unmounts. The session runs on a loop of its own until the
+/// watcher stops listening.
+pub async fn watch(check_tx: mpsc::Sender<()>) -> Result<Option<JoinHandle<()>>, VolumeError> {
+	let (started_tx, started_rx) = oneshot::channel();
+
+	let thread = task::spawn_blocking(move || {
+		// SAFETY: the default allocator, the session is released once the loop stops
+		let session = unsafe { DASessionCreate(kCFAllocatorDefault) };
+		if session.is_null() {
+			let _ = started_tx.send(Err(VolumeError::WatchInitFailed {
+				source: "no DiskArbitration session could be created".into(),
+			}));
+			return;
+		}
+
+		// Boxed for an address that stays put while the callbacks hold it
+		let context = Box::into_raw(Box::new(check_tx));
+		let run_loop = CFRunLoop::get_current();
+		// SAFETY: `session` is valid, null matching dictionaries and watched keys stand for
+		// every disk and key. `context` is freed only once the session is off the loop.
+		unsafe {
+			DARegisterDiskAppearedCallback(session, ptr::null(), on_disk, context.cast());
+			DARegisterDiskDisappearedCallback(session, ptr::null(), on_disk, context.cast());
+			DARegisterDiskDescriptionChangedCallback(
+				session,
+				ptr::null(),
+				ptr::null(),
+				on_description_changed,
+				context.cast(),
+			);
+			DASessionScheduleWithRunLoop(
+				session,
+				run_loop.as_concrete_TypeRef(),
+				kCFRunLoopDefaultMode,
+			);
+		}
+		let _ = started_tx.send(Ok(()));
+
+		// SAFETY: `context` is valid until it is freed below
+		while !unsafe { &*context }.is_closed() {
+			// SAFETY: reading a constant of CoreFoundation
+			CFRunLoop::run_in_mode(unsafe { kCFRunLoopDefaultMode }, WATCH_WAKEUP, false);
+		}
+
+		// SAFETY: the session was scheduled on this loop above, and no callback runs once it
+		// is taken off
+		unsafe {
+			DASessionUnscheduleFromRunLoop(
+				session,
+				run_loop.as_concrete_TypeRef(),
+				kCFRunLoopDefaultMode,
+			);
+			CFRelease(session as *const c_void);
+			drop(Box::from_raw(context));
+		}
+		debug!("Stopped listening to DiskArbitration;");
+	});
+
+	started_rx.await.unwrap_or_else(|_| {
+		Err(VolumeError::WatchInitFailed {
+			source: "the DiskArbitration thread stopped before starting".into(),
+		})
+	})?;
+
+	Ok(Some(thread))
+}
+
+/// Called for the disks present when the callback is registered too, the check they ask for
+/// finds nothing new
+extern "C" fn on_disk(_disk: DADiskRef, context: *mut c_void) {
+	// SAFETY: the sender registered in `watch`, valid while the session is on the loop
+	let check_tx = unsafe { &*context.cast::<mpsc::Sender<()>>() };
+	request_check(check_tx);
+}
+
+extern "C" fn on_description_changed(disk: DADiskRef, _keys: CFArrayRef, context: *mut c_void) {
+	on_disk(disk, context);
+}
@@ -0,0 +1,305 @@
+//! FreeBSD, from `getfsstat` and GEOM
+
+use super::common::{c_string, DegradedModeTracker};
+use super::*;
+use crate::volume::{
+	command, health,
+	retry::{retry, RetryPolicy},
+	DeviceCapabilities, MissingVolumeData, VolumeHealth,
+};
+use std::{
+	collections::HashMap,
+	ffi::{CString, OsStr},
+	io, mem,
+	os::unix::ffi::OsStrExt,
+	path::PathBuf,
+};
+use tracing::warn;
+
+/// The mount flags of `sys/mount.h` the volumes are told apart with
+const MNT_RDONLY: u64 = 0x0000_0001;
+const MNT_LOCAL: u64 = 0x0000_1000;
+const MNT_ROOTFS: u64 = 0x0000_4000;
+
+/// Where automount(8) mounts the removable drives
+const MEDIA_DIR: &str = "/media";
+
+#[derive(Debug, Default)]
+pub struct FreeBsdPlatform {
+	degraded_mode: DegradedModeTracker,
+}
+
+/// An entry of the mount table
+#[derive(Debug, Clone)]
+pub(super) struct Mount {
+	pub mount_point: PathBuf,
+	/// Like `/dev/ada0p2`, `zroot/usr/home` for ZFS datasets or `server:/export` for NFS
+	pub device: String,
+	pub file_system: String,
+	pub flags: u64,
+	pub total_bytes: u64,
+	pub available_bytes: u64,
+}
+
+#[async_trait]
+impl VolumePlatform for FreeBsdPlatform {
+	async fn get_volumes(&self) -> Result<Vec<Volume>, VolumeError> {
+		let mounts = task::spawn_blocking(read_mounts).await??;
+
+		let mut missing = Vec::new();
+		// Disks hold several mounted partitions, GEOM is asked once for each
+		let mut disk_types = HashMap::new();
+		let mut volumes = Vec::new();
+		for mount in mounts
+			.iter()
+			.filter(|mount| !common::is_virtual_filesystem(&mount.file_system))
+		{
+			let disk_type = match disk_name(&mount.device) {
+				Some(disk) => match disk_types.get(disk) {
+					Some(disk_type) => *disk_type,
+					None => {
+						let disk_type = geom_disk_type(disk).await.unwrap_or_else(|e| {
+							warn!(?e, disk, "Failed to read the disk type from GEOM;");
+							missing.push(MissingVolumeData::DeviceInfo);
+							DiskType::Unknown
+						});
+						disk_types.insert(disk, disk_type);
+						disk_type
+					}
+				},
+				None => DiskType::Unknown,
+			};
+
+			volumes.push(volume_from(mount, disk_type));
+		}
+
+		self.degraded_mode.update(DegradedMode::new(missing));
+
+		Ok(volumes)
+	}
+
+	async fn unmount_volume(&self, path: &Path) -> Result<(), VolumeError> {
+		// A device stays busy for a moment after its last file is closed
+		retry(
+			RetryPolicy::OPERATION,
+			"unmount",
+			VolumeError::is_retriable,
+			|| unmount(path),
+		)
+		.await
+	}
+
+	/// Nothing to elevate with, the health is unknown when smartctl can't open the drive
+	async fn health(
+		&self,
+		mount_point: &Path,
+		_elevate: bool,
+	) -> Result<VolumeHealth, VolumeError> {
+		let mounts = task::spawn_blocking(read_mounts).await??;
+		let Some(disk) = mounts
+			.iter()
+			.find(|mount| mount.mount_point == mount_point)
+			.and_then(|mount| disk_name(&mount.device))
+		else {
+			return Ok(VolumeHealth::UNKNOWN);
+		};
+
+		health::smartctl(&Path::new("/dev").join(disk), None).await
+	}
+
+	async fn watch(
+		&self,
+		_backend: WatcherBackend,
+		_check_tx: mpsc::Sender<()>,
+	) -> Result<Option<JoinHandle<()>>, VolumeError> {
+		Err(VolumeError::UnsupportedPlatform {
+			operation: "watching for volume changes",
+		})
+	}
+
+	/// devd isn't listened to yet, the watcher polls
+	fn watcher_backends(&self) -> Vec<WatcherBackend> {
+		vec![WatcherBackend::Poll]
+	}
+
+	fn degraded_mode(&self) -> DegradedMode {
+		self.degraded_mode.get()
+	}
+
+	fn capabilities(&self) -> DeviceCapabilities {
+		DeviceCapabilities {
+			smart: true,
+			unmount: true,
+			..Default::default()
+		}
+	}
+}
+
+/// The mount table, read with `getfsstat` into a buffer of our own: the one of `getmntinfo`
+/// is shared by every thread
+fn read_mounts() -> io::Result<Vec<Mount>> {
+	// SAFETY: a null buffer only asks for the number of mounts
+	let count = unsafe { libc::getfsstat(std::ptr::null_mut(), 0, libc::MNT_NOWAIT) };
+	if count < 0 {
+		return Err(io::Error::last_os_error());
+	}
+
+	// Room for the mounts made between the two calls
+	let capacity = count as usize + 8;
+	let mut entries = Vec::<libc::statfs>::with_capacity(capacity);
+	// SAFETY: the buffer has room for `capacity` entries of the size passed
+	let count = unsafe {
+		libc::getfsstat(
+			entries.as_mut_ptr(),
+			(capacity * mem::size_of::<libc::statfs>()) as libc::c_long,
+			libc::MNT_NOWAIT,
+		)
+	};
+	if count < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	// SAFETY: getfsstat filled in the first `count` entries
+	unsafe { entries.set_len(count as usize) };
+
+	Ok(entries.iter().map(Mount::from_statfs).collect())
+}
+
+impl Mount {
+	fn from_statfs(entry: &libc::statfs) -> Self {
+		Self {
+			mount_point: PathBuf::from(c_string(&entry.f_mntonname)),
+			device: c_string(&entry.f_mntfromname),
+			file_system: c_string(&entry.f_fstypename),
+			flags: entry.f_flags,
+			total_bytes: entry.f_blocks.saturating_mul(entry.f_bsize),
+			// Negative once the space reserved for root is used
+			available_bytes: u64::try_from(entry.f_bavail)
+				.unwrap_or_default()
+				.saturating_mul(entry.f_bsize),
+		}
+	}
+}
+
+pub(super) fn volume_from(mount: &Mount, disk_type: DiskType) -> Volume {
+	let mount_type = if mount.flags & MNT_ROOTFS != 0 {
+		MountType::System
+	} else if mount.flags & MNT_LOCAL == 0 || common::is_network_filesystem(&mount.file_system) {
+		MountType::Network
+	} else if mount.mount_point.starts_with(MEDIA_DIR) {
+		MountType::External
+	} else {
+		MountType::System
+	};
+
+	Volume::new(
+		mount.device.clone(),
+		mount_type,
+		mount.mount_point.clone(),
+		vec![mount.mount_point.clone()],
+		disk_type,
+		FileSystem::from_string(&mount.file_system),
+		mount.total_bytes,
+		mount.available_bytes,
+		mount.flags & MNT_RDONLY != 0,
+	)
+}
+
+/// The disk a device is on, like `ada0` for `/dev/ada0p2`. GEOM names disks after their
+/// driver and unit, then appends the GPT partitions (`p2`), the MBR slices (`s1`) and the BSD
+/// labels (`a`) in them. `None` for the labels in `/dev/gpt` and the other sources that aren't
+/// named after their disk.
+pub(super) fn disk_name(device: &str) -> Option<&str> {
+	let name = device.strip_prefix("/dev/")?;
+	let driver = name.bytes().take_while(u8::is_ascii_alphabetic).count();
+	let unit = name[driver..]
+		.bytes()
+		.take_while(u8::is_ascii_digit)
+		.count();
+	if driver == 0 || unit == 0 {
+		return None;
+	}
+
+	Some(&name[..driver + unit])
+}
+
+/// The type of `disk` from the rotation rate `geom disk list` prints
+async fn geom_disk_type(disk: &str) -> Result<DiskType, VolumeError> {
+	let output = command::run(
+		"geom",
+		[OsStr::new("disk"), OsStr::new("list"), OsStr::new(disk)],
+	)
+	.await?;
+	if !output.status.success() {
+		return Err(VolumeError::DeviceError(format!(
+			"geom disk list {disk}: {}",
+			output.stderr_lossy().trim()
+		)));
+	}
+
+	Ok(parse_disk_type(&output.stdout_lossy()))
+}
+
+/// The rotation rate is 0 for the disks that don't spin, in RPM for those that do and
+/// `unknown` when the drive doesn't report it
+pub(super) fn parse_disk_type(output: &str) -> DiskType {
+	let rate = output
+		.lines()
+		.find_map(|line| line.trim().strip_prefix("rotationrate:").map(str::trim));
+
+	match rate.map(str::parse::<u32>) {
+		Some(Ok(0)) => DiskType::SSD,
+		Some(Ok(_)) => DiskType::HDD,
+		_ => DiskType::Unknown,
+	}
+}
+
+async fn unmount(path: &Path) -> Result<(), VolumeError> {
+	let target = CString::new(path.as_os_str().as_bytes())
+		.map_err(|e| VolumeError::from_unmount_errno(path, e.into()))?;
+
+	// The kernel flushes the file system first, which takes a while on slow drives
+	task::spawn_blocking(move || {
+		// SAFETY: `target` is nul terminated
+		if unsafe { libc::unmount(target.as_ptr(), 0) } == 0 {
+			Ok(())
+		} else {
+			Err(io::Error::last_os_error())
+		}
+	})
+	.await?
+	.map_err(|e| VolumeError::from_unmount_errno(path, e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn finds_the_disk_of_a_device() {
+		assert_eq!(disk_name("/dev/ada0p2"), Some("ada0"));
+		assert_eq!(disk_name("/dev/da10s1a"), Some("da10"));
+		assert_eq!(disk_name("/dev/nvd0"), Some("nvd0"));
+		assert_eq!(disk_name("/dev/gpt/rootfs"), None);
+		assert_eq!(disk_name("zroot/usr/home"), None);
+	}
+
+	#[test]
+	fn reads_the_disk_type_from_geom() {
+		let output = "Geom name: ada0
+Providers:
+1. Name: ada0
+   Mediasize: 500107862016 (466G)
+   descr: Samsung SSD 860 EVO 500GB
+   rotationrate: 0
+";
+		assert_eq!(parse_disk_type(output), DiskType::SSD);
+		assert_eq!(
+			parse_disk_type(&output.replace("rotationrate: 0", "rotationrate: 7200")),
+			DiskType::HDD
+		);
+		assert_eq!(
+			parse_disk_type(&output.replace("rotationrate: 0", "rotationrate: unknown")),
+			DiskType::Unknown
+		);
+	}
+}
@@ -0,0 +1,68 @@
+//! Every other desktop OS, until they get a backend of their own
+
+use super::*;
+use sysinfo::{DiskExt, System, SystemExt};
+
+/// Lists the disks sysinfo knows about, without the devices or read-only status
+#[derive(Debug, Default)]
+pub struct SysinfoPlatform;
+
+#[async_trait]
+impl VolumePlatform for SysinfoPlatform {
+	async fn get_volumes(&self) -> Result<Vec<Volume>, VolumeError> {
+		let volumes = task::spawn_blocking(|| {
+			let mut sys = System::new_all();
+			sys.refresh_disks_list();
+
+			sys.disks()
+				.iter()
+				.filter(|disk| {
+					!common::is_virtual_filesystem(
+						std::str::from_utf8(disk.file_system()).unwrap_or(""),
+					)
+				})
+				.map(|disk| {
+					Volume::new(
+						disk.name().to_string_lossy().to_string(),
+						if disk.is_removable() {
+							MountType::External
+						} else {
+							MountType::System
+						},
+						disk.mount_point().to_path_buf(),
+						vec![disk.mount_point().to_path_buf()],
+						DiskType::Unknown,
+						FileSystem::from_string(&String::from_utf8_lossy(disk.file_system())),
+						disk.total_space(),
+						disk.available_space(),
+						false,
+					)
+				})
+				.collect()
+		})
+		.await?;
+
+		Ok(volumes)
+	}
+
+	async fn unmount_volume(&self, _path: &Path) -> Result<(), VolumeError> {
+		Err(VolumeError::UnsupportedPlatform {
+			operation: "unmounting",
+		})
+	}
+
+	async fn watch(
+		&self,
+		_backend: WatcherBackend,
+		_check_tx: mpsc::Sender<()>,
+	) -> Result<Option<JoinHandle<()>>, VolumeError> {
+		Err(VolumeError::UnsupportedPlatform {
+			operation: "watching for volume changes",
+		})
+	}
+
+	/// sysinfo has no events, the watcher polls
+	fn watcher_backends(&self) -> Vec<WatcherBackend> {
+		vec![WatcherBackend::Poll]
+	}
+}
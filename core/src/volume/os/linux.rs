@@ -0,0 +1,2323 @@
+use super::common::{local_time, request_check, DegradedModeTracker};
+use super::*;
+use crate::volume::{
+	command, health,
+	mountinfo::{self, MountEntry},
+	network, platform,
+	retry::{retry, RetryPolicy},
+	topology, AttachedImage, BusyProcess, ByteSize, DeviceCapabilities, ElevatedOperation,
+	Elevation, ElevationScope, EncryptionKind, FileSystemSnapshot, MissingVolumeData, MountOptions,
+	MountOwner, NetworkShare, OperationPlan, PlanStep, ScanProgress, ShareCredentials,
+	ShareProtocol, UnmountedDevice, VolumeHealth, VolumeOperation,
+};
+use futures::{
+	stream::{self, StreamExt, TryStreamExt},
+	FutureExt,
+};
+use serde::Deserialize;
+use std::{
+	collections::{HashMap, HashSet},
+	ffi::{CStr, CString, OsStr, OsString},
+	fs::File,
+	io::{self, Seek, Write},
+	mem::{self, MaybeUninit},
+	os::{
+		fd::{AsRawFd, FromRawFd, OwnedFd},
+		unix::ffi::OsStrExt,
+	},
+	path::PathBuf,
+	sync::{Arc, Mutex, PoisonError},
+	time::Duration,
+};
+use sysinfo::{DiskExt, System, SystemExt};
+use tokio::time::timeout;
+use tracing::{error, info, warn};
+use zeroize::Zeroizing;
+
+/// Volumes probed at the same time, the tools they run are further limited by the executor
+const MAX_CONCURRENT_PROBES: usize = 8;
+/// Past this a volume is considered hung, usually a network mount whose server went away
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+const MOUNTINFO: &str = "/proc/self/mountinfo";
+const SYSFS_BLOCK: &str = "/sys/class/block";
+/// What dm-crypt starts the uuid of the devices it opens from LUKS containers with
+const LUKS_UUID_PREFIX: &str = "CRYPT-LUKS";
+/// Where udev keeps what it found on each device, in a file named after its type and number
+const UDEV_DATA_DIR: &str = "/run/udev/data";
+/// Where udev links the devices by the UUID of their file system, and of their partition
+const DEVICES_BY_UUID: &str = "/dev/disk/by-uuid";
+const DEVICES_BY_PARTUUID: &str = "/dev/disk/by-partuuid";
+/// Created by the udisks2 daemon when it starts
+const UDISKS2_RUNTIME_DIR: &str = "/run/udisks2";
+/// Where the udisks2 daemon puts the block devices on the system bus
+const UDISKS2_BLOCK_DEVICES: &str = "/org/freedesktop/UDisks2/block_devices";
+/// Seconds a format is waited for, writing the file system of a large drive takes a while
+const FORMAT_TIMEOUT_SECS: &str = "300";
+/// Where logind keeps the state of each logged in user, in a file named after their uid
+const LOGIND_USERS_DIR: &str = "/run/systemd/users";
+/// Directories inotify watches, the usual homes of mount points
+const INOTIFY_PATHS: [&str; 4] = ["/dev", "/media", "/mnt", "/run/media"];
+/// Multicast group of the uevents sent by the kernel, group 2 is udevd's rebroadcast
+const UEVENT_KERNEL_GROUP: u32 = 1;
+
+/// What mounts, unmounts and ejects the volumes. Both list them the same way, from sysinfo or
+/// the mount table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinuxBackend {
+	/// Through the udisks2 daemon, like desktop environments do. Removable drives can be
+	/// mounted and ejected without root, the rest asks through polkit.
+	Udisks2,
+	/// Through the util-linux tools only, for servers and containers without udisks2. Mounting
+	/// isn't available and the volumes of other users can only be unmounted as root.
+	UtilLinux,
+}
+
+#[derive(Debug)]
+pub struct LinuxPlatform {
+	backend: LinuxBackend,
+	degraded_mode: DegradedModeTracker,
+	/// For the operations the app isn't privileged enough to run by itself
+	elevation: Elevation,
+	last_scan: LastScan,
+}
+
+impl LinuxPlatform {
+	pub fn new(backend: LinuxBackend) -> Self {
+		Self {
+			backend,
+			degraded_mode: DegradedModeTracker::default(),
+			elevation: Elevation::default(),
+			last_scan: LastScan::default(),
+		}
+	}
+
+	/// Picks udisks2 when its daemon runs
+	pub async fn detect() -> Self {
+		let backend = if tokio::fs::metadata(UDISKS2_RUNTIME_DIR).await.is_ok() {
+			LinuxBackend::Udisks2
+		} else {
+			LinuxBackend::UtilLinux
+		};
+		info!(?backend, "Selected the volume platform backend;");
+
+		Self::new(backend)
+	}
+
+	pub fn backend(&self) -> LinuxBackend {
+		self.backend
+	}
+
+	/// Runs a udisksctl operation on `device`, asking through polkit if the user can't run it
+	/// by themselves
+	async fn udisksctl(
+		&self,
+		operation: &'static str,
+		scope: &ElevationScope,
+		device: &Path,
+		options: &[&OsStr],
+		into_error: impl Fn(&str) -> VolumeError,
+	) -> Result<String, VolumeError> {
+		let mut args = vec![
+			OsStr::new(operation),
+			OsStr::new("--block-device"),
+			command::path_arg("udisksctl", device)?,
+			OsStr::new("--no-user-interaction"),
+		];
+		args.extend(options);
+
+		self.elevation
+			.run_or_elevate(scope, "udisksctl", &args, into_error)
+			.await
+	}
+
+	/// Detaches a loop device, unless the kernel already did as its file system was unmounted
+	async fn delete_loop(&self, device: &Path, mount_point: &Path) -> Result<(), VolumeError> {
+		let Some(name) = device.file_name() else {
+			return Ok(());
+		};
+		let backing_file = Path::new(SYSFS_BLOCK).join(name).join("loop/backing_file");
+		if tokio::fs::metadata(backing_file).await.is_err() {
+			return Ok(());
+		}
+
+		self.udisksctl(
+			"loop-delete",
+			&ElevationScope::new(ElevatedOperation::Unmount, mount_point),
+			device,
+			&[],
+			|stderr| VolumeError::DeviceError(format!("udisksctl loop-delete: {}", stderr.trim())),
+		)
+		.await
+		.map(|_| ())
+	}
+}
+
+#[async_trait]
+impl VolumePlatform for LinuxPlatform {
+	async fn get_volumes(&self) -> Result<Vec<Volume>, VolumeError> {
+		let (volumes, degraded_mode) = scan(&self.last_scan).await?;
+		self.degraded_mode.update(degraded_mode);
+
+		Ok(volumes)
+	}
+
+	async fn scan_with_progress(
+		&self,
+		progress_tx: mpsc::UnboundedSender<ScanProgress>,
+	) -> Result<Vec<Volume>, VolumeError> {
+		let (volumes, degraded_mode) =
+			scan_with_progress(&self.last_scan, |processed, total, volume| {
+				let _ = progress_tx.send(ScanProgress::Probed {
+					processed,
+					total,
+					volume: volume.cloned(),
+				});
+			})
+			.await?;
+		self.degraded_mode.update(degraded_mode);
+
+		Ok(volumes)
+	}
+
+	async fn unmount_volume(&self, path: &Path) -> Result<(), VolumeError> {
+		match unmount(path).await {
+			Err(e) if e.is_permission_denied() && self.backend == LinuxBackend::Udisks2 => {
+				// udisks2 only knows about block devices
+				let device = block_device(path).await?.ok_or(e)?;
+				self.udisksctl(
+					"unmount",
+					&ElevationScope::new(ElevatedOperation::Unmount, path),
+					Path::new(&device),
+					&[],
+					|stderr| VolumeError::from_unmount(path, stderr),
+				)
+				.await
+				.map(|_| ())
+			}
+			res => res,
+		}
+	}
+
+	async fn mount_volume(
+		&self,
+		device: &Path,
+		options: &MountOptions,
+	) -> Result<PathBuf, VolumeError> {
+		if self.backend != LinuxBackend::Udisks2 {
+			return Err(VolumeError::UnsupportedPlatform {
+				operation: "mounting without udisks2",
+			});
+		}
+
+		let read_only = [OsStr::new("--options"), OsStr::new("ro")];
+		let stdout = self
+			.udisksctl(
+				"mount",
+				&ElevationScope::new(ElevatedOperation::Mount, device),
+				device,
+				if options.read_only {
+					&read_only[..]
+				} else {
+					&[]
+				},
+				|stderr| VolumeError::from_mount(device, stderr),
+			)
+			.await?;
+
+		parse_mounted_at(&stdout, device).ok_or_else(|| VolumeError::MountFailed {
+			device: device.to_path_buf(),
+			reason: format!("unexpected udisksctl output: {}", stdout.trim()),
+		})
+	}
+
+	async fn unmounted_devices(&self) -> Result<Vec<UnmountedDevice>, VolumeError> {
+		let output = command::run(
+			"lsblk",
+			[
+				"--json",
+				"--list",
+				"--bytes",
+				"--paths",
+				"--output",
+				"NAME,LABEL,FSTYPE,SIZE,MOUNTPOINT,TYPE",
+			],
+		)
+		.await?;
+		if !output.status.success() {
+			return Err(VolumeError::DeviceError(format!(
+				"lsblk: {}",
+				output.stderr_lossy().trim()
+			)));
+		}
+
+		parse_unmounted_devices(&output.stdout)
+	}
+
+	async fn eject_volume(&self, path: &Path) -> Result<(), VolumeError> {
+		if self.backend == LinuxBackend::UtilLinux {
+			let output = command::run("eject", [command::path_arg("eject", path)?]).await?;
+			return if output.status.success() {
+				Ok(())
+			} else {
+				Err(VolumeError::from_unmount(path, &output.stderr_lossy()))
+			};
+		}
+
+		// Looked up first, the volume leaves the mount table once unmounted
+		let device = block_device(path)
+			.await?
+			.ok_or_else(|| VolumeError::NotMounted(path.to_path_buf()))?;
+
+		// Never detached while busy, the drive loses its power right after
+		let unmounted = match unmount_without_detaching(path).await {
+			Err(e) if e.is_permission_denied() => self
+				.udisksctl(
+					"unmount",
+					&ElevationScope::new(ElevatedOperation::Unmount, path),
+					Path::new(&device),
+					&[],
+					|stderr| VolumeError::from_unmount(path, stderr),
+				)
+				.await
+				.map(|_| ()),
+			res => res,
+		};
+		if let Err(VolumeError::DeviceBusy { .. }) = unmounted {
+			return Err(VolumeError::InUse {
+				path: path.to_path_buf(),
+				holders: processes_using(path).await,
+			});
+		}
+		unmounted?;
+
+		self.udisksctl(
+			"power-off",
+			&ElevationScope::new(ElevatedOperation::Eject, path),
+			Path::new(&device),
+			&[],
+			|stderr| VolumeError::from_unmount(path, stderr),
+		)
+		.await
+		.map(|_| ())
+	}
+
+	async fn mount_network_share(
+		&self,
+		share: &NetworkShare,
+		mount_point: &Path,
+		credentials: Option<&ShareCredentials>,
+	) -> Result<(), VolumeError> {
+		mount_share(share, mount_point, credentials).await
+	}
+
+	/// Sets a loop device up for the image through udisks2, then mounts the file system on it
+	/// or on its first partition holding one
+	async fn attach_disk_image(
+		&self,
+		image: &Path,
+		read_only: bool,
+	) -> Result<AttachedImage, VolumeError> {
+		if self.backend != LinuxBackend::Udisks2 {
+			return Err(VolumeError::UnsupportedPlatform {
+				operation: "attaching disk images without udisks2",
+			});
+		}
+
+		let attach_failed = |reason| VolumeError::MountFailed {
+			device: image.to_path_buf(),
+			reason,
+		};
+		let mut args = vec![
+			OsStr::new("loop-setup"),
+			OsStr::new("--file"),
+			command::path_arg("udisksctl", image)?,
+			OsStr::new("--no-user-interaction"),
+		];
+		if read_only {
+			args.push(OsStr::new("--read-only"));
+		}
+
+		let output = command::run("udisksctl", &args).await?;
+		if !output.status.success() {
+			return Err(attach_failed(output.stderr_lossy().trim().to_string()));
+		}
+		let stdout = output.stdout_lossy();
+		let loop_device = parse_loop_device(&stdout).ok_or_else(|| {
+			attach_failed(format!("unexpected udisksctl output: {}", stdout.trim()))
+		})?;
+
+		let mounted = async {
+			let output = command::run(
+				"lsblk",
+				[
+					OsStr::new("--raw"),
+					OsStr::new("--noheadings"),
+					OsStr::new("--paths"),
+					OsStr::new("--output"),
+					OsStr::new("NAME,FSTYPE"),
+					command::path_arg("lsblk", &loop_device)?,
+				],
+			)
+			.await?;
+			let device = parse_file_system_device(&output.stdout_lossy()).ok_or_else(|| {
+				VolumeError::InvalidDiskImage {
+					path: image.to_path_buf(),
+					reason: "no file system was found on it",
+				}
+			})?;
+
+			self.mount_volume(&device, &MountOptions { read_only })
+				.await
+		}
+		.await;
+
+		match mounted {
+			Ok(mount_point) => Ok(AttachedImage {
+				image: image.to_path_buf(),
+				device: loop_device,
+				mount_point,
+			}),
+			Err(e) => {
+				// Left behind, the loop device would hold the image until the next reboot
+				if let Err(e) = self.delete_loop(&loop_device, image).await {
+					warn!(
+						?e,
+						"Failed to detach a disk image that couldn't be mounted;"
+					);
+				}
+				Err(e)
+			}
+		}
+	}
+
+	/// The Btrfs snapshots, `btrfs subvolume list` needs root to search the file system
+	async fn list_snapshots(
+		&self,
+		mount_point: &Path,
+	) -> Result<Vec<FileSystemSnapshot>, VolumeError> {
+		let stdout = self
+			.elevation
+			.run_or_elevate(
+				&ElevationScope::new(ElevatedOperation::Snapshots, mount_point),
+				"btrfs",
+				&[
+					OsStr::new("subvolume"),
+					OsStr::new("list"),
+					OsStr::new("-s"),
+					command::path_arg("btrfs", mount_point)?,
+				],
+				|stderr| match VolumeError::from_mount(mount_point, stderr) {
+					VolumeError::MountFailed { reason, .. } => {
+						VolumeError::DeviceError(format!("btrfs subvolume list: {reason}"))
+					}
+					e => e,
+				},
+			)
+			.await?;
+
+		// The subvolume mounted there, the snapshots are listed from the top of the file system
+		let contents = tokio::fs::read(MOUNTINFO).await?;
+		let root = mountinfo::parse(&contents)
+			.into_iter()
+			.rev()
+			.find(|mount| mount.mount_point == mount_point)
+			.map_or_else(|| PathBuf::from("/"), |mount| mount.root);
+
+		Ok(parse_btrfs_snapshots(&stdout, mount_point, &root))
+	}
+
+	/// Mounts the snapshot subvolume on its own, which only root can do
+	async fn mount_snapshot(
+		&self,
+		mount_point: &Path,
+		snapshot: &FileSystemSnapshot,
+		target: &Path,
+	) -> Result<(), VolumeError> {
+		let device = block_device(mount_point)
+			.await?
+			.ok_or_else(|| VolumeError::NotMounted(mount_point.to_path_buf()))?;
+		let options = format!("ro,subvolid={}", snapshot.id);
+
+		self.elevation
+			.run_or_elevate(
+				&ElevationScope::new(ElevatedOperation::Snapshots, mount_point),
+				"mount",
+				&[
+					OsStr::new("-t"),
+					OsStr::new("btrfs"),
+					OsStr::new("-o"),
+					OsStr::new(&options),
+					command::path_arg("mount", Path::new(&device))?,
+					command::path_arg("mount", target)?,
+				],
+				|stderr| VolumeError::from_mount(target, stderr),
+			)
+			.await
+			.map(|_| ())
+	}
+
+	async fn unmount_snapshot(&self, target: &Path) -> Result<(), VolumeError> {
+		match unmount(target).await {
+			Err(e) if e.is_permission_denied() => self
+				.elevation
+				.run_or_elevate(
+					&ElevationScope::new(ElevatedOperation::Snapshots, target),
+					"umount",
+					&[command::path_arg("umount", target)?],
+					|stderr| VolumeError::from_unmount(target, stderr),
+				)
+				.await
+				.map(|_| ()),
+			res => res,
+		}
+	}
+
+	/// Unmounts the volume, then has udisks2 write the new file system on its device
+	async fn format_volume(
+		&self,
+		path: &Path,
+		file_system: &FileSystem,
+	) -> Result<(), VolumeError> {
+		if self.backend != LinuxBackend::Udisks2 {
+			return Err(VolumeError::UnsupportedPlatform {
+				operation: "formatting without udisks2",
+			});
+		}
+		let fs_type = udisks_fs_type(file_system).ok_or(VolumeError::UnsupportedPlatform {
+			operation: "formatting with this file system",
+		})?;
+		// Looked up first, the volume leaves the mount table once unmounted
+		let object_path = block_device(path)
+			.await?
+			.and_then(|device| udisks_object_path(Path::new(&device)))
+			.ok_or_else(|| VolumeError::NotMounted(path.to_path_buf()))?;
+
+		self.unmount_volume(path).await?;
+
+		let output = self
+			.elevation
+			.run(
+				&ElevationScope::new(ElevatedOperation::Format, path),
+				"gdbus",
+				[
+					"call",
+					"--system",
+					"--dest",
+					"org.freedesktop.UDisks2",
+					"--object-path",
+					&object_path,
+					"--timeout",
+					FORMAT_TIMEOUT_SECS,
+					"--method",
+					"org.freedesktop.UDisks2.Block.Format",
+					fs_type,
+					"{}",
+				],
+			)
+			.await?;
+		if !output.status.success() {
+			return Err(VolumeError::DeviceError(format!(
+				"udisks2 format: {}",
+				output.stderr_lossy().trim()
+			)));
+		}
+
+		Ok(())
+	}
+
+	async fn detach_disk_image(&self, image: &AttachedImage) -> Result<(), VolumeError> {
+		match self.unmount_volume(&image.mount_point).await {
+			// Unmounted by something else in the meantime
+			Ok(()) | Err(VolumeError::NotMounted(_)) => {}
+			Err(e) => return Err(e),
+		}
+
+		self.delete_loop(&image.device, &image.mount_point).await
+	}
+
+	async fn check_operation(
+		&self,
+		operation: &VolumeOperation,
+		path: &Path,
+		plan: &mut OperationPlan,
+	) -> Result<(), VolumeError> {
+		plan.busy = processes_using(path).await;
+		let busy = !plan.busy.is_empty();
+		// SAFETY: geteuid can't fail
+		let privileged = unsafe { libc::geteuid() } == 0;
+		// An unprivileged umount is refused unless /etc/fstab allows it, udisks2 does it after
+		// asking polkit.
+		let authorize = !privileged && self.backend == LinuxBackend::Udisks2;
+
+		let device = match operation {
+			VolumeOperation::Unmount => None,
+			// Powered off while busy, a lazily unmounted file system loses what's not written
+			VolumeOperation::Eject if busy => {
+				return Err(VolumeError::DeviceBusy {
+					path: path.to_path_buf(),
+					source: None,
+				})
+			}
+			VolumeOperation::Eject => Some(
+				block_device(path)
+					.await?
+					.ok_or_else(|| VolumeError::NotMounted(path.to_path_buf()))?,
+			),
+			// Erased while busy, what the processes still write is lost
+			VolumeOperation::Format { .. } if busy => {
+				return Err(VolumeError::DeviceBusy {
+					path: path.to_path_buf(),
+					source: None,
+				})
+			}
+			VolumeOperation::Format { file_system } => {
+				if self.backend != LinuxBackend::Udisks2 {
+					return Err(VolumeError::UnsupportedPlatform {
+						operation: "formatting without udisks2",
+					});
+				}
+				if udisks_fs_type(file_system).is_none() {
+					return Err(VolumeError::UnsupportedPlatform {
+						operation: "formatting with this file system",
+					});
+				}
+				let device = block_device(path)
+					.await?
+					.ok_or_else(|| VolumeError::NotMounted(path.to_path_buf()))?;
+
+				// polkit is always asked, erasing a drive is never allowed by default
+				plan.steps.extend([
+					PlanStep::Authorize,
+					PlanStep::Unmount {
+						mount_point: path.to_path_buf(),
+					},
+					PlanStep::Format {
+						device: Some(PathBuf::from(device)),
+						file_system: file_system.clone(),
+					},
+				]);
+				return Ok(());
+			}
+		};
+
+		if authorize {
+			// udisks2 refuses to unmount a busy file system, without a lazy unmount to fall
+			// back on
+			if busy {
+				return Err(VolumeError::DeviceBusy {
+					path: path.to_path_buf(),
+					source: None,
+				});
+			}
+			plan.steps.push(PlanStep::Authorize);
+		}
+
+		let mount_point = path.to_path_buf();
+		plan.steps.push(if busy {
+			PlanStep::LazyUnmount { mount_point }
+		} else {
+			PlanStep::Unmount { mount_point }
+		});
+
+		if let Some(device) = device {
+			plan.steps.push(PlanStep::PowerOff {
+				device: Some(PathBuf::from(device)),
+			});
+		}
+
+		Ok(())
+	}
+
+	async fn health(&self, mount_point: &Path, elevate: bool) -> Result<VolumeHealth, VolumeError> {
+		let Some(device) = block_device(mount_point).await? else {
+			return Ok(VolumeHealth::UNKNOWN);
+		};
+		let Some(disk) = whole_disk(&device).await else {
+			return Ok(VolumeHealth::UNKNOWN);
+		};
+
+		health::smartctl(&disk, elevate.then_some(&self.elevation)).await
+	}
+
+	async fn watch(
+		&self,
+		backend: WatcherBackend,
+		check_tx: mpsc::Sender<()>,
+	) -> Result<Option<JoinHandle<()>>, VolumeError> {
+		match backend {
+			WatcherBackend::MountinfoPoll => watch_mountinfo(check_tx).await,
+			WatcherBackend::Udev => watch_uevents(check_tx).await,
+			WatcherBackend::Inotify => watch_inotify(check_tx),
+			// Polling is run by the watcher itself
+			WatcherBackend::Native | WatcherBackend::Poll => {
+				Err(VolumeError::UnsupportedPlatform {
+					operation: "watching without a backend",
+				})
+			}
+		}
+	}
+
+	/// The mount table first as the volumes are read from it, then the device events which
+	/// don't reach containers, then the directories which miss the mounts made elsewhere
+	fn watcher_backends(&self) -> Vec<WatcherBackend> {
+		vec![
+			WatcherBackend::MountinfoPoll,
+			WatcherBackend::Udev,
+			WatcherBackend::Inotify,
+		]
+	}
+
+	fn degraded_mode(&self) -> DegradedMode {
+		self.degraded_mode.get()
+	}
+
+	fn capabilities(&self) -> DeviceCapabilities {
+		DeviceCapabilities {
+			native_watch: true,
+			elevation: self.elevation.is_available(),
+			mount: self.backend == LinuxBackend::Udisks2,
+			unmount: true,
+			eject: true,
+			network_shares: true,
+			disk_images: self.backend == LinuxBackend::Udisks2,
+			format: self.backend == LinuxBackend::Udisks2,
+			smart: true,
+			..Default::default()
+		}
+	}
+}
+
+/// The read end of a pipe that hangs up once the watcher stops listening, the blocking
+/// backends poll it along with their own descriptor to stop right away
+pub(super) fn shutdown_pipe(check_tx: &mpsc::Sender<()>) -> io::Result<OwnedFd> {
+	let mut fds = [0; 2];
+	// SAFETY: `fds` has room for the two descriptors
+	if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	// SAFETY: both were just opened and nothing else owns them
+	let (read, write) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+
+	let check_tx = check_tx.clone();
+	tokio::spawn(async move {
+		check_tx.closed().await;
+		drop(write);
+	});
+
+	Ok(read)
+}
+
+/// Blocks until `fd` has one of `events`, `false` when the watcher stopped listening instead
+pub(super) fn wait_for(
+	fd: &impl AsRawFd,
+	events: libc::c_short,
+	shutdown: &OwnedFd,
+) -> io::Result<bool> {
+	let mut poll_fds = [
+		libc::pollfd {
+			fd: fd.as_raw_fd(),
+			events,
+			revents: 0,
+		},
+		libc::pollfd {
+			fd: shutdown.as_raw_fd(),
+			events: libc::POLLIN,
+			revents: 0,
+		},
+	];
+
+	// SAFETY: two valid pollfds, both descriptors outlive the call
+	while unsafe { libc::poll(poll_fds.as_mut_ptr(), 2, -1) } < 0 {
+		let e = io::Error::last_os_error();
+		if e.kind() != io::ErrorKind::Interrupted {
+			return Err(e);
+		}
+	}
+
+	Ok(poll_fds[1].revents == 0)
+}
+
+/// Waits for the kernel to signal a change of the mount table, which it does by flagging the
+/// open file with `POLLPRI` on every mount and unmount
+async fn watch_mountinfo(
+	check_tx: mpsc::Sender<()>,
+) -> Result<Option<JoinHandle<()>>, VolumeError> {
+	let file = task::spawn_blocking(|| File::open(MOUNTINFO))
+		.await?
+		.map_err(|e| VolumeError::WatchInitFailed {
+			source: Box::new(e),
+		})?;
+	let shutdown = shutdown_pipe(&check_tx).map_err(|e| VolumeError::WatchInitFailed {
+		source: Box::new(e),
+	})?;
+
+	let thread = task::spawn_blocking(move || loop {
+		match wait_for(&file, libc::POLLPRI, &shutdown) {
+			Ok(true) => {
+				if !request_check(&check_tx) {
+					break;
+				}
+			}
+			Ok(false) => break,
+			Err(e) => {
+				error!(?e, "Failed to poll the mount table;");
+				break;
+			}
+		}
+	});
+
+	Ok(Some(thread))
+}
+
+/// Listens to the uevents of the kernel, the ones about block devices trigger a check
+async fn watch_uevents(check_tx: mpsc::Sender<()>) -> Result<Option<JoinHandle<()>>, VolumeError> {
+	let socket =
+		task::spawn_blocking(uevent_socket)
+			.await?
+			.map_err(|e| VolumeError::WatchInitFailed {
+				source: Box::new(e),
+			})?;
+	let shutdown = shutdown_pipe(&check_tx).map_err(|e| VolumeError::WatchInitFailed {
+		source: Box::new(e),
+	})?;
+
+	let thread = task::spawn_blocking(move || {
+		let mut buffer = [0u8; 8192];
+		loop {
+			match wait_for(&socket, libc::POLLIN, &shutdown) {
+				Ok(true) => {}
+				Ok(false) => break,
+				Err(e) => {
+					error!(?e, "Failed to poll the uevent socket;");
+					break;
+				}
+			}
+
+			// SAFETY: the buffer is valid for its length, `socket` outlives the call
+			let received = unsafe {
+				libc::recv(
+					socket.as_raw_fd(),
+					buffer.as_mut_ptr().cast(),
+					buffer.len(),
+					libc::MSG_DONTWAIT,
+				)
+			};
+			if received < 0 {
+				let e = io::Error::last_os_error();
+				match e.kind() {
+					io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted => continue,
+					// Messages were dropped, some of them may have been about block devices
+					_ if e.raw_os_error() == Some(libc::ENOBUFS) => {}
+					_ => {
+						error!(?e, "Failed to receive uevents;");
+						break;
+					}
+				}
+			} else if !is_block_uevent(&buffer[..received as usize]) {
+				continue;
+			}
+
+			if !request_check(&check_tx) {
+				break;
+			}
+		}
+	});
+
+	Ok(Some(thread))
+}
+
+/// A netlink socket bound to the kernel uevents
+fn uevent_socket() -> io::Result<OwnedFd> {
+	// SAFETY: plain socket creation, the descriptor is owned right after
+	let fd = unsafe {
+		libc::socket(
+			libc::AF_NETLINK,
+			libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+			libc::NETLINK_KOBJECT_UEVENT,
+		)
+	};
+	if fd < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	// SAFETY: `fd` was just opened and nothing else owns it
+	let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+	// SAFETY: sockaddr_nl is plain data, all zeroes is a valid value
+	let mut address: libc::sockaddr_nl = unsafe { mem::zeroed() };
+	address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+	address.nl_groups = UEVENT_KERNEL_GROUP;
+	// SAFETY: the address is a valid sockaddr_nl of the given size
+	if unsafe {
+		libc::bind(
+			socket.as_raw_fd(),
+			(&address as *const libc::sockaddr_nl).cast(),
+			mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+		)
+	} < 0
+	{
+		return Err(io::Error::last_os_error());
+	}
+
+	Ok(socket)
+}
+
+/// Whether a kernel uevent, like "add@/devices/...\0ACTION=add\0SUBSYSTEM=block\0...", is
+/// a block device being added, removed or changed. The bind, online and such of the same
+/// devices don't change the volumes.
+pub(super) fn is_block_uevent(message: &[u8]) -> bool {
+	let mut block = false;
+	let mut relevant = false;
+	for field in message.split(|byte| *byte == 0) {
+		match field {
+			b"SUBSYSTEM=block" => block = true,
+			b"ACTION=add" | b"ACTION=remove" | b"ACTION=change" => relevant = true,
+			_ => {}
+		}
+	}
+
+	block && relevant
+}
+
+/// Watches the directories volumes are usually mounted in, the last resort when neither the
+/// mount table nor the uevents can be watched. Mounts made elsewhere go unnoticed until the
+/// next periodic check.
+fn watch_inotify(check_tx: mpsc::Sender<()>) -> Result<Option<JoinHandle<()>>, VolumeError> {
+	use inotify::{Inotify, WatchMask};
+
+	let inotify = Inotify::init().map_err(|e| VolumeError::WatchInitFailed {
+		source: Box::new(e),
+	})?;
+
+	// Only entries coming and going, writes to the device nodes in /dev would trigger checks
+	// all the time
+	for path in INOTIFY_PATHS {
+		if let Err(e) = inotify
+			.watches()
+			.add(path, WatchMask::CREATE | WatchMask::DELETE)
+		{
+			warn!("Failed to watch path {}: {}", path, e);
+		}
+	}
+
+	let mut events =
+		inotify
+			.into_event_stream([0; 4096])
+			.map_err(|e| VolumeError::WatchInitFailed {
+				source: Box::new(e),
+			})?;
+
+	let task = tokio::spawn(async move {
+		loop {
+			let event = tokio::select! {
+				event = events.next() => event,
+				// The watcher is gone, or switched to another backend
+				() = check_tx.closed() => break,
+			};
+
+			match event {
+				Some(Ok(_)) => {
+					if !request_check(&check_tx) {
+						break;
+					}
+				}
+				Some(Err(e)) => error!("Inotify error: {}", e),
+				None => break,
+			}
+		}
+	});
+
+	Ok(Some(task))
+}
+
+/// Where udisksctl mounted `device`, from the "Mounted /dev/sdb1 at /media/user/USB" it prints.
+/// Versions before 2.8 end the line with a period.
+/// The file system type udisks2 formats with, it has no tools for the Apple ones
+pub(super) fn udisks_fs_type(file_system: &FileSystem) -> Option<&'static str> {
+	match file_system {
+		FileSystem::EXT4 => Some("ext4"),
+		FileSystem::FAT32 => Some("vfat"),
+		FileSystem::ExFAT => Some("exfat"),
+		FileSystem::NTFS => Some("ntfs"),
+		FileSystem::APFS | FileSystem::HFSPlus | FileSystem::Other(_) => None,
+	}
+}
+
+/// The object of a block device on the system bus, named after the device with the bytes
+/// D-Bus doesn't take escaped as `_` and their hex code, like `dm_2d0` for `dm-0`
+pub(super) fn udisks_object_path(device: &Path) -> Option<String> {
+	let name = device.file_name()?.as_bytes();
+	let mut object_path = format!("{UDISKS2_BLOCK_DEVICES}/");
+	for byte in name {
+		if byte.is_ascii_alphanumeric() {
+			object_path.push(char::from(*byte));
+		} else {
+			object_path.push_str(&format!("_{byte:02x}"));
+		}
+	}
+
+	Some(object_path)
+}
+
+pub(super) fn parse_mounted_at(stdout: &str, device: &Path) -> Option<PathBuf> {
+	let prefix = format!("Mounted {} at ", device.display());
+	stdout
+		.lines()
+		.find_map(|line| line.strip_prefix(&prefix))
+		.map(|mount_point| mount_point.trim_end().trim_end_matches('.'))
+		.filter(|mount_point| !mount_point.is_empty())
+		.map(PathBuf::from)
+}
+
+/// The loop device in what `udisksctl loop-setup` printed, `Mapped file X as /dev/loop0.`
+pub(super) fn parse_loop_device(stdout: &str) -> Option<PathBuf> {
+	stdout
+		.lines()
+		.filter(|line| line.starts_with("Mapped file "))
+		.find_map(|line| line.trim_end().trim_end_matches('.').rsplit_once(" as "))
+		.map(|(_, device)| PathBuf::from(device))
+		.filter(|device| device.starts_with("/dev"))
+}
+
+/// The first device with a file system in the raw `NAME FSTYPE` output of lsblk, the loop
+/// device itself or one of its partitions
+pub(super) fn parse_file_system_device(stdout: &str) -> Option<PathBuf> {
+	stdout.lines().find_map(|line| {
+		let mut fields = line.split(' ');
+		let name = fields.next()?;
+		fields
+			.next()
+			.filter(|fstype| !fstype.is_empty())
+			.map(|_| PathBuf::from(name))
+	})
+}
+
+/// The snapshots in what `btrfs subvolume list -s` printed, like `ID 258 gen 10 cgen 10 top
+/// level 5 otime 2024-03-01 10:22:33 path @snapshots/home`. The ones taken under `root`, the
+/// subvolume mounted at `mount_point`, can be browsed from there.
+pub(super) fn parse_btrfs_snapshots(
+	stdout: &str,
+	mount_point: &Path,
+	root: &Path,
+) -> Vec<FileSystemSnapshot> {
+	let root = root.strip_prefix("/").unwrap_or(root);
+
+	stdout
+		.lines()
+		.filter_map(|line| {
+			let (fields, path) = line.split_once(" path ")?;
+			let id = fields.strip_prefix("ID ")?.split_whitespace().next()?;
+			let path = Path::new(path.trim_end());
+			let created_at = fields
+				.split_once(" otime ")
+				.and_then(|(_, otime)| local_time(otime.trim(), "%Y-%m-%d %H:%M:%S"));
+
+			Some(FileSystemSnapshot {
+				id: id.to_string(),
+				name: path.file_name()?.to_string_lossy().to_string(),
+				created_at,
+				path: path
+					.strip_prefix(root)
+					.ok()
+					.map(|path| mount_point.join(path)),
+			})
+		})
+		.collect()
+}
+
+/// Whether the device is a loop device, which the disk images are attached as
+fn is_loop_device(name: &str) -> bool {
+	Path::new(name)
+		.file_name()
+		.and_then(|name| name.to_str())
+		.and_then(|name| name.strip_prefix("loop"))
+		.is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+}
+
+#[derive(Debug, Deserialize)]
+struct LsblkOutput {
+	blockdevices: Vec<LsblkDevice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LsblkDevice {
+	name: PathBuf,
+	label: Option<String>,
+	fstype: Option<String>,
+	/// A string before util-linux 2.33, even with `--bytes`
+	size: Option<serde_json::Value>,
+	mountpoint: Option<String>,
+	#[serde(rename = "type")]
+	kind: String,
+}
+
+/// File systems that only hold the devices stacked on them, or nothing to mount at all
+const UNMOUNTABLE_FILE_SYSTEMS: [&str; 6] = [
+	"swap",
+	"crypto_LUKS",
+	"LVM2_member",
+	"linux_raid_member",
+	"zfs_member",
+	"bcache",
+];
+
+/// The devices with a file system and no mount point in the JSON of `lsblk --list`
+pub(super) fn parse_unmounted_devices(json: &[u8]) -> Result<Vec<UnmountedDevice>, VolumeError> {
+	let output = serde_json::from_slice::<LsblkOutput>(json)
+		.map_err(|e| VolumeError::DeviceError(format!("unexpected lsblk output: {e}")))?;
+
+	Ok(output
+		.blockdevices
+		.into_iter()
+		.filter(|device| device.mountpoint.is_none() && device.kind != "loop")
+		.filter_map(|device| {
+			let file_system = device
+				.fstype
+				.filter(|fs| !UNMOUNTABLE_FILE_SYSTEMS.contains(&fs.as_str()))?;
+			let size = match device.size {
+				Some(serde_json::Value::Number(size)) => size.as_u64(),
+				Some(serde_json::Value::String(size)) => size.parse().ok(),
+				_ => None,
+			};
+
+			Some(UnmountedDevice {
+				device: device.name,
+				label: device.label.filter(|label| !label.is_empty()),
+				file_system: FileSystem::from_string(&file_system),
+				total_bytes_capacity: ByteSize(size.unwrap_or_default()),
+			})
+		})
+		.collect())
+}
+
+/// A mounted file system, as reported by sysinfo or read from the mount table
+#[derive(Debug)]
+struct DiskInfo {
+	name: String,
+	/// Read from sysfs when unknown
+	is_removable: Option<bool>,
+	mount_point: PathBuf,
+	file_system: String,
+	/// Total and available bytes, read with statvfs when unknown
+	capacity: Option<(u64, u64)>,
+}
+
+/// Lists the volumes, along with the data that had to be done without
+///
+/// sysinfo comes first. When it reports nothing, as in most containers, the volumes are read from
+/// the mount table and their capacity from statvfs.
+/// The volumes that don't answer in time keep the state `last_scan` has of them, a hung mount would
+/// flap between removed and added otherwise.
+pub(super) async fn scan(last_scan: &LastScan) -> Result<(Vec<Volume>, DegradedMode), VolumeError> {
+	scan_with_progress(last_scan, |_, _, _| {}).await
+}
+
+/// Like [`scan`], calling `on_probed` with the mounts processed so far, out of how many, and
+/// the volume found on the last one as soon as each is probed
+async fn scan_with_progress(
+	last_scan: &LastScan,
+	mut on_probed: impl FnMut(u32, u32, Option<&Volume>) + Send,
+) -> Result<(Vec<Volume>, DegradedMode), VolumeError> {
+	let mut missing = HashSet::new();
+
+	let mounts = match tokio::fs::read(MOUNTINFO).await {
+		Ok(contents) => mountinfo::entries(&contents)
+			.filter_map(|entry| {
+				entry
+					.map_err(|e| warn!(?e, "Skipping unreadable mount table line;"))
+					.ok()
+			})
+			.collect::<Vec<_>>(),
+		Err(e) => {
+			warn!(?e, "Failed to read the mount table;");
+			vec![]
+		}
+	};
+
+	let mut disks = task::spawn_blocking(|| {
+		let mut sys = System::new_all();
+		sys.refresh_disks_list();
+
+		sys.disks()
+			.iter()
+			.filter(|disk| {
+				!common::is_virtual_filesystem(
+					std::str::from_utf8(disk.file_system()).unwrap_or(""),
+				)
+			})
+			.map(|disk| DiskInfo {
+				name: disk.name().to_string_lossy().to_string(),
+				is_removable: Some(disk.is_removable()),
+				mount_point: disk.mount_point().to_path_buf(),
+				file_system: String::from_utf8_lossy(disk.file_system()).to_string(),
+				capacity: (disk.total_space() > 0)
+					.then(|| (disk.total_space(), disk.available_space())),
+			})
+			.collect::<Vec<_>>()
+	})
+	.await?;
+
+	if disks.is_empty() {
+		disks = mounts
+			.iter()
+			.filter(|mount| !common::is_virtual_filesystem(&mount.file_system))
+			.map(|mount| DiskInfo {
+				name: mount.source.to_string_lossy().into_owned(),
+				is_removable: None,
+				mount_point: mount.mount_point.clone(),
+				file_system: mount.file_system.clone(),
+				capacity: None,
+			})
+			.collect();
+
+		if !disks.is_empty() {
+			missing.insert(MissingVolumeData::DiskList);
+		}
+	}
+
+	// sysinfo leaves network file systems out unless built with its `linux-netdevs` feature
+	let network_mounts = mounts
+		.iter()
+		.filter(|mount| {
+			common::is_network_filesystem(&mount.file_system)
+				&& !disks
+					.iter()
+					.any(|disk| disk.mount_point == mount.mount_point)
+		})
+		.map(|mount| DiskInfo {
+			name: mount.source.to_string_lossy().into_owned(),
+			is_removable: Some(false),
+			mount_point: mount.mount_point.clone(),
+			file_system: mount.file_system.clone(),
+			capacity: None,
+		})
+		.collect::<Vec<_>>();
+	disks.extend(network_mounts);
+
+	if tokio::fs::metadata(SYSFS_BLOCK).await.is_err() {
+		missing.insert(MissingVolumeData::DeviceInfo);
+	}
+
+	let total = disks.len() as u32;
+	let mounts = Arc::new(mounts);
+	let mut probes = stream::iter(disks)
+		.map(|disk| {
+			let mount_point = disk.mount_point.clone();
+			probe_volume(disk, Arc::clone(&mounts)).map(move |res| match res {
+				Err(e) if e.is_timeout() => Ok(last_scan.unresponsive(&mount_point, &e)),
+				res => res,
+			})
+		})
+		.buffer_unordered(MAX_CONCURRENT_PROBES);
+
+	let mut volumes = Vec::with_capacity(total as usize);
+	let mut processed = 0;
+	while let Some(probed) = probes.try_next().await? {
+		processed += 1;
+
+		let volume = probed.map(|(volume, volume_missing)| {
+			missing.extend(volume_missing);
+			volume
+		});
+		on_probed(processed, total, volume.as_ref());
+		volumes.extend(volume);
+	}
+	last_scan.replace(&volumes);
+
+	volumes.extend(locked_luks_volumes().await);
+
+	Ok((volumes, DegradedMode::new(missing)))
+}
+
+/// The LUKS containers nothing opened, listed locked as their files can't be read until the
+/// user unlocks them. Nothing is mounted from them, the device stands in for the mount point.
+async fn locked_luks_volumes() -> Vec<Volume> {
+	let Ok(mut devices) = tokio::fs::read_dir(SYSFS_BLOCK).await else {
+		return vec![];
+	};
+
+	let mut volumes = vec![];
+	while let Ok(Some(device)) = devices.next_entry().await {
+		let device_dir = device.path();
+		let Ok(number) = tokio::fs::read_to_string(device_dir.join("dev")).await else {
+			continue;
+		};
+		let Ok(udev_data) =
+			tokio::fs::read_to_string(Path::new(UDEV_DATA_DIR).join(format!("b{}", number.trim())))
+				.await
+		else {
+			continue;
+		};
+		if udev_property(&udev_data, "ID_FS_TYPE") != Some("crypto_LUKS") {
+			continue;
+		}
+
+		// dm-crypt holds the containers it opened
+		let is_open = match tokio::fs::read_dir(device_dir.join("holders")).await {
+			Ok(mut holders) => holders.next_entry().await.ok().flatten().is_some(),
+			Err(_) => false,
+		};
+		if is_open {
+			continue;
+		}
+
+		let name = format!("/dev/{}", device.file_name().to_string_lossy());
+		let is_removable = read_block_attr(&name, "removable").await.as_deref() == Some("1");
+		// In sectors of 512 bytes, whatever the sector size of the disk
+		let total_space = tokio::fs::read_to_string(device_dir.join("size"))
+			.await
+			.ok()
+			.and_then(|sectors| sectors.trim().parse::<u64>().ok())
+			.map_or(0, |sectors| sectors * 512);
+
+		let mut volume = Volume::new(
+			name.clone(),
+			if is_removable {
+				MountType::External
+			} else {
+				MountType::System
+			},
+			PathBuf::from(&name),
+			vec![],
+			detect_disk_type(&name).await,
+			FileSystem::Other("LUKS".to_string()),
+			total_space,
+			0,
+			true,
+		);
+		volume.is_accessible = false;
+		volume.encryption = Some(EncryptionKind::Luks);
+		volume.is_locked = true;
+		volume.serial = udev_property(&udev_data, "ID_FS_UUID").map(str::to_string);
+		volumes.push(volume);
+	}
+
+	volumes
+}
+
+/// A property of a device in the udev database, its lines being like `E:ID_FS_TYPE=ext4`
+fn udev_property<'a>(udev_data: &'a str, key: &str) -> Option<&'a str> {
+	udev_data.lines().find_map(|line| {
+		line.strip_prefix("E:")?
+			.strip_prefix(key)?
+			.strip_prefix('=')
+	})
+}
+
+/// The volumes found by the last scan, by mount point
+#[derive(Debug, Default)]
+pub(super) struct LastScan(Mutex<HashMap<PathBuf, Volume>>);
+
+impl LastScan {
+	/// The volume last found at `mount_point`, for one that didn't answer in time
+	fn unresponsive(
+		&self,
+		mount_point: &Path,
+		e: &VolumeError,
+	) -> Option<(Volume, Vec<MissingVolumeData>)> {
+		let volume = self
+			.0
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.get(mount_point)
+			.cloned();
+
+		if volume.is_some() {
+			warn!(
+				?e,
+				mount_point = %mount_point.display(),
+				"Keeping the last known state of an unresponsive volume;",
+			);
+		} else {
+			warn!(?e, mount_point = %mount_point.display(), "Skipping unresponsive volume;");
+		}
+
+		volume.map(|volume| (volume, vec![]))
+	}
+
+	fn replace(&self, volumes: &[Volume]) {
+		*self.0.lock().unwrap_or_else(PoisonError::into_inner) = volumes
+			.iter()
+			.map(|volume| (volume.mount_point.clone(), volume.clone()))
+			.collect();
+	}
+}
+
+/// Checks a mounted file system, `None` if it isn't a mounted directory, a timeout error if it
+/// doesn't answer in time
+async fn probe_volume(
+	disk: DiskInfo,
+	mounts: Arc<Vec<MountEntry>>,
+) -> Result<Option<(Volume, Vec<MissingVolumeData>)>, VolumeError> {
+	let DiskInfo {
+		name,
+		is_removable,
+		mount_point,
+		file_system,
+		capacity,
+	} = disk;
+
+	let probe = async {
+		let mut missing = vec![];
+
+		// A stat on a hung network mount blocks until the server comes back. Containers also
+		// bind mount single files, like /etc/hosts, which aren't volumes
+		let is_dir = task::spawn_blocking({
+			let mount_point = mount_point.clone();
+			move || mount_point.is_dir()
+		})
+		.await?;
+
+		if !is_dir {
+			return Ok(None);
+		}
+
+		let capacity = match capacity {
+			Some(capacity) => Some(capacity),
+			None => {
+				task::spawn_blocking({
+					let mount_point = mount_point.clone();
+					move || statvfs(&mount_point)
+				})
+				.await?
+			}
+		};
+		let (total_space, available_space) = capacity.unwrap_or_else(|| {
+			missing.push(MissingVolumeData::Capacity);
+			(0, 0)
+		});
+
+		let read_only = match mounts
+			.iter()
+			.rev()
+			.find(|mount| mount.mount_point == mount_point)
+		{
+			Some(mount) => mount.is_read_only(),
+			None => findmnt_read_only(&mount_point).await?.unwrap_or_else(|| {
+				missing.push(MissingVolumeData::ReadOnlyStatus);
+				false
+			}),
+		};
+
+		let disk_type = detect_disk_type(&name).await;
+		let is_removable = match is_removable {
+			Some(is_removable) => is_removable,
+			None => read_block_attr(&name, "removable").await.as_deref() == Some("1"),
+		};
+
+		let mount = mounts
+			.iter()
+			.rev()
+			.find(|mount| mount.mount_point == mount_point);
+		let owner = mount_owner(&mount_point, mount).await?;
+		let is_accessible = task::spawn_blocking({
+			let mount_point = mount_point.clone();
+			move || is_accessible(&mount_point)
+		})
+		.await?;
+
+		let mount_type = if common::is_network_filesystem(&file_system) {
+			MountType::Network
+		} else if is_loop_device(&name) {
+			MountType::DiskImage
+		} else if is_removable {
+			MountType::External
+		} else {
+			MountType::System
+		};
+
+		let mut volume = Volume::new(
+			name.clone(),
+			mount_type,
+			mount_point.clone(),
+			vec![mount_point.clone()],
+			disk_type,
+			FileSystem::from_string(&file_system),
+			total_space,
+			available_space,
+			read_only,
+		);
+		volume.owner = owner;
+		volume.is_accessible = is_accessible;
+		// Locked LUKS devices have nothing mounted, the ones listed are open
+		volume.encryption = detect_encryption(&name).await;
+		volume.serial = device_serial(&name).await;
+		// Subvolumes and bind mounts share the serial of the whole file system
+		volume.mount_root = mount.map(|mount| mount.root.to_string_lossy().into_owned());
+		volume.topology = topology::detect(&name, &file_system).await;
+
+		Ok::<_, VolumeError>(Some((volume, missing)))
+	};
+
+	timeout(PROBE_TIMEOUT, probe)
+		.await
+		.unwrap_or_else(|_| Err(VolumeError::Timeout(PROBE_TIMEOUT.as_secs())))
+}
+
+/// Whether the current user can list the volume, blocking. udisks2 mounts the drives of each
+/// user in a directory only they can open.
+fn is_accessible(path: &Path) -> bool {
+	let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+		return false;
+	};
+
+	// SAFETY: `path` is nul terminated
+	unsafe {
+		libc::faccessat(
+			libc::AT_FDCWD,
+			path.as_ptr(),
+			libc::R_OK | libc::X_OK,
+			libc::AT_EACCESS,
+		) == 0
+	}
+}
+
+/// The user the volume mounted at `mount_point` was mounted for, from the directory udisks2
+/// mounted it in or the uid its file system was mounted with. None for the mounts of root.
+async fn mount_owner(
+	mount_point: &Path,
+	mount: Option<&MountEntry>,
+) -> Result<Option<MountOwner>, VolumeError> {
+	let user = if let Some(name) = owner_from_path(mount_point) {
+		let name = name.to_string();
+		task::spawn_blocking(move || user_by_name(&name)).await?
+	} else if let Some(uid) = mount.and_then(owner_from_options) {
+		let name = task::spawn_blocking(move || user_by_uid(uid)).await?;
+		Some((uid, name))
+	} else {
+		None
+	};
+
+	let Some((uid, name)) = user.filter(|(uid, _)| *uid != 0) else {
+		return Ok(None);
+	};
+
+	let seats = match tokio::fs::read_to_string(format!("{LOGIND_USERS_DIR}/{uid}")).await {
+		Ok(contents) => parse_seats(&contents),
+		// Not logged in, or not running systemd
+		Err(_) => Vec::new(),
+	};
+
+	Ok(Some(MountOwner { uid, name, seats }))
+}
+
+/// The user of a udisks2 mount point, `/run/media/<user>/<label>` or `/media/<user>/<label>`
+/// on Debian and its derivatives
+pub(super) fn owner_from_path(mount_point: &Path) -> Option<&str> {
+	let user_dir = ["/run/media", "/media"]
+		.into_iter()
+		.find_map(|dir| mount_point.strip_prefix(dir).ok())?;
+
+	let mut components = user_dir.components();
+	let user = components.next()?.as_os_str().to_str()?;
+	// `/media/<label>` is a drive mounted by hand, without a user directory
+	components.next()?;
+	components.next().is_none().then_some(user)
+}
+
+/// The uid a file system was mounted with, vfat and the like take a `uid` option and FUSE
+/// a `user_id` one
+pub(super) fn owner_from_options(mount: &MountEntry) -> Option<u32> {
+	mount
+		.super_options
+		.iter()
+		.chain(&mount.options)
+		.find_map(|option| {
+			option
+				.strip_prefix("uid=")
+				.or_else(|| option.strip_prefix("user_id="))
+		})
+		.and_then(|uid| uid.parse().ok())
+}
+
+/// The seats in the `SEATS=` line of a logind user file
+pub(super) fn parse_seats(contents: &str) -> Vec<String> {
+	contents
+		.lines()
+		.find_map(|line| line.strip_prefix("SEATS="))
+		.map(|seats| seats.split_whitespace().map(str::to_string).collect())
+		.unwrap_or_default()
+}
+
+/// The uid and name of the user called `name`, blocking as the user database may be remote
+fn user_by_name(name: &str) -> Option<(u32, Option<String>)> {
+	let name = CString::new(name).ok()?;
+	// SAFETY: `name` is nul terminated, the other arguments come from `passwd_entry`
+	passwd_entry(|passwd, buffer, length, result| unsafe {
+		libc::getpwnam_r(name.as_ptr(), passwd, buffer, length, result)
+	})
+	.map(|(uid, name)| (uid, Some(name)))
+}
+
+/// The name of the user with `uid`, blocking as the user database may be remote
+fn user_by_uid(uid: u32) -> Option<String> {
+	// SAFETY: the arguments come from `passwd_entry`
+	passwd_entry(|passwd, buffer, length, result| unsafe {
+		libc::getpwuid_r(uid, passwd, buffer, length, result)
+	})
+	.map(|(_, name)| name)
+}
+
+/// Runs a `getpw*_r` lookup, returning the uid and name of the user it found
+fn passwd_entry(
+	lookup: impl FnOnce(
+		*mut libc::passwd,
+		*mut libc::c_char,
+		libc::size_t,
+		*mut *mut libc::passwd,
+	) -> libc::c_int,
+) -> Option<(u32, String)> {
+	let mut passwd = MaybeUninit::<libc::passwd>::uninit();
+	let mut buffer = [0 as libc::c_char; 4096];
+	let mut result = std::ptr::null_mut();
+
+	if lookup(
+		passwd.as_mut_ptr(),
+		buffer.as_mut_ptr(),
+		buffer.len(),
+		&mut result,
+	) != 0 || result.is_null()
+	{
+		return None;
+	}
+
+	// SAFETY: a non null result points to `passwd`, filled in with strings kept in `buffer`
+	let passwd = unsafe { passwd.assume_init() };
+	let name = unsafe { CStr::from_ptr(passwd.pw_name) };
+	Some((passwd.pw_uid, name.to_string_lossy().into_owned()))
+}
+
+/// Total and available bytes of the file system mounted at `path`, blocking
+fn statvfs(path: &Path) -> Option<(u64, u64)> {
+	let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+	let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+
+	// SAFETY: `path` is nul terminated and `stat` is only read once statvfs filled it in
+	if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+		return None;
+	}
+	let stat = unsafe { stat.assume_init() };
+
+	// The field types differ between architectures
+	#[allow(clippy::unnecessary_cast)]
+	let fragment_size = stat.f_frsize as u64;
+	Some((
+		stat.f_blocks as u64 * fragment_size,
+		stat.f_bavail as u64 * fragment_size,
+	))
+}
+
+/// Whether the device or one it's stacked on, like the LUKS container below an LVM volume, is
+/// opened by dm-crypt
+async fn detect_encryption(device: &str) -> Option<EncryptionKind> {
+	// The names in /dev/mapper link to the dm-N nodes sysfs knows the devices by
+	let device = tokio::fs::canonicalize(device).await.ok()?;
+	let mut pending = vec![device.file_name()?.to_os_string()];
+
+	while let Some(name) = pending.pop() {
+		let device_dir = Path::new(SYSFS_BLOCK).join(&name);
+		if let Ok(uuid) = tokio::fs::read_to_string(device_dir.join("dm/uuid")).await {
+			if uuid.starts_with(LUKS_UUID_PREFIX) {
+				return Some(EncryptionKind::Luks);
+			}
+		}
+
+		let Ok(mut slaves) = tokio::fs::read_dir(device_dir.join("slaves")).await else {
+			continue;
+		};
+		while let Ok(Some(slave)) = slaves.next_entry().await {
+			pending.push(slave.file_name());
+		}
+	}
+
+	None
+}
+
+/// The UUID udev found for the file system on the device, or for its partition when the file
+/// system has none
+async fn device_serial(device: &str) -> Option<String> {
+	// Network mounts and the other sources that aren't devices can't be resolved
+	let device = tokio::fs::canonicalize(device).await.ok()?;
+
+	for dir in [DEVICES_BY_UUID, DEVICES_BY_PARTUUID] {
+		let Ok(mut links) = tokio::fs::read_dir(dir).await else {
+			continue;
+		};
+		while let Ok(Some(link)) = links.next_entry().await {
+			if tokio::fs::canonicalize(link.path())
+				.await
+				.is_ok_and(|target| target == device)
+			{
+				return link.file_name().into_string().ok();
+			}
+		}
+	}
+
+	None
+}
+
+/// Reads an attribute of a block device from sysfs, from the parent disk for partitions
+async fn read_block_attr(device: &str, attr: &str) -> Option<String> {
+	let device = device.trim_start_matches("/dev/");
+	if device.is_empty() || device.contains('/') {
+		return None;
+	}
+
+	let device_dir = PathBuf::from(format!("{SYSFS_BLOCK}/{device}"));
+	let read = || async {
+		let mut last_error = None;
+		for path in [device_dir.join(attr), device_dir.join("..").join(attr)] {
+			match tokio::fs::read_to_string(path).await {
+				Ok(contents) => return Ok(contents.trim().to_string()),
+				Err(e) => last_error = Some(e),
+			}
+		}
+		Err(last_error.unwrap_or_else(|| io::ErrorKind::NotFound.into()))
+	};
+
+	// A device that just appeared has its directory before the kernel fills it in
+	retry(
+		RetryPolicy::PROBE,
+		"read_block_attr",
+		|e: &io::Error| e.kind() == io::ErrorKind::NotFound && device_dir.is_dir(),
+		read,
+	)
+	.await
+	.ok()
+}
+
+async fn detect_disk_type(device_name: &str) -> DiskType {
+	match read_block_attr(device_name, "queue/rotational")
+		.await
+		.as_deref()
+	{
+		Some("0") => DiskType::SSD,
+		Some("1") => DiskType::HDD,
+		_ => DiskType::Unknown,
+	}
+}
+
+/// Asks findmnt whether the volume is mounted read-only, for the mounts that weren't in the
+/// table we read. `None` when it can't tell, findmnt is often missing from minimal images
+#[cfg(feature = "volume-commands")]
+async fn findmnt_read_only(mount_point: &Path) -> Result<Option<bool>, VolumeError> {
+	let res = command::run(
+		"findmnt",
+		[
+			OsStr::new("--noheadings"),
+			OsStr::new("--output"),
+			OsStr::new("OPTIONS"),
+			command::path_arg("findmnt", mount_point)?,
+		],
+	)
+	.await;
+
+	match res {
+		Ok(output) if output.status.success() => Ok(Some(
+			output
+				.stdout_lossy()
+				.trim()
+				.split(',')
+				.any(|option| option == "ro"),
+		)),
+		Ok(_) | Err(VolumeError::CommandFailed { .. }) => Ok(None),
+		Err(e) => Err(e),
+	}
+}
+
+#[cfg(not(feature = "volume-commands"))]
+async fn findmnt_read_only(_mount_point: &Path) -> Result<Option<bool>, VolumeError> {
+	Ok(None)
+}
+
+/// Unmounts with umount2(2), lazily if the volume is in use
+async fn unmount(path: &Path) -> Result<(), VolumeError> {
+	if unmount_without_detaching(path).await.is_ok() {
+		return Ok(());
+	}
+
+	// Detached, it's unmounted for good once nothing uses it
+	match umount2(path, libc::MNT_DETACH).await {
+		// Only root may call it, the setuid tool also lets users unmount what fstab allows them to
+		#[cfg(feature = "volume-commands")]
+		Err(e) if e.is_permission_denied() => umount_command(path).await,
+		res => res,
+	}
+}
+
+/// Unmounts with umount2(2), failing with [`VolumeError::DeviceBusy`] while the volume is in use
+async fn unmount_without_detaching(path: &Path) -> Result<(), VolumeError> {
+	// A device stays busy for a moment after its last file is closed
+	retry(
+		RetryPolicy::OPERATION,
+		"umount2",
+		VolumeError::is_retriable,
+		|| umount2(path, 0),
+	)
+	.await
+}
+
+async fn umount2(path: &Path, flags: libc::c_int) -> Result<(), VolumeError> {
+	let target = CString::new(path.as_os_str().as_bytes())
+		.map_err(|e| VolumeError::from_unmount_errno(path, e.into()))?;
+
+	// The kernel flushes the file system first, which takes a while on slow drives
+	task::spawn_blocking(move || {
+		// SAFETY: `target` is nul terminated
+		if unsafe { libc::umount2(target.as_ptr(), flags) } == 0 {
+			Ok(())
+		} else {
+			Err(io::Error::last_os_error())
+		}
+	})
+	.await?
+	.map_err(|e| VolumeError::from_unmount_errno(path, e))
+}
+
+/// Unmounts with umount, lazily if the volume is in use
+#[cfg(feature = "volume-commands")]
+async fn umount_command(path: &Path) -> Result<(), VolumeError> {
+	let path_arg = command::path_arg("umount", path)?;
+
+	// Try regular unmount first, a device stays busy for a moment after its last file is closed
+	let result = retry(
+		RetryPolicy::OPERATION,
+		"umount",
+		VolumeError::is_retriable,
+		|| async {
+			let output = command::run("umount", [path_arg]).await?;
+			if output.status.success() {
+				Ok(())
+			} else {
+				Err(VolumeError::from_unmount(path, &output.stderr_lossy()))
+			}
+		},
+	)
+	.await;
+
+	if result.is_ok() {
+		return Ok(());
+	}
+
+	// If regular unmount fails, try lazy unmount
+	let lazy_result = command::run("umount", [OsStr::new("-l"), path_arg]).await?;
+
+	if lazy_result.status.success() {
+		return Ok(());
+	}
+
+	Err(VolumeError::from_unmount(path, &lazy_result.stderr_lossy()))
+}
+
+/// Mounts a network share with the util-linux tools, through pkexec unless we run as root: the
+/// udisks2 actions only cover block devices. The credentials of SMB shares go through a file
+/// only we can read, mount.davfs asks for them on its input.
+async fn mount_share(
+	share: &NetworkShare,
+	mount_point: &Path,
+	credentials: Option<&ShareCredentials>,
+) -> Result<(), VolumeError> {
+	// SAFETY: these can't fail
+	let (uid, gid, privileged) = unsafe { (libc::getuid(), libc::getgid(), libc::geteuid() == 0) };
+	let mut options = vec![];
+	let mut credentials_file = None;
+	let mut input = Zeroizing::new(Vec::new());
+
+	let (file_system, source) = match share.protocol {
+		ShareProtocol::Smb => {
+			options.extend([format!("uid={uid}"), format!("gid={gid}")]);
+			match credentials {
+				Some(credentials) => {
+					let file = CredentialsFile::write(credentials).await?;
+					options.push(format!("credentials={}", file.option()?));
+					credentials_file = Some(file);
+				}
+				None => options.push("guest".to_string()),
+			}
+			options.extend(share.port.map(|port| format!("port={port}")));
+			("cifs", format!("//{}/{}", share.host, share.path))
+		}
+		ShareProtocol::Nfs => {
+			// Calls to a server that went away fail instead of hanging, until it's remounted
+			options.push("soft".to_string());
+			options.extend(share.port.map(|port| format!("port={port}")));
+			("nfs", format!("{}:/{}", share.host, share.path))
+		}
+		ShareProtocol::WebDav => {
+			options.extend([format!("uid={uid}"), format!("gid={gid}")]);
+			// The username then the password, one per line, empty ones to connect anonymously.
+			// Sized up front so the secret isn't left behind by a reallocation.
+			let (username, password) = credentials.map_or(("", ""), |credentials| {
+				(credentials.username.as_str(), credentials.password.as_str())
+			});
+			input.reserve_exact(username.len() + password.len() + 2);
+			for line in [username, password] {
+				input.extend_from_slice(line.as_bytes());
+				input.push(b'\n');
+			}
+			("davfs", share.webdav_url())
+		}
+	};
+
+	let options = options.join(",");
+	let mut args = vec![
+		OsStr::new("-t"),
+		OsStr::new(file_system),
+		OsStr::new("-o"),
+		OsStr::new(&options),
+		// Nothing after this is read as an option, whatever the share is called
+		OsStr::new("--"),
+		OsStr::new(&source),
+		command::path_arg("mount", mount_point)?,
+	];
+	let output = if privileged {
+		command::run_with_input("mount", args, &input).await
+	} else {
+		args.insert(0, OsStr::new("mount"));
+		command::run_with_input("pkexec", args, &input).await
+	};
+
+	// Erased and removed before anything else, whether the mount worked or not
+	if let Some(file) = credentials_file {
+		task::spawn_blocking(move || drop(file)).await?;
+	}
+
+	let output = output?;
+	match output.status.code() {
+		Some(0) => Ok(()),
+		// pkexec exits with 126 when the authentication dialog is dismissed, 127 when refused
+		Some(126) if !privileged => Err(VolumeError::Cancelled),
+		Some(127) if !privileged => Err(VolumeError::AuthorizationDenied {
+			operation: ElevatedOperation::Mount,
+		}),
+		_ => Err(VolumeError::from_mount(mount_point, &output.stderr_lossy())),
+	}
+}
+
+/// The credentials of an SMB share in the format of mount.cifs, in a file of the runtime
+/// directory only the user can read. Overwritten with zeros before it's removed on drop,
+/// which blocks.
+struct CredentialsFile {
+	file: tempfile::NamedTempFile,
+	len: usize,
+}
+
+impl CredentialsFile {
+	async fn write(credentials: &ShareCredentials) -> Result<Self, VolumeError> {
+		let domain = credentials.domain.as_deref().unwrap_or_default();
+		let mut contents = Zeroizing::new(String::with_capacity(
+			credentials.username.len() + credentials.password.len() + domain.len() + 32,
+		));
+		for (key, value) in [
+			("username", credentials.username.as_str()),
+			("password", credentials.password.as_str()),
+			("domain", domain),
+		] {
+			if !value.is_empty() {
+				contents.push_str(key);
+				contents.push('=');
+				contents.push_str(value);
+				contents.push('\n');
+			}
+		}
+
+		let dir = network::runtime_dir();
+		task::spawn_blocking(move || -> Result<Self, VolumeError> {
+			std::fs::create_dir_all(&dir)?;
+			// Created with a random name, readable and writable by its owner only
+			let mut file = tempfile::Builder::new()
+				.prefix(".share-credentials-")
+				.tempfile_in(&dir)?;
+			file.write_all(contents.as_bytes())?;
+			file.as_file().sync_all()?;
+
+			Ok(Self {
+				file,
+				len: contents.len(),
+			})
+		})
+		.await?
+	}
+
+	/// The path, as it goes in the mount options, which are separated by commas
+	fn option(&self) -> Result<&str, VolumeError> {
+		let path = self.file.path();
+		path.to_str()
+			.filter(|path| !path.contains(','))
+			.ok_or_else(|| VolumeError::UnsafeArgument {
+				command: "mount",
+				argument: path.as_os_str().to_os_string(),
+				reason: "can't be passed as a mount option",
+			})
+	}
+
+	fn erase(&mut self) -> io::Result<()> {
+		let file = self.file.as_file_mut();
+		file.rewind()?;
+		file.write_all(&vec![0; self.len])?;
+		file.sync_all()
+	}
+}
+
+impl Drop for CredentialsFile {
+	fn drop(&mut self) {
+		// Removed right after, when the temporary file drops
+		if let Err(e) = self.erase() {
+			warn!(?e, "Failed to erase the credentials of a network share;");
+		}
+	}
+}
+
+/// The processes with a working directory, executable or open file on the volume mounted at
+/// `path`. Those of other users can't be read without root.
+async fn processes_using(path: &Path) -> Vec<BusyProcess> {
+	let mut busy = Vec::new();
+	let Ok(mut processes) = tokio::fs::read_dir("/proc").await else {
+		return busy;
+	};
+
+	while let Ok(Some(process)) = processes.next_entry().await {
+		let Some(pid) = process
+			.file_name()
+			.to_str()
+			.and_then(|name| name.parse::<u32>().ok())
+		else {
+			continue;
+		};
+
+		if uses(&process.path(), path).await {
+			let name = tokio::fs::read_to_string(process.path().join("comm"))
+				.await
+				.map(|comm| comm.trim().to_string())
+				.unwrap_or_default();
+			busy.push(BusyProcess { pid, name });
+		}
+	}
+
+	busy
+}
+
+async fn uses(process: &Path, mount_point: &Path) -> bool {
+	let on_volume = |target: io::Result<PathBuf>| target.is_ok_and(|t| t.starts_with(mount_point));
+
+	for link in ["cwd", "exe"] {
+		if on_volume(tokio::fs::read_link(process.join(link)).await) {
+			return true;
+		}
+	}
+
+	let Ok(mut fds) = tokio::fs::read_dir(process.join("fd")).await else {
+		return false;
+	};
+	while let Ok(Some(fd)) = fds.next_entry().await {
+		if on_volume(tokio::fs::read_link(fd.path()).await) {
+			return true;
+		}
+	}
+
+	false
+}
+
+/// The whole disk `device` is a partition of, or `device` itself when it isn't one. `None` for
+/// the devices sysfs doesn't know.
+async fn whole_disk(device: &OsStr) -> Option<PathBuf> {
+	let name = Path::new(device).file_name()?.to_str()?;
+	let device_dir = Path::new(SYSFS_BLOCK).join(name);
+	if tokio::fs::metadata(device_dir.join("partition"))
+		.await
+		.is_err()
+	{
+		return tokio::fs::metadata(&device_dir)
+			.await
+			.is_ok()
+			.then(|| PathBuf::from(device));
+	}
+
+	// The partitions are links into the directory of their disk
+	let resolved = tokio::fs::canonicalize(&device_dir).await.ok()?;
+	let disk = resolved.parent()?.file_name()?;
+	Some(Path::new("/dev").join(disk))
+}
+
+/// The block device mounted at `path`, `None` for the file systems without one
+async fn block_device(path: &Path) -> Result<Option<OsString>, VolumeError> {
+	let contents = tokio::fs::read(MOUNTINFO).await?;
+	Ok(mountinfo::parse(&contents)
+		.into_iter()
+		.rev()
+		.find(|mount| mount.mount_point == path && Path::new(&mount.source).starts_with("/dev"))
+		.map(|mount| mount.source))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::volume::{ByteSize, UnmountedDevice};
+
+	use std::{
+		path::{Path, PathBuf},
+		time::Duration,
+	};
+
+	use tokio::time::{interval, sleep, timeout, Instant, MissedTickBehavior};
+
+	/// On a single threaded runtime, anything blocking in the scan delays the ticker
+	#[tokio::test(flavor = "current_thread")]
+	async fn get_volumes_does_not_stall_the_runtime() {
+		let ticker = tokio::spawn(async {
+			let mut ticks = interval(Duration::from_millis(10));
+			ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+			let mut last = Instant::now();
+			loop {
+				ticks.tick().await;
+				if last.elapsed() > Duration::from_millis(250) {
+					break;
+				}
+				last = Instant::now();
+			}
+		});
+
+		scan(&LastScan::default()).await.unwrap();
+		// Lets the ticker notice a stall that happened right at the end of the scan
+		sleep(Duration::from_millis(20)).await;
+
+		assert!(
+			!ticker.is_finished(),
+			"the runtime stalled while scanning volumes"
+		);
+		ticker.abort();
+	}
+
+	#[test]
+	fn unresponsive_volumes_keep_their_last_state() {
+		let nas = Volume::new(
+			"nas:/photos".to_string(),
+			MountType::Network,
+			PathBuf::from("/mnt/photos"),
+			vec![PathBuf::from("/mnt/photos")],
+			DiskType::Unknown,
+			FileSystem::from_string("nfs4"),
+			1000,
+			400,
+			false,
+		);
+		let last_scan = LastScan::default();
+		last_scan.replace(&[nas.clone()]);
+
+		let timeout = VolumeError::Timeout(PROBE_TIMEOUT.as_secs());
+		assert_eq!(
+			last_scan.unresponsive(Path::new("/mnt/photos"), &timeout),
+			Some((nas, vec![]))
+		);
+		assert_eq!(
+			last_scan.unresponsive(Path::new("/mnt/music"), &timeout),
+			None
+		);
+	}
+
+	#[test]
+	fn finds_where_udisks_mounted_the_device() {
+		let device = Path::new("/dev/sdb1");
+		assert_eq!(
+			parse_mounted_at("Mounted /dev/sdb1 at /media/user/My Drive\n", device),
+			Some(PathBuf::from("/media/user/My Drive"))
+		);
+		// udisks2 before 2.8
+		assert_eq!(
+			parse_mounted_at("Mounted /dev/sdb1 at /media/user/USB.\n", device),
+			Some(PathBuf::from("/media/user/USB"))
+		);
+		assert_eq!(
+			parse_mounted_at("Mounted /dev/sdc1 at /media/user/USB\n", device),
+			None
+		);
+	}
+
+	#[test]
+	fn finds_the_loop_device_of_the_image() {
+		assert_eq!(
+			parse_loop_device("Mapped file /home/user/Ubuntu as CD.iso as /dev/loop12.\n"),
+			Some(PathBuf::from("/dev/loop12"))
+		);
+		assert_eq!(parse_loop_device("Error setting up loop device\n"), None);
+
+		// A partitioned image, the loop device itself holds no file system
+		assert_eq!(
+			parse_file_system_device("/dev/loop3 \n/dev/loop3p1 vfat\n/dev/loop3p2 ext4\n"),
+			Some(PathBuf::from("/dev/loop3p1"))
+		);
+		assert_eq!(
+			parse_file_system_device("/dev/loop4 iso9660\n"),
+			Some(PathBuf::from("/dev/loop4"))
+		);
+		assert_eq!(parse_file_system_device("/dev/loop5 \n"), None);
+	}
+
+	#[test]
+	fn names_the_udisks_objects_of_the_devices() {
+		assert_eq!(
+			udisks_object_path(Path::new("/dev/sdb1")).as_deref(),
+			Some("/org/freedesktop/UDisks2/block_devices/sdb1")
+		);
+		assert_eq!(
+			udisks_object_path(Path::new("/dev/dm-0")).as_deref(),
+			Some("/org/freedesktop/UDisks2/block_devices/dm_2d0")
+		);
+		assert_eq!(udisks_fs_type(&FileSystem::FAT32), Some("vfat"));
+		assert_eq!(udisks_fs_type(&FileSystem::APFS), None);
+	}
+
+	#[test]
+	fn reads_the_properties_udev_found() {
+		let udev_data = "S:disk/by-uuid/0b3f\n\
+			E:ID_FS_UUID=0b3f\n\
+			E:ID_FS_TYPE=crypto_LUKS\n\
+			E:ID_FS_TYPE_NEW=x\n";
+
+		assert_eq!(udev_property(udev_data, "ID_FS_TYPE"), Some("crypto_LUKS"));
+		assert_eq!(udev_property(udev_data, "ID_FS_UUID"), Some("0b3f"));
+		assert_eq!(udev_property(udev_data, "ID_FS_LABEL"), None);
+	}
+
+	#[test]
+	fn lists_the_file_systems_nothing_mounted() {
+		let json = br#"{
+			"blockdevices": [
+				{"name": "/dev/sda", "label": null, "fstype": null, "size": 500107862016, "mountpoint": null, "type": "disk"},
+				{"name": "/dev/sda1", "label": null, "fstype": "vfat", "size": 536870912, "mountpoint": "/boot/efi", "type": "part"},
+				{"name": "/dev/sda2", "label": null, "fstype": "swap", "size": 8589934592, "mountpoint": "[SWAP]", "type": "part"},
+				{"name": "/dev/sdb1", "label": "Backup", "fstype": "ext4", "size": "2000396746752", "mountpoint": null, "type": "part"},
+				{"name": "/dev/sdc1", "label": "", "fstype": "crypto_LUKS", "size": 1000204886016, "mountpoint": null, "type": "part"},
+				{"name": "/dev/loop0", "label": null, "fstype": "squashfs", "size": 4096, "mountpoint": null, "type": "loop"}
+			]
+		}"#;
+
+		assert_eq!(
+			parse_unmounted_devices(json).unwrap(),
+			vec![UnmountedDevice {
+				device: PathBuf::from("/dev/sdb1"),
+				label: Some("Backup".to_string()),
+				file_system: FileSystem::EXT4,
+				total_bytes_capacity: ByteSize(2_000_396_746_752),
+			}]
+		);
+	}
+
+	#[tokio::test]
+	async fn blocking_backends_wake_up_when_the_watcher_stops() {
+		let (check_tx, check_rx) = mpsc::channel(1);
+		let shutdown = shutdown_pipe(&check_tx).unwrap();
+		// Never readable while its channel is open, only the shutdown ends the wait
+		let (idle_tx, _idle_rx) = mpsc::channel::<()>(1);
+		let idle = shutdown_pipe(&idle_tx).unwrap();
+
+		let waiting = task::spawn_blocking(move || wait_for(&idle, libc::POLLIN, &shutdown));
+		drop(check_rx);
+
+		let still_listening = timeout(Duration::from_secs(5), waiting)
+			.await
+			.expect("the wait wasn't interrupted")
+			.unwrap()
+			.unwrap();
+		assert!(!still_listening);
+	}
+
+	#[test]
+	fn only_block_device_uevents_trigger_checks() {
+		assert!(is_block_uevent(
+			b"add@/devices/pci0000:00/usb1/1-1/block/sdb\0ACTION=add\0\
+			  DEVPATH=/devices/pci0000:00/usb1/1-1/block/sdb\0SUBSYSTEM=block\0DEVNAME=sdb\0"
+		));
+		assert!(!is_block_uevent(
+			b"change@/devices/virtual/net/wlan0\0ACTION=change\0SUBSYSTEM=net\0"
+		));
+		assert!(is_block_uevent(
+			b"change@/devices/virtual/block/loop0\0ACTION=change\0SUBSYSTEM=block\0"
+		));
+		assert!(is_block_uevent(
+			b"remove@/devices/virtual/block/dm-0\0ACTION=remove\0SUBSYSTEM=block\0"
+		));
+		// Binding a driver or onlining the device doesn't change the volumes
+		assert!(!is_block_uevent(
+			b"bind@/devices/pci0000:00/usb1/1-1\0ACTION=bind\0SUBSYSTEM=block\0"
+		));
+		assert!(!is_block_uevent(
+			b"online@/devices/system/block/sdb\0ACTION=online\0SUBSYSTEM=block\0"
+		));
+		// Only whole fields count
+		assert!(!is_block_uevent(b"ACTION=add\0SUBSYSTEM=blockdev\0"));
+		assert!(!is_block_uevent(b"ACTION=added\0SUBSYSTEM=block\0"));
+	}
+
+	#[test]
+	fn finds_who_a_volume_was_mounted_for() {
+		assert_eq!(
+			owner_from_path(Path::new("/run/media/alice/USB")),
+			Some("alice")
+		);
+		assert_eq!(
+			owner_from_path(Path::new("/media/bob/My Drive")),
+			Some("bob")
+		);
+		assert_eq!(owner_from_path(Path::new("/media/usb")), None);
+		assert_eq!(owner_from_path(Path::new("/media/bob/USB/nested")), None);
+		assert_eq!(owner_from_path(Path::new("/mnt/alice/USB")), None);
+
+		let mounts = crate::volume::mountinfo::parse(
+			b"61 22 8:17 / /mnt/usb rw,nosuid - vfat /dev/sdb1 rw,uid=1001,gid=1001\n\
+			 62 22 0:52 / /mnt/sshfs rw - fuse.sshfs host: rw,user_id=1002,group_id=1002\n\
+			 63 22 8:18 / /mnt/data rw - ext4 /dev/sdb2 rw\n",
+		);
+		assert_eq!(owner_from_options(&mounts[0]), Some(1001));
+		assert_eq!(owner_from_options(&mounts[1]), Some(1002));
+		assert_eq!(owner_from_options(&mounts[2]), None);
+
+		assert_eq!(
+			parse_seats("NAME=alice\nSTATE=active\nSEATS=seat0 seat1\nACTIVE_SEATS=seat0\n"),
+			["seat0", "seat1"]
+		);
+		assert!(parse_seats("NAME=alice\nSTATE=lingering\n").is_empty());
+	}
+
+	#[test]
+	fn parses_the_mount_table() {
+		let mounts = crate::volume::mountinfo::parse(
+			b"22 1 8:2 / / rw,relatime shared:1 - ext4 /dev/sda2 rw\n\
+			 35 22 0:31 / /proc rw,nosuid - proc proc rw\n\
+			 61 22 8:17 / /media/My\\040Drive ro,nosuid,nodev - vfat /dev/sdb1 ro\n\
+			 garbage\n",
+		);
+
+		assert_eq!(mounts.len(), 3);
+		assert_eq!(mounts[0].source, "/dev/sda2");
+		assert!(!mounts[0].is_read_only());
+		assert_eq!(mounts[1].file_system, "proc");
+		assert_eq!(mounts[2].mount_point, Path::new("/media/My Drive"));
+		assert!(mounts[2].is_read_only());
+	}
+
+	#[test]
+	fn lists_the_btrfs_snapshots() {
+		let snapshots = parse_btrfs_snapshots(
+			"ID 262 gen 51 cgen 51 top level 256 otime 2024-03-01 09:30:00 path @home/.snapshots/1/snapshot\n\
+			 ID 270 gen 60 cgen 60 top level 5 otime - path @snapshots/root-2024-03-02\n\
+			 garbage\n",
+			Path::new("/home"),
+			Path::new("/@home"),
+		);
+
+		assert_eq!(snapshots.len(), 2);
+		assert_eq!(snapshots[0].id, "262");
+		assert_eq!(snapshots[0].name, "snapshot");
+		assert!(snapshots[0].created_at.is_some());
+		assert_eq!(
+			snapshots[0].path.as_deref(),
+			Some(Path::new("/home/.snapshots/1/snapshot"))
+		);
+		// Outside of the mounted subvolume, it has to be mounted to be browsed
+		assert_eq!(snapshots[1].name, "root-2024-03-02");
+		assert_eq!(snapshots[1].created_at, None);
+		assert_eq!(snapshots[1].path, None);
+	}
+}
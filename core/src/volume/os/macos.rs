@@ -0,0 +1,815 @@
+//! macOS, from the mount table and `diskutil`
+
+use super::common::{c_string, local_time, DegradedModeTracker};
+use super::*;
+use crate::volume::{
+	command,
+	retry::{retry, RetryPolicy},
+	AttachedImage, ByteSize, DeviceCapabilities, ElevatedOperation, Elevation, ElevationScope,
+	EncryptionKind, FileSystemSnapshot, MissingVolumeData, MountOptions, UnmountedDevice,
+};
+use serde::Deserialize;
+use std::{ffi::OsStr, io, mem, path::PathBuf};
+use tracing::warn;
+
+/// The mount flags of `sys/mount.h` the volumes are told apart with
+const MNT_RDONLY: u32 = 0x0000_0001;
+const MNT_LOCAL: u32 = 0x0000_1000;
+const MNT_ROOTFS: u32 = 0x0000_4000;
+const MNT_REMOVABLE: u32 = 0x0000_0200;
+/// Set on the volumes Finder hides, like the VM and Preboot volumes of the system container
+const MNT_DONTBROWSE: u32 = 0x0010_0000;
+
+/// Where the writable half of the system volume is mounted, it's the same volume to the user
+const DATA_VOLUME: &str = "/System/Volumes/Data";
+
+#[derive(Debug, Default)]
+pub struct MacOsPlatform {
+	degraded_mode: DegradedModeTracker,
+	elevation: Elevation,
+}
+
+/// An entry of the mount table
+#[derive(Debug, Clone)]
+pub(super) struct Mount {
+	pub mount_point: PathBuf,
+	/// Like `/dev/disk3s1`, or `//user@server/share` for network shares
+	pub device: String,
+	pub file_system: String,
+	pub flags: u32,
+	pub total_bytes: u64,
+	pub available_bytes: u64,
+}
+
+/// What `diskutil info -plist` says about a volume, every key is left out on some of them
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub(super) struct DiskInfo {
+	pub volume_name: Option<String>,
+	pub filesystem_type: Option<String>,
+	pub solid_state: Option<bool>,
+	pub fusion: Option<bool>,
+	pub internal: Option<bool>,
+	pub removable_media_or_external_device: Option<bool>,
+	pub ejectable: Option<bool>,
+	/// "Physical", or "Virtual" for disk images and APFS containers on them
+	pub virtual_or_physical: Option<String>,
+	/// Like "USB" or "PCI-Express", "Disk Image" for the attached images
+	pub bus_protocol: Option<String>,
+	pub file_vault: Option<bool>,
+	/// Also set for the encrypted APFS volumes FileVault doesn't manage, like external ones
+	pub encryption: Option<bool>,
+	pub locked: Option<bool>,
+	/// Empty while unmounted
+	pub mount_point: Option<PathBuf>,
+	#[serde(rename = "VolumeUUID")]
+	pub volume_uuid: Option<String>,
+	/// The UUID of the partition, for the file systems without one like FAT
+	#[serde(rename = "DiskUUID")]
+	pub disk_uuid: Option<String>,
+}
+
+/// What `diskutil list -plist` says about the disks, only the parts read here
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub(super) struct DiskList {
+	#[serde(default)]
+	pub all_disks_and_partitions: Vec<ListedDisk>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub(super) struct ListedDisk {
+	pub partitions: Vec<ListedPartition>,
+	#[serde(rename = "APFSVolumes")]
+	pub apfs_volumes: Vec<ListedPartition>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub(super) struct ListedPartition {
+	pub device_identifier: String,
+	/// The partition type, like `Apple_HFS` or `EFI`
+	pub content: Option<String>,
+	pub size: u64,
+	pub mount_point: Option<String>,
+	/// The APFS volumes macOS keeps to itself, like Preboot or VM
+	#[serde(rename = "OSInternal")]
+	pub os_internal: bool,
+}
+
+/// What `hdiutil attach -plist` says about the devices of the image
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct AttachOutput {
+	#[serde(rename = "system-entities", default)]
+	pub system_entities: Vec<AttachedEntity>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct AttachedEntity {
+	#[serde(rename = "dev-entry")]
+	pub dev_entry: PathBuf,
+	#[serde(rename = "mount-point")]
+	pub mount_point: Option<PathBuf>,
+}
+
+/// The snapshots `tmutil listlocalsnapshots` printed, one name a line after a `Snapshots for
+/// disk /:` header
+pub(super) fn parse_local_snapshots(stdout: &str) -> Vec<FileSystemSnapshot> {
+	stdout
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.ends_with(':'))
+		.map(|name| FileSystemSnapshot {
+			id: name.to_string(),
+			name: name.to_string(),
+			// Named after when they were taken, `.local` only since Big Sur
+			created_at: name
+				.strip_prefix("com.apple.TimeMachine.")
+				.map(|date| date.trim_end_matches(".local"))
+				.and_then(|date| local_time(date, "%Y-%m-%d-%H%M%S")),
+			path: None,
+		})
+		.collect()
+}
+
+/// The whole disk the image was attached as, listed first, and where its file system was
+/// mounted
+pub(super) fn attached_at(output: AttachOutput) -> Option<(PathBuf, PathBuf)> {
+	let disk = output.system_entities.first()?.dev_entry.clone();
+	let mount_point = output
+		.system_entities
+		.into_iter()
+		.find_map(|entity| entity.mount_point)?;
+
+	Some((disk, mount_point))
+}
+
+/// Partitions that hold no file system to mount, or one only macOS uses
+const UNMOUNTABLE_CONTENTS: [&str; 5] = [
+	"EFI",
+	"Apple_APFS",
+	"Apple_APFS_ISC",
+	"Apple_APFS_Recovery",
+	"Apple_Boot",
+];
+
+/// The identifiers and sizes of the listed partitions and APFS volumes nothing mounted
+pub(super) fn unmounted_identifiers(list: DiskList) -> Vec<(String, u64)> {
+	list.all_disks_and_partitions
+		.into_iter()
+		.flat_map(|disk| disk.partitions.into_iter().chain(disk.apfs_volumes))
+		.filter(|partition| {
+			partition
+				.mount_point
+				.as_deref()
+				.unwrap_or_default()
+				.is_empty() && !partition.os_internal
+				&& !partition
+					.content
+					.as_deref()
+					.is_some_and(|content| UNMOUNTABLE_CONTENTS.contains(&content))
+		})
+		.map(|partition| (partition.device_identifier, partition.size))
+		.collect()
+}
+
+#[async_trait]
+impl VolumePlatform for MacOsPlatform {
+	async fn get_volumes(&self) -> Result<Vec<Volume>, VolumeError> {
+		let mounts = task::spawn_blocking(read_mounts).await??;
+
+		let mut missing = Vec::new();
+		let mut volumes = Vec::new();
+		for mount in visible_mounts(&mounts) {
+			let info = if mount.device.starts_with("/dev/") {
+				match disk_info(&mount.device).await {
+					Ok(info) => Some(info),
+					Err(e) => {
+						warn!(?e, device = mount.device, "Failed to read the disk info;");
+						missing.push(MissingVolumeData::DeviceInfo);
+						None
+					}
+				}
+			} else {
+				None
+			};
+
+			volumes.push(volume_from(mount, info.as_ref()));
+		}
+
+		merge_data_volume(&mut volumes, &mounts);
+		self.degraded_mode.update(DegradedMode::new(missing));
+
+		Ok(volumes)
+	}
+
+	async fn unmount_volume(&self, path: &Path) -> Result<(), VolumeError> {
+		let path_arg = command::path_arg("diskutil", path)?;
+		let scope = ElevationScope::new(ElevatedOperation::Unmount, path);
+
+		// A volume stays busy while Spotlight or Finder let go of it
+		retry(
+			RetryPolicy::OPERATION,
+			"diskutil unmount",
+			VolumeError::is_retriable,
+			|| async {
+				self.elevation
+					.run_or_elevate(
+						&scope,
+						"diskutil",
+						&[OsStr::new("unmount"), path_arg],
+						|stderr| VolumeError::from_unmount(path, stderr),
+					)
+					.await
+					.map(|_| ())
+			},
+		)
+		.await
+	}
+
+	async fn mount_volume(
+		&self,
+		device: &Path,
+		options: &MountOptions,
+	) -> Result<PathBuf, VolumeError> {
+		let device_arg = command::path_arg("diskutil", device)?;
+		let args = if options.read_only {
+			vec![OsStr::new("mount"), OsStr::new("readOnly"), device_arg]
+		} else {
+			vec![OsStr::new("mount"), device_arg]
+		};
+
+		self.elevation
+			.run_or_elevate(
+				&ElevationScope::new(ElevatedOperation::Mount, device),
+				"diskutil",
+				&args,
+				|stderr| VolumeError::from_mount(device, stderr),
+			)
+			.await?;
+
+		// diskutil only says the volume was mounted, not where
+		let device_name = device.to_string_lossy();
+		disk_info(&device_name)
+			.await?
+			.mount_point
+			.filter(|mount_point| !mount_point.as_os_str().is_empty())
+			.ok_or_else(|| VolumeError::MountFailed {
+				device: device.to_path_buf(),
+				reason: "diskutil doesn't list a mount point after mounting".to_string(),
+			})
+	}
+
+	async fn unmounted_devices(&self) -> Result<Vec<UnmountedDevice>, VolumeError> {
+		let output = command::run("diskutil", ["list", "-plist"]).await?;
+		if !output.status.success() {
+			return Err(VolumeError::DeviceError(format!(
+				"diskutil list: {}",
+				output.stderr_lossy().trim()
+			)));
+		}
+		let list = plist::from_bytes::<DiskList>(&output.stdout)
+			.map_err(|e| VolumeError::DeviceError(format!("diskutil list: {e}")))?;
+
+		let mut devices = Vec::new();
+		for (identifier, size) in unmounted_identifiers(list) {
+			let device = format!("/dev/{identifier}");
+			let info = match disk_info(&device).await {
+				Ok(info) => info,
+				Err(e) => {
+					warn!(?e, %device, "Failed to read an unmounted device;");
+					continue;
+				}
+			};
+			let Some(file_system_type) = info.filesystem_type.filter(|fs| !fs.is_empty()) else {
+				continue;
+			};
+
+			devices.push(UnmountedDevice {
+				device: PathBuf::from(device),
+				label: info.volume_name.filter(|name| !name.is_empty()),
+				file_system: file_system(&file_system_type, Some(&file_system_type)),
+				total_bytes_capacity: ByteSize(size),
+			});
+		}
+
+		Ok(devices)
+	}
+
+	/// Attaches the image with hdiutil, browsable so the volume is listed like the ones Finder
+	/// opens
+	async fn attach_disk_image(
+		&self,
+		image: &Path,
+		read_only: bool,
+	) -> Result<AttachedImage, VolumeError> {
+		let mut args = vec![
+			OsStr::new("attach"),
+			OsStr::new("-plist"),
+			OsStr::new("-noverify"),
+			OsStr::new("-noautoopen"),
+		];
+		if read_only {
+			args.push(OsStr::new("-readonly"));
+		}
+		args.push(command::path_arg("hdiutil", image)?);
+
+		let output = command::run("hdiutil", args).await?;
+		if !output.status.success() {
+			return Err(VolumeError::from_mount(image, &output.stderr_lossy()));
+		}
+
+		let (device, mount_point) = plist::from_bytes::<AttachOutput>(&output.stdout)
+			.ok()
+			.and_then(attached_at)
+			.ok_or_else(|| VolumeError::InvalidDiskImage {
+				path: image.to_path_buf(),
+				reason: "no file system was mounted from it",
+			})?;
+
+		Ok(AttachedImage {
+			image: image.to_path_buf(),
+			device,
+			mount_point,
+		})
+	}
+
+	/// The local snapshots Time Machine took of the volume
+	async fn list_snapshots(
+		&self,
+		mount_point: &Path,
+	) -> Result<Vec<FileSystemSnapshot>, VolumeError> {
+		let output = command::run(
+			"tmutil",
+			[
+				OsStr::new("listlocalsnapshots"),
+				command::path_arg("tmutil", mount_point)?,
+			],
+		)
+		.await?;
+		if !output.status.success() {
+			return Err(VolumeError::DeviceError(format!(
+				"tmutil listlocalsnapshots: {}",
+				output.stderr_lossy().trim()
+			)));
+		}
+
+		Ok(parse_local_snapshots(&output.stdout_lossy()))
+	}
+
+	async fn mount_snapshot(
+		&self,
+		mount_point: &Path,
+		snapshot: &FileSystemSnapshot,
+		target: &Path,
+	) -> Result<(), VolumeError> {
+		// The snapshots of the system volume are the ones of its data volume
+		let volume = if mount_point == Path::new("/") {
+			Path::new(DATA_VOLUME)
+		} else {
+			mount_point
+		};
+
+		// Hidden from Finder, and from the volume list along with it
+		let output = command::run(
+			"mount_apfs",
+			[
+				OsStr::new("-o"),
+				OsStr::new("rdonly,nobrowse"),
+				OsStr::new("-s"),
+				OsStr::new(&snapshot.id),
+				command::path_arg("mount_apfs", volume)?,
+				command::path_arg("mount_apfs", target)?,
+			],
+		)
+		.await?;
+		if output.status.success() {
+			Ok(())
+		} else {
+			Err(VolumeError::from_mount(target, &output.stderr_lossy()))
+		}
+	}
+
+	async fn detach_disk_image(&self, image: &AttachedImage) -> Result<(), VolumeError> {
+		let device_arg = command::path_arg("hdiutil", &image.device)?;
+
+		// Like unmounting, Spotlight or Finder may hold on to the volume for a moment
+		retry(
+			RetryPolicy::OPERATION,
+			"hdiutil detach",
+			VolumeError::is_retriable,
+			|| async {
+				let output = command::run("hdiutil", [OsStr::new("detach"), device_arg]).await?;
+				if output.status.success() {
+					Ok(())
+				} else {
+					Err(VolumeError::from_unmount(
+						&image.mount_point,
+						&output.stderr_lossy(),
+					))
+				}
+			},
+		)
+		.await
+	}
+
+	async fn watch(
+		&self,
+		backend: WatcherBackend,
+		check_tx: mpsc::Sender<()>,
+	) -> Result<Option<JoinHandle<()>>, VolumeError> {
+		match backend {
+			WatcherBackend::Native => super::disk_arbitration::watch(check_tx).await,
+			_ => Err(VolumeError::UnsupportedPlatform {
+				operation: "watching without DiskArbitration",
+			}),
+		}
+	}
+
+	fn degraded_mode(&self) -> DegradedMode {
+		self.degraded_mode.get()
+	}
+
+	fn capabilities(&self) -> DeviceCapabilities {
+		DeviceCapabilities {
+			native_watch: true,
+			mount: true,
+			unmount: true,
+			disk_images: true,
+			elevation: self.elevation.is_available(),
+			..Default::default()
+		}
+	}
+}
+
+/// The mount table, read with `getfsstat` into a buffer of our own: the one of `getmntinfo`
+/// is shared by every thread
+fn read_mounts() -> io::Result<Vec<Mount>> {
+	// SAFETY: a null buffer only asks for the number of mounts
+	let count = unsafe { libc::getfsstat(std::ptr::null_mut(), 0, libc::MNT_NOWAIT) };
+	if count < 0 {
+		return Err(io::Error::last_os_error());
+	}
+
+	// Room for the mounts made between the two calls
+	let capacity = count as usize + 8;
+	let mut entries = Vec::<libc::statfs>::with_capacity(capacity);
+	// SAFETY: the buffer has room for `capacity` entries of the size passed
+	let count = unsafe {
+		libc::getfsstat(
+			entries.as_mut_ptr(),
+			(capacity * mem::size_of::<libc::statfs>()) as libc::c_int,
+			libc::MNT_NOWAIT,
+		)
+	};
+	if count < 0 {
+		return Err(io::Error::last_os_error());
+	}
+	// SAFETY: getfsstat filled in the first `count` entries
+	unsafe { entries.set_len(count as usize) };
+
+	Ok(entries.iter().map(Mount::from_statfs).collect())
+}
+
+impl Mount {
+	fn from_statfs(entry: &libc::statfs) -> Self {
+		let block_size = u64::from(entry.f_bsize);
+		Self {
+			mount_point: PathBuf::from(c_string(&entry.f_mntonname)),
+			device: c_string(&entry.f_mntfromname),
+			file_system: c_string(&entry.f_fstypename),
+			flags: entry.f_flags,
+			total_bytes: entry.f_blocks.saturating_mul(block_size),
+			available_bytes: entry.f_bavail.saturating_mul(block_size),
+		}
+	}
+}
+
+/// The mounts the user sees in Finder, without the file systems of the kernel
+pub(super) fn visible_mounts(mounts: &[Mount]) -> impl Iterator<Item = &Mount> {
+	mounts.iter().filter(|mount| {
+		mount.flags & MNT_DONTBROWSE == 0 && !common::is_virtual_filesystem(&mount.file_system)
+	})
+}
+
+async fn disk_info(device: &str) -> Result<DiskInfo, VolumeError> {
+	let device_arg = command::path_arg("diskutil", Path::new(device))?;
+	let output = command::run(
+		"diskutil",
+		[OsStr::new("info"), OsStr::new("-plist"), device_arg],
+	)
+	.await?;
+	if !output.status.success() {
+		return Err(VolumeError::DeviceError(format!(
+			"diskutil info {device}: {}",
+			output.stderr_lossy().trim()
+		)));
+	}
+
+	plist::from_bytes(&output.stdout)
+		.map_err(|e| VolumeError::DeviceError(format!("diskutil info {device}: {e}")))
+}
+
+pub(super) fn volume_from(mount: &Mount, info: Option<&DiskInfo>) -> Volume {
+	let info = info.cloned().unwrap_or_default();
+
+	let mount_type =
+		if common::is_network_filesystem(&mount.file_system) || mount.flags & MNT_LOCAL == 0 {
+			MountType::Network
+		} else if mount.flags & MNT_ROOTFS != 0 || mount.mount_point == Path::new("/") {
+			MountType::System
+		} else if info.bus_protocol.as_deref() == Some("Disk Image") {
+			MountType::DiskImage
+		} else if info.virtual_or_physical.as_deref() == Some("Virtual") {
+			MountType::Virtual
+		} else if mount.flags & MNT_REMOVABLE != 0
+			|| info.internal == Some(false)
+			|| info.removable_media_or_external_device == Some(true)
+			|| info.ejectable == Some(true)
+		{
+			MountType::External
+		} else {
+			MountType::System
+		};
+
+	let disk_type = match (info.fusion, info.solid_state) {
+		(Some(true), _) => DiskType::Fusion,
+		(_, Some(true)) => DiskType::SSD,
+		(_, Some(false)) => DiskType::HDD,
+		_ => DiskType::Unknown,
+	};
+
+	let name = info
+		.volume_name
+		.filter(|name| !name.is_empty())
+		.or_else(|| {
+			mount
+				.mount_point
+				.file_name()
+				.map(|name| name.to_string_lossy().into_owned())
+		})
+		.unwrap_or_else(|| mount.device.clone());
+
+	let mut volume = Volume::new(
+		name,
+		mount_type,
+		mount.mount_point.clone(),
+		vec![mount.mount_point.clone()],
+		disk_type,
+		file_system(&mount.file_system, info.filesystem_type.as_deref()),
+		mount.total_bytes,
+		mount.available_bytes,
+		mount.flags & MNT_RDONLY != 0,
+	);
+	if info.file_vault == Some(true) || info.encryption == Some(true) {
+		volume.encryption = Some(EncryptionKind::FileVault);
+		volume.is_locked = info.locked == Some(true);
+	}
+	volume.serial = info.volume_uuid.or(info.disk_uuid);
+
+	volume
+}
+
+/// The mount table names HFS+ `hfs` and FAT `msdos`, diskutil tells FAT32 apart
+pub(super) fn file_system(mounted: &str, diskutil: Option<&str>) -> FileSystem {
+	match mounted {
+		"hfs" => FileSystem::HFSPlus,
+		"msdos" if diskutil.is_some_and(|fs| fs.eq_ignore_ascii_case("msdos")) => FileSystem::FAT32,
+		other => FileSystem::from_string(other),
+	}
+}
+
+/// Since Catalina `/` is a read-only snapshot and the user's files live on the data volume
+/// mounted under it, both are reported as one volume, writable if the data volume is
+pub(super) fn merge_data_volume(volumes: &mut [Volume], mounts: &[Mount]) {
+	let Some(data) = mounts
+		.iter()
+		.find(|mount| mount.mount_point == Path::new(DATA_VOLUME))
+	else {
+		return;
+	};
+
+	if let Some(root) = volumes
+		.iter_mut()
+		.find(|volume| volume.mount_point == Path::new("/"))
+	{
+		root.mount_points.push(data.mount_point.clone());
+		root.read_only = data.flags & MNT_RDONLY != 0;
+		root.total_bytes_available = ByteSize(data.available_bytes);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn mount(mount_point: &str, device: &str, file_system: &str, flags: u32) -> Mount {
+		Mount {
+			mount_point: PathBuf::from(mount_point),
+			device: device.to_string(),
+			file_system: file_system.to_string(),
+			flags,
+			total_bytes: 500_000_000_000,
+			available_bytes: 200_000_000_000,
+		}
+	}
+
+	#[test]
+	fn reads_the_disk_info_of_diskutil() {
+		let plist = br#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+<key>VolumeName</key><string>Backup</string>
+<key>FilesystemType</key><string>hfs</string>
+<key>SolidState</key><false/>
+<key>Internal</key><false/>
+<key>Ejectable</key><true/>
+<key>VirtualOrPhysical</key><string>Physical</string>
+</dict>
+</plist>"#;
+		let info = plist::from_bytes::<DiskInfo>(plist).unwrap();
+
+		let volume = volume_from(
+			&mount("/Volumes/Backup", "/dev/disk4s2", "hfs", MNT_LOCAL),
+			Some(&info),
+		);
+		assert_eq!(volume.name, "Backup");
+		assert_eq!(volume.mount_type, MountType::External);
+		assert_eq!(volume.disk_type, DiskType::HDD);
+		assert_eq!(volume.file_system, FileSystem::HFSPlus);
+		assert!(!volume.read_only);
+		assert_eq!(volume.encryption, None);
+
+		let encrypted = plist::from_bytes::<DiskInfo>(
+			br#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+<key>FilesystemType</key><string>apfs</string>
+<key>FileVault</key><true/>
+<key>Encryption</key><true/>
+<key>Locked</key><false/>
+</dict>
+</plist>"#,
+		)
+		.unwrap();
+		let volume = volume_from(
+			&mount("/System/Volumes/Data", "/dev/disk3s5", "apfs", MNT_LOCAL),
+			Some(&encrypted),
+		);
+		assert_eq!(volume.encryption, Some(EncryptionKind::FileVault));
+		assert!(!volume.is_locked);
+	}
+
+	#[test]
+	fn finds_where_hdiutil_attached_the_image() {
+		let plist = br#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+<key>system-entities</key>
+<array>
+	<dict>
+		<key>content-hint</key><string>GUID_partition_scheme</string>
+		<key>dev-entry</key><string>/dev/disk4</string>
+	</dict>
+	<dict>
+		<key>content-hint</key><string>Apple_HFS</string>
+		<key>dev-entry</key><string>/dev/disk4s1</string>
+		<key>mount-point</key><string>/Volumes/Installer</string>
+	</dict>
+</array>
+</dict>
+</plist>"#;
+		let output = plist::from_bytes::<AttachOutput>(plist).unwrap();
+		assert_eq!(
+			attached_at(output),
+			Some((
+				PathBuf::from("/dev/disk4"),
+				PathBuf::from("/Volumes/Installer")
+			))
+		);
+		assert_eq!(attached_at(AttachOutput::default()), None);
+
+		let info = DiskInfo {
+			bus_protocol: Some("Disk Image".to_string()),
+			virtual_or_physical: Some("Virtual".to_string()),
+			..Default::default()
+		};
+		let volume = volume_from(
+			&mount("/Volumes/Installer", "/dev/disk4s1", "hfs", MNT_LOCAL),
+			Some(&info),
+		);
+		assert_eq!(volume.mount_type, MountType::DiskImage);
+	}
+
+	#[test]
+	fn lists_the_partitions_nothing_mounted() {
+		let plist = br#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+<key>AllDisksAndPartitions</key>
+<array>
+	<dict>
+		<key>DeviceIdentifier</key><string>disk3</string>
+		<key>APFSVolumes</key>
+		<array>
+			<dict>
+				<key>DeviceIdentifier</key><string>disk3s1</string>
+				<key>MountPoint</key><string>/</string>
+				<key>OSInternal</key><false/>
+				<key>Size</key><integer>494384795648</integer>
+			</dict>
+			<dict>
+				<key>DeviceIdentifier</key><string>disk3s2</string>
+				<key>OSInternal</key><true/>
+				<key>Size</key><integer>494384795648</integer>
+			</dict>
+		</array>
+	</dict>
+	<dict>
+		<key>DeviceIdentifier</key><string>disk4</string>
+		<key>Partitions</key>
+		<array>
+			<dict>
+				<key>Content</key><string>EFI</string>
+				<key>DeviceIdentifier</key><string>disk4s1</string>
+				<key>Size</key><integer>209715200</integer>
+			</dict>
+			<dict>
+				<key>Content</key><string>Apple_HFS</string>
+				<key>DeviceIdentifier</key><string>disk4s2</string>
+				<key>Size</key><integer>999995129856</integer>
+				<key>VolumeName</key><string>Backup</string>
+			</dict>
+		</array>
+	</dict>
+</array>
+</dict>
+</plist>"#;
+		let list = plist::from_bytes::<DiskList>(plist).unwrap();
+
+		assert_eq!(
+			unmounted_identifiers(list),
+			vec![("disk4s2".to_string(), 999_995_129_856)]
+		);
+	}
+
+	#[test]
+	fn reports_the_system_and_data_volumes_as_one() {
+		let mounts = [
+			mount(
+				"/",
+				"/dev/disk3s1s1",
+				"apfs",
+				MNT_LOCAL | MNT_ROOTFS | MNT_RDONLY,
+			),
+			mount(
+				"/System/Volumes/VM",
+				"/dev/disk3s6",
+				"apfs",
+				MNT_LOCAL | MNT_DONTBROWSE,
+			),
+			mount(
+				DATA_VOLUME,
+				"/dev/disk3s5",
+				"apfs",
+				MNT_LOCAL | MNT_DONTBROWSE,
+			),
+			mount("/dev", "devfs", "devfs", MNT_LOCAL),
+			mount("/Volumes/share", "//me@nas/share", "smbfs", 0),
+		];
+
+		let mut volumes = visible_mounts(&mounts)
+			.map(|mount| volume_from(mount, None))
+			.collect::<Vec<_>>();
+		merge_data_volume(&mut volumes, &mounts);
+
+		assert_eq!(volumes.len(), 2);
+		assert_eq!(volumes[0].mount_type, MountType::System);
+		assert_eq!(
+			volumes[0].mount_points,
+			[PathBuf::from("/"), PathBuf::from(DATA_VOLUME)]
+		);
+		assert!(!volumes[0].read_only);
+		assert_eq!(volumes[1].mount_type, MountType::Network);
+		assert_eq!(volumes[1].name, "share");
+	}
+
+	#[test]
+	fn lists_the_local_snapshots() {
+		let snapshots = parse_local_snapshots(
+			"Snapshots for disk /:\n\
+			 com.apple.TimeMachine.2024-03-01-093000.local\n\
+			 com.apple.os.update-1A2B3C\n\n",
+		);
+
+		assert_eq!(snapshots.len(), 2);
+		assert_eq!(
+			snapshots[0].id,
+			"com.apple.TimeMachine.2024-03-01-093000.local"
+		);
+		assert!(snapshots[0].created_at.is_some());
+		assert_eq!(snapshots[1].name, "com.apple.os.update-1A2B3C");
+		assert_eq!(snapshots[1].created_at, None);
+	}
+}
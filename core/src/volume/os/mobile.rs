@@ -0,0 +1,27 @@
+use super::*;
+
+/// The mobile shells find the volumes through their native APIs, see
+/// `Node::new_with_volume_platform`. Without them there is nothing to mount.
+#[derive(Debug, Default)]
+pub struct MobilePlatform;
+
+#[async_trait]
+impl VolumePlatform for MobilePlatform {
+	async fn get_volumes(&self) -> Result<Vec<Volume>, VolumeError> {
+		Ok(Vec::new())
+	}
+
+	async fn unmount_volume(&self, _path: &Path) -> Result<(), VolumeError> {
+		Err(VolumeError::UnsupportedPlatform {
+			operation: "unmounting",
+		})
+	}
+
+	async fn watch(
+		&self,
+		_backend: WatcherBackend,
+		_check_tx: mpsc::Sender<()>,
+	) -> Result<Option<JoinHandle<()>>, VolumeError> {
+		Ok(None)
+	}
+}
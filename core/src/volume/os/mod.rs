@@ -0,0 +1,155 @@
+//! The [`VolumePlatform`] of each OS, [`super::system_platform`] picks the one to use.
+//!
+//! Everything here runs on the async runtime: blocking calls go through `spawn_blocking` and
+//! system tools through the shared command executor. The std calls that would block a runtime
+//! thread are disallowed in `core/clippy.toml`.
+#![deny(clippy::disallowed_methods)]
+
+use super::error::VolumeError;
+use super::platform::VolumePlatform;
+use super::types::{DegradedMode, DiskType, FileSystem, MountType, Volume};
+use super::watcher::WatcherBackend;
+use async_trait::async_trait;
+use std::path::Path;
+use tokio::{
+	sync::mpsc,
+	task::{self, JoinHandle},
+};
+
+#[cfg(target_os = "macos")]
+mod disk_arbitration;
+
+#[cfg(target_os = "windows")]
+mod device_change;
+
+#[cfg(target_os = "freebsd")]
+pub mod freebsd;
+
+#[cfg(not(any(
+	target_os = "linux",
+	target_os = "macos",
+	target_os = "windows",
+	target_os = "freebsd",
+	target_os = "ios",
+	target_os = "android"
+)))]
+pub mod generic;
+
+#[cfg(target_os = "linux")]
+pub mod linux;
+
+#[cfg(target_os = "macos")]
+pub mod macos;
+
+#[cfg(any(target_os = "ios", target_os = "android"))]
+pub mod mobile;
+
+#[cfg(target_os = "windows")]
+pub mod windows;
+
+/// Common utilities for volume detection across platforms
+mod common {
+	/// How often the blocking backends check whether the watcher is still listening
+	#[cfg(any(target_os = "macos", target_os = "windows"))]
+	pub const WATCH_WAKEUP: std::time::Duration = std::time::Duration::from_secs(1);
+
+	pub fn is_virtual_filesystem(fs: &str) -> bool {
+		matches!(
+			fs.to_lowercase().as_str(),
+			"devfs"
+				| "sysfs" | "proc"
+				| "tmpfs" | "ramfs"
+				| "devtmpfs" | "cgroup"
+				| "cgroup2" | "devpts"
+				| "mqueue" | "securityfs"
+				| "debugfs" | "tracefs"
+				| "pstore" | "bpf"
+				| "autofs" | "hugetlbfs"
+				| "fusectl" | "configfs"
+				| "binfmt_misc"
+				| "nsfs" | "efivarfs"
+				| "rpc_pipefs"
+				| "selinuxfs"
+				| "procfs" | "fdescfs"
+				| "linprocfs"
+				| "linsysfs"
+		)
+	}
+
+	pub fn is_network_filesystem(fs: &str) -> bool {
+		matches!(
+			fs.to_lowercase().as_str(),
+			"cifs" | "smb3" | "smbfs" | "nfs" | "nfs4" | "davfs" | "fuse.sshfs" | "afpfs"
+		)
+	}
+
+	/// A string field of `statfs`
+	#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+	pub fn c_string(chars: &[std::os::raw::c_char]) -> String {
+		// The fields are nul-terminated, unless the name fills them
+		let bytes = chars
+			.iter()
+			.map(|c| *c as u8)
+			.take_while(|b| *b != 0)
+			.collect::<Vec<_>>();
+		String::from_utf8_lossy(&bytes).into_owned()
+	}
+
+	/// A date the snapshot tools print in the local time zone, `None` when it's ambiguous
+	#[cfg(any(target_os = "linux", target_os = "macos"))]
+	pub fn local_time(date: &str, format: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+		use chrono::TimeZone;
+
+		let date = chrono::NaiveDateTime::parse_from_str(date, format).ok()?;
+		chrono::Local
+			.from_local_datetime(&date)
+			.single()
+			.map(|date| date.with_timezone(&chrono::Utc))
+	}
+
+	/// What the last scan of a platform had to do without. Only the changes are logged, every scan
+	/// would repeat the same thing.
+	#[cfg(any(
+		target_os = "linux",
+		target_os = "macos",
+		target_os = "windows",
+		target_os = "freebsd"
+	))]
+	#[derive(Debug, Default)]
+	pub struct DegradedModeTracker(std::sync::Mutex<super::DegradedMode>);
+
+	#[cfg(any(
+		target_os = "linux",
+		target_os = "macos",
+		target_os = "windows",
+		target_os = "freebsd"
+	))]
+	impl DegradedModeTracker {
+		pub fn get(&self) -> super::DegradedMode {
+			self.0.lock().expect("degraded mode lock poisoned").clone()
+		}
+
+		pub fn update(&self, degraded_mode: super::DegradedMode) {
+			let mut current = self.0.lock().expect("degraded mode lock poisoned");
+
+			if *current != degraded_mode {
+				if degraded_mode.is_degraded() {
+					tracing::warn!(missing = ?degraded_mode.missing, "Volume detection is degraded;");
+				} else {
+					tracing::info!("Volume detection is no longer degraded;");
+				}
+				*current = degraded_mode;
+			}
+		}
+	}
+
+	/// Asks the watcher for a check, returning `false` once it stopped listening. A check already
+	/// queued covers the new event too.
+	#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+	pub fn request_check(check_tx: &tokio::sync::mpsc::Sender<()>) -> bool {
+		!matches!(
+			check_tx.try_send(()),
+			Err(tokio::sync::mpsc::error::TrySendError::Closed(()))
+		)
+	}
+}
@@ -0,0 +1,1142 @@
+//! Windows, one volume per drive letter
+
+use super::common::DegradedModeTracker;
+use super::*;
+use crate::volume::{
+	command,
+	retry::{retry, RetryPolicy},
+	AttachedImage, ByteSize, DeviceCapabilities, ElevatedOperation, Elevation, ElevationScope,
+	EncryptionKind, MissingVolumeData, MountOptions, UnmountedDevice,
+};
+use serde::Deserialize;
+use std::{
+	collections::HashSet,
+	ffi::{c_void, OsStr},
+	mem,
+	path::PathBuf,
+	sync::{Arc, Mutex, PoisonError},
+	time::{Duration, Instant},
+};
+use tracing::{debug, warn};
+// The crate, the `windows` module of `os` is in scope too
+use ::windows::{
+	core::{GUID, PCWSTR, PWSTR},
+	Win32::{
+		Devices::DeviceAndDriverInstallation::{
+			CM_Get_Parent, CM_Request_Device_EjectW, PNP_VetoTypeUnknown,
+			SetupDiDestroyDeviceInfoList, SetupDiEnumDeviceInterfaces, SetupDiGetClassDevsW,
+			SetupDiGetDeviceInterfaceDetailW, CR_SUCCESS, DIGCF_DEVICEINTERFACE, DIGCF_PRESENT,
+			HDEVINFO, SP_DEVICE_INTERFACE_DATA, SP_DEVICE_INTERFACE_DETAIL_DATA_W, SP_DEVINFO_DATA,
+		},
+		Foundation::{
+			CloseHandle, ERROR_ACCESS_DENIED, ERROR_NOT_READY, FVE_E_LOCKED_VOLUME, GENERIC_READ,
+			GENERIC_WRITE, HANDLE, HWND, MAX_PATH, NO_ERROR,
+		},
+		NetworkManagement::WNet::WNetGetConnectionW,
+		Storage::FileSystem::{
+			BusType1394, BusTypeFileBackedVirtual, BusTypeMmc, BusTypeSd, BusTypeUsb, CreateFileW,
+			FindFirstVolumeW, FindNextVolumeW, FindVolumeClose, FlushFileBuffers,
+			GetDiskFreeSpaceExW, GetDriveTypeW, GetLogicalDrives, GetVolumeInformationW,
+			GetVolumeNameForVolumeMountPointW, GetVolumePathNamesForVolumeNameW,
+			SetVolumeMountPointW, FILE_FLAGS_AND_ATTRIBUTES, FILE_READ_ONLY_VOLUME,
+			FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+		},
+		System::{
+			Ioctl::{
+				PropertyStandardQuery, StorageDeviceProperty, StorageDeviceSeekPenaltyProperty,
+				DEVICE_SEEK_PENALTY_DESCRIPTOR, FSCTL_DISMOUNT_VOLUME, FSCTL_LOCK_VOLUME,
+				GUID_DEVINTERFACE_DISK, IOCTL_DISK_GET_PARTITION_INFO_EX,
+				IOCTL_STORAGE_EJECT_MEDIA, IOCTL_STORAGE_GET_DEVICE_NUMBER,
+				IOCTL_STORAGE_MEDIA_REMOVAL, IOCTL_STORAGE_QUERY_PROPERTY,
+				PARTITION_INFORMATION_EX, PARTITION_STYLE_GPT, PARTITION_STYLE_MBR,
+				PREVENT_MEDIA_REMOVAL, STORAGE_DEVICE_DESCRIPTOR, STORAGE_DEVICE_NUMBER,
+				STORAGE_PROPERTY_ID, STORAGE_PROPERTY_QUERY,
+			},
+			WindowsProgramming::{
+				DRIVE_CDROM, DRIVE_FIXED, DRIVE_RAMDISK, DRIVE_REMOTE, DRIVE_REMOVABLE,
+			},
+			IO::DeviceIoControl,
+		},
+	},
+};
+use wmi::{COMLibrary, WMIConnection};
+
+/// The WMI namespace of BitLocker, only administrators can query it
+const BITLOCKER_NAMESPACE: &str = r"ROOT\CIMV2\Security\MicrosoftVolumeEncryption";
+/// How long the answer of WMI is kept, it takes a while to give one
+const BITLOCKER_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// The GPT partitions of Windows itself: EFI System, Recovery and Microsoft Reserved
+const SYSTEM_GPT_TYPES: [GUID; 3] = [
+	GUID::from_u128(0xc12a7328_f81f_11d2_ba4b_00a0c93ec93b),
+	GUID::from_u128(0xde94bba4_06d1_4d40_a16a_bfd50179d6ac),
+	GUID::from_u128(0xe3c9e316_0b5c_4db8_817d_f92df00215ae),
+];
+
+/// The same for MBR disks: EFI System and Recovery
+const SYSTEM_MBR_TYPES: [u8; 2] = [0xef, 0x27];
+
+#[derive(Debug, Default)]
+pub struct WindowsPlatform {
+	degraded_mode: DegradedModeTracker,
+	elevation: Elevation,
+	bitlocker: Arc<BitLockerCache>,
+}
+
+/// The drives BitLocker protected when WMI was last asked, asked again once a drive letter comes
+/// or goes or the answer is too old. Locking doesn't need it, a locked drive can't be read.
+#[derive(Debug, Default)]
+struct BitLockerCache(Mutex<Option<BitLockerDrives>>);
+
+#[derive(Debug)]
+struct BitLockerDrives {
+	/// The drive letters when it was asked, as `GetLogicalDrives` returns them
+	mask: u32,
+	letters: HashSet<char>,
+	queried_at: Instant,
+}
+
+impl BitLockerCache {
+	/// Blocking when WMI is asked
+	fn get(&self, mask: u32) -> HashSet<char> {
+		let mut cached = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+		match cached.as_ref() {
+			Some(drives)
+				if drives.mask == mask && drives.queried_at.elapsed() < BITLOCKER_CACHE_TTL =>
+			{
+				drives.letters.clone()
+			}
+			_ => {
+				let letters = bitlocker_drives();
+				*cached = Some(BitLockerDrives {
+					mask,
+					letters: letters.clone(),
+					queried_at: Instant::now(),
+				});
+				letters
+			}
+		}
+	}
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename = "Win32_EncryptableVolume", rename_all = "PascalCase")]
+struct EncryptableVolume {
+	drive_letter: Option<String>,
+	/// 0 while BitLocker is off or suspended, 1 while it protects the volume
+	protection_status: u32,
+}
+
+/// The type of a partition, by the partition table it's in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PartitionType {
+	Gpt(GUID),
+	Mbr(u8),
+}
+
+impl PartitionType {
+	/// The partitions Windows boots and recovers from, not for the user to mount
+	fn is_system(self) -> bool {
+		match self {
+			Self::Gpt(partition_type) => SYSTEM_GPT_TYPES.contains(&partition_type),
+			Self::Mbr(partition_type) => SYSTEM_MBR_TYPES.contains(&partition_type),
+		}
+	}
+}
+
+/// What the storage driver says about the disk of a drive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct DeviceProperties {
+	/// Plugged in through USB, FireWire or a card reader, even when Windows calls it fixed
+	pub external_bus: bool,
+	/// A disk image mounted with Mount-DiskImage, or from Explorer
+	pub file_backed: bool,
+	pub seek_penalty: Option<bool>,
+}
+
+#[async_trait]
+impl VolumePlatform for WindowsPlatform {
+	async fn get_volumes(&self) -> Result<Vec<Volume>, VolumeError> {
+		let bitlocker = Arc::clone(&self.bitlocker);
+		let (volumes, degraded_mode) =
+			task::spawn_blocking(move || list_volumes(&bitlocker)).await?;
+		self.degraded_mode.update(degraded_mode);
+		Ok(volumes)
+	}
+
+	/// Only removable drives: the media is ejected once dismounted, or Windows mounts it again
+	/// on the next access
+	async fn unmount_volume(&self, path: &Path) -> Result<(), VolumeError> {
+		let letter = drive_letter(path).ok_or_else(|| VolumeError::NotMounted(path.into()))?;
+		let root = root_of(letter);
+		// SAFETY: a valid nul-terminated root path
+		let drive_type = unsafe { GetDriveTypeW(PCWSTR(wide(&root).as_ptr())) };
+		if drive_type != DRIVE_REMOVABLE {
+			return Err(VolumeError::UnsupportedPlatform {
+				operation: "unmounting drives that aren't removable",
+			});
+		}
+
+		// Explorer and the indexer let go of a drive a moment after its last file closed
+		retry(
+			RetryPolicy::OPERATION,
+			"dismount",
+			VolumeError::is_retriable,
+			|| async {
+				let path = path.to_path_buf();
+				task::spawn_blocking(move || dismount(letter, &path, true)).await?
+			},
+		)
+		.await
+	}
+
+	/// Dismounts the volume, then asks Plug and Play to eject the device its disk is on, which
+	/// powers USB drives down so they can be unplugged. Only drives on an external bus, ejecting
+	/// an internal one would take its controller away.
+	async fn eject_volume(&self, path: &Path) -> Result<(), VolumeError> {
+		let letter = drive_letter(path).ok_or_else(|| VolumeError::NotMounted(path.into()))?;
+		let root = root_of(letter);
+		// SAFETY: a valid nul-terminated root path
+		let drive_type = unsafe { GetDriveTypeW(PCWSTR(wide(&root).as_ptr())) };
+		let external = drive_type == DRIVE_REMOVABLE
+			|| device_properties(letter).is_some_and(|properties| properties.external_bus);
+		if !external {
+			return Err(VolumeError::UnsupportedPlatform {
+				operation: "ejecting internal drives",
+			});
+		}
+
+		// Looked up first, the volume can't be asked once dismounted
+		let disk = open_volume(letter, 0)
+			.ok()
+			.and_then(|volume| device_number(&volume))
+			.ok_or_else(|| VolumeError::UnmountFailed {
+				path: path.into(),
+				reason: "the disk of the volume couldn't be found".to_string(),
+			})?;
+
+		retry(
+			RetryPolicy::OPERATION,
+			"dismount",
+			VolumeError::is_retriable,
+			|| async {
+				let path = path.to_path_buf();
+				task::spawn_blocking(move || dismount(letter, &path, drive_type == DRIVE_REMOVABLE))
+					.await?
+			},
+		)
+		.await?;
+
+		// Explorer is told about the removal and may veto it while it still holds the drive
+		retry(
+			RetryPolicy::OPERATION,
+			"eject device",
+			VolumeError::is_retriable,
+			|| async {
+				let path = path.to_path_buf();
+				task::spawn_blocking(move || request_device_eject(disk, &path)).await?
+			},
+		)
+		.await
+	}
+
+	/// Gives the volume the first free drive letter, like `mountvol` does
+	async fn mount_volume(
+		&self,
+		device: &Path,
+		options: &MountOptions,
+	) -> Result<PathBuf, VolumeError> {
+		if options.read_only {
+			return Err(VolumeError::UnsupportedPlatform {
+				operation: "mounting read-only",
+			});
+		}
+
+		let device = device.to_path_buf();
+		task::spawn_blocking(move || assign_drive_letter(&device)).await?
+	}
+
+	/// Mounts the image with Mount-DiskImage, Windows gives its volume the next free drive
+	/// letter
+	async fn attach_disk_image(
+		&self,
+		image: &Path,
+		read_only: bool,
+	) -> Result<AttachedImage, VolumeError> {
+		let access = if read_only { "ReadOnly" } else { "ReadWrite" };
+		let path = powershell_quote(command::path_arg("powershell", image)?);
+		let script = format!(
+			"(Mount-DiskImage -ImagePath {path} -Access {access} -PassThru \
+			 | Get-Volume).DriveLetter"
+		);
+		let output = powershell(&script).await?;
+		if !output.status.success() {
+			return Err(VolumeError::from_mount(image, &output.stderr_lossy()));
+		}
+
+		let mut attached = AttachedImage {
+			image: image.to_path_buf(),
+			// Images are dismounted by their path
+			device: image.to_path_buf(),
+			mount_point: PathBuf::new(),
+		};
+		match parse_drive_letter(&output.stdout_lossy()) {
+			Some(letter) => {
+				attached.mount_point = PathBuf::from(root_of(letter));
+				Ok(attached)
+			}
+			None => {
+				// Mounted without a file system Windows can read, there's nothing to open
+				if let Err(e) = self.detach_disk_image(&attached).await {
+					warn!(
+						?e,
+						"Failed to dismount a disk image without a drive letter;"
+					);
+				}
+				Err(VolumeError::InvalidDiskImage {
+					path: image.to_path_buf(),
+					reason: "no file system was mounted from it",
+				})
+			}
+		}
+	}
+
+	async fn detach_disk_image(&self, image: &AttachedImage) -> Result<(), VolumeError> {
+		let script = format!(
+			"Dismount-DiskImage -ImagePath {} | Out-Null",
+			powershell_quote(command::path_arg("powershell", &image.device)?)
+		);
+		// Only administrators can detach the images they didn't attach themselves
+		self.elevation
+			.run_or_elevate(
+				&ElevationScope::new(ElevatedOperation::Unmount, &image.mount_point),
+				"powershell",
+				&[
+					OsStr::new("-NoProfile"),
+					OsStr::new("-NonInteractive"),
+					OsStr::new("-Command"),
+					OsStr::new(&script),
+				],
+				|stderr| VolumeError::from_unmount(&image.mount_point, stderr),
+			)
+			.await
+			.map(|_| ())
+	}
+
+	async fn unmounted_devices(&self) -> Result<Vec<UnmountedDevice>, VolumeError> {
+		task::spawn_blocking(list_unmounted_volumes).await?
+	}
+
+	async fn watch(
+		&self,
+		backend: WatcherBackend,
+		check_tx: mpsc::Sender<()>,
+	) -> Result<Option<JoinHandle<()>>, VolumeError> {
+		match backend {
+			WatcherBackend::Native => super::device_change::watch(check_tx).await,
+			_ => Err(VolumeError::UnsupportedPlatform {
+				operation: "watching without device notifications",
+			}),
+		}
+	}
+
+	fn degraded_mode(&self) -> DegradedMode {
+		self.degraded_mode.get()
+	}
+
+	fn capabilities(&self) -> DeviceCapabilities {
+		DeviceCapabilities {
+			native_watch: true,
+			mount: true,
+			unmount: true,
+			eject: true,
+			disk_images: true,
+			elevation: self.elevation.is_available(),
+			..Default::default()
+		}
+	}
+}
+
+/// A handle closed when dropped
+struct OwnedHandle(HANDLE);
+
+impl Drop for OwnedHandle {
+	fn drop(&mut self) {
+		// SAFETY: the handle was opened by us and is closed once
+		unsafe {
+			let _ = CloseHandle(self.0);
+		}
+	}
+}
+
+fn list_volumes(bitlocker: &BitLockerCache) -> (Vec<Volume>, DegradedMode) {
+	let mut missing = Vec::new();
+	// SAFETY: no arguments, returns a bitmask of the drive letters
+	let mask = unsafe { GetLogicalDrives() };
+	let bitlocker = bitlocker.get(mask);
+	let volumes = drive_letters(mask)
+		.filter_map(|letter| {
+			let volume = read_volume(letter, &bitlocker, &mut missing);
+			if volume.is_none() {
+				debug!(%letter, "Skipped a drive without a readable volume;");
+			}
+			volume
+		})
+		.collect();
+
+	(volumes, DegradedMode::new(missing))
+}
+
+/// The letters set in the mask of `GetLogicalDrives`, bit 0 being A
+pub(super) fn drive_letters(mask: u32) -> impl Iterator<Item = char> {
+	(b'A'..=b'Z')
+		.enumerate()
+		.filter(move |(bit, _)| mask & (1 << bit) != 0)
+		.map(|(_, letter)| letter as char)
+}
+
+/// The drive letters of the volumes BitLocker protects, blocking. Empty when WMI can't be asked,
+/// the locked volumes are still told apart by `GetVolumeInformationW` failing on them.
+fn bitlocker_drives() -> HashSet<char> {
+	let query = || -> Result<Vec<EncryptableVolume>, wmi::WMIError> {
+		WMIConnection::with_namespace_path(BITLOCKER_NAMESPACE, COMLibrary::new()?)?.query()
+	};
+
+	match query() {
+		Ok(volumes) => volumes
+			.into_iter()
+			.filter(|volume| volume.protection_status != 0)
+			.filter_map(|volume| volume.drive_letter?.chars().next())
+			.map(|letter| letter.to_ascii_uppercase())
+			.collect(),
+		Err(e) => {
+			debug!(?e, "Couldn't ask WMI which volumes BitLocker protects;");
+			HashSet::new()
+		}
+	}
+}
+
+/// `None` for the drives without a volume, like an empty card reader
+fn read_volume(
+	letter: char,
+	bitlocker: &HashSet<char>,
+	missing: &mut Vec<MissingVolumeData>,
+) -> Option<Volume> {
+	let root = root_of(letter);
+	let root_wide = wide(&root);
+	let root_arg = PCWSTR(root_wide.as_ptr());
+
+	// SAFETY: a valid nul-terminated root path
+	let drive_type = unsafe { GetDriveTypeW(root_arg) };
+	if !matches!(
+		drive_type,
+		DRIVE_REMOVABLE | DRIVE_FIXED | DRIVE_REMOTE | DRIVE_CDROM | DRIVE_RAMDISK
+	) {
+		return None;
+	}
+
+	let mut label = [0u16; 261];
+	let mut file_system = [0u16; 261];
+	let mut serial_number = 0u32;
+	let mut flags = 0u32;
+	// SAFETY: the buffers are valid for their lengths, the root path is nul-terminated
+	let info = unsafe {
+		GetVolumeInformationW(
+			root_arg,
+			Some(&mut label),
+			Some(&mut serial_number),
+			None,
+			Some(&mut flags),
+			Some(&mut file_system),
+		)
+	};
+
+	let properties = device_properties(letter);
+	if properties.is_none() && drive_type != DRIVE_REMOTE {
+		missing.push(MissingVolumeData::DeviceInfo);
+	}
+	let properties = properties.unwrap_or_default();
+	let mount_type = mount_type(drive_type, properties);
+	let disk_type = match properties.seek_penalty {
+		Some(true) => DiskType::HDD,
+		Some(false) => DiskType::SSD,
+		None => DiskType::Unknown,
+	};
+	let mount_point = PathBuf::from(&root);
+
+	match info {
+		Ok(()) => {}
+		// Listed locked, its files can't be read until the user unlocks it
+		Err(e) if e.code() == FVE_E_LOCKED_VOLUME => {
+			let mut volume = Volume::new(
+				format!("{letter}:"),
+				mount_type,
+				mount_point.clone(),
+				vec![mount_point],
+				disk_type,
+				FileSystem::Other("BitLocker".to_string()),
+				0,
+				0,
+				true,
+			);
+			volume.is_accessible = false;
+			volume.encryption = Some(EncryptionKind::BitLocker);
+			volume.is_locked = true;
+			return Some(volume);
+		}
+		Err(e) if e.code() == ERROR_NOT_READY.to_hresult() => return None,
+		Err(e) => {
+			warn!(?e, %letter, "Failed to read the volume information;");
+			return None;
+		}
+	}
+
+	let (mut available, mut total) = (0u64, 0u64);
+	// SAFETY: the outputs are valid u64s, the root path is nul-terminated
+	if let Err(e) =
+		unsafe { GetDiskFreeSpaceExW(root_arg, Some(&mut available), Some(&mut total), None) }
+	{
+		warn!(?e, %letter, "Failed to read the free space of the volume;");
+		missing.push(MissingVolumeData::Capacity);
+	}
+
+	let label = from_wide(&label);
+	let mut volume = Volume::new(
+		if label.is_empty() {
+			format!("{letter}:")
+		} else {
+			label
+		},
+		mount_type,
+		mount_point.clone(),
+		vec![mount_point],
+		disk_type,
+		FileSystem::from_string(&from_wide(&file_system)),
+		total,
+		available,
+		// Also set when BitLocker denies writing to the removable drives it doesn't protect
+		flags & FILE_READ_ONLY_VOLUME != 0,
+	);
+	if bitlocker.contains(&letter) {
+		volume.encryption = Some(EncryptionKind::BitLocker);
+	}
+	volume.serial = serial(serial_number, root_arg);
+	// The shares of a server mapped to drives can share the serial of the disk they're on
+	if drive_type == DRIVE_REMOTE {
+		volume.mount_root = remote_share(letter);
+	}
+
+	Some(volume)
+}
+
+/// The share a network drive is mapped to, like `\\server\photos`
+fn remote_share(letter: char) -> Option<String> {
+	let local_name = wide(&format!("{letter}:"));
+	let mut remote_name = [0u16; 1024];
+	let mut len = remote_name.len() as u32;
+	// SAFETY: the local name is nul-terminated, the buffer is valid for the length given
+	let res = unsafe {
+		WNetGetConnectionW(
+			PCWSTR(local_name.as_ptr()),
+			PWSTR(remote_name.as_mut_ptr()),
+			&mut len,
+		)
+	};
+
+	(res == NO_ERROR).then(|| from_wide(&remote_name))
+}
+
+/// The serial number of the file system as `dir` prints it, or the GUID Windows gave the volume
+/// for the few file systems without one. Both stay the same when the drive letter changes.
+fn serial(serial_number: u32, root: PCWSTR) -> Option<String> {
+	if serial_number != 0 {
+		return Some(format!(
+			"{:04X}-{:04X}",
+			serial_number >> 16,
+			serial_number & 0xFFFF
+		));
+	}
+
+	let mut volume_name = [0u16; 50];
+	// SAFETY: the buffer is valid for its length, the root path is nul-terminated
+	unsafe { GetVolumeNameForVolumeMountPointW(root, &mut volume_name) }.ok()?;
+	Some(from_wide(&volume_name))
+}
+
+pub(super) fn mount_type(drive_type: u32, properties: DeviceProperties) -> MountType {
+	match drive_type {
+		DRIVE_REMOTE => MountType::Network,
+		DRIVE_RAMDISK => MountType::Virtual,
+		_ if properties.file_backed => MountType::DiskImage,
+		DRIVE_REMOVABLE | DRIVE_CDROM => MountType::External,
+		_ if properties.external_bus => MountType::External,
+		_ => MountType::System,
+	}
+}
+
+/// The bus and seek penalty of the disk, `None` when the driver can't be asked
+fn device_properties(letter: char) -> Option<DeviceProperties> {
+	// Querying the properties needs no access rights, so no elevation either
+	let device = open_volume(letter, 0).ok()?;
+
+	let mut descriptor = STORAGE_DEVICE_DESCRIPTOR::default();
+	query_property(&device, StorageDeviceProperty, &mut descriptor).ok()?;
+
+	let mut seek_penalty = DEVICE_SEEK_PENALTY_DESCRIPTOR::default();
+	// Older drivers don't answer this one
+	let seek_penalty = query_property(&device, StorageDeviceSeekPenaltyProperty, &mut seek_penalty)
+		.ok()
+		.map(|()| seek_penalty.IncursSeekPenalty.as_bool());
+
+	Some(DeviceProperties {
+		external_bus: matches!(
+			descriptor.BusType,
+			BusTypeUsb | BusType1394 | BusTypeSd | BusTypeMmc
+		),
+		file_backed: descriptor.BusType == BusTypeFileBackedVirtual,
+		seek_penalty,
+	})
+}
+
+fn query_property<T>(
+	device: &OwnedHandle,
+	property: STORAGE_PROPERTY_ID,
+	output: &mut T,
+) -> ::windows::core::Result<()> {
+	let query = STORAGE_PROPERTY_QUERY {
+		PropertyId: property,
+		QueryType: PropertyStandardQuery,
+		..Default::default()
+	};
+
+	// SAFETY: both buffers are valid for the sizes passed, the device handle is open
+	unsafe {
+		DeviceIoControl(
+			device.0,
+			IOCTL_STORAGE_QUERY_PROPERTY,
+			Some((&query as *const STORAGE_PROPERTY_QUERY).cast()),
+			mem::size_of::<STORAGE_PROPERTY_QUERY>() as u32,
+			Some((output as *mut T).cast()),
+			mem::size_of::<T>() as u32,
+			None,
+			None,
+		)
+	}
+}
+
+/// The volumes without a drive letter or folder to open them from, but the ones Windows boots and
+/// recovers from, blocking
+fn list_unmounted_volumes() -> Result<Vec<UnmountedDevice>, VolumeError> {
+	let mut name = [0u16; 261];
+	// SAFETY: the buffer is valid for its length
+	let find = unsafe { FindFirstVolumeW(&mut name) }
+		.map_err(|e| VolumeError::DeviceError(format!("listing the volumes: {}", e.message())))?;
+
+	let mut devices = Vec::new();
+	loop {
+		let volume_name = from_wide(&name);
+		if mount_paths(&volume_name).is_empty()
+			&& !partition_type(&volume_name).is_some_and(PartitionType::is_system)
+		{
+			devices.extend(unmounted_device(volume_name));
+		}
+
+		// SAFETY: the search handle is open, the buffer is valid for its length
+		if unsafe { FindNextVolumeW(find, &mut name) }.is_err() {
+			break;
+		}
+	}
+	// SAFETY: the search handle is open and not used after this
+	let _ = unsafe { FindVolumeClose(find) };
+
+	Ok(devices)
+}
+
+/// The drive letters and folders the volume is mounted on, `volume_name` being its GUID path
+fn mount_paths(volume_name: &str) -> Vec<String> {
+	let volume_name = wide(volume_name);
+	let mut paths = [0u16; 1024];
+	let mut len = 0u32;
+	// SAFETY: the buffer is valid for its length, the volume name is nul-terminated
+	let res = unsafe {
+		GetVolumePathNamesForVolumeNameW(PCWSTR(volume_name.as_ptr()), Some(&mut paths), &mut len)
+	};
+	if res.is_err() {
+		return Vec::new();
+	}
+
+	// A list of nul-terminated strings, ended by an empty one
+	paths[..(len as usize).min(paths.len())]
+		.split(|c| *c == 0)
+		.filter(|path| !path.is_empty())
+		.map(String::from_utf16_lossy)
+		.collect()
+}
+
+/// `None` when the partition table can't be read, the volume is offered then
+fn partition_type(volume_name: &str) -> Option<PartitionType> {
+	// Without the trailing backslash the volume is opened, not its root directory
+	let volume = open_device(volume_name.trim_end_matches('\\'), 0).ok()?;
+
+	let mut info = PARTITION_INFORMATION_EX::default();
+	// SAFETY: the output is valid for its size, the volume handle is open
+	unsafe {
+		DeviceIoControl(
+			volume.0,
+			IOCTL_DISK_GET_PARTITION_INFO_EX,
+			None,
+			0,
+			Some((&mut info as *mut PARTITION_INFORMATION_EX).cast()),
+			mem::size_of::<PARTITION_INFORMATION_EX>() as u32,
+			None,
+			None,
+		)
+	}
+	.ok()?;
+
+	// SAFETY: the partition style tells which member of the union was written
+	match info.PartitionStyle {
+		PARTITION_STYLE_GPT => Some(PartitionType::Gpt(unsafe {
+			info.Anonymous.Gpt.PartitionType
+		})),
+		PARTITION_STYLE_MBR => Some(PartitionType::Mbr(unsafe {
+			info.Anonymous.Mbr.PartitionType
+		})),
+		_ => None,
+	}
+}
+
+/// `None` for the volumes without a file system Windows can read, like the ones of Linux
+fn unmounted_device(volume_name: String) -> Option<UnmountedDevice> {
+	let root = wide(&volume_name);
+	let root_arg = PCWSTR(root.as_ptr());
+
+	let mut label = [0u16; 261];
+	let mut file_system = [0u16; 261];
+	// SAFETY: the buffers are valid for their lengths, the root path is nul-terminated
+	unsafe {
+		GetVolumeInformationW(
+			root_arg,
+			Some(&mut label),
+			None,
+			None,
+			None,
+			Some(&mut file_system),
+		)
+	}
+	.ok()?;
+
+	let mut total = 0u64;
+	// SAFETY: the output is a valid u64, the root path is nul-terminated
+	let _ = unsafe { GetDiskFreeSpaceExW(root_arg, None, Some(&mut total), None) };
+
+	let label = from_wide(&label);
+	Some(UnmountedDevice {
+		device: PathBuf::from(volume_name),
+		label: (!label.is_empty()).then_some(label),
+		file_system: FileSystem::from_string(&from_wide(&file_system)),
+		total_bytes_capacity: ByteSize(total),
+	})
+}
+
+/// Mounts the volume at `device`, its GUID path, on the first free drive letter after C
+fn assign_drive_letter(device: &Path) -> Result<PathBuf, VolumeError> {
+	let mount_failed = |reason: String| VolumeError::MountFailed {
+		device: device.to_path_buf(),
+		reason,
+	};
+
+	let volume_name = device
+		.to_str()
+		.filter(|name| name.starts_with(r"\\?\Volume{"))
+		.ok_or_else(|| mount_failed("not a volume GUID path".to_string()))?;
+	// SetVolumeMountPointW wants the trailing backslash
+	let volume_name = if volume_name.ends_with('\\') {
+		volume_name.to_string()
+	} else {
+		format!("{volume_name}\\")
+	};
+
+	// SAFETY: no arguments, returns a bitmask of the drive letters
+	let used = unsafe { GetLogicalDrives() };
+	let letter = (b'D'..=b'Z')
+		.map(char::from)
+		.find(|letter| !drive_letters(used).any(|used| used == *letter))
+		.ok_or_else(|| mount_failed("no drive letter is free".to_string()))?;
+
+	let root = root_of(letter);
+	// SAFETY: both paths are valid and nul-terminated
+	unsafe {
+		SetVolumeMountPointW(
+			PCWSTR(wide(&root).as_ptr()),
+			PCWSTR(wide(&volume_name).as_ptr()),
+		)
+	}
+	.map_err(|e| {
+		if e.code() == ERROR_ACCESS_DENIED.to_hresult() {
+			VolumeError::PermissionDenied {
+				path: device.to_path_buf(),
+				source: None,
+			}
+		} else {
+			mount_failed(e.message())
+		}
+	})?;
+
+	Ok(PathBuf::from(root))
+}
+
+/// Locks the volume, so nothing has files open on it, then dismounts it. The media of removable
+/// drives is ejected too, or Windows mounts it again on the next access.
+fn dismount(letter: char, path: &Path, eject_media: bool) -> Result<(), VolumeError> {
+	let volume = open_volume(letter, GENERIC_READ.0 | GENERIC_WRITE.0).map_err(|e| {
+		if e.code() == ERROR_ACCESS_DENIED.to_hresult() {
+			VolumeError::PermissionDenied {
+				path: path.into(),
+				source: None,
+			}
+		} else {
+			VolumeError::UnmountFailed {
+				path: path.into(),
+				reason: e.message(),
+			}
+		}
+	})?;
+
+	// SAFETY: the volume handle is open, flushing takes no buffers
+	unsafe {
+		let _ = FlushFileBuffers(volume.0);
+	}
+
+	control(&volume, FSCTL_LOCK_VOLUME, None).map_err(|e| {
+		if e.code() == ERROR_ACCESS_DENIED.to_hresult() {
+			VolumeError::DeviceBusy {
+				path: path.into(),
+				source: None,
+			}
+		} else {
+			unmount_failed(path, e)
+		}
+	})?;
+	control(&volume, FSCTL_DISMOUNT_VOLUME, None).map_err(|e| unmount_failed(path, e))?;
+	if !eject_media {
+		return Ok(());
+	}
+
+	let allow_removal = PREVENT_MEDIA_REMOVAL {
+		PreventMediaRemoval: false.into(),
+	};
+	control(
+		&volume,
+		IOCTL_STORAGE_MEDIA_REMOVAL,
+		Some((
+			(&allow_removal as *const PREVENT_MEDIA_REMOVAL).cast(),
+			mem::size_of::<PREVENT_MEDIA_REMOVAL>() as u32,
+		)),
+	)
+	.map_err(|e| unmount_failed(path, e))?;
+	control(&volume, IOCTL_STORAGE_EJECT_MEDIA, None).map_err(|e| unmount_failed(path, e))
+}
+
+/// The number of the disk a volume is on, like the 2 of `\\.\PhysicalDrive2`
+fn device_number(device: &OwnedHandle) -> Option<u32> {
+	let mut number = STORAGE_DEVICE_NUMBER::default();
+	// SAFETY: the output is valid for its size, the device handle is open
+	unsafe {
+		DeviceIoControl(
+			device.0,
+			IOCTL_STORAGE_GET_DEVICE_NUMBER,
+			None,
+			0,
+			Some((&mut number as *mut STORAGE_DEVICE_NUMBER).cast()),
+			mem::size_of::<STORAGE_DEVICE_NUMBER>() as u32,
+			None,
+			None,
+		)
+	}
+	.ok()?;
+
+	Some(number.DeviceNumber)
+}
+
+/// Ejects the device the disk numbered `disk` is on, the USB mass storage device of a USB drive
+fn request_device_eject(disk: u32, path: &Path) -> Result<(), VolumeError> {
+	let eject_failed = |reason: String| VolumeError::UnmountFailed {
+		path: path.into(),
+		reason,
+	};
+
+	let instance = disk_instance(disk)
+		.ok_or_else(|| eject_failed("the disk isn't listed by Plug and Play".to_string()))?;
+	let mut parent = 0u32;
+	// SAFETY: the output is a valid u32, the instance a device node from SetupAPI
+	let res = unsafe { CM_Get_Parent(&mut parent, instance, 0) };
+	if res != CR_SUCCESS {
+		return Err(eject_failed(format!("no parent device ({})", res.0)));
+	}
+
+	let mut veto_type = PNP_VetoTypeUnknown;
+	let mut veto_name = [0u16; MAX_PATH as usize];
+	// SAFETY: both outputs are valid for their sizes
+	let res =
+		unsafe { CM_Request_Device_EjectW(parent, Some(&mut veto_type), Some(&mut veto_name), 0) };
+	if res == CR_SUCCESS && veto_type == PNP_VetoTypeUnknown {
+		return Ok(());
+	}
+
+	// Vetoed by whatever still has the device open, usually for a moment
+	debug!(
+		veto_type = veto_type.0,
+		veto_name = from_wide(&veto_name),
+		"Device eject vetoed;"
+	);
+	Err(VolumeError::DeviceBusy {
+		path: path.into(),
+		source: None,
+	})
+}
+
+/// The device node of the disk numbered `disk`, found among the present disk interfaces
+fn disk_instance(disk: u32) -> Option<u32> {
+	// SAFETY: a valid class GUID, no enumerator or window
+	let devices = unsafe {
+		SetupDiGetClassDevsW(
+			Some(&GUID_DEVINTERFACE_DISK),
+			PCWSTR::null(),
+			HWND::default(),
+			DIGCF_PRESENT | DIGCF_DEVICEINTERFACE,
+		)
+	}
+	.ok()?;
+
+	let instance = (0..)
+		.map_while(|index| {
+			let mut interface = SP_DEVICE_INTERFACE_DATA {
+				cbSize: mem::size_of::<SP_DEVICE_INTERFACE_DATA>() as u32,
+				..Default::default()
+			};
+			// SAFETY: the device set is open, the output is sized
+			unsafe {
+				SetupDiEnumDeviceInterfaces(
+					devices,
+					None,
+					&GUID_DEVINTERFACE_DISK,
+					index,
+					&mut interface,
+				)
+			}
+			.ok()
+			.map(|()| interface)
+		})
+		.find_map(|interface| {
+			let (path, instance) = interface_detail(devices, &interface)?;
+			let device = open_device(&path, 0).ok()?;
+			(device_number(&device)? == disk).then_some(instance)
+		});
+
+	// SAFETY: the device set is open and not used after this
+	unsafe {
+		let _ = SetupDiDestroyDeviceInfoList(devices);
+	}
+
+	instance
+}
+
+/// The path the disk interface is opened with, and its device node
+fn interface_detail(
+	devices: HDEVINFO,
+	interface: &SP_DEVICE_INTERFACE_DATA,
+) -> Option<(String, u32)> {
+	let mut size = 0u32;
+	// SAFETY: only asks for the size, which fails with ERROR_INSUFFICIENT_BUFFER
+	let _ = unsafe {
+		SetupDiGetDeviceInterfaceDetailW(devices, interface, None, 0, Some(&mut size), None)
+	};
+	if (size as usize) < mem::size_of::<SP_DEVICE_INTERFACE_DETAIL_DATA_W>() {
+		return None;
+	}
+
+	// Aligned for the cbSize field, the path follows it
+	let mut buffer = vec![0u32; (size as usize).div_ceil(mem::size_of::<u32>())];
+	let detail = buffer
+		.as_mut_ptr()
+		.cast::<SP_DEVICE_INTERFACE_DETAIL_DATA_W>();
+	let mut info = SP_DEVINFO_DATA {
+		cbSize: mem::size_of::<SP_DEVINFO_DATA>() as u32,
+		..Default::default()
+	};
+	// SAFETY: the buffer holds `size` bytes, cbSize is the size of the fixed part as required
+	unsafe {
+		(*detail).cbSize = mem::size_of::<SP_DEVICE_INTERFACE_DETAIL_DATA_W>() as u32;
+		SetupDiGetDeviceInterfaceDetailW(
+			devices,
+			interface,
+			Some(detail),
+			size,
+			None,
+			Some(&mut info),
+		)
+	}
+	.ok()?;
+
+	// SAFETY: the path is nul-terminated within the buffer
+	let path = unsafe { PCWSTR((*detail).DevicePath.as_ptr()).to_string() }.ok()?;
+	Some((path, info.DevInst))
+}
+
+fn control(
+	volume: &OwnedHandle,
+	code: u32,
+	input: Option<(*const c_void, u32)>,
+) -> ::windows::core::Result<()> {
+	let (input, input_size) = input.map_or((None, 0), |(input, size)| (Some(input), size));
+	let mut returned = 0u32;
+	// SAFETY: the input is valid for its size, this control code has no output
+	unsafe {
+		DeviceIoControl(
+			volume.0,
+			code,
+			input,
+			input_size,
+			None,
+			0,
+			Some(&mut returned),
+			None,
+		)
+	}
+}
+
+fn unmount_failed(path: &Path, e: ::windows::core::Error) -> VolumeError {
+	VolumeError::UnmountFailed {
+		path: path.into(),
+		reason: e.message(),
+	}
+}
+
+fn open_volume(letter: char, access: u32) -> ::windows::core::Result<OwnedHandle> {
+	open_device(&format!("\\\\.\\{letter}:"), access)
+}
+
+fn open_device(path: &str, access: u32) -> ::windows::core::Result<OwnedHandle> {
+	let device = wide(path);
+	// SAFETY: a valid nul-terminated device path, the handle is owned right after
+	unsafe {
+		CreateFileW(
+			PCWSTR(device.as_ptr()),
+			access,
+			FILE_SHARE_READ | FILE_SHARE_WRITE,
+			None,
+			OPEN_EXISTING,
+			FILE_FLAGS_AND_ATTRIBUTES(0),
+			HANDLE::default(),
+		)
+	}
+	.map(OwnedHandle)
+}
+
+/// `E` for `E:\` or anything on it
+pub(super) fn drive_letter(path: &Path) -> Option<char> {
+	let path = path.to_str()?;
+	let mut chars = path.chars();
+	let letter = chars.next()?.to_ascii_uppercase();
+	(letter.is_ascii_uppercase() && chars.next() == Some(':')).then_some(letter)
+}
+
+fn root_of(letter: char) -> String {
+	format!("{letter}:\\")
+}
+
+async fn powershell(script: &str) -> Result<command::CommandOutput, VolumeError> {
+	command::run(
+		"powershell",
+		["-NoProfile", "-NonInteractive", "-Command", script],
+	)
+	.await
+}
+
+/// A single-quoted PowerShell string, nothing in it is expanded
+pub(super) fn powershell_quote(arg: &OsStr) -> String {
+	format!("'{}'", arg.to_string_lossy().replace('\'', "''"))
+}
+
+/// The drive letter `Get-Volume` printed, empty for the volumes without one
+pub(super) fn parse_drive_letter(stdout: &str) -> Option<char> {
+	stdout
+		.lines()
+		.map(str::trim)
+		.find(|line| !line.is_empty())
+		.and_then(|line| drive_letter(Path::new(&format!("{line}:"))))
+}
+
+fn wide(s: &str) -> Vec<u16> {
+	s.encode_utf16().chain([0]).collect()
+}
+
+fn from_wide(buffer: &[u16]) -> String {
+	let len = buffer.iter().position(|c| *c == 0).unwrap_or(buffer.len());
+	String::from_utf16_lossy(&buffer[..len])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reads_the_drive_letters() {
+		assert_eq!(drive_letters(1 << 25 | 0b110).collect::<String>(), "BCZ");
+		assert_eq!(drive_letter(Path::new("e:\\Photos")), Some('E'));
+		assert_eq!(drive_letter(Path::new("\\\\nas\\share")), None);
+	}
+
+	#[test]
+	fn usb_disks_are_external_even_when_fixed() {
+		let usb = DeviceProperties {
+			external_bus: true,
+			file_backed: false,
+			seek_penalty: Some(true),
+		};
+		assert_eq!(mount_type(DRIVE_FIXED, usb), MountType::External);
+		assert_eq!(
+			mount_type(DRIVE_FIXED, DeviceProperties::default()),
+			MountType::System
+		);
+		assert_eq!(
+			mount_type(DRIVE_REMOTE, DeviceProperties::default()),
+			MountType::Network
+		);
+	}
+
+	#[test]
+	fn mounted_images_are_disk_images_even_on_a_cd_drive() {
+		let image = DeviceProperties {
+			file_backed: true,
+			..Default::default()
+		};
+		assert_eq!(mount_type(DRIVE_CDROM, image), MountType::DiskImage);
+		assert_eq!(
+			mount_type(DRIVE_CDROM, DeviceProperties::default()),
+			MountType::External
+		);
+	}
+
+	#[test]
+	fn leaves_out_the_partitions_of_windows() {
+		let efi = GUID::from_u128(0xc12a7328_f81f_11d2_ba4b_00a0c93ec93b);
+		let basic_data = GUID::from_u128(0xebd0a0a2_b9e5_4433_87c0_68b6b72699c7);
+		assert!(PartitionType::Gpt(efi).is_system());
+		assert!(!PartitionType::Gpt(basic_data).is_system());
+		assert!(PartitionType::Mbr(0x27).is_system());
+		// NTFS
+		assert!(!PartitionType::Mbr(0x07).is_system());
+	}
+
+	#[test]
+	fn quotes_the_image_path_for_powershell() {
+		assert_eq!(
+			powershell_quote(OsStr::new("C:\\Users\\me\\Bob's disc.iso")),
+			"'C:\\Users\\me\\Bob''s disc.iso'"
+		);
+		assert_eq!(parse_drive_letter("E\r\n"), Some('E'));
+		assert_eq!(parse_drive_letter("\r\n"), None);
+	}
+}
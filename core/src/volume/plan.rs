@@ -0,0 +1,155 @@
+//! The checks run before the operations that take a volume away, shared by their dry runs so the
+//! confirmation the UI shows is the plan that runs.
+
+use super::{
+	error::VolumeError,
+	platform::VolumePlatform,
+	types::{OperationPlan, PredictedOutcome, Volume, VolumeErrorInfo, VolumeOperation},
+};
+
+use std::path::Path;
+
+/// Where the OS and its own data live, the volumes mounted there are never taken away
+const SYSTEM_MOUNT_POINTS: &[&str] = &[
+	"/",
+	"/boot",
+	"/boot/efi",
+	"/usr",
+	"/var",
+	"/System/Volumes/Data",
+	"C:\\",
+];
+
+/// Checks `operation` on `volume` and plans its steps. The plan predicts the outcome, the result
+/// is what running the operation now would fail with, if anything.
+pub(super) async fn plan(
+	platform: &dyn VolumePlatform,
+	operation: VolumeOperation,
+	volume: &Volume,
+) -> (OperationPlan, Result<(), VolumeError>) {
+	let mut plan = OperationPlan::new(operation, volume);
+	let checked = check(platform, volume, &mut plan).await;
+
+	plan.outcome = match &checked {
+		Err(e) => PredictedOutcome::Fails(VolumeErrorInfo::from(e)),
+		Ok(()) if plan.needs_authorization() => PredictedOutcome::NeedsAuthorization,
+		Ok(()) => PredictedOutcome::Succeeds,
+	};
+
+	(plan, checked)
+}
+
+async fn check(
+	platform: &dyn VolumePlatform,
+	volume: &Volume,
+	plan: &mut OperationPlan,
+) -> Result<(), VolumeError> {
+	if !volume.is_mounted {
+		return Err(VolumeError::NotMounted(volume.mount_point.clone()));
+	}
+
+	if is_system_volume(volume) {
+		return Err(VolumeError::SystemVolume(volume.mount_point.clone()));
+	}
+
+	let capabilities = platform.capabilities();
+	let supported = match plan.operation {
+		VolumeOperation::Unmount => capabilities.unmount,
+		VolumeOperation::Eject => capabilities.eject,
+		VolumeOperation::Format { .. } => capabilities.format,
+	};
+	if !supported {
+		return Err(VolumeError::UnsupportedPlatform {
+			operation: plan.operation.gerund(),
+		});
+	}
+
+	let operation = plan.operation.clone();
+	platform
+		.check_operation(&operation, &volume.mount_point, plan)
+		.await
+}
+
+fn is_system_volume(volume: &Volume) -> bool {
+	SYSTEM_MOUNT_POINTS.iter().any(|system| {
+		volume.mount_point == Path::new(system)
+			|| volume
+				.mount_points
+				.iter()
+				.any(|mount_point| mount_point == Path::new(system))
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::volume::{
+		types::{DeviceCapabilities, FileSystem, MountType, PlanStep, VolumeErrorCode},
+		MockVolumeProvider,
+	};
+
+	use std::path::PathBuf;
+
+	fn usb() -> Volume {
+		MockVolumeProvider::volume("USB", "/media/usb", MountType::External)
+	}
+
+	#[tokio::test]
+	async fn plans_the_steps_of_a_supported_operation() {
+		let platform = MockVolumeProvider::new([usb()]);
+
+		let (planned, checked) = plan(&platform, VolumeOperation::Eject, &usb()).await;
+		assert!(checked.is_ok());
+		assert_eq!(planned.outcome, PredictedOutcome::Succeeds);
+		assert_eq!(
+			planned.steps,
+			[
+				PlanStep::Unmount {
+					mount_point: PathBuf::from("/media/usb")
+				},
+				PlanStep::PowerOff { device: None },
+			]
+		);
+		assert_eq!(
+			planned.bytes_affected.as_u64(),
+			usb().total_bytes_capacity.as_u64() / 2
+		);
+	}
+
+	#[tokio::test]
+	async fn refuses_what_would_fail_before_asking_the_platform() {
+		let platform = MockVolumeProvider::new([]);
+
+		let mut unmounted = usb();
+		unmounted.is_mounted = false;
+		let (planned, checked) = plan(&platform, VolumeOperation::Unmount, &unmounted).await;
+		assert!(matches!(checked, Err(VolumeError::NotMounted(_))));
+		assert!(planned.steps.is_empty());
+
+		let system = MockVolumeProvider::volume("System", "/", MountType::System);
+		let (planned, _) = plan(&platform, VolumeOperation::Unmount, &system).await;
+		assert!(matches!(
+			planned.outcome,
+			PredictedOutcome::Fails(VolumeErrorInfo {
+				code: VolumeErrorCode::SystemVolume,
+				..
+			})
+		));
+
+		let platform = MockVolumeProvider::new([]).with_capabilities(DeviceCapabilities {
+			unmount: true,
+			..Default::default()
+		});
+		let format = VolumeOperation::Format {
+			file_system: FileSystem::ExFAT,
+		};
+		let (_, checked) = plan(&platform, format, &usb()).await;
+		assert!(matches!(
+			checked,
+			Err(VolumeError::UnsupportedPlatform {
+				operation: "formatting"
+			})
+		));
+	}
+}
@@ -0,0 +1,251 @@
+//! What the volume manager needs from the OS, behind a trait so it can be swapped for
+//! `MockVolumeProvider` in tests.
+//!
+//! Each OS implements it in its own file of the `os` module. [`system_platform`] picks the
+//! implementation, and on Linux whether it goes through udisks2, when the node starts.
+
+use super::{
+	error::VolumeError,
+	fs_snapshot::FileSystemSnapshot,
+	image::AttachedImage,
+	network::{NetworkShare, ShareCredentials},
+	os,
+	types::{
+		DegradedMode, DeviceCapabilities, FileSystem, MountOptions, OperationPlan, PlanStep,
+		ScanProgress, UnmountedDevice, Volume, VolumeHealth, VolumeOperation,
+	},
+	watcher::WatcherBackend,
+};
+
+use std::{
+	fmt,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
+
+use async_trait::async_trait;
+use tokio::{sync::mpsc, task::JoinHandle};
+
+#[async_trait]
+pub trait VolumePlatform: fmt::Debug + Send + Sync + 'static {
+	/// Every volume currently mounted on the system
+	async fn get_volumes(&self) -> Result<Vec<Volume>, VolumeError>;
+
+	/// Like [`Self::get_volumes`], sending [`ScanProgress::Probed`] on `progress_tx` as each mount
+	/// is probed. Platforms that can't list their volumes one by one report them all at the end.
+	async fn scan_with_progress(
+		&self,
+		progress_tx: mpsc::UnboundedSender<ScanProgress>,
+	) -> Result<Vec<Volume>, VolumeError> {
+		let volumes = self.get_volumes().await?;
+		replay(&volumes, &progress_tx);
+		Ok(volumes)
+	}
+
+	async fn unmount_volume(&self, path: &Path) -> Result<(), VolumeError>;
+
+	/// Mounts the file system of a block device, like `/dev/sdb1`, returning where it was mounted
+	async fn mount_volume(
+		&self,
+		_device: &Path,
+		_options: &MountOptions,
+	) -> Result<PathBuf, VolumeError> {
+		Err(VolumeError::UnsupportedPlatform {
+			operation: "mounting",
+		})
+	}
+
+	/// The devices with a file system [`Self::mount_volume`] can mount, the ones already mounted
+	/// are listed by [`Self::get_volumes`]
+	async fn unmounted_devices(&self) -> Result<Vec<UnmountedDevice>, VolumeError> {
+		Err(VolumeError::UnsupportedPlatform {
+			operation: "listing the unmounted devices",
+		})
+	}
+
+	/// Mounts a network share on `mount_point`, an existing directory, with its credentials or as
+	/// a guest without them. They must not end up in the arguments of a command, which other users
+	/// can read.
+	async fn mount_network_share(
+		&self,
+		_share: &NetworkShare,
+		_mount_point: &Path,
+		_credentials: Option<&ShareCredentials>,
+	) -> Result<(), VolumeError> {
+		Err(VolumeError::UnsupportedPlatform {
+			operation: "mounting network shares",
+		})
+	}
+
+	/// Attaches a disk image, an existing file, as a device and mounts its file system
+	async fn attach_disk_image(
+		&self,
+		_image: &Path,
+		_read_only: bool,
+	) -> Result<AttachedImage, VolumeError> {
+		Err(VolumeError::UnsupportedPlatform {
+			operation: "attaching disk images",
+		})
+	}
+
+	/// Unmounts the file system of an image [`Self::attach_disk_image`] attached and detaches it
+	async fn detach_disk_image(&self, _image: &AttachedImage) -> Result<(), VolumeError> {
+		Err(VolumeError::UnsupportedPlatform {
+			operation: "detaching disk images",
+		})
+	}
+
+	/// The snapshots the file system mounted at `mount_point` keeps, in the order the OS lists them
+	async fn list_snapshots(
+		&self,
+		_mount_point: &Path,
+	) -> Result<Vec<FileSystemSnapshot>, VolumeError> {
+		Err(VolumeError::UnsupportedPlatform {
+			operation: "listing snapshots",
+		})
+	}
+
+	/// Mounts a snapshot of the file system mounted at `mount_point` read-only on `target`, an
+	/// existing empty directory
+	async fn mount_snapshot(
+		&self,
+		_mount_point: &Path,
+		_snapshot: &FileSystemSnapshot,
+		_target: &Path,
+	) -> Result<(), VolumeError> {
+		Err(VolumeError::UnsupportedPlatform {
+			operation: "mounting snapshots",
+		})
+	}
+
+	/// Unmounts a snapshot [`Self::mount_snapshot`] mounted on `target`
+	async fn unmount_snapshot(&self, target: &Path) -> Result<(), VolumeError> {
+		self.unmount_volume(target).await
+	}
+
+	/// Unmounts the volume mounted at `path` and powers its drive off, so it can be unplugged
+	async fn eject_volume(&self, _path: &Path) -> Result<(), VolumeError> {
+		Err(VolumeError::UnsupportedPlatform {
+			operation: "ejecting",
+		})
+	}
+
+	/// Erases the volume mounted at `path` with a new file system
+	async fn format_volume(
+		&self,
+		_path: &Path,
+		_file_system: &FileSystem,
+	) -> Result<(), VolumeError> {
+		Err(VolumeError::UnsupportedPlatform {
+			operation: "formatting",
+		})
+	}
+
+	/// Adds the steps `operation` would run on the volume mounted at `path` to `plan`, without
+	/// changing anything. Fails with the error running it would, like a busy device. Platforms that
+	/// can't check more plan the steps of [`default_steps`].
+	async fn check_operation(
+		&self,
+		operation: &VolumeOperation,
+		path: &Path,
+		plan: &mut OperationPlan,
+	) -> Result<(), VolumeError> {
+		plan.steps.extend(default_steps(operation, path));
+		Ok(())
+	}
+
+	/// The S.M.A.R.T. health of the drive the volume mounted at `mount_point` is on, unknown when
+	/// the drive doesn't report it. With `elevate` the user is asked for the privileges to read it,
+	/// only for when they asked for the health themselves.
+	async fn health(
+		&self,
+		_mount_point: &Path,
+		_elevate: bool,
+	) -> Result<VolumeHealth, VolumeError> {
+		Err(VolumeError::UnsupportedPlatform {
+			operation: "reading the drive health",
+		})
+	}
+
+	/// Starts listening for OS events with `backend`, one of [`Self::watcher_backends`], sending on
+	/// `check_tx` whenever the volumes may have changed. Listening stops once the receiving side is
+	/// dropped, the watcher then waits for the returned task, if any, to be over.
+	async fn watch(
+		&self,
+		backend: WatcherBackend,
+		check_tx: mpsc::Sender<()>,
+	) -> Result<Option<JoinHandle<()>>, VolumeError>;
+
+	/// The backends [`Self::watch`] can listen with, the one to use by default first and the ones
+	/// to fall back on after it
+	fn watcher_backends(&self) -> Vec<WatcherBackend> {
+		vec![WatcherBackend::Native]
+	}
+
+	/// What the last `get_volumes` call had to do without
+	fn degraded_mode(&self) -> DegradedMode {
+		DegradedMode::default()
+	}
+
+	/// The operations this platform implements, none of them unless it says otherwise
+	fn capabilities(&self) -> DeviceCapabilities {
+		DeviceCapabilities::default()
+	}
+}
+
+/// Reports volumes that were already listed as if they had just been probed
+pub(super) fn replay(volumes: &[Volume], progress_tx: &mpsc::UnboundedSender<ScanProgress>) {
+	let total = volumes.len() as u32;
+	for (processed, volume) in (1..).zip(volumes) {
+		// Nobody is watching anymore, the scan is still worth finishing
+		let _ = progress_tx.send(ScanProgress::Probed {
+			processed,
+			total,
+			volume: Some(volume.clone()),
+		});
+	}
+}
+
+/// The steps of an operation when nothing more is known about the volume
+pub(super) fn default_steps(operation: &VolumeOperation, path: &Path) -> Vec<PlanStep> {
+	let unmount = PlanStep::Unmount {
+		mount_point: path.to_path_buf(),
+	};
+
+	match operation {
+		VolumeOperation::Unmount => vec![unmount],
+		VolumeOperation::Eject => vec![unmount, PlanStep::PowerOff { device: None }],
+		VolumeOperation::Format { file_system } => vec![
+			unmount,
+			PlanStep::Format {
+				device: None,
+				file_system: file_system.clone(),
+			},
+		],
+	}
+}
+
+/// The platform of the OS the core runs on, with the backend picked from what the system has
+pub async fn system_platform() -> Arc<dyn VolumePlatform> {
+	#[cfg(target_os = "linux")]
+	let platform = os::linux::LinuxPlatform::detect().await;
+	#[cfg(target_os = "macos")]
+	let platform = os::macos::MacOsPlatform::default();
+	#[cfg(target_os = "windows")]
+	let platform = os::windows::WindowsPlatform::default();
+	#[cfg(any(target_os = "ios", target_os = "android"))]
+	let platform = os::mobile::MobilePlatform;
+	#[cfg(target_os = "freebsd")]
+	let platform = os::freebsd::FreeBsdPlatform::default();
+	#[cfg(not(any(
+		target_os = "linux",
+		target_os = "macos",
+		target_os = "windows",
+		target_os = "freebsd",
+		target_os = "ios",
+		target_os = "android"
+	)))]
+	let platform = os::generic::SysinfoPlatform;
+
+	Arc::new(platform)
+}
@@ -0,0 +1,206 @@
+//! Quarantine of the volumes that keep failing to read or write.
+//!
+//! A dying disk or a flaky USB connection makes every job on it fail with errors that don't say
+//! why. The device failures the location jobs run into are counted for the volume the location is
+//! on, those of the jobs failing outright included, a job without any clears the count. Once a
+//! tracked volume reaches the configured `io_error_threshold` in a row it's marked degraded, the
+//! jobs on its locations are paused and
+//! [`VolumeEvent::VolumeDegraded`](super::VolumeEvent::VolumeDegraded) goes out. New jobs on its
+//! locations are refused until it's mounted again, the paused ones are left for the user to resume.
+
+use super::types::VolumeFingerprint;
+use crate::{library::Library, old_job::JobManagerError, Node};
+
+use sd_core_heavy_lifting::{
+	file_identifier::NonCriticalFileIdentifierError,
+	indexer::{self, NonCriticalIndexerError},
+	job_system::report::{ReportInputMetadata, ReportMetadata, Status},
+	Error, JobOutput, NonCriticalError,
+};
+use sd_prisma::prisma::location;
+
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+};
+
+use tracing::{info, warn};
+
+/// The I/O errors each volume ran into in a row
+#[derive(Debug, Default)]
+pub(crate) struct IoErrors(Mutex<HashMap<VolumeFingerprint, u32>>);
+
+impl IoErrors {
+	/// Adds up the errors of a job, a job without any clears the count. Returns the count when it
+	/// just reached the threshold, 0 never does.
+	pub fn record(
+		&self,
+		fingerprint: &VolumeFingerprint,
+		errors: u32,
+		threshold: u32,
+	) -> Option<u32> {
+		let mut counts = self.0.lock().unwrap_or_else(|e| e.into_inner());
+		if errors == 0 {
+			counts.remove(fingerprint);
+			return None;
+		}
+
+		let count = counts.entry(fingerprint.clone()).or_default();
+		let previous = *count;
+		*count = count.saturating_add(errors);
+
+		(threshold > 0 && previous < threshold && *count >= threshold).then_some(*count)
+	}
+
+	pub fn forget(&self, fingerprint: &VolumeFingerprint) {
+		self.0
+			.lock()
+			.unwrap_or_else(|e| e.into_inner())
+			.remove(fingerprint);
+	}
+}
+
+/// Counts the device failures of a job, quarantining the volume they're on when they're too many.
+/// A failed job is counted by the path its error is about, the others by their location.
+pub(crate) async fn on_job_output(node: &Arc<Node>, res: &Result<JobOutput, Error>) {
+	let (path, errors) = match res {
+		Ok(output) => {
+			let Some(path) = location_path(output.metadata()) else {
+				return;
+			};
+			let errors = output
+				.non_critical_errors()
+				.iter()
+				.filter(|error| is_device_failure(error))
+				.count();
+
+			(path, u32::try_from(errors).unwrap_or(u32::MAX))
+		}
+		Err(Error::Indexer(indexer::Error::FileIO(e))) if e.is_device_failure() => {
+			(e.path.to_path_buf(), 1)
+		}
+		// Not the device's fault, that says nothing of the volume either way
+		Err(_) => return,
+	};
+
+	match node.volumes.report_io_errors(path, errors).await {
+		Ok(Some(volume)) => pause_jobs_on(node, &volume.mount_point).await,
+		Ok(None) => {}
+		Err(e) => warn!(?e, "Failed to count the I/O errors of a job;"),
+	}
+}
+
+/// Whether `path` is on a quarantined volume, the jobs there would only run into more errors
+pub(crate) async fn is_quarantined(node: &Node, path: impl Into<PathBuf>) -> bool {
+	match node.volumes.volume_at(path.into()).await {
+		Ok(volume) => volume.is_some_and(|volume| volume.is_degraded),
+		// The job fails on its own if the volume is gone
+		Err(e) => {
+			warn!(?e, "Failed to find the volume of a path;");
+			false
+		}
+	}
+}
+
+/// Fails when one of the locations is on a quarantined volume
+pub(crate) async fn ensure_healthy(
+	node: &Node,
+	library: &Library,
+	location_ids: Vec<location::id::Type>,
+) -> Result<(), JobManagerError> {
+	let locations = library
+		.db
+		.location()
+		.find_many(vec![location::id::in_vec(location_ids)])
+		.select(location::select!({ path }))
+		.exec()
+		.await?;
+
+	for path in locations.into_iter().filter_map(|location| location.path) {
+		if let Ok(Some(volume)) = node.volumes.volume_at(PathBuf::from(path)).await {
+			if volume.is_degraded {
+				return Err(JobManagerError::DegradedVolume(volume.name));
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Pauses the running jobs on the locations inside `mount_point`
+async fn pause_jobs_on(node: &Node, mount_point: &Path) {
+	for (job_id, report) in node.job_system.get_active_reports().await {
+		let on_volume =
+			location_path(&report.metadata).is_some_and(|path| path.starts_with(mount_point));
+		if report.status != Status::Running || !on_volume {
+			continue;
+		}
+
+		info!(%job_id, name = ?report.name, "Pausing a job on a quarantined volume;");
+		if let Err(e) = node.job_system.pause(job_id).await {
+			warn!(?e, %job_id, "Failed to pause a job on a quarantined volume;");
+		}
+	}
+}
+
+fn location_path(metadata: &[ReportMetadata]) -> Option<PathBuf> {
+	metadata.iter().find_map(|metadata| match metadata {
+		ReportMetadata::Input(ReportInputMetadata::Location(location)) => {
+			location.path.as_deref().map(PathBuf::from)
+		}
+		_ => None,
+	})
+}
+
+fn is_device_failure(error: &NonCriticalError) -> bool {
+	matches!(
+		error,
+		NonCriticalError::Indexer(NonCriticalIndexerError::DeviceFailure(_))
+			| NonCriticalError::FileIdentifier(NonCriticalFileIdentifierError::DeviceFailure(_))
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use sd_utils::error::FileIOError;
+
+	use std::io;
+
+	#[test]
+	fn quarantines_once_the_errors_in_a_row_reach_the_threshold() {
+		let errors = IoErrors::default();
+		let fingerprint = VolumeFingerprint(vec![1]);
+
+		assert_eq!(errors.record(&fingerprint, 2, 5), None);
+		// A job without errors clears the count
+		assert_eq!(errors.record(&fingerprint, 0, 5), None);
+		assert_eq!(errors.record(&fingerprint, 3, 5), None);
+		assert_eq!(errors.record(&fingerprint, 4, 5), Some(7));
+		assert_eq!(errors.record(&fingerprint, 1, 5), None);
+
+		let disabled = VolumeFingerprint(vec![2]);
+		assert_eq!(errors.record(&disabled, 100, 0), None);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn tells_device_errors_from_the_others() {
+		let error = |code| FileIOError::from(("/media/usb", io::Error::from_raw_os_error(code)));
+
+		assert!(error(libc::EIO).is_device_failure());
+		assert!(error(libc::ENODEV).is_device_failure());
+		assert!(!error(libc::ENOENT).is_device_failure());
+		assert!(!error(libc::EACCES).is_device_failure());
+		assert!(!FileIOError::from(("/media/usb", io::Error::other("closed"))).is_device_failure());
+
+		assert!(is_device_failure(&NonCriticalError::Indexer(
+			NonCriticalIndexerError::DeviceFailure("/media/usb".to_string())
+		)));
+		assert!(!is_device_failure(&NonCriticalError::Indexer(
+			NonCriticalIndexerError::Metadata("/media/usb".to_string())
+		)));
+	}
+}
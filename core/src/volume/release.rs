@@ -0,0 +1,204 @@
+//! What the node lets go of before a volume is taken away.
+//!
+//! The jobs working on the locations on the volume are waited for, those of the old job system
+//! included, then the watchers of these locations are paused: they would take the unmount for all
+//! their files being deleted, and their open directory handles keep Windows and macOS from ejecting
+//! the drive. The changes they already received are written to the library before the volume goes,
+//! they would be lost with its files, a watcher taking too long is given up on. The watchers
+//! resume once the operation is over, the location manager finds the locations offline from there.
+
+use super::{
+	error::VolumeError,
+	types::{OperationPlan, PredictedOutcome, VolumeFingerprint, VolumeOperation},
+};
+use crate::{library::Library, Node};
+
+use sd_core_heavy_lifting::JobName;
+use sd_prisma::prisma::location;
+
+use std::{
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::Duration,
+};
+
+use tokio::time::{sleep, timeout, Instant};
+use tracing::{debug, warn};
+
+/// How long the jobs working on the volume are waited for, before giving up on the operation
+pub const JOBS_TIMEOUT: Duration = Duration::from_secs(30);
+
+const JOBS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long the changes of a watcher are waited for, the volume goes without the rest
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The jobs reading or writing the files of a location
+const LOCATION_JOBS: [JobName; 8] = [
+	JobName::Indexer,
+	JobName::FileIdentifier,
+	JobName::MediaProcessor,
+	JobName::Copy,
+	JobName::Move,
+	JobName::Delete,
+	JobName::Erase,
+	JobName::FileValidator,
+];
+
+/// The locations on a volume whose watchers were paused, to resume once the operation is over
+#[must_use]
+pub(crate) struct ReleasedVolume<'a> {
+	node: &'a Node,
+	locations: Vec<(location::id::Type, Arc<Library>)>,
+}
+
+impl ReleasedVolume<'_> {
+	pub async fn resume(self) {
+		for (location_id, library) in self.locations {
+			if let Err(e) = self
+				.node
+				.locations
+				.resume_watcher(location_id, library)
+				.await
+			{
+				warn!(?e, %location_id, "Failed to resume the watcher of a location;");
+			}
+		}
+	}
+}
+
+/// Runs `operation` on the volume once the node let go of it, dry runs and the operations their
+/// checks refuse don't wait for anything
+pub(crate) async fn run_operation(
+	node: &Node,
+	fingerprint: VolumeFingerprint,
+	operation: VolumeOperation,
+	dry_run: bool,
+) -> Result<OperationPlan, VolumeError> {
+	let plan = node
+		.volumes
+		.run_operation(fingerprint.clone(), operation.clone(), true)
+		.await?;
+	if dry_run {
+		return Ok(plan);
+	}
+	if let PredictedOutcome::Fails(_) = plan.outcome {
+		return node
+			.volumes
+			.run_operation(fingerprint, operation, false)
+			.await;
+	}
+
+	let released = release(node, &plan.mount_point).await?;
+	let res = node
+		.volumes
+		.run_operation(fingerprint, operation, false)
+		.await;
+	released.resume().await;
+
+	res
+}
+
+/// Waits up to [`JOBS_TIMEOUT`] for the jobs on the locations on the volume mounted at
+/// `mount_point`, then pauses their watchers and flushes them
+pub(crate) async fn release<'a>(
+	node: &'a Node,
+	mount_point: &Path,
+) -> Result<ReleasedVolume<'a>, VolumeError> {
+	let locations = locations_on(node, mount_point).await?;
+
+	let deadline = Instant::now() + JOBS_TIMEOUT;
+	for (location_id, library) in &locations {
+		while has_jobs_on(node, library, *location_id).await {
+			if Instant::now() >= deadline {
+				return Err(VolumeError::JobsRunning {
+					path: mount_point.to_path_buf(),
+				});
+			}
+			debug!(%location_id, "Waiting for the jobs on a location of the volume;");
+			sleep(JOBS_POLL_INTERVAL).await;
+		}
+	}
+
+	let mut released = ReleasedVolume {
+		node,
+		locations: Vec::with_capacity(locations.len()),
+	};
+	for (location_id, library) in locations {
+		match node
+			.locations
+			.pause_watcher(location_id, Arc::clone(&library))
+			.await
+		{
+			Ok(()) => released.locations.push((location_id, library)),
+			// Left watching, the volume can still go
+			Err(e) => warn!(?e, %location_id, "Failed to pause the watcher of a location;"),
+		}
+	}
+
+	for (location_id, library) in &released.locations {
+		match timeout(
+			FLUSH_TIMEOUT,
+			node.locations
+				.flush_watcher(*location_id, Arc::clone(library)),
+		)
+		.await
+		{
+			Ok(Ok(())) => {}
+			Ok(Err(e)) => warn!(?e, %location_id, "Failed to flush the watcher of a location;"),
+			Err(_) => warn!(
+				%location_id,
+				"Timed out flushing the watcher of a location, its last changes may be lost;"
+			),
+		}
+	}
+
+	Ok(released)
+}
+
+/// Whether a job of either job system works on the location
+async fn has_jobs_on(node: &Node, library: &Library, location_id: location::id::Type) -> bool {
+	let new_jobs = node
+		.job_system
+		.check_running_jobs(LOCATION_JOBS.to_vec(), location_id);
+	let old_jobs = node.old_jobs.has_location_jobs(library.id, &[location_id]);
+
+	new_jobs.await || old_jobs.await
+}
+
+/// The locations of every library inside `mount_point`
+async fn locations_on(
+	node: &Node,
+	mount_point: &Path,
+) -> Result<Vec<(location::id::Type, Arc<Library>)>, VolumeError> {
+	// Locations are stored as UTF-8, none can be on a mount point that isn't
+	let Some(prefix) = mount_point.to_str() else {
+		return Ok(vec![]);
+	};
+
+	let mut locations = vec![];
+	for library in node.libraries.get_all().await {
+		let found = library
+			.db
+			.location()
+			.find_many(vec![location::path::starts_with(prefix.to_string())])
+			.select(location::select!({ id path }))
+			.exec()
+			.await?;
+
+		// The prefix also matches the siblings, like `/media/usb2` for `/media/usb`
+		locations.extend(
+			found
+				.into_iter()
+				.filter(|location| {
+					location
+						.path
+						.as_ref()
+						.is_some_and(|path| PathBuf::from(path).starts_with(mount_point))
+				})
+				.map(|location| (location.id, Arc::clone(&library))),
+		);
+	}
+
+	Ok(locations)
+}
@@ -0,0 +1,177 @@
+//! Retries for the platform calls that fail for a moment around a hotplug: a device still busy
+//! while the desktop environment probes it, sysfs attributes the kernel hasn't populated yet, the
+//! inotify instances of a watcher that is shutting down not released yet, the server of a network
+//! share that dropped.
+//!
+//! Only failures the caller classifies as transient are tried again, after an exponential backoff
+//! with jitter so the probes of a hub full of drives don't retry in lockstep.
+
+use std::{
+	collections::hash_map::RandomState,
+	fmt,
+	future::Future,
+	hash::{BuildHasher, Hasher},
+	time::Duration,
+};
+
+use tokio::time::sleep;
+use tracing::debug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+	/// Tries in total, the first one included
+	pub max_attempts: u32,
+	/// Delay before the second try, doubled after every failed one
+	pub initial_delay: Duration,
+	pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+	/// For the probes of a scan, which is waited on by the UI
+	pub const PROBE: Self = Self {
+		max_attempts: 3,
+		initial_delay: Duration::from_millis(50),
+		max_delay: Duration::from_millis(200),
+	};
+
+	/// For the operations the user asked for, like an unmount
+	pub const OPERATION: Self = Self {
+		max_attempts: 4,
+		initial_delay: Duration::from_millis(250),
+		max_delay: Duration::from_secs(2),
+	};
+
+	/// For starting the watcher, which nothing waits on
+	pub const WATCHER: Self = Self {
+		max_attempts: 5,
+		initial_delay: Duration::from_millis(500),
+		max_delay: Duration::from_secs(8),
+	};
+
+	/// For mounting the network shares that dropped, whose server may take minutes to come back
+	pub const RECONNECT: Self = Self {
+		max_attempts: 10,
+		initial_delay: Duration::from_secs(2),
+		max_delay: Duration::from_secs(60),
+	};
+
+	/// How long to wait after the failed try number `attempt`, starting at 1. `jitter` scales the
+	/// exponential delay, between 0.5 and 1.
+	pub fn delay(&self, attempt: u32, jitter: f64) -> Duration {
+		let exponential = self
+			.initial_delay
+			.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+			.min(self.max_delay);
+
+		exponential.mul_f64(jitter.clamp(0.5, 1.0))
+	}
+}
+
+/// Runs `operation` until it succeeds, fails for a reason `is_transient` doesn't accept, or runs
+/// out of attempts. The error of the last try is returned.
+pub async fn retry<T, E, Fut>(
+	policy: RetryPolicy,
+	name: &'static str,
+	is_transient: impl Fn(&E) -> bool,
+	mut operation: impl FnMut() -> Fut,
+) -> Result<T, E>
+where
+	Fut: Future<Output = Result<T, E>>,
+	E: fmt::Display,
+{
+	let mut attempt = 1;
+	loop {
+		match operation().await {
+			Err(e) if attempt < policy.max_attempts && is_transient(&e) => {
+				let delay = policy.delay(attempt, jitter());
+				debug!(%e, name, attempt, ?delay, "Retrying a transient volume failure;");
+
+				sleep(delay).await;
+				attempt += 1;
+			}
+			res => return res,
+		}
+	}
+}
+
+/// A factor between 0.5 and 1, from the random keys std seeds its hash maps with
+fn jitter() -> f64 {
+	let bits = RandomState::new().build_hasher().finish();
+	0.5 + (bits >> 11) as f64 / (1u64 << 53) as f64 / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::sync::atomic::{AtomicU32, Ordering};
+
+	const FAST: RetryPolicy = RetryPolicy {
+		max_attempts: 3,
+		initial_delay: Duration::from_millis(1),
+		max_delay: Duration::from_millis(2),
+	};
+
+	async fn failing(tries: &AtomicU32, failures: u32, error: &'static str) -> Result<u32, String> {
+		let attempt = tries.fetch_add(1, Ordering::Relaxed) + 1;
+		if attempt <= failures {
+			Err(error.to_string())
+		} else {
+			Ok(attempt)
+		}
+	}
+
+	#[test]
+	fn delays_double_up_to_the_maximum() {
+		let policy = RetryPolicy::OPERATION;
+		assert_eq!(policy.delay(1, 1.0), Duration::from_millis(250));
+		assert_eq!(policy.delay(2, 1.0), Duration::from_millis(500));
+		assert_eq!(policy.delay(3, 0.5), Duration::from_millis(500));
+		assert_eq!(policy.delay(10, 1.0), Duration::from_secs(2));
+		assert_eq!(policy.delay(u32::MAX, 1.0), Duration::from_secs(2));
+
+		for _ in 0..100 {
+			let jitter = jitter();
+			assert!((0.5..1.0).contains(&jitter), "{jitter} out of range");
+		}
+	}
+
+	#[tokio::test]
+	async fn retries_transient_failures_only() {
+		let tries = AtomicU32::new(0);
+		let res = retry(
+			FAST,
+			"test",
+			|e: &String| e == "busy",
+			|| failing(&tries, 2, "busy"),
+		)
+		.await;
+		assert_eq!(res, Ok(3));
+
+		let tries = AtomicU32::new(0);
+		let res = retry(
+			FAST,
+			"test",
+			|e: &String| e == "busy",
+			|| failing(&tries, 2, "denied"),
+		)
+		.await;
+		assert_eq!(res, Err("denied".to_string()));
+		assert_eq!(tries.load(Ordering::Relaxed), 1);
+	}
+
+	#[tokio::test]
+	async fn gives_up_after_the_last_attempt() {
+		let tries = AtomicU32::new(0);
+		let res = retry(
+			FAST,
+			"test",
+			|_: &String| true,
+			|| failing(&tries, 5, "busy"),
+		)
+		.await;
+
+		assert_eq!(res, Err("busy".to_string()));
+		assert_eq!(tries.load(Ordering::Relaxed), FAST.max_attempts);
+	}
+}
@@ -0,0 +1,85 @@
+//! The settings the user picks for each volume.
+//!
+//! They're kept by fingerprint in the `volumes.settings` of the core config, so they apply to
+//! every library and to the volumes that aren't tracked. Hidden volumes are left out of the
+//! explorer's list, the jobs writing to the locations of a volume forced read-only are refused
+//! before they're dispatched and `auto_track` overrides which volumes a library tracks when it
+//! loads.
+
+use super::types::Volume;
+use crate::{library::Library, old_job::JobManagerError, Node};
+
+use sd_prisma::prisma::location;
+
+use std::path::PathBuf;
+
+use tracing::warn;
+
+/// Leaves out the volumes the user hid
+pub(crate) fn without_hidden(node: &Node, volumes: Vec<Volume>) -> Vec<Volume> {
+	without_hidden_by(node, volumes, |volume| volume)
+}
+
+/// Leaves out what's listed for a volume the user hid
+pub(crate) fn without_hidden_by<T>(
+	node: &Node,
+	items: Vec<T>,
+	volume_of: impl Fn(&T) -> &Volume,
+) -> Vec<T> {
+	let config = node.core_config.get().volumes;
+	items
+		.into_iter()
+		.filter(|item| {
+			!volume_of(item)
+				.fingerprint
+				.as_ref()
+				.is_some_and(|fingerprint| config.settings_of(fingerprint).is_hidden)
+		})
+		.collect()
+}
+
+/// Fails when one of the locations is on a volume forced read-only
+pub(crate) async fn ensure_writable(
+	node: &Node,
+	library: &Library,
+	location_ids: Vec<location::id::Type>,
+) -> Result<(), JobManagerError> {
+	let config = node.core_config.get().volumes;
+	if !config
+		.settings
+		.values()
+		.any(|settings| settings.force_read_only)
+	{
+		return Ok(());
+	}
+
+	let locations = library
+		.db
+		.location()
+		.find_many(vec![location::id::in_vec(location_ids)])
+		.select(location::select!({ path }))
+		.exec()
+		.await?;
+
+	for path in locations.into_iter().filter_map(|location| location.path) {
+		let volume = match node.volumes.volume_at(PathBuf::from(path)).await {
+			Ok(volume) => volume,
+			// The job fails on its own if the volume can't be written to
+			Err(e) => {
+				warn!(?e, "Failed to find the volume of a location;");
+				continue;
+			}
+		};
+
+		if let Some(volume) = volume.filter(|volume| {
+			volume
+				.fingerprint
+				.as_ref()
+				.is_some_and(|fingerprint| config.settings_of(fingerprint).force_read_only)
+		}) {
+			return Err(JobManagerError::ReadOnlyVolume(volume.name));
+		}
+	}
+
+	Ok(())
+}
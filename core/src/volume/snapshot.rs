@@ -0,0 +1,118 @@
+//! Versioned captures of what the volume manager knows, to attach to bug reports and to load into
+//! the mock provider to reproduce the environment they came from.
+
+use super::{
+	error::VolumeError,
+	types::{DegradedMode, Volume},
+	watcher::WatcherStatus,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// Bumped whenever a change to the format would make older snapshots misread
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct VolumeSnapshot {
+	/// Format the snapshot was written in, see [`SNAPSHOT_VERSION`]
+	pub version: u32,
+	pub captured_at: DateTime<Utc>,
+	/// Fingerprints are derived from it, reproductions should run as this device
+	pub device_id: Vec<u8>,
+	/// Every volume the manager knew of, with its fingerprint
+	pub volumes: Vec<Volume>,
+	pub degraded_mode: DegradedMode,
+	pub watcher: WatcherStatus,
+}
+
+impl VolumeSnapshot {
+	pub fn new(
+		device_id: Vec<u8>,
+		volumes: Vec<Volume>,
+		degraded_mode: DegradedMode,
+		watcher: WatcherStatus,
+	) -> Self {
+		Self {
+			version: SNAPSHOT_VERSION,
+			captured_at: Utc::now(),
+			device_id,
+			volumes,
+			degraded_mode,
+			watcher,
+		}
+	}
+
+	pub fn to_json(&self) -> Result<String, VolumeError> {
+		serde_json::to_string_pretty(self).map_err(|e| VolumeError::InvalidSnapshot(e.to_string()))
+	}
+
+	/// Reads a snapshot, refusing the ones written in a format we don't know
+	pub fn from_json(json: &str) -> Result<Self, VolumeError> {
+		#[derive(Deserialize)]
+		struct Versioned {
+			version: u32,
+		}
+
+		// Newer formats may not deserialize at all, the version makes for a clearer error
+		let Versioned { version } =
+			serde_json::from_str(json).map_err(|e| VolumeError::InvalidSnapshot(e.to_string()))?;
+		check_version(version)?;
+
+		serde_json::from_str(json).map_err(|e| VolumeError::InvalidSnapshot(e.to_string()))
+	}
+
+	pub(super) fn check_version(&self) -> Result<(), VolumeError> {
+		check_version(self.version)
+	}
+}
+
+fn check_version(version: u32) -> Result<(), VolumeError> {
+	if version == SNAPSHOT_VERSION {
+		Ok(())
+	} else {
+		Err(VolumeError::InvalidSnapshot(format!(
+			"unsupported version {version}, expected {SNAPSHOT_VERSION}"
+		)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::volume::{MockVolumeProvider, MountType, VolumePlatform};
+
+	fn snapshot() -> VolumeSnapshot {
+		VolumeSnapshot::new(
+			vec![1, 2, 3],
+			vec![
+				MockVolumeProvider::volume("System", "/", MountType::System),
+				MockVolumeProvider::volume("USB", "/media/usb", MountType::External),
+			],
+			DegradedMode::default(),
+			WatcherStatus::default(),
+		)
+	}
+
+	#[tokio::test]
+	async fn round_trips_into_the_mock_provider() {
+		let snapshot = VolumeSnapshot::from_json(&snapshot().to_json().unwrap()).unwrap();
+		let provider = MockVolumeProvider::from_snapshot(&snapshot);
+
+		let volumes = provider.get_volumes().await.unwrap();
+		assert_eq!(volumes.len(), 2);
+		assert_eq!(volumes[1].name, "USB");
+	}
+
+	#[test]
+	fn refuses_unknown_versions() {
+		let mut json = serde_json::to_value(snapshot()).unwrap();
+		json["version"] = (SNAPSHOT_VERSION + 1).into();
+
+		assert!(matches!(
+			VolumeSnapshot::from_json(&json.to_string()),
+			Err(VolumeError::InvalidSnapshot(_))
+		));
+	}
+}
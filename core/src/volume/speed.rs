@@ -1,11 +1,11 @@
 use super::error::VolumeError;
 use super::types::{MountType, Volume, VolumeEvent};
+use crate::node::Publisher;
 use std::path::PathBuf;
 use std::time::Instant;
 use tokio::{
 	fs::{File, OpenOptions},
 	io::{AsyncReadExt, AsyncWriteExt},
-	sync::broadcast::Sender,
 	time::{timeout, Duration},
 };
 use tracing::{debug, error, instrument, trace};
@@ -49,7 +49,7 @@ pub trait SpeedTest {
 	async fn speed_test(
 		&mut self,
 		config: Option<SpeedTestConfig>,
-		event_tx: Option<&Sender<VolumeEvent>>,
+		event_tx: Option<&Publisher<VolumeEvent>>,
 	) -> Result<SpeedTestResult, VolumeError>;
 }
 
@@ -96,7 +96,7 @@ impl SpeedTest for Volume {
 	async fn speed_test(
 		&mut self,
 		config: Option<SpeedTestConfig>,
-		event_tx: Option<&Sender<VolumeEvent>>,
+		event_tx: Option<&Publisher<VolumeEvent>>,
 	) -> Result<SpeedTestResult, VolumeError> {
 		let config = config.unwrap_or_default();
 
@@ -129,19 +129,15 @@ impl SpeedTest for Volume {
 		self.read_speed_mbps = Some(read_speed as u64);
 		self.write_speed_mbps = Some(write_speed as u64);
 
-		// Emit event if requested
-		// if config.emit_events {
-		println!("emitting event for {:?}", self.fingerprint);
-		if let Some(fingerprint) = self.fingerprint.clone() {
-			if let Some(tx) = event_tx {
-				let _ = tx.send(VolumeEvent::VolumeSpeedTested {
-					fingerprint,
-					read_speed: read_speed as u64,
-					write_speed: write_speed as u64,
-				});
-			}
+		if let (true, Some(fingerprint), Some(tx)) =
+			(config.emit_events, self.fingerprint.clone(), event_tx)
+		{
+			tx.send(VolumeEvent::VolumeSpeedTested {
+				fingerprint,
+				read_speed: read_speed as u64,
+				write_speed: write_speed as u64,
+			});
 		}
-		// }
 
 		// Cleanup
 		test_location.cleanup().await?;
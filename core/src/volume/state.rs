@@ -1,18 +1,16 @@
 use crate::{
 	library::Library,
-	volume::{
-		speed::SpeedTest,
-		types::{Volume, VolumeEvent, VolumeFingerprint},
-	},
+	node::{CoreConfig, Publisher},
+	volume::types::{ScanProgress, Volume, VolumeEvent, VolumeFingerprint},
 };
 
 use sd_core_sync::DevicePubId;
 use std::collections::HashSet;
-use std::{collections::HashMap, sync::Arc, time::Instant};
-use tokio::sync::{broadcast, RwLock};
-use tracing::{debug, error};
+use std::{collections::HashMap, path::Path, sync::Arc, time::Instant};
+use tokio::sync::{mpsc, watch, RwLock};
+use tracing::debug;
 
-use super::{MountType, VolumeError, VolumeOptions};
+use super::{tracked, VolumeError, VolumeOptions, VolumePlatform};
 // Core volume registry
 pub struct VolumeRegistry {
 	volumes: HashMap<VolumeFingerprint, Volume>,
@@ -28,7 +26,11 @@ impl VolumeRegistry {
 	}
 
 	pub fn register_volume(&mut self, mut volume: Volume) -> (Volume, VolumeFingerprint) {
-		let fingerprint = VolumeFingerprint::new(&self.device_id, &volume);
+		// Volumes restored from a snapshot keep the fingerprint they were captured with
+		let fingerprint = volume
+			.fingerprint
+			.clone()
+			.unwrap_or_else(|| VolumeFingerprint::new(&self.device_id.to_db(), &volume));
 		debug!(
 			"Registering volume {} with fingerprint {}",
 			volume.name, fingerprint
@@ -51,7 +53,7 @@ impl VolumeRegistry {
 	}
 
 	pub fn update_volume(&mut self, volume: Volume) -> VolumeFingerprint {
-		let fingerprint = VolumeFingerprint::new(&self.device_id, &volume);
+		let fingerprint = VolumeFingerprint::new(&self.device_id.to_db(), &volume);
 		self.volumes.insert(fingerprint.clone(), volume);
 		fingerprint
 	}
@@ -59,13 +61,25 @@ impl VolumeRegistry {
 	pub fn get_volume_mut(&mut self, id: &VolumeFingerprint) -> Option<&mut Volume> {
 		self.volumes.get_mut(id)
 	}
+
+	/// The innermost mounted volume holding `path`, a volume mounted inside another holds the
+	/// files below it
+	pub fn volume_at(&self, path: &Path) -> Option<(&VolumeFingerprint, &Volume)> {
+		self.volumes
+			.iter()
+			.filter(|(_, volume)| volume.is_mounted && volume.contains_path(path))
+			.max_by_key(|(_, volume)| volume.mount_point.components().count())
+	}
 }
 
 // Main state manager
 pub struct VolumeManagerState {
 	pub registry: Arc<RwLock<VolumeRegistry>>,
 	options: VolumeOptions,
-	event_tx: broadcast::Sender<VolumeEvent>,
+	event_tx: Publisher<VolumeEvent>,
+	platform: Arc<dyn VolumePlatform>,
+	/// For the paths the user dismissed the volumes of
+	config: watch::Receiver<CoreConfig>,
 	last_scan: Instant,
 }
 
@@ -73,18 +87,70 @@ impl VolumeManagerState {
 	pub fn new(
 		device_id: DevicePubId,
 		options: VolumeOptions,
-		event_tx: broadcast::Sender<VolumeEvent>,
+		event_tx: Publisher<VolumeEvent>,
+		platform: Arc<dyn VolumePlatform>,
+		config: watch::Receiver<CoreConfig>,
 	) -> Self {
 		Self {
 			registry: Arc::new(RwLock::new(VolumeRegistry::new(device_id))),
 			options,
 			event_tx,
+			platform,
+			config,
 			last_scan: Instant::now(),
 		}
 	}
 
 	pub async fn scan_volumes(&mut self) -> Result<(), VolumeError> {
-		let detected_volumes = super::os::get_volumes().await?;
+		let detected_volumes = self.platform.get_volumes().await?;
+		self.register_scan(detected_volumes).await;
+		Ok(())
+	}
+
+	/// Like [`Self::scan_volumes`], forwarding each volume on `progress_tx` with its fingerprint as
+	/// soon as it's probed. The registry is only updated once every mount was probed.
+	pub async fn scan_volumes_with_progress(
+		&mut self,
+		progress_tx: mpsc::UnboundedSender<ScanProgress>,
+	) -> Result<(), VolumeError> {
+		let device_id = self.registry.read().await.device_id.to_db();
+
+		let (platform_tx, mut platform_rx) = mpsc::unbounded_channel();
+		let forward = async {
+			while let Some(mut progress) = platform_rx.recv().await {
+				if let ScanProgress::Probed { volume, .. } = &mut progress {
+					// Listed as a mount that isn't a volume, so the count still adds up
+					if volume.as_ref().is_some_and(|volume| {
+						self.config.borrow().volumes.is_ignored(&volume.mount_point)
+					}) {
+						*volume = None;
+					}
+				}
+				if let ScanProgress::Probed {
+					volume: Some(volume),
+					..
+				} = &mut progress
+				{
+					if volume.fingerprint.is_none() {
+						volume.fingerprint = Some(VolumeFingerprint::new(&device_id, volume));
+					}
+				}
+				let _ = progress_tx.send(progress);
+			}
+		};
+
+		// The platform drops its sender once done, which ends the forwarding
+		let (detected_volumes, ()) =
+			tokio::join!(self.platform.scan_with_progress(platform_tx), forward);
+		self.register_scan(detected_volumes?).await;
+		Ok(())
+	}
+
+	async fn register_scan(&mut self, mut detected_volumes: Vec<Volume>) {
+		{
+			let config = self.config.borrow();
+			detected_volumes.retain(|volume| !config.volumes.is_ignored(&volume.mount_point));
+		}
 
 		let mut registry = self.registry.write().await;
 
@@ -99,35 +165,36 @@ impl VolumeManagerState {
 
 			// Emit event for new volumes
 			if !existing.contains(&fingerprint) {
-				let event_tx = self.event_tx.clone();
-				let _ = event_tx.send(VolumeEvent::VolumeAdded(volume.clone()));
-
-				let mut volume_clone = volume.clone();
-				let event_tx = self.event_tx.clone();
-				drop(registry);
-
-				// Spawn a background task to perform the speed test only for system volumes
-				if volume.mount_type == MountType::System {
-					tokio::spawn(async move {
-						if let Err(e) = volume_clone.speed_test(None, Some(&event_tx)).await {
-							error!(?e, "Failed to perform speed test for volume");
-						}
-					});
-				}
-
-				registry = self.registry.write().await;
+				// Speed tests are run by the maintenance job once the volume is tracked in a library
+				self.event_tx.send(VolumeEvent::VolumeAdded(volume));
 			}
 		}
 
 		// Find and remove volumes that no longer exist
 		for fingerprint in existing.difference(&seen) {
 			if let Some(volume) = registry.remove_volume(fingerprint) {
-				let _ = self.event_tx.send(VolumeEvent::VolumeRemoved(volume));
+				self.event_tx.send(VolumeEvent::VolumeRemoved(volume));
 			}
 		}
 
 		self.last_scan = Instant::now();
-		Ok(())
+	}
+
+	/// Replaces the known volumes with restored ones, publishing what changed
+	pub async fn restore(&mut self, volumes: Vec<Volume>) {
+		let mut registry = self.registry.write().await;
+		let mut previous = std::mem::take(&mut registry.volumes);
+
+		for volume in volumes {
+			let (volume, fingerprint) = registry.register_volume(volume);
+			if previous.remove(&fingerprint).is_none() {
+				self.event_tx.send(VolumeEvent::VolumeAdded(volume));
+			}
+		}
+
+		for volume in previous.into_values() {
+			self.event_tx.send(VolumeEvent::VolumeRemoved(volume));
+		}
 	}
 
 	// pub async fn register_with_library(
@@ -165,40 +232,36 @@ impl VolumeManagerState {
 			.collect()
 	}
 
+	/// The mounted volumes merged with their records in the library, then the tracked volumes
+	/// that aren't mounted
 	pub async fn get_volumes_for_library(
 		&self,
 		library: Arc<Library>,
 	) -> Result<Vec<Volume>, VolumeError> {
-		let registry = self.registry.read().await;
-
-		let mut volumes = Vec::new();
+		let device_id = self.registry.read().await.device_id.to_db();
 
-		let device_id = &self.registry.read().await.device_id;
-
-		let db_volumes = library
-			.db
-			.volume()
-			.find_many(vec![])
-			.exec()
+		let mut db_volumes = tracked::device_records(&library.db, &device_id)
 			.await?
 			.into_iter()
-			.map(Volume::from)
-			.map(|v| {
-				// TODO: maybe just store the fingerprint at this point?
-				let fingerprint = VolumeFingerprint::new(device_id, &v);
-				(fingerprint, v)
-			})
 			.collect::<HashMap<VolumeFingerprint, Volume>>();
 
+		let registry = self.registry.read().await;
+		let mut volumes = Vec::new();
 		for (fingerprint, volume) in registry.volumes() {
 			let mut volume = volume.clone();
 
-			if let Some(db_volume) = db_volumes.get(fingerprint) {
-				volume = Volume::merge_with_db(&volume, db_volume);
+			if let Some(db_volume) = db_volumes.remove(fingerprint) {
+				volume = Volume::merge_with_db(&volume, &db_volume);
+			} else {
+				// Tracked in another library
+				volume.id = None;
+				volume.pub_id = None;
+				volume.device_id = None;
 			}
 
 			volumes.push(volume);
 		}
+		volumes.extend(tracked::offline(db_volumes));
 
 		Ok(volumes)
 	}
@@ -217,7 +280,7 @@ impl VolumeManagerState {
 			.await
 			.ok_or_else(|| VolumeError::NotFound(fingerprint.clone()))?;
 
-		let _ = self.event_tx.send(VolumeEvent::VolumeMountChanged {
+		self.event_tx.send(VolumeEvent::VolumeMountChanged {
 			fingerprint: fingerprint.clone(),
 			is_mounted,
 		});
@@ -0,0 +1,681 @@
+//! The RAID arrays, LVM logical volumes and ZFS pools the volumes of Linux are on.
+//!
+//! The mounts only show the device the file system is on. The layers under it are found walking
+//! its slaves in sysfs, and the closest pool is asked about: md arrays with `mdadm --detail`,
+//! logical volumes with `lvs` and the pools of ZFS datasets with `zpool status`. These need root
+//! for some of their details, when one can't be run or prints something unexpected the volume is
+//! listed without its topology.
+
+use super::{
+	command,
+	types::{MemberState, PoolHealth, PoolKind, PoolMember, VolumeTopology},
+};
+
+use std::{ffi::OsStr, path::Path};
+
+use serde::Deserialize;
+use tracing::debug;
+
+const SYSFS_BLOCK: &str = "/sys/class/block";
+/// What device mapper starts the uuid of the logical volumes of LVM with
+const LVM_UUID_PREFIX: &str = "LVM-";
+/// How deep the logical volumes nest, those with RAID layouts being made of hidden ones
+const MAX_LVM_DEPTH: usize = 4;
+
+#[derive(Debug, Deserialize)]
+struct LvsOutput {
+	report: Vec<LvsReport>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LvsReport {
+	#[serde(default)]
+	lv: Vec<LogicalVolume>,
+}
+
+/// A segment of a logical volume, the ones spanning several are listed once per segment
+#[derive(Debug, Deserialize)]
+struct LogicalVolume {
+	lv_name: String,
+	segtype: String,
+	stripes: String,
+	lv_health_status: String,
+	copy_percent: String,
+	devices: String,
+}
+
+/// The pool under the volume mounted from `device`, `file_system` as the mounts name it
+pub(super) async fn detect(device: &str, file_system: &str) -> Option<VolumeTopology> {
+	if file_system == "zfs" {
+		// Datasets are named after their pool, like `tank/home`
+		return zpool(device.split('/').next()?).await;
+	}
+
+	// The names in /dev/mapper link to the dm-N nodes sysfs knows the devices by
+	let device = tokio::fs::canonicalize(device).await.ok()?;
+	let mut pending = vec![device.file_name()?.to_os_string()];
+
+	while let Some(name) = pending.pop() {
+		let device_dir = Path::new(SYSFS_BLOCK).join(&name);
+		if tokio::fs::metadata(device_dir.join("md")).await.is_ok() {
+			return mdadm(&Path::new("/dev").join(&name)).await;
+		}
+		if let Ok(uuid) = tokio::fs::read_to_string(device_dir.join("dm/uuid")).await {
+			if uuid.starts_with(LVM_UUID_PREFIX) {
+				let dm_name = tokio::fs::read_to_string(device_dir.join("dm/name"))
+					.await
+					.ok()?;
+				return lvs(dm_name.trim()).await;
+			}
+		}
+
+		// Partitions of an array are in the directory of the array
+		if tokio::fs::metadata(device_dir.join("partition"))
+			.await
+			.is_ok()
+		{
+			if let Some(parent) = tokio::fs::canonicalize(&device_dir)
+				.await
+				.ok()
+				.as_deref()
+				.and_then(Path::parent)
+				.and_then(Path::file_name)
+			{
+				pending.push(parent.to_os_string());
+			}
+			continue;
+		}
+
+		let Ok(mut slaves) = tokio::fs::read_dir(device_dir.join("slaves")).await else {
+			continue;
+		};
+		while let Ok(Some(slave)) = slaves.next_entry().await {
+			pending.push(slave.file_name());
+		}
+	}
+
+	None
+}
+
+async fn mdadm(array: &Path) -> Option<VolumeTopology> {
+	let name = array.file_name()?.to_str()?;
+	let arg = command::path_arg("mdadm", array).ok()?;
+	let output = run("mdadm", [OsStr::new("--detail"), arg]).await?;
+
+	parse_mdadm(name, &output)
+}
+
+async fn zpool(pool: &str) -> Option<VolumeTopology> {
+	// Full paths rather than the names of the devices in /dev
+	let output = run("zpool", ["status", "-P", pool]).await?;
+
+	parse_zpool(&output)
+}
+
+async fn lvs(dm_name: &str) -> Option<VolumeTopology> {
+	let (vg, lv) = split_dm_name(dm_name)?;
+	// The hidden logical volumes too, the images of the RAID layouts are
+	let output = run(
+		"lvs",
+		[
+			"--all",
+			"--reportformat",
+			"json",
+			"--options",
+			"lv_name,segtype,stripes,lv_health_status,copy_percent,devices",
+			vg.as_str(),
+		],
+	)
+	.await?;
+
+	parse_lvs(&vg, &lv, &output)
+}
+
+/// What the tool printed, None when it can't be run or fails
+async fn run<I, S>(tool: &'static str, args: I) -> Option<String>
+where
+	I: IntoIterator<Item = S>,
+	S: AsRef<OsStr>,
+{
+	match command::run(tool, args).await {
+		Ok(output) if output.status.success() => Some(output.stdout_lossy()),
+		Ok(output) => {
+			debug!(tool, stderr = %output.stderr_lossy(), "Failed to read the pool of a volume;");
+			None
+		}
+		Err(e) => {
+			debug!(?e, tool, "Failed to read the pool of a volume;");
+			None
+		}
+	}
+}
+
+/// The members that can fail without losing data for a `level` of `legs` devices, mirrors
+/// tolerating all of them but one
+fn redundancy(level: &str, legs: u32) -> u32 {
+	match level {
+		"raid1" | "mirror" => legs.saturating_sub(1),
+		// One in each mirrored pair, so at least one
+		"raid10" => 1,
+		_ if level.starts_with("raid4") || level.starts_with("raid5") => 1,
+		_ if level.starts_with("raid6") => 2,
+		_ => level
+			.strip_prefix("raidz")
+			.or_else(|| level.strip_prefix("draid"))
+			.and_then(|parity| parity.parse().ok())
+			.unwrap_or(0),
+	}
+}
+
+/// The array in what `mdadm --detail` printed about `name`
+pub(super) fn parse_mdadm(name: &str, output: &str) -> Option<VolumeTopology> {
+	let mut level = None;
+	let mut state = String::new();
+	let mut raid_devices = None;
+	let mut members = vec![];
+	let mut in_devices = false;
+
+	for line in output.lines().map(str::trim) {
+		if in_devices {
+			members.extend(mdadm_member(line));
+		} else if line.starts_with("Number") {
+			// The table of the devices closes the output
+			in_devices = true;
+		} else if let Some((key, value)) = line.split_once(" : ") {
+			match key.trim() {
+				"Raid Level" => level = Some(value.trim().to_string()),
+				"State" => state = value.trim().to_lowercase(),
+				"Raid Devices" => raid_devices = value.trim().parse::<u32>().ok(),
+				_ => {}
+			}
+		}
+	}
+
+	let level = level?;
+	// Like `clean, degraded, recovering`
+	let flags = state.split(',').map(str::trim).collect::<Vec<_>>();
+	let health = if flags
+		.iter()
+		.any(|flag| matches!(*flag, "failed" | "inactive"))
+	{
+		PoolHealth::Failed
+	} else if flags.contains(&"recovering") {
+		PoolHealth::Rebuilding
+	} else if flags.contains(&"degraded") {
+		PoolHealth::Degraded
+	} else if flags.iter().any(|flag| matches!(*flag, "clean" | "active")) {
+		PoolHealth::Healthy
+	} else {
+		PoolHealth::Unknown
+	};
+	let legs = raid_devices.unwrap_or(members.len() as u32);
+
+	Some(VolumeTopology {
+		kind: PoolKind::Raid,
+		name: name.to_string(),
+		redundancy: redundancy(&level, legs),
+		level,
+		health,
+		members,
+	})
+}
+
+/// A row of the devices of `mdadm --detail`, like `0  8  1  0  active sync  /dev/sda1`
+fn mdadm_member(line: &str) -> Option<PoolMember> {
+	let columns = line.split_whitespace().collect::<Vec<_>>();
+	// Number, major, minor and slot, then the state and the device
+	if columns.len() < 5 {
+		return None;
+	}
+
+	let (words, device) = match columns[4..].split_last() {
+		Some((device, words)) if device.starts_with('/') => (words, device.to_string()),
+		// Removed devices are only a slot
+		_ => (&columns[4..], "removed".to_string()),
+	};
+	let state = if words.contains(&"faulty") {
+		MemberState::Faulty
+	} else if words.contains(&"removed") {
+		MemberState::Missing
+	} else if words.contains(&"rebuilding") {
+		MemberState::Rebuilding
+	} else if words.contains(&"spare") {
+		MemberState::Spare
+	} else if words.contains(&"active") || words.contains(&"sync") {
+		MemberState::Active
+	} else {
+		MemberState::Unknown
+	};
+
+	Some(PoolMember { device, state })
+}
+
+/// The pool in what `zpool status -P` printed about it
+pub(super) fn parse_zpool(output: &str) -> Option<VolumeTopology> {
+	let mut name = None;
+	let mut state = "";
+	let mut resilvering = false;
+	let mut config = vec![];
+
+	let mut lines = output.lines();
+	for line in lines.by_ref() {
+		match line.trim().split_once(':') {
+			Some(("pool", value)) => name = Some(value.trim().to_string()),
+			Some(("state", value)) => state = value.trim(),
+			Some(("scan", value)) => resilvering = value.contains("resilver in progress"),
+			Some(("config", _)) => break,
+			_ => {}
+		}
+	}
+	for line in lines {
+		if line.trim_start().starts_with("errors:") {
+			break;
+		}
+		if !line.trim().is_empty() && !line.trim_start().starts_with("NAME") {
+			config.push(line);
+		}
+	}
+
+	// The pool, then its virtual devices one level deeper and their devices under them
+	let indent = |line: &str| line.len() - line.trim_start().len();
+	let (&pool, config) = config.split_first()?;
+	let base = indent(pool);
+
+	let mut vdevs: Vec<(String, Vec<PoolMember>)> = vec![];
+	let mut spares = vec![];
+	let mut section = "";
+	for &line in config {
+		let mut columns = line.split_whitespace();
+		let Some(device) = columns.next() else {
+			continue;
+		};
+		let member = PoolMember {
+			device: device.to_string(),
+			state: zpool_member_state(columns.next().unwrap_or_default(), line),
+		};
+
+		match indent(line).saturating_sub(base) {
+			// The logs, caches, spares and special devices are listed after the data
+			0 => section = device,
+			_ if section == "spares" => spares.push(member),
+			// Logs and caches hold no data of their own
+			_ if !section.is_empty() => {}
+			2 => match zpool_group(device) {
+				Some(layout) if !matches!(layout, "replacing" | "spare") => {
+					vdevs.push((layout.to_string(), vec![]))
+				}
+				Some(_) => vdevs.push(("stripe".to_string(), vec![])),
+				None => vdevs.push(("stripe".to_string(), vec![member])),
+			},
+			// Under a vdev, or under one of the devices being replaced
+			_ if zpool_group(device).is_none() => {
+				if let Some((_, members)) = vdevs.last_mut() {
+					members.push(member);
+				}
+			}
+			_ => {}
+		}
+	}
+
+	let level = vdevs.first().map(|(layout, _)| layout.clone())?;
+	// As redundant as the least redundant vdev, the pool is lost with any of them
+	let redundancy = vdevs
+		.iter()
+		.map(|(layout, members)| redundancy(layout, members.len() as u32))
+		.min()
+		.unwrap_or(0);
+	let health = match state {
+		"ONLINE" | "DEGRADED" if resilvering => PoolHealth::Rebuilding,
+		"ONLINE" => PoolHealth::Healthy,
+		"DEGRADED" => PoolHealth::Degraded,
+		"FAULTED" | "UNAVAIL" | "SUSPENDED" => PoolHealth::Failed,
+		_ => PoolHealth::Unknown,
+	};
+
+	Some(VolumeTopology {
+		kind: PoolKind::Zfs,
+		name: name?,
+		level,
+		redundancy,
+		health,
+		members: vdevs
+			.into_iter()
+			.flat_map(|(_, members)| members)
+			.chain(spares)
+			.collect(),
+	})
+}
+
+/// The layout of a group in the config of `zpool status`, like `mirror` for `mirror-0`
+fn zpool_group(name: &str) -> Option<&str> {
+	if name.starts_with("draid") {
+		// Like `draid2:4d:6c:1s-0`
+		return name.split(':').next();
+	}
+
+	let (group, index) = name.rsplit_once('-')?;
+	if !index.bytes().all(|b| b.is_ascii_digit()) {
+		return None;
+	}
+	match group {
+		"raidz" => Some("raidz1"),
+		"mirror" | "raidz1" | "raidz2" | "raidz3" | "replacing" | "spare" => Some(group),
+		_ => None,
+	}
+}
+
+fn zpool_member_state(state: &str, line: &str) -> MemberState {
+	if line.contains("(resilvering)") {
+		return MemberState::Rebuilding;
+	}
+
+	match state {
+		// In use is for the spares that replaced a member
+		"ONLINE" | "INUSE" => MemberState::Active,
+		"AVAIL" => MemberState::Spare,
+		"DEGRADED" | "FAULTED" => MemberState::Faulty,
+		"UNAVAIL" | "REMOVED" | "OFFLINE" => MemberState::Missing,
+		_ => MemberState::Unknown,
+	}
+}
+
+/// The volume group and logical volume device mapper names `vg-lv` after, their own dashes being
+/// doubled
+pub(super) fn split_dm_name(name: &str) -> Option<(String, String)> {
+	let bytes = name.as_bytes();
+	let mut i = 0;
+	while i < bytes.len() {
+		if bytes[i] == b'-' {
+			if bytes.get(i + 1) == Some(&b'-') {
+				i += 2;
+				continue;
+			}
+			let unescape = |part: &str| part.replace("--", "-");
+			return Some((unescape(&name[..i]), unescape(&name[i + 1..])));
+		}
+		i += 1;
+	}
+
+	None
+}
+
+/// The logical volume `lv` in what `lvs --reportformat json` printed about the group `vg`
+pub(super) fn parse_lvs(vg: &str, lv: &str, json: &str) -> Option<VolumeTopology> {
+	let output = serde_json::from_str::<LvsOutput>(json)
+		.map_err(|e| debug!(?e, "Unexpected lvs output;"))
+		.ok()?;
+	let volumes = output
+		.report
+		.into_iter()
+		.flat_map(|report| report.lv)
+		.collect::<Vec<_>>();
+
+	let segment = volumes
+		.iter()
+		.find(|volume| lvm_name(&volume.lv_name) == lv)?;
+	let level = segment.segtype.clone();
+	let redundancy = redundancy(&level, segment.stripes.parse().unwrap_or(0));
+
+	let mut members = vec![];
+	lvm_members(&volumes, lv, 0, &mut members);
+
+	let health = match segment.lv_health_status.as_str() {
+		// Missing physical volumes, which lose the data of the layouts without redundancy
+		"partial" if redundancy == 0 => PoolHealth::Failed,
+		"partial" | "refresh needed" | "mismatches exist" => PoolHealth::Degraded,
+		// The layouts without copies have no sync percent
+		"" if segment
+			.copy_percent
+			.parse::<f32>()
+			.is_ok_and(|percent| percent < 100.0) =>
+		{
+			PoolHealth::Rebuilding
+		}
+		"" => PoolHealth::Healthy,
+		_ => PoolHealth::Unknown,
+	};
+
+	Some(VolumeTopology {
+		kind: PoolKind::Lvm,
+		name: format!("{vg}/{lv}"),
+		level,
+		redundancy,
+		health,
+		members,
+	})
+}
+
+/// The hidden logical volumes are listed in brackets, like `[home_rimage_0]`
+fn lvm_name(name: &str) -> &str {
+	name.trim_matches(&['[', ']'][..])
+}
+
+/// Collects the physical volumes under the logical volume `name`, going through the hidden ones
+fn lvm_members(volumes: &[LogicalVolume], name: &str, depth: usize, members: &mut Vec<PoolMember>) {
+	for segment in volumes
+		.iter()
+		.filter(|volume| lvm_name(&volume.lv_name) == name)
+	{
+		for device in segment
+			.devices
+			.split(',')
+			.filter(|device| !device.is_empty())
+		{
+			// With the extent they start at, like `/dev/sda1(0)`
+			let device = device.rsplit_once('(').map_or(device, |(device, _)| device);
+
+			let member = if device == "[unknown]" {
+				PoolMember {
+					device: device.to_string(),
+					state: MemberState::Missing,
+				}
+			} else if device.starts_with('/') {
+				PoolMember {
+					device: device.to_string(),
+					state: MemberState::Active,
+				}
+			} else {
+				if depth < MAX_LVM_DEPTH {
+					lvm_members(volumes, lvm_name(device), depth + 1, members);
+				}
+				continue;
+			};
+			if !members.contains(&member) {
+				members.push(member);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reads_md_arrays() {
+		let output = "/dev/md0:
+           Version : 1.2
+        Raid Level : raid1
+        Array Size : 976630464 (931.39 GiB 1000.07 GB)
+      Raid Devices : 2
+     Total Devices : 3
+             State : clean, degraded, recovering
+    Active Devices : 1
+    Rebuild Status : 12% complete
+
+    Number   Major   Minor   RaidDevice State
+       0       8        1        0      active sync   /dev/sda1
+       2       8       17        1      spare rebuilding   /dev/sdb1
+       3       8       33        -      faulty   /dev/sdc1
+";
+		let topology = parse_mdadm("md0", output).unwrap();
+		assert_eq!(topology.kind, PoolKind::Raid);
+		assert_eq!(topology.level, "raid1");
+		assert_eq!(topology.redundancy, 1);
+		assert_eq!(topology.health, PoolHealth::Rebuilding);
+		assert_eq!(
+			topology
+				.members
+				.iter()
+				.map(|member| member.state)
+				.collect::<Vec<_>>(),
+			[
+				MemberState::Active,
+				MemberState::Rebuilding,
+				MemberState::Faulty
+			]
+		);
+
+		let degraded = "        Raid Level : raid5
+      Raid Devices : 3
+             State : clean, degraded
+
+    Number   Major   Minor   RaidDevice State
+       0       8        1        0      active sync   /dev/sda1
+       -       0        0        1      removed
+       2       8       33        2      active sync   /dev/sdc1
+";
+		let topology = parse_mdadm("md1", degraded).unwrap();
+		assert_eq!(topology.health, PoolHealth::Degraded);
+		assert_eq!(topology.redundancy, 1);
+		assert_eq!(
+			topology.members[1],
+			PoolMember {
+				device: "removed".to_string(),
+				state: MemberState::Missing
+			}
+		);
+	}
+
+	#[test]
+	fn reads_zfs_pools() {
+		let output = "  pool: tank
+ state: DEGRADED
+status: One or more devices could not be used because the label is missing or
+	invalid.  Sufficient replicas exist for the pool to continue
+	functioning in a degraded state.
+  scan: scrub repaired 0B in 00:10:12 with 0 errors on Sun Oct 11 00:34:13 2026
+config:
+
+	NAME                      STATE     READ WRITE CKSUM
+	tank                      DEGRADED     0     0     0
+	  mirror-0                DEGRADED     0     0     0
+	    /dev/sda1             ONLINE       0     0     0
+	    12345678901234567     UNAVAIL      0     0     0  was /dev/sdb1
+	  mirror-1                ONLINE       0     0     0
+	    /dev/sdc1             ONLINE       0     0     0
+	    /dev/sdd1             ONLINE       0     0     0
+	    /dev/sde1             ONLINE       0     0     0
+	logs
+	  /dev/nvme0n1p1          ONLINE       0     0     0
+	spares
+	  /dev/sdf1               AVAIL
+
+errors: No known data errors
+";
+		let topology = parse_zpool(output).unwrap();
+		assert_eq!(topology.kind, PoolKind::Zfs);
+		assert_eq!(topology.name, "tank");
+		assert_eq!(topology.level, "mirror");
+		// The pool is lost with the first mirror
+		assert_eq!(topology.redundancy, 1);
+		assert_eq!(topology.health, PoolHealth::Degraded);
+		assert_eq!(topology.members.len(), 6);
+		assert_eq!(topology.members[1].state, MemberState::Missing);
+		assert_eq!(
+			topology.members[5],
+			PoolMember {
+				device: "/dev/sdf1".to_string(),
+				state: MemberState::Spare
+			}
+		);
+
+		let resilvering = "  pool: backup
+ state: ONLINE
+  scan: resilver in progress since Sun Oct 11 00:24:01 2026
+config:
+
+	NAME              STATE     READ WRITE CKSUM
+	backup            ONLINE       0     0     0
+	  raidz2-0        ONLINE       0     0     0
+	    /dev/sda1     ONLINE       0     0     0
+	    /dev/sdb1     ONLINE       0     0     0
+	    /dev/sdc1     ONLINE       0     0     0
+	    /dev/sdd1     ONLINE       0     0     0  (resilvering)
+";
+		let topology = parse_zpool(resilvering).unwrap();
+		assert_eq!(topology.level, "raidz2");
+		assert_eq!(topology.redundancy, 2);
+		assert_eq!(topology.health, PoolHealth::Rebuilding);
+		assert_eq!(topology.members[3].state, MemberState::Rebuilding);
+
+		let stripe = "  pool: scratch
+ state: ONLINE
+config:
+
+	NAME          STATE     READ WRITE CKSUM
+	scratch       ONLINE       0     0     0
+	  /dev/sda1   ONLINE       0     0     0
+	  /dev/sdb1   ONLINE       0     0     0
+";
+		let topology = parse_zpool(stripe).unwrap();
+		assert_eq!(topology.level, "stripe");
+		assert_eq!(topology.redundancy, 0);
+		assert_eq!(topology.members.len(), 2);
+	}
+
+	#[test]
+	fn reads_logical_volumes() {
+		assert_eq!(
+			split_dm_name("vg0-home"),
+			Some(("vg0".to_string(), "home".to_string()))
+		);
+		assert_eq!(
+			split_dm_name("my--vg-data--lv"),
+			Some(("my-vg".to_string(), "data-lv".to_string()))
+		);
+		assert_eq!(split_dm_name("luks"), None);
+
+		let json = r#"{
+			"report": [{ "lv": [
+				{"lv_name":"home", "segtype":"raid1", "stripes":"2", "lv_health_status":"partial",
+					"copy_percent":"100.00", "devices":"home_rimage_0(0),home_rimage_1(0)"},
+				{"lv_name":"[home_rimage_0]", "segtype":"linear", "stripes":"1",
+					"lv_health_status":"", "copy_percent":"", "devices":"/dev/sda1(1)"},
+				{"lv_name":"[home_rimage_1]", "segtype":"linear", "stripes":"1",
+					"lv_health_status":"partial", "copy_percent":"", "devices":"[unknown](1)"},
+				{"lv_name":"root", "segtype":"linear", "stripes":"1", "lv_health_status":"",
+					"copy_percent":"", "devices":"/dev/sda1(100)"},
+				{"lv_name":"root", "segtype":"linear", "stripes":"1", "lv_health_status":"",
+					"copy_percent":"", "devices":"/dev/sdb1(0)"}
+			] }]
+		}"#;
+
+		let home = parse_lvs("vg0", "home", json).unwrap();
+		assert_eq!(home.kind, PoolKind::Lvm);
+		assert_eq!(home.name, "vg0/home");
+		assert_eq!(home.redundancy, 1);
+		assert_eq!(home.health, PoolHealth::Degraded);
+		assert_eq!(
+			home.members,
+			[
+				PoolMember {
+					device: "/dev/sda1".to_string(),
+					state: MemberState::Active
+				},
+				PoolMember {
+					device: "[unknown]".to_string(),
+					state: MemberState::Missing
+				}
+			]
+		);
+
+		let root = parse_lvs("vg0", "root", json).unwrap();
+		assert_eq!(root.level, "linear");
+		assert_eq!(root.redundancy, 0);
+		assert_eq!(root.health, PoolHealth::Healthy);
+		assert_eq!(root.members.len(), 2);
+
+		assert!(parse_lvs("vg0", "swap", json).is_none());
+	}
+}
@@ -0,0 +1,334 @@
+//! The records of the volumes tracked in the libraries, which outlive the mounts.
+//!
+//! Records keep the fingerprint the volume was tracked with and when it was last seen, the space
+//! it had over time is in its [history](super::history). Records written before the fingerprint
+//! was stored are matched by computing it again from their fields, then given it. The volumes
+//! tracked before their serial was read are matched by the fingerprint of their properties, see
+//! [`VolumeFingerprint::from_properties`], and moved to the one of their serial.
+//!
+//! The records are synced to the other devices of the library, which list them as the
+//! [volumes of those devices](RemoteVolume). The history stays on the device that recorded it.
+
+use super::{
+	error::VolumeError,
+	types::{Volume, VolumeFingerprint, VolumeRecord},
+};
+use crate::library::Library;
+
+use sd_prisma::{
+	prisma::{device, volume, volume_history, PrismaClient},
+	prisma_sync,
+};
+use sd_sync::{sync_entry, OperationFactory};
+
+use chrono::Utc;
+use serde::Serialize;
+use specta::Type;
+
+/// A volume tracked by another device of the library, as it was when that device last synced
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct RemoteVolume {
+	pub device_pub_id: Vec<u8>,
+	/// Unknown until the record of the device itself was synced
+	pub device_name: Option<String>,
+	pub volume: Volume,
+}
+
+/// The records of the volumes of the device, with their fingerprint
+pub(crate) async fn device_records(
+	db: &PrismaClient,
+	device_pub_id: &[u8],
+) -> Result<Vec<(VolumeFingerprint, Volume)>, VolumeError> {
+	Ok(db
+		.volume()
+		.find_many(vec![volume::device::is(vec![device::pub_id::equals(
+			device_pub_id.to_vec(),
+		)])])
+		.exec()
+		.await?
+		.into_iter()
+		.map(Volume::from_record)
+		.map(|volume| {
+			let fingerprint = volume
+				.fingerprint
+				.clone()
+				.unwrap_or_else(|| VolumeFingerprint::new(device_pub_id, &volume));
+			(fingerprint, volume)
+		})
+		.collect())
+}
+
+/// The records of the volumes the other devices of the library track, for the UI to show where
+/// they're connected
+pub async fn remote_volumes(
+	db: &PrismaClient,
+	device_pub_id: &[u8],
+) -> Result<Vec<RemoteVolume>, VolumeError> {
+	Ok(db
+		.volume()
+		.find_many(vec![volume::device::is(vec![device::pub_id::not(
+			device_pub_id.to_vec(),
+		)])])
+		.with(volume::device::fetch())
+		.exec()
+		.await?
+		.into_iter()
+		.filter_map(|mut record| {
+			let device = record.device.take().flatten()?;
+			let mut volume = Volume::from_record(record);
+			// Computed from the pub ID of the device that tracked it
+			volume.fingerprint = Some(
+				volume
+					.fingerprint
+					.clone()
+					.unwrap_or_else(|| VolumeFingerprint::new(&device.pub_id, &volume)),
+			);
+			Some(RemoteVolume {
+				device_pub_id: device.pub_id,
+				device_name: device.name,
+				volume,
+			})
+		})
+		.collect())
+}
+
+/// The pub IDs of the records with this fingerprint, which their sync operations are keyed by
+async fn record_pub_ids(
+	db: &PrismaClient,
+	fingerprint: &VolumeFingerprint,
+) -> Result<Vec<Vec<u8>>, VolumeError> {
+	Ok(db
+		.volume()
+		.find_many(vec![volume::fingerprint::equals(Some(
+			fingerprint.0.clone(),
+		))])
+		.select(volume::select!({ pub_id }))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|record| record.pub_id)
+		.collect())
+}
+
+/// Brings the records of the volume up to date with how it was just seen, doing nothing when it
+/// isn't tracked
+pub(crate) async fn seen(
+	library: &Library,
+	fingerprint: &VolumeFingerprint,
+	volume: &Volume,
+	is_mounted: bool,
+) -> Result<(), VolumeError> {
+	let Library { db, sync, .. } = library;
+	let pub_ids = record_pub_ids(db, fingerprint).await?;
+	if pub_ids.is_empty() {
+		return Ok(());
+	}
+
+	let date_last_seen = Utc::now().into();
+	let capacity = volume.total_bytes_capacity.0.to_string();
+	let available = volume.total_bytes_available.0.to_string();
+	let mount_point = volume.mount_point.to_string_lossy().to_string();
+
+	let mut params = vec![
+		volume::is_mounted::set(Some(is_mounted)),
+		volume::date_last_seen::set(Some(date_last_seen)),
+		volume::total_bytes_capacity::set(Some(capacity.clone())),
+		volume::total_bytes_available::set(Some(available.clone())),
+	];
+	if is_mounted {
+		params.push(volume::mount_point::set(Some(mount_point.clone())));
+	}
+
+	let ops = pub_ids
+		.into_iter()
+		.map(|pub_id| {
+			let mut values = vec![
+				sync_entry!(is_mounted, volume::is_mounted),
+				sync_entry!(date_last_seen, volume::date_last_seen),
+				sync_entry!(&capacity, volume::total_bytes_capacity),
+				sync_entry!(&available, volume::total_bytes_available),
+			];
+			if is_mounted {
+				values.push(sync_entry!(&mount_point, volume::mount_point));
+			}
+			sync.shared_update(prisma_sync::volume::SyncId { pub_id }, values)
+		})
+		.collect();
+
+	sync.write_ops(
+		db,
+		(
+			ops,
+			db.volume().update_many(
+				vec![volume::fingerprint::equals(Some(fingerprint.0.clone()))],
+				params,
+			),
+		),
+	)
+	.await?;
+
+	Ok(())
+}
+
+/// Moves the records and the history of the volume to the fingerprint it has now
+pub(crate) async fn refingerprint(
+	library: &Library,
+	from: &VolumeFingerprint,
+	to: &VolumeFingerprint,
+) -> Result<(), VolumeError> {
+	let Library { db, sync, .. } = library;
+
+	let ops = record_pub_ids(db, from)
+		.await?
+		.into_iter()
+		.map(|pub_id| {
+			sync.shared_update(
+				prisma_sync::volume::SyncId { pub_id },
+				[sync_entry!(&to.0, volume::fingerprint)],
+			)
+		})
+		.collect::<Vec<_>>();
+	if !ops.is_empty() {
+		sync.write_ops(
+			db,
+			(
+				ops,
+				db.volume().update_many(
+					vec![volume::fingerprint::equals(Some(from.0.clone()))],
+					vec![volume::fingerprint::set(Some(to.0.clone()))],
+				),
+			),
+		)
+		.await?;
+	}
+
+	db.volume_history()
+		.update_many(
+			vec![volume_history::fingerprint::equals(from.0.clone())],
+			vec![volume_history::fingerprint::set(to.0.clone())],
+		)
+		.exec()
+		.await?;
+
+	Ok(())
+}
+
+/// Records the volumes that went away while the node wasn't running as unmounted, they were last
+/// seen whenever their record was last written
+pub(crate) async fn gone(library: &Library, records: &[Volume]) -> Result<(), VolumeError> {
+	let Library { db, sync, .. } = library;
+	let (ops, ids) = records
+		.iter()
+		.filter(|record| record.is_mounted)
+		.filter_map(|record| Some((record.pub_id.clone()?, record.id?)))
+		.map(|(pub_id, id)| {
+			(
+				sync.shared_update(
+					prisma_sync::volume::SyncId { pub_id },
+					[sync_entry!(false, volume::is_mounted)],
+				),
+				id,
+			)
+		})
+		.unzip::<_, _, Vec<_>, Vec<_>>();
+	if ops.is_empty() {
+		return Ok(());
+	}
+
+	sync.write_ops(
+		db,
+		(
+			ops,
+			db.volume().update_many(
+				vec![volume::id::in_vec(ids)],
+				vec![volume::is_mounted::set(Some(false))],
+			),
+		),
+	)
+	.await?;
+
+	Ok(())
+}
+
+/// Deletes the records of the volume, on the other devices of the library as well
+pub(crate) async fn delete(library: &Library, records: &[Volume]) -> Result<(), VolumeError> {
+	let Library { db, sync, .. } = library;
+	let (ops, ids) = records
+		.iter()
+		.filter_map(|record| Some((record.pub_id.clone()?, record.id?)))
+		.map(|(pub_id, id)| {
+			(
+				sync.shared_delete(prisma_sync::volume::SyncId { pub_id }),
+				id,
+			)
+		})
+		.unzip::<_, _, Vec<_>, Vec<_>>();
+	if ops.is_empty() {
+		return Err(VolumeError::NotInDatabase);
+	}
+
+	sync.write_ops(
+		db,
+		(ops, db.volume().delete_many(vec![volume::id::in_vec(ids)])),
+	)
+	.await?;
+
+	Ok(())
+}
+
+/// The records of the tracked volumes that aren't mounted, as volumes to list
+pub(crate) fn offline(
+	records: impl IntoIterator<Item = (VolumeFingerprint, Volume)>,
+) -> impl Iterator<Item = Volume> {
+	records.into_iter().map(|(fingerprint, mut volume)| {
+		volume.fingerprint = Some(fingerprint);
+		volume.is_mounted = false;
+		volume
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::volume::{DiskType, FileSystem, MountType};
+
+	use std::path::PathBuf;
+
+	fn record(id: i32, name: &str, fingerprint: Option<VolumeFingerprint>) -> Volume {
+		let mut volume = Volume::new(
+			name.to_string(),
+			MountType::External,
+			PathBuf::from("/media").join(name),
+			vec![],
+			DiskType::SSD,
+			FileSystem::EXT4,
+			1000,
+			500,
+			false,
+		);
+		volume.id = Some(id);
+		volume.fingerprint = fingerprint;
+		volume
+	}
+
+	#[test]
+	fn lists_the_records_as_unmounted_volumes() {
+		let device_pub_id = [1; 16];
+		let stored = VolumeFingerprint(vec![7]);
+		let legacy = record(2, "backup", None);
+		let legacy_fingerprint = VolumeFingerprint::new(&device_pub_id, &legacy);
+
+		let records = vec![
+			(stored.clone(), record(1, "usb", Some(stored.clone()))),
+			(legacy_fingerprint.clone(), legacy),
+		];
+		let offline = offline(records).collect::<Vec<_>>();
+
+		assert_eq!(offline[0].fingerprint, Some(stored));
+		// Listed with the fingerprint it was matched with, the record gets it on the next update
+		assert_eq!(offline[1].id, Some(2));
+		assert_eq!(offline[1].fingerprint, Some(legacy_fingerprint));
+		assert!(offline.iter().all(|volume| !volume.is_mounted));
+	}
+}
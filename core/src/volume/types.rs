@@ -1,46 +1,29 @@
 use super::error::VolumeError;
-use crate::volume::speed::SpeedTest;
-use sd_core_sync::DevicePubId;
-use sd_prisma::prisma::{
-	device,
-	volume::{self},
-	PrismaClient,
+use crate::{
+	library::Library,
+	node::{Replay, Topic},
 };
+use chrono::Utc;
+use sd_prisma::{
+	prisma::{device, volume},
+	prisma_sync,
+};
+use sd_sync::{
+	option_sync_db_entry, sync_db_entry, sync_db_nullable_entry, sync_entry, OperationFactory,
+};
+use sd_utils::chain_optional_iter;
 use serde::{Deserialize, Serialize};
-use serde_with::{serde_as, DisplayFromStr};
-use specta::Type;
-use std::fmt;
 use std::path::PathBuf;
-use std::{path::Path, sync::Arc};
-use strum_macros::Display;
 use uuid::Uuid;
 
-/// A fingerprint of a volume, used to identify it when it is not persisted in the database
-#[derive(Debug, Clone, Hash, Eq, PartialEq, Type)]
-pub struct VolumeFingerprint(pub Vec<u8>);
-
-impl VolumeFingerprint {
-	pub fn new(device_id: &DevicePubId, volume: &Volume) -> Self {
-		// Hash the device ID, mount point, name, total bytes capacity, and file system
-		let mut hasher = blake3::Hasher::new();
-		hasher.update(&device_id.to_db());
-		hasher.update(volume.mount_point.to_string_lossy().as_bytes());
-		hasher.update(volume.name.as_bytes());
-		hasher.update(&volume.total_bytes_capacity.to_be_bytes());
-		hasher.update(volume.file_system.to_string().as_bytes());
-		// These are all properties that are unique to a volume and unlikely to change
-		// If a .spacedrive file is found in the volume, and is fingerprint does not match,
-		// but the `pub_id` is the same, we can update the values and regenerate the fingerprint
-		// preserving the tracked instance of the volume
-		Self(hasher.finalize().as_bytes().to_vec())
-	}
-}
-
-impl fmt::Display for VolumeFingerprint {
-	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		write!(f, "{}", hex::encode(&self.0))
-	}
-}
+pub use sd_core_volume_types::{
+	BusyProcess, ByteSize, DegradedMode, DeviceCapabilities, DiskType, EncryptionKind, FileSystem,
+	HealthStatus, LocalizedMessage, LowSpaceThreshold, MemberState, MissingVolumeData,
+	MountOptions, MountOwner, MountType, OperationPlan, PlanStep, PoolHealth, PoolKind, PoolMember,
+	PredictedOutcome, ScanProgress, UnitPolicy, UnmountedDevice, Volume, VolumeErrorCode,
+	VolumeErrorInfo, VolumeEvent, VolumeFingerprint, VolumeHealth, VolumeOperation, VolumeSettings,
+	VolumeTopology,
+};
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct VolumePubId(pub Vec<u8>);
@@ -59,106 +42,49 @@ impl Into<Vec<u8>> for VolumePubId {
 
 pub type LibraryId = Uuid;
 
-/// Events emitted by the Volume Manager when volume state changes
-#[derive(Debug, Clone, Type, Deserialize, Serialize)]
-pub enum VolumeEvent {
-	/// Emitted when a new volume is discovered and added
-	VolumeAdded(Volume),
-	/// Emitted when a volume is removed from the system
-	VolumeRemoved(Volume),
-	/// Emitted when a volume's properties are updated
-	VolumeUpdated { old: Volume, new: Volume },
-	/// Emitted when a volume's speed test completes
-	VolumeSpeedTested {
-		fingerprint: VolumeFingerprint,
-		read_speed: u64,
-		write_speed: u64,
-	},
-	/// Emitted when a volume's mount status changes
-	VolumeMountChanged {
-		fingerprint: VolumeFingerprint,
-		is_mounted: bool,
-	},
-	/// Emitted when a volume encounters an error
-	VolumeError {
-		fingerprint: VolumeFingerprint,
-		error: String,
-	},
+impl Topic for VolumeEvent {
+	const NAME: &'static str = "volumes";
+
+	/// New subscribers get the volumes currently present, keyed by mount point as the volumes
+	/// reported by the watcher aren't fingerprinted yet. An update can move the volume, its
+	/// removal comes with the new mount point.
+	fn replay(&self) -> Replay {
+		let key = |volume: &Volume| volume.mount_point.to_string_lossy().to_string();
+		match self {
+			VolumeEvent::VolumeAdded(volume) => Replay::Keep(key(volume)),
+			VolumeEvent::VolumeUpdated { old, new } => Replay::Rekey {
+				old: key(old),
+				new: key(new),
+			},
+			VolumeEvent::VolumeRemoved(volume) => Replay::Forget(key(volume)),
+			// Carried by the `VolumeUpdated` they follow
+			VolumeEvent::VolumeRemounted { .. }
+			| VolumeEvent::VolumeSpaceChanged { .. }
+			| VolumeEvent::VolumeSpeedTested { .. }
+			| VolumeEvent::VolumeMountChanged { .. }
+			| VolumeEvent::VolumeError { .. }
+			| VolumeEvent::VolumeFillingUp { .. }
+			| VolumeEvent::VolumeHealthChanged { .. }
+			| VolumeEvent::VolumeDegraded { .. }
+			| VolumeEvent::LowSpace { .. } => Replay::Skip,
+		}
+	}
 }
 
-/// Represents a physical or virtual storage volume in the system
-#[serde_as]
-#[derive(Serialize, Deserialize, Debug, Clone, Type)]
-pub struct Volume {
-	/// Fingerprint of the volume as a hash of its properties, not persisted to the database
-	/// Used as the unique identifier for a volume in this module
-	pub fingerprint: Option<VolumeFingerprint>,
-	/// Database ID (None if not yet committed to database)
-	pub id: Option<i32>,
-	/// Unique public identifier
-	pub pub_id: Option<Vec<u8>>,
-	/// Database ID of the device this volume is attached to, if any
-	pub device_id: Option<i32>,
+/// Persistence of volumes, the shared types don't know about the database
+pub(crate) trait VolumeRecord: Sized {
+	/// Falls back to defaults for the fields the record is missing
+	fn from_record(vol: volume::Data) -> Self;
 
-	/// Human-readable volume name
-	pub name: String,
-	/// Type of mount (system, external, etc)
-	pub mount_type: MountType,
-	/// Path where the volume is mounted
-	#[specta(type = Vec<String>)]
-	pub mount_point: PathBuf,
-	/// for APFS volumes like Macintosh HD, additional mount points are returned
-	#[specta(type = Vec<String>)]
-	pub mount_points: Vec<PathBuf>,
-	/// Whether the volume is currently mounted
-	pub is_mounted: bool,
-	/// Type of storage device (SSD, HDD, etc)
-	pub disk_type: DiskType,
-	/// Filesystem type (NTFS, EXT4, etc)
-	pub file_system: FileSystem,
-	/// Whether the volume is mounted read-only
-	pub read_only: bool,
-	/// Current error status if any
-	pub error_status: Option<String>,
+	/// Creates a new volume record in the library, synced to its other devices
+	async fn create(&self, library: &Library, device_pub_id: Vec<u8>) -> Result<Self, VolumeError>;
 
-	// Performance metrics
-	/// Read speed in megabytes per second
-	pub read_speed_mbps: Option<u64>,
-	/// Write speed in megabytes per second
-	pub write_speed_mbps: Option<u64>,
-	/// Total storage capacity in bytes
-	#[specta(type = String)]
-	#[serde_as(as = "DisplayFromStr")]
-	pub total_bytes_capacity: u64,
-	/// Available storage space in bytes
-	#[specta(type = String)]
-	#[serde_as(as = "DisplayFromStr")]
-	pub total_bytes_available: u64,
+	/// Updates an existing volume record in the library, synced to its other devices
+	async fn update(&self, library: &Library) -> Result<(), VolumeError>;
 }
 
-// We can use this to see if a volume has changed
-impl PartialEq for Volume {
-	fn eq(&self, other: &Self) -> bool {
-		self.name == other.name
-            && self.disk_type == other.disk_type
-            && self.file_system == other.file_system
-			&& self.mount_type == other.mount_type
-			&& self.mount_point == other.mount_point
-			// Check if any mount points overlap
-			&& (self.mount_points.iter().any(|mp| other.mount_points.contains(mp))
-			|| other.mount_points.iter().any(|mp| self.mount_points.contains(mp)))
-			&& self.is_mounted == other.is_mounted
-			&& self.read_only == other.read_only
-			&& self.error_status == other.error_status
-			&& self.total_bytes_capacity == other.total_bytes_capacity
-			&& self.total_bytes_available == other.total_bytes_available
-	}
-}
-
-impl Eq for Volume {}
-
-impl From<volume::Data> for Volume {
-	fn from(vol: volume::Data) -> Self {
+impl VolumeRecord for Volume {
+	fn from_record(vol: volume::Data) -> Self {
 		Volume {
 			id: Some(vol.id),
 			pub_id: Some(vol.pub_id),
@@ -183,248 +109,152 @@ impl From<volume::Data> for Volume {
 				.map(FileSystem::from_string)
 				.unwrap_or_else(|| FileSystem::Other("Unknown".to_string())),
 			read_only: vol.read_only.unwrap_or(false),
+			// Only known while mounted, a scan fills them in
+			owner: None,
+			is_accessible: true,
+			encryption: None,
+			is_locked: false,
 			error_status: vol.error_status,
+			serial: vol.serial,
+			// Only known while mounted, the fingerprint stored with the volume already has it
+			mount_root: None,
+			// Read from the pool on every scan
+			topology: None,
+			is_degraded: false,
 			total_bytes_capacity: vol
 				.total_bytes_capacity
 				.and_then(|t| t.parse().ok())
-				.unwrap_or(0),
+				.unwrap_or_default(),
 			total_bytes_available: vol
 				.total_bytes_available
 				.and_then(|a| a.parse().ok())
-				.unwrap_or(0),
+				.unwrap_or_default(),
 			read_speed_mbps: vol.read_speed_mbps.map(|s| s as u64),
 			write_speed_mbps: vol.write_speed_mbps.map(|s| s as u64),
-			fingerprint: None,
+			// Records from before the fingerprint was stored have it computed by the callers
+			fingerprint: vol.fingerprint.map(VolumeFingerprint),
 		}
 	}
-}
-
-impl Volume {
-	/// Creates a new Volume instance from detected system volume information
-	pub fn new(
-		name: String,
-		mount_type: MountType,
-		mount_point: PathBuf,
-		mount_points: Vec<PathBuf>,
-		disk_type: DiskType,
-		file_system: FileSystem,
-		total_bytes_capacity: u64,
-		total_bytes_available: u64,
-		read_only: bool,
-	) -> Self {
-		Self {
-			id: None,
-			pub_id: None,
-			device_id: None,
-			name,
-			mount_type,
-			mount_point,
-			mount_points,
-			is_mounted: true,
-			disk_type,
-			file_system,
-			read_only,
-			error_status: None,
-			read_speed_mbps: None,
-			write_speed_mbps: None,
-			total_bytes_capacity,
-			total_bytes_available,
-			fingerprint: None,
-		}
-	}
-
-	/// Check if a path is under any of this volume's mount points
-	pub fn contains_path(&self, path: &Path) -> bool {
-		self.mount_points.iter().any(|mp| path.starts_with(mp))
-	}
 
-	/// Merge system detected volume with database volume, preferring system values for hardware info
-	pub fn merge_with_db(system_volume: &Volume, db_volume: &Volume) -> Volume {
-		Volume {
-			// Keep system-detected hardware properties
-			mount_point: system_volume.mount_point.clone(),
-			mount_points: system_volume.mount_points.clone(),
-			total_bytes_capacity: system_volume.total_bytes_capacity,
-			total_bytes_available: system_volume.total_bytes_available,
-			disk_type: system_volume.disk_type.clone(),
-			file_system: system_volume.file_system.clone(),
-			mount_type: system_volume.mount_type.clone(),
-			is_mounted: system_volume.is_mounted,
-			fingerprint: system_volume.fingerprint.clone(),
-			name: system_volume.name.clone(),
-			read_only: system_volume.read_only,
-			error_status: system_volume.error_status.clone(),
-			read_speed_mbps: system_volume.read_speed_mbps,
-			write_speed_mbps: system_volume.write_speed_mbps,
-
-			// Keep database-tracked properties and metadata
-			id: db_volume.id,
-			device_id: db_volume.device_id,
-			pub_id: db_volume.pub_id.clone(),
-		}
-	}
-
-	pub fn is_volume_tracked(&self) -> bool {
-		self.pub_id.is_some()
-	}
-
-	/// Creates a new volume record in the database
-	pub async fn create(
+	async fn create(
 		&self,
-		db: &Arc<PrismaClient>,
+		library: &Library,
 		device_pub_id: Vec<u8>,
 	) -> Result<Volume, VolumeError> {
+		let Library { db, sync, .. } = library;
 		let pub_id = Uuid::now_v7().as_bytes().to_vec();
+		let fingerprint = self
+			.fingerprint
+			.clone()
+			.unwrap_or_else(|| VolumeFingerprint::new(&device_pub_id, self));
 
-		let device_id = db
+		if db
 			.device()
-			.find_unique(device::pub_id::equals(device_pub_id.clone()))
-			.select(device::select!({ id }))
+			.count(vec![device::pub_id::equals(device_pub_id.clone())])
 			.exec()
-			.await?
-			.ok_or(VolumeError::DeviceNotFound(device_pub_id))?
-			.id;
+			.await? == 0
+		{
+			return Err(VolumeError::DeviceNotFound(device_pub_id));
+		}
 
-		let volume = db
-			.volume()
-			.create(
-				pub_id,
-				vec![
-					volume::name::set(Some(self.name.clone())),
-					volume::mount_type::set(Some(self.mount_type.to_string())),
-					volume::mount_point::set(Some(self.mount_point.to_str().unwrap().to_string())),
-					volume::is_mounted::set(Some(self.is_mounted)),
-					volume::disk_type::set(Some(self.disk_type.to_string())),
-					volume::file_system::set(Some(self.file_system.to_string())),
-					volume::read_only::set(Some(self.read_only)),
-					volume::error_status::set(self.error_status.clone()),
-					volume::total_bytes_capacity::set(Some(self.total_bytes_capacity.to_string())),
-					volume::total_bytes_available::set(Some(
-						self.total_bytes_available.to_string(),
-					)),
-					volume::read_speed_mbps::set(
-						self.read_speed_mbps.filter(|&v| v != 0).map(|v| v as i64),
+		let (sync_params, db_params) = synced_fields(self)
+			.into_iter()
+			.chain([
+				sync_db_entry!(fingerprint.0, volume::fingerprint),
+				sync_db_entry!(Utc::now(), volume::date_last_seen),
+				(
+					sync_entry!(
+						prisma_sync::device::SyncId {
+							pub_id: device_pub_id.clone()
+						},
+						volume::device
 					),
-					volume::write_speed_mbps::set(
-						self.write_speed_mbps.filter(|&v| v != 0).map(|v| v as i64),
-					),
-					volume::device_id::set(Some(device_id)),
-				],
+					volume::device::connect(device::pub_id::equals(device_pub_id)),
+				),
+			])
+			.unzip::<_, _, Vec<_>, Vec<_>>();
+
+		let volume = sync
+			.write_op(
+				db,
+				sync.shared_create(
+					prisma_sync::volume::SyncId {
+						pub_id: pub_id.clone(),
+					},
+					sync_params,
+				),
+				db.volume().create(pub_id, db_params),
 			)
-			.exec()
 			.await?;
-		Ok(volume.into())
+		Ok(Volume::from_record(volume))
 	}
 
-	/// Updates an existing volume record in the database
-	pub async fn update(&self, db: &PrismaClient) -> Result<(), VolumeError> {
-		let id = self.id.ok_or(VolumeError::NotInDatabase)?;
-
-		db.volume()
-			.update(
-				volume::id::equals(id),
-				vec![
-					volume::name::set(Some(self.name.clone())),
-					volume::mount_type::set(Some(self.mount_type.to_string())),
-					volume::mount_point::set(Some(self.mount_point.to_str().unwrap().to_string())),
-					volume::is_mounted::set(Some(self.is_mounted)),
-					volume::disk_type::set(Some(self.disk_type.to_string())),
-					volume::file_system::set(Some(self.file_system.to_string())),
-					volume::read_only::set(Some(self.read_only)),
-					volume::error_status::set(self.error_status.clone()),
-					volume::total_bytes_capacity::set(Some(self.total_bytes_capacity.to_string())),
-					volume::total_bytes_available::set(Some(
-						self.total_bytes_available.to_string(),
-					)),
-					volume::read_speed_mbps::set(
-						self.read_speed_mbps.filter(|&v| v != 0).map(|v| v as i64),
-					),
-					volume::write_speed_mbps::set(
-						self.write_speed_mbps.filter(|&v| v != 0).map(|v| v as i64),
-					),
-				],
-			)
-			.exec()
-			.await?;
+	async fn update(&self, library: &Library) -> Result<(), VolumeError> {
+		let Library { db, sync, .. } = library;
+		let (Some(id), Some(pub_id)) = (self.id, self.pub_id.clone()) else {
+			return Err(VolumeError::NotInDatabase);
+		};
+
+		let (sync_params, db_params) = chain_optional_iter(
+			synced_fields(self),
+			[
+				// Backfills the records from before the fingerprint was stored
+				option_sync_db_entry!(
+					self.fingerprint
+						.as_ref()
+						.map(|fingerprint| fingerprint.0.clone()),
+					volume::fingerprint
+				),
+				self.is_mounted
+					.then(|| sync_db_entry!(Utc::now(), volume::date_last_seen)),
+			],
+		)
+		.into_iter()
+		.unzip::<_, _, Vec<_>, Vec<_>>();
+
+		sync.write_op(
+			db,
+			sync.shared_update(prisma_sync::volume::SyncId { pub_id }, sync_params),
+			db.volume().update(volume::id::equals(id), db_params),
+		)
+		.await?;
 		Ok(())
 	}
 }
 
-/// Represents the type of physical storage device
-#[derive(Serialize, Deserialize, Debug, Clone, Type, Hash, PartialEq, Eq, Display)]
-#[allow(clippy::upper_case_acronyms)]
-pub enum DiskType {
-	/// Solid State Drive
-	SSD,
-	/// Hard Disk Drive
-	HDD,
-	/// Unknown or virtual disk type
-	Unknown,
-}
-
-impl DiskType {
-	pub fn from_string(disk_type: &str) -> Self {
-		match disk_type.to_uppercase().as_str() {
-			"SSD" => Self::SSD,
-			"HDD" => Self::HDD,
-			_ => Self::Unknown,
-		}
-	}
-}
-
-/// Represents the filesystem type of the volume
-#[derive(Serialize, Deserialize, Debug, Clone, Type, Hash, PartialEq, Eq, Display)]
-pub enum FileSystem {
-	/// Windows NTFS filesystem
-	NTFS,
-	/// FAT32 filesystem
-	FAT32,
-	/// Linux EXT4 filesystem
-	EXT4,
-	/// Apple APFS filesystem
-	APFS,
-	/// ExFAT filesystem
-	ExFAT,
-	/// Other/unknown filesystem type
-	Other(String),
-}
-
-impl FileSystem {
-	pub fn from_string(fs: &str) -> Self {
-		match fs.to_uppercase().as_str() {
-			"NTFS" => FileSystem::NTFS,
-			"FAT32" => FileSystem::FAT32,
-			"EXT4" => FileSystem::EXT4,
-			"APFS" => FileSystem::APFS,
-			"EXFAT" => FileSystem::ExFAT,
-			other => FileSystem::Other(other.to_string()),
-		}
-	}
-}
-
-/// Represents how the volume is mounted in the system
-#[derive(Serialize, Deserialize, Debug, Clone, Type, Hash, PartialEq, Eq, Display)]
-pub enum MountType {
-	/// System/boot volume
-	System,
-	/// External/removable volume
-	External,
-	/// Network-attached volume
-	Network,
-	/// Virtual/container volume
-	Virtual,
-}
-
-impl MountType {
-	pub fn from_string(mount_type: &str) -> Self {
-		match mount_type.to_uppercase().as_str() {
-			"SYSTEM" => Self::System,
-			"EXTERNAL" => Self::External,
-			"NETWORK" => Self::Network,
-			"VIRTUAL" => Self::Virtual,
-			_ => Self::System,
-		}
-	}
+/// The fields of the record the other devices of the library get, along with the values written
+/// to the local record
+fn synced_fields(vol: &Volume) -> Vec<((&'static str, rmpv::Value), volume::SetParam)> {
+	vec![
+		sync_db_entry!(vol.name.clone(), volume::name),
+		sync_db_entry!(vol.mount_type.to_string(), volume::mount_type),
+		sync_db_entry!(
+			vol.mount_point.to_string_lossy().to_string(),
+			volume::mount_point
+		),
+		sync_db_entry!(vol.is_mounted, volume::is_mounted),
+		sync_db_entry!(vol.disk_type.to_string(), volume::disk_type),
+		sync_db_entry!(vol.file_system.to_string(), volume::file_system),
+		sync_db_entry!(vol.read_only, volume::read_only),
+		sync_db_nullable_entry!(vol.error_status.clone(), volume::error_status),
+		sync_db_nullable_entry!(vol.serial.clone(), volume::serial),
+		sync_db_entry!(
+			vol.total_bytes_capacity.0.to_string(),
+			volume::total_bytes_capacity
+		),
+		sync_db_entry!(
+			vol.total_bytes_available.0.to_string(),
+			volume::total_bytes_available
+		),
+		sync_db_nullable_entry!(
+			vol.read_speed_mbps.filter(|&v| v != 0).map(|v| v as i64),
+			volume::read_speed_mbps
+		),
+		sync_db_nullable_entry!(
+			vol.write_speed_mbps.filter(|&v| v != 0).map(|v| v as i64),
+			volume::write_speed_mbps
+		),
+	]
 }
 
 /// Configuration options for volume operations
@@ -450,25 +280,3 @@ impl Default for VolumeOptions {
 		}
 	}
 }
-
-impl Serialize for VolumeFingerprint {
-	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-	where
-		S: serde::Serializer,
-	{
-		// Convert to hex string when serializing
-		serializer.serialize_str(&hex::encode(&self.0))
-	}
-}
-
-impl<'de> Deserialize<'de> for VolumeFingerprint {
-	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-	where
-		D: serde::Deserializer<'de>,
-	{
-		let s = String::deserialize(deserializer)?;
-		hex::decode(s)
-			.map(VolumeFingerprint)
-			.map_err(serde::de::Error::custom)
-	}
-}
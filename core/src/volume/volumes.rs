@@ -2,35 +2,90 @@
 use super::{
 	actor::VolumeManagerMessage,
 	error::VolumeError,
-	types::{Volume, VolumeEvent, VolumeFingerprint},
+	fs_snapshot::{self, FileSystemSnapshot, MountedSnapshots},
+	health::HealthStatuses,
+	image::{self, AttachedImages},
+	network::{self, DiscoveredShare, NetworkShare, ShareProtocol, ShareServer, DISCOVERY_TIMEOUT},
+	platform::VolumePlatform,
+	snapshot::VolumeSnapshot,
+	types::{
+		DegradedMode, DeviceCapabilities, MountOptions, OperationPlan, ScanProgress,
+		UnmountedDevice, Volume, VolumeErrorInfo, VolumeEvent, VolumeFingerprint, VolumeHealth,
+		VolumeOperation,
+	},
+	watcher::VolumeWatcher,
+};
+use crate::{
+	library::Library,
+	node::{Publisher, Subscription},
 };
-use crate::library::Library;
 use async_channel as chan;
-use std::sync::Arc;
-use tokio::sync::broadcast;
-use tokio::sync::oneshot;
-use tracing::instrument;
+use futures::Stream;
+use std::{
+	path::{Path, PathBuf},
+	sync::{Arc, OnceLock},
+};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{error, info, instrument};
 
 /// The public interface for volume management
 #[derive(Clone)]
 pub struct Volumes {
 	pub message_tx: chan::Sender<VolumeManagerMessage>,
-	pub event_tx: broadcast::Sender<VolumeEvent>,
+	pub event_tx: Publisher<VolumeEvent>,
+	platform: Arc<dyn VolumePlatform>,
+	/// Set once the actor started the watcher
+	watcher: Arc<OnceLock<Arc<VolumeWatcher>>>,
+	/// The disk images attached through the actor
+	images: Arc<AttachedImages>,
+	/// The file system snapshots mounted for browsing
+	snapshots: Arc<MountedSnapshots>,
+	/// Shared with the actor, which forgets the volumes that went away
+	health_statuses: Arc<HealthStatuses>,
 }
 
 impl Volumes {
 	pub(crate) fn new(
 		message_tx: chan::Sender<VolumeManagerMessage>,
-		event_tx: broadcast::Sender<VolumeEvent>,
+		event_tx: Publisher<VolumeEvent>,
+		platform: Arc<dyn VolumePlatform>,
+		watcher: Arc<OnceLock<Arc<VolumeWatcher>>>,
+		images: Arc<AttachedImages>,
+		health_statuses: Arc<HealthStatuses>,
 	) -> Self {
 		Self {
 			message_tx,
 			event_tx,
+			platform,
+			watcher,
+			images,
+			snapshots: Arc::default(),
+			health_statuses,
+		}
+	}
+
+	/// Stops the volume watcher, returning once it stopped listening to the platform, then
+	/// unmounts the snapshots and detaches the disk images mounted through the manager
+	pub async fn shutdown(&self) {
+		if let Some(watcher) = self.watcher.get() {
+			watcher.stop().await;
 		}
+		fs_snapshot::unmount_all(&*self.platform, &self.snapshots).await;
+		image::detach_all(&*self.platform, &self.images).await;
+	}
+
+	/// What the last scan had to do without, so the UI can explain missing volumes or capacities
+	pub fn degraded_mode(&self) -> DegradedMode {
+		self.platform.degraded_mode()
+	}
+
+	/// What this device can do with its volumes, for the frontend to hide the other actions
+	pub fn capabilities(&self) -> DeviceCapabilities {
+		self.platform.capabilities()
 	}
 
-	/// Creates a new subscription for volume events
-	pub fn subscribe(&self) -> broadcast::Receiver<VolumeEvent> {
+	/// Creates a new subscription for volume events, starting with the volumes currently present
+	pub fn subscribe(&self) -> Subscription<VolumeEvent> {
 		self.event_tx.subscribe()
 	}
 
@@ -50,6 +105,163 @@ impl Volumes {
 		rx.await.map_err(|_| VolumeError::Cancelled)?
 	}
 
+	/// Lists the volumes currently known to this device, without any library information
+	pub async fn list_volumes(&self) -> Result<Vec<Volume>, VolumeError> {
+		let (tx, rx) = oneshot::channel();
+		let msg = VolumeManagerMessage::ListVolumes { ack: tx };
+
+		self.message_tx
+			.send(msg)
+			.await
+			.map_err(|_| VolumeError::Cancelled)?;
+
+		rx.await.map_err(|_| VolumeError::Cancelled)
+	}
+
+	/// Scans the volumes again, yielding each one as soon as it's probed so lists can fill in while
+	/// slow mounts are still answering. Ends with [`ScanProgress::Finished`] or
+	/// [`ScanProgress::Failed`].
+	pub fn scan_with_progress(&self) -> impl Stream<Item = ScanProgress> + Send + 'static {
+		let message_tx = self.message_tx.clone();
+		let platform = Arc::clone(&self.platform);
+
+		async_stream::stream! {
+			let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+			let (tx, rx) = oneshot::channel();
+			let msg = VolumeManagerMessage::ScanVolumes { progress_tx, ack: tx };
+
+			if message_tx.send(msg).await.is_err() {
+				yield ScanProgress::Failed(VolumeErrorInfo::from(&VolumeError::Cancelled));
+				return;
+			}
+
+			// The actor drops the sender along with the scan
+			while let Some(progress) = progress_rx.recv().await {
+				yield progress;
+			}
+
+			yield match rx.await.unwrap_or(Err(VolumeError::Cancelled)) {
+				Ok(()) => ScanProgress::Finished {
+					degraded_mode: platform.degraded_mode(),
+				},
+				Err(e) => ScanProgress::Failed(VolumeErrorInfo::from(&e)),
+			};
+		}
+	}
+
+	/// Captures the volume state, for bug reports
+	pub async fn snapshot(&self) -> Result<VolumeSnapshot, VolumeError> {
+		let (tx, rx) = oneshot::channel();
+		let msg = VolumeManagerMessage::Snapshot { ack: tx };
+
+		self.message_tx
+			.send(msg)
+			.await
+			.map_err(|_| VolumeError::Cancelled)?;
+
+		rx.await.map_err(|_| VolumeError::Cancelled)
+	}
+
+	/// Loads a snapshot in place of the detected volumes
+	pub async fn restore_snapshot(&self, snapshot: VolumeSnapshot) -> Result<(), VolumeError> {
+		let (tx, rx) = oneshot::channel();
+		let msg = VolumeManagerMessage::RestoreSnapshot { snapshot, ack: tx };
+
+		self.message_tx
+			.send(msg)
+			.await
+			.map_err(|_| VolumeError::Cancelled)?;
+
+		rx.await.map_err(|_| VolumeError::Cancelled)?
+	}
+
+	/// Counts the I/O errors a job ran into on the volume holding `path`, a job without any clears
+	/// the count. Returns the volume when it was just quarantined for them.
+	pub async fn report_io_errors(
+		&self,
+		path: PathBuf,
+		errors: u32,
+	) -> Result<Option<Volume>, VolumeError> {
+		let (tx, rx) = oneshot::channel();
+		let msg = VolumeManagerMessage::ReportIoErrors {
+			path,
+			errors,
+			ack: tx,
+		};
+
+		self.message_tx
+			.send(msg)
+			.await
+			.map_err(|_| VolumeError::Cancelled)?;
+
+		rx.await.map_err(|_| VolumeError::Cancelled)
+	}
+
+	/// The innermost mounted volume holding `path` with its fingerprint set, `None` when it's on
+	/// none of them
+	pub async fn volume_at(&self, path: PathBuf) -> Result<Option<Volume>, VolumeError> {
+		let (tx, rx) = oneshot::channel();
+		let msg = VolumeManagerMessage::VolumeAt { path, ack: tx };
+
+		self.message_tx
+			.send(msg)
+			.await
+			.map_err(|_| VolumeError::Cancelled)?;
+
+		rx.await.map_err(|_| VolumeError::Cancelled)
+	}
+
+	/// The mounted volume with this fingerprint
+	async fn get_volume(&self, fingerprint: &VolumeFingerprint) -> Result<Volume, VolumeError> {
+		let (tx, rx) = oneshot::channel();
+		let msg = VolumeManagerMessage::GetVolume {
+			fingerprint: fingerprint.clone(),
+			ack: tx,
+		};
+
+		self.message_tx
+			.send(msg)
+			.await
+			.map_err(|_| VolumeError::Cancelled)?;
+
+		rx.await
+			.map_err(|_| VolumeError::Cancelled)?
+			.ok_or_else(|| VolumeError::NotFound(fingerprint.clone()))
+	}
+
+	/// The snapshots the file system of the volume keeps, oldest first. Only Btrfs and APFS
+	/// volumes have them.
+	#[instrument(skip(self))]
+	pub async fn list_snapshots(
+		&self,
+		fingerprint: VolumeFingerprint,
+	) -> Result<Vec<FileSystemSnapshot>, VolumeError> {
+		let volume = self.get_volume(&fingerprint).await?;
+		fs_snapshot::list(&*self.platform, &volume).await
+	}
+
+	/// Where a snapshot of the volume can be browsed, mounting it read-only when it isn't
+	/// reachable inside the volume. It stays mounted until [`Self::unmount_snapshot`] or shutdown.
+	#[instrument(skip(self))]
+	pub async fn mount_snapshot(
+		&self,
+		fingerprint: VolumeFingerprint,
+		id: String,
+	) -> Result<PathBuf, VolumeError> {
+		let volume = self.get_volume(&fingerprint).await?;
+		fs_snapshot::mount(&*self.platform, &self.snapshots, &volume, &fingerprint, &id).await
+	}
+
+	/// Unmounts a snapshot [`Self::mount_snapshot`] mounted
+	#[instrument(skip(self))]
+	pub async fn unmount_snapshot(
+		&self,
+		fingerprint: VolumeFingerprint,
+		id: String,
+	) -> Result<(), VolumeError> {
+		fs_snapshot::unmount(&*self.platform, &self.snapshots, &fingerprint, &id).await
+	}
+
 	/// Lists volumes for a specific library including system volumes
 	pub async fn list_library_volumes(
 		&self,
@@ -66,6 +278,60 @@ impl Volumes {
 		rx.await.map_err(|_| VolumeError::Cancelled)?
 	}
 
+	/// Measures the read and write speeds of the volume with a temporary file, returning the
+	/// volume with them
+	#[instrument(skip(self))]
+	pub async fn speed_test(
+		&self,
+		fingerprint: VolumeFingerprint,
+		library: Arc<Library>,
+	) -> Result<Volume, VolumeError> {
+		let (tx, rx) = oneshot::channel();
+		let msg = VolumeManagerMessage::SpeedTest {
+			fingerprint,
+			library,
+			ack: tx,
+		};
+
+		self.message_tx
+			.send(msg)
+			.await
+			.map_err(|_| VolumeError::Cancelled)?;
+
+		rx.await.map_err(|_| VolumeError::Cancelled)?
+	}
+
+	/// Reads the health of the drive behind a volume now, asking the user for the privileges to
+	/// read it when it takes them
+	pub async fn health(
+		&self,
+		fingerprint: VolumeFingerprint,
+	) -> Result<VolumeHealth, VolumeError> {
+		let volume = self.get_volume(&fingerprint).await?;
+		self.check_health(fingerprint, &volume, true).await
+	}
+
+	/// Reads the health of the drive behind the volume, publishing it when its status changed.
+	/// With `elevate` the user may be asked for the privileges to read it.
+	pub async fn check_health(
+		&self,
+		fingerprint: VolumeFingerprint,
+		volume: &Volume,
+		elevate: bool,
+	) -> Result<VolumeHealth, VolumeError> {
+		let health = self.platform.health(&volume.mount_point, elevate).await?;
+		if self.health_statuses.changed(&fingerprint, health.status) {
+			info!(%volume.name, ?health, "Drive health changed;");
+			self.event_tx.send(VolumeEvent::VolumeHealthChanged {
+				fingerprint,
+				name: volume.name.clone(),
+				health: health.clone(),
+			});
+		}
+
+		Ok(health)
+	}
+
 	/// Track a volume in a specific library
 	#[instrument(skip(self))]
 	pub async fn track_volume(
@@ -88,6 +354,14 @@ impl Volumes {
 		rx.await.map_err(|_| VolumeError::Cancelled)?
 	}
 
+	/// Brings the records of the volumes in the library up to date, as when it's loaded
+	pub async fn initialize_library(&self, library: Arc<Library>) {
+		let msg = VolumeManagerMessage::InitializeLibrary { library };
+		if self.message_tx.send(msg).await.is_err() {
+			error!("Volume manager actor is gone, dropping library initialization;");
+		}
+	}
+
 	/// Stop tracking a volume
 	#[instrument(skip(self))]
 	pub async fn untrack_volume(
@@ -111,9 +385,53 @@ impl Volumes {
 	}
 
 	pub async fn unmount_volume(&self, fingerprint: VolumeFingerprint) -> Result<(), VolumeError> {
+		self.run_operation(fingerprint, VolumeOperation::Unmount, false)
+			.await
+			.map(|_| ())
+	}
+
+	/// The partitions and disks with a file system nothing mounted, which [`Self::mount_volume`]
+	/// can mount
+	pub async fn unmounted_devices(&self) -> Result<Vec<UnmountedDevice>, VolumeError> {
+		self.platform.unmounted_devices().await
+	}
+
+	/// Mounts one of the [`Self::unmounted_devices`], returning the volume it became
+	#[instrument(skip(self))]
+	pub async fn mount_volume(
+		&self,
+		device: &Path,
+		options: MountOptions,
+	) -> Result<Volume, VolumeError> {
+		let (tx, rx) = oneshot::channel();
+		let msg = VolumeManagerMessage::MountVolume {
+			device: device.to_path_buf(),
+			options,
+			ack: tx,
+		};
+
+		self.message_tx
+			.send(msg)
+			.await
+			.map_err(|_| VolumeError::Cancelled)?;
+
+		rx.await.map_err(|_| VolumeError::Cancelled)?
+	}
+
+	/// Unmounts, ejects or formats a volume, returning the plan that ran. A dry run only checks
+	/// the operation, the plan says what it would do and whether it would succeed.
+	#[instrument(skip(self))]
+	pub async fn run_operation(
+		&self,
+		fingerprint: VolumeFingerprint,
+		operation: VolumeOperation,
+		dry_run: bool,
+	) -> Result<OperationPlan, VolumeError> {
 		let (tx, rx) = oneshot::channel();
-		let msg = VolumeManagerMessage::UnmountVolume {
+		let msg = VolumeManagerMessage::RunOperation {
 			fingerprint,
+			operation,
+			dry_run,
 			ack: tx,
 		};
 
@@ -122,8 +440,70 @@ impl Volumes {
 			.await
 			.map_err(|_| VolumeError::Cancelled)?;
 
-		let _ = rx.await.map_err(|_| VolumeError::Cancelled)?;
-		Ok(())
+		rx.await.map_err(|_| VolumeError::Cancelled)?
+	}
+
+	/// Mounts the SMB, NFS or WebDAV share at `url`, returning the volume it became. Credentials
+	/// are read from the keyring, as a JSON `{ username, password, domain }` stored under
+	/// [`network_share_key`](crate::node::network_share_key), the share is mounted as a guest
+	/// without them. It's mounted again when it drops, until it's unmounted through here.
+	#[instrument(skip(self))]
+	pub async fn mount_network_share(
+		&self,
+		url: &str,
+		mount_point: Option<PathBuf>,
+	) -> Result<Volume, VolumeError> {
+		let (tx, rx) = oneshot::channel();
+		let msg = VolumeManagerMessage::MountNetworkShare {
+			share: NetworkShare::parse(url)?,
+			mount_point,
+			ack: tx,
+		};
+
+		self.message_tx
+			.send(msg)
+			.await
+			.map_err(|_| VolumeError::Cancelled)?;
+
+		rx.await.map_err(|_| VolumeError::Cancelled)?
+	}
+
+	/// Attaches an ISO, DMG or IMG file as a volume, returning the volume it became. ISO files are
+	/// always attached read-only, the images are detached on shutdown.
+	#[instrument(skip(self))]
+	pub async fn attach_disk_image(
+		&self,
+		image: PathBuf,
+		read_only: bool,
+	) -> Result<Volume, VolumeError> {
+		let (tx, rx) = oneshot::channel();
+		let msg = VolumeManagerMessage::AttachDiskImage {
+			image,
+			read_only,
+			ack: tx,
+		};
+
+		self.message_tx
+			.send(msg)
+			.await
+			.map_err(|_| VolumeError::Cancelled)?;
+
+		rx.await.map_err(|_| VolumeError::Cancelled)?
+	}
+
+	/// The file servers on the local network that answer within [`DISCOVERY_TIMEOUT`]
+	pub async fn discover_share_servers(&self) -> Vec<ShareServer> {
+		network::discover_servers(DISCOVERY_TIMEOUT).await
+	}
+
+	/// The shares one of the servers offers over `protocol`, as a guest
+	#[instrument(skip(self))]
+	pub async fn list_network_shares(
+		&self,
+		host: &str,
+		protocol: ShareProtocol,
+	) -> Result<Vec<DiscoveredShare>, VolumeError> {
+		network::list_shares(host, protocol).await
 	}
 
 	// Other public methods...
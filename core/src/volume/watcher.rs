@@ -1,180 +1,737 @@
-use crate::volume::types::VolumeFingerprint;
+use crate::{
+	node::{CoreConfig, Publisher},
+	volume::types::VolumeFingerprint,
+};
 
 use super::error::VolumeError;
-use super::types::VolumeEvent;
-use super::VolumeManagerActor;
+use super::retry::{retry, RetryPolicy};
+use super::types::{Volume, VolumeEvent};
+use super::{state::VolumeRegistry, VolumePlatform};
+use chrono::{DateTime, Utc};
+use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use sd_core_sync::DevicePubId;
-use std::{collections::HashSet, path::PathBuf, sync::Arc, time::Duration};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::{
+	collections::{BTreeSet, HashSet},
+	path::PathBuf,
+	sync::Arc,
+};
 use tokio::sync::Mutex;
 use tokio::{
-	sync::{broadcast, mpsc, RwLock},
+	sync::{mpsc, watch, RwLock},
+	task::JoinHandle,
 	time::{sleep, Instant},
 };
-use tracing::{debug, error,warn};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+/// Where the watcher learns that the volumes may have changed from. The platform lists the ones it
+/// has with [`VolumePlatform::watcher_backends`], polling works everywhere and comes after them.
+/// `watcher.backend` in the core config picks one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum WatcherBackend {
+	/// Changes of `/proc/self/mountinfo`, which the kernel signals on every mount and unmount
+	MountinfoPoll,
+	/// The block device uevents the kernel sends udev over netlink, unavailable in containers
+	Udev,
+	/// inotify on the directories the volumes are usually mounted in
+	Inotify,
+	/// Whatever the platform listens to, for the ones with a single way to do it
+	Native,
+	/// Checks every `watcher.poll_interval_ms`, for the platforms without events or when none of
+	/// their backends starts
+	Poll,
+}
 
-const DEBOUNCE_MS: u64 = 100;
+impl WatcherBackend {
+	pub const fn as_str(self) -> &'static str {
+		match self {
+			Self::MountinfoPoll => "mountinfo_poll",
+			Self::Udev => "udev",
+			Self::Inotify => "inotify",
+			Self::Native => "native",
+			Self::Poll => "poll",
+		}
+	}
+}
+
+/// What the watcher has been up to, for snapshots and diagnostics
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct WatcherStatus {
+	pub running: bool,
+	/// The backend the events come from, none until one started
+	pub backend: Option<WatcherBackend>,
+	/// Checks run since the watcher started, debounced ones excluded
+	pub checks: u64,
+	pub last_check: Option<DateTime<Utc>>,
+	/// Why the last check failed, cleared by the next successful one
+	pub last_error: Option<String>,
+}
 
 #[derive(Debug)]
 pub struct VolumeWatcher {
-	event_tx: broadcast::Sender<VolumeEvent>,
-	ignored_paths: Arc<RwLock<HashSet<PathBuf>>>,
-	running: Arc<RwLock<bool>>,
+	event_tx: Publisher<VolumeEvent>,
+	config: watch::Receiver<CoreConfig>,
+	platform: Arc<dyn VolumePlatform>,
+	cancel: CancellationToken,
+	/// The loop handling the checks, until the watcher is stopped
+	task: Mutex<Option<JoinHandle<()>>>,
+	status: Arc<RwLock<WatcherStatus>>,
 }
 
 impl VolumeWatcher {
-	pub fn new(event_tx: broadcast::Sender<VolumeEvent>) -> Self {
+	pub fn new(
+		event_tx: Publisher<VolumeEvent>,
+		config: watch::Receiver<CoreConfig>,
+		platform: Arc<dyn VolumePlatform>,
+	) -> Self {
 		Self {
 			event_tx,
-			ignored_paths: Arc::new(RwLock::new(HashSet::new())),
-			running: Arc::new(RwLock::new(true)),
+			config,
+			platform,
+			cancel: CancellationToken::new(),
+			task: Mutex::default(),
+			status: Arc::default(),
 		}
 	}
 
+	/// Reports the status to `status`, so it can be read once the watcher is spawned
+	pub fn with_status(mut self, status: Arc<RwLock<WatcherStatus>>) -> Self {
+		self.status = status;
+		self
+	}
+
 	pub async fn start(
 		&self,
 		device_id: DevicePubId,
-		actor: Arc<Mutex<VolumeManagerActor>>,
+		registry: Arc<RwLock<VolumeRegistry>>,
 	) -> Result<(), VolumeError> {
 		debug!("Starting volume watcher");
 
-		let (check_tx, mut check_rx) = mpsc::channel(1);
-
-		// Start OS-specific watcher
-		self.spawn_platform_watcher(check_tx.clone()).await?;
+		let mut config = self.config.clone();
+		let (mut preferred, mut ignored_paths, extra) = {
+			let config = config.borrow_and_update();
+			(
+				config.watcher.backend,
+				config.volumes.ignored_paths.clone(),
+				config.watcher.extra_paths.clone(),
+			)
+		};
+		let mut order = fallback_order(preferred, &available_backends(&*self.platform));
+		let (mut backend, mut check_rx, mut backend_task) =
+			start_backend(&*self.platform, &config, &order).await?;
+
+		let (extra_tx, mut extra_rx) = mpsc::channel(1);
+		let mut extra_paths = ExtraPaths::new(extra_tx);
+		extra_paths.update(&extra);
 
 		// Handle volume checks when triggered by OS events
 		let event_tx = self.event_tx.clone();
-		let running = self.running.clone();
+		let platform = Arc::clone(&self.platform);
+		let cancel = self.cancel.clone();
+		let status = self.status.clone();
+		{
+			let mut status = status.write().await;
+			status.running = true;
+			status.backend = Some(backend);
+		}
 
-		tokio::spawn(async move {
+		let handle = tokio::spawn(async move {
 			let mut last_check = Instant::now();
 
-			while *running.read().await {
-				// Wait for check trigger from OS watcher
-				if check_rx.recv().await.is_some() {
-					// Debounce checks
-					if last_check.elapsed() < Duration::from_millis(DEBOUNCE_MS) {
-						continue;
-					}
-					last_check = Instant::now();
-
-					let discovered_volumes = match super::os::get_volumes().await {
-						Ok(volumes) => volumes,
-						Err(e) => {
-							error!("Failed to get volumes: {}", e);
-							// Return empty volumes to avoid sending events
-							vec![]
+			loop {
+				// Wait for check trigger from OS watcher, or for the config to change
+				let triggered = tokio::select! {
+					() = cancel.cancelled() => break,
+					triggered = check_rx.recv() => triggered.is_some(),
+					Some(()) = extra_rx.recv() => true,
+					Ok(()) = config.changed() => {
+						let (configured, ignored, extra) = {
+							let config = config.borrow_and_update();
+							(
+								config.watcher.backend,
+								config.volumes.ignored_paths.clone(),
+								config.watcher.extra_paths.clone(),
+							)
+						};
+						extra_paths.update(&extra);
+						if configured != preferred {
+							preferred = configured;
+							order = fallback_order(preferred, &available_backends(&*platform));
+							if order.first() != Some(&backend) {
+								switch_backend(
+									&*platform,
+									&config,
+									&order,
+									&mut backend,
+									&mut check_rx,
+									&mut backend_task,
+									&status,
+								)
+								.await;
+							}
 						}
-					};
-
-					let actor = actor.lock().await;
-
-					// Find new volumes
-					for volume in &discovered_volumes {
-						let fingerprint = VolumeFingerprint::new(&device_id, volume);
-
-						let volume_exists = actor.volume_exists(fingerprint.clone()).await;
-						// if the volume doesn't exist in the actor state, we need to send an event
-						if !volume_exists {
-							let _ = event_tx.send(VolumeEvent::VolumeAdded(volume.clone()));
+						// The volumes ignored or no longer ignored are removed or added right away
+						if ignored == ignored_paths {
+							continue;
 						}
+						ignored_paths = ignored;
+						true
 					}
+				};
+
+				if !triggered {
+					// The backend stopped, the ones after it in the order are tried before giving up
+					let rest = order
+						.iter()
+						.skip_while(|b| **b != backend)
+						.skip(1)
+						.copied()
+						.collect::<Vec<_>>();
+					if rest.is_empty()
+						|| !switch_backend(
+							&*platform,
+							&config,
+							&rest,
+							&mut backend,
+							&mut check_rx,
+							&mut backend_task,
+							&status,
+						)
+						.await
+					{
+						error!(
+							?backend,
+							"The volume watcher backend stopped without a fallback;"
+						);
+						break;
+					}
+					continue;
+				}
 
-					// Find removed volumes and send an event
-					for volume in &actor.get_volumes().await {
-						let fingerprint = VolumeFingerprint::new(&device_id, volume);
-						if !discovered_volumes
-							.iter()
-							.any(|v| VolumeFingerprint::new(&device_id, v) == fingerprint)
-						{
-							let _ = event_tx.send(VolumeEvent::VolumeRemoved(volume.clone()));
-						}
+				// Debounce checks
+				let debounce = config.borrow().watcher.debounce();
+				if last_check.elapsed() < debounce {
+					continue;
+				}
+				last_check = Instant::now();
+
+				let res = platform.get_volumes().await;
+				{
+					let mut status = status.write().await;
+					status.checks += 1;
+					status.last_check = Some(Utc::now());
+					status.last_error = res.as_ref().err().map(ToString::to_string);
+				}
+
+				let discovered_volumes = match res {
+					Ok(volumes) => {
+						let config = config.borrow();
+						volumes
+							.into_iter()
+							.filter(|volume| !config.volumes.is_ignored(&volume.mount_point))
+							.collect::<Vec<_>>()
 					}
+					Err(e) => {
+						error!("Failed to get volumes: {}", e);
+						// Diffing against nothing would report every volume as removed
+						continue;
+					}
+				};
+
+				// Copied out, the registry stays free for the actor while diffing
+				let known_volumes = registry
+					.read()
+					.await
+					.volumes()
+					.map(|(_, volume)| volume.clone())
+					.collect::<Vec<_>>();
+
+				for event in diff_volumes(&device_id, &known_volumes, &discovered_volumes) {
+					event_tx.send(event);
 				}
 			}
+
+			// Dropping the receiver stops the backend, the blocking ones included
+			drop(check_rx);
+			join_backend(backend, backend_task).await;
+			let mut status = status.write().await;
+			status.running = false;
+			status.backend = None;
 		});
+		*self.task.lock().await = Some(handle);
 
 		Ok(())
 	}
 
-	async fn spawn_platform_watcher(&self, check_tx: mpsc::Sender<()>) -> Result<(), VolumeError> {
-		let running = self.running.clone();
+	/// Stops the watcher, returning once the loop handling the checks and the backend are over
+	pub async fn stop(&self) {
+		debug!("Stopping volume watcher");
+		self.cancel.cancel();
+		let Some(handle) = self.task.lock().await.take() else {
+			return;
+		};
+		if let Err(e) = handle.await {
+			error!(?e, "The volume watcher loop panicked;");
+		}
+	}
+}
 
-		#[cfg(target_os = "linux")]
-		{
-			use inotify::{Inotify, WatchMask};
-
-			let mut inotify = Inotify::init().map_err(|e| {
-				VolumeError::Platform(format!("Failed to initialize inotify: {}", e))
-			})?;
-
-			// Watch mount points and device changes
-			for path in ["/dev", "/media", "/mnt", "/run/media"] {
-				if let Err(e) = inotify.add_watch(
-					path,
-					WatchMask::CREATE | WatchMask::DELETE | WatchMask::MODIFY,
-				) {
-					warn!("Failed to watch path {}: {}", path, e);
-				}
-			}
+/// Watches the `watcher.extra_paths` for entries coming and going, alongside the backend
+struct ExtraPaths {
+	/// None when it couldn't be started, the backend still sees the mounts made there
+	watcher: Option<RecommendedWatcher>,
+	watched: BTreeSet<PathBuf>,
+}
 
-			let check_tx = check_tx.clone();
-			tokio::spawn(async move {
-				let mut buffer = [0; 4096];
-				while *running.read().await {
-					match inotify.read_events_blocking(&mut buffer) {
-						Ok(_) => {
-							if let Err(e) = check_tx.send(()).await {
-								error!("Failed to trigger volume check: {}", e);
-							}
-						}
-						Err(e) => error!("Inotify error: {}", e),
-					}
+impl ExtraPaths {
+	fn new(check_tx: mpsc::Sender<()>) -> Self {
+		let watcher = RecommendedWatcher::new(
+			move |res: notify::Result<notify::Event>| {
+				if res.is_ok_and(|event| event.kind.is_create() || event.kind.is_remove()) {
+					// Full when a check is already pending
+					let _ = check_tx.try_send(());
 				}
-			});
+			},
+			Config::default(),
+		)
+		.map_err(|e| warn!(?e, "Failed to start watching the extra volume paths;"))
+		.ok();
+
+		Self {
+			watcher,
+			watched: BTreeSet::new(),
 		}
+	}
 
+	/// Watches the paths that aren't yet and stops watching the ones no longer listed. The ones
+	/// that failed are tried again on the next config change.
+	fn update(&mut self, paths: &BTreeSet<PathBuf>) {
+		let Some(watcher) = &mut self.watcher else {
+			return;
+		};
+
+		let mut watched = BTreeSet::new();
+		for path in paths {
+			if self.watched.contains(path) {
+				watched.insert(path.clone());
+				continue;
+			}
+			match watcher.watch(path, RecursiveMode::NonRecursive) {
+				Ok(()) => {
+					watched.insert(path.clone());
+				}
+				Err(e) => warn!(?e, path = %path.display(), "Failed to watch a volume path;"),
+			}
+		}
+		for path in self.watched.difference(&watched) {
+			if let Err(e) = watcher.unwatch(path) {
+				warn!(?e, path = %path.display(), "Failed to stop watching a volume path;");
+			}
+		}
 
+		self.watched = watched;
+	}
+}
 
+/// The backends to try, in order: the configured one when available, then the rest of the
+/// available ones
+pub(super) fn fallback_order(
+	preferred: Option<WatcherBackend>,
+	available: &[WatcherBackend],
+) -> Vec<WatcherBackend> {
+	let preferred = preferred.filter(|preferred| {
+		let supported = available.contains(preferred);
+		if !supported {
+			warn!(
+				?preferred,
+				?available,
+				"The configured volume watcher backend isn't available here;"
+			);
+		}
+		supported
+	});
 
+	preferred
+		.into_iter()
+		.chain(available.iter().copied().filter(|b| Some(*b) != preferred))
+		.collect()
+}
 
-		Ok(())
+/// The platform's backends, then polling as it works everywhere
+fn available_backends(platform: &dyn VolumePlatform) -> Vec<WatcherBackend> {
+	let mut backends = platform.watcher_backends();
+	if !backends.contains(&WatcherBackend::Poll) {
+		backends.push(WatcherBackend::Poll);
 	}
+	backends
+}
 
-	pub async fn stop(&self) {
-		debug!("Stopping volume watcher");
-		*self.running.write().await = false;
+/// Starts the first backend of `order` that works, returning it with the receiver of its checks
+async fn start_backend(
+	platform: &dyn VolumePlatform,
+	config: &watch::Receiver<CoreConfig>,
+	order: &[WatcherBackend],
+) -> Result<(WatcherBackend, mpsc::Receiver<()>, Option<JoinHandle<()>>), VolumeError> {
+	let mut last_error = None;
+	for &backend in order {
+		let (check_tx, check_rx) = mpsc::channel(1);
+
+		if backend == WatcherBackend::Poll {
+			let task = poll(config.clone(), check_tx);
+			debug!(?backend, "Started the volume watcher backend;");
+			return Ok((backend, check_rx, Some(task)));
+		}
+
+		// The inotify instances of the last watcher may not be released yet
+		match retry(
+			RetryPolicy::WATCHER,
+			"watch",
+			VolumeError::is_retriable,
+			|| platform.watch(backend, check_tx.clone()),
+		)
+		.await
+		{
+			Ok(task) => {
+				debug!(?backend, "Started the volume watcher backend;");
+				return Ok((backend, check_rx, task));
+			}
+			Err(e) => {
+				warn!(?e, ?backend, "Failed to start the volume watcher backend;");
+				last_error = Some(e);
+			}
+		}
 	}
 
-	pub async fn ignore_path(&self, path: PathBuf) {
-		self.ignored_paths.write().await.insert(path);
+	Err(last_error.unwrap_or(VolumeError::UnsupportedPlatform {
+		operation: "watching",
+	}))
+}
+
+/// Starts the first backend of `order` that works in place of `backend`, which keeps running if
+/// none does. Returns whether it was replaced.
+async fn switch_backend(
+	platform: &dyn VolumePlatform,
+	config: &watch::Receiver<CoreConfig>,
+	order: &[WatcherBackend],
+	backend: &mut WatcherBackend,
+	check_rx: &mut mpsc::Receiver<()>,
+	task: &mut Option<JoinHandle<()>>,
+	status: &RwLock<WatcherStatus>,
+) -> bool {
+	match start_backend(platform, config, order).await {
+		Ok((started, started_rx, started_task)) => {
+			info!(from = ?backend, to = ?started, "Switched the volume watcher backend;");
+			let last = std::mem::replace(backend, started);
+			let last_task = std::mem::replace(task, started_task);
+			// Dropping the receiver stops the last backend
+			*check_rx = started_rx;
+			join_backend(last, last_task).await;
+			status.write().await.backend = Some(started);
+			true
+		}
+		Err(e) => {
+			warn!(?e, from = ?backend, "Failed to switch the volume watcher backend;");
+			false
+		}
 	}
+}
 
-	pub async fn unignore_path(&self, path: &PathBuf) {
-		self.ignored_paths.write().await.remove(path);
+/// Waits for a backend to be over once its receiver is dropped, the blocking ones wake up to
+/// notice it
+async fn join_backend(backend: WatcherBackend, task: Option<JoinHandle<()>>) {
+	let Some(task) = task else {
+		return;
+	};
+	if let Err(e) = task.await {
+		error!(?e, ?backend, "The volume watcher backend panicked;");
 	}
 }
 
-// #[cfg(test)]
-// mod tests {
-// 	use super::*;
-// 	use tokio::time::timeout;
+/// Requests a check every `watcher.poll_interval_ms` until `check_tx` is dropped, following
+/// changes of the interval
+fn poll(config: watch::Receiver<CoreConfig>, check_tx: mpsc::Sender<()>) -> JoinHandle<()> {
+	tokio::spawn(async move {
+		loop {
+			let interval = config.borrow().watcher.poll_interval();
+			tokio::select! {
+				() = sleep(interval) => {}
+				// The watcher is gone, or switched to another backend
+				() = check_tx.closed() => break,
+			}
 
-// 	#[tokio::test]
-// 	async fn test_watcher() {
-// 		let (tx, mut rx) = broadcast::channel(16);
-// 		let watcher = VolumeWatcher::new(tx);
+			// A check is already pending if the channel is full
+			if let Err(mpsc::error::TrySendError::Closed(())) = check_tx.try_send(()) {
+				break;
+			}
+		}
+	})
+}
 
-// 		watcher.start().await.expect("Failed to start watcher");
+/// Compares the volumes reported by the platform with the ones we know of, returning
+/// `VolumeAdded` events for the new ones, `VolumeRemoved` events for the missing ones, then
+/// `VolumeUpdated` events for the ones that changed, each followed by `VolumeRemounted` and
+/// `VolumeSpaceChanged` for what changed
+pub fn diff_volumes(
+	device_id: &DevicePubId,
+	known: &[Volume],
+	discovered: &[Volume],
+) -> Vec<VolumeEvent> {
+	let device_id = device_id.to_db();
+	let fingerprinted = |volumes: &[Volume]| {
+		volumes
+			.iter()
+			.map(|volume| (VolumeFingerprint::new(&device_id, volume), volume))
+			.collect::<Vec<_>>()
+	};
+
+	let known = fingerprinted(known);
+	let discovered = fingerprinted(discovered);
+
+	let known_fingerprints = known
+		.iter()
+		.map(|(fingerprint, _)| fingerprint)
+		.collect::<HashSet<_>>();
+	let discovered_fingerprints = discovered
+		.iter()
+		.map(|(fingerprint, _)| fingerprint)
+		.collect::<HashSet<_>>();
+
+	let added = discovered
+		.iter()
+		.filter(|(fingerprint, _)| !known_fingerprints.contains(fingerprint))
+		.map(|(_, volume)| VolumeEvent::VolumeAdded((*volume).clone()));
+
+	let removed = known
+		.iter()
+		.filter(|(fingerprint, _)| !discovered_fingerprints.contains(fingerprint))
+		.map(|(_, volume)| VolumeEvent::VolumeRemoved((*volume).clone()));
+
+	let updated = discovered.iter().flat_map(|(fingerprint, volume)| {
+		let Some((_, old)) = known.iter().find(|(id, _)| id == fingerprint) else {
+			return vec![];
+		};
+		let new = refreshed(old, volume);
+		if new == **old {
+			return vec![];
+		}
 
-// 		// Wait for potential volume events
-// 		let result = timeout(Duration::from_secs(2), rx.recv()).await;
+		let remounted = (new.read_only != old.read_only).then(|| VolumeEvent::VolumeRemounted {
+			fingerprint: fingerprint.clone(),
+			read_only: new.read_only,
+		});
+		let space_changed = (new.total_bytes_available != old.total_bytes_available).then(|| {
+			VolumeEvent::VolumeSpaceChanged {
+				fingerprint: fingerprint.clone(),
+				total_bytes_available: new.total_bytes_available,
+			}
+		});
+
+		[VolumeEvent::VolumeUpdated {
+			old: (*old).clone(),
+			new,
+		}]
+		.into_iter()
+		.chain(remounted)
+		.chain(space_changed)
+		.collect()
+	});
+
+	added.chain(removed).chain(updated).collect()
+}
+
+/// Whether the known volume already has what an update reports, the monitor and the watcher
+/// can both report the same change
+pub(super) fn is_up_to_date(known: &Volume, new: &Volume) -> bool {
+	refreshed(known, new) == *known
+}
+
+/// The known volume with what the platform now reports about it, keeping what the platform
+/// doesn't know: the library records, measured speeds and errors
+fn refreshed(known: &Volume, discovered: &Volume) -> Volume {
+	let mut volume = Volume::merge_with_db(discovered, known);
+	volume.fingerprint = known.fingerprint.clone();
+	volume.error_status = known.error_status.clone();
+	volume.is_degraded = known.is_degraded;
+	volume
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::volume::{ByteSize, MockStep, MockVolumeProvider, MountType};
+	use std::time::Duration;
+	use tokio::time::timeout;
+	use uuid::Uuid;
+
+	#[tokio::test]
+	async fn reports_volumes_plugged_on_the_platform() {
+		let device_id = DevicePubId::from(Uuid::now_v7());
+		let platform = Arc::new(MockVolumeProvider::new([]).with_script([MockStep::Plug(
+			MockVolumeProvider::volume("USB", "/media/usb", MountType::External),
+		)]));
+
+		let mut core_config = CoreConfig::default();
+		core_config.watcher.debounce_ms = 0;
+		let (_config_tx, config) = watch::channel(core_config);
+		let registry = Arc::new(RwLock::new(VolumeRegistry::new(device_id.clone())));
+
+		let event_tx = Publisher::detached();
+		let mut event_rx = event_tx.subscribe();
+		let watcher = VolumeWatcher::new(event_tx, config, platform.clone());
+		watcher.start(device_id, registry).await.unwrap();
+
+		assert!(platform.advance());
+
+		let event = timeout(Duration::from_secs(5), event_rx.recv())
+			.await
+			.expect("no volume event")
+			.expect("the publisher is gone");
+		assert!(matches!(event, VolumeEvent::VolumeAdded(volume) if volume.name == "USB"));
+
+		watcher.stop().await;
+	}
+
+	#[tokio::test]
+	async fn polls_when_no_backend_of_the_platform_starts() {
+		let device_id = DevicePubId::from(Uuid::now_v7());
+		let platform = Arc::new(
+			MockVolumeProvider::new([])
+				.with_watcher_backends([WatcherBackend::Native], [WatcherBackend::Native])
+				.with_script([MockStep::Plug(MockVolumeProvider::volume(
+					"USB",
+					"/media/usb",
+					MountType::External,
+				))]),
+		);
+
+		let mut core_config = CoreConfig::default();
+		core_config.watcher.debounce_ms = 0;
+		core_config.watcher.poll_interval_ms = 10;
+		let (_config_tx, config) = watch::channel(core_config);
+		let registry = Arc::new(RwLock::new(VolumeRegistry::new(device_id.clone())));
+
+		let event_tx = Publisher::detached();
+		let mut event_rx = event_tx.subscribe();
+		let status = Arc::<RwLock<WatcherStatus>>::default();
+		let watcher =
+			VolumeWatcher::new(event_tx, config, platform.clone()).with_status(Arc::clone(&status));
+		watcher.start(device_id, registry).await.unwrap();
+		assert_eq!(status.read().await.backend, Some(WatcherBackend::Poll));
+
+		// Nothing tells the watcher, the next poll finds the volume
+		assert!(platform.advance());
+		let event = timeout(Duration::from_secs(5), event_rx.recv())
+			.await
+			.expect("no volume event")
+			.expect("the publisher is gone");
+		assert!(matches!(event, VolumeEvent::VolumeAdded(volume) if volume.name == "USB"));
+
+		// Returns once the loop is over, waiting on the next poll or not
+		timeout(Duration::from_secs(5), watcher.stop())
+			.await
+			.expect("the watcher didn't stop");
+		assert!(!status.read().await.running);
+	}
 
-// 		// Cleanup
-// 		watcher.stop().await;
+	#[test]
+	fn reports_remounts_and_space_changes_of_known_volumes() {
+		let device_id = DevicePubId::from(Uuid::now_v7());
+		let mut known = MockVolumeProvider::volume("USB", "/media/usb", MountType::External);
+		known.pub_id = Some(vec![1]);
+		known.read_speed_mbps = Some(100);
+
+		let mut discovered = known.clone();
+		discovered.pub_id = None;
+		discovered.read_speed_mbps = None;
+		assert!(diff_volumes(&device_id, &[known.clone()], &[discovered.clone()]).is_empty());
+
+		discovered.read_only = true;
+		discovered.total_bytes_available = ByteSize(known.total_bytes_available.0 - 1);
+		let events = diff_volumes(&device_id, &[known.clone()], &[discovered]);
+		assert_eq!(events.len(), 3);
+		assert!(matches!(
+			&events[0],
+			VolumeEvent::VolumeUpdated { new, .. }
+				if new.read_only && new.pub_id == known.pub_id && new.read_speed_mbps == Some(100)
+		));
+		assert!(matches!(
+			events[1],
+			VolumeEvent::VolumeRemounted {
+				read_only: true,
+				..
+			}
+		));
+		assert!(matches!(
+			events[2],
+			VolumeEvent::VolumeSpaceChanged { total_bytes_available, .. }
+				if total_bytes_available == ByteSize(known.total_bytes_available.0 - 1)
+		));
+	}
+
+	#[test]
+	fn the_configured_backend_goes_first_when_available() {
+		use WatcherBackend::*;
+
+		let available = [MountinfoPoll, Udev, Inotify];
+		assert_eq!(fallback_order(None, &available), available);
+		assert_eq!(
+			fallback_order(Some(Inotify), &available),
+			[Inotify, MountinfoPoll, Udev]
+		);
+		assert_eq!(fallback_order(Some(Native), &available), available);
+	}
+
+	#[tokio::test]
+	async fn falls_back_and_switches_backends_at_runtime() {
+		let device_id = DevicePubId::from(Uuid::now_v7());
+		let platform = Arc::new(MockVolumeProvider::new([]).with_watcher_backends(
+			[
+				WatcherBackend::Udev,
+				WatcherBackend::Inotify,
+				WatcherBackend::MountinfoPoll,
+			],
+			[WatcherBackend::Udev],
+		));
+
+		let mut core_config = CoreConfig::default();
+		core_config.watcher.backend = Some(WatcherBackend::Udev);
+		let (config_tx, config) = watch::channel(core_config);
+		let registry = Arc::new(RwLock::new(VolumeRegistry::new(device_id.clone())));
+
+		let status = Arc::<RwLock<WatcherStatus>>::default();
+		let watcher = VolumeWatcher::new(Publisher::detached(), config, platform.clone())
+			.with_status(Arc::clone(&status));
+		watcher.start(device_id, registry).await.unwrap();
+		assert_eq!(status.read().await.backend, Some(WatcherBackend::Inotify));
+
+		config_tx
+			.send_modify(|config| config.watcher.backend = Some(WatcherBackend::MountinfoPoll));
+		timeout(Duration::from_secs(5), async {
+			while status.read().await.backend != Some(WatcherBackend::MountinfoPoll) {
+				tokio::task::yield_now().await;
+			}
+		})
+		.await
+		.expect("the backend wasn't switched");
 
-// 		if let Ok(Ok(event)) = result {
-// 			println!("Received volume event: {:?}", event);
-// 		}
-// 	}
-// }
+		watcher.stop().await;
+		assert_eq!(status.read().await.backend, None);
+	}
+
+	#[test]
+	fn updates_already_applied_are_up_to_date() {
+		let mut known = MockVolumeProvider::volume("USB", "/media/usb", MountType::External);
+		known.read_speed_mbps = Some(120);
+
+		let mut new = MockVolumeProvider::volume("USB", "/media/usb", MountType::External);
+		new.total_bytes_available -= 1;
+		assert!(!is_up_to_date(&known, &new));
+
+		// Reported again by the other side, without what only the registry knows
+		known.total_bytes_available = new.total_bytes_available;
+		assert!(is_up_to_date(&known, &new));
+	}
+}
@@ -245,6 +245,14 @@ impl<Id: ActorId> ActorsCollection<Id> {
 		}
 	}
 
+	pub async fn is_running(&self, identifier: Id) -> bool {
+		self.actors_map
+			.read()
+			.await
+			.get(&identifier)
+			.is_some_and(|actor| actor.is_running.load(Ordering::Relaxed))
+	}
+
 	pub async fn get_state(&self) -> Vec<(String, bool)> {
 		self.actors_map
 			.read()
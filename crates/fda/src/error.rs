@@ -1,4 +1,7 @@
 #[derive(Debug, thiserror::Error)]
-pub enum Error {}
+pub enum Error {
+	#[error("failed to open the system settings pane: {0}")]
+	OpenSettings(#[from] std::io::Error),
+}
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -28,19 +28,167 @@ pub mod error;
 
 use error::Result;
 
+/// The TCC (Transparency, Consent, and Control) permissions that affect indexing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Permission {
+	/// Access to every protected folder on the system, e.g. `~/Library/Mail`
+	FullDisk,
+	/// Access to volumes mounted under `/Volumes`
+	RemovableVolumes,
+}
+
+impl Permission {
+	pub const ALL: [Self; 2] = [Self::FullDisk, Self::RemovableVolumes];
+
+	/// URL of the Privacy & Security pane for this permission.
+	#[must_use]
+	pub const fn settings_url(self) -> &'static str {
+		match self {
+			Self::FullDisk => {
+				"x-apple.systempreferences:com.apple.preference.security?Privacy_AllFiles"
+			}
+			Self::RemovableVolumes => {
+				"x-apple.systempreferences:com.apple.preference.security?Privacy_RemovableVolume"
+			}
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PermissionStatus {
+	Granted,
+	Denied,
+	/// There was nothing to probe, e.g. no removable volume is plugged in
+	Unknown,
+	/// The platform doesn't gate this permission
+	NotRequired,
+}
+
 pub struct DiskAccess;
 
 impl DiskAccess {
+	/// Checks the current status of a permission.
+	///
+	/// macOS has no public API to query TCC, so we probe a path that is only
+	/// readable with the permission granted.
+	#[must_use]
+	pub fn status(permission: Permission) -> PermissionStatus {
+		#[cfg(target_os = "macos")]
+		{
+			macos::status(permission)
+		}
+
+		#[cfg(not(target_os = "macos"))]
+		{
+			let _ = permission;
+			PermissionStatus::NotRequired
+		}
+	}
+
+	/// Opens the settings pane where the user can grant the permission.
+	/// This function is a no-op on non-MacOS systems.
+	pub fn open_settings(permission: Permission) -> Result<()> {
+		#[cfg(target_os = "macos")]
+		{
+			std::process::Command::new("open")
+				.arg(permission.settings_url())
+				.spawn()?;
+		}
+
+		#[cfg(not(target_os = "macos"))]
+		let _ = permission;
+
+		Ok(())
+	}
+
+	/// Prompts for full disk access if we don't already have it.
 	/// This function is a no-op on non-MacOS systems.
-	#[allow(clippy::missing_const_for_fn)]
 	pub fn request_fda() -> Result<()> {
+		if Self::status(Permission::FullDisk) == PermissionStatus::Denied {
+			Self::open_settings(Permission::FullDisk)?;
+		}
+
 		Ok(())
 	}
 }
 
+#[cfg(target_os = "macos")]
+mod macos {
+	use super::{Permission, PermissionStatus};
+
+	use std::{env, fs, io::ErrorKind, path::PathBuf};
+
+	/// Only readable by processes with full disk access
+	const FDA_PROBES: [&str; 2] = [
+		"Library/Application Support/com.apple.TCC/TCC.db",
+		"Library/Safari/Bookmarks.plist",
+	];
+
+	pub fn status(permission: Permission) -> PermissionStatus {
+		match permission {
+			Permission::FullDisk => full_disk_status(),
+			Permission::RemovableVolumes => removable_volumes_status(),
+		}
+	}
+
+	fn full_disk_status() -> PermissionStatus {
+		let Some(home) = env::var_os("HOME").map(PathBuf::from) else {
+			return PermissionStatus::Unknown;
+		};
+
+		let mut probed = false;
+		for probe in FDA_PROBES {
+			match fs::File::open(home.join(probe)) {
+				Ok(_) => return PermissionStatus::Granted,
+				Err(e) if e.kind() == ErrorKind::PermissionDenied => probed = true,
+				Err(_) => {}
+			}
+		}
+
+		if probed {
+			PermissionStatus::Denied
+		} else {
+			PermissionStatus::Unknown
+		}
+	}
+
+	fn removable_volumes_status() -> PermissionStatus {
+		let Ok(volumes) = fs::read_dir("/Volumes") else {
+			return PermissionStatus::Unknown;
+		};
+
+		// The boot volume is symlinked into `/Volumes` and is never gated
+		let external = volumes
+			.filter_map(std::result::Result::ok)
+			.filter(|entry| entry.file_type().is_ok_and(|t| !t.is_symlink()))
+			.map(|entry| entry.path());
+
+		let mut status = PermissionStatus::Unknown;
+		for volume in external {
+			match fs::read_dir(volume) {
+				Ok(_) => return PermissionStatus::Granted,
+				Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+					status = PermissionStatus::Denied;
+				}
+				Err(_) => {}
+			}
+		}
+
+		status
+	}
+}
+
 #[cfg(test)]
 mod tests {
-	use super::DiskAccess;
+	use super::{DiskAccess, Permission, PermissionStatus};
+
+	#[test]
+	#[cfg(not(target_os = "macos"))]
+	fn permissions_not_required_off_macos() {
+		for permission in Permission::ALL {
+			assert_eq!(DiskAccess::status(permission), PermissionStatus::NotRequired);
+		}
+	}
 
 	#[test]
 	#[cfg_attr(miri, ignore = "Miri can't run this test")]
@@ -479,7 +479,8 @@ async fn start(
 
 					// For mode 1 the stream will be dropped now
 					if mode[0] != 1 {
-						let stream = UnicastStream::new(identity, stream.compat());
+						let stream = UnicastStream::new(identity, stream.compat())
+							.throttled(p2p.bandwidth().clone());
 						p2p.connected_to_incoming(
 							id,
 							remote_metadata,
@@ -741,7 +742,9 @@ async fn start(
 
 									p2p.connected_to_outgoing(id, remote_metadata, req.to);
 
-									let _ = req.tx.send(Ok(UnicastStream::new(req.to, stream.compat())));
+									let stream = UnicastStream::new(req.to, stream.compat())
+										.throttled(p2p.bandwidth().clone());
+									let _ = req.tx.send(Ok(stream));
 								},
 								Err(e) => {
 									let _ = req.tx.send(Err(e.to_string()));
@@ -8,6 +8,7 @@ mod p2p;
 mod peer;
 mod smart_guards;
 mod stream;
+mod throttle;
 
 pub use hook::{HookEvent, HookId, ListenerId, ShutdownGuard};
 pub use identity::{Identity, IdentityErr, RemoteIdentity};
@@ -15,6 +16,7 @@ pub use p2p::{Listener, P2P};
 pub use peer::{ConnectionRequest, Peer, PeerConnectionCandidate};
 pub use smart_guards::SmartWriteGuard;
 pub use stream::UnicastStream;
+pub use throttle::{Bandwidth, BandwidthLimit, Direction};
 
 pub use flume;
 
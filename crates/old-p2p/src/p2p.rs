@@ -15,7 +15,7 @@ use tracing::info;
 use crate::{
 	hook::{HandlerFn, Hook, HookEvent, ListenerData, ListenerId, ShutdownGuard},
 	smart_guards::SmartWriteGuard,
-	HookId, Identity, Peer, PeerConnectionCandidate, RemoteIdentity, UnicastStream,
+	Bandwidth, HookId, Identity, Peer, PeerConnectionCandidate, RemoteIdentity, UnicastStream,
 };
 
 /// Manager for the entire P2P system.
@@ -37,6 +37,8 @@ pub struct P2P {
 	pub(crate) peers: RwLock<HashMap<RemoteIdentity, Arc<Peer>>>,
 	/// Hooks can be registered to react to state changes in the P2P system.
 	pub(crate) hooks: RwLock<StableVec<Hook>>,
+	/// The bandwidth limits every stream is held to, shared with them so they apply as they change.
+	bandwidth: Arc<Bandwidth>,
 }
 
 impl P2P {
@@ -63,6 +65,7 @@ impl P2P {
 			peers: Default::default(),
 			handler_tx,
 			hooks: Default::default(),
+			bandwidth: Default::default(),
 		})
 	}
 
@@ -111,6 +114,11 @@ impl P2P {
 		})
 	}
 
+	/// The bandwidth limits of the streams, opened and accepted alike.
+	pub fn bandwidth(&self) -> &Arc<Bandwidth> {
+		&self.bandwidth
+	}
+
 	/// A list of all peers known to the P2P system. Be aware a peer could be connected and/or discovered at any time.
 	pub fn peers(&self) -> RwLockReadGuard<HashMap<RemoteIdentity, Arc<Peer>>> {
 		self.peers.read().unwrap_or_else(PoisonError::into_inner)
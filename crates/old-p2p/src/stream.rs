@@ -1,13 +1,17 @@
 use std::{
 	fmt, io,
 	pin::Pin,
-	task::{Context, Poll},
+	sync::Arc,
+	task::{ready, Context, Poll},
 };
 
 use sync_wrapper::SyncWrapper;
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
 
-use crate::RemoteIdentity;
+use crate::{
+	throttle::{Direction, Throttle},
+	Bandwidth, RemoteIdentity,
+};
 
 trait IoStream: AsyncRead + AsyncWrite {}
 impl<S: AsyncRead + AsyncWrite> IoStream for S {}
@@ -16,6 +20,7 @@ impl<S: AsyncRead + AsyncWrite> IoStream for S {}
 pub struct UnicastStream {
 	io: SyncWrapper<Pin<Box<dyn IoStream + Send>>>,
 	remote: RemoteIdentity,
+	throttle: Option<Throttle>,
 }
 
 impl fmt::Debug for UnicastStream {
@@ -31,9 +36,16 @@ impl UnicastStream {
 		Self {
 			io: SyncWrapper::new(Box::pin(io)),
 			remote,
+			throttle: None,
 		}
 	}
 
+	/// Holds the stream to the limits of `bandwidth`, following them as they change
+	pub(crate) fn throttled(mut self, bandwidth: Arc<Bandwidth>) -> Self {
+		self.throttle = Some(Throttle::new(bandwidth));
+		self
+	}
+
 	#[must_use]
 	pub fn remote_identity(&self) -> RemoteIdentity {
 		self.remote
@@ -50,9 +62,26 @@ impl AsyncRead for UnicastStream {
 		cx: &mut Context<'_>,
 		buf: &mut ReadBuf<'_>,
 	) -> Poll<io::Result<()>> {
-		Pin::new(&mut self.get_mut().io)
+		let this = self.get_mut();
+		let Some(throttle) = this.throttle.as_mut().filter(|_| buf.remaining() > 0) else {
+			return Pin::new(&mut this.io).get_pin_mut().poll_read(cx, buf);
+		};
+
+		let allowed =
+			ready!(throttle.poll_allowance(cx, Direction::Download, &this.remote, buf.remaining()));
+
+		let mut limited = buf.take(allowed);
+		ready!(Pin::new(&mut this.io)
 			.get_pin_mut()
-			.poll_read(cx, buf)
+			.poll_read(cx, &mut limited))?;
+		let read = limited.filled().len();
+
+		// SAFETY: `limited` is the start of the unfilled part of `buf`, the read initialized them
+		unsafe { buf.assume_init(read) };
+		buf.advance(read);
+		throttle.consume(Direction::Download, &this.remote, read);
+
+		Poll::Ready(Ok(()))
 	}
 }
 
@@ -62,9 +91,22 @@ impl AsyncWrite for UnicastStream {
 		cx: &mut Context<'_>,
 		buf: &[u8],
 	) -> Poll<io::Result<usize>> {
-		Pin::new(&mut self.get_mut().io)
-			.get_pin_mut()
-			.poll_write(cx, buf)
+		let this = self.get_mut();
+		let buf = match this.throttle.as_mut() {
+			Some(throttle) if !buf.is_empty() => {
+				let allowed =
+					ready!(throttle.poll_allowance(cx, Direction::Upload, &this.remote, buf.len()));
+				&buf[..allowed]
+			}
+			_ => buf,
+		};
+
+		let written = ready!(Pin::new(&mut this.io).get_pin_mut().poll_write(cx, buf))?;
+		if let Some(throttle) = &this.throttle {
+			throttle.consume(Direction::Upload, &this.remote, written);
+		}
+
+		Poll::Ready(Ok(written))
 	}
 
 	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
@@ -0,0 +1,280 @@
+//! Bandwidth limits, enforced on every [`UnicastStream`](crate::UnicastStream) with token buckets.
+//!
+//! A stream is held to the global limit and to the limit of its peer, each bucket being shared by
+//! all the streams it applies to. A bucket holds up to a second worth of its rate, so a stream that
+//! was idle can go that far before being slowed down.
+//!
+//! Other transports can be held to the global limit too with [`Bandwidth::pace`], cloud sync does
+//! so with the batches it pushes and pulls.
+
+use std::{
+	collections::HashMap,
+	future::Future,
+	num::NonZeroU32,
+	pin::Pin,
+	sync::{Arc, Mutex, PoisonError},
+	task::{ready, Context, Poll},
+	time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::time::{sleep, Sleep};
+
+use crate::RemoteIdentity;
+
+/// A bucket that ran dry waits for this part of its rate before letting bytes through again, so a
+/// stream isn't woken up for a handful of them
+const REFILL_FRACTION: f64 = 20.0;
+
+/// Upload and download limits in KB/s, unlimited when unset
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct BandwidthLimit {
+	#[serde(default)]
+	pub upload_kbps: Option<NonZeroU32>,
+	#[serde(default)]
+	pub download_kbps: Option<NonZeroU32>,
+}
+
+/// Which way bytes go, from this node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+	Upload,
+	Download,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+	/// In bytes per second
+	rate: f64,
+	tokens: f64,
+	refilled_at: Instant,
+}
+
+impl TokenBucket {
+	fn new(kbps: NonZeroU32) -> Self {
+		let rate = to_rate(kbps);
+		Self {
+			rate,
+			tokens: rate,
+			refilled_at: Instant::now(),
+		}
+	}
+
+	fn refill(&mut self, now: Instant) {
+		let elapsed = now.saturating_duration_since(self.refilled_at);
+		self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate).min(self.rate);
+		self.refilled_at = now;
+	}
+
+	fn set_rate(&mut self, kbps: NonZeroU32) {
+		self.refill(Instant::now());
+		self.rate = to_rate(kbps);
+		self.tokens = self.tokens.min(self.rate);
+	}
+
+	/// The bytes of `wanted` that can go through now, or how long until a chunk of them can
+	fn available(&mut self, now: Instant, wanted: usize) -> Result<usize, Duration> {
+		self.refill(now);
+
+		let chunk = (wanted as f64).min(self.rate / REFILL_FRACTION).max(1.0);
+		if self.tokens >= chunk {
+			Ok(wanted.min(self.tokens as usize))
+		} else {
+			Err(Duration::from_secs_f64((chunk - self.tokens) / self.rate))
+		}
+	}
+
+	fn consume(&mut self, bytes: usize) {
+		self.tokens -= bytes as f64;
+	}
+
+	/// Takes `bytes` even if the bucket goes into debt, returning how long until it's paid off
+	fn take(&mut self, now: Instant, bytes: usize) -> Duration {
+		self.refill(now);
+		self.consume(bytes);
+
+		if self.tokens >= 0.0 {
+			Duration::ZERO
+		} else {
+			Duration::from_secs_f64(-self.tokens / self.rate)
+		}
+	}
+}
+
+fn to_rate(kbps: NonZeroU32) -> f64 {
+	f64::from(kbps.get()) * 1024.0
+}
+
+#[derive(Debug, Default)]
+struct Buckets {
+	upload: Option<TokenBucket>,
+	download: Option<TokenBucket>,
+}
+
+impl Buckets {
+	fn set(&mut self, limit: BandwidthLimit) {
+		set_bucket(&mut self.upload, limit.upload_kbps);
+		set_bucket(&mut self.download, limit.download_kbps);
+	}
+
+	fn get(&mut self, direction: Direction) -> Option<&mut TokenBucket> {
+		match direction {
+			Direction::Upload => self.upload.as_mut(),
+			Direction::Download => self.download.as_mut(),
+		}
+	}
+}
+
+/// Keeps the tokens of a bucket whose limit changed, so changing it doesn't let a burst through
+fn set_bucket(bucket: &mut Option<TokenBucket>, kbps: Option<NonZeroU32>) {
+	match (bucket.as_mut(), kbps) {
+		(_, None) => *bucket = None,
+		(Some(bucket), Some(kbps)) => bucket.set_rate(kbps),
+		(None, Some(kbps)) => *bucket = Some(TokenBucket::new(kbps)),
+	}
+}
+
+#[derive(Debug, Default)]
+struct State {
+	global: Buckets,
+	peers: HashMap<RemoteIdentity, Buckets>,
+}
+
+impl State {
+	fn buckets(
+		&mut self,
+		direction: Direction,
+		remote: &RemoteIdentity,
+	) -> impl Iterator<Item = &mut TokenBucket> {
+		let Self { global, peers } = self;
+
+		[
+			global.get(direction),
+			peers
+				.get_mut(remote)
+				.and_then(|buckets| buckets.get(direction)),
+		]
+		.into_iter()
+		.flatten()
+	}
+}
+
+/// The bandwidth limits of a [`P2P`](crate::P2P), they can be changed while streams are open
+#[derive(Debug, Default)]
+pub struct Bandwidth {
+	state: Mutex<State>,
+}
+
+impl Bandwidth {
+	/// Replaces the limits, the peers left out of `peers` are only held to the global one
+	pub fn set_limits(
+		&self,
+		global: BandwidthLimit,
+		peers: &HashMap<RemoteIdentity, BandwidthLimit>,
+	) {
+		let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+		state.global.set(global);
+		state
+			.peers
+			.retain(|identity, _| peers.contains_key(identity));
+		for (identity, limit) in peers {
+			state.peers.entry(*identity).or_default().set(*limit);
+		}
+	}
+
+	/// Holds `bytes` moved outside of the streams of this crate to the global limit, waiting for
+	/// them to fit in it. They're taken whole, so a big message is paid off after it went through.
+	pub async fn pace(&self, direction: Direction, bytes: usize) {
+		let wait = self
+			.state
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.global
+			.get(direction)
+			.map_or(Duration::ZERO, |bucket| bucket.take(Instant::now(), bytes));
+
+		if !wait.is_zero() {
+			sleep(wait).await;
+		}
+	}
+
+	fn allowance(
+		&self,
+		direction: Direction,
+		remote: &RemoteIdentity,
+		wanted: usize,
+	) -> Result<usize, Duration> {
+		let now = Instant::now();
+
+		self.state
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.buckets(direction, remote)
+			.try_fold(wanted, |allowed, bucket| {
+				bucket
+					.available(now, wanted)
+					.map(|available| allowed.min(available))
+			})
+	}
+
+	fn consume(&self, direction: Direction, remote: &RemoteIdentity, bytes: usize) {
+		self.state
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.buckets(direction, remote)
+			.for_each(|bucket| bucket.consume(bytes));
+	}
+}
+
+/// What holds a stream back, with the delay each direction is waiting on
+#[derive(Debug)]
+pub(crate) struct Throttle {
+	bandwidth: Arc<Bandwidth>,
+	upload_delay: Option<Pin<Box<Sleep>>>,
+	download_delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl Throttle {
+	pub(crate) fn new(bandwidth: Arc<Bandwidth>) -> Self {
+		Self {
+			bandwidth,
+			upload_delay: None,
+			download_delay: None,
+		}
+	}
+
+	/// The bytes of `wanted` the stream can move now, pending until some of them can
+	pub(crate) fn poll_allowance(
+		&mut self,
+		cx: &mut Context<'_>,
+		direction: Direction,
+		remote: &RemoteIdentity,
+		wanted: usize,
+	) -> Poll<usize> {
+		let delay = match direction {
+			Direction::Upload => &mut self.upload_delay,
+			Direction::Download => &mut self.download_delay,
+		};
+
+		loop {
+			if let Some(delay) = delay.as_mut() {
+				ready!(delay.as_mut().poll(cx));
+			}
+
+			match self.bandwidth.allowance(direction, remote, wanted) {
+				Ok(allowed) => {
+					*delay = None;
+					return Poll::Ready(allowed);
+				}
+				Err(wait) => *delay = Some(Box::pin(sleep(wait))),
+			}
+		}
+	}
+
+	/// Takes the bytes the stream moved out of its buckets
+	pub(crate) fn consume(&self, direction: Direction, remote: &RemoteIdentity, bytes: usize) {
+		self.bandwidth.consume(direction, remote, bytes);
+	}
+}
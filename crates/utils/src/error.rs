@@ -41,6 +41,32 @@ impl<P: AsRef<Path>> From<(P, io::Error, &'static str)> for FileIOError {
 	}
 }
 
+impl FileIOError {
+	/// Whether the device holding the path failed, not the path being missing or denied
+	#[must_use]
+	pub fn is_device_failure(&self) -> bool {
+		is_device_failure(&self.source)
+	}
+}
+
+/// The OS error codes of a device failing
+#[cfg(unix)]
+const DEVICE_ERRORS: &[i32] = &[libc::EIO, libc::ENXIO, libc::ENODEV];
+/// `ERROR_CRC`, `ERROR_SECTOR_NOT_FOUND`, `ERROR_WRITE_FAULT`, `ERROR_READ_FAULT`,
+/// `ERROR_GEN_FAILURE`, `ERROR_IO_DEVICE` and `ERROR_DEVICE_NOT_CONNECTED`
+#[cfg(windows)]
+const DEVICE_ERRORS: &[i32] = &[23, 27, 29, 30, 31, 1117, 1167];
+#[cfg(not(any(unix, windows)))]
+const DEVICE_ERRORS: &[i32] = &[];
+
+/// Whether the error comes from a dying disk or a dropped connection to it
+#[must_use]
+pub fn is_device_failure(error: &io::Error) -> bool {
+	error
+		.raw_os_error()
+		.is_some_and(|code| DEVICE_ERRORS.contains(&code))
+}
+
 impl From<FileIOError> for rspc::Error {
 	fn from(value: FileIOError) -> Self {
 		Self::with_cause(
@@ -11,6 +11,7 @@ import { usePlatform, type Platform } from '~/util/Platform';
 import { useExplorerContext } from '../Context';
 import { getQuickPreviewStore } from '../QuickPreview/store';
 import { RevealInNativeExplorerBase } from '../RevealInNativeExplorer';
+import { useOpenTerminal } from '../hooks/useOpenTerminal';
 import { explorerStore } from '../store';
 import { getPaths } from '../useExplorerDnd';
 import { useViewItemDoubleClick } from '../View/ViewItem';
@@ -173,6 +174,23 @@ export const RevealInNativeExplorer = new ConditionalItem({
 	Component: ({ items }) => <RevealInNativeExplorerBase items={items} />
 });
 
+export const OpenInTerminal = new ConditionalItem({
+	useCondition: () => {
+		const { selectedItems } = useContextMenuContext();
+		const { isAvailable } = useOpenTerminal();
+
+		if (!isAvailable || selectedItems.length !== 1) return null;
+
+		return { item: selectedItems[0] };
+	},
+	Component: ({ item }) => {
+		const { t } = useLocale();
+		const { openTerminal } = useOpenTerminal();
+
+		return <ContextMenu.Item label={t('open_in_terminal')} onClick={() => openTerminal(item)} />;
+	}
+});
+
 export const Deselect = new ConditionalItem({
 	useCondition: () => {
 		const cutCopyState = useSelector(explorerStore, (s) => s.cutCopyState);
@@ -28,6 +28,7 @@ const Items = ({ children }: PropsWithChildren) => {
 			<SeparatedConditional
 				items={[
 					SharedItems.RevealInNativeExplorer,
+					SharedItems.OpenInTerminal,
 					SharedItems.Rename,
 					FilePathItems.CutCopyItems,
 					SharedItems.Deselect
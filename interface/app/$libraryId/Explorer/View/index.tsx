@@ -18,6 +18,8 @@ import { isNonEmpty } from '~/util';
 import CreateDialog from '../../settings/library/tags/CreateDialog';
 import { useExplorerContext } from '../Context';
 import { useExplorerCopyPaste } from '../hooks/useExplorerCopyPaste';
+import { useOpenTerminal } from '../hooks/useOpenTerminal';
+import { useQuickLook } from '../hooks/useQuickLook';
 import { QuickPreview } from '../QuickPreview';
 import { useQuickPreviewContext } from '../QuickPreview/Context';
 import { getQuickPreviewStore, useQuickPreviewStore } from '../QuickPreview/store';
@@ -214,6 +216,8 @@ const useExplorerShortcuts = () => {
 	const { doubleClick } = useViewItemDoubleClick();
 
 	const { copy, cut, duplicate, paste } = useExplorerCopyPaste();
+	const { quickLook, isAvailable: isQuickLookAvailable } = useQuickLook();
+	const { openTerminal } = useOpenTerminal();
 
 	useShortcut('copyObject', copy);
 	useShortcut('cutObject', cut);
@@ -225,6 +229,7 @@ const useExplorerShortcuts = () => {
 	});
 
 	useShortcut('toggleQuickPreview', (e) => {
+		if (isQuickLookAvailable && !quickPreviewStore.open) return;
 		if (isRenaming || dialogManager.isAnyDialogOpen()) return;
 		if (explorerStore.isCMDPOpen) return;
 		if (explorer.selectedItems.size === 0) return;
@@ -232,6 +237,37 @@ const useExplorerShortcuts = () => {
 		getQuickPreviewStore().open = !quickPreviewStore.open;
 	});
 
+	useShortcut('quickLook', (e) => {
+		// Space still closes the quick preview when it was opened some other way
+		if (!isQuickLookAvailable || quickPreviewStore.open) return;
+		if (isRenaming || dialogManager.isAnyDialogOpen()) return;
+		if (explorerStore.isCMDPOpen) return;
+		if (explorer.selectedItems.size === 0) return;
+		e.preventDefault();
+		quickLook();
+	});
+
+	// Triggered from the native menu
+	useEffect(() => {
+		const handler = () => {
+			if (explorer.selectedItems.size > 0) quickLook();
+		};
+
+		document.addEventListener('quick_look', handler);
+		return () => document.removeEventListener('quick_look', handler);
+	});
+
+	// Triggered from the native menu, opens the selected item or the current directory
+	useEffect(() => {
+		const handler = () => {
+			const items = Array.from(explorer.selectedItems);
+			openTerminal(items.length === 1 ? items[0] : undefined);
+		};
+
+		document.addEventListener('open_terminal', handler);
+		return () => document.removeEventListener('open_terminal', handler);
+	});
+
 	useShortcut('openObject', (e) => {
 		if (isRenaming || quickPreviewStore.open) return;
 		e.stopPropagation();
@@ -4,11 +4,13 @@ import { useExplorerContext } from '~/app/$libraryId/Explorer/Context';
 import { explorerStore } from '~/app/$libraryId/Explorer/store';
 import { useExplorerSearchParams } from '~/app/$libraryId/Explorer/util';
 import { useLocale } from '~/hooks';
+import { usePlatform } from '~/util/Platform';
 
 export const useExplorerCopyPaste = () => {
 	const { t } = useLocale();
 
 	const explorer = useExplorerContext();
+	const platform = usePlatform();
 	const [{ path: currentPath }] = useExplorerSearchParams();
 
 	const copyFiles = useLibraryMutation('files.copyFiles');
@@ -47,6 +49,27 @@ export const useExplorerCopyPaste = () => {
 		return { sourcePaths };
 	}
 
+	/**
+	 * Mirrors the selection to the OS clipboard so it can be pasted in other apps
+	 */
+	async function writeOsClipboard(type: 'Copy' | 'Cut') {
+		if (!platform.writeFilesToClipboard) return;
+
+		const paths: string[] = [...(getEphemeralArgs()?.sourcePaths ?? [])];
+
+		const indexedArgs = getIndexedArgs();
+		if (indexedArgs) {
+			await Promise.all(
+				indexedArgs.sourcePathIds.map(async (id) => {
+					const path = await libraryClient.query(['files.getPath', id]);
+					if (path) paths.push(path);
+				})
+			);
+		}
+
+		if (paths.length > 0) await platform.writeFilesToClipboard(paths, type);
+	}
+
 	function copy() {
 		explorerStore.cutCopyState = {
 			type: 'Copy',
@@ -54,6 +77,7 @@ export const useExplorerCopyPaste = () => {
 			indexedArgs: getIndexedArgs(),
 			ephemeralArgs: getEphemeralArgs()
 		};
+		writeOsClipboard('Copy');
 	}
 
 	function cut() {
@@ -63,6 +87,33 @@ export const useExplorerCopyPaste = () => {
 			indexedArgs: getIndexedArgs(),
 			ephemeralArgs: getEphemeralArgs()
 		};
+		writeOsClipboard('Cut');
+	}
+
+	/**
+	 * Pastes files copied in other apps, which we only know by their path
+	 */
+	async function pasteFromOsClipboard() {
+		const result = await platform.readFilesFromClipboard?.();
+		if (result?.status !== 'ok' || !result.data) return;
+
+		const { operation, paths } = result.data;
+		const mutation = operation === 'Copy' ? copyEphemeralFiles : cutEphemeralFiles;
+
+		let targetDir = path;
+		if (explorer.parent?.type === 'Location') {
+			targetDir = explorer.parent.location.path + path;
+		}
+
+		try {
+			await mutation.mutateAsync({ sources: paths, target_dir: targetDir });
+			toast.success(t(`${operation.toLowerCase()}_success`));
+		} catch (error) {
+			toast.error({
+				title: t(operation === 'Copy' ? 'failed_to_copy_file' : 'failed_to_cut_file'),
+				body: t('error_message', { error })
+			});
+		}
 	}
 
 	async function duplicate() {
@@ -101,7 +152,7 @@ export const useExplorerCopyPaste = () => {
 	}
 
 	async function paste() {
-		if (explorerStore.cutCopyState.type === 'Idle') return;
+		if (explorerStore.cutCopyState.type === 'Idle') return pasteFromOsClipboard();
 
 		const { type, indexedArgs, ephemeralArgs } = explorerStore.cutCopyState;
 
@@ -0,0 +1,53 @@
+import { ExplorerItem } from '@sd/client';
+import { toast } from '@sd/ui';
+import { useExplorerContext } from '~/app/$libraryId/Explorer/Context';
+import { useExplorerSearchParams } from '~/app/$libraryId/Explorer/util';
+import { useLocale } from '~/hooks';
+import { usePlatform } from '~/util/Platform';
+
+import { getPaths } from '../useExplorerDnd';
+
+/**
+ * Opens the preferred terminal in the given item, or in the directory being browsed when there's none
+ */
+export const useOpenTerminal = () => {
+	const { t } = useLocale();
+
+	const explorer = useExplorerContext();
+	const platform = usePlatform();
+	const [{ path: subPath }] = useExplorerSearchParams();
+
+	function currentDirectory() {
+		switch (explorer.parent?.type) {
+			case 'Location': {
+				const { path } = explorer.parent.location;
+				return path ? `${path}${subPath ?? ''}` : null;
+			}
+			case 'Ephemeral':
+				return explorer.parent.path;
+			default:
+				return null;
+		}
+	}
+
+	async function openTerminal(item?: ExplorerItem) {
+		if (!platform.openTerminal) return;
+
+		let path: string | null | undefined;
+		if (!item) path = currentDirectory();
+		else if (item.type === 'Location') path = item.item.path;
+		else path = (await getPaths([item]))[0];
+
+		if (!path) return;
+
+		const result = await platform.openTerminal(path);
+		if (result.status === 'error') {
+			toast.error({
+				title: t('failed_to_open_terminal'),
+				body: t('error_message', { error: result.error })
+			});
+		}
+	}
+
+	return { openTerminal, isAvailable: !!platform.openTerminal };
+};
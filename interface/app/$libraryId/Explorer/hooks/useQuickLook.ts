@@ -0,0 +1,48 @@
+import { getIndexedItemFilePath, libraryClient } from '@sd/client';
+import { toast } from '@sd/ui';
+import { useExplorerContext } from '~/app/$libraryId/Explorer/Context';
+import { useLocale } from '~/hooks';
+import { usePlatform } from '~/util/Platform';
+
+/**
+ * Opens the selection in the native preview (Quick Look on macOS), when the platform supports it
+ */
+export const useQuickLook = () => {
+	const { t } = useLocale();
+
+	const explorer = useExplorerContext();
+	const platform = usePlatform();
+
+	async function quickLook() {
+		if (!platform.quickLook) return;
+
+		const paths: string[] = [];
+
+		await Promise.all(
+			Array.from(explorer.selectedItems).map(async (item) => {
+				if (item.type === 'NonIndexedPath') {
+					paths.push(item.item.path);
+					return;
+				}
+
+				const filePath = getIndexedItemFilePath(item);
+				if (!filePath) return;
+
+				const path = await libraryClient.query(['files.getPath', filePath.id]);
+				if (path) paths.push(path);
+			})
+		);
+
+		if (paths.length === 0) return;
+
+		const result = await platform.quickLook(paths);
+		if (result.status === 'error') {
+			toast.error({
+				title: t('failed_to_preview_file'),
+				body: t('error_message', { error: result.error })
+			});
+		}
+	}
+
+	return { quickLook, isAvailable: !!platform.quickLook };
+};
@@ -1,7 +1,9 @@
 import {
+	ChartPieSlice,
 	Copy,
 	Fingerprint,
 	Folder,
+	HardDrives,
 	Icon,
 	Image,
 	Info,
@@ -33,7 +35,9 @@ export const JobIcon: Record<JobName, Icon> = {
 	Delete: Trash,
 	Erase: Trash,
 	Move: Scissors,
-	FileValidator: Fingerprint
+	FileValidator: Fingerprint,
+	VolumeMaintenance: HardDrives,
+	VolumeBreakdown: ChartPieSlice
 };
 
 // Jobs like deleting and copying files do not have simplied job names
@@ -1,4 +1,4 @@
-import { EjectSimple } from '@phosphor-icons/react';
+import { EjectSimple, LockSimple } from '@phosphor-icons/react';
 import { useQueryClient } from '@tanstack/react-query';
 import clsx from 'clsx';
 import { MouseEvent, PropsWithChildren, useMemo } from 'react';
@@ -34,7 +34,7 @@ const EjectButton = ({
 			onClick={async (e: MouseEvent) => {
 				e.preventDefault(); // Prevent navigation
 				try {
-					await unmountMutation.mutateAsync(Array.from(fingerprint));
+					await unmountMutation.mutateAsync({ fingerprint: Array.from(fingerprint) });
 					toast.success('Volume ejected successfully');
 				} catch (error) {
 					toast.error('Failed to eject volume');
@@ -50,6 +50,28 @@ const SidebarIcon = ({ name }: { name: IconName }) => {
 	return <Icon name={name} size={20} className="mr-1" />;
 };
 
+// Nothing can be browsed or indexed on an encrypted volume until the user unlocks it
+const LockedVolume = ({ volume }: { volume: Volume }) => {
+	const { t } = useLocale();
+	const name = volume.name || volume.mount_point;
+
+	return (
+		<button
+			className="relative flex w-full flex-row items-center gap-0.5 truncate rounded px-2 py-1 font-plex text-sm font-medium tracking-wide text-sidebar-inkDull outline-none"
+			onClick={() =>
+				toast.info({
+					title: t('volume_locked'),
+					body: t('volume_locked_description', { name })
+				})
+			}
+		>
+			<SidebarIcon name={getVolumeIcon(volume)} />
+			<Name>{name}</Name>
+			<LockSimple weight="fill" size={18} className="absolute right-[7px] size-3 opacity-70" />
+		</button>
+	);
+};
+
 export default function LocalSection() {
 	const platform = usePlatform();
 	const queryClient = useQueryClient();
@@ -129,6 +151,10 @@ export default function LocalSection() {
 				)}
 
 				{uniqueVolumes.map((volume) => {
+					// Locked LUKS containers have nothing mounted
+					if (volume.is_locked)
+						return <LockedVolume key={volume.mount_point} volume={volume} />;
+
 					const mountPoint = volume.mount_points[0];
 					if (!mountPoint) return null;
 					const key = `${volume.pub_id}-${mountPoint}`;
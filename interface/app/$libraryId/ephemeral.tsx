@@ -2,7 +2,7 @@ import { ArrowLeft, ArrowRight, Info } from '@phosphor-icons/react';
 import * as Dialog from '@radix-ui/react-dialog';
 import { iconNames } from '@sd/assets/util';
 import clsx from 'clsx';
-import { memo, Suspense, useCallback, useDeferredValue, useEffect, useMemo } from 'react';
+import { memo, Suspense, useCallback, useDeferredValue, useEffect, useMemo, useRef } from 'react';
 import {
 	ExplorerItem,
 	getExplorerItemData,
@@ -11,7 +11,7 @@ import {
 	useUnsafeStreamedQuery,
 	type EphemeralPathOrder
 } from '@sd/client';
-import { Button, Tooltip } from '@sd/ui';
+import { Button, Tooltip, z } from '@sd/ui';
 import { PathParamsSchema, type PathParams } from '~/app/route-schemas';
 import { Icon } from '~/components';
 import {
@@ -27,6 +27,7 @@ import { useRouteTitle } from '~/hooks/useRouteTitle';
 
 import Explorer from './Explorer';
 import { ExplorerContextProvider } from './Explorer/Context';
+import { getQuickPreviewStore } from './Explorer/QuickPreview/store';
 import { createDefaultExplorerSettings, explorerStore } from './Explorer/store';
 import { DefaultTopBarOptions } from './Explorer/TopBarOptions';
 import { useExplorer, useExplorerSettings } from './Explorer/useExplorer';
@@ -152,7 +153,11 @@ const EphemeralNotice = memo(({ path }: { path: string }) => {
 	);
 });
 
-const EphemeralExplorer = memo(({ args: path }: { args: PathParams['path'] }) => {
+// `open` is set when the OS asked us to open a file, it's previewed once its folder loads
+const EphemeralParamsSchema = PathParamsSchema.extend({ open: z.string().optional() });
+
+const EphemeralExplorer = memo((props: { args: PathParams['path']; open?: string }) => {
+	const { args: path, open } = props;
 	const os = useOperatingSystem();
 
 	const explorerSettings = useExplorerSettings({
@@ -225,6 +230,21 @@ const EphemeralExplorer = memo(({ args: path }: { args: PathParams['path'] }) =>
 
 	useKeyDeleteFile(explorer.selectedItems, null);
 
+	// Only previewed once, not on every refresh of the items
+	const previewedPath = useRef<string>();
+	const { resetSelectedItems } = explorer;
+
+	useEffect(() => {
+		if (!open || previewedPath.current === open) return;
+
+		const item = items.find((item) => item.type === 'NonIndexedPath' && item.item.path === open);
+		if (!item) return;
+
+		previewedPath.current = open;
+		resetSelectedItems([item]);
+		getQuickPreviewStore().open = true;
+	}, [open, items, resetSelectedItems]);
+
 	const { t } = useLocale();
 
 	return (
@@ -251,7 +271,7 @@ const EphemeralExplorer = memo(({ args: path }: { args: PathParams['path'] }) =>
 });
 
 export const Component = () => {
-	let [{ path }] = useZodSearchParams(PathParamsSchema);
+	let [{ path, open }] = useZodSearchParams(EphemeralParamsSchema);
 
 	path = useDeferredValue(path);
 
@@ -260,7 +280,7 @@ export const Component = () => {
 	return (
 		<Suspense>
 			<EphemeralNotice path={path ?? ''} />
-			<EphemeralExplorer args={path} />
+			<EphemeralExplorer args={path} open={open} />
 		</Suspense>
 	);
 };
@@ -1,4 +1,5 @@
 import { CheckCircle } from '@phosphor-icons/react';
+import { useQuery, useQueryClient } from '@tanstack/react-query';
 import clsx from 'clsx';
 import { useState } from 'react';
 import { Themes, useExplorerLayoutStore, useThemeStore, useUnitFormatStore } from '@sd/client';
@@ -73,7 +74,18 @@ const LANGUAGE_OPTIONS = [
 LANGUAGE_OPTIONS.sort((a, b) => a.label.localeCompare(b.label));
 
 export const Component = () => {
-	const { lockAppTheme } = usePlatform();
+	const { lockAppTheme, getLocaleSettings, setLocale } = usePlatform();
+	const queryClient = useQueryClient();
+	// On desktop the backend owns the language, so it can follow the OS and stay in sync across windows
+	const localeSettings = useQuery({
+		queryKey: ['localeSettings'],
+		queryFn: async () => {
+			const result = await getLocaleSettings!();
+			if (result.status === 'error') throw new Error('Failed to get locale settings');
+			return result.data;
+		},
+		enabled: getLocaleSettings !== undefined
+	});
 	const themeStore = useThemeStore();
 	const formatStore = useUnitFormatStore();
 	const explorerLayout = useExplorerLayoutStore();
@@ -188,8 +200,19 @@ export const Component = () => {
 			<Setting mini title={t('language')} description={t('language_description')}>
 				<div className="flex h-[30px] gap-2">
 					<Select
-						value={i18n.resolvedLanguage || i18n.language || 'en'}
-						onChange={(e) => {
+						value={
+							localeSettings.data
+								? (localeSettings.data.preferred ?? 'system')
+								: i18n.resolvedLanguage || i18n.language || 'en'
+						}
+						onChange={async (e) => {
+							if (setLocale) {
+								await setLocale(e === 'system' ? null : e);
+								queryClient.invalidateQueries({ queryKey: ['localeSettings'] });
+								// The backend tells every window about the new language once it's switched
+								if (e === 'system') return;
+							}
+
 							// if previous language was English, set date formatting for default value
 							if ((i18n.resolvedLanguage || i18n.language) === 'en') {
 								localStorage.setItem('sd-date-format', 'LL');
@@ -204,6 +227,9 @@ export const Component = () => {
 						}}
 						containerClassName="h-[30px] whitespace-nowrap"
 					>
+						{localeSettings.data && (
+							<SelectOption value="system">{t('system')}</SelectOption>
+						)}
 						{LANGUAGE_OPTIONS.map((lang, key) => (
 							<SelectOption key={key} value={lang.value}>
 								{lang.label}
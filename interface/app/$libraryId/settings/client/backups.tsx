@@ -66,7 +66,7 @@ export const Component = () => {
 					<div className="flex h-[45px] space-x-2 p-2">
 						<Button
 							disabled={doRestore.isPending}
-							onClick={() => doRestore.mutate(backup.path)}
+							onClick={() => doRestore.mutate({ path: backup.path })}
 							variant="gray"
 						>
 							{t('restore')}
@@ -1,3 +1,4 @@
+import { useQuery } from '@tanstack/react-query';
 import { PropsWithChildren } from 'react';
 import { Controller, FormProvider } from 'react-hook-form';
 import {
@@ -45,6 +46,183 @@ function RenderListenerPill(props: PropsWithChildren<{ listener?: ListenerState
 	return null;
 }
 
+function FileAssociationSettings() {
+	const { t } = useLocale();
+	const {
+		getFileAssociations,
+		registerFileAssociations,
+		unregisterFileAssociations,
+		setFolderHandler
+	} = usePlatform();
+
+	const associations = useQuery({
+		queryKey: ['fileAssociations'],
+		queryFn: async () => {
+			const result = await getFileAssociations?.();
+			return result?.status === 'ok' ? result.data : null;
+		},
+		enabled: !!getFileAssociations
+	});
+
+	if (
+		!associations.data ||
+		!registerFileAssociations ||
+		!unregisterFileAssociations ||
+		!setFolderHandler
+	)
+		return null;
+
+	const { fileTypes, folderHandler, requiresUserConfirmation } = associations.data;
+
+	const kinds = {
+		Image: t('open_image_files'),
+		Video: t('open_video_files'),
+		Audio: t('open_audio_files'),
+		Document: t('open_document_files')
+	};
+
+	return (
+		<>
+			{Object.entries(kinds).map(([kind, title]) => {
+				const types = fileTypes.filter((type) => type.kind === kind);
+				const registered = types.every((type) => type.registered);
+				const conflicts = [
+					...new Set(types.flatMap((type) => (type.conflict ? [type.conflict] : [])))
+				];
+
+				let description = t('open_file_types_with_spacedrive', {
+					extensions: types.map((type) => type.extension).join(', ')
+				});
+				if (registered && requiresUserConfirmation)
+					description += ` ${t('file_association_requires_confirmation')}`;
+				else if (!registered && conflicts.length > 0)
+					description += ` ${t('file_association_conflict', { apps: conflicts.join(', ') })}`;
+
+				return (
+					<Setting
+						key={kind}
+						mini
+						title={title}
+						description={description}
+					>
+						<Switch
+							size="md"
+							checked={registered}
+							onClick={async () => {
+								const extensions = types.map((type) => type.extension);
+								await (registered
+									? unregisterFileAssociations(extensions)
+									: registerFileAssociations(extensions));
+								associations.refetch();
+							}}
+						/>
+					</Setting>
+				);
+			})}
+			<Setting
+				mini
+				title={t('open_folders_with_spacedrive')}
+				description={t('open_folders_with_spacedrive_description')}
+			>
+				<Switch
+					size="md"
+					checked={folderHandler}
+					onClick={async () => {
+						await setFolderHandler(!folderHandler);
+						associations.refetch();
+					}}
+				/>
+			</Setting>
+		</>
+	);
+}
+
+function AutostartSettings() {
+	const { t } = useLocale();
+	const { getAutostartSettings, setAutostartEnabled, setStartMinimized } = usePlatform();
+
+	const settings = useQuery({
+		queryKey: ['autostartSettings'],
+		queryFn: async () => {
+			const result = await getAutostartSettings?.();
+			return result?.status === 'ok' ? result.data : null;
+		},
+		enabled: !!getAutostartSettings
+	});
+
+	if (!settings.data || !setAutostartEnabled || !setStartMinimized) return null;
+
+	const { enabled, startMinimized } = settings.data;
+
+	return (
+		<>
+			<Setting mini title={t('launch_at_login')} description={t('launch_at_login_description')}>
+				<Switch
+					size="md"
+					checked={enabled}
+					onClick={async () => {
+						await setAutostartEnabled(!enabled);
+						settings.refetch();
+					}}
+				/>
+			</Setting>
+			<Setting
+				mini
+				title={t('start_minimized')}
+				description={t('start_minimized_description')}
+			>
+				<Switch
+					size="md"
+					checked={startMinimized}
+					disabled={!enabled}
+					onClick={async () => {
+						await setStartMinimized(!startMinimized);
+						settings.refetch();
+					}}
+				/>
+			</Setting>
+		</>
+	);
+}
+
+function TerminalSetting() {
+	const { t } = useLocale();
+	const { getTerminalSettings, setPreferredTerminal } = usePlatform();
+
+	const settings = useQuery({
+		queryKey: ['terminalSettings'],
+		queryFn: async () => {
+			const result = await getTerminalSettings?.();
+			return result?.status === 'ok' ? result.data : null;
+		},
+		enabled: !!getTerminalSettings
+	});
+
+	if (!settings.data || !setPreferredTerminal) return null;
+
+	return (
+		<Setting mini title={t('terminal')} description={t('terminal_description')}>
+			<div className="flex h-[30px]">
+				<Select
+					value={settings.data.preferred ?? 'auto'}
+					onChange={async (id) => {
+						await setPreferredTerminal(id === 'auto' ? null : id);
+						settings.refetch();
+					}}
+					containerClassName="h-[30px] whitespace-nowrap"
+				>
+					<SelectOption value="auto">{t('auto')}</SelectOption>
+					{settings.data.available.map((terminal) => (
+						<SelectOption key={terminal.id} value={terminal.id}>
+							{terminal.name}
+						</SelectOption>
+					))}
+				</Select>
+			</div>
+		</Setting>
+	);
+}
+
 export const Component = () => {
 	const node = useBridgeQuery(['nodeState']);
 	const listeners = useBridgeQuery(['p2p.listeners'], {
@@ -56,6 +234,8 @@ export const Component = () => {
 	const connectedPeers = useConnectedPeers();
 	// const image_labeler_versions = useBridgeQuery(['models.image_detection.list']);
 	const updateThumbnailerPreferences = useBridgeMutation('nodes.updateThumbnailerPreferences');
+	const setPreventSleepDuringJobs = useBridgeMutation('nodes.setPreventSleepDuringJobs');
+	const setOfflineMode = useBridgeMutation('nodes.setOfflineMode');
 
 	const locations = useLibraryQuery(['locations.list']);
 
@@ -212,6 +392,36 @@ export const Component = () => {
 					onClick={() => (debugState.enabled = !debugState.enabled)}
 				/>
 			</Setting>
+			{/* Prevent Sleep */}
+			<Setting
+				mini
+				title={t('prevent_sleep_during_jobs')}
+				description={t('prevent_sleep_during_jobs_description')}
+			>
+				<Switch
+					size="md"
+					checked={node.data?.preferences.prevent_sleep_during_jobs ?? true}
+					onClick={() =>
+						setPreventSleepDuringJobs.mutate(
+							!(node.data?.preferences.prevent_sleep_during_jobs ?? true)
+						)
+					}
+				/>
+			</Setting>
+			{/* Offline mode */}
+			<Setting mini title={t('offline_mode')} description={t('offline_mode_description')}>
+				<Switch
+					size="md"
+					checked={node.data?.preferences.offline ?? false}
+					onClick={() => setOfflineMode.mutate(!(node.data?.preferences.offline ?? false))}
+				/>
+			</Setting>
+			{/* Launch at login */}
+			<AutostartSettings />
+			{/* File associations */}
+			<FileAssociationSettings />
+			{/* Terminal */}
+			<TerminalSetting />
 			{/* Background Processing */}
 			<Setting
 				mini
@@ -63,6 +63,7 @@ export const Component = () => {
 					},
 					{ shortcut: 'showInspector', description: t('toggle_inspector') },
 					{ shortcut: 'toggleQuickPreview', description: t('toggle_quick_preview') },
+					{ shortcut: 'quickLook', description: t('open_selection_in_native_preview') },
 					{ shortcut: 'toggleMetaData', description: t('toggle_metadata') },
 					{
 						shortcut: 'quickPreviewMoveBack',
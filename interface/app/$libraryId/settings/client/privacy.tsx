@@ -1,5 +1,11 @@
-import { TelemetryLevelPreference, telemetryState, useTelemetryState } from '@sd/client';
-import { Select, SelectOption } from '@sd/ui';
+import {
+	TelemetryLevelPreference,
+	telemetryState,
+	useBridgeMutation,
+	useBridgeQuery,
+	useTelemetryState
+} from '@sd/client';
+import { Select, SelectOption, Switch } from '@sd/ui';
 import i18n from '~/app/I18n';
 import { useLocale } from '~/hooks';
 
@@ -17,6 +23,10 @@ export const Component = () => {
 
 	const { telemetryLevelPreference } = useTelemetryState();
 
+	const node = useBridgeQuery(['nodeState']);
+	const setTelemetry = useBridgeMutation('nodes.setTelemetry');
+	const diagnosticsEnabled = node.data?.preferences.telemetry ?? false;
+
 	return (
 		<>
 			<Heading title={t('privacy')} description="" />
@@ -43,6 +53,17 @@ export const Component = () => {
 					))}
 				</Select>
 			</Setting>
+			<Setting
+				mini
+				title={t('share_diagnostics')}
+				description={t('share_diagnostics_description')}
+			>
+				<Switch
+					size="md"
+					checked={diagnosticsEnabled}
+					onClick={() => setTelemetry.mutate(!diagnosticsEnabled)}
+				/>
+			</Setting>
 		</>
 	);
 };
@@ -1,11 +1,13 @@
 import { Globe } from '@phosphor-icons/react';
+import { useQuery } from '@tanstack/react-query';
+import { useState } from 'react';
 import { AppLogo } from '@sd/assets/images';
 import { Discord, Github } from '@sd/assets/svgs/brands';
-import { useBridgeQuery, useDebugStateEnabler } from '@sd/client';
+import { humanizeSize, useBridgeQuery, useDebugStateEnabler } from '@sd/client';
 import { Button, Divider } from '@sd/ui';
 import { useLocale } from '~/hooks';
 import { useOperatingSystem } from '~/hooks/useOperatingSystem';
-import { usePlatform } from '~/util/Platform';
+import { SystemInfo, usePlatform } from '~/util/Platform';
 
 export const Component = () => {
 	const buildInfo = useBridgeQuery(['buildInfo']);
@@ -69,6 +71,7 @@ export const Component = () => {
 					{t('website')}
 				</Button>
 			</div>
+			<SystemInformation />
 			<Divider />
 			<div className="my-5">
 				<h1 className="mb-3 font-plex text-lg font-bold text-ink">
@@ -89,3 +92,75 @@ export const Component = () => {
 		</div>
 	);
 };
+
+// Plain text so it can be pasted as is into bug reports
+function formatSystemInfo(info: SystemInfo) {
+	const { os, cpu, memory } = info;
+	const cores = [
+		cpu.physicalCores != null && `${cpu.physicalCores} physical`,
+		`${cpu.logicalCores} logical`
+	]
+		.filter(Boolean)
+		.join(', ');
+
+	return [
+		`Spacedrive: v${info.appVersion}`,
+		`OS: ${os.version ?? os.name ?? 'Unknown'} (${os.arch}, kernel ${os.kernelVersion ?? 'unknown'})`,
+		`CPU: ${cpu.brand ?? 'Unknown'} (${cores} cores)`,
+		`Memory: ${humanizeSize(memory.availableBytes)} free of ${humanizeSize(memory.totalBytes)}`,
+		`GPU: ${info.gpus.length > 0 ? info.gpus.join(', ') : 'Unknown'}`,
+		'Disks:',
+		...info.disks.map(
+			(disk) =>
+				`  ${disk.name} at ${disk.mountPoint}: ${disk.fileSystem} ${disk.diskType}, ${humanizeSize(disk.availableBytes)} free of ${humanizeSize(disk.totalBytes)}${disk.readOnly ? ', read only' : ''}`
+		),
+		'Permissions:',
+		...Object.entries(info.permissions).map(
+			([permission, status]) => `  ${permission}: ${status}`
+		)
+	].join('\n');
+}
+
+function SystemInformation() {
+	const platform = usePlatform();
+	const { t } = useLocale();
+	const [copied, setCopied] = useState(false);
+
+	const systemInfo = useQuery({
+		queryKey: ['systemInfo'],
+		queryFn: async () => {
+			const result = await platform.systemInfo!();
+			if (result.status === 'error') throw new Error(result.error);
+			return formatSystemInfo(result.data);
+		},
+		enabled: platform.systemInfo !== undefined
+	});
+
+	if (!systemInfo.data) return null;
+
+	return (
+		<>
+			<Divider />
+			<div className="my-5">
+				<div className="mb-3 flex items-center justify-between">
+					<h1 className="font-plex text-lg font-bold text-ink">
+						{t('system_information')}
+					</h1>
+					<Button
+						variant="gray"
+						size="sm"
+						onClick={async () => {
+							await navigator.clipboard.writeText(systemInfo.data);
+							setCopied(true);
+						}}
+					>
+						{copied ? t('copied') : t('copy')}
+					</Button>
+				</div>
+				<pre className="w-full select-text whitespace-pre-wrap rounded-md bg-app-box p-3 text-xs text-ink-dull">
+					{systemInfo.data}
+				</pre>
+			</div>
+		</>
+	);
+}
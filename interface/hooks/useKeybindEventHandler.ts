@@ -58,6 +58,12 @@ export const useKeybindEventHandler = (libraryId?: string) => {
 				case 'select_all':
 					console.log('Select All!'); // TODO Implement
 					break;
+				case 'quick_look':
+					document.dispatchEvent(new CustomEvent('quick_look'));
+					break;
+				case 'open_terminal':
+					document.dispatchEvent(new CustomEvent('open_terminal'));
+					break;
 				case 'window_fullscreened':
 					windowState.isFullScreen = true;
 					break;
@@ -89,6 +89,10 @@ const shortcuts = {
 	toggleQuickPreview: {
 		all: [' ']
 	},
+	// Takes over Space from the quick preview where the native preview is available
+	quickLook: {
+		all: [' ']
+	},
 	toggleMetaData: {
 		macOS: ['Meta', 'KeyI'],
 		all: ['Control', 'KeyI']
@@ -12,6 +12,26 @@ export type DragAndDropEvent =
 
 export type Result<T, E> = { status: 'ok'; data: T } | { status: 'error'; error: E };
 export type OpenWithApplication = { url: string; name: string };
+// Byte counts are strings as they can exceed `Number.MAX_SAFE_INTEGER`
+export type SystemInfo = {
+	appVersion: string;
+	os: { name: string | null; version: string | null; kernelVersion: string | null; arch: string };
+	cpu: { brand: string | null; physicalCores: number | null; logicalCores: number };
+	memory: { totalBytes: string; availableBytes: string };
+	gpus: string[];
+	disks: {
+		name: string;
+		mountPoint: string;
+		fileSystem: string;
+		diskType: string;
+		totalBytes: string;
+		availableBytes: string;
+		readOnly: boolean;
+	}[];
+	permissions: Partial<
+		Record<'FullDisk' | 'RemovableVolumes', 'Granted' | 'Denied' | 'Unknown' | 'NotRequired'>
+	>;
+};
 
 // Platform represents the underlying native layer the app is running on.
 // This could be Tauri or web.
@@ -52,7 +72,54 @@ export type Platform = {
 			| { Ephemeral: { path: string } }
 		)[]
 	): Promise<unknown>;
+	// Shows the native preview (e.g. Quick Look on macOS) for the given paths
+	quickLook?(paths: string[]): Promise<Result<null, string>>;
+	// Opens the preferred terminal emulator in the given directory
+	openTerminal?(path: string): Promise<Result<null, string>>;
+	getTerminalSettings?(): Promise<
+		Result<{ available: { id: string; name: string }[]; preferred: string | null }, string>
+	>;
+	setPreferredTerminal?(id: string | null): Promise<Result<null, string>>;
+	getFileAssociations?(): Promise<
+		Result<
+			{
+				fileTypes: {
+					extension: string;
+					kind: 'Image' | 'Video' | 'Audio' | 'Document';
+					registered: boolean;
+					isDefault: boolean;
+					conflict: string | null;
+				}[];
+				folderHandler: boolean;
+				requiresUserConfirmation: boolean;
+			},
+			string
+		>
+	>;
+	registerFileAssociations?(extensions: string[]): Promise<Result<null, string>>;
+	unregisterFileAssociations?(extensions: string[]): Promise<Result<null, string>>;
+	setFolderHandler?(enabled: boolean): Promise<Result<null, string>>;
+	getAutostartSettings?(): Promise<Result<{ enabled: boolean; startMinimized: boolean }, string>>;
+	setAutostartEnabled?(enabled: boolean): Promise<Result<null, string>>;
+	setStartMinimized?(startMinimized: boolean): Promise<Result<null, string>>;
+	// The app language, `preferred` is `null` when following the OS
+	getLocaleSettings?(): Promise<
+		Result<
+			{ system: string | null; preferred: string | null; current: string; available: string[] },
+			null
+		>
+	>;
+	setLocale?(preferred: string | null): Promise<unknown>;
+	// Hardware and environment details for the About panel and bug reports
+	systemInfo?(): Promise<Result<SystemInfo, string>>;
 	requestFdaMacos?(): void;
+	writeFilesToClipboard?(
+		paths: string[],
+		operation: ClipboardOperation
+	): Promise<Result<null, string>>;
+	readFilesFromClipboard?(): Promise<
+		Result<{ operation: ClipboardOperation; paths: string[] } | null, string>
+	>;
 	getFilePathOpenWithApps?(
 		library: string,
 		ids: number[]
@@ -75,13 +142,18 @@ export type Platform = {
 	landingApiOrigin: string;
 };
 
-export type Update = { version: string };
+export type ClipboardOperation = 'Copy' | 'Cut';
+
+export type Update = { version: string; changelog: string | null; date: string | null };
 export type UpdateStore =
 	| { status: 'idle' }
 	| { status: 'loading' }
 	| { status: 'error' }
 	| { status: 'updateAvailable'; update: Update }
 	| { status: 'noUpdateAvailable' }
+	| { status: 'downloading'; downloaded: number; total: number | null }
+	| { status: 'downloaded'; update: Update }
+	| { status: 'installScheduled'; update: Update }
 	| { status: 'installing' };
 
 // Keep this private and use through helpers below
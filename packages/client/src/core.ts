@@ -3,7 +3,9 @@
 
 export type Procedures = {
     queries: 
+        { key: "backups.config", input: never, result: BackupsConfig } | 
         { key: "backups.getAll", input: never, result: GetAll } | 
+        { key: "backups.list", input: LibraryArgs<null>, result: Backup[] } | 
         { key: "buildInfo", input: never, result: BuildInfo } | 
         { key: "cloud.devices.get", input: CloudDevicePubId, result: CloudDevice } | 
         { key: "cloud.devices.get_current_device", input: never, result: CloudDevice } | 
@@ -16,8 +18,11 @@ export type Procedures = {
         { key: "cloud.syncGroups.leave", input: CloudSyncGroupPubId, result: null } | 
         { key: "cloud.syncGroups.list", input: never, result: CloudSyncGroupBaseData[] } | 
         { key: "cloud.syncGroups.remove_device", input: CloudSyncGroupsRemoveDeviceArgs, result: null } | 
+        { key: "coreHealth", input: never, result: CoreHealth } | 
+        { key: "deviceCapabilities", input: never, result: DeviceCapabilities } | 
         { key: "devices.list", input: LibraryArgs<null>, result: Device[] } | 
         { key: "ephemeralFiles.getMediaData", input: string, result: MediaData | null } | 
+        { key: "featureFlags", input: never, result: FeatureFlagState[] } | 
         { key: "files.get", input: LibraryArgs<number>, result: ObjectWithFilePaths2 | null } | 
         { key: "files.getConvertibleImageExtensions", input: never, result: string[] } | 
         { key: "files.getMediaData", input: LibraryArgs<number>, result: MediaData } | 
@@ -42,7 +47,9 @@ export type Procedures = {
         { key: "locations.indexer_rules.list", input: LibraryArgs<null>, result: IndexerRule[] } | 
         { key: "locations.indexer_rules.listForLocation", input: LibraryArgs<number>, result: IndexerRule[] } | 
         { key: "locations.list", input: LibraryArgs<null>, result: Location[] } | 
+        { key: "locations.sync_rules.list", input: LibraryArgs<number>, result: SyncRule[] } | 
         { key: "locations.systemLocations", input: never, result: SystemLocations } | 
+        { key: "logs.filters", input: never, result: LogFilters } | 
         { key: "models.image_detection.list", input: never, result: string[] } | 
         { key: "nodeState", input: never, result: NodeState } | 
         { key: "nodes.listLocations", input: LibraryArgs<string | null>, result: ExplorerItem[] } | 
@@ -58,18 +65,38 @@ export type Procedures = {
         { key: "search.pathsCount", input: LibraryArgs<{ filters?: SearchFilterArgs[] }>, result: number } | 
         { key: "search.saved.get", input: LibraryArgs<number>, result: SavedSearch | null } | 
         { key: "search.saved.list", input: LibraryArgs<null>, result: SavedSearch[] } | 
+        { key: "sync.compaction", input: LibraryArgs<null>, result: CompactionStats } | 
+        { key: "sync.conflicts.list", input: LibraryArgs<null>, result: SyncConflict[] } | 
         { key: "sync.enabled", input: LibraryArgs<null>, result: boolean } | 
+        { key: "sync.models.list", input: LibraryArgs<null>, result: ModelToggle[] } | 
+        { key: "sync.outbox", input: LibraryArgs<null>, result: OutboxDepth[] } | 
+        { key: "sync.preview", input: LibraryArgs<null>, result: SyncPreview } | 
         { key: "tags.get", input: LibraryArgs<number>, result: Tag | null } | 
         { key: "tags.getForObject", input: LibraryArgs<number>, result: Tag[] } | 
         { key: "tags.getWithObjects", input: LibraryArgs<number[]>, result: { [key in number]: ({ object: { id: number }; date_created: string | null })[] } } | 
         { key: "tags.list", input: LibraryArgs<null>, result: Tag[] } | 
+        { key: "volumes.capacityForecast", input: LibraryArgs<CapacityForecastArgs>, result: CapacityForecast } | 
+        { key: "volumes.degradedMode", input: never, result: DegradedMode } | 
+        { key: "volumes.discoverShareServers", input: never, result: ShareServer[] } | 
+        { key: "volumes.history", input: LibraryArgs<VolumeHistoryArgs>, result: VolumeHistoryEntry[] } | 
         { key: "volumes.list", input: LibraryArgs<null>, result: Volume[] } | 
-        { key: "volumes.listForLibrary", input: LibraryArgs<null>, result: Volume[] },
+        { key: "volumes.listForLibrary", input: LibraryArgs<null>, result: Volume[] } | 
+        { key: "volumes.listHidden", input: LibraryArgs<null>, result: Volume[] } | 
+        { key: "volumes.listIgnoredPaths", input: never, result: string[] } | 
+        { key: "volumes.listNetworkShares", input: ListNetworkSharesArgs, result: DiscoveredShare[] } | 
+        { key: "volumes.listRemote", input: LibraryArgs<null>, result: RemoteVolume[] } | 
+        { key: "volumes.listSnapshots", input: VolumeFingerprint, result: FileSystemSnapshot[] } | 
+        { key: "volumes.listUnmounted", input: never, result: UnmountedDevice[] } | 
+        { key: "volumes.settings", input: VolumeFingerprint, result: VolumeSettings } | 
+        { key: "volumes.snapshot", input: never, result: VolumeSnapshot } | 
+        { key: "volumes.spaceBreakdown", input: LibraryArgs<SpaceBreakdownArgs>, result: SpaceBreakdown } | 
+        { key: "volumes.watcherConfig", input: never, result: VolumeWatcherConfig },
     mutations: 
         { key: "api.sendFeedback", input: Feedback, result: null } | 
         { key: "backups.backup", input: LibraryArgs<null>, result: string } | 
         { key: "backups.delete", input: string, result: null } | 
-        { key: "backups.restore", input: string, result: null } | 
+        { key: "backups.restore", input: RestoreBackupArgs, result: null } | 
+        { key: "backups.updateConfig", input: BackupsConfig, result: null } | 
         { key: "cloud.bootstrap", input: LibraryArgs<[AccessToken, RefreshToken]>, result: null } | 
         { key: "cloud.devices.delete", input: CloudDevicePubId, result: null } | 
         { key: "cloud.devices.update", input: CloudUpdateDeviceArgs, result: null } | 
@@ -83,6 +110,7 @@ export type Procedures = {
         { key: "cloud.syncGroups.request_join", input: SyncGroupsRequestJoinArgs, result: null } | 
         { key: "cloud.thumbnails.get", input: CloudThumbnailRequestArgs, result: null } | 
         { key: "cloud.userResponse", input: CloudP2PUserResponse, result: null } | 
+        { key: "devices.revoke", input: LibraryArgs<RevokeDeviceArgs>, result: null } | 
         { key: "ephemeralFiles.copyFiles", input: LibraryArgs<EphemeralFileSystemOps>, result: null } | 
         { key: "ephemeralFiles.createFile", input: LibraryArgs<CreateEphemeralFileArgs>, result: string } | 
         { key: "ephemeralFiles.createFolder", input: LibraryArgs<CreateEphemeralFolderArgs>, result: string } | 
@@ -112,13 +140,19 @@ export type Procedures = {
         { key: "jobs.objectValidator", input: LibraryArgs<ObjectValidatorArgs>, result: null } | 
         { key: "jobs.pause", input: LibraryArgs<string>, result: null } | 
         { key: "jobs.resume", input: LibraryArgs<string>, result: null } | 
+        { key: "keys.deleteSecret", input: string, result: null } | 
         { key: "keys.save", input: string, result: null } | 
         { key: "keys.saveEmailAddress", input: LibraryArgs<string>, result: null } | 
+        { key: "keys.setSecret", input: SetSecretArgs, result: null } | 
         { key: "labels.delete", input: LibraryArgs<number>, result: null } | 
         { key: "library.create", input: CreateLibraryArgs, result: LibraryConfigWrapped } | 
         { key: "library.delete", input: string, result: null } | 
         { key: "library.edit", input: EditLibraryArgs, result: null } | 
+        { key: "library.export", input: LibraryArgs<ExportLibraryArgs>, result: ArchiveManifest } | 
+        { key: "library.import", input: ImportLibraryArgs, result: LibraryConfigWrapped } | 
+        { key: "library.makeWritable", input: string, result: null } | 
         { key: "library.vacuumDb", input: LibraryArgs<null>, result: null } | 
+        { key: "library.verify", input: LibraryArgs<VerifyArgs>, result: VerifyReport } | 
         { key: "locations.addLibrary", input: LibraryArgs<LocationCreateArgs>, result: number | null } | 
         { key: "locations.create", input: LibraryArgs<LocationCreateArgs>, result: number | null } | 
         { key: "locations.delete", input: LibraryArgs<number>, result: null } | 
@@ -127,26 +161,55 @@ export type Procedures = {
         { key: "locations.indexer_rules.delete", input: LibraryArgs<number>, result: null } | 
         { key: "locations.relink", input: LibraryArgs<string>, result: number } | 
         { key: "locations.subPathRescan", input: LibraryArgs<RescanArgs>, result: string | null } | 
+        { key: "locations.sync_rules.delete", input: LibraryArgs<number>, result: null } | 
+        { key: "locations.sync_rules.set", input: LibraryArgs<SyncRuleSetArgs>, result: null } | 
         { key: "locations.update", input: LibraryArgs<LocationUpdateArgs>, result: null } | 
+        { key: "logs.capture", input: CaptureLogsArgs, result: string } | 
+        { key: "logs.setLevel", input: SetLogLevelArgs, result: null } | 
         { key: "nodes.edit", input: ChangeNodeNameArgs, result: null } | 
+        { key: "nodes.setOfflineMode", input: boolean, result: null } | 
+        { key: "nodes.setPreventSleepDuringJobs", input: boolean, result: null } | 
+        { key: "nodes.setTelemetry", input: boolean, result: null } | 
         { key: "nodes.updateThumbnailerPreferences", input: UpdateThumbnailerPreferences, result: null } | 
         { key: "p2p.acceptSpacedrop", input: [string, string | null], result: null } | 
         { key: "p2p.cancelSpacedrop", input: string, result: null } | 
         { key: "p2p.debugConnect", input: RemoteIdentity, result: string } | 
+        { key: "p2p.setTransferBandwidth", input: SetTransferBandwidthArgs, result: null } | 
         { key: "p2p.spacedrop", input: SpacedropArgs, result: string } | 
         { key: "preferences.update", input: LibraryArgs<LibraryPreferences>, result: null } | 
         { key: "search.saved.create", input: LibraryArgs<{ name: string; target?: SearchTarget; search?: string | null; filters?: string | null; description?: string | null; icon?: string | null }>, result: null } | 
         { key: "search.saved.delete", input: LibraryArgs<number>, result: null } | 
         { key: "search.saved.update", input: LibraryArgs<[number, Args]>, result: null } | 
         { key: "sync.backfill", input: LibraryArgs<null>, result: null } | 
+        { key: "sync.backfill.pause", input: LibraryArgs<null>, result: null } | 
+        { key: "sync.backfill.resume", input: LibraryArgs<null>, result: null } | 
+        { key: "sync.compact", input: LibraryArgs<null>, result: CompactionReport } | 
+        { key: "sync.conflicts.resolve", input: LibraryArgs<ResolveConflictArgs>, result: null } | 
+        { key: "sync.models.set", input: LibraryArgs<SetModelSyncArgs>, result: null } | 
         { key: "tags.assign", input: LibraryArgs<{ targets: Target[]; tag_id: number; unassign: boolean }>, result: null } | 
         { key: "tags.create", input: LibraryArgs<TagCreateArgs>, result: Tag } | 
         { key: "tags.delete", input: LibraryArgs<number>, result: null } | 
         { key: "tags.update", input: LibraryArgs<TagUpdateArgs>, result: null } | 
         { key: "toggleFeatureFlag", input: BackendFeature, result: null } | 
+        { key: "volumes.attachDiskImage", input: AttachDiskImageArgs, result: Volume } | 
+        { key: "volumes.checkHealth", input: LibraryArgs<VolumeFingerprint>, result: VolumeHealth } | 
+        { key: "volumes.computeSpaceBreakdown", input: LibraryArgs<VolumeFingerprint>, result: null } | 
+        { key: "volumes.eject", input: LibraryArgs<VolumeOperationArgs>, result: OperationPlan } | 
+        { key: "volumes.format", input: LibraryArgs<FormatVolumeArgs>, result: OperationPlan } | 
+        { key: "volumes.ignorePath", input: string, result: null } | 
+        { key: "volumes.mount", input: MountVolumeArgs, result: Volume } | 
+        { key: "volumes.mountNetworkShare", input: MountNetworkShareArgs, result: Volume } | 
+        { key: "volumes.mountSnapshot", input: SnapshotArgs, result: string } | 
+        { key: "volumes.speedTest", input: LibraryArgs<VolumeFingerprint>, result: Volume } | 
         { key: "volumes.track", input: LibraryArgs<VolumeFingerprint>, result: null } | 
-        { key: "volumes.unmount", input: LibraryArgs<number[]>, result: null },
+        { key: "volumes.unignorePath", input: string, result: null } | 
+        { key: "volumes.unmount", input: LibraryArgs<VolumeOperationArgs>, result: OperationPlan } | 
+        { key: "volumes.unmountSnapshot", input: SnapshotArgs, result: null } | 
+        { key: "volumes.untrack", input: LibraryArgs<VolumeFingerprint>, result: null } | 
+        { key: "volumes.updateSettings", input: LibraryArgs<UpdateVolumeSettingsArgs>, result: null } | 
+        { key: "volumes.updateWatcherConfig", input: VolumeWatcherConfig, result: null },
     subscriptions: 
+        { key: "backups.restoreProgress", input: never, result: RestoreProgress } | 
         { key: "cloud.listenCloudServicesNotifications", input: never, result: CloudP2PNotifyUser } | 
         { key: "invalidation.listen", input: never, result: InvalidateOperationEvent[] } | 
         { key: "jobs.newFilePathIdentified", input: LibraryArgs<null>, result: number[] } | 
@@ -160,7 +223,9 @@ export type Procedures = {
         { key: "p2p.events", input: never, result: P2PEvent } | 
         { key: "search.ephemeralPaths", input: LibraryArgs<EphemeralPathSearchArgs>, result: { entries: ExplorerItem[]; errors: Error[] } } | 
         { key: "sync.active", input: LibraryArgs<null>, result: SyncStatus } | 
-        { key: "volumes.events", input: LibraryArgs<null>, result: VolumeEvent }
+        { key: "sync.backfill.progress", input: LibraryArgs<null>, result: BackfillProgress } | 
+        { key: "volumes.events", input: LibraryArgs<null>, result: VolumeEvent } | 
+        { key: "volumes.scan", input: never, result: ScanProgress }
 };
 
 /**
@@ -168,27 +233,121 @@ export type Procedures = {
  */
 export type AccessToken = string
 
+export type ArchiveEntry = { name: string; size: bigint; 
+/**
+ * The blake3 hash of the entry, in hex
+ */
+checksum: string }
+
+export type ArchiveManifest = { version: number; library_id: string; library_name: string; exported_at: string; entries: ArchiveEntry[] }
+
 export type Args = { search?: string | null; filters?: string | null; name?: string | null; icon?: string | null; description?: string | null }
 
+export type AttachDiskImageArgs = { path: string; read_only?: boolean }
+
 export type AudioProps = { delay: number; padding: number; sample_rate: number | null; sample_format: string | null; bit_per_sample: number | null; channel_layout: string | null }
 
 /**
  * All of the feature flags provided by the core itself. The frontend has it's own set of feature flags!
  * 
  * If you want a variant of this to show up on the frontend it must be added to `backendFeatures` in `useFeatureFlag.tsx`
+ * 
+ * Subsystems check them with [`Node::feature_flags`](crate::Node::feature_flags).
+ */
+export type BackendFeature = 
+/**
+ * Start tracking volumes in the library as soon as they are plugged in
+ */
+"autoTracking" | 
+/**
+ * Poll S.M.A.R.T. data of the drives backing the volumes
+ */
+"smartMonitoring" | 
+/**
+ * Browse the volumes of other devices over p2p
+ */
+"remoteVolumeBrowsing"
+
+export type BackfillProgress = { running: boolean; paused: boolean; 
+/**
+ * In the order they're backfilled
+ */
+models: ModelProgress[]; 
+/**
+ * From how fast the records were backfilled so far, unknown until some are
+ */
+estimated_completion: string | null }
+
+export type Backup = ({ id: string; timestamp: string; library_id: string; library_name: string; 
+/**
+ * Made on the schedule, only these are rotated
+ */
+scheduled: boolean; 
+/**
+ * The blake3 hash of the archive after the header in hex, `null` for the older backups
+ */
+checksum: string | null }) & { path: string }
+
+export type BackupSchedule = "off" | "daily" | "weekly"
+
+export type BackupsConfig = { 
+/**
+ * How often every library is backed up
+ */
+schedule: BackupSchedule; 
+/**
+ * Scheduled backups kept for each library, the oldest are removed past this. The ones made by
+ * hand aren't counted nor removed.
  */
-export type BackendFeature = never
+retention: number }
 
-export type Backup = ({ id: string; timestamp: string; library_id: string; library_name: string }) & { path: string }
+/**
+ * Upload and download limits in KB/s, unlimited when unset
+ */
+export type BandwidthLimit = { upload_kbps?: number | null; download_kbps?: number | null }
 
 export type BasicLibraryCreationArgs = { id: CloudLibraryPubId; name: string; description: string | null }
 
 export type BuildInfo = { version: string; commit: string }
 
+/**
+ * A process with files open on the volume, or running from it
+ */
+export type BusyProcess = { pid: number; name: string }
+
 export type CameraData = { device_make: string | null; device_model: string | null; color_space: string | null; color_profile: ColorProfile | null; focal_length: number | null; shutter_speed: number | null; flash: Flash | null; orientation: Orientation; lens_make: string | null; lens_model: string | null; bit_depth: number | null; zoom: number | null; iso: number | null; software: string | null; serial_number: string | null; lens_serial_number: string | null; contrast: number | null; saturation: number | null; sharpness: number | null; composite: Composite | null }
 
+export type CapacityForecast = { 
+/**
+ * The usage records the trend was fitted on
+ */
+samples: number; 
+/**
+ * When the oldest of them was recorded
+ */
+since: string | null; 
+/**
+ * How fast the used space grows, negative while the volume is freed up. `None` without
+ * enough records for a trend.
+ */
+bytes_per_day: number | null; 
+/**
+ * The free space at the latest record
+ */
+total_bytes_available: string | null; 
+/**
+ * `None` when the volume doesn't fill up at the current trend, or there is no trend
+ */
+days_until_full: number | null; full_at: string | null }
+
+export type CapacityForecastArgs = { fingerprint: VolumeFingerprint }
+
+export type CaptureLogsArgs = { module: string; seconds: number }
+
 export type CasId = string
 
+export type CategoryUsage = { category: SpaceCategory; usage: SpaceUsage }
+
 export type ChangeNodeNameArgs = { name: string | null; p2p_port: Port | null; p2p_disabled: boolean | null; p2p_ipv6_disabled: boolean | null; p2p_relay_disabled: boolean | null; p2p_discovery: P2PDiscoveryState | null; p2p_remote_access: boolean | null; p2p_manual_peers: string[] | null }
 
 export type Chapter = { id: number; start: [number, number]; end: [number, number]; time_base_den: number; time_base_num: number; metadata: Metadata }
@@ -245,6 +404,23 @@ export type Codec = { kind: string | null; sub_kind: string | null; tag: string
 
 export type ColorProfile = "Normal" | "Custom" | "HDRNoOriginal" | "HDRWithOriginal" | "OriginalForHDR" | "Panorama" | "PortraitHDR" | "Portrait"
 
+export type CompactionReport = { removed: number; 
+/**
+ * What the removed operations took in the database, the file itself only shrinks once SQLite
+ * reuses or vacuums their pages
+ */
+reclaimed_bytes: bigint; 
+/**
+ * The operations from before this were looked at, the start of the retention window unless the
+ * other devices acknowledged less
+ */
+cutoff: string; finished_at: string }
+
+/**
+ * Since the library was opened
+ */
+export type CompactionStats = { runs: number; removed: bigint; reclaimed_bytes: bigint; last_run: CompactionReport | null }
+
 export type Composite = 
 /**
  * The data is present, but we're unable to determine what they mean
@@ -263,6 +439,21 @@ export type Composite =
  */
 "Live"
 
+export type ConflictResolution = 
+/**
+ * The value of this device stays, it's the one the record already has
+ */
+"keep_mine" | 
+/**
+ * The value of the other device replaces it on every device
+ */
+"keep_theirs" | 
+/**
+ * The record keeps the value of this device, and a copy of it named after the other device
+ * gets theirs
+ */
+"keep_both"
+
 /**
  * The method used for the connection with this peer.
  * *Technically* you can have multiple under the hood but this simplifies things for the UX.
@@ -277,6 +468,12 @@ export type CoreDevicePubId = CorePubId
 
 export type CoreHardwareModel = "Other" | "MacStudio" | "MacBookAir" | "MacBookPro" | "MacBook" | "MacMini" | "MacPro" | "IMac" | "IMacPro" | "IPad" | "IPhone" | "Simulator" | "Android"
 
+export type CoreHealth = { 
+/**
+ * The worst severity of the subsystems
+ */
+severity: Severity; checked_at: string; watcher: WatcherHealth; volumes: VolumesHealth; sync: SyncHealth; jobs: JobsHealth }
+
 export type CorePubId = { Uuid: string } | { Vec: number[] }
 
 export type CreateEphemeralFileArgs = { path: string; context: EphemeralFileCreateContextTypes; name: string | null }
@@ -293,10 +490,64 @@ export type CursorOrderItem<T> = { order: SortOrder; data: T }
 
 export type DefaultLocations = { desktop: boolean; documents: boolean; downloads: boolean; pictures: boolean; music: boolean; videos: boolean }
 
-export type Device = { id: number; pub_id: CoreDevicePubId; name: string; os: DeviceOS; hardware_model: CoreHardwareModel; date_created: string; is_current_device: boolean }
+/**
+ * What the volumes were listed without, empty when nothing is missing
+ */
+export type DegradedMode = { missing: MissingVolumeData[] }
+
+export type Device = { id: number; pub_id: CoreDevicePubId; name: string; os: DeviceOS; hardware_model: CoreHardwareModel; date_created: string; 
+/**
+ * Its operations are rejected since then
+ */
+date_revoked: string | null; is_current_device: boolean }
+
+/**
+ * What this device can do with its volumes, so the frontend hides the actions that would fail
+ * instead of reporting the failure once clicked
+ */
+export type DeviceCapabilities = { 
+/**
+ * The OS reports volume changes, without it they are found by the periodic checks
+ */
+native_watch: boolean; 
+/**
+ * The health data of the drives can be read, like their SMART attributes
+ */
+smart: boolean; 
+/**
+ * The operations the user isn't allowed to do can ask for their password instead of failing
+ */
+elevation: boolean; mount: boolean; unmount: boolean; eject: boolean; format: boolean; 
+/**
+ * Network shares, like SMB or NFS ones, can be mounted
+ */
+network_shares: boolean; 
+/**
+ * Disk images, like ISO or DMG files, can be attached as volumes
+ */
+disk_images: boolean }
 
 export type DeviceOS = "Linux" | "Windows" | "MacOS" | "iOS" | "Android"
 
+export type DirectoryUsage = { 
+/**
+ * Relative to the mount point, empty for the files right in it
+ */
+directory: string; usage: SpaceUsage; categories: CategoryUsage[] }
+
+/**
+ * A share a server offers, its URL is the one to mount it with
+ */
+export type DiscoveredShare = { protocol: ShareProtocol; 
+/**
+ * The SMB share or the NFS export, as the server names it
+ */
+name: string; url: string; 
+/**
+ * The comment of SMB shares, the clients allowed to mount NFS exports
+ */
+comment: string | null }
+
 /**
  * The method used for the discovery of this peer.
  * *Technically* you can have multiple under the hood but this simplifies things for the UX.
@@ -315,6 +566,10 @@ export type DiskType =
  * Hard Disk Drive
  */
 "HDD" | 
+/**
+ * Apple Fusion Drive, an SSD and a hard disk merged into one volume
+ */
+"Fusion" | 
 /**
  * Unknown or virtual disk type
  */
@@ -324,6 +579,23 @@ export type DoubleClickAction = "openFile" | "quickPreview"
 
 export type EditLibraryArgs = { id: string; name: LibraryName | null; description: MaybeUndefined<string> }
 
+/**
+ * The full disk encryption a volume is protected with
+ */
+export type EncryptionKind = 
+/**
+ * Linux Unified Key Setup, opened through dm-crypt
+ */
+"Luks" | 
+/**
+ * Windows BitLocker, or BitLocker To Go on removable drives
+ */
+"BitLocker" | 
+/**
+ * FileVault, or the APFS encryption it's built on for the volumes it doesn't manage
+ */
+"FileVault"
+
 export type EphemeralFileCreateContextTypes = "empty" | "text"
 
 export type EphemeralFileSystemOps = { sources: string[]; target_dir: string }
@@ -357,8 +629,20 @@ export type ExplorerLayout = "grid" | "list" | "media"
 
 export type ExplorerSettings<TOrder> = { layoutMode: ExplorerLayout | null; gridItemSize: number | null; gridGap: number | null; mediaColumns: number | null; mediaAspectSquare: boolean | null; mediaViewWithDescendants: boolean | null; openOnDoubleClick: DoubleClickAction | null; showBytesInGridView: boolean | null; colVisibility: { [key in string]: boolean } | null; colSizes: { [key in string]: number } | null; listViewIconSize: string | null; listViewTextSize: string | null; order?: TOrder | null; showHiddenFiles?: boolean }
 
+export type ExportLibraryArgs = { 
+/**
+ * Where the archive is written, a `.sdlibrary` file
+ */
+path: string }
+
 export type FFmpegMetadata = { formats: string[]; duration: [number, number] | null; start_time: [number, number] | null; bit_rate: [number, number]; chapters: Chapter[]; programs: Program[]; metadata: Metadata }
 
+export type FeatureFlagState = { feature: BackendFeature; enabled: boolean; 
+/**
+ * Forced by `SD_FEATURES`, toggling the flag has no effect until the override is removed
+ */
+overridden: boolean }
+
 export type Feedback = { message: string; emoji: number }
 
 export type FfmpegMediaAudioProps = { id: number; delay: number; padding: number; sample_rate: number | null; sample_format: string | null; bit_per_sample: number | null; channel_layout: string | null; codec_id: number }
@@ -369,7 +653,7 @@ export type FfmpegMediaVideoProps = { id: number; pixel_format: string | null; c
 
 export type FileCreateContextTypes = "empty" | "text"
 
-export type FilePath = { id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null; device_id: number | null }
+export type FilePath = { id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null; sync_mode: number | null; device_id: number | null }
 
 export type FilePathCursor = { isDir: boolean; variant: FilePathCursorVariant }
 
@@ -377,7 +661,7 @@ export type FilePathCursorVariant = "none" | { name: CursorOrderItem<string> } |
 
 export type FilePathFilterArgs = { locations: InOrNotIn<number> } | { path: { location_id: number; path: string; include_descendants: boolean } } | { name: TextMatch } | { extension: InOrNotIn<string> } | { createdAt: Range<string> } | { modifiedAt: Range<string> } | { indexedAt: Range<string> } | { hidden: boolean }
 
-export type FilePathForFrontend = { id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; object: { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; tags: ({ object_id: number; tag_id: number; tag: Tag; date_created: string | null; device_id: number | null })[]; exif_data: { resolution: number[] | null; media_date: number[] | null; media_location: number[] | null; camera_data: number[] | null; artist: string | null; description: string | null; copyright: string | null; exif_version: string | null } | null; device_id: number | null } | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null; device_id: number | null }
+export type FilePathForFrontend = { id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; object: { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; tags: ({ object_id: number; tag_id: number; tag: Tag; date_created: string | null; device_id: number | null })[]; exif_data: { resolution: number[] | null; media_date: number[] | null; media_location: number[] | null; camera_data: number[] | null; artist: string | null; description: string | null; copyright: string | null; exif_version: string | null } | null; device_id: number | null } | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null; sync_mode: number | null; device_id: number | null }
 
 export type FilePathObjectCursor = { dateAccessed: CursorOrderItem<string> } | { kind: CursorOrderItem<number> }
 
@@ -385,6 +669,12 @@ export type FilePathOrder = { field: "name"; value: SortOrder } | { field: "size
 
 export type FilePathSearchArgs = { take?: number | null; orderAndPagination?: OrderAndPagination<number, FilePathOrder, FilePathCursor> | null; filters?: SearchFilterArgs[]; groupDirectories?: boolean }
 
+export type FilePathsPreview = { mode: SyncMode; count: number; 
+/**
+ * Of the files, directories left out as their size is the one of what's in them
+ */
+bytes: bigint }
+
 /**
  * Represents the filesystem type of the volume
  */
@@ -405,6 +695,10 @@ export type FileSystem =
  * Apple APFS filesystem
  */
 "APFS" | 
+/**
+ * Apple HFS+ filesystem, or Mac OS Extended
+ */
+"HFSPlus" | 
 /**
  * ExFAT filesystem
  */
@@ -414,6 +708,20 @@ export type FileSystem =
  */
 { Other: string }
 
+export type FileSystemSnapshot = { 
+/**
+ * What the snapshot is mounted by, the subvolume ID on Btrfs and its name on APFS
+ */
+id: string; name: string; 
+/**
+ * Unknown for the APFS snapshots not taken by Time Machine
+ */
+created_at: string | null; 
+/**
+ * Where it can be browsed without mounting it
+ */
+path: string | null }
+
 export type Flash = { 
 /**
  * Specifies how flash was used (on, auto, off, forced, onvalid)
@@ -459,6 +767,14 @@ export type FlashMode =
  */
 "Forced"
 
+export type ForeignKeyViolation = { table: string; 
+/**
+ * The table the rows point at
+ */
+parent: string; rows: number }
+
+export type FormatVolumeArgs = { fingerprint: VolumeFingerprint; file_system: FileSystem; dry_run?: boolean }
+
 export type FromPattern = { pattern: string; replace_all: boolean }
 
 export type FullRescanArgs = { location_id: number; reidentify_objects: boolean }
@@ -469,8 +785,36 @@ export type GetAll = { backups: Backup[]; directory: string }
 
 export type HardwareModel = "Other" | "MacStudio" | "MacBookAir" | "MacBookPro" | "MacBook" | "MacMini" | "MacPro" | "IMac" | "IMacPro" | "IPad" | "IPhone" | "Simulator" | "Android"
 
+/**
+ * What the drive says about itself, from most to least worrying
+ */
+export type HealthStatus = 
+/**
+ * The drive failed its self-assessment, its data should be copied off it
+ */
+"failing" | 
+/**
+ * The drive passes but some attributes are wearing, like remapped sectors
+ */
+"warning" | "healthy" | 
+/**
+ * The drive doesn't report its health, like most USB enclosures
+ */
+"unknown"
+
+/**
+ * The records to return, both ends included. An open end goes as far as the records do.
+ */
+export type HistoryRange = { from: string | null; to: string | null }
+
 export type IdentifyUniqueFilesArgs = { id: number; path: string }
 
+export type ImportLibraryArgs = { path: string; 
+/**
+ * Opens it as a guest library, to be looked through without changing it
+ */
+read_only?: boolean }
+
 export type InOrNotIn<T> = { in: T[] } | { notIn: T[] }
 
 export type IndexerRule = { id: number; pub_id: number[]; name: string | null; default: boolean | null; rules_per_kind: number[] | null; date_created: string | null; date_modified: string | null }
@@ -491,16 +835,23 @@ export type InvalidateOperationEvent = { type: "single"; data: SingleInvalidateO
 
 export type JobGroup = { id: string; running_job_id: string | null; action: string | null; status: Status; created_at: string; jobs: Report[] }
 
-export type JobName = "Indexer" | "FileIdentifier" | "MediaProcessor" | "Copy" | "Move" | "Delete" | "Erase" | "FileValidator"
+export type JobName = "Indexer" | "FileIdentifier" | "MediaProcessor" | "Copy" | "Move" | "Delete" | "Erase" | "FileValidator" | "VolumeMaintenance" | "VolumeBreakdown"
 
 export type JobProgressEvent = { id: string; library_id: string; task_count: number; completed_task_count: number; phase: string; message: string; info: string; estimated_completion: string }
 
+/**
+ * A warning when more than [`JOB_BACKLOG_WARNING`] jobs wait for a worker
+ */
+export type JobsHealth = { severity: Severity; running: number; queued: number }
+
 export type JoinSyncGroupError = "Communication" | "InternalServer" | "Auth"
 
 export type JoinSyncGroupResponse = { Accepted: { authorizor_device: CloudDevice } } | { Failed: CloudP2PError } | "CriticalError"
 
 export type JsonValue = null | boolean | number | string | JsonValue[] | { [key in string]: JsonValue }
 
+export type KindBreakdown = { kind: number; name: string; files: bigint; bytes: bigint }
+
 export type KindStatistic = { kind: number; name: string; count: [number, number]; total_bytes: [number, number] }
 
 export type KindStatistics = { statistics: { [key in number]: KindStatistic }; total_identified_files: number; total_unidentified_files: number }
@@ -514,6 +865,28 @@ export type LabelWithObjects = { id: number; name: string; date_created: string
  */
 export type LibraryArgs<T> = { library_id: string; arg: T }
 
+export type LibraryBreakdown = { total_files: bigint; total_bytes: bigint; 
+/**
+ * Of the first file of each object and of the files without one
+ */
+unique_bytes: bigint; 
+/**
+ * Of the files after the first of each object
+ */
+duplicate_bytes: bigint; 
+/**
+ * The kinds with files, the unidentified ones as `Unknown`
+ */
+kinds: KindBreakdown[]; locations: LocationBreakdown[]; 
+/**
+ * The tracked volumes with a location, each location counted on the innermost one
+ */
+volumes: VolumeBreakdown[]; 
+/**
+ * The thumbnails generated for the library
+ */
+thumbnails_bytes: bigint; computed_at: string }
+
 /**
  * LibraryConfig holds the configuration for a specific library. This is stored as a '{uuid}.sdlibrary' file.
  */
@@ -534,7 +907,12 @@ instance_id: number;
  * cloud_id is the ID of the cloud library this library is linked to.
  * If this is set we can assume the library is synced with the Cloud.
  */
-cloud_id?: string | null; generate_sync_operations?: boolean; version: LibraryConfigVersion; 
+cloud_id?: string | null; generate_sync_operations?: boolean; sync?: SyncConfig; 
+/**
+ * Guest libraries, opened from a backup or an archive to be looked through without changing
+ * them
+ */
+read_only?: boolean; version: LibraryConfigVersion; 
 /**
  * cloud_email_address is the email address of the user who owns the cloud library this library is linked to.
  */
@@ -550,12 +928,16 @@ export type LibraryPreferences = { location?: { [key in string]: LocationSetting
 
 export type LightScanArgs = { location_id: number; sub_path: string }
 
+export type ListNetworkSharesArgs = { host: string; protocol: ShareProtocol }
+
 export type ListenerState = { type: "Listening" } | { type: "Error"; error: string } | { type: "NotListening" }
 
 export type Listeners = { ipv4: ListenerState; ipv6: ListenerState; relay: ListenerState }
 
 export type Location = { id: number; pub_id: number[]; name: string | null; path: string | null; total_capacity: number | null; available_capacity: number | null; size_in_bytes: number[] | null; is_archived: boolean | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; date_created: string | null; scan_state: number; device_id: number | null; instance_id: number | null }
 
+export type LocationBreakdown = { location_id: number; name: string | null; files: bigint; bytes: bigint }
+
 /**
  * `LocationCreateArgs` is the argument received from the client using `rspc` to create a new location.
  * It has the actual path and a vector of indexer rules ids, to create many-to-many relationships
@@ -577,6 +959,31 @@ export type LocationUpdateArgs = { id: number; name: string | null; generate_pre
 
 export type LocationWithIndexerRule = { id: number; pub_id: number[]; name: string | null; path: string | null; total_capacity: number | null; available_capacity: number | null; size_in_bytes: number[] | null; is_archived: boolean | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; date_created: string | null; instance_id: number | null; indexer_rules: IndexerRule[] }
 
+export type LogFilters = { 
+/**
+ * `RUST_LOG` or the default directives the app started with
+ */
+base: string; 
+/**
+ * Levels set at runtime, by module
+ */
+overrides: { [key in string]: LogLevel }; 
+/**
+ * Modules being captured right now
+ */
+capturing: string[] }
+
+export type LogLevel = "off" | "error" | "warn" | "info" | "debug" | "trace"
+
+/**
+ * How little free space a volume can have left before it's low on space
+ */
+export type LowSpaceThreshold = 
+/**
+ * Percentage of the capacity, from 0 to 100
+ */
+{ percentage: number } | { bytes: string }
+
 export type MaybeUndefined<T> = null | T
 
 export type MediaData = { Exif: ExifMetadata } | { FFmpeg: FFmpegMetadata }
@@ -589,8 +996,65 @@ export type MediaDate = string
 
 export type MediaLocation = { latitude: number; longitude: number; pluscode: PlusCode; altitude: number | null; direction: number | null }
 
+export type MemberState = "active" | 
+/**
+ * Standing by to replace a member that fails
+ */
+"spare" | "rebuilding" | "faulty" | 
+/**
+ * Expected by the pool but not there
+ */
+"missing" | "unknown"
+
 export type Metadata = { album: string | null; album_artist: string | null; artist: string | null; comment: string | null; composer: string | null; copyright: string | null; creation_time: string | null; date: string | null; disc: number | null; encoder: string | null; encoded_by: string | null; filename: string | null; genre: string | null; language: string | null; performer: string | null; publisher: string | null; service_name: string | null; service_provider: string | null; title: string | null; track: number | null; variant_bit_rate: number | null; custom: { [key in string]: string } }
 
+/**
+ * Volume data the platform couldn't provide, usually in containers and locked down environments
+ */
+export type MissingVolumeData = 
+/**
+ * No disks were reported, the volumes come from the mount table
+ */
+"DiskList" | 
+/**
+ * The capacity of some volumes couldn't be read and is reported as 0
+ */
+"Capacity" | 
+/**
+ * sysfs isn't readable, disk types are unknown and removable drives may show as system ones
+ */
+"DeviceInfo" | 
+/**
+ * The mount options couldn't be read, volumes are assumed to be writable
+ */
+"ReadOnlyStatus"
+
+export type ModelPreview = { model: string; records: number }
+
+export type ModelProgress = { model: string; total: number; applied: number; remaining: number }
+
+export type ModelToggle = { model: string; enabled: boolean }
+
+export type MountNetworkShareArgs = { url: string; mount_point?: string | null }
+
+/**
+ * How a device is mounted
+ */
+export type MountOptions = { read_only?: boolean }
+
+/**
+ * The user a volume was mounted for, on machines shared by several users
+ */
+export type MountOwner = { uid: number; 
+/**
+ * Login name, unless the user database doesn't know the uid
+ */
+name: string | null; 
+/**
+ * Seats the user is logged in on, like `seat0`, empty when they aren't
+ */
+seats: string[] }
+
 /**
  * Represents how the volume is mounted in the system
  */
@@ -610,7 +1074,13 @@ export type MountType =
 /**
  * Virtual/container volume
  */
-"Virtual"
+"Virtual" | 
+/**
+ * A disk image attached as a device, like an ISO or a DMG file
+ */
+"DiskImage"
+
+export type MountVolumeArgs = { device: string; options?: MountOptions }
 
 export type NodeConfigP2P = { discovery?: P2PDiscoveryState; port: Port; disabled: boolean; disable_ipv6: boolean; disable_relay: boolean; enable_remote_access: boolean; 
 /**
@@ -624,9 +1094,34 @@ export type NodeConfigP2P = { discovery?: P2PDiscoveryState; port: Port; disable
  * 
  * which is why we use `String` not `SocketAddr`
  */
-manual_peers?: string[] }
+manual_peers?: string[]; 
+/**
+ * The limit every transfer with a peer over p2p is held to, on top of the one of the peer.
+ * Cloud sync is held to it too, though it doesn't go through p2p.
+ */
+transfer_bandwidth?: BandwidthLimit; 
+/**
+ * The transfer limits of some peers, by their identity
+ */
+peer_transfer_bandwidth?: { [key in RemoteIdentity]: BandwidthLimit }; 
+/**
+ * Hold sync back while the network is metered, like a phone hotspot
+ */
+unmetered_sync_only: boolean }
 
-export type NodePreferences = Record<string, never>
+export type NodePreferences = { 
+/**
+ * Keep the system from going to sleep while jobs are running
+ */
+prevent_sleep_during_jobs: boolean; 
+/**
+ * Suspend p2p, cloud services and other traffic the app starts on its own
+ */
+offline: boolean; 
+/**
+ * Share anonymous usage and error counts, off unless the user opts in
+ */
+telemetry: boolean }
 
 export type NodeState = ({ 
 /**
@@ -640,9 +1135,9 @@ name: string; identity: RemoteIdentity; p2p: NodeConfigP2P; features: BackendFea
 
 export type NonCriticalError = { indexer: NonCriticalIndexerError } | { file_identifier: NonCriticalFileIdentifierError } | { media_processor: NonCriticalMediaProcessorError }
 
-export type NonCriticalFileIdentifierError = { failed_to_extract_file_metadata: string } | { failed_to_extract_isolated_file_path_data: { file_path_pub_id: string; error: string } } | { file_path_without_is_dir_field: number }
+export type NonCriticalFileIdentifierError = { failed_to_extract_file_metadata: string } | { failed_to_extract_isolated_file_path_data: { file_path_pub_id: string; error: string } } | { file_path_without_is_dir_field: number } | { device_failure: string }
 
-export type NonCriticalIndexerError = { failed_directory_entry: string } | { metadata: string } | { indexer_rule: string } | { file_path_metadata: string } | { fetch_already_existing_file_path_ids: string } | { fetch_file_paths_to_remove: string } | { iso_file_path: string } | { dispatch_keep_walking: string } | { missing_file_path_data: string }
+export type NonCriticalIndexerError = { failed_directory_entry: string } | { metadata: string } | { indexer_rule: string } | { file_path_metadata: string } | { fetch_already_existing_file_path_ids: string } | { fetch_file_paths_to_remove: string } | { iso_file_path: string } | { dispatch_keep_walking: string } | { missing_file_path_data: string } | { device_failure: string }
 
 export type NonCriticalMediaDataExtractorError = { FailedToExtractImageMediaData: [string, string] } | { FilePathMissingObjectId: number } | { FailedToConstructIsolatedFilePathData: [number, string] }
 
@@ -681,9 +1176,9 @@ export type ObjectSearchArgs = { take: number; orderAndPagination?: OrderAndPagi
 
 export type ObjectValidatorArgs = { id: number; path: string }
 
-export type ObjectWithFilePaths = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; file_paths: ({ id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; object: { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; exif_data: { resolution: number[] | null; media_date: number[] | null; media_location: number[] | null; camera_data: number[] | null; artist: string | null; description: string | null; copyright: string | null; exif_version: string | null } | null; ffmpeg_data: { id: number; formats: string; bit_rate: number[]; duration: number[] | null; start_time: number[] | null; chapters: FfmpegMediaChapter[]; programs: ({ program_id: number; streams: ({ stream_id: number; name: string | null; codec: { id: number; kind: string | null; sub_kind: string | null; tag: string | null; name: string | null; profile: string | null; bit_rate: number; video_props: FfmpegMediaVideoProps | null; audio_props: FfmpegMediaAudioProps | null; stream_id: number; program_id: number; ffmpeg_data_id: number } | null; aspect_ratio_num: number; aspect_ratio_den: number; frames_per_second_num: number; frames_per_second_den: number; time_base_real_den: number; time_base_real_num: number; dispositions: string | null; title: string | null; encoder: string | null; language: string | null; duration: number[] | null; metadata: number[] | null; program_id: number; ffmpeg_data_id: number })[]; name: string | null; metadata: number[] | null; ffmpeg_data_id: number })[]; title: string | null; creation_time: string | null; date: string | null; album_artist: string | null; disc: string | null; track: string | null; album: string | null; artist: string | null; metadata: number[] | null; object_id: number } | null; device_id: number | null } | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null; device_id: number | null })[]; device_id: number | null }
+export type ObjectWithFilePaths = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; file_paths: ({ id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; object: { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; exif_data: { resolution: number[] | null; media_date: number[] | null; media_location: number[] | null; camera_data: number[] | null; artist: string | null; description: string | null; copyright: string | null; exif_version: string | null } | null; ffmpeg_data: { id: number; formats: string; bit_rate: number[]; duration: number[] | null; start_time: number[] | null; chapters: FfmpegMediaChapter[]; programs: ({ program_id: number; streams: ({ stream_id: number; name: string | null; codec: { id: number; kind: string | null; sub_kind: string | null; tag: string | null; name: string | null; profile: string | null; bit_rate: number; video_props: FfmpegMediaVideoProps | null; audio_props: FfmpegMediaAudioProps | null; stream_id: number; program_id: number; ffmpeg_data_id: number } | null; aspect_ratio_num: number; aspect_ratio_den: number; frames_per_second_num: number; frames_per_second_den: number; time_base_real_den: number; time_base_real_num: number; dispositions: string | null; title: string | null; encoder: string | null; language: string | null; duration: number[] | null; metadata: number[] | null; program_id: number; ffmpeg_data_id: number })[]; name: string | null; metadata: number[] | null; ffmpeg_data_id: number })[]; title: string | null; creation_time: string | null; date: string | null; album_artist: string | null; disc: string | null; track: string | null; album: string | null; artist: string | null; metadata: number[] | null; object_id: number } | null; device_id: number | null } | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null; sync_mode: number | null; device_id: number | null })[]; device_id: number | null }
 
-export type ObjectWithFilePaths2 = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; file_paths: ({ id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; object: { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; exif_data: { resolution: number[] | null; media_date: number[] | null; media_location: number[] | null; camera_data: number[] | null; artist: string | null; description: string | null; copyright: string | null; exif_version: string | null } | null; ffmpeg_data: { id: number; formats: string; bit_rate: number[]; duration: number[] | null; start_time: number[] | null; chapters: FfmpegMediaChapter[]; programs: ({ program_id: number; streams: ({ stream_id: number; name: string | null; codec: { id: number; kind: string | null; sub_kind: string | null; tag: string | null; name: string | null; profile: string | null; bit_rate: number; video_props: FfmpegMediaVideoProps | null; audio_props: FfmpegMediaAudioProps | null; stream_id: number; program_id: number; ffmpeg_data_id: number } | null; aspect_ratio_num: number; aspect_ratio_den: number; frames_per_second_num: number; frames_per_second_den: number; time_base_real_den: number; time_base_real_num: number; dispositions: string | null; title: string | null; encoder: string | null; language: string | null; duration: number[] | null; metadata: number[] | null; program_id: number; ffmpeg_data_id: number })[]; name: string | null; metadata: number[] | null; ffmpeg_data_id: number })[]; title: string | null; creation_time: string | null; date: string | null; album_artist: string | null; disc: string | null; track: string | null; album: string | null; artist: string | null; metadata: number[] | null; object_id: number } | null; device_id: number | null } | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null; device_id: number | null })[] }
+export type ObjectWithFilePaths2 = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; file_paths: ({ id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; object: { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; exif_data: { resolution: number[] | null; media_date: number[] | null; media_location: number[] | null; camera_data: number[] | null; artist: string | null; description: string | null; copyright: string | null; exif_version: string | null } | null; ffmpeg_data: { id: number; formats: string; bit_rate: number[]; duration: number[] | null; start_time: number[] | null; chapters: FfmpegMediaChapter[]; programs: ({ program_id: number; streams: ({ stream_id: number; name: string | null; codec: { id: number; kind: string | null; sub_kind: string | null; tag: string | null; name: string | null; profile: string | null; bit_rate: number; video_props: FfmpegMediaVideoProps | null; audio_props: FfmpegMediaAudioProps | null; stream_id: number; program_id: number; ffmpeg_data_id: number } | null; aspect_ratio_num: number; aspect_ratio_den: number; frames_per_second_num: number; frames_per_second_den: number; time_base_real_den: number; time_base_real_num: number; dispositions: string | null; title: string | null; encoder: string | null; language: string | null; duration: number[] | null; metadata: number[] | null; program_id: number; ffmpeg_data_id: number })[]; name: string | null; metadata: number[] | null; ffmpeg_data_id: number })[]; title: string | null; creation_time: string | null; date: string | null; album_artist: string | null; disc: string | null; track: string | null; album: string | null; artist: string | null; metadata: number[] | null; object_id: number } | null; device_id: number | null } | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null; sync_mode: number | null; device_id: number | null })[] }
 
 export type OldFileCopierJobInit = { source_location_id: number; target_location_id: number; sources_file_path_ids: number[]; target_location_relative_directory_path: string }
 
@@ -699,20 +1194,111 @@ export type OldFileEraserJobInit = { location_id: number; file_path_ids: number[
  */
 export type OperatingSystem = "Windows" | "Linux" | "MacOS" | "Ios" | "Android" | { Other: string }
 
+/**
+ * The steps an operation runs on a volume and what they are expected to lead to, from the
+ * checks it runs before doing anything
+ */
+export type OperationPlan = { operation: VolumeOperation; name: string; mount_point: string; steps: PlanStep[]; 
+/**
+ * Only the processes of the current user are found, unless the core runs as root
+ */
+busy: BusyProcess[]; 
+/**
+ * The data that stops being reachable, or is erased by a format
+ */
+bytes_affected: string; outcome: PredictedOutcome }
+
 export type OrderAndPagination<TId, TOrder, TCursor> = { orderOnly: TOrder } | { offset: { offset: number; order: TOrder | null } } | { cursor: { id: TId; cursor: TCursor } }
 
 export type Orientation = "Normal" | "CW90" | "CW180" | "CW270" | "MirroredVertical" | "MirroredHorizontal" | "MirroredHorizontalAnd90CW" | "MirroredHorizontalAnd270CW"
 
+/**
+ * The batches a peer of the sync group is still waiting for, every batch goes to all of them
+ */
+export type OutboxDepth = { device_pub_id: CloudDevicePubId; device_name: string | null; group_pub_id: CloudSyncGroupPubId; batches: number; operations: bigint; 
+/**
+ * Of the oldest batch, the one holding the others back
+ */
+attempts: number; next_attempt_at: string | null; last_error: string | null; 
+/**
+ * Batches given up on, the peer won't get their operations
+ */
+dead_letters: number }
+
 export type P2PDiscoveryState = "Everyone" | "ContactsOnly" | "Disabled"
 
 export type P2PEvent = { type: "PeerChange"; identity: RemoteIdentity; connection: ConnectionMethod; discovery: DiscoveryMethod; metadata: PeerMetadata; addrs: string[] } | { type: "PeerDelete"; identity: RemoteIdentity } | { type: "SpacedropRequest"; id: string; identity: RemoteIdentity; peer_name: string; files: string[] } | { type: "SpacedropProgress"; id: string; percent: number } | { type: "SpacedropTimedOut"; id: string } | { type: "SpacedropRejected"; id: string }
 
 export type PeerMetadata = { name: string; operating_system: OperatingSystem | null; device_model: CoreHardwareModel | null; version: string | null }
 
+/**
+ * What running an operation would do, in order
+ */
+export type PlanStep = 
+/**
+ * The user is asked for the privileges the following steps need
+ */
+{ step: "authorize" } | { step: "unmount"; mount_point: string } | 
+/**
+ * Detaches the file system while it's busy, it's unmounted for good once nothing uses it
+ */
+{ step: "lazy_unmount"; mount_point: string } | { step: "power_off"; device: string | null } | { step: "format"; device: string | null; file_system: FileSystem }
+
 export type PlusCode = string
 
+/**
+ * How the pool is doing as a whole, from most to least worrying
+ */
+export type PoolHealth = 
+/**
+ * Data was lost or the pool can't be read, like an array missing more members than it can
+ * do without
+ */
+"failed" | 
+/**
+ * Members are missing or failed, the data is intact but one more failure can lose it
+ */
+"degraded" | 
+/**
+ * A member is being rebuilt, the pool is degraded until it's done
+ */
+"rebuilding" | "healthy" | "unknown"
+
+/**
+ * What pools the devices of a volume together
+ */
+export type PoolKind = 
+/**
+ * Linux software RAID, an md array
+ */
+"raid" | 
+/**
+ * A logical volume of LVM
+ */
+"lvm" | 
+/**
+ * A dataset of a ZFS pool
+ */
+"zfs"
+
+export type PoolMember = { 
+/**
+ * The device, like `/dev/sda1`, or the name the tool gives a missing one
+ */
+device: string; state: MemberState }
+
 export type Port = { type: "random" } | { type: "discrete"; value: number }
 
+export type PredictedOutcome = { outcome: "succeeds" } | 
+/**
+ * Succeeds once the user grants the privileges
+ */
+{ outcome: "needs_authorization" } | 
+/**
+ * The operation would be refused, or fail
+ */
+{ outcome: "fails"; error: VolumeErrorInfo }
+
 export type Program = { id: number; name: string | null; streams: Stream[]; metadata: Metadata }
 
 export type Props = { Video: VideoProps } | { Audio: AudioProps } | { Subtitle: SubtitleProps }
@@ -726,6 +1312,15 @@ export type RefreshToken = string
 
 export type RemoteIdentity = string
 
+/**
+ * A volume tracked by another device of the library, as it was when that device last synced
+ */
+export type RemoteVolume = { device_pub_id: number[]; 
+/**
+ * Unknown until the record of the device itself was synced
+ */
+device_name: string | null; volume: Volume }
+
 export type RenameFileArgs = { location_id: number; kind: RenameKind }
 
 export type RenameKind = { One: RenameOne } | { Many: RenameMany }
@@ -746,10 +1341,48 @@ export type RescanArgs = { location_id: number; sub_path: string }
 
 export type Resolution = { width: number; height: number }
 
+export type ResolveConflictArgs = { id: number; resolution: ConflictResolution }
+
+export type RestoreBackupArgs = { path: string; 
+/**
+ * Opens it as a guest library, to be looked through without changing it
+ */
+read_only?: boolean }
+
+/**
+ * Published while a backup is restored, new subscribers get the restores still going on
+ */
+export type RestoreProgress = { path: string; step: RestoreStep }
+
+export type RestoreStep = "verifying" | "unpacking" | "copying" | "loading" | "done" | { failed: { error: string } }
+
+export type RevokeDeviceArgs = { pub_id: CoreDevicePubId; 
+/**
+ * The cloud sync group of the library, the device is removed from it and its key rotated
+ */
+sync_group_pub_id: CloudSyncGroupPubId | null }
+
 export type RuleKind = "AcceptFilesByGlob" | "RejectFilesByGlob" | "AcceptIfChildrenDirectoriesArePresent" | "RejectIfChildrenDirectoriesArePresent" | "IgnoredByGit"
 
 export type SavedSearch = { id: number; pub_id: number[]; target: string | null; search: string | null; filters: string | null; name: string | null; icon: string | null; description: string | null; date_created: string | null; date_modified: string | null }
 
+/**
+ * Where a volume scan is at, so lists can fill in while slow mounts are still being probed
+ */
+export type ScanProgress = 
+/**
+ * A mount was probed, `volume` is `None` when it turned out not to be a volume
+ */
+{ Probed: { processed: number; total: number; volume: Volume | null } } | 
+/**
+ * Every mount was probed, with what the scan had to do without
+ */
+{ Finished: { degraded_mode: DegradedMode } } | 
+/**
+ * The scan stopped, the volumes probed so far aren't registered
+ */
+{ Failed: VolumeErrorInfo }
+
 export type SearchData<T> = { cursor: number[] | null; items: T[] }
 
 export type SearchFilterArgs = { filePath: FilePathFilterArgs } | { object: ObjectFilterArgs }
@@ -758,21 +1391,100 @@ export type SearchTarget = "paths" | "objects"
 
 export type SetFavoriteArgs = { id: number; favorite: boolean }
 
+export type SetLogLevelArgs = { 
+/**
+ * A tracing target, e.g. `sd_core::volume`
+ */
+module: string; 
+/**
+ * `null` goes back to the level the app started with
+ */
+level: LogLevel | null }
+
+export type SetModelSyncArgs = { model: string; enabled: boolean }
+
 export type SetNoteArgs = { id: number; note: string | null }
 
+export type SetSecretArgs = { key: string; secret: string }
+
+export type SetTransferBandwidthArgs = { limit: BandwidthLimit | null; 
+/**
+ * Replaces the limits of every peer, the ones left out get none of their own
+ */
+peer_limits: { [key in RemoteIdentity]: BandwidthLimit } | null; unmetered_sync_only: boolean | null }
+
+export type Severity = "ok" | 
+/**
+ * Working, but with less than it should, like volumes without their capacity
+ */
+"warning" | 
+/**
+ * Not working, like a watcher that stopped
+ */
+"error"
+
+export type ShareProtocol = "smb" | "nfs" | "webdav"
+
+/**
+ * A file server found on the local network
+ */
+export type ShareServer = { 
+/**
+ * As it advertises itself, like `Living Room NAS`
+ */
+name: string; 
+/**
+ * What its share URLs are built with, a `.local` name for the ones found through mDNS
+ */
+host: string; protocols: ShareProtocol[] }
+
 export type SingleInvalidateOperationEvent = { 
 /**
  * This fields are intentionally private.
  */
 key: string; arg: JsonValue; result: JsonValue | null }
 
+export type SnapshotArgs = { fingerprint: VolumeFingerprint; id: string }
+
 export type SortOrder = "Asc" | "Desc"
 
+export type SpaceBreakdown = { 
+/**
+ * When the oldest of the directories was walked, `None` before the first breakdown
+ */
+computed_at: string | null; usage: SpaceUsage; categories: CategoryUsage[]; 
+/**
+ * The largest first
+ */
+directories: DirectoryUsage[] }
+
+export type SpaceBreakdownArgs = { fingerprint: VolumeFingerprint }
+
+export type SpaceCategory = 
+/**
+ * Documents, text, books, code and the files configuring apps
+ */
+"documents" | 
+/**
+ * Pictures, videos, audio and 3D models
+ */
+"media" | "archives" | 
+/**
+ * The operating system and the programs
+ */
+"system" | "other"
+
+export type SpaceUsage = { total_bytes: string; files_count: bigint }
+
 export type SpacedropArgs = { identity: RemoteIdentity; file_path: string[] }
 
 export type Statistics = { id: number; date_captured: string; total_object_count: number; library_db_size: string; total_local_bytes_used: string; total_local_bytes_capacity: string; total_local_bytes_free: string; total_library_bytes: string; total_library_unique_bytes: string; total_library_preview_media_bytes: string }
 
-export type StatisticsResponse = { statistics: Statistics | null }
+export type StatisticsResponse = { statistics: Statistics | null; 
+/**
+ * From the aggregator of the library, `null` until its first pass is done
+ */
+breakdown: LibraryBreakdown | null }
 
 export type Status = "Queued" | "Running" | "Completed" | "Canceled" | "Failed" | "Paused" | "CompletedWithErrors"
 
@@ -780,8 +1492,64 @@ export type Stream = { id: number; name: string | null; codec: Codec | null; asp
 
 export type SubtitleProps = { width: number; height: number }
 
+/**
+ * What of the library is synced with its other devices
+ */
+export type SyncConfig = { 
+/**
+ * The models whose sync is turned off, by name like `tag`
+ */
+disabled_models?: string[] }
+
+export type SyncConflict = { id: number; 
+/**
+ * The model of the record, like `tag` or `file_path`
+ */
+model: string; record_id: JsonValue; field: string; mine: JsonValue; theirs: JsonValue; theirs_device_pub_id: number[]; theirs_device_name: string | null; date_created: string }
+
 export type SyncGroupsRequestJoinArgs = { sync_group: CloudSyncGroupWithDevices; asking_device: CloudDevice }
 
+/**
+ * A warning when libraries sync but the cloud isn't reachable, offline mode aside
+ */
+export type SyncHealth = { severity: Severity; offline: boolean; cloud_connected: boolean; 
+/**
+ * Libraries generating sync operations
+ */
+syncing_libraries: number }
+
+export type SyncMode = "full" | 
+/**
+ * The other devices see the file paths, not what's in them
+ */
+"metadata_only" | 
+/**
+ * Nothing is synced, the file paths stay on this device
+ */
+"local_only"
+
+export type SyncPreview = { 
+/**
+ * In the order they'd be synced, without the file paths kept on this device
+ */
+models: ModelPreview[]; 
+/**
+ * One for each sync mode
+ */
+file_paths: FilePathsPreview[]; total_records: bigint }
+
+export type SyncRule = { id: number; 
+/**
+ * The materialized path of the directory, like `/Photos/Raw/`
+ */
+path: string; mode: SyncMode }
+
+export type SyncRuleSetArgs = { location_id: number; 
+/**
+ * The directory relative to the location, like `/Photos/Raw/`
+ */
+path: string; mode: SyncMode }
+
 export type SyncStatus = { ingest: boolean; cloud_send: boolean; cloud_receive: boolean; cloud_ingest: boolean }
 
 export type SystemLocations = { desktop: string | null; documents: string | null; downloads: string | null; pictures: string | null; music: string | null; videos: string | null }
@@ -804,8 +1572,47 @@ export type TextMatch = { contains: string } | { startsWith: string } | { endsWi
  */
 export type ThumbKey = { shard_hex: string; cas_id: CasId; base_directory_str: string }
 
+/**
+ * A partition or disk with a file system nothing mounted, which can be mounted by its device
+ */
+export type UnmountedDevice = { 
+/**
+ * The device node, like `/dev/sdb1` or `/dev/disk4s2`, or the volume GUID path on Windows
+ */
+device: string; 
+/**
+ * Label of the file system, if it has one
+ */
+label: string | null; file_system: FileSystem; total_bytes_capacity: string }
+
 export type UpdateThumbnailerPreferences = Record<string, never>
 
+export type UpdateVolumeSettingsArgs = { fingerprint: VolumeFingerprint; settings: VolumeSettings }
+
+export type VerifyArgs = { 
+/**
+ * Repairs what's found instead of only reporting it
+ */
+repair: boolean }
+
+export type VerifyReport = { 
+/**
+ * What SQLite found wrong with the database, empty when it's sound
+ */
+integrity_errors: string[]; foreign_key_violations: ForeignKeyViolation[]; orphan_objects: number; 
+/**
+ * Without a location or with one that doesn't exist anymore
+ */
+paths_without_location: number; 
+/**
+ * Contents whose file paths are linked to more than one object
+ */
+conflicting_fingerprints: number; 
+/**
+ * If the repairs were made, they aren't when asked not to or when the database is corrupt
+ */
+repaired: boolean; relinked_paths: number; quarantined_paths: number; removed_objects: number }
+
 export type VideoProps = { pixel_format: string | null; color_range: string | null; bits_per_channel: number | null; color_space: string | null; color_primaries: string | null; color_transfer: string | null; field_order: string | null; chroma_location: string | null; width: number; height: number; aspect_ratio_num: number | null; aspect_ratio_den: number | null; properties: string[] }
 
 /**
@@ -813,8 +1620,8 @@ export type VideoProps = { pixel_format: string | null; color_range: string | nu
  */
 export type Volume = { 
 /**
- * Fingerprint of the volume as a hash of its properties, not persisted to the database
- * Used as the unique identifier for a volume in this module
+ * Fingerprint of the volume as a hash of its properties, stored with the volume once it's
+ * tracked. Used as the unique identifier for a volume in this module
  */
 fingerprint: VolumeFingerprint | null; 
 /**
@@ -861,10 +1668,48 @@ file_system: FileSystem;
  * Whether the volume is mounted read-only
  */
 read_only: boolean; 
+/**
+ * User the volume was mounted for, none for the mounts of the whole system
+ */
+owner?: MountOwner | null; 
+/**
+ * Whether the current user can open the volume, the mounts of other users usually can't be.
+ * Such volumes aren't tracked automatically.
+ */
+is_accessible?: boolean; 
+/**
+ * How the volume is encrypted, `None` for the plain ones and when the platform can't tell
+ */
+encryption?: EncryptionKind | null; 
+/**
+ * Whether the volume is encrypted and waits to be unlocked, its files can't be read until then
+ */
+is_locked?: boolean; 
 /**
  * Current error status if any
  */
 error_status: string | null; 
+/**
+ * The UUID or serial number of the file system, or of the partition without one, the same
+ * wherever the volume is mounted
+ */
+serial?: string | null; 
+/**
+ * The directory of the file system that's mounted, `/` for all of it, like a btrfs subvolume,
+ * the source of a bind mount or the share of a mapped network drive. `None` when the platform
+ * doesn't tell.
+ */
+mount_root?: string | null; 
+/**
+ * The RAID array, LVM volume group or ZFS pool the volume is on, `None` on a plain partition
+ * and on the platforms that don't tell
+ */
+topology?: VolumeTopology | null; 
+/**
+ * Quarantined after failing to read or write too many times in a row, the jobs on it are
+ * paused. Cleared once it's mounted again.
+ */
+is_degraded?: boolean; 
 /**
  * Read speed in megabytes per second
  */
@@ -882,6 +1727,32 @@ total_bytes_capacity: string;
  */
 total_bytes_available: string }
 
+export type VolumeBreakdown = { fingerprint: VolumeFingerprint | null; name: string; files: bigint; bytes: bigint }
+
+/**
+ * Stable error codes, for the frontend to branch on and to pick a translated message
+ * 
+ * These are part of the API, existing codes must not be renamed.
+ */
+export type VolumeErrorCode = "ALREADY_EXISTS" | "CANCELLED" | "CREDENTIALS_UNAVAILABLE" | "DATABASE" | "DEVICE_BUSY" | "DEVICE_ERROR" | "INSUFFICIENT_SPACE" | "INTERNAL" | "INVALID_ARGUMENT" | "INVALID_CONFIGURATION" | "INVALID_DISK_IMAGE" | "INVALID_FINGERPRINT" | "INVALID_SHARE_URL" | "INVALID_SNAPSHOT" | "IO" | "JOBS_RUNNING" | "LOCKED" | "MOUNT_FAILED" | "NOT_FOUND" | "NOT_MOUNTED" | "OPERATION_FAILED" | "PERMISSION_DENIED" | "READ_ONLY" | "RESOURCE_EXHAUSTED" | "SNAPSHOT_NOT_FOUND" | "SPEED_TEST_FAILED" | "SYSTEM_VOLUME" | "TIMEOUT" | "UNMOUNT_FAILED" | "UNSUPPORTED_PLATFORM" | "WATCH_INIT_FAILED" | "WATCHER_FAILED"
+
+/**
+ * A volume error as sent to the frontend, built from the core's `VolumeError`
+ */
+export type VolumeErrorInfo = { code: VolumeErrorCode; 
+/**
+ * Translation key, the frontend falls back to `message` when it has none
+ */
+message_key: string; 
+/**
+ * Values for the placeholders of the translation, like `path`
+ */
+params: { [key in string]: string }; 
+/**
+ * Untranslated description, with the underlying cause
+ */
+message: string; path: string | null }
+
 /**
  * Events emitted by the Volume Manager when volume state changes
  */
@@ -898,6 +1769,15 @@ export type VolumeEvent =
  * Emitted when a volume's properties are updated
  */
 { VolumeUpdated: { old: Volume; new: Volume } } | 
+/**
+ * Emitted after `VolumeUpdated` when the volume was remounted read-only, or writable
+ */
+{ VolumeRemounted: { fingerprint: VolumeFingerprint; read_only: boolean } } | 
+/**
+ * Emitted after `VolumeUpdated` when the free space of the volume changed. The capacity is
+ * part of the fingerprint, a volume growing is removed and added again.
+ */
+{ VolumeSpaceChanged: { fingerprint: VolumeFingerprint; total_bytes_available: string } } | 
 /**
  * Emitted when a volume's speed test completes
  */
@@ -909,9 +1789,185 @@ export type VolumeEvent =
 /**
  * Emitted when a volume encounters an error
  */
-{ VolumeError: { fingerprint: VolumeFingerprint; error: string } }
+{ VolumeError: { fingerprint: VolumeFingerprint; error: VolumeErrorInfo } } | 
+/**
+ * Emitted when the usage trend of a volume says it fills up within the configured horizon
+ */
+{ VolumeFillingUp: { fingerprint: VolumeFingerprint; name: string; days_until_full: number } } | 
+/**
+ * Emitted when the drive of a volume reports another health status, not when it stops
+ * reporting one
+ */
+{ VolumeHealthChanged: { fingerprint: VolumeFingerprint; name: string; health: VolumeHealth } } | 
+/**
+ * Emitted when a tracked volume is quarantined after too many I/O errors in a row, the jobs
+ * on its locations are paused
+ */
+{ VolumeDegraded: { volume: Volume; errors: number } } | 
+/**
+ * Emitted when the free space of a tracked volume drops below the configured threshold, again
+ * only once it went back above it
+ */
+{ LowSpace: { volume: Volume; threshold: LowSpaceThreshold } }
 
 /**
  * A fingerprint of a volume, used to identify it when it is not persisted in the database
  */
 export type VolumeFingerprint = number[]
+
+/**
+ * The S.M.A.R.T. data of the drive a volume is on
+ */
+export type VolumeHealth = { status: HealthStatus; temperature_celsius: number | null; 
+/**
+ * Sectors the drive moved elsewhere after they failed, a growing count means the disk wears
+ */
+reallocated_sectors: bigint | null; power_on_hours: bigint | null }
+
+export type VolumeHistoryArgs = { fingerprint: VolumeFingerprint; range?: HistoryRange }
+
+export type VolumeHistoryEntry = { kind: VolumeHistoryKind; recorded_at: string; total_bytes_capacity: string | null; total_bytes_available: string | null }
+
+export type VolumeHistoryKind = "attached" | "detached" | 
+/**
+ * The space used while the volume stayed attached
+ */
+"usage"
+
+/**
+ * The operations that take a volume away, which can be checked with a dry run first
+ */
+export type VolumeOperation = "unmount" | 
+/**
+ * Unmounts the volume and powers its drive off, so it can be unplugged
+ */
+"eject" | 
+/**
+ * Erases the volume with a new file system
+ */
+{ format: { file_system: FileSystem } }
+
+export type VolumeOperationArgs = { fingerprint: VolumeFingerprint; dry_run?: boolean }
+
+/**
+ * What the user set for a volume, kept by fingerprint so it follows the volume across mounts
+ */
+export type VolumeSettings = { 
+/**
+ * Left out of the explorer, it's still listed in the volume settings to be shown again
+ */
+is_hidden?: boolean; 
+/**
+ * The jobs writing to its locations are refused, whatever the volume is mounted as
+ */
+force_read_only?: boolean; 
+/**
+ * Whether it's tracked as soon as a library loads, `null` only tracks the system volumes
+ */
+auto_track?: boolean | null }
+
+export type VolumeSnapshot = { 
+/**
+ * Format the snapshot was written in, see [`SNAPSHOT_VERSION`]
+ */
+version: number; captured_at: string; 
+/**
+ * Fingerprints are derived from it, reproductions should run as this device
+ */
+device_id: number[]; 
+/**
+ * Every volume the manager knew of, with its fingerprint
+ */
+volumes: Volume[]; degraded_mode: DegradedMode; watcher: WatcherStatus }
+
+/**
+ * The pool a volume is on, for the volumes on more than a plain partition
+ */
+export type VolumeTopology = { kind: PoolKind; 
+/**
+ * The array, logical volume or pool, like `md0`, `vg0/home` or `tank`
+ */
+name: string; 
+/**
+ * The layout as the tool names it, like `raid5`, `linear` or `mirror`
+ */
+level: string; 
+/**
+ * Members that can fail without losing data, 0 for the layouts without redundancy
+ */
+redundancy: number; health: PoolHealth; members: PoolMember[] }
+
+export type VolumeWatcherConfig = { 
+/**
+ * OS events closer than this to the last volume check don't trigger a new one
+ */
+debounce_ms?: bigint; 
+/**
+ * Where the OS events come from, the platform's own order when unset or unavailable. The
+ * watcher switches over as soon as this changes.
+ */
+backend?: WatcherBackend | null; 
+/**
+ * How often the `poll` backend checks the volumes, the one the watcher falls back on when
+ * none of the platform's starts
+ */
+poll_interval_ms?: bigint; 
+/**
+ * Directories watched for mount points coming and going on top of the backend, for the
+ * volumes mounted outside of where the platform looks, like `/srv/disks`
+ */
+extra_paths?: string[] }
+
+/**
+ * A warning when the volumes were listed without some of their data
+ */
+export type VolumesHealth = { severity: Severity; degraded_mode: DegradedMode }
+
+/**
+ * Where the watcher learns that the volumes may have changed from. The platform lists the ones it
+ * has with [`VolumePlatform::watcher_backends`], `watcher.backend` in the core config picks one.
+ */
+export type WatcherBackend = 
+/**
+ * Changes of `/proc/self/mountinfo`, which the kernel signals on every mount and unmount
+ */
+"mountinfo_poll" | 
+/**
+ * The block device uevents the kernel sends udev over netlink, unavailable in containers
+ */
+"udev" | 
+/**
+ * inotify on the directories the volumes are usually mounted in
+ */
+"inotify" | 
+/**
+ * Whatever the platform listens to, for the ones with a single way to do it
+ */
+"native" | 
+/**
+ * Checks every `watcher.poll_interval_ms`, for the platforms without events or when none of
+ * their backends starts
+ */
+"poll"
+
+/**
+ * An error once the watcher stopped, a warning when its last check failed
+ */
+export type WatcherHealth = { severity: Severity; status: WatcherStatus }
+
+/**
+ * What the watcher has been up to, for snapshots and diagnostics
+ */
+export type WatcherStatus = { running: boolean; 
+/**
+ * The backend the events come from, none until one started
+ */
+backend: WatcherBackend | null; 
+/**
+ * Checks run since the watcher started, debounced ones excluded
+ */
+checks: bigint; last_check: string | null; 
+/**
+ * Why the last check failed, cleared by the next successful one
+ */
+last_error: string | null }
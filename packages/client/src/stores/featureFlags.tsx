@@ -2,7 +2,7 @@ import { useEffect } from 'react';
 import { createMutable } from 'solid-js/store';
 
 import type { BackendFeature } from '../core';
-import { useBridgeQuery } from '../rspc';
+import { nonLibraryClient, useBridgeQuery } from '../rspc';
 import { createPersistedMutable, useObserver } from '../solid';
 
 export const features = [
@@ -17,7 +17,11 @@ export const features = [
 
 // This defines which backend feature flags show up in the UI.
 // This is kinda a hack to not having the runtime array of possible features as Specta only exports the types.
-export const backendFeatures: BackendFeature[] = [];
+export const backendFeatures: BackendFeature[] = [
+	'autoTracking',
+	'smartMonitoring',
+	'remoteVolumeBrowsing'
+];
 
 export type FeatureFlag = (typeof features)[number] | BackendFeature;
 
@@ -78,11 +82,11 @@ export function toggleFeatureFlag(flags: FeatureFlag | FeatureFlag[]) {
 				const result = featureFlagsStore.enabled.find((ff) => f === ff)
 					? true
 					: await confirm(
-							'This feature will render your database broken and it WILL need to be reset! Use at your own risk!'
+							'This feature is experimental and may misbehave. Use at your own risk!'
 						);
 
 				if (result) {
-					// nonLibraryClient.mutation(['toggleFeatureFlag', f as any]);
+					await nonLibraryClient.mutation(['toggleFeatureFlag', f as BackendFeature]);
 				}
 			})();
 
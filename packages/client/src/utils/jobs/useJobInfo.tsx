@@ -298,6 +298,18 @@ export function useJobInfo(job: Report, realtimeUpdate: JobProgressEvent | null)
 				} ${plural(completedTaskCount, 'file')}`,
 				textItems: [[{ text: job.status }]]
 			};
+		case 'VolumeMaintenance':
+			return {
+				...data,
+				name: `${isQueued ? 'Maintain' : isRunning ? 'Maintaining' : 'Maintained'} volumes`,
+				textItems: [[{ text: isRunning && realtimeUpdate?.message ? realtimeUpdate.message : job.status }]]
+			};
+		case 'VolumeBreakdown':
+			return {
+				...data,
+				name: `${isQueued ? 'Measure' : isRunning ? 'Measuring' : 'Measured'} volume space`,
+				textItems: [[{ text: isRunning && realtimeUpdate?.message ? realtimeUpdate.message : job.status }]]
+			};
 		default:
 			return {
 				...data,